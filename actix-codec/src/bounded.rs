@@ -0,0 +1,304 @@
+use std::{error::Error, fmt, io};
+
+use bytes::BytesMut;
+
+use super::{Decoder, Encoder};
+
+/// Errors returned by [`BoundedCodec`].
+#[derive(Debug)]
+pub enum BoundedError<E> {
+    /// An encoded frame exceeded the maximum frame size.
+    EncodeTooLarge {
+        /// Size of the encoded frame.
+        size: usize,
+
+        /// Configured maximum frame size.
+        max: usize,
+    },
+
+    /// A frame being decoded exceeded the maximum frame size.
+    DecodeTooLarge {
+        /// Number of bytes consumed or buffered for the frame.
+        size: usize,
+
+        /// Configured maximum frame size.
+        max: usize,
+    },
+
+    /// Error returned by the inner codec.
+    Codec(E),
+}
+
+impl<E> BoundedError<E> {
+    /// Returns true if the error was caused by exceeding the maximum frame size.
+    pub fn is_too_large(&self) -> bool {
+        matches!(
+            self,
+            Self::EncodeTooLarge { .. } | Self::DecodeTooLarge { .. }
+        )
+    }
+
+    /// Returns the inner codec error, if any.
+    pub fn into_codec_error(self) -> Option<E> {
+        match self {
+            Self::Codec(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl<E: fmt::Display> fmt::Display for BoundedError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EncodeTooLarge { size, max } => {
+                write!(
+                    f,
+                    "Encoded frame of {} bytes exceeds limit of {} bytes",
+                    size, max
+                )
+            }
+            Self::DecodeTooLarge { size, max } => {
+                write!(
+                    f,
+                    "Decoded frame of {} bytes exceeds limit of {} bytes",
+                    size, max
+                )
+            }
+            Self::Codec(err) => fmt::Display::fmt(err, f),
+        }
+    }
+}
+
+impl<E: Error + 'static> Error for BoundedError<E> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Codec(err) => Some(err),
+            Self::EncodeTooLarge { .. } | Self::DecodeTooLarge { .. } => None,
+        }
+    }
+}
+
+impl<E: From<io::Error>> From<io::Error> for BoundedError<E> {
+    fn from(err: io::Error) -> Self {
+        Self::Codec(E::from(err))
+    }
+}
+
+/// Codec wrapper enforcing a maximum frame size on any inner codec.
+///
+/// Frame sizes are measured from the outside: an encoded frame is the number of bytes the inner
+/// encoder appended to the write buffer, and a decoded frame is the number of bytes the inner
+/// decoder consumed from the read buffer. When the inner decoder cannot yet produce a frame while
+/// more than the maximum number of bytes are buffered, the first frame is known to be oversized and
+/// decoding fails early, bounding the memory a peer can make the read buffer hold.
+///
+/// # Examples
+/// ```
+/// use actix_codec::{BoundedCodec, Decoder as _, LinesCodec};
+/// use bytes::BytesMut;
+///
+/// let mut codec = BoundedCodec::new(LinesCodec::default(), 8);
+///
+/// let mut buf = BytesMut::from("short\n");
+/// assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "short");
+///
+/// let mut buf = BytesMut::from("much too long");
+/// let err = codec.decode(&mut buf).unwrap_err();
+/// assert!(err.is_too_large());
+/// ```
+#[derive(Debug, Clone)]
+pub struct BoundedCodec<C> {
+    inner: C,
+    max_encode: usize,
+    max_decode: usize,
+}
+
+impl<C> BoundedCodec<C> {
+    /// Constructs new bounded codec using the same limit for encoded and decoded frames.
+    pub fn new(inner: C, max_frame_size: usize) -> Self {
+        Self {
+            inner,
+            max_encode: max_frame_size,
+            max_decode: max_frame_size,
+        }
+    }
+
+    /// Sets the maximum size of encoded frames.
+    pub fn max_encode_size(mut self, size: usize) -> Self {
+        self.max_encode = size;
+        self
+    }
+
+    /// Sets the maximum size of decoded frames.
+    pub fn max_decode_size(mut self, size: usize) -> Self {
+        self.max_decode = size;
+        self
+    }
+
+    /// Returns a reference to the inner codec.
+    pub fn get_ref(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner codec.
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    /// Consumes the wrapper, returning the inner codec.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    fn check_decoded<T, E>(
+        &self,
+        res: Result<Option<T>, E>,
+        before: usize,
+        src: &BytesMut,
+    ) -> Result<Option<T>, BoundedError<E>> {
+        match res.map_err(BoundedError::Codec)? {
+            Some(item) => {
+                let size = before - src.len();
+
+                if size > self.max_decode {
+                    return Err(BoundedError::DecodeTooLarge {
+                        size,
+                        max: self.max_decode,
+                    });
+                }
+
+                Ok(Some(item))
+            }
+
+            None if src.len() > self.max_decode => Err(BoundedError::DecodeTooLarge {
+                size: src.len(),
+                max: self.max_decode,
+            }),
+
+            None => Ok(None),
+        }
+    }
+}
+
+impl<C, I> Encoder<I> for BoundedCodec<C>
+where
+    C: Encoder<I>,
+{
+    type Error = BoundedError<C::Error>;
+
+    fn encode(&mut self, item: I, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let start = dst.len();
+
+        self.inner.encode(item, dst).map_err(BoundedError::Codec)?;
+
+        let size = dst.len() - start;
+
+        if size > self.max_encode {
+            dst.truncate(start);
+
+            return Err(BoundedError::EncodeTooLarge {
+                size,
+                max: self.max_encode,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+impl<C> Decoder for BoundedCodec<C>
+where
+    C: Decoder,
+{
+    type Item = C::Item;
+    type Error = BoundedError<C::Error>;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let before = src.len();
+        let res = self.inner.decode(src);
+        self.check_decoded(res, before, src)
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let before = src.len();
+        let res = self.inner.decode_eof(src);
+        self.check_decoded(res, before, src)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+
+    use super::*;
+    use crate::{BytesCodec, LinesCodec};
+
+    #[test]
+    fn encode_limit() {
+        let mut codec = BoundedCodec::new(BytesCodec, 4);
+        let mut buf = BytesMut::from("pre");
+
+        codec.encode(Bytes::from_static(b"1234"), &mut buf).unwrap();
+        assert_eq!(buf, "pre1234");
+
+        let err = codec
+            .encode(Bytes::from_static(b"12345"), &mut buf)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            BoundedError::EncodeTooLarge { size: 5, max: 4 }
+        ));
+
+        // oversized frame is not left in the buffer
+        assert_eq!(buf, "pre1234");
+    }
+
+    #[test]
+    fn decode_limit() {
+        let mut codec = BoundedCodec::new(LinesCodec::default(), 4);
+
+        // delimiter counts towards consumed bytes
+        let mut buf = BytesMut::from("abc\nabcd\n");
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "abc");
+
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert!(matches!(
+            err,
+            BoundedError::DecodeTooLarge { size: 5, max: 4 }
+        ));
+    }
+
+    #[test]
+    fn decode_incomplete_over_limit() {
+        let mut codec = BoundedCodec::new(LinesCodec::default(), 4);
+
+        let mut buf = BytesMut::from("abcd");
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+
+        buf.extend_from_slice(b"e");
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert!(err.is_too_large());
+        assert!(err.to_string().contains("exceeds limit"));
+    }
+
+    #[test]
+    fn separate_limits() {
+        let mut codec = BoundedCodec::new(BytesCodec, 1)
+            .max_encode_size(8)
+            .max_decode_size(2);
+
+        let mut buf = BytesMut::new();
+        codec.encode(Bytes::from_static(b"abc"), &mut buf).unwrap();
+        assert!(codec.decode(&mut buf).unwrap_err().is_too_large());
+    }
+
+    #[test]
+    fn inner_error() {
+        let mut codec = BoundedCodec::new(LinesCodec::default(), 16);
+
+        let mut buf = BytesMut::from(&b"\xff\n"[..]);
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert!(!err.is_too_large());
+        assert!(err.into_codec_error().is_some());
+    }
+}
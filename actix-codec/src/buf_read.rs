@@ -0,0 +1,165 @@
+use std::{
+    future::Future,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::{Buf as _, BytesMut};
+use futures_core::ready;
+use memchr::memchr;
+use tokio::io::AsyncBufRead;
+
+use crate::{AsyncRead, Framed, ReadBuf};
+
+impl<T, U> Framed<T, U>
+where
+    T: AsyncRead + Unpin,
+{
+    /// Returns an [`AsyncRead`] and [`AsyncBufRead`] view of the byte stream, bypassing the codec.
+    ///
+    /// Data already buffered by `Framed` is served first. Anything not consumed through the reader
+    /// remains in the read buffer and is decoded as frames as usual, so raw reads can be freely
+    /// interleaved with framed decoding.
+    pub fn raw_reader(&mut self) -> RawReader<'_, T, U> {
+        RawReader { framed: self }
+    }
+
+    /// Reads raw bytes up to and including `delim`, bypassing the codec.
+    ///
+    /// If EOF is reached before the delimiter is found, all remaining bytes are returned. The
+    /// returned buffer is split off the read buffer without copying.
+    pub fn read_until(&mut self, delim: u8) -> ReadUntil<'_, T, U> {
+        ReadUntil {
+            framed: self,
+            delim,
+            searched: 0,
+        }
+    }
+
+    /// Reads exactly `len` raw bytes, bypassing the codec.
+    ///
+    /// Fails with [`io::ErrorKind::UnexpectedEof`] if the stream ends first, in which case any
+    /// partially read data remains in the read buffer. The returned buffer is split off the read
+    /// buffer without copying.
+    pub fn read_exact_bytes(&mut self, len: usize) -> ReadExactBytes<'_, T, U> {
+        ReadExactBytes { framed: self, len }
+    }
+}
+
+/// Raw reader over a [`Framed`] transport's read buffer and I/O stream.
+///
+/// Returned by [`Framed::raw_reader`].
+#[derive(Debug)]
+pub struct RawReader<'a, T, U> {
+    framed: &'a mut Framed<T, U>,
+}
+
+impl<T, U> AsyncBufRead for RawReader<'_, T, U>
+where
+    T: AsyncRead + Unpin,
+{
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let framed = &mut *self.get_mut().framed;
+
+        if framed.read_buf_ref().is_empty() {
+            ready!(framed.poll_read_raw(cx))?;
+        }
+
+        Poll::Ready(Ok(framed.read_buf_ref()))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.get_mut().framed.read_buf_raw().advance(amt);
+    }
+}
+
+impl<T, U> AsyncRead for RawReader<'_, T, U>
+where
+    T: AsyncRead + Unpin,
+{
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let data = ready!(self.as_mut().poll_fill_buf(cx))?;
+        let n = data.len().min(buf.remaining());
+        buf.put_slice(&data[..n]);
+        self.consume(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Future returned by [`Framed::read_until`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ReadUntil<'a, T, U> {
+    framed: &'a mut Framed<T, U>,
+    delim: u8,
+
+    /// Number of buffered bytes already known not to contain the delimiter.
+    searched: usize,
+}
+
+impl<T, U> Future for ReadUntil<'_, T, U>
+where
+    T: AsyncRead + Unpin,
+{
+    type Output = io::Result<BytesMut>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        loop {
+            let buf = this.framed.read_buf_ref();
+
+            if let Some(idx) = memchr(this.delim, &buf[this.searched..]) {
+                let len = this.searched + idx + 1;
+                return Poll::Ready(Ok(this.framed.read_buf_raw().split_to(len)));
+            }
+
+            this.searched = buf.len();
+
+            if ready!(this.framed.poll_read_raw(cx))? == 0 {
+                return Poll::Ready(Ok(this.framed.read_buf_raw().split()));
+            }
+        }
+    }
+}
+
+/// Future returned by [`Framed::read_exact_bytes`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct ReadExactBytes<'a, T, U> {
+    framed: &'a mut Framed<T, U>,
+    len: usize,
+}
+
+impl<T, U> Future for ReadExactBytes<'_, T, U>
+where
+    T: AsyncRead + Unpin,
+{
+    type Output = io::Result<BytesMut>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        loop {
+            let buffered = this.framed.read_buf_ref().len();
+
+            if buffered >= this.len {
+                return Poll::Ready(Ok(this.framed.read_buf_raw().split_to(this.len)));
+            }
+
+            this.framed.read_buf_raw().reserve(this.len - buffered);
+
+            if ready!(this.framed.poll_read_raw(cx))? == 0 {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "stream ended before enough bytes were read",
+                )));
+            }
+        }
+    }
+}
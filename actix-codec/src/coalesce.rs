@@ -0,0 +1,70 @@
+use std::{
+    future::Future as _,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use tokio::time::{sleep, Instant, Sleep};
+
+/// Flush coalescing state for [`Framed`](crate::Framed).
+#[derive(Debug)]
+pub(crate) struct Coalesce {
+    max_frames: usize,
+    max_delay: Duration,
+
+    /// Number of frames written since the last completed flush.
+    frames: usize,
+
+    /// Timer is reused across batches; only meaningful while `armed` is set.
+    timer: Option<Pin<Box<Sleep>>>,
+    armed: bool,
+}
+
+impl Coalesce {
+    pub(crate) fn new(max_frames: usize, max_delay: Duration) -> Self {
+        Self {
+            max_frames,
+            max_delay,
+            frames: 0,
+            timer: None,
+            armed: false,
+        }
+    }
+
+    pub(crate) fn frame_written(&mut self) {
+        self.frames += 1;
+    }
+
+    /// Resolves once buffered data should be flushed, given `buffered` bytes are pending and the
+    /// buffer is considered full at `limit` bytes.
+    pub(crate) fn poll_release(
+        &mut self,
+        cx: &mut Context<'_>,
+        buffered: usize,
+        limit: usize,
+    ) -> Poll<()> {
+        if buffered == 0 || buffered >= limit || self.frames >= self.max_frames {
+            return Poll::Ready(());
+        }
+
+        if !self.armed {
+            let deadline = Instant::now() + self.max_delay;
+
+            match &mut self.timer {
+                Some(timer) => timer.as_mut().reset(deadline),
+                None => self.timer = Some(Box::pin(sleep(self.max_delay))),
+            }
+
+            self.armed = true;
+        }
+
+        self.timer.as_mut().unwrap().as_mut().poll(cx)
+    }
+
+    /// Marks the current batch as flushed.
+    pub(crate) fn reset(&mut self) {
+        self.frames = 0;
+        self.armed = false;
+    }
+}
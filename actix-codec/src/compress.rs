@@ -0,0 +1,452 @@
+use std::io::{self, Read as _, Write as _};
+
+use bytes::{Buf as _, BufMut as _, BytesMut};
+
+use super::{Decoder, Encoder};
+
+/// Length of the big-endian frame length prefix.
+const LEN_PREFIX: usize = 4;
+
+/// Default limit on the size of a single compressed frame.
+const DEFAULT_MAX_FRAME_SIZE: usize = 8 * 1024 * 1024;
+
+/// Default limit on the size of decompressed data buffered for the inner decoder.
+const DEFAULT_MAX_DECOMPRESSED_SIZE: usize = 8 * 1024 * 1024;
+
+/// Compression algorithms supported by [`CompressionCodec`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Compression {
+    /// Raw DEFLATE stream (RFC 1951).
+    #[cfg(feature = "compress-gzip")]
+    Deflate,
+
+    /// Gzip stream (RFC 1952).
+    #[cfg(feature = "compress-gzip")]
+    Gzip,
+
+    /// Zstandard stream (RFC 8878).
+    #[cfg(feature = "compress-zstd")]
+    Zstd,
+}
+
+/// Codec adapter that transparently compresses frames produced by an inner codec.
+///
+/// Each call to `encode` runs the inner encoder, compresses its output as one self-contained
+/// stream, and writes it to the destination prefixed with its length as a big-endian `u32`.
+/// Decoding reverses this: length-prefixed frames are decompressed into an internal buffer from
+/// which the inner decoder reads. Inner frames may therefore span several compressed frames.
+///
+/// Compression and decompression failures, as well as frames exceeding the configured limits, are
+/// reported as [`io::ErrorKind::InvalidData`] errors converted into the inner codec's error type.
+///
+/// # Examples
+/// ```
+/// # #[cfg(feature = "compress-gzip")] {
+/// use actix_codec::{Compression, CompressionCodec, Decoder as _, Encoder as _, LinesCodec};
+/// use bytes::BytesMut;
+///
+/// let mut codec = CompressionCodec::new(LinesCodec::default(), Compression::Gzip);
+///
+/// let mut buf = BytesMut::new();
+/// codec.encode("hello world", &mut buf).unwrap();
+///
+/// let line = codec.decode(&mut buf).unwrap().unwrap();
+/// assert_eq!(line, "hello world");
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct CompressionCodec<C> {
+    inner: C,
+    compression: Compression,
+    level: Option<i32>,
+    max_frame_size: usize,
+    max_decompressed_size: usize,
+    encode_buf: BytesMut,
+    decode_buf: BytesMut,
+}
+
+impl<C> CompressionCodec<C> {
+    /// Constructs new compression codec wrapping `inner` and using the given algorithm.
+    pub fn new(inner: C, compression: Compression) -> Self {
+        Self {
+            inner,
+            compression,
+            level: None,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+            encode_buf: BytesMut::new(),
+            decode_buf: BytesMut::new(),
+        }
+    }
+
+    /// Sets compression level.
+    ///
+    /// For DEFLATE and gzip the level is clamped to `0..=9`; for zstd it is passed through as-is.
+    /// By default, each algorithm's default level is used.
+    pub fn level(mut self, level: i32) -> Self {
+        self.level = Some(level);
+        self
+    }
+
+    /// Sets the maximum accepted size of a compressed frame, excluding its length prefix.
+    ///
+    /// Frames announcing a larger size are rejected before being buffered. Sizes are capped by the
+    /// `u32` length prefix. By default, this is 8MiB.
+    pub fn max_frame_size(mut self, size: usize) -> Self {
+        self.max_frame_size = size.min(u32::MAX as usize);
+        self
+    }
+
+    /// Sets the maximum size of decompressed data buffered for the inner decoder.
+    ///
+    /// Applies to the data of all frames not yet consumed by the inner decoder, so inner frames
+    /// spanning many compressed frames are limited too. This guards against decompression bombs.
+    /// By default, this is 8MiB.
+    pub fn max_decompressed_size(mut self, size: usize) -> Self {
+        self.max_decompressed_size = size;
+        self
+    }
+
+    /// Returns the compression algorithm in use.
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Returns a reference to the inner codec.
+    pub fn get_ref(&self) -> &C {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner codec.
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.inner
+    }
+
+    /// Consumes the codec, returning the inner codec.
+    ///
+    /// Any decompressed data not yet consumed by the inner decoder is lost.
+    pub fn into_inner(self) -> C {
+        self.inner
+    }
+
+    /// Compresses `src` and appends the result to `dst`.
+    fn compress(&self, src: &[u8], dst: &mut BytesMut) -> io::Result<()> {
+        let writer = dst.writer();
+
+        match self.compression {
+            #[cfg(feature = "compress-gzip")]
+            Compression::Deflate => {
+                let mut enc = flate2::write::DeflateEncoder::new(writer, self.flate2_level());
+                enc.write_all(src)?;
+                enc.finish()?;
+            }
+
+            #[cfg(feature = "compress-gzip")]
+            Compression::Gzip => {
+                let mut enc = flate2::write::GzEncoder::new(writer, self.flate2_level());
+                enc.write_all(src)?;
+                enc.finish()?;
+            }
+
+            #[cfg(feature = "compress-zstd")]
+            Compression::Zstd => {
+                let level = self.level.unwrap_or(zstd::DEFAULT_COMPRESSION_LEVEL);
+                let mut enc = zstd::stream::write::Encoder::new(writer, level)?;
+                enc.write_all(src)?;
+                enc.finish()?;
+            }
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "compress-gzip")]
+    fn flate2_level(&self) -> flate2::Compression {
+        match self.level {
+            Some(level) => flate2::Compression::new(level.clamp(0, 9) as u32),
+            None => flate2::Compression::default(),
+        }
+    }
+}
+
+/// Decompresses `src` and appends the result to `dst`, failing if it expands past `max_size`.
+fn decompress(
+    compression: Compression,
+    max_size: usize,
+    src: &[u8],
+    dst: &mut BytesMut,
+) -> io::Result<()> {
+    // read one byte past the limit to detect frames that exceed it
+    let limit = max_size as u64 + 1;
+    let mut writer = dst.writer();
+
+    let n = match compression {
+        #[cfg(feature = "compress-gzip")]
+        Compression::Deflate => {
+            let dec = flate2::read::DeflateDecoder::new(src);
+            io::copy(&mut dec.take(limit), &mut writer)?
+        }
+
+        #[cfg(feature = "compress-gzip")]
+        Compression::Gzip => {
+            let dec = flate2::read::GzDecoder::new(src);
+            io::copy(&mut dec.take(limit), &mut writer)?
+        }
+
+        #[cfg(feature = "compress-zstd")]
+        Compression::Zstd => {
+            let dec = zstd::stream::read::Decoder::new(src)?;
+            io::copy(&mut dec.take(limit), &mut writer)?
+        }
+    };
+
+    if n >= limit {
+        return Err(invalid_data("decompressed data exceeds size limit"));
+    }
+
+    Ok(())
+}
+
+impl<C, I> Encoder<I> for CompressionCodec<C>
+where
+    C: Encoder<I>,
+{
+    type Error = C::Error;
+
+    fn encode(&mut self, item: I, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.encode_buf.clear();
+        self.inner.encode(item, &mut self.encode_buf)?;
+
+        // reserve space for length prefix and fill it in once the compressed size is known
+        let start = dst.len();
+        dst.put_u32(0);
+
+        if let Err(err) = self.compress(&self.encode_buf, dst) {
+            dst.truncate(start);
+            return Err(err.into());
+        }
+
+        let len = dst.len() - start - LEN_PREFIX;
+        if len > self.max_frame_size {
+            dst.truncate(start);
+            return Err(invalid_data("compressed frame exceeds size limit").into());
+        }
+
+        dst[start..start + LEN_PREFIX].copy_from_slice(&(len as u32).to_be_bytes());
+
+        Ok(())
+    }
+}
+
+impl<C> Decoder for CompressionCodec<C>
+where
+    C: Decoder,
+{
+    type Item = C::Item;
+    type Error = C::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            if !self.decode_buf.is_empty() {
+                if let Some(item) = self.inner.decode(&mut self.decode_buf)? {
+                    return Ok(Some(item));
+                }
+            }
+
+            if src.len() < LEN_PREFIX {
+                return Ok(None);
+            }
+
+            let len = u32::from_be_bytes([src[0], src[1], src[2], src[3]]) as usize;
+
+            if len > self.max_frame_size {
+                return Err(invalid_data("compressed frame exceeds size limit").into());
+            }
+
+            // buffer grows as data arrives instead of reserving the announced size up front
+            if src.len() < LEN_PREFIX + len {
+                return Ok(None);
+            }
+
+            src.advance(LEN_PREFIX);
+            let frame = src.split_to(len);
+
+            // limit applies to data buffered across frames not yet consumed by inner decoder
+            let max_size = self
+                .max_decompressed_size
+                .saturating_sub(self.decode_buf.len());
+
+            decompress(self.compression, max_size, &frame, &mut self.decode_buf)?;
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.decode(src)? {
+            Some(item) => Ok(Some(item)),
+
+            None if !src.is_empty() => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "truncated compressed frame",
+            )
+            .into()),
+
+            None => self.inner.decode_eof(&mut self.decode_buf),
+        }
+    }
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{BufMut as _, Bytes};
+
+    use super::*;
+    use crate::{BytesCodec, LinesCodec};
+
+    fn algorithms() -> Vec<Compression> {
+        vec![
+            #[cfg(feature = "compress-gzip")]
+            Compression::Deflate,
+            #[cfg(feature = "compress-gzip")]
+            Compression::Gzip,
+            #[cfg(feature = "compress-zstd")]
+            Compression::Zstd,
+        ]
+    }
+
+    #[test]
+    fn roundtrip() {
+        for compression in algorithms() {
+            let mut codec = CompressionCodec::new(LinesCodec::default(), compression);
+            let mut buf = BytesMut::new();
+
+            codec.encode("foo", &mut buf).unwrap();
+            codec.encode("bar".repeat(1000), &mut buf).unwrap();
+
+            // compression is effective on repetitive input
+            assert!(buf.len() < 1000, "{:?}", compression);
+
+            assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "foo");
+            assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "bar".repeat(1000));
+            assert!(codec.decode(&mut buf).unwrap().is_none());
+            assert!(buf.is_empty());
+        }
+    }
+
+    #[test]
+    fn partial_frames() {
+        for compression in algorithms() {
+            let mut codec = CompressionCodec::new(LinesCodec::default(), compression);
+
+            let mut encoded = BytesMut::new();
+            codec.encode("hello", &mut encoded).unwrap();
+
+            let mut buf = BytesMut::new();
+            for &byte in &encoded[..encoded.len() - 1] {
+                buf.put_u8(byte);
+                assert!(codec.decode(&mut buf).unwrap().is_none());
+            }
+
+            buf.put_u8(encoded[encoded.len() - 1]);
+            assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "hello");
+        }
+    }
+
+    #[test]
+    fn inner_frame_spans_compressed_frames() {
+        for compression in algorithms() {
+            let mut codec = CompressionCodec::new(BytesCodec, compression);
+
+            let mut buf = BytesMut::new();
+            codec.encode(Bytes::from_static(b"hel"), &mut buf).unwrap();
+            codec.encode(Bytes::from_static(b"lo\n"), &mut buf).unwrap();
+
+            let mut lines = CompressionCodec::new(LinesCodec::default(), compression);
+            assert_eq!(lines.decode(&mut buf).unwrap().unwrap(), "hello");
+        }
+    }
+
+    #[test]
+    fn decompressed_size_limit() {
+        for compression in algorithms() {
+            let mut codec =
+                CompressionCodec::new(LinesCodec::default(), compression).max_decompressed_size(64);
+
+            let mut buf = BytesMut::new();
+            codec.encode("a".repeat(100), &mut buf).unwrap();
+
+            let err = codec.decode(&mut buf).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn buffered_decompressed_size_limit() {
+        for compression in algorithms() {
+            let mut encoder = CompressionCodec::new(BytesCodec, compression);
+            let mut codec =
+                CompressionCodec::new(LinesCodec::default(), compression).max_decompressed_size(64);
+
+            // inner frames without delimiter pile up across compressed frames
+            let mut buf = BytesMut::new();
+            for _ in 0..10 {
+                encoder
+                    .encode(Bytes::from_static(b"aaaaaaaaaa"), &mut buf)
+                    .unwrap();
+            }
+
+            let err = codec.decode(&mut buf).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn default_frame_size_limit() {
+        for compression in algorithms() {
+            let mut codec = CompressionCodec::new(LinesCodec::default(), compression);
+
+            let mut buf = BytesMut::new();
+            buf.put_u32(u32::MAX);
+            let err = codec.decode(&mut buf).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+            // announced size is not reserved before payload arrives
+            let mut buf = BytesMut::new();
+            buf.put_u32(DEFAULT_MAX_FRAME_SIZE as u32);
+            assert!(codec.decode(&mut buf).unwrap().is_none());
+            assert!(buf.capacity() < 1024);
+        }
+    }
+
+    #[test]
+    fn frame_size_limit() {
+        for compression in algorithms() {
+            let mut codec =
+                CompressionCodec::new(LinesCodec::default(), compression).max_frame_size(8);
+
+            let mut buf = BytesMut::new();
+            buf.put_u32(9);
+
+            let err = codec.decode(&mut buf).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn truncated_at_eof() {
+        for compression in algorithms() {
+            let mut codec = CompressionCodec::new(LinesCodec::default(), compression);
+
+            let mut buf = BytesMut::new();
+            codec.encode("hello", &mut buf).unwrap();
+            buf.truncate(buf.len() - 1);
+
+            let err = codec.decode_eof(&mut buf).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        }
+    }
+}
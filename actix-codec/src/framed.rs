@@ -1,25 +1,36 @@
 use std::{
-    fmt, io,
+    cmp,
+    collections::VecDeque,
+    fmt,
+    io::{self, IoSlice},
     pin::Pin,
     task::{Context, Poll},
+    time::Duration,
 };
 
+use actix_utils::budget::{BudgetExceeded, MemoryBudget, Reservation};
 use bitflags::bitflags;
-use bytes::{Buf, BytesMut};
+use bytes::{Buf, Bytes, BytesMut};
 use futures_core::{ready, Stream};
 use futures_sink::Sink;
 use pin_project_lite::pin_project;
 
-use crate::{AsyncRead, AsyncWrite, Decoder, Encoder};
+use crate::{
+    coalesce::Coalesce,
+    stats::{FramedEvent, Metrics},
+    AsyncRead, AsyncWrite, Decoder, Encoder, FramedStats,
+};
 
 /// Low-water mark
-const LW: usize = 1024;
+pub(crate) const LW: usize = 1024;
 /// High-water mark
-const HW: usize = 8 * 1024;
+pub(crate) const HW: usize = 8 * 1024;
+/// Maximum number of chunks handed to a single vectored write.
+const MAX_IOVECS: usize = 64;
 
 bitflags! {
     #[derive(Debug, Clone, Copy)]
-    struct Flags: u8 {
+    pub(crate) struct Flags: u8 {
         const EOF = 0b0001;
         const READABLE = 0b0010;
     }
@@ -40,6 +51,10 @@ pin_project! {
         flags: Flags,
         read_buf: BytesMut,
         write_buf: BytesMut,
+        write_chunks: WriteChunks,
+        metrics: Metrics,
+        coalesce: Option<Coalesce>,
+        budget: Option<Reservation>,
     }
 }
 
@@ -58,6 +73,10 @@ where
             flags: Flags::empty(),
             read_buf: BytesMut::with_capacity(HW),
             write_buf: BytesMut::with_capacity(HW),
+            write_chunks: WriteChunks::default(),
+            metrics: Metrics::default(),
+            coalesce: None,
+            budget: None,
         }
     }
 }
@@ -101,19 +120,81 @@ impl<T, U> Framed<T, U> {
 
     /// Check if write buffer is empty.
     pub fn is_write_buf_empty(&self) -> bool {
-        self.write_buf.is_empty()
+        self.write_buf.is_empty() && self.write_chunks.is_empty()
     }
 
     /// Check if write buffer is full.
     pub fn is_write_buf_full(&self) -> bool {
-        self.write_buf.len() >= HW
+        self.write_len() >= HW
     }
 
     /// Check if framed is able to write more data.
     ///
     /// `Framed` object considers ready if there is free space in write buffer.
     pub fn is_write_ready(&self) -> bool {
-        self.write_buf.len() < HW
+        self.write_len() < HW
+    }
+
+    /// Total number of buffered bytes not yet written to the underlying I/O stream.
+    fn write_len(&self) -> usize {
+        self.write_chunks.len() + self.write_buf.len()
+    }
+
+    /// Returns a snapshot of the transport counters.
+    pub fn stats(&self) -> FramedStats {
+        self.metrics.stats()
+    }
+
+    /// Sets a hook that is called for every recorded [`FramedEvent`], replacing any previous one.
+    ///
+    /// The hook is called inline from the read, write and flush paths so it should be cheap.
+    pub fn set_event_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(FramedEvent) + Send + Sync + 'static,
+    {
+        self.metrics.set_hook(hook);
+    }
+
+    /// Enables flush coalescing.
+    ///
+    /// While enabled, flushing is delayed until either `max_frames` frames have been written since
+    /// the last flush or `max_delay` has elapsed since the first delayed flush attempt, trading a
+    /// little latency for fewer, larger writes. The write buffer is always flushed immediately once
+    /// it is full.
+    ///
+    /// Note that a delayed flush returns `Poll::Pending`, so futures like `SinkExt::send` will not
+    /// complete until the batch is released. Delays use Tokio's timer and so require a runtime
+    /// with the time driver enabled, such as the one provided by `actix-rt`.
+    pub fn set_flush_coalescing(&mut self, max_frames: usize, max_delay: Duration) {
+        self.coalesce = Some(Coalesce::new(max_frames, max_delay));
+    }
+
+    /// Disables flush coalescing, making every flush write buffered data immediately.
+    pub fn clear_flush_coalescing(&mut self) {
+        self.coalesce = None;
+    }
+
+    /// Accounts the capacity of the read and write buffers against `budget`.
+    ///
+    /// Once set, reading, writing, or flushing fails with an I/O error wrapping
+    /// [`BudgetExceeded`] whenever the buffers have grown beyond what the budget allows, e.g.,
+    /// because a peer sends a huge frame or never reads responses. Buffers are checked after they
+    /// grow, so they can exceed the budget by up to the size of a single read or encoded frame.
+    ///
+    /// Returns an error if the current buffers already exceed the budget.
+    pub fn set_memory_budget(&mut self, budget: &MemoryBudget) -> Result<(), BudgetExceeded> {
+        self.budget = Some(budget.reserve(self.buffered_capacity())?);
+        Ok(())
+    }
+
+    /// Stops accounting buffers against the memory budget, releasing their reservation.
+    pub fn clear_memory_budget(&mut self) {
+        self.budget = None;
+    }
+
+    /// Number of bytes held by the read and write buffers.
+    fn buffered_capacity(&self) -> usize {
+        buffered_capacity(&self.read_buf, &self.write_buf, &self.write_chunks)
     }
 
     /// Consume the `Frame`, returning `Frame` with different codec.
@@ -124,6 +205,10 @@ impl<T, U> Framed<T, U> {
             flags: self.flags,
             read_buf: self.read_buf,
             write_buf: self.write_buf,
+            write_chunks: self.write_chunks,
+            metrics: self.metrics,
+            coalesce: self.coalesce,
+            budget: self.budget,
         }
     }
 
@@ -138,6 +223,10 @@ impl<T, U> Framed<T, U> {
             flags: self.flags,
             read_buf: self.read_buf,
             write_buf: self.write_buf,
+            write_chunks: self.write_chunks,
+            metrics: self.metrics,
+            coalesce: self.coalesce,
+            budget: self.budget,
         }
     }
 
@@ -152,6 +241,10 @@ impl<T, U> Framed<T, U> {
             flags: self.flags,
             read_buf: self.read_buf,
             write_buf: self.write_buf,
+            write_chunks: self.write_chunks,
+            metrics: self.metrics,
+            coalesce: self.coalesce,
+            budget: self.budget,
         }
     }
 }
@@ -164,12 +257,27 @@ impl<T, U> Framed<T, U> {
         U: Encoder<I>,
     {
         let this = self.as_mut().project();
-        let remaining = this.write_buf.capacity() - this.write_buf.len();
-        if remaining < LW {
-            this.write_buf.reserve(HW - remaining);
+        encode_frame(
+            &*this.io,
+            this.codec,
+            this.write_buf,
+            this.write_chunks,
+            this.metrics,
+            item,
+        )?;
+
+        charge_budget(
+            this.budget,
+            this.read_buf,
+            this.write_buf,
+            this.write_chunks,
+        )
+        .map_err(io::Error::from)?;
+
+        if let Some(coalesce) = this.coalesce {
+            coalesce.frame_written();
         }
 
-        this.codec.encode(item, this.write_buf)?;
         Ok(())
     }
 
@@ -182,55 +290,27 @@ impl<T, U> Framed<T, U> {
         T: AsyncRead,
         U: Decoder,
     {
-        loop {
-            let this = self.as_mut().project();
-            // Repeatedly call `decode` or `decode_eof` as long as it is "readable". Readable is
-            // defined as not having returned `None`. If the upstream has returned EOF, and the
-            // decoder is no longer readable, it can be assumed that the decoder will never become
-            // readable again, at which point the stream is terminated.
-
-            if this.flags.contains(Flags::READABLE) {
-                if this.flags.contains(Flags::EOF) {
-                    match this.codec.decode_eof(this.read_buf) {
-                        Ok(Some(frame)) => return Poll::Ready(Some(Ok(frame))),
-                        Ok(None) => return Poll::Ready(None),
-                        Err(err) => return Poll::Ready(Some(Err(err))),
-                    }
-                }
-
-                tracing::trace!("attempting to decode a frame");
-
-                match this.codec.decode(this.read_buf) {
-                    Ok(Some(frame)) => {
-                        tracing::trace!("frame decoded from buffer");
-                        return Poll::Ready(Some(Ok(frame)));
-                    }
-                    Err(err) => return Poll::Ready(Some(Err(err))),
-                    _ => (), // Need more data
-                }
-
-                this.flags.remove(Flags::READABLE);
-            }
-
-            debug_assert!(!this.flags.contains(Flags::EOF));
-
-            // Otherwise, try to read more data and try again. Make sure we've got room.
-            let remaining = this.read_buf.capacity() - this.read_buf.len();
-            if remaining < LW {
-                this.read_buf.reserve(HW - remaining)
-            }
-
-            let cnt = match tokio_util::io::poll_read_buf(this.io, cx, this.read_buf) {
-                Poll::Pending => return Poll::Pending,
-                Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err.into()))),
-                Poll::Ready(Ok(cnt)) => cnt,
-            };
-
-            if cnt == 0 {
-                this.flags.insert(Flags::EOF);
-            }
-            this.flags.insert(Flags::READABLE);
+        let this = self.as_mut().project();
+        let res = poll_next_frame(
+            this.io,
+            this.codec,
+            this.flags,
+            this.read_buf,
+            this.metrics,
+            cx,
+        );
+
+        // checked even if no frame is ready since the buffer grows while reading partial frames
+        if let Err(err) = charge_budget(
+            this.budget,
+            this.read_buf,
+            this.write_buf,
+            this.write_chunks,
+        ) {
+            return Poll::Ready(Some(Err(io::Error::from(err).into())));
         }
+
+        res
     }
 
     /// Flush write buffer to underlying I/O stream.
@@ -239,30 +319,33 @@ impl<T, U> Framed<T, U> {
         T: AsyncWrite,
         U: Encoder<I>,
     {
-        let mut this = self.as_mut().project();
-        tracing::trace!("flushing framed transport");
-
-        while !this.write_buf.is_empty() {
-            tracing::trace!("writing; remaining={}", this.write_buf.len());
-
-            let n = ready!(this.io.as_mut().poll_write(cx, this.write_buf))?;
-
-            if n == 0 {
-                return Poll::Ready(Err(io::Error::new(
-                    io::ErrorKind::WriteZero,
-                    "failed to write frame to transport",
-                )
-                .into()));
-            }
+        let this = self.as_mut().project();
 
-            // remove written data
-            this.write_buf.advance(n);
+        if let Some(coalesce) = this.coalesce.as_mut() {
+            let buffered = this.write_chunks.len() + this.write_buf.len();
+            ready!(coalesce.poll_release(cx, buffered, HW));
         }
 
-        // Try flushing the underlying IO
-        ready!(this.io.poll_flush(cx))?;
+        ready!(poll_flush_buf(
+            this.io,
+            this.write_buf,
+            this.write_chunks,
+            this.metrics,
+            cx
+        ))?;
+
+        charge_budget(
+            this.budget,
+            this.read_buf,
+            this.write_buf,
+            this.write_chunks,
+        )
+        .map_err(io::Error::from)?;
+
+        if let Some(coalesce) = this.coalesce {
+            coalesce.reset();
+        }
 
-        tracing::trace!("framed transport flushed");
         Poll::Ready(Ok(()))
     }
 
@@ -279,6 +362,61 @@ impl<T, U> Framed<T, U> {
     }
 }
 
+impl<T, U> Framed<T, U> {
+    /// Reads more data from the underlying I/O stream into the read buffer, bypassing the decoder.
+    ///
+    /// Resolves to the number of bytes read; zero means the stream has reached EOF. Fails with an
+    /// error wrapping [`BudgetExceeded`] if the read buffer, including any capacity reserved by the
+    /// caller, has grown beyond the memory budget.
+    pub(crate) fn poll_read_raw(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>>
+    where
+        T: AsyncRead + Unpin,
+    {
+        let remaining = self.read_buf.capacity() - self.read_buf.len();
+        if remaining < LW {
+            self.read_buf.reserve(HW - remaining);
+        }
+
+        // checked before reading since raw readers reserve space for data that has not arrived
+        charge_budget(
+            &mut self.budget,
+            &self.read_buf,
+            &self.write_buf,
+            &self.write_chunks,
+        )
+        .map_err(io::Error::from)?;
+
+        let cnt = ready!(tokio_util::io::poll_read_buf(
+            Pin::new(&mut self.io),
+            cx,
+            &mut self.read_buf
+        ))?;
+
+        if cnt == 0 {
+            self.flags.insert(Flags::EOF);
+        } else {
+            self.metrics.record(FramedEvent::BytesRead(cnt));
+        }
+
+        // give the decoder a chance to look at the new data, or to observe EOF
+        self.flags.insert(Flags::READABLE);
+
+        Poll::Ready(Ok(cnt))
+    }
+
+    /// Returns the read buffer for raw access.
+    ///
+    /// Marks the buffer readable since raw consumption may leave a complete frame at its front.
+    pub(crate) fn read_buf_raw(&mut self) -> &mut BytesMut {
+        self.flags.insert(Flags::READABLE);
+        &mut self.read_buf
+    }
+
+    pub(crate) fn read_buf_ref(&self) -> &BytesMut {
+        &self.read_buf
+    }
+}
+
 impl<T, U> Stream for Framed<T, U>
 where
     T: AsyncRead,
@@ -347,12 +485,18 @@ impl<T, U> Framed<T, U> {
             flags: parts.flags,
             write_buf: parts.write_buf,
             read_buf: parts.read_buf,
+            write_chunks: WriteChunks::default(),
+            metrics: parts.metrics,
+            coalesce: None,
+            budget: None,
         }
     }
 
     /// Consumes the `Frame`, returning its underlying I/O stream, the buffer with unprocessed data,
     /// and the codec.
     ///
+    /// The buffers are no longer accounted against the memory budget, if one is set.
+    ///
     /// Note that care should be taken to not tamper with the underlying stream of data coming in as
     /// it may corrupt the stream of frames otherwise being worked with.
     pub fn into_parts(self) -> FramedParts<T, U> {
@@ -361,7 +505,8 @@ impl<T, U> Framed<T, U> {
             codec: self.codec,
             flags: self.flags,
             read_buf: self.read_buf,
-            write_buf: self.write_buf,
+            write_buf: self.write_chunks.merge_into(self.write_buf),
+            metrics: self.metrics,
         }
     }
 }
@@ -385,6 +530,7 @@ pub struct FramedParts<T, U> {
     pub write_buf: BytesMut,
 
     flags: Flags,
+    metrics: Metrics,
 }
 
 impl<T, U> FramedParts<T, U> {
@@ -396,6 +542,7 @@ impl<T, U> FramedParts<T, U> {
             flags: Flags::empty(),
             read_buf: BytesMut::new(),
             write_buf: BytesMut::new(),
+            metrics: Metrics::default(),
         }
     }
 
@@ -407,6 +554,278 @@ impl<T, U> FramedParts<T, U> {
             read_buf,
             flags: Flags::empty(),
             write_buf: BytesMut::new(),
+            metrics: Metrics::default(),
         }
     }
 }
+
+/// Encodes `item` into the write buffer, queueing it as a separate chunk when `io` supports
+/// vectored writes.
+pub(crate) fn encode_frame<T, U, I>(
+    io: &T,
+    codec: &mut U,
+    write_buf: &mut BytesMut,
+    write_chunks: &mut WriteChunks,
+    metrics: &mut Metrics,
+    item: I,
+) -> Result<(), U::Error>
+where
+    T: AsyncWrite,
+    U: Encoder<I>,
+{
+    let remaining = write_buf.capacity() - write_buf.len();
+    if remaining < LW {
+        write_buf.reserve(HW - remaining);
+    }
+
+    codec.encode(item, write_buf)?;
+    metrics.record(FramedEvent::FrameEncoded);
+
+    // When the I/O supports vectored writes, queue each encoded frame as its own chunk so
+    // that the flush path can hand them to the I/O in a single `poll_write_vectored` call.
+    if io.is_write_vectored() && !write_buf.is_empty() {
+        write_chunks.push(write_buf.split().freeze());
+    }
+
+    Ok(())
+}
+
+/// Number of bytes held by framed buffers.
+fn buffered_capacity(
+    read_buf: &BytesMut,
+    write_buf: &BytesMut,
+    write_chunks: &WriteChunks,
+) -> usize {
+    read_buf.capacity() + write_buf.capacity() + write_chunks.len()
+}
+
+/// Resizes the budget reservation, if any, to the number of bytes held by framed buffers.
+fn charge_budget(
+    budget: &mut Option<Reservation>,
+    read_buf: &BytesMut,
+    write_buf: &BytesMut,
+    write_chunks: &WriteChunks,
+) -> Result<(), BudgetExceeded> {
+    match budget {
+        Some(budget) => budget.resize(buffered_capacity(read_buf, write_buf, write_chunks)),
+        None => Ok(()),
+    }
+}
+
+/// Reads from `io` into the read buffer until a frame can be decoded.
+pub(crate) fn poll_next_frame<T, U>(
+    mut io: Pin<&mut T>,
+    codec: &mut U,
+    flags: &mut Flags,
+    read_buf: &mut BytesMut,
+    metrics: &mut Metrics,
+    cx: &mut Context<'_>,
+) -> Poll<Option<Result<U::Item, U::Error>>>
+where
+    T: AsyncRead,
+    U: Decoder,
+{
+    loop {
+        // Repeatedly call `decode` or `decode_eof` as long as it is "readable". Readable is
+        // defined as not having returned `None`. If the upstream has returned EOF, and the
+        // decoder is no longer readable, it can be assumed that the decoder will never become
+        // readable again, at which point the stream is terminated.
+
+        if flags.contains(Flags::READABLE) {
+            if flags.contains(Flags::EOF) {
+                match codec.decode_eof(read_buf) {
+                    Ok(Some(frame)) => {
+                        metrics.record(FramedEvent::FrameDecoded);
+                        return Poll::Ready(Some(Ok(frame)));
+                    }
+                    Ok(None) => return Poll::Ready(None),
+                    Err(err) => {
+                        metrics.record(FramedEvent::DecodeError);
+                        return Poll::Ready(Some(Err(err)));
+                    }
+                }
+            }
+
+            tracing::trace!("attempting to decode a frame");
+
+            match codec.decode(read_buf) {
+                Ok(Some(frame)) => {
+                    tracing::trace!("frame decoded from buffer");
+                    metrics.record(FramedEvent::FrameDecoded);
+                    return Poll::Ready(Some(Ok(frame)));
+                }
+                Err(err) => {
+                    metrics.record(FramedEvent::DecodeError);
+                    return Poll::Ready(Some(Err(err)));
+                }
+                _ => (), // Need more data
+            }
+
+            flags.remove(Flags::READABLE);
+        }
+
+        debug_assert!(!flags.contains(Flags::EOF));
+
+        // Otherwise, try to read more data and try again. Make sure we've got room.
+        let remaining = read_buf.capacity() - read_buf.len();
+        if remaining < LW {
+            // grow geometrically while a large frame is being buffered instead of a fixed
+            // amount per read; decoders that know the frame size can also reserve it up front
+            read_buf.reserve(cmp::max(HW - remaining, read_buf.len()));
+        }
+
+        let cnt = match tokio_util::io::poll_read_buf(io.as_mut(), cx, read_buf) {
+            Poll::Pending => return Poll::Pending,
+            Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err.into()))),
+            Poll::Ready(Ok(cnt)) => cnt,
+        };
+
+        if cnt == 0 {
+            flags.insert(Flags::EOF);
+        } else {
+            metrics.record(FramedEvent::BytesRead(cnt));
+        }
+        flags.insert(Flags::READABLE);
+    }
+}
+
+/// Writes all buffered data to `io` and flushes it.
+pub(crate) fn poll_flush_buf<T>(
+    mut io: Pin<&mut T>,
+    write_buf: &mut BytesMut,
+    write_chunks: &mut WriteChunks,
+    metrics: &mut Metrics,
+    cx: &mut Context<'_>,
+) -> Poll<io::Result<()>>
+where
+    T: AsyncWrite,
+{
+    tracing::trace!("flushing framed transport");
+
+    while !write_chunks.is_empty() {
+        tracing::trace!(
+            "writing vectored; chunks={} remaining={}",
+            write_chunks.chunks.len(),
+            write_chunks.len + write_buf.len()
+        );
+
+        let n = {
+            let mut slices = [IoSlice::new(&[]); MAX_IOVECS];
+            let cnt = write_chunks.fill_slices(write_buf, &mut slices);
+            ready!(io.as_mut().poll_write_vectored(cx, &slices[..cnt]))?
+        };
+
+        if n == 0 {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "failed to write frame to transport",
+            )));
+        }
+
+        // remove written data
+        metrics.record(FramedEvent::BytesWritten(n));
+        let rem = write_chunks.advance(n);
+        write_buf.advance(rem);
+    }
+
+    while !write_buf.is_empty() {
+        tracing::trace!("writing; remaining={}", write_buf.len());
+
+        let n = ready!(io.as_mut().poll_write(cx, write_buf))?;
+
+        if n == 0 {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::WriteZero,
+                "failed to write frame to transport",
+            )));
+        }
+
+        // remove written data
+        metrics.record(FramedEvent::BytesWritten(n));
+        write_buf.advance(n);
+    }
+
+    // Try flushing the underlying IO
+    ready!(io.poll_flush(cx))?;
+
+    tracing::trace!("framed transport flushed");
+    Poll::Ready(Ok(()))
+}
+
+/// Queue of encoded frames waiting to be written with a vectored write.
+///
+/// Queued chunks always precede any data left in the `Framed` write buffer.
+#[derive(Debug, Default)]
+pub(crate) struct WriteChunks {
+    chunks: VecDeque<Bytes>,
+    len: usize,
+}
+
+impl WriteChunks {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Total number of queued bytes.
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    fn push(&mut self, chunk: Bytes) {
+        self.len += chunk.len();
+        self.chunks.push_back(chunk);
+    }
+
+    /// Fills `slices` with queued chunks followed by the contents of `buf`, returning the number
+    /// of slices filled.
+    fn fill_slices<'a>(&'a self, buf: &'a BytesMut, slices: &mut [IoSlice<'a>]) -> usize {
+        let mut cnt = 0;
+
+        let tail = (!buf.is_empty()).then_some(buf.as_ref());
+
+        for chunk in self.chunks.iter().map(Bytes::as_ref).chain(tail) {
+            if cnt == slices.len() {
+                break;
+            }
+
+            slices[cnt] = IoSlice::new(chunk);
+            cnt += 1;
+        }
+
+        cnt
+    }
+
+    /// Removes `n` written bytes from the front of the queue, returning the number of bytes that
+    /// spilled over into the write buffer.
+    fn advance(&mut self, mut n: usize) -> usize {
+        while let Some(chunk) = self.chunks.front_mut() {
+            if n < chunk.len() {
+                chunk.advance(n);
+                self.len -= n;
+                return 0;
+            }
+
+            n -= chunk.len();
+            self.len -= chunk.len();
+            self.chunks.pop_front();
+        }
+
+        n
+    }
+
+    /// Prepends all queued chunks to `buf`.
+    pub(crate) fn merge_into(self, buf: BytesMut) -> BytesMut {
+        if self.is_empty() {
+            return buf;
+        }
+
+        let mut merged = BytesMut::with_capacity(self.len + buf.len());
+
+        for chunk in self.chunks {
+            merged.extend_from_slice(&chunk);
+        }
+
+        merged.extend_from_slice(&buf);
+        merged
+    }
+}
@@ -0,0 +1,198 @@
+use std::{
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::BytesMut;
+use futures_core::Stream;
+use pin_project_lite::pin_project;
+
+use crate::{
+    framed::{poll_next_frame, Flags, HW},
+    stats::Metrics,
+    AsyncRead, Decoder, FramedEvent, FramedStats,
+};
+
+pin_project! {
+    /// A `Stream` of frames decoded from an underlying readable I/O object.
+    ///
+    /// Unlike [`Framed`](crate::Framed), this type only holds a read buffer and only requires the
+    /// codec to implement `Decoder`, making it suitable for the read half of a split I/O object
+    /// or for protocols that only ever receive data.
+    pub struct FramedRead<T, D> {
+        #[pin]
+        io: T,
+        decoder: D,
+        flags: Flags,
+        read_buf: BytesMut,
+        metrics: Metrics,
+    }
+}
+
+impl<T, D> FramedRead<T, D>
+where
+    T: AsyncRead,
+    D: Decoder,
+{
+    /// Creates a new `FramedRead` with the given decoder.
+    pub fn new(io: T, decoder: D) -> FramedRead<T, D> {
+        FramedRead {
+            io,
+            decoder,
+            flags: Flags::empty(),
+            read_buf: BytesMut::with_capacity(HW),
+            metrics: Metrics::default(),
+        }
+    }
+
+    /// Creates a new `FramedRead` that starts decoding from the data in `read_buf`.
+    pub fn with_read_buf(io: T, decoder: D, read_buf: BytesMut) -> FramedRead<T, D> {
+        FramedRead {
+            io,
+            decoder,
+            flags: Flags::empty(),
+            read_buf,
+            metrics: Metrics::default(),
+        }
+    }
+}
+
+impl<T, D> FramedRead<T, D> {
+    /// Returns a reference to the underlying decoder.
+    pub fn decoder_ref(&self) -> &D {
+        &self.decoder
+    }
+
+    /// Returns a mutable reference to the underlying decoder.
+    pub fn decoder_mut(&mut self) -> &mut D {
+        &mut self.decoder
+    }
+
+    /// Returns a reference to the underlying I/O stream.
+    ///
+    /// Note that care should be taken to not tamper with the underlying stream of data coming in as
+    /// it may corrupt the stream of frames otherwise being worked with.
+    pub fn io_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Returns a mutable reference to the underlying I/O stream.
+    ///
+    /// Note that care should be taken to not tamper with the underlying stream of data coming in as
+    /// it may corrupt the stream of frames otherwise being worked with.
+    pub fn io_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    /// Returns a `Pin` of a mutable reference to the underlying I/O stream.
+    pub fn io_pin(self: Pin<&mut Self>) -> Pin<&mut T> {
+        self.project().io
+    }
+
+    /// Returns a reference to the read buffer.
+    pub fn read_buf(&self) -> &BytesMut {
+        &self.read_buf
+    }
+
+    /// Check if read buffer is empty.
+    pub fn is_read_buf_empty(&self) -> bool {
+        self.read_buf.is_empty()
+    }
+
+    /// Returns a snapshot of the transport counters.
+    pub fn stats(&self) -> FramedStats {
+        self.metrics.stats()
+    }
+
+    /// Sets a hook that is called for every recorded [`FramedEvent`], replacing any previous one.
+    pub fn set_event_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(FramedEvent) + Send + Sync + 'static,
+    {
+        self.metrics.set_hook(hook);
+    }
+
+    /// Consume the `FramedRead`, returning `FramedRead` with different decoder.
+    pub fn replace_decoder<D2>(self, decoder: D2) -> FramedRead<T, D2> {
+        FramedRead {
+            io: self.io,
+            decoder,
+            flags: self.flags,
+            read_buf: self.read_buf,
+            metrics: self.metrics,
+        }
+    }
+
+    /// Consume the `FramedRead`, returning `FramedRead` with different io.
+    pub fn into_map_io<F, T2>(self, f: F) -> FramedRead<T2, D>
+    where
+        F: FnOnce(T) -> T2,
+    {
+        FramedRead {
+            io: f(self.io),
+            decoder: self.decoder,
+            flags: self.flags,
+            read_buf: self.read_buf,
+            metrics: self.metrics,
+        }
+    }
+
+    /// Consumes the `FramedRead`, returning its underlying I/O stream.
+    ///
+    /// Any data left in the read buffer is lost.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+
+    /// Consumes the `FramedRead`, returning its underlying I/O stream, the decoder, and the
+    /// buffer with unprocessed data.
+    pub fn into_parts(self) -> (T, D, BytesMut) {
+        (self.io, self.decoder, self.read_buf)
+    }
+
+    /// Try to read underlying I/O stream and decode item.
+    pub fn next_item(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<<D as Decoder>::Item, D::Error>>>
+    where
+        T: AsyncRead,
+        D: Decoder,
+    {
+        let this = self.project();
+        poll_next_frame(
+            this.io,
+            this.decoder,
+            this.flags,
+            this.read_buf,
+            this.metrics,
+            cx,
+        )
+    }
+}
+
+impl<T, D> Stream for FramedRead<T, D>
+where
+    T: AsyncRead,
+    D: Decoder,
+{
+    type Item = Result<D::Item, D::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.next_item(cx)
+    }
+}
+
+impl<T, D> fmt::Debug for FramedRead<T, D>
+where
+    T: fmt::Debug,
+    D: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FramedRead")
+            .field("io", &self.io)
+            .field("decoder", &self.decoder)
+            .finish()
+    }
+}
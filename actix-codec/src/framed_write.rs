@@ -0,0 +1,232 @@
+use std::{
+    fmt, io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use bytes::BytesMut;
+use futures_core::ready;
+use futures_sink::Sink;
+use pin_project_lite::pin_project;
+
+use crate::{
+    framed::{encode_frame, poll_flush_buf, WriteChunks, HW},
+    stats::Metrics,
+    AsyncWrite, Encoder, FramedEvent, FramedStats,
+};
+
+pin_project! {
+    /// A `Sink` of frames encoded to an underlying writable I/O object.
+    ///
+    /// Unlike [`Framed`](crate::Framed), this type only holds a write buffer and only requires the
+    /// codec to implement `Encoder`, making it suitable for the write half of a split I/O object
+    /// or for protocols that only ever send data.
+    pub struct FramedWrite<T, E> {
+        #[pin]
+        io: T,
+        encoder: E,
+        write_buf: BytesMut,
+        write_chunks: WriteChunks,
+        metrics: Metrics,
+    }
+}
+
+impl<T, E> FramedWrite<T, E>
+where
+    T: AsyncWrite,
+{
+    /// Creates a new `FramedWrite` with the given encoder.
+    pub fn new(io: T, encoder: E) -> FramedWrite<T, E> {
+        FramedWrite {
+            io,
+            encoder,
+            write_buf: BytesMut::with_capacity(HW),
+            write_chunks: WriteChunks::default(),
+            metrics: Metrics::default(),
+        }
+    }
+}
+
+impl<T, E> FramedWrite<T, E> {
+    /// Returns a reference to the underlying encoder.
+    pub fn encoder_ref(&self) -> &E {
+        &self.encoder
+    }
+
+    /// Returns a mutable reference to the underlying encoder.
+    pub fn encoder_mut(&mut self) -> &mut E {
+        &mut self.encoder
+    }
+
+    /// Returns a reference to the underlying I/O stream.
+    pub fn io_ref(&self) -> &T {
+        &self.io
+    }
+
+    /// Returns a mutable reference to the underlying I/O stream.
+    ///
+    /// Note that care should be taken to not write to the underlying stream directly as it may
+    /// interleave with buffered frames.
+    pub fn io_mut(&mut self) -> &mut T {
+        &mut self.io
+    }
+
+    /// Returns a `Pin` of a mutable reference to the underlying I/O stream.
+    pub fn io_pin(self: Pin<&mut Self>) -> Pin<&mut T> {
+        self.project().io
+    }
+
+    /// Check if write buffer is empty.
+    pub fn is_write_buf_empty(&self) -> bool {
+        self.write_buf.is_empty() && self.write_chunks.is_empty()
+    }
+
+    /// Check if write buffer is full.
+    pub fn is_write_buf_full(&self) -> bool {
+        self.write_len() >= HW
+    }
+
+    /// Check if `FramedWrite` is able to write more data.
+    ///
+    /// `FramedWrite` object considers ready if there is free space in write buffer.
+    pub fn is_write_ready(&self) -> bool {
+        self.write_len() < HW
+    }
+
+    /// Total number of buffered bytes not yet written to the underlying I/O stream.
+    fn write_len(&self) -> usize {
+        self.write_chunks.len() + self.write_buf.len()
+    }
+
+    /// Returns a snapshot of the transport counters.
+    pub fn stats(&self) -> FramedStats {
+        self.metrics.stats()
+    }
+
+    /// Sets a hook that is called for every recorded [`FramedEvent`], replacing any previous one.
+    pub fn set_event_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(FramedEvent) + Send + Sync + 'static,
+    {
+        self.metrics.set_hook(hook);
+    }
+
+    /// Consume the `FramedWrite`, returning `FramedWrite` with different encoder.
+    pub fn replace_encoder<E2>(self, encoder: E2) -> FramedWrite<T, E2> {
+        FramedWrite {
+            io: self.io,
+            encoder,
+            write_buf: self.write_buf,
+            write_chunks: self.write_chunks,
+            metrics: self.metrics,
+        }
+    }
+
+    /// Consume the `FramedWrite`, returning `FramedWrite` with different io.
+    pub fn into_map_io<F, T2>(self, f: F) -> FramedWrite<T2, E>
+    where
+        F: FnOnce(T) -> T2,
+    {
+        FramedWrite {
+            io: f(self.io),
+            encoder: self.encoder,
+            write_buf: self.write_buf,
+            write_chunks: self.write_chunks,
+            metrics: self.metrics,
+        }
+    }
+
+    /// Consumes the `FramedWrite`, returning its underlying I/O stream.
+    ///
+    /// Any buffered data that has not been flushed is lost.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+
+    /// Consumes the `FramedWrite`, returning its underlying I/O stream, the encoder, and the
+    /// buffer with data which is not written yet.
+    pub fn into_parts(self) -> (T, E, BytesMut) {
+        (
+            self.io,
+            self.encoder,
+            self.write_chunks.merge_into(self.write_buf),
+        )
+    }
+
+    /// Serialize item and write to the inner buffer.
+    pub fn write<I>(self: Pin<&mut Self>, item: I) -> Result<(), <E as Encoder<I>>::Error>
+    where
+        T: AsyncWrite,
+        E: Encoder<I>,
+    {
+        let this = self.project();
+        encode_frame(
+            &*this.io,
+            this.encoder,
+            this.write_buf,
+            this.write_chunks,
+            this.metrics,
+            item,
+        )
+    }
+
+    /// Flush write buffer to underlying I/O stream.
+    pub fn flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>
+    where
+        T: AsyncWrite,
+    {
+        let this = self.project();
+        poll_flush_buf(this.io, this.write_buf, this.write_chunks, this.metrics, cx)
+    }
+
+    /// Flush write buffer and shutdown underlying I/O stream.
+    pub fn close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>
+    where
+        T: AsyncWrite,
+    {
+        ready!(self.as_mut().flush(cx))?;
+        self.project().io.poll_shutdown(cx)
+    }
+}
+
+impl<T, E, I> Sink<I> for FramedWrite<T, E>
+where
+    T: AsyncWrite,
+    E: Encoder<I>,
+    E::Error: From<io::Error>,
+{
+    type Error = E::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.is_write_ready() {
+            Poll::Ready(Ok(()))
+        } else {
+            self.flush(cx).map_err(Into::into)
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: I) -> Result<(), Self::Error> {
+        self.write(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.flush(cx).map_err(Into::into)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.close(cx).map_err(Into::into)
+    }
+}
+
+impl<T, E> fmt::Debug for FramedWrite<T, E>
+where
+    T: fmt::Debug,
+    E: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FramedWrite")
+            .field("io", &self.io)
+            .field("encoder", &self.encoder)
+            .finish()
+    }
+}
@@ -10,19 +10,39 @@
 #![warn(future_incompatible, missing_docs)]
 #![doc(html_logo_url = "https://actix.rs/img/logo.png")]
 #![doc(html_favicon_url = "https://actix.rs/favicon.ico")]
+#![cfg_attr(docsrs, feature(doc_auto_cfg))]
 
-pub use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+pub use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, ReadBuf};
 pub use tokio_util::{
     codec::{Decoder, Encoder},
     io::poll_read_buf,
 };
 
 mod bcodec;
+mod bounded;
+mod buf_read;
+mod coalesce;
+#[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+mod compress;
 mod framed;
+mod framed_read;
+mod framed_write;
 mod lines;
+mod split;
+mod stateful;
+mod stats;
 
+#[cfg(any(feature = "compress-gzip", feature = "compress-zstd"))]
+pub use self::compress::{Compression, CompressionCodec};
 pub use self::{
     bcodec::BytesCodec,
+    bounded::{BoundedCodec, BoundedError},
+    buf_read::{RawReader, ReadExactBytes, ReadUntil},
     framed::{Framed, FramedParts},
+    framed_read::FramedRead,
+    framed_write::FramedWrite,
     lines::LinesCodec,
+    split::{split_frame, split_until, to_byte_string},
+    stateful::{StatefulCodec, StatefulDecoder, Step},
+    stats::{FramedEvent, FramedStats},
 };
@@ -0,0 +1,144 @@
+use std::io;
+
+use bytes::{Buf as _, Bytes, BytesMut};
+use bytestring::ByteString;
+use memchr::memchr;
+
+/// Splits a frame of exactly `len` bytes off the front of the read buffer.
+///
+/// The returned `Bytes` is a view into the read buffer's allocation; no data is copied. If `src`
+/// does not yet contain `len` bytes, `None` is returned and enough capacity is reserved for the
+/// whole frame so that it can be read without repeated reallocation.
+///
+/// Note that frames returned from this function keep the underlying allocation alive. Decoders
+/// that hold on to frames for a long time should copy them out instead, otherwise the read buffer
+/// has to allocate fresh memory for subsequent reads.
+///
+/// # Examples
+/// ```
+/// use actix_codec::{split_frame, Decoder};
+/// use bytes::{Buf as _, Bytes, BytesMut};
+///
+/// /// Decodes frames prefixed with their length as a big-endian `u16`.
+/// #[derive(Default)]
+/// struct LengthPrefixed {
+///     len: Option<usize>,
+/// }
+///
+/// impl Decoder for LengthPrefixed {
+///     type Item = Bytes;
+///     type Error = std::io::Error;
+///
+///     fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Bytes>, Self::Error> {
+///         let len = match self.len {
+///             Some(len) => len,
+///             None if src.len() < 2 => return Ok(None),
+///             None => *self.len.insert(src.get_u16() as usize),
+///         };
+///
+///         let frame = split_frame(src, len);
+///
+///         if frame.is_some() {
+///             self.len = None;
+///         }
+///
+///         Ok(frame)
+///     }
+/// }
+///
+/// let mut codec = LengthPrefixed::default();
+///
+/// let mut buf = BytesMut::from(&b"\x00\x05hel"[..]);
+/// assert!(codec.decode(&mut buf).unwrap().is_none());
+///
+/// buf.extend_from_slice(b"lo");
+/// assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "hello");
+/// ```
+pub fn split_frame(src: &mut BytesMut, len: usize) -> Option<Bytes> {
+    if src.len() < len {
+        src.reserve(len - src.len());
+        return None;
+    }
+
+    Some(src.split_to(len).freeze())
+}
+
+/// Splits a frame terminated by `delim` off the front of the read buffer.
+///
+/// The delimiter is removed from the buffer but not included in the returned frame. Returns `None`
+/// if the delimiter has not been received yet, in which case the buffer is left untouched.
+///
+/// Like [`split_frame`], the returned `Bytes` is a view into the read buffer's allocation.
+pub fn split_until(src: &mut BytesMut, delim: u8) -> Option<Bytes> {
+    let idx = memchr(delim, src)?;
+
+    let frame = src.split_to(idx).freeze();
+    src.advance(1);
+
+    Some(frame)
+}
+
+/// Converts a frame into a [`ByteString`] without copying, validating that it is UTF-8.
+///
+/// Errors are returned as [`io::ErrorKind::InvalidData`] so that decoders can propagate them with
+/// `?`, since all decoder error types must be convertible from [`io::Error`].
+///
+/// # Examples
+/// ```
+/// use actix_codec::{split_until, to_byte_string};
+/// use bytes::BytesMut;
+///
+/// let mut buf = BytesMut::from("foo\nbar");
+///
+/// let line = split_until(&mut buf, b'\n').map(to_byte_string).transpose().unwrap();
+/// assert_eq!(line.unwrap(), "foo");
+/// assert_eq!(buf, "bar");
+/// ```
+pub fn to_byte_string(frame: Bytes) -> io::Result<ByteString> {
+    ByteString::try_from(frame).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_frame_reserves_whole_frame() {
+        let mut buf = BytesMut::from(&b"abc"[..]);
+
+        assert!(split_frame(&mut buf, 64 * 1024).is_none());
+        assert_eq!(buf, b"abc"[..]);
+        assert!(buf.capacity() >= 64 * 1024);
+
+        assert_eq!(split_frame(&mut buf, 2).unwrap(), b"ab"[..]);
+        assert_eq!(buf, b"c"[..]);
+    }
+
+    #[test]
+    fn split_frame_shares_allocation() {
+        let mut buf = BytesMut::from(&b"hello world"[..]);
+        let start = buf.as_ptr();
+
+        let frame = split_frame(&mut buf, 5).unwrap();
+        assert_eq!(frame.as_ptr(), start);
+    }
+
+    #[test]
+    fn split_until_delim() {
+        let mut buf = BytesMut::from(&b"foo\nbar\n\nbaz"[..]);
+
+        assert_eq!(split_until(&mut buf, b'\n').unwrap(), "foo");
+        assert_eq!(split_until(&mut buf, b'\n').unwrap(), "bar");
+        assert_eq!(split_until(&mut buf, b'\n').unwrap(), "");
+        assert!(split_until(&mut buf, b'\n').is_none());
+        assert_eq!(buf, b"baz"[..]);
+    }
+
+    #[test]
+    fn byte_string_validation() {
+        assert_eq!(to_byte_string(Bytes::from_static(b"ok")).unwrap(), "ok");
+
+        let err = to_byte_string(Bytes::from_static(b"\xff")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
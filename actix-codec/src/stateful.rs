@@ -0,0 +1,346 @@
+use std::{io, mem};
+
+use bytes::BytesMut;
+
+use super::{Decoder, Encoder};
+
+/// Outcome of a single [`StatefulDecoder::step`].
+#[derive(Debug)]
+pub enum Step<S, T> {
+    /// Transition to the given state and continue decoding.
+    Next(S),
+
+    /// A frame was decoded; the decoder returns to its initial state.
+    Done(T),
+}
+
+/// Decoder for frames that are parsed in multiple passes, such as a header followed by a body.
+///
+/// Rather than tracking partially parsed frames by hand, implementors describe each state of the
+/// frame along with the number of bytes needed to make progress from it. The [`StatefulCodec`]
+/// adapter drives the state machine, only calling [`step`](Self::step) once enough data has been
+/// buffered and reserving read buffer capacity for the rest of the current part.
+///
+/// # Examples
+/// ```
+/// use actix_codec::{Decoder as _, StatefulCodec, StatefulDecoder, Step};
+/// use bytes::{Buf as _, Bytes, BytesMut};
+///
+/// /// Frames consist of a big-endian `u16` length header, followed by the body.
+/// struct LengthPrefixed;
+///
+/// #[derive(Default)]
+/// enum State {
+///     #[default]
+///     Header,
+///     Body(usize),
+/// }
+///
+/// impl StatefulDecoder for LengthPrefixed {
+///     type State = State;
+///     type Item = Bytes;
+///     type Error = std::io::Error;
+///
+///     fn required(&self, state: &State) -> usize {
+///         match state {
+///             State::Header => 2,
+///             State::Body(len) => *len,
+///         }
+///     }
+///
+///     fn step(&mut self, state: State, src: &mut BytesMut) -> Result<Step<State, Bytes>, Self::Error> {
+///         Ok(match state {
+///             State::Header => Step::Next(State::Body(src.get_u16() as usize)),
+///             State::Body(len) => Step::Done(src.split_to(len).freeze()),
+///         })
+///     }
+/// }
+///
+/// let mut codec = StatefulCodec::new(LengthPrefixed);
+///
+/// let mut buf = BytesMut::from(&b"\x00\x05hel"[..]);
+/// assert!(codec.decode(&mut buf).unwrap().is_none());
+///
+/// buf.extend_from_slice(b"lo");
+/// assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), "hello");
+/// ```
+pub trait StatefulDecoder {
+    /// States of a partially decoded frame. The default value is the state at a frame boundary.
+    type State: Default;
+
+    /// The type of decoded frames.
+    type Item;
+
+    /// The type of unrecoverable frame decoding errors.
+    type Error: From<io::Error>;
+
+    /// Returns the number of bytes that must be buffered before `step` is called in `state`.
+    ///
+    /// By default, no bytes are required.
+    fn required(&self, state: &Self::State) -> usize {
+        let _ = state;
+        0
+    }
+
+    /// Advances the state machine.
+    ///
+    /// Called only once at least [`required`](Self::required) bytes are available in `src`.
+    /// Implementations should consume the bytes they have processed from `src`.
+    fn step(
+        &mut self,
+        state: Self::State,
+        src: &mut BytesMut,
+    ) -> Result<Step<Self::State, Self::Item>, Self::Error>;
+}
+
+/// Adapter implementing [`Decoder`] for a [`StatefulDecoder`].
+///
+/// Encoding, if supported by the inner type, is forwarded unchanged.
+#[derive(Debug)]
+pub struct StatefulCodec<D: StatefulDecoder> {
+    inner: D,
+    state: D::State,
+    in_frame: bool,
+}
+
+impl<D: StatefulDecoder> StatefulCodec<D> {
+    /// Constructs new adapter around a stateful decoder.
+    pub fn new(inner: D) -> Self {
+        Self {
+            inner,
+            state: D::State::default(),
+            in_frame: false,
+        }
+    }
+
+    /// Returns a reference to the inner decoder.
+    pub fn get_ref(&self) -> &D {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the inner decoder.
+    pub fn get_mut(&mut self) -> &mut D {
+        &mut self.inner
+    }
+
+    /// Returns the state of the partially decoded frame, if any.
+    pub fn state(&self) -> Option<&D::State> {
+        self.in_frame.then_some(&self.state)
+    }
+
+    /// Returns true if decoding is in the middle of a frame.
+    pub fn is_in_frame(&self) -> bool {
+        self.in_frame
+    }
+
+    /// Consumes the adapter, returning the inner decoder.
+    ///
+    /// The state of any partially decoded frame is lost.
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+impl<D: StatefulDecoder> Decoder for StatefulCodec<D> {
+    type Item = D::Item;
+    type Error = D::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            let required = self.inner.required(&self.state);
+
+            if src.len() < required {
+                src.reserve(required - src.len());
+                return Ok(None);
+            }
+
+            let state = mem::take(&mut self.state);
+
+            match self.inner.step(state, src) {
+                Ok(Step::Next(next)) => {
+                    self.state = next;
+                    self.in_frame = true;
+                }
+
+                Ok(Step::Done(item)) => {
+                    self.in_frame = false;
+                    return Ok(Some(item));
+                }
+
+                Err(err) => {
+                    self.in_frame = false;
+                    return Err(err);
+                }
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.decode(src)? {
+            Some(item) => Ok(Some(item)),
+            None if src.is_empty() && !self.is_in_frame() => Ok(None),
+            None => Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "stream ended in the middle of a frame",
+            )
+            .into()),
+        }
+    }
+}
+
+impl<D, I> Encoder<I> for StatefulCodec<D>
+where
+    D: StatefulDecoder + Encoder<I>,
+{
+    type Error = <D as Encoder<I>>::Error;
+
+    fn encode(&mut self, item: I, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        self.inner.encode(item, dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::{Buf as _, BufMut as _, Bytes};
+
+    use super::*;
+
+    /// Header is a one byte tag and a one byte body length, followed by the body.
+    struct Tagged {
+        steps: usize,
+    }
+
+    #[derive(Debug, Default, PartialEq, Eq)]
+    enum State {
+        #[default]
+        Header,
+        Body {
+            tag: u8,
+            len: usize,
+        },
+    }
+
+    impl StatefulDecoder for Tagged {
+        type State = State;
+        type Item = (u8, Bytes);
+        type Error = io::Error;
+
+        fn required(&self, state: &State) -> usize {
+            match state {
+                State::Header => 2,
+                State::Body { len, .. } => *len,
+            }
+        }
+
+        fn step(
+            &mut self,
+            state: State,
+            src: &mut BytesMut,
+        ) -> Result<Step<State, Self::Item>, Self::Error> {
+            self.steps += 1;
+
+            match state {
+                State::Header => {
+                    let tag = src.get_u8();
+                    if tag == 0 {
+                        return Err(io::Error::new(io::ErrorKind::InvalidData, "bad tag"));
+                    }
+
+                    let len = src.get_u8() as usize;
+                    Ok(Step::Next(State::Body { tag, len }))
+                }
+
+                State::Body { tag, len } => Ok(Step::Done((tag, src.split_to(len).freeze()))),
+            }
+        }
+    }
+
+    impl Encoder<(u8, Bytes)> for Tagged {
+        type Error = io::Error;
+
+        fn encode(&mut self, (tag, body): (u8, Bytes), dst: &mut BytesMut) -> io::Result<()> {
+            dst.put_u8(tag);
+            dst.put_u8(body.len() as u8);
+            dst.put_slice(&body);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn byte_by_byte() {
+        let mut codec = StatefulCodec::new(Tagged { steps: 0 });
+
+        let mut encoded = BytesMut::new();
+        codec
+            .encode((7, Bytes::from_static(b"abc")), &mut encoded)
+            .unwrap();
+
+        let mut buf = BytesMut::new();
+        for &byte in &encoded[..encoded.len() - 1] {
+            buf.put_u8(byte);
+            assert!(codec.decode(&mut buf).unwrap().is_none());
+        }
+
+        assert_eq!(codec.state(), Some(&State::Body { tag: 7, len: 3 }));
+
+        buf.put_u8(encoded[encoded.len() - 1]);
+        let (tag, body) = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(tag, 7);
+        assert_eq!(body, "abc");
+        assert!(!codec.is_in_frame());
+
+        // step is only called once enough data is buffered
+        assert_eq!(codec.get_ref().steps, 2);
+    }
+
+    #[test]
+    fn reserves_body() {
+        let mut codec = StatefulCodec::new(Tagged { steps: 0 });
+
+        let mut buf = BytesMut::from(&[1, 200][..]);
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        assert!(buf.capacity() >= 200);
+    }
+
+    #[test]
+    fn empty_buffer_is_frame_boundary() {
+        let mut codec = StatefulCodec::new(Tagged { steps: 0 });
+
+        assert!(codec.decode(&mut BytesMut::new()).unwrap().is_none());
+        assert!(!codec.is_in_frame());
+        assert!(codec.decode_eof(&mut BytesMut::new()).unwrap().is_none());
+    }
+
+    #[test]
+    fn multiple_frames() {
+        let mut codec = StatefulCodec::new(Tagged { steps: 0 });
+
+        let mut buf = BytesMut::from(&[1, 1, b'a', 2, 0, 3][..]);
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap().0, 1);
+        assert_eq!(codec.decode(&mut buf).unwrap().unwrap(), (2, Bytes::new()));
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        assert_eq!(buf, [3][..]);
+    }
+
+    #[test]
+    fn step_error() {
+        let mut codec = StatefulCodec::new(Tagged { steps: 0 });
+
+        let mut buf = BytesMut::from(&[0, 1][..]);
+        let err = codec.decode(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn eof_mid_frame() {
+        let mut codec = StatefulCodec::new(Tagged { steps: 0 });
+
+        // header consumed, body missing
+        let mut buf = BytesMut::from(&[1, 4][..]);
+        let err = codec.decode_eof(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        let mut codec = StatefulCodec::new(Tagged { steps: 0 });
+        assert!(codec.decode_eof(&mut BytesMut::new()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,100 @@
+use std::fmt;
+
+/// Transport-level counters collected by framed types.
+///
+/// Returned by [`Framed::stats`](crate::Framed::stats) and the respective methods on
+/// [`FramedRead`](crate::FramedRead) and [`FramedWrite`](crate::FramedWrite).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct FramedStats {
+    /// Number of bytes read from the underlying I/O stream.
+    pub bytes_read: u64,
+
+    /// Number of bytes written to the underlying I/O stream.
+    pub bytes_written: u64,
+
+    /// Number of frames successfully decoded.
+    pub frames_decoded: u64,
+
+    /// Number of frames successfully encoded into the write buffer.
+    pub frames_encoded: u64,
+
+    /// Number of errors returned by the decoder.
+    pub decode_errors: u64,
+}
+
+/// Event passed to a hook set with [`Framed::set_event_hook`](crate::Framed::set_event_hook).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FramedEvent {
+    /// Given number of bytes were read from the underlying I/O stream.
+    BytesRead(usize),
+
+    /// Given number of bytes were written to the underlying I/O stream.
+    BytesWritten(usize),
+
+    /// A frame was decoded.
+    FrameDecoded,
+
+    /// A frame was encoded into the write buffer.
+    FrameEncoded,
+
+    /// The decoder returned an error.
+    DecodeError,
+}
+
+type EventHook = Box<dyn FnMut(FramedEvent) + Send + Sync>;
+
+/// Counters plus optional event hook, shared by the framed types.
+#[derive(Default)]
+pub(crate) struct Metrics {
+    stats: FramedStats,
+    hook: Option<EventHook>,
+}
+
+impl Metrics {
+    pub(crate) fn stats(&self) -> FramedStats {
+        self.stats
+    }
+
+    pub(crate) fn set_hook<F>(&mut self, hook: F)
+    where
+        F: FnMut(FramedEvent) + Send + Sync + 'static,
+    {
+        self.hook = Some(Box::new(hook));
+    }
+
+    pub(crate) fn record(&mut self, event: FramedEvent) {
+        match event {
+            FramedEvent::BytesRead(n) => self.stats.bytes_read += n as u64,
+            FramedEvent::BytesWritten(n) => self.stats.bytes_written += n as u64,
+            FramedEvent::FrameDecoded => self.stats.frames_decoded += 1,
+            FramedEvent::FrameEncoded => self.stats.frames_encoded += 1,
+            FramedEvent::DecodeError => self.stats.decode_errors += 1,
+        }
+
+        if let Some(hook) = &mut self.hook {
+            hook(event);
+        }
+    }
+}
+
+impl fmt::Debug for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metrics")
+            .field("stats", &self.stats)
+            .field("hook", &self.hook.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use static_assertions::assert_impl_all;
+
+    use crate::{BytesCodec, Framed, FramedRead, FramedWrite};
+
+    assert_impl_all!(Framed<&'static [u8], BytesCodec>: Send, Sync);
+    assert_impl_all!(FramedRead<&'static [u8], BytesCodec>: Send, Sync);
+    assert_impl_all!(FramedWrite<Vec<u8>, BytesCodec>: Send, Sync);
+}
@@ -0,0 +1,127 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_codec::{AsyncRead, AsyncWrite, BytesCodec, Framed, LinesCodec, ReadBuf};
+use actix_utils::budget::{BudgetExceeded, MemoryBudget};
+use bytes::Bytes;
+use futures_util::{SinkExt as _, StreamExt as _};
+use tokio_test::io::Builder;
+
+fn budget_exceeded(err: &io::Error) -> BudgetExceeded {
+    *err.get_ref()
+        .and_then(|err| err.downcast_ref::<BudgetExceeded>())
+        .expect("error should wrap BudgetExceeded")
+}
+
+/// Stream that never runs out of data without a newline.
+struct Endless;
+
+impl AsyncRead for Endless {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        buf.put_slice(&vec![b'a'; buf.remaining()]);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for Endless {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[tokio::test]
+async fn read_exceeds_budget() {
+    let chunk = vec![b'a'; 8 * 1024];
+    let mut io = Builder::new();
+    for _ in 0..8 {
+        io.read(&chunk);
+    }
+
+    let budget = MemoryBudget::new(32 * 1024);
+    let mut framed = Framed::new(io.build(), LinesCodec::default());
+    framed.set_memory_budget(&budget).unwrap();
+    assert_eq!(budget.used(), 16 * 1024);
+
+    // a line that never ends keeps growing the read buffer
+    let err = framed.next().await.unwrap().unwrap_err();
+    assert_eq!(budget_exceeded(&err).cap, 32 * 1024);
+
+    framed.clear_memory_budget();
+    assert_eq!(budget.used(), 0);
+}
+
+#[tokio::test]
+async fn write_exceeds_budget() {
+    let io = Builder::new().write(b"small").build();
+
+    let budget = MemoryBudget::new(32 * 1024);
+    let mut framed = Framed::new(io, BytesCodec);
+    framed.set_memory_budget(&budget).unwrap();
+
+    framed.send(Bytes::from_static(b"small")).await.unwrap();
+
+    let err = framed
+        .send(Bytes::from(vec![0; 64 * 1024]))
+        .await
+        .unwrap_err();
+    budget_exceeded(&err);
+
+    drop(framed);
+    assert_eq!(budget.used(), 0);
+}
+
+#[test]
+fn existing_buffers_exceed_budget() {
+    let io = Builder::new().build();
+    let mut framed = Framed::new(io, BytesCodec);
+
+    let budget = MemoryBudget::new(1024);
+    assert!(framed.set_memory_budget(&budget).is_err());
+    assert_eq!(budget.used(), 0);
+}
+
+#[tokio::test]
+async fn raw_reads_exceed_budget() {
+    let budget = MemoryBudget::new(32 * 1024);
+    let mut framed = Framed::new(Endless, LinesCodec::default());
+    framed.set_memory_budget(&budget).unwrap();
+
+    // a delimiter that never arrives keeps growing the read buffer
+    let err = framed.read_until(b'\n').await.unwrap_err();
+    assert_eq!(budget_exceeded(&err).cap, 32 * 1024);
+
+    framed.clear_memory_budget();
+    assert_eq!(budget.used(), 0);
+}
+
+#[tokio::test]
+async fn read_exact_reservation_exceeds_budget() {
+    let io = Builder::new().build();
+
+    let budget = MemoryBudget::new(32 * 1024);
+    let mut framed = Framed::new(io, BytesCodec);
+    framed.set_memory_budget(&budget).unwrap();
+
+    // capacity reserved for the announced length is charged before any of it is read
+    let err = framed.read_exact_bytes(1024 * 1024).await.unwrap_err();
+    budget_exceeded(&err);
+}
@@ -0,0 +1,40 @@
+use std::io;
+
+use actix_codec::{Framed, LinesCodec};
+use actix_utils::duplex::DuplexBuilder;
+use futures_util::{SinkExt as _, StreamExt as _};
+
+#[tokio::test]
+async fn lines_over_chunked_duplex() {
+    let (client, server) = DuplexBuilder::new().chunk_size(1).max_buf_size(4).build();
+
+    let mut client = Framed::new(client, LinesCodec::default());
+    let mut server = Framed::new(server, LinesCodec::default());
+
+    let echo = tokio::spawn(async move {
+        while let Some(line) = server.next().await {
+            server.send(line.unwrap().to_uppercase()).await.unwrap();
+        }
+    });
+
+    for line in ["hello", "framed world"] {
+        client.send(line).await.unwrap();
+        let res = client.next().await.unwrap().unwrap();
+        assert_eq!(res, line.to_uppercase());
+    }
+
+    drop(client);
+    echo.await.unwrap();
+}
+
+#[tokio::test]
+async fn injected_read_error() {
+    let (client, _server) = DuplexBuilder::new().build();
+    let control = client.control();
+
+    let mut client = Framed::new(client, LinesCodec::default());
+    control.fail_next_read(io::ErrorKind::ConnectionReset.into());
+
+    let err = client.next().await.unwrap().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+}
@@ -0,0 +1,108 @@
+use std::sync::{Arc, Mutex};
+
+use actix_codec::{BytesCodec, FramedEvent, FramedRead, FramedWrite, LinesCodec};
+use bytes::Bytes;
+use futures_util::{SinkExt, StreamExt as _};
+use tokio_test::io::Builder;
+
+#[tokio::test]
+async fn framed_read_lines() {
+    let io = Builder::new().read(b"foo\nba").read(b"r\nbaz").build();
+    let mut framed = FramedRead::new(io, LinesCodec::default());
+
+    assert_eq!(framed.next().await.unwrap().unwrap(), "foo");
+    assert_eq!(framed.next().await.unwrap().unwrap(), "bar");
+    assert_eq!(framed.next().await.unwrap().unwrap(), "baz");
+    assert!(framed.next().await.is_none());
+    assert!(framed.is_read_buf_empty());
+}
+
+#[tokio::test]
+async fn framed_read_with_read_buf() {
+    let io = Builder::new().read(b"ar\n").build();
+    let mut framed = FramedRead::with_read_buf(io, LinesCodec::default(), "foo\nb".into());
+
+    assert_eq!(framed.next().await.unwrap().unwrap(), "foo");
+    assert_eq!(framed.next().await.unwrap().unwrap(), "bar");
+    assert!(framed.next().await.is_none());
+}
+
+#[tokio::test]
+async fn framed_write_lines() {
+    let io = Builder::new().write(b"foo\nbar\n").build();
+    let mut framed = FramedWrite::new(io, LinesCodec::default());
+
+    framed.feed("foo").await.unwrap();
+    framed.feed("bar").await.unwrap();
+    assert!(!framed.is_write_buf_empty());
+
+    SinkExt::<&str>::flush(&mut framed).await.unwrap();
+    assert!(framed.is_write_buf_empty());
+}
+
+#[tokio::test]
+async fn framed_write_close_flushes() {
+    let io = Builder::new().write(b"hello").build();
+    let mut framed = FramedWrite::new(io, BytesCodec);
+
+    framed.feed(Bytes::from_static(b"hello")).await.unwrap();
+    SinkExt::<Bytes>::close(&mut framed).await.unwrap();
+}
+
+#[tokio::test]
+async fn framed_write_into_parts() {
+    let io = Builder::new().build();
+    let mut framed = FramedWrite::new(io, BytesCodec);
+
+    framed.feed(Bytes::from_static(b"pending")).await.unwrap();
+
+    let (_io, _codec, write_buf) = framed.into_parts();
+    assert_eq!(&write_buf[..], b"pending");
+}
+
+#[tokio::test]
+async fn framed_read_stats() {
+    let io = Builder::new().read(b"foo\n\xff\nbar").build();
+    let mut framed = FramedRead::new(io, LinesCodec::default());
+
+    let events = Arc::new(Mutex::new(Vec::new()));
+    framed.set_event_hook({
+        let events = Arc::clone(&events);
+        move |ev| events.lock().unwrap().push(ev)
+    });
+
+    assert_eq!(framed.next().await.unwrap().unwrap(), "foo");
+    assert!(framed.next().await.unwrap().is_err());
+    assert_eq!(framed.next().await.unwrap().unwrap(), "bar");
+    assert!(framed.next().await.is_none());
+
+    let stats = framed.stats();
+    assert_eq!(stats.bytes_read, 9);
+    assert_eq!(stats.frames_decoded, 2);
+    assert_eq!(stats.decode_errors, 1);
+    assert_eq!(stats.bytes_written, 0);
+
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![
+            FramedEvent::BytesRead(9),
+            FramedEvent::FrameDecoded,
+            FramedEvent::DecodeError,
+            FramedEvent::FrameDecoded,
+        ]
+    );
+}
+
+#[tokio::test]
+async fn framed_write_stats() {
+    let io = Builder::new().write(b"foo\nbar\n").build();
+    let mut framed = FramedWrite::new(io, LinesCodec::default());
+
+    framed.feed("foo").await.unwrap();
+    framed.feed("bar").await.unwrap();
+    assert_eq!(framed.stats().frames_encoded, 2);
+    assert_eq!(framed.stats().bytes_written, 0);
+
+    SinkExt::<&str>::flush(&mut framed).await.unwrap();
+    assert_eq!(framed.stats().bytes_written, 8);
+}
@@ -0,0 +1,65 @@
+use std::io;
+
+use actix_codec::{Framed, LinesCodec};
+use futures_util::StreamExt as _;
+use tokio::io::{AsyncBufReadExt as _, AsyncReadExt as _};
+use tokio_test::io::Builder;
+
+#[tokio::test]
+async fn interleaved_raw_reads() {
+    let io = Builder::new()
+        .read(b"HEAD 5\nhel")
+        .read(b"lo")
+        .read(b"next\ntail")
+        .build();
+    let mut framed = Framed::new(io, LinesCodec::default());
+
+    assert_eq!(framed.next().await.unwrap().unwrap(), "HEAD 5");
+
+    let body = framed.read_exact_bytes(5).await.unwrap();
+    assert_eq!(body, "hello");
+
+    assert_eq!(framed.next().await.unwrap().unwrap(), "next");
+
+    let tail = framed.read_until(b'\n').await.unwrap();
+    assert_eq!(tail, "tail");
+    assert!(framed.next().await.is_none());
+}
+
+#[tokio::test]
+async fn read_until_includes_delimiter() {
+    let io = Builder::new().read(b"abc").read(b"d;ef\n").build();
+    let mut framed = Framed::new(io, LinesCodec::default());
+
+    assert_eq!(framed.read_until(b';').await.unwrap(), "abcd;");
+
+    // remaining buffered data is still decoded as frames
+    assert_eq!(framed.next().await.unwrap().unwrap(), "ef");
+}
+
+#[tokio::test]
+async fn read_exact_bytes_eof() {
+    let io = Builder::new().read(b"abc").build();
+    let mut framed = Framed::new(io, LinesCodec::default());
+
+    let err = framed.read_exact_bytes(4).await.unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[tokio::test]
+async fn raw_reader() {
+    let io = Builder::new().read(b"line one\nraw\nline two\n").build();
+    let mut framed = Framed::new(io, LinesCodec::default());
+
+    assert_eq!(framed.next().await.unwrap().unwrap(), "line one");
+
+    let mut raw = String::new();
+    framed.raw_reader().read_line(&mut raw).await.unwrap();
+    assert_eq!(raw, "raw\n");
+
+    let mut buf = [0; 4];
+    framed.raw_reader().read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"line");
+
+    assert_eq!(framed.next().await.unwrap().unwrap(), " two");
+}
@@ -6,12 +6,13 @@ use std::{
         Context,
         Poll::{self, Pending, Ready},
     },
+    time::Duration,
 };
 
 use actix_codec::*;
 use bytes::{Buf as _, BufMut as _, BytesMut};
 use futures_sink::Sink;
-use tokio_test::{assert_ready, task};
+use tokio_test::task;
 
 macro_rules! bilateral {
     ($($x:expr,)*) => {{
@@ -220,3 +221,173 @@ fn test_write_hits_highwater_mark() {
         assert_eq!(0, Pin::new(&framed).get_ref().io_ref().calls.len());
     });
 }
+
+/// Writer that only accepts vectored writes and records the number of slices of each call.
+#[derive(Debug, Default)]
+pub struct Vectored {
+    pub written: Vec<u8>,
+    pub calls: Vec<usize>,
+}
+
+impl AsyncWrite for Vectored {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        _buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        panic!("expected vectored write")
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<Result<usize, io::Error>> {
+        let this = Pin::get_mut(self);
+        this.calls.push(bufs.len());
+
+        // accept at most 6 bytes per call to exercise partial chunk writes
+        let mut n = 0;
+        for buf in bufs {
+            let take = buf.len().min(6 - n);
+            this.written.extend_from_slice(&buf[..take]);
+            n += take;
+
+            if n == 6 {
+                break;
+            }
+        }
+
+        Ready(Ok(n))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Ready(Ok(()))
+    }
+}
+
+impl AsyncRead for Vectored {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        _buf: &mut ReadBuf<'_>,
+    ) -> Poll<Result<(), std::io::Error>> {
+        Ready(Ok(()))
+    }
+}
+
+#[test]
+fn test_write_vectored() {
+    let mut framed = Framed::new(Vectored::default(), U32);
+
+    let mut task = task::spawn(());
+    task.enter(|cx, _| {
+        let mut framed = Pin::new(&mut framed);
+
+        for i in 0..4 {
+            assert!(framed.as_mut().start_send(i).is_ok());
+        }
+        assert!(!framed.is_write_buf_empty());
+
+        assert!(assert_ready!(framed.as_mut().poll_flush(cx)).is_ok());
+        assert!(framed.is_write_buf_empty());
+    });
+
+    let stats = framed.stats();
+    assert_eq!(stats.frames_encoded, 4);
+    assert_eq!(stats.bytes_written, 16);
+
+    // counters are kept when converting to and from parts
+    let framed = Framed::from_parts(framed.into_parts());
+    assert_eq!(framed.stats(), stats);
+
+    let io = framed.into_parts().io;
+
+    let mut expected = BytesMut::new();
+    for i in 0..4 {
+        expected.put_u32(i);
+    }
+    assert_eq!(io.written, expected);
+
+    // frames are passed to the IO as separate slices
+    assert_eq!(io.calls, vec![4, 3, 1]);
+}
+
+#[test]
+fn test_write_vectored_into_parts() {
+    let mut framed = Framed::new(Vectored::default(), U32);
+
+    let mut task = task::spawn(());
+    task.enter(|_cx, _| {
+        let mut framed = Pin::new(&mut framed);
+        assert!(framed.as_mut().start_send(1).is_ok());
+        assert!(framed.as_mut().start_send(2).is_ok());
+    });
+
+    // queued chunks are returned as part of the write buffer
+    let parts = framed.into_parts();
+    assert_eq!(&parts.write_buf[..], &[0, 0, 0, 1, 0, 0, 0, 2]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_flush_coalescing() {
+    let mut framed = Framed::new(Vectored::default(), U32);
+    framed.set_flush_coalescing(3, Duration::from_millis(10));
+
+    let mut task = task::spawn(());
+
+    // flush is held back until enough frames are buffered
+    task.enter(|cx, _| {
+        let mut framed = Pin::new(&mut framed);
+        assert!(framed.as_mut().start_send(1).is_ok());
+        assert!(framed.as_mut().start_send(2).is_ok());
+        assert!(framed.as_mut().poll_flush(cx).is_pending());
+    });
+    assert!(framed.io_ref().written.is_empty());
+
+    task.enter(|cx, _| {
+        let mut framed = Pin::new(&mut framed);
+        assert!(framed.as_mut().start_send(3).is_ok());
+        assert!(assert_ready!(framed.as_mut().poll_flush(cx)).is_ok());
+    });
+    assert_eq!(framed.io_ref().written.len(), 12);
+
+    // ...or until the delay elapses
+    task.enter(|cx, _| {
+        let mut framed = Pin::new(&mut framed);
+        assert!(framed.as_mut().start_send(4).is_ok());
+        assert!(framed.as_mut().poll_flush(cx).is_pending());
+    });
+    assert_eq!(framed.io_ref().written.len(), 12);
+
+    tokio::time::sleep(Duration::from_millis(10)).await;
+    assert!(task.is_woken());
+
+    task.enter(|cx, _| {
+        let framed = Pin::new(&mut framed);
+        assert!(assert_ready!(framed.poll_flush(cx)).is_ok());
+    });
+    assert_eq!(framed.io_ref().written.len(), 16);
+
+    // flushing with an empty buffer is never delayed
+    task.enter(|cx, _| {
+        let framed = Pin::new(&mut framed);
+        assert!(assert_ready!(framed.poll_flush(cx)).is_ok());
+    });
+
+    framed.clear_flush_coalescing();
+    task.enter(|cx, _| {
+        let mut framed = Pin::new(&mut framed);
+        assert!(framed.as_mut().start_send(5).is_ok());
+        assert!(assert_ready!(framed.as_mut().poll_flush(cx)).is_ok());
+    });
+    assert_eq!(framed.io_ref().written.len(), 20);
+}
@@ -7,6 +7,35 @@
 //!
 //! # Tests
 //! See docs for the [`#[test]`](macro@test) macro.
+//!
+//! # Benchmarks
+//! See docs for the [`#[bench]`](macro@bench) macro.
+//!
+//! # Runtime Configuration
+//! All macros run the function in a [`System`] created with `System::new()`, which is backed by a
+//! single-threaded Tokio runtime. This can be changed with the following arguments:
+//!
+//! - `system = "path::to::System"`: use a re-exported or wrapped `System` type;
+//! - `flavor = "multi_thread"`: back the system with a multi-threaded Tokio runtime, so that tasks
+//!   spawned with `tokio::spawn` run on worker threads. The `tokio` crate, with its
+//!   `rt-multi-thread` feature enabled, must be available for macro output to compile;
+//! - `worker_threads = N`: number of worker threads of the `multi_thread` flavor;
+//! - `runtime = "path::to::factory"`: create the Tokio runtime by calling the given function, as
+//!   for [`System::with_tokio_rt`]. Cannot be combined with `flavor`.
+//!
+//! The `multi_thread` flavor and the `runtime` option are not supported when the `io-uring`
+//! feature of `actix-rt` is enabled, since its system cannot be backed by a custom Tokio runtime.
+//! Using them fails to compile.
+//!
+//! ```ignore
+//! #[actix_rt::main(flavor = "multi_thread", worker_threads = 4)]
+//! async fn main() {
+//!     tokio::spawn(async { println!("Hello from a worker thread") });
+//! }
+//! ```
+//!
+//! [`System`]: https://docs.rs/actix-rt/2/actix_rt/struct.System.html
+//! [`System::with_tokio_rt`]: https://docs.rs/actix-rt/2/actix_rt/struct.System.html#method.with_tokio_rt
 
 #![deny(rust_2018_idioms, nonstandard_style)]
 #![warn(future_incompatible)]
@@ -14,13 +43,15 @@
 #![doc(html_favicon_url = "https://actix.rs/favicon.ico")]
 
 use proc_macro::TokenStream;
-use quote::quote;
+use quote::{quote, quote_spanned};
 use syn::parse::Parser as _;
 
 type AttributeArgs = syn::punctuated::Punctuated<syn::Meta, syn::Token![,]>;
 
 /// Marks async entry-point function to be executed by Actix system.
 ///
+/// See the [crate docs](crate#runtime-configuration) for supported arguments.
+///
 /// # Examples
 /// ```
 /// #[actix_rt::main]
@@ -56,52 +87,19 @@ pub fn main(args: TokenStream, item: TokenStream) -> TokenStream {
         .into();
     }
 
-    let mut system = syn::parse_str::<syn::Path>("::actix_rt::System").unwrap();
-
-    for arg in &args {
-        match arg {
-            syn::Meta::NameValue(syn::MetaNameValue {
-                path,
-                value:
-                    syn::Expr::Lit(syn::ExprLit {
-                        lit: syn::Lit::Str(lit),
-                        ..
-                    }),
-                ..
-            }) => match path
-                .get_ident()
-                .map(|i| i.to_string().to_lowercase())
-                .as_deref()
-            {
-                Some("system") => match lit.parse() {
-                    Ok(path) => system = path,
-                    Err(_) => {
-                        return syn::Error::new_spanned(lit, "Expected path")
-                            .to_compile_error()
-                            .into();
-                    }
-                },
-                _ => {
-                    return syn::Error::new_spanned(arg, "Unknown attribute specified")
-                        .to_compile_error()
-                        .into();
-                }
-            },
+    let args = match Args::parse(&args, false) {
+        Ok(args) => args,
+        Err(err) => return err.to_compile_error().into(),
+    };
 
-            _ => {
-                return syn::Error::new_spanned(arg, "Unknown attribute specified")
-                    .to_compile_error()
-                    .into();
-            }
-        }
-    }
+    let system_runner = args.system_runner();
 
     sig.asyncness = None;
 
     (quote! {
         #(#attrs)*
         #vis #sig {
-            <#system>::new().block_on(async move { #body })
+            #system_runner.block_on(async move { #body })
         }
     })
     .into()
@@ -109,12 +107,42 @@ pub fn main(args: TokenStream, item: TokenStream) -> TokenStream {
 
 /// Marks async test function to be executed in an Actix system.
 ///
+/// See the [crate docs](crate#runtime-configuration) for supported arguments.
+///
+/// # Timeout
+/// Passing a duration with `timeout = "..."` fails the test if it does not complete in time. The
+/// duration is an integer followed by one of the units `ms`, `s`, `m`, or `h`.
+///
+/// # Setup and Teardown
+/// Passing `setup = "path::to::fn"` runs the given async function within the system before the
+/// test. If the test function takes an argument, it receives the value returned by `setup`;
+/// otherwise the value is kept alive until the test and any teardown complete.
+///
+/// Passing `teardown = "path::to::fn"` runs the given async function within the system after the
+/// test, even if the test panics or times out. The test's panic is then resumed.
+///
 /// # Examples
 /// ```
 /// #[actix_rt::test]
 /// async fn my_test() {
 ///     assert!(true);
 /// }
+///
+/// #[actix_rt::test(timeout = "30s")]
+/// async fn my_timed_test() {
+///     assert!(true);
+/// }
+///
+/// async fn start_server() -> u16 {
+///     8080
+/// }
+///
+/// async fn clean_up() {}
+///
+/// #[actix_rt::test(setup = "start_server", teardown = "clean_up")]
+/// async fn my_fixture_test(port: u16) {
+///     assert_eq!(port, 8080);
+/// }
 /// ```
 #[proc_macro_attribute]
 pub fn test(args: TokenStream, item: TokenStream) -> TokenStream {
@@ -159,55 +187,384 @@ pub fn test(args: TokenStream, item: TokenStream) -> TokenStream {
         quote! { #[::core::prelude::v1::test] }
     };
 
-    let mut system = syn::parse_str::<syn::Path>("::actix_rt::System").unwrap();
-
-    for arg in &args {
-        match arg {
-            syn::Meta::NameValue(syn::MetaNameValue {
-                path,
-                value:
-                    syn::Expr::Lit(syn::ExprLit {
-                        lit: syn::Lit::Str(lit),
-                        ..
-                    }),
-                ..
-            }) => match path
-                .get_ident()
-                .map(|i| i.to_string().to_lowercase())
-                .as_deref()
-            {
-                Some("system") => match lit.parse() {
-                    Ok(path) => system = path,
-                    Err(_) => {
-                        return syn::Error::new_spanned(lit, "Expected path")
-                            .to_compile_error()
-                            .into();
-                    }
-                },
-                _ => {
-                    return syn::Error::new_spanned(arg, "Unknown attribute specified")
+    let args = match Args::parse(&args, true) {
+        Ok(args) => args,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let system_runner = args.system_runner();
+
+    let setup = match &args.setup {
+        Some(setup) => {
+            if sig.inputs.len() > 1 {
+                return syn::Error::new_spanned(
+                    &sig.inputs,
+                    "Expected at most one argument to receive the output of `setup`",
+                )
+                .to_compile_error()
+                .into();
+            }
+
+            match sig.inputs.pop().map(|arg| arg.into_value()) {
+                Some(syn::FnArg::Typed(arg)) => {
+                    let pat = &arg.pat;
+                    let ty = &arg.ty;
+                    quote! { let #pat: #ty = #setup().await; }
+                }
+                Some(arg) => {
+                    return syn::Error::new_spanned(arg, "Unexpected `self` argument")
                         .to_compile_error()
                         .into();
                 }
-            },
-            _ => {
-                return syn::Error::new_spanned(arg, "Unknown attribute specified")
-                    .to_compile_error()
-                    .into();
+                None => quote! { let _actix_fixture = #setup().await; },
             }
         }
-    }
+        None => quote! {},
+    };
+
+    let body = match args.timeout {
+        Some((millis, display)) => {
+            let name = sig.ident.to_string();
+
+            quote! {
+                match ::actix_rt::time::timeout(
+                    ::core::time::Duration::from_millis(#millis),
+                    async { #body },
+                )
+                .await
+                {
+                    ::core::result::Result::Ok(res) => res,
+                    ::core::result::Result::Err(_) => {
+                        ::core::panic!("test `{}` timed out after {}", #name, #display)
+                    }
+                }
+            }
+        }
+        None => quote! { #body },
+    };
+
+    let body = match &args.teardown {
+        // run test as a separate task so that its panics are caught and teardown still runs
+        Some(teardown) => quote! {
+            #setup
+            let res = ::actix_rt::spawn(async move { #body }).await;
+            #teardown().await;
+
+            match res {
+                ::core::result::Result::Ok(res) => res,
+                ::core::result::Result::Err(err) => ::std::panic::resume_unwind(err.into_panic()),
+            }
+        },
+        None => quote! {
+            #setup
+            #body
+        },
+    };
 
     (quote! {
         #missing_test_attr
         #(#attrs)*
         #vis #sig {
-            <#system>::new().block_on(async { #body })
+            #system_runner.block_on(async { #body })
         }
     })
     .into()
 }
 
+/// Marks benchmark function to be given an Actix system for driving async code.
+///
+/// The function's last argument receives a reference to a [`SystemRunner`] that is created once
+/// per call, before the benchmark runs. The argument is removed from the generated function so
+/// that it can be registered with benchmark harnesses such as [Criterion]. Async iterations are
+/// driven with [`SystemRunner::block_on`], typically from within Criterion's `iter_custom`.
+///
+/// See the [crate docs](crate#runtime-configuration) for supported arguments.
+///
+/// # Examples
+/// ```ignore
+/// use std::time::Instant;
+///
+/// use actix_rt::SystemRunner;
+/// use criterion::{criterion_group, criterion_main, Criterion};
+///
+/// #[actix_rt::bench]
+/// fn bench_call(c: &mut Criterion, sys: &SystemRunner) {
+///     let service = sys.block_on(make_service());
+///
+///     c.bench_function("call", |b| {
+///         b.iter_custom(|iters| {
+///             sys.block_on(async {
+///                 let start = Instant::now();
+///                 for _ in 0..iters {
+///                     service.call(()).await.unwrap();
+///                 }
+///                 start.elapsed()
+///             })
+///         })
+///     });
+/// }
+///
+/// criterion_group!(benches, bench_call);
+/// criterion_main!(benches);
+/// ```
+///
+/// [`SystemRunner`]: https://docs.rs/actix-rt/2/actix_rt/struct.SystemRunner.html
+/// [`SystemRunner::block_on`]: https://docs.rs/actix-rt/2/actix_rt/struct.SystemRunner.html#method.block_on
+/// [Criterion]: https://docs.rs/criterion
+#[proc_macro_attribute]
+pub fn bench(args: TokenStream, item: TokenStream) -> TokenStream {
+    let mut input = match syn::parse::<syn::ItemFn>(item.clone()) {
+        Ok(input) => input,
+        // on parse err, make IDEs happy; see fn docs
+        Err(err) => return input_and_compile_error(item, err),
+    };
+
+    let parser = AttributeArgs::parse_terminated;
+    let args = match parser.parse(args.clone()) {
+        Ok(args) => args,
+        Err(err) => return input_and_compile_error(args, err),
+    };
+
+    let attrs = &input.attrs;
+    let vis = &input.vis;
+    let sig = &mut input.sig;
+    let body = &input.block;
+
+    if let Some(asyncness) = sig.asyncness {
+        return syn::Error::new_spanned(
+            asyncness,
+            "the async keyword is not supported; drive async code using the system runner argument",
+        )
+        .to_compile_error()
+        .into();
+    }
+
+    let runner_arg = match sig.inputs.pop().map(|arg| arg.into_value()) {
+        Some(syn::FnArg::Typed(arg)) => arg,
+        _ => {
+            return syn::Error::new_spanned(
+                &sig.ident,
+                "Expected a last argument to receive the system runner",
+            )
+            .to_compile_error()
+            .into();
+        }
+    };
+
+    let args = match Args::parse(&args, false) {
+        Ok(args) => args,
+        Err(err) => return err.to_compile_error().into(),
+    };
+
+    let system_runner = args.system_runner();
+    let pat = &runner_arg.pat;
+    let ty = &runner_arg.ty;
+
+    (quote! {
+        #(#attrs)*
+        #vis #sig {
+            let system_runner = #system_runner;
+            let #pat: #ty = &system_runner;
+            #body
+        }
+    })
+    .into()
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Flavor {
+    CurrentThread,
+    MultiThread,
+}
+
+/// Arguments accepted by the `main`, `test`, and `bench` macros.
+struct Args {
+    system: syn::Path,
+    flavor: Option<(Flavor, syn::LitStr)>,
+    worker_threads: Option<syn::LitInt>,
+    runtime: Option<(syn::Path, syn::LitStr)>,
+    /// Test timeout in milliseconds, along with the duration as written.
+    timeout: Option<(u64, String)>,
+    setup: Option<syn::Path>,
+    teardown: Option<syn::Path>,
+}
+
+impl Args {
+    /// Parses macro arguments; `timeout`, `setup`, and `teardown` are only accepted if `is_test` is
+    /// true.
+    fn parse(args: &AttributeArgs, is_test: bool) -> Result<Self, syn::Error> {
+        let mut this = Args {
+            system: syn::parse_str("::actix_rt::System").unwrap(),
+            flavor: None,
+            worker_threads: None,
+            runtime: None,
+            timeout: None,
+            setup: None,
+            teardown: None,
+        };
+
+        for arg in args {
+            let (path, value) = match arg {
+                syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    value: syn::Expr::Lit(syn::ExprLit { lit, .. }),
+                    ..
+                }) => (path, lit),
+                _ => return Err(syn::Error::new_spanned(arg, "Unknown attribute specified")),
+            };
+
+            let name = path.get_ident().map(|i| i.to_string().to_lowercase());
+
+            match (name.as_deref(), value) {
+                (Some("system"), syn::Lit::Str(lit)) => {
+                    this.system = lit
+                        .parse()
+                        .map_err(|_| syn::Error::new_spanned(lit, "Expected path"))?;
+                }
+
+                (Some("flavor"), syn::Lit::Str(lit)) => {
+                    let flavor = match lit.value().as_str() {
+                        "current_thread" => Flavor::CurrentThread,
+                        "multi_thread" => Flavor::MultiThread,
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                lit,
+                                "Expected \"current_thread\" or \"multi_thread\"",
+                            ))
+                        }
+                    };
+
+                    this.flavor = Some((flavor, lit.clone()));
+                }
+
+                (Some("worker_threads"), syn::Lit::Int(lit)) => {
+                    if lit.base10_parse::<usize>()? == 0 {
+                        return Err(syn::Error::new_spanned(
+                            lit,
+                            "Expected a number of worker threads greater than 0",
+                        ));
+                    }
+
+                    this.worker_threads = Some(lit.clone());
+                }
+
+                (Some("runtime"), syn::Lit::Str(lit)) => match lit.parse() {
+                    Ok(path) => this.runtime = Some((path, lit.clone())),
+                    Err(_) => return Err(syn::Error::new_spanned(lit, "Expected path")),
+                },
+
+                (Some("timeout"), syn::Lit::Str(lit)) if is_test => {
+                    match parse_duration_millis(&lit.value()) {
+                        Some(millis) => this.timeout = Some((millis, lit.value())),
+                        None => {
+                            return Err(syn::Error::new_spanned(
+                                lit,
+                                "Expected duration such as \"500ms\", \"30s\", or \"1m\"",
+                            ))
+                        }
+                    }
+                }
+
+                (Some("setup"), syn::Lit::Str(lit)) if is_test => {
+                    this.setup = Some(
+                        lit.parse()
+                            .map_err(|_| syn::Error::new_spanned(lit, "Expected path"))?,
+                    );
+                }
+
+                (Some("teardown"), syn::Lit::Str(lit)) if is_test => {
+                    this.teardown = Some(
+                        lit.parse()
+                            .map_err(|_| syn::Error::new_spanned(lit, "Expected path"))?,
+                    );
+                }
+
+                _ => return Err(syn::Error::new_spanned(arg, "Unknown attribute specified")),
+            }
+        }
+
+        if let Some((_, lit)) = &this.runtime {
+            if this.flavor.is_some() {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    "The `runtime` option cannot be combined with `flavor`",
+                ));
+            }
+        }
+
+        if let Some(lit) = &this.worker_threads {
+            if !matches!(this.flavor, Some((Flavor::MultiThread, _))) {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    "The `worker_threads` option requires `flavor = \"multi_thread\"`",
+                ));
+            }
+        }
+
+        Ok(this)
+    }
+
+    /// Generates an expression creating the `SystemRunner` that drives the function body.
+    fn system_runner(&self) -> proc_macro2::TokenStream {
+        let system = &self.system;
+
+        if let Some((runtime, lit)) = &self.runtime {
+            let check = require_tokio_rt("runtime", lit);
+            return quote! { { #check <#system>::with_tokio_rt(#runtime) } };
+        }
+
+        match &self.flavor {
+            Some((Flavor::MultiThread, lit)) => {
+                let check = require_tokio_rt("flavor", lit);
+                let worker_threads = self
+                    .worker_threads
+                    .as_ref()
+                    .map(|n| quote! { .worker_threads(#n) });
+
+                quote! {
+                    {
+                        #check
+                        <#system>::with_tokio_rt(|| {
+                            ::tokio::runtime::Builder::new_multi_thread()
+                                .enable_all()
+                                #worker_threads
+                                .build()
+                                .expect("Multi-threaded Tokio runtime could not be created.")
+                        })
+                    }
+                }
+            }
+
+            Some((Flavor::CurrentThread, _)) | None => quote! { <#system>::new() },
+        }
+    }
+}
+
+/// Generates a statement failing compilation at `lit` if `actix-rt` is built for io-uring, which
+/// does not support custom Tokio runtimes.
+fn require_tokio_rt(option: &str, lit: &syn::LitStr) -> proc_macro2::TokenStream {
+    quote_spanned! {lit.span()=>
+        ::actix_rt::__require_tokio_rt!(#option);
+    }
+}
+
+/// Parses durations of the form `<integer><unit>` where unit is one of `ms`, `s`, `m`, or `h`.
+fn parse_duration_millis(duration: &str) -> Option<u64> {
+    let unit_start = duration.find(|c: char| !c.is_ascii_digit())?;
+    let (value, unit) = duration.split_at(unit_start);
+    let value = value.parse::<u64>().ok()?;
+
+    let multiplier = match unit {
+        "ms" => 1,
+        "s" => 1_000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        _ => return None,
+    };
+
+    value.checked_mul(multiplier)
+}
+
 /// Converts the error to a token stream and appends it to the original input.
 ///
 /// Returning the original input in addition to the error is good for IDEs which can gracefully
@@ -9,6 +9,9 @@ fn compile_macros() {
     t.pass("tests/trybuild/main-04-system-path.rs");
     t.compile_fail("tests/trybuild/main-05-system-expect-path.rs");
     t.compile_fail("tests/trybuild/main-06-unknown-attr.rs");
+    t.pass("tests/trybuild/main-07-flavor.rs");
+    t.pass("tests/trybuild/main-08-runtime-path.rs");
+    t.compile_fail("tests/trybuild/main-09-invalid-runtime-args.rs");
 
     t.pass("tests/trybuild/test-01-basic.rs");
     t.pass("tests/trybuild/test-02-keep-attrs.rs");
@@ -16,4 +19,12 @@ fn compile_macros() {
     t.pass("tests/trybuild/test-04-system-path.rs");
     t.compile_fail("tests/trybuild/test-05-system-expect-path.rs");
     t.compile_fail("tests/trybuild/test-06-unknown-attr.rs");
+    t.pass("tests/trybuild/test-07-timeout.rs");
+    t.compile_fail("tests/trybuild/test-08-timeout-expect-duration.rs");
+    t.pass("tests/trybuild/test-09-flavor.rs");
+    t.pass("tests/trybuild/test-10-setup-teardown.rs");
+    t.compile_fail("tests/trybuild/test-11-setup-expect-one-arg.rs");
+
+    t.pass("tests/trybuild/bench-01-basic.rs");
+    t.compile_fail("tests/trybuild/bench-02-expect-runner.rs");
 }
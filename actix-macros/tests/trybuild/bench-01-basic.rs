@@ -0,0 +1,10 @@
+struct Harness;
+
+#[actix_rt::bench]
+fn my_bench(_harness: &mut Harness, sys: &actix_rt::SystemRunner) {
+    sys.block_on(async { futures_util::future::ready(()).await });
+}
+
+fn main() {
+    my_bench(&mut Harness);
+}
@@ -0,0 +1,7 @@
+#[actix_rt::bench]
+fn my_bench_1() {}
+
+#[actix_rt::bench]
+async fn my_bench_2(sys: &actix_rt::SystemRunner) {}
+
+fn main() {}
@@ -0,0 +1,4 @@
+#[actix_rt::main(flavor = "multi_thread", worker_threads = 2)]
+async fn main() {
+    tokio::spawn(async { println!("Hello world") }).await.unwrap();
+}
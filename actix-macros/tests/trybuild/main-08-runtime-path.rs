@@ -0,0 +1,11 @@
+fn build_runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .unwrap()
+}
+
+#[actix_rt::main(runtime = "build_runtime")]
+async fn main() {
+    futures_util::future::ready(()).await
+}
@@ -0,0 +1,13 @@
+#[actix_rt::main(flavor = "work_stealing")]
+async fn main_1() {}
+
+#[actix_rt::main(worker_threads = 2)]
+async fn main_2() {}
+
+#[actix_rt::main(flavor = "multi_thread", worker_threads = 0)]
+async fn main_3() {}
+
+#[actix_rt::main(runtime = "build_runtime", flavor = "current_thread")]
+async fn main_4() {}
+
+fn main() {}
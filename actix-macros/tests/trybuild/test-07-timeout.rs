@@ -0,0 +1,6 @@
+#[actix_rt::test(timeout = "30s")]
+async fn my_test() {
+    futures_util::future::ready(()).await
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+#[actix_rt::test(timeout = "30")]
+async fn my_test_1() {}
+
+#[actix_rt::test(timeout = "5 days")]
+async fn my_test_2() {}
+
+fn main() {}
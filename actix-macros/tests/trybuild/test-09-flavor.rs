@@ -0,0 +1,6 @@
+#[actix_rt::test(flavor = "multi_thread", worker_threads = 2, timeout = "30s")]
+async fn my_test() {
+    tokio::spawn(async {}).await.unwrap();
+}
+
+fn main() {}
@@ -0,0 +1,17 @@
+async fn setup() -> u16 {
+    8080
+}
+
+async fn teardown() {}
+
+#[actix_rt::test(setup = "setup", teardown = "teardown")]
+async fn my_test(port: u16) {
+    assert_eq!(port, 8080);
+}
+
+#[actix_rt::test(teardown = "teardown")]
+async fn my_result_test() -> Result<(), std::io::Error> {
+    futures_util::future::ready(Ok(())).await
+}
+
+fn main() {}
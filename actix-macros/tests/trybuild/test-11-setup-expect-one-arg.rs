@@ -0,0 +1,8 @@
+async fn setup() -> u16 {
+    8080
+}
+
+#[actix_rt::test(setup = "setup")]
+async fn my_test(port: u16, host: &str) {}
+
+fn main() {}
@@ -51,6 +51,8 @@ compile_error!("io_uring is a linux only feature.");
 
 use std::future::Future;
 
+#[cfg(feature = "macros")]
+pub use actix_macros::bench;
 // Cannot define a main macro when compiled into test harness.
 // Workaround for https://github.com/rust-lang/rust/issues/62127.
 #[cfg(all(feature = "macros", not(test)))]
@@ -58,6 +60,27 @@ pub use actix_macros::main;
 #[cfg(feature = "macros")]
 pub use actix_macros::test;
 
+/// Fails compilation of macro output using runtime `$option`s unavailable with io-uring.
+#[cfg(feature = "io-uring")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __require_tokio_rt {
+    ($option:literal) => {
+        compile_error!(concat!(
+            "The `",
+            $option,
+            "` option is not supported with the `io-uring` feature of actix-rt"
+        ));
+    };
+}
+
+#[cfg(not(feature = "io-uring"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __require_tokio_rt {
+    ($option:literal) => {};
+}
+
 mod arbiter;
 mod runtime;
 mod system;
@@ -0,0 +1,54 @@
+//! Checks the setup and teardown arguments of the test macro.
+
+#![cfg(feature = "macros")]
+
+use std::cell::Cell;
+
+thread_local! {
+    static TORN_DOWN: Cell<bool> = const { Cell::new(false) };
+}
+
+async fn setup() -> u16 {
+    // setup runs within the system
+    actix_rt::System::current();
+    8080
+}
+
+async fn teardown() {
+    actix_rt::System::current();
+    TORN_DOWN.with(|torn_down| torn_down.set(true));
+}
+
+async fn teardown_after_panic() {
+    assert!(!TORN_DOWN.with(Cell::get));
+    panic!("teardown ran");
+}
+
+#[actix_rt::test(setup = "setup")]
+async fn setup_output_passed_to_test(port: u16) {
+    assert_eq!(port, 8080);
+}
+
+#[actix_rt::test(setup = "setup", teardown = "teardown")]
+async fn setup_without_argument() {
+    assert!(!TORN_DOWN.with(Cell::get));
+}
+
+#[actix_rt::test(teardown = "teardown_after_panic")]
+#[should_panic(expected = "teardown ran")]
+async fn teardown_runs_after_panic() {
+    panic!("test failed");
+}
+
+#[actix_rt::test(teardown = "teardown_after_panic", timeout = "10ms")]
+#[should_panic(expected = "teardown ran")]
+async fn teardown_runs_after_timeout() {
+    actix_rt::time::sleep(std::time::Duration::from_secs(5)).await;
+}
+
+#[test]
+fn teardown_runs_after_test() {
+    // test functions generated by the macro can be called directly
+    setup_without_argument();
+    assert!(TORN_DOWN.with(Cell::get));
+}
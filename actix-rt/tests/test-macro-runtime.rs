@@ -0,0 +1,37 @@
+//! Checks runtime configuration arguments of the main and test macros.
+
+#![cfg(all(feature = "macros", not(feature = "io-uring")))]
+
+use std::thread;
+
+fn build_runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .thread_name("custom-runtime")
+        .build()
+        .unwrap()
+}
+
+#[actix_rt::test(flavor = "multi_thread", worker_threads = 2)]
+async fn multi_thread_flavor() {
+    let main_thread = thread::current().id();
+
+    // spawned tasks run on worker threads while local tasks stay on the system's thread
+    let worker_thread = tokio::spawn(async { thread::current().id() })
+        .await
+        .unwrap();
+    assert_ne!(worker_thread, main_thread);
+
+    let local_thread = actix_rt::spawn(async { thread::current().id() })
+        .await
+        .unwrap();
+    assert_eq!(local_thread, main_thread);
+}
+
+#[actix_rt::test(runtime = "build_runtime")]
+async fn custom_runtime() {
+    let thread = actix_rt::task::spawn_blocking(|| thread::current().name().map(str::to_owned))
+        .await
+        .unwrap();
+    assert_eq!(thread.as_deref(), Some("custom-runtime"));
+}
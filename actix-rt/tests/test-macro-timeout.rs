@@ -0,0 +1,16 @@
+//! Checks that the test macro's `timeout` argument fails tests that do not complete in time.
+
+#![cfg(feature = "macros")]
+
+use std::time::Duration;
+
+#[actix_rt::test(timeout = "5s")]
+async fn completes_within_timeout() {
+    actix_rt::time::sleep(Duration::from_millis(2)).await;
+}
+
+#[actix_rt::test(timeout = "10ms")]
+#[should_panic(expected = "test `exceeds_timeout` timed out after 10ms")]
+async fn exceeds_timeout() {
+    actix_rt::time::sleep(Duration::from_secs(5)).await;
+}
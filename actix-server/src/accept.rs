@@ -12,7 +12,7 @@ use crate::{
     ServerBuilder, ServerHandle,
 };
 
-const TIMEOUT_DURATION_ON_ERROR: Duration = Duration::from_millis(510);
+pub(crate) const TIMEOUT_DURATION_ON_ERROR: Duration = Duration::from_millis(510);
 
 struct ServerSocketInfo {
     token: usize,
@@ -59,7 +59,12 @@ impl Accept {
                     .collect::<Vec<_>>();
 
                 // start worker using service factories
-                ServerWorker::start(idx, factories, waker_queue.clone(), builder.worker_config)
+                ServerWorker::start(
+                    idx,
+                    factories,
+                    waker_queue.clone(),
+                    builder.worker_config.clone(),
+                )
             })
             .collect::<io::Result<Vec<_>>>()?
             .into_iter()
@@ -157,6 +162,11 @@ impl Accept {
     }
 
     fn handle_waker(&mut self, sockets: &mut [ServerSocketInfo]) -> bool {
+        // Workers notified they became available; take all of them at once.
+        if self.waker_queue.take_available(&mut self.avail) && !self.paused {
+            self.accept_all(sockets);
+        }
+
         // This is a loop because interests for command from previous version was
         // a loop that would try to drain the command channel. It's yet unknown
         // if it's necessary/good practice to actively drain the waker queue.
@@ -167,17 +177,6 @@ impl Accept {
 
             #[allow(clippy::significant_drop_in_scrutinee)]
             match guard.pop_front() {
-                // Worker notified it became available.
-                Some(WakerInterest::WorkerAvailable(idx)) => {
-                    drop(guard);
-
-                    self.avail.set_available(idx, true);
-
-                    if !self.paused {
-                        self.accept_all(sockets);
-                    }
-                }
-
                 // A new worker thread has been created so store its handle.
                 Some(WakerInterest::Worker(handle)) => {
                     drop(guard);
@@ -370,7 +369,7 @@ impl Accept {
             let next = self.next();
             let idx = next.idx();
 
-            if self.avail.get_available(idx) {
+            if self.avail.get_available(idx) && !next.is_overloaded() {
                 match self.send_connection(conn) {
                     Ok(_) => return,
                     Err(c) => conn = c,
@@ -395,7 +394,12 @@ impl Accept {
 
             match info.lst.accept() {
                 Ok(io) => {
-                    let conn = Conn { io, token };
+                    let conn = Conn {
+                        io,
+                        token,
+                        state: None,
+                        dispatched_at: None,
+                    };
                     self.accept_one(conn);
                 }
                 Err(ref err) if err.kind() == io::ErrorKind::WouldBlock => return,
@@ -455,7 +459,7 @@ impl Accept {
 /// All other errors will incur a timeout before next `accept()` call is attempted. The timeout is
 /// useful to handle resource exhaustion errors like `ENFILE` and `EMFILE`. Otherwise, it could
 /// enter into a temporary spin loop.
-fn connection_error(e: &io::Error) -> bool {
+pub(crate) fn connection_error(e: &io::Error) -> bool {
     e.kind() == io::ErrorKind::ConnectionRefused
         || e.kind() == io::ErrorKind::ConnectionAborted
         || e.kind() == io::ErrorKind::ConnectionReset
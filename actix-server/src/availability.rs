@@ -1,3 +1,5 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
 use crate::worker::WorkerHandleAccept;
 
 /// Array of u128 with every bit as marker for a worker handle's availability.
@@ -55,6 +57,59 @@ impl Availability {
     }
 }
 
+/// Worker availability shared by workers with `Accept`, for batching wake-ups of `Accept`.
+///
+/// Workers becoming available again set their bit and only wake up `Accept` if no other worker
+/// has done so since `Accept` last took the bits, so that a burst of workers becoming available
+/// costs a single wake-up instead of one each.
+///
+/// # Atomic Ordering:
+///
+/// Workers set their bit before the notified flag and `Accept` clears the flag before taking the
+/// bits. With sequentially consistent operations, a bit set after `Accept` took the bits is
+/// always followed by another wake-up.
+#[derive(Debug, Default)]
+pub(crate) struct SharedAvailability {
+    bits: [AtomicU64; 8],
+    notified: AtomicBool,
+}
+
+impl SharedAvailability {
+    /// Mark worker handle available by index and return true if `Accept` needs to be woken up.
+    pub(crate) fn set_available(&self, idx: usize) -> bool {
+        let (offset, idx) = Self::offset(idx);
+
+        self.bits[offset].fetch_or(1 << idx, Ordering::SeqCst);
+        !self.notified.swap(true, Ordering::SeqCst)
+    }
+
+    /// Move worker handles marked available into `avail` and return true if there were any.
+    pub(crate) fn take(&self, avail: &mut Availability) -> bool {
+        self.notified.store(false, Ordering::SeqCst);
+
+        let mut any = false;
+
+        for (offset, bits) in self.bits.iter().enumerate() {
+            // skip read-modify-write of words without available workers
+            if bits.load(Ordering::SeqCst) == 0 {
+                continue;
+            }
+
+            let bits = bits.swap(0, Ordering::SeqCst) as u128;
+            avail.0[offset / 2] |= bits << (offset % 2 * 64);
+            any = true;
+        }
+
+        any
+    }
+
+    /// Get offset and adjusted index of given worker handle index.
+    fn offset(idx: usize) -> (usize, usize) {
+        assert!(idx < 64 * 8, "Max WorkerHandle count is 512");
+        (idx / 64, idx % 64)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -118,4 +173,35 @@ mod tests {
 
         assert_eq!(aval.0[3], 1 << (438 - 384) | 1 << (479 - 384));
     }
+
+    #[test]
+    fn shared() {
+        let shared = SharedAvailability::default();
+        let mut aval = Availability::default();
+
+        assert!(!shared.take(&mut aval));
+
+        // only first worker becoming available wakes up `Accept`
+        assert!(shared.set_available(3));
+        assert!(!shared.set_available(64));
+        assert!(!shared.set_available(511));
+
+        assert!(shared.take(&mut aval));
+        assert!(aval.get_available(3));
+        assert!(aval.get_available(64));
+        assert!(aval.get_available(511));
+        assert!(!aval.get_available(4));
+
+        // bits are taken and next worker wakes up `Accept` again
+        assert!(!shared.take(&mut aval));
+        assert!(shared.set_available(200));
+        assert!(shared.take(&mut aval));
+        assert!(aval.get_available(200));
+    }
+
+    #[test]
+    #[should_panic]
+    fn shared_overflow() {
+        SharedAvailability::default().set_available(512);
+    }
 }
@@ -0,0 +1,439 @@
+//! Load generators for benchmarking servers in-process.
+//!
+//! [`BenchHarness`] drives a [`Workload`] from a number of concurrent client tasks against a
+//! server, such as one started with [`TestServer`](crate::TestServer), and summarizes the results
+//! in a [`BenchReport`]. Built-in workloads measure the accept rate ([`AcceptRate`]), echo
+//! throughput ([`EchoThroughput`]), and handshake rate of, e.g., TLS acceptors
+//! ([`HandshakeRate`]). Custom workloads can be added by implementing [`Workload`].
+//!
+//! Load generators run on the current Actix runtime, so servers under test should run on other
+//! threads, as `TestServer` does, to avoid measuring the two competing with each other.
+//!
+//! # Examples
+//! ```
+//! use std::time::Duration;
+//!
+//! use actix_server::{
+//!     bench::{self, BenchHarness, EchoThroughput},
+//!     TestServer,
+//! };
+//!
+//! # actix_rt::System::new().block_on(async {
+//! let srv = TestServer::start(bench::echo);
+//!
+//! let report = BenchHarness::new()
+//!     .concurrency(4)
+//!     .duration(Duration::from_millis(100))
+//!     .run(srv.addr(), EchoThroughput::new(1024))
+//!     .await;
+//!
+//! assert_eq!(report.errors(), 0);
+//! println!("{report}");
+//! # });
+//! ```
+
+use std::{
+    cell::{Cell, RefCell},
+    fmt,
+    future::Future,
+    io,
+    net::SocketAddr,
+    rc::Rc,
+    time::Duration,
+};
+
+use actix_rt::{net::TcpStream, time::Instant};
+use actix_service::{fn_service, ServiceFactory};
+use futures_core::future::LocalBoxFuture;
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+/// Returns service factory echoing all data received on a connection back to the client.
+///
+/// Suitable as the server under test for [`AcceptRate`] and [`EchoThroughput`] workloads.
+pub fn echo(
+) -> impl ServiceFactory<TcpStream, Config = (), Response = (), Error = io::Error, InitError = ()> + Clone
+{
+    fn_service(|mut stream: TcpStream| async move {
+        let (mut rd, mut wr) = stream.split();
+        tokio::io::copy(&mut rd, &mut wr).await?;
+        Ok(())
+    })
+}
+
+/// Client side of a benchmark.
+pub trait Workload {
+    /// Performs one iteration against the server at `addr`, returning the number of payload
+    /// bytes transferred.
+    fn iteration(&self, addr: SocketAddr) -> LocalBoxFuture<'_, io::Result<u64>>;
+}
+
+/// Workload measuring how quickly connections are accepted and served.
+///
+/// Each iteration opens a new connection and waits for a single byte to be echoed back, so the
+/// server under test must echo data, e.g., using [`echo`].
+#[derive(Debug, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct AcceptRate;
+
+impl AcceptRate {
+    /// Constructs accept rate workload.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Workload for AcceptRate {
+    fn iteration(&self, addr: SocketAddr) -> LocalBoxFuture<'_, io::Result<u64>> {
+        Box::pin(async move {
+            let mut stream = TcpStream::connect(addr).await?;
+
+            stream.write_all(&[0]).await?;
+            stream.read_exact(&mut [0]).await?;
+
+            Ok(0)
+        })
+    }
+}
+
+/// Workload measuring echo throughput.
+///
+/// Each iteration opens a new connection and sends a payload for the server to echo back a number
+/// of times, reporting the number of bytes echoed. The server under test must echo data, e.g.,
+/// using [`echo`].
+#[derive(Debug, Clone)]
+pub struct EchoThroughput {
+    payload: Vec<u8>,
+    round_trips: usize,
+}
+
+impl EchoThroughput {
+    /// Constructs echo workload sending payloads of `payload_size` bytes.
+    ///
+    /// Performs 100 round trips per connection by default.
+    pub fn new(payload_size: usize) -> Self {
+        Self {
+            payload: vec![0x5a; payload_size],
+            round_trips: 100,
+        }
+    }
+
+    /// Sets number of round trips performed per connection.
+    ///
+    /// Larger values reduce the share of connection setup in measurements.
+    pub fn round_trips(mut self, round_trips: usize) -> Self {
+        self.round_trips = round_trips;
+        self
+    }
+}
+
+impl Workload for EchoThroughput {
+    fn iteration(&self, addr: SocketAddr) -> LocalBoxFuture<'_, io::Result<u64>> {
+        Box::pin(async move {
+            let mut stream = TcpStream::connect(addr).await?;
+            stream.set_nodelay(true)?;
+
+            let mut buf = vec![0; self.payload.len()];
+
+            for _ in 0..self.round_trips {
+                stream.write_all(&self.payload).await?;
+                stream.read_exact(&mut buf).await?;
+            }
+
+            Ok((self.payload.len() * self.round_trips) as u64)
+        })
+    }
+}
+
+/// Workload measuring handshake rate.
+///
+/// Each iteration opens a new connection and performs a handshake on it using the given function,
+/// e.g., a TLS connector's connect method. The connection is dropped once the handshake completes.
+///
+/// # Examples
+/// ```
+/// use actix_rt::net::TcpStream;
+/// use actix_server::bench::HandshakeRate;
+///
+/// // a TLS benchmark would wrap the stream in a TLS connector here
+/// let workload = HandshakeRate::new(|stream: TcpStream| async move {
+///     stream.set_nodelay(true)?;
+///     Ok(stream)
+/// });
+/// # drop(workload);
+/// ```
+pub struct HandshakeRate<F> {
+    handshake: F,
+}
+
+impl<F, Fut, S> HandshakeRate<F>
+where
+    F: Fn(TcpStream) -> Fut,
+    Fut: Future<Output = io::Result<S>>,
+{
+    /// Constructs handshake workload performing handshakes using `handshake`.
+    pub fn new(handshake: F) -> Self {
+        Self { handshake }
+    }
+}
+
+impl<F, Fut, S> Workload for HandshakeRate<F>
+where
+    F: Fn(TcpStream) -> Fut,
+    Fut: Future<Output = io::Result<S>>,
+{
+    fn iteration(&self, addr: SocketAddr) -> LocalBoxFuture<'_, io::Result<u64>> {
+        Box::pin(async move {
+            let stream = TcpStream::connect(addr).await?;
+            (self.handshake)(stream).await?;
+            Ok(0)
+        })
+    }
+}
+
+impl<F> fmt::Debug for HandshakeRate<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HandshakeRate").finish_non_exhaustive()
+    }
+}
+
+/// Runner of benchmark workloads.
+///
+/// By default, runs 16 concurrent clients for 5 seconds without warm-up.
+#[derive(Debug, Clone)]
+pub struct BenchHarness {
+    concurrency: usize,
+    duration: Duration,
+    warmup: Duration,
+    iterations: Option<u64>,
+}
+
+impl BenchHarness {
+    /// Constructs harness with default settings.
+    pub fn new() -> Self {
+        Self {
+            concurrency: 16,
+            duration: Duration::from_secs(5),
+            warmup: Duration::ZERO,
+            iterations: None,
+        }
+    }
+
+    /// Sets number of concurrent client tasks.
+    ///
+    /// # Panics
+    /// Panics if `concurrency` is zero.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        assert!(concurrency > 0, "concurrency must be non-zero");
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Sets how long to measure for, after warm-up.
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Sets how long to run the workload before measuring, e.g., to fill caches and pools.
+    pub fn warmup(mut self, warmup: Duration) -> Self {
+        self.warmup = warmup;
+        self
+    }
+
+    /// Sets number of measured iterations after which to stop, even if the duration has not
+    /// elapsed yet.
+    pub fn iterations(mut self, iterations: u64) -> Self {
+        self.iterations = Some(iterations);
+        self
+    }
+
+    /// Runs `workload` against the server at `addr`.
+    ///
+    /// # Panics
+    /// Panics if called outside of an Actix runtime.
+    pub async fn run<W: Workload + 'static>(&self, addr: SocketAddr, workload: W) -> BenchReport {
+        let start = Instant::now();
+        let measure_from = start + self.warmup;
+
+        let ctl = Rc::new(Control {
+            workload,
+            deadline: measure_from + self.duration,
+            measure_from,
+            remaining: Cell::new(self.iterations),
+            stats: RefCell::new(Stats::default()),
+        });
+
+        let clients = (0..self.concurrency)
+            .map(|_| actix_rt::spawn(Rc::clone(&ctl).client(addr)))
+            .collect::<Vec<_>>();
+
+        for client in clients {
+            // clients do not panic unless the workload does
+            client.await.unwrap();
+        }
+
+        let elapsed = Instant::now().saturating_duration_since(measure_from);
+        let stats = ctl.stats.take();
+
+        BenchReport::new(stats, elapsed)
+    }
+}
+
+impl Default for BenchHarness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct Control<W> {
+    workload: W,
+    deadline: Instant,
+    measure_from: Instant,
+
+    /// Measured iterations left to start, if limited.
+    remaining: Cell<Option<u64>>,
+
+    stats: RefCell<Stats>,
+}
+
+impl<W: Workload> Control<W> {
+    /// Returns true if another iteration should be started at `now`.
+    fn start_iteration(&self, now: Instant) -> bool {
+        if now >= self.deadline {
+            return false;
+        }
+
+        if now < self.measure_from {
+            return true;
+        }
+
+        match self.remaining.get() {
+            None => true,
+            Some(0) => false,
+            Some(n) => {
+                self.remaining.set(Some(n - 1));
+                true
+            }
+        }
+    }
+
+    async fn client(self: Rc<Self>, addr: SocketAddr) {
+        loop {
+            let start = Instant::now();
+
+            if !self.start_iteration(start) {
+                break;
+            }
+
+            let res = self.workload.iteration(addr).await;
+
+            if start < self.measure_from {
+                continue;
+            }
+
+            let mut stats = self.stats.borrow_mut();
+
+            match res {
+                Ok(bytes) => {
+                    stats.bytes += bytes;
+                    stats.latencies.push(start.elapsed());
+                }
+                Err(_) => stats.errors += 1,
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Stats {
+    bytes: u64,
+    errors: u64,
+    latencies: Vec<Duration>,
+}
+
+/// Results of a benchmark run.
+#[derive(Debug, Clone)]
+pub struct BenchReport {
+    iterations: u64,
+    errors: u64,
+    bytes: u64,
+    elapsed: Duration,
+
+    /// Latencies of successful iterations, sorted.
+    latencies: Vec<Duration>,
+}
+
+impl BenchReport {
+    fn new(mut stats: Stats, elapsed: Duration) -> Self {
+        stats.latencies.sort_unstable();
+
+        Self {
+            iterations: stats.latencies.len() as u64,
+            errors: stats.errors,
+            bytes: stats.bytes,
+            elapsed,
+            latencies: stats.latencies,
+        }
+    }
+
+    /// Returns number of successful iterations.
+    pub fn iterations(&self) -> u64 {
+        self.iterations
+    }
+
+    /// Returns number of failed iterations.
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    /// Returns number of payload bytes transferred by successful iterations.
+    pub fn bytes(&self) -> u64 {
+        self.bytes
+    }
+
+    /// Returns duration of the measurement.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns successful iterations per second.
+    pub fn rate(&self) -> f64 {
+        self.iterations as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Returns payload bytes transferred per second.
+    pub fn throughput(&self) -> f64 {
+        self.bytes as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// Returns latency of successful iterations at `percentile`, between 0 and 100.
+    ///
+    /// Returns `None` if no iteration succeeded.
+    pub fn latency(&self, percentile: f64) -> Option<Duration> {
+        let last = self.latencies.len().checked_sub(1)?;
+        let idx = (percentile.clamp(0.0, 100.0) / 100.0 * last as f64).round() as usize;
+        Some(self.latencies[idx])
+    }
+}
+
+impl fmt::Display for BenchReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} iterations ({} errors) in {:.2?}: {:.1} iter/s, {:.1} KiB/s",
+            self.iterations,
+            self.errors,
+            self.elapsed,
+            self.rate(),
+            self.throughput() / 1024.0,
+        )?;
+
+        if let (Some(p50), Some(p99), Some(max)) =
+            (self.latency(50.0), self.latency(99.0), self.latency(100.0))
+        {
+            write!(f, ", latency p50 {p50:.2?} p99 {p99:.2?} max {max:.2?}")?;
+        }
+
+        Ok(())
+    }
+}
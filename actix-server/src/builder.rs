@@ -1,13 +1,20 @@
-use std::{io, time::Duration};
+use std::{collections::HashMap, future::Future, io, sync::Arc, time::Duration};
 
 use actix_rt::net::TcpStream;
+use actix_utils::clock::Clock;
+use socket2::Socket;
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
 use tracing::{info, trace};
 
 use crate::{
+    close::CloseReasonCounter,
+    preprocess::AcceptedSocket,
     server::ServerCommand,
     service::{InternalServiceFactory, ServerServiceFactory, StreamNewService},
-    socket::{create_mio_tcp_listener, MioListener, MioTcpListener, StdTcpListener, ToSocketAddrs},
+    socket::{
+        create_mio_tcp_listener, MioListener, MioTcpListener, PreBind, StdSocketAddr,
+        StdTcpListener, ToSocketAddrs,
+    },
     worker::ServerWorkerConfig,
     Server,
 };
@@ -35,7 +42,10 @@ pub struct ServerBuilder {
     pub(crate) backlog: u32,
     pub(crate) factories: Vec<Box<dyn InternalServiceFactory>>,
     pub(crate) sockets: Vec<(usize, String, MioListener)>,
+    /// Backlogs of sockets bound by the builder, keyed by token.
+    pub(crate) backlogs: HashMap<usize, u32>,
     pub(crate) mptcp: MpTcp,
+    pub(crate) pre_bind: Vec<PreBind>,
     pub(crate) exit: bool,
     pub(crate) listen_os_signals: bool,
     pub(crate) cmd_tx: UnboundedSender<ServerCommand>,
@@ -59,8 +69,10 @@ impl ServerBuilder {
             token: 0,
             factories: Vec::new(),
             sockets: Vec::new(),
+            backlogs: HashMap::new(),
             backlog: 2048,
             mptcp: MpTcp::Disabled,
+            pre_bind: Vec::new(),
             exit: false,
             listen_os_signals: true,
             cmd_tx,
@@ -114,6 +126,37 @@ impl ServerBuilder {
         self
     }
 
+    /// Adds a hook called with each TCP socket created by [`bind`](Self::bind) before it is bound.
+    ///
+    /// Allows setting socket options the builder does not cover, many of them platform-specific,
+    /// such as `IP_TRANSPARENT`, `SO_MARK`, or `IP_FREEBIND`. The hook is also passed the address
+    /// the socket is about to be bound to. Hooks run in the order they were added, after the
+    /// builder has set its own options. Returning an error fails binding to that address.
+    ///
+    /// Listeners passed to the `listen` methods and Unix domain sockets are not affected.
+    ///
+    /// This method should be called before `bind()` method call.
+    ///
+    /// # Examples
+    /// ```
+    /// # use actix_server::Server;
+    /// let builder = Server::build().pre_bind(|socket, addr| {
+    ///     if addr.is_ipv6() {
+    ///         socket.set_only_v6(true)?;
+    ///     }
+    ///
+    ///     socket.set_recv_buffer_size(1 << 20)
+    /// });
+    /// # drop(builder);
+    /// ```
+    pub fn pre_bind<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(&Socket, StdSocketAddr) -> io::Result<()> + Send + 'static,
+    {
+        self.pre_bind.push(Box::new(hook));
+        self
+    }
+
     /// Sets MultiPath TCP (MPTCP) preference on bound sockets.
     ///
     /// Multipath TCP (MPTCP) builds on top of TCP to improve connection redundancy and performance
@@ -173,6 +216,187 @@ impl ServerBuilder {
         self
     }
 
+    /// Closes connections that have not recorded activity for longer than `dur`.
+    ///
+    /// Protects workers from connections that are kept open without making progress, such as
+    /// slow-loris style attacks. Services record activity through [`ConnectionActivity`]; see
+    /// its docs for details.
+    ///
+    /// By default, idle connections are not closed.
+    ///
+    /// # Panics
+    /// Panics if `dur` is zero.
+    ///
+    /// [`ConnectionActivity`]: crate::ConnectionActivity
+    pub fn connection_idle_timeout(mut self, dur: Duration) -> Self {
+        assert!(!dur.is_zero(), "idle timeout must be non-zero");
+        self.worker_config.idle_timeout(dur);
+        self
+    }
+
+    /// Closes connections that waited longer than `max_age` between being accepted and reaching
+    /// their worker, without calling their service.
+    ///
+    /// Connections queue up for workers that are busy or overloaded, and clients have often given
+    /// up on the oldest ones by the time they would be served. Shedding them lets overloaded
+    /// workers catch up with connections that are still wanted, instead of wasting work. Ages are
+    /// measured with the server's [clock](Self::clock).
+    ///
+    /// By default, connections are served however long they waited.
+    ///
+    /// # Panics
+    /// Panics if `max_age` is zero.
+    pub fn shed_stale_connections(mut self, max_age: Duration) -> Self {
+        assert!(!max_age.is_zero(), "max connection age must be non-zero");
+        self.worker_config.max_dispatch_age(max_age);
+        self
+    }
+
+    /// Sets the clock used to measure how long connections have been idle or waited for workers.
+    ///
+    /// Allows tests to drive [idle timeouts](Self::connection_idle_timeout) with a mock clock, such
+    /// as [`MockClock`], instead of waiting for real time to pass or pausing the runtime's timer.
+    ///
+    /// By default, the Actix (Tokio) runtime's timer is used.
+    ///
+    /// [`MockClock`]: actix_utils::clock::MockClock
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.worker_config.clock(Arc::new(clock));
+        self
+    }
+
+    /// Adds a preprocessor that runs on each accepted socket before it is passed to its service.
+    ///
+    /// Preprocessors run on the worker in the order they were added and are suited to cheap,
+    /// transport-level policies such as setting socket options, attaching [`ConnectionTags`] for
+    /// services to read, or rejecting connections by peer address. Returning an error closes the
+    /// connection without calling the service or any later preprocessors.
+    ///
+    /// Preprocessors must not block since they run on the worker thread.
+    ///
+    /// # Examples
+    /// ```
+    /// # use actix_server::Server;
+    /// let builder = Server::build().preprocess(|sock| {
+    ///     sock.socket().set_nodelay(true)?;
+    ///     sock.tags().insert("tagged");
+    ///     Ok(())
+    /// });
+    /// ```
+    ///
+    /// [`ConnectionTags`]: crate::ConnectionTags
+    pub fn preprocess<F>(mut self, preprocessor: F) -> Self
+    where
+        F: Fn(&mut AcceptedSocket<'_>) -> io::Result<()> + Send + Sync + 'static,
+    {
+        self.worker_config.preprocess(Arc::new(preprocessor));
+        self
+    }
+
+    /// Enables handing connections off between workers using [`Handoff`].
+    ///
+    /// Allows services that own their connections to rebalance load by moving connections from a
+    /// hot worker to another one; see [`Handoff`] docs for details.
+    ///
+    /// By default, connection hand-off is disabled.
+    ///
+    /// [`Handoff`]: crate::Handoff
+    pub fn connection_handoff(mut self) -> Self {
+        self.worker_config.connection_handoff();
+        self
+    }
+
+    /// Tracks active connections in a registry that can be queried using
+    /// [`ServerHandle::connections`].
+    ///
+    /// Each connection is registered with its listener, peer address, worker, and age once its
+    /// worker receives it, and removed once its service future completes. Byte counts are reported
+    /// for connections whose services wrap their streams in a [`TrackedStream`].
+    ///
+    /// Adds a lock on a registry shared by all workers to the path of each connection, so it is
+    /// disabled by default.
+    ///
+    /// [`ServerHandle::connections`]: crate::ServerHandle::connections
+    /// [`TrackedStream`]: crate::TrackedStream
+    pub fn track_connections(mut self) -> Self {
+        self.worker_config.track_connections();
+        self
+    }
+
+    /// Counts the reasons connections are closed in `counter`.
+    ///
+    /// When a connection's service future completes or is dropped, its [`CloseReason`] is
+    /// determined and added to `counter`, which stays readable by the caller, so operators can
+    /// break down connection churn by cause. The reason is also recorded as the `close_reason`
+    /// field of the connection's span, and each close is logged as a `debug` event within it.
+    ///
+    /// Reasons are detected from the service's result or the server closing the connection.
+    /// Services can report reasons the server cannot detect, such as TLS handshake failures, using
+    /// [`CloseReason::report`]. [`NetApp`](crate::NetApp) reports failed PROXY protocol and TLS
+    /// handshakes this way.
+    ///
+    /// By default, close reasons are not counted.
+    ///
+    /// # Examples
+    /// ```
+    /// use actix_rt::net::TcpStream;
+    /// use actix_server::{CloseReason, CloseReasonCounter, Server};
+    /// use actix_service::fn_service;
+    ///
+    /// # fn build() -> std::io::Result<Server> {
+    /// let counter = CloseReasonCounter::new();
+    ///
+    /// let srv = Server::build()
+    ///     .count_close_reasons(counter.clone())
+    ///     .bind("app", ("127.0.0.1", 8080), || {
+    ///         fn_service(|_stream: TcpStream| async { Ok::<_, std::io::Error>(()) })
+    ///     })?
+    ///     .run();
+    ///
+    /// // later, e.g. when exporting metrics
+    /// for reason in CloseReason::ALL {
+    ///     println!("{reason}: {}", counter.count(reason));
+    /// }
+    /// # Ok(srv)
+    /// # }
+    /// ```
+    ///
+    /// [`CloseReason`]: crate::CloseReason
+    /// [`CloseReason::report`]: crate::CloseReason::report
+    pub fn count_close_reasons(mut self, counter: CloseReasonCounter) -> Self {
+        self.worker_config.count_close_reasons(counter);
+        self
+    }
+
+    /// Runs `warmup` on each worker after its services have been created and before it is handed
+    /// any connections.
+    ///
+    /// Lets workers prime caches, open upstream connections, and so on, so the first connections
+    /// after (re)starting do not pay for it. The hook is called with the worker index and also
+    /// runs for workers restarted after a fault. Returning an error fails starting the worker, as
+    /// do errors creating its services.
+    ///
+    /// Workers are started one after another and the server does not accept connections until all
+    /// of them have started, so warm-up time adds up across workers.
+    ///
+    /// # Examples
+    /// ```
+    /// # use actix_server::Server;
+    /// let builder = Server::build().worker_warmup(|idx| async move {
+    ///     println!("warming up worker {}", idx);
+    ///     Ok(())
+    /// });
+    /// ```
+    pub fn worker_warmup<F, Fut>(mut self, warmup: F) -> Self
+    where
+        F: Fn(usize) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = io::Result<()>> + 'static,
+    {
+        self.worker_config
+            .warmup(Arc::new(move |idx| Box::pin(warmup(idx))));
+        self
+    }
+
     /// Add new service to the server.
     pub fn bind<F, U, N>(mut self, name: N, addr: U, factory: F) -> io::Result<Self>
     where
@@ -180,20 +404,47 @@ impl ServerBuilder {
         U: ToSocketAddrs,
         N: AsRef<str>,
     {
-        let sockets = bind_addr(addr, self.backlog, &self.mptcp)?;
+        let sockets = bind_addr(addr, self.backlog, &self.mptcp, &self.pre_bind)?;
 
         trace!("binding server to: {:?}", &sockets);
 
         for lst in sockets {
-            let token = self.next_token();
-            self.factories.push(StreamNewService::create(
-                name.as_ref().to_string(),
-                token,
-                factory.clone(),
-                lst.local_addr()?,
-            ));
-            self.sockets
-                .push((token, name.as_ref().to_string(), MioListener::Tcp(lst)));
+            self.add_tcp_listener(name.as_ref(), lst, factory.clone())?;
+        }
+
+        Ok(self)
+    }
+
+    /// Add new service to the server, accepting connections to non-local addresses.
+    ///
+    /// Listening sockets are bound with `IP_TRANSPARENT` (or `IPV6_TRANSPARENT`) set, so they can
+    /// be used as targets of iptables `TPROXY` rules and accept connections to any address routed
+    /// to the local host. The destination each client originally connected to is available as
+    /// [`OriginalDst::current`](crate::OriginalDst::current) within the service, also for
+    /// connections redirected with NAT rules.
+    ///
+    /// Setting the transparent option requires the `CAP_NET_ADMIN` capability; binding fails with
+    /// [`PermissionDenied`](io::ErrorKind::PermissionDenied) without it.
+    ///
+    /// Only available on Linux.
+    #[cfg(target_os = "linux")]
+    pub fn bind_transparent<F, U, N>(mut self, name: N, addr: U, factory: F) -> io::Result<Self>
+    where
+        F: ServerServiceFactory<TcpStream>,
+        U: ToSocketAddrs,
+        N: AsRef<str>,
+    {
+        self.pre_bind
+            .insert(0, Box::new(crate::transparent::set_transparent));
+        let res = bind_addr(addr, self.backlog, &self.mptcp, &self.pre_bind);
+        let _ = self.pre_bind.remove(0);
+        let sockets = res?;
+
+        trace!("binding transparent server to: {:?}", &sockets);
+
+        for lst in sockets {
+            let token = self.add_tcp_listener(name.as_ref(), lst, factory.clone())?;
+            self.worker_config.transparent(token);
         }
 
         Ok(self)
@@ -236,11 +487,53 @@ impl ServerBuilder {
         }
     }
 
+    /// Starts processing incoming connections on the current thread and returns an embedded
+    /// server future.
+    ///
+    /// Unlike [`run`](Self::run), no accept or worker threads are spawned and OS signals are not
+    /// listened for; see [`EmbeddedServer`] for details.
+    ///
+    /// # Panics
+    /// Panics if no listeners were bound.
+    ///
+    /// [`EmbeddedServer`]: crate::EmbeddedServer
+    #[cfg(feature = "embedded")]
+    pub fn run_embedded(self) -> crate::EmbeddedServer {
+        if self.sockets.is_empty() {
+            panic!("Server should have at least one bound socket");
+        } else {
+            info!("starting embedded server");
+            crate::EmbeddedServer::new(self)
+        }
+    }
+
     fn next_token(&mut self) -> usize {
         let token = self.token;
         self.token += 1;
         token
     }
+
+    fn add_tcp_listener<F>(
+        &mut self,
+        name: &str,
+        lst: MioTcpListener,
+        factory: F,
+    ) -> io::Result<usize>
+    where
+        F: ServerServiceFactory<TcpStream>,
+    {
+        let token = self.next_token();
+        self.factories.push(StreamNewService::create(
+            name.to_string(),
+            token,
+            factory,
+            lst.local_addr()?,
+        ));
+        self.backlogs.insert(token, self.backlog);
+        self.sockets
+            .push((token, name.to_string(), MioListener::Tcp(lst)));
+        Ok(token)
+    }
 }
 
 #[cfg(unix)]
@@ -297,13 +590,14 @@ pub(super) fn bind_addr<S: ToSocketAddrs>(
     addr: S,
     backlog: u32,
     mptcp: &MpTcp,
+    pre_bind: &[PreBind],
 ) -> io::Result<Vec<MioTcpListener>> {
     let mut opt_err = None;
     let mut success = false;
     let mut sockets = Vec::new();
 
     for addr in addr.to_socket_addrs()? {
-        match create_mio_tcp_listener(addr, backlog, mptcp) {
+        match create_mio_tcp_listener(addr, backlog, mptcp, pre_bind) {
             Ok(lst) => {
                 success = true;
                 sockets.push(lst);
@@ -0,0 +1,280 @@
+//! Reporting why connections were closed.
+//!
+//! See [`ServerBuilder::count_close_reasons`](crate::ServerBuilder::count_close_reasons) for main
+//! docs.
+
+use std::{
+    any::Any,
+    cell::Cell,
+    fmt, io,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+};
+
+use tracing::{debug, Span};
+
+use crate::{connection, ConnectionActivity};
+
+/// Reason a connection was closed.
+///
+/// Determined when the future handling a connection completes or is dropped: from the result of
+/// the service, or from the server closing the connection. Services can also report a reason that
+/// the server cannot detect, such as a failed TLS handshake, using [`report`](Self::report).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum CloseReason {
+    /// Service completed successfully.
+    Completed,
+
+    /// Peer reset or aborted the connection.
+    ///
+    /// Detected from services responding with an [`io::Error`] of kind `ConnectionReset`,
+    /// `ConnectionAborted`, or `BrokenPipe`.
+    PeerReset,
+
+    /// Server closed the connection after it was idle for longer than the
+    /// [idle timeout](crate::ServerBuilder::connection_idle_timeout).
+    IdleTimeout,
+
+    /// Server closed the connection when shutting down, before its service completed.
+    Drain,
+
+    /// TLS or other protocol handshake failed.
+    ///
+    /// Reported by services, e.g., from the error handler of a TLS acceptor. [`NetApp`] reports it
+    /// when reading the PROXY protocol header or the TLS handshake fails.
+    ///
+    /// [`NetApp`]: crate::NetApp
+    HandshakeFailure,
+
+    /// Service responded with any other error.
+    ServiceError,
+}
+
+impl CloseReason {
+    /// All close reasons, e.g., for exporting the counts of a [`CloseReasonCounter`].
+    pub const ALL: [Self; 6] = [
+        Self::Completed,
+        Self::PeerReset,
+        Self::IdleTimeout,
+        Self::Drain,
+        Self::HandshakeFailure,
+        Self::ServiceError,
+    ];
+
+    /// Returns the name of the reason in snake case, e.g., for use as a metric label.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Completed => "completed",
+            Self::PeerReset => "peer_reset",
+            Self::IdleTimeout => "idle_timeout",
+            Self::Drain => "drain",
+            Self::HandshakeFailure => "handshake_failure",
+            Self::ServiceError => "service_error",
+        }
+    }
+
+    /// Reports this as the reason the connection being handled is closed.
+    ///
+    /// Takes precedence over the reason detected by the server once the connection closes. Only
+    /// the first reported reason is kept.
+    ///
+    /// Returns false if close reasons are not counted or if not called while handling a
+    /// connection, i.e., while calling the service with an accepted stream or while its future
+    /// runs.
+    pub fn report(self) -> bool {
+        match connection::current().and_then(|cx| cx.close) {
+            Some(state) => {
+                state.report(self);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Returns reason for a service responding with `err`.
+    fn from_error<E: 'static>(err: &E) -> Self {
+        let kind = (err as &dyn Any)
+            .downcast_ref::<io::Error>()
+            .map(io::Error::kind);
+
+        match kind {
+            Some(
+                io::ErrorKind::ConnectionReset
+                | io::ErrorKind::ConnectionAborted
+                | io::ErrorKind::BrokenPipe,
+            ) => Self::PeerReset,
+            _ => Self::ServiceError,
+        }
+    }
+}
+
+impl fmt::Display for CloseReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Counts of connections closed for each [`CloseReason`].
+///
+/// Passed to [`ServerBuilder::count_close_reasons`](crate::ServerBuilder::count_close_reasons).
+/// Cheap to clone and can be shared between servers; clones share the same counts, which keep
+/// being readable after the server has stopped.
+#[derive(Clone, Default)]
+pub struct CloseReasonCounter {
+    counts: Arc<[AtomicU64; CloseReason::ALL.len()]>,
+}
+
+impl CloseReasonCounter {
+    /// Constructs new counter with all counts at zero.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns number of connections closed for `reason`.
+    pub fn count(&self, reason: CloseReason) -> u64 {
+        self.counts[reason as usize].load(Ordering::Relaxed)
+    }
+
+    /// Returns number of connections closed for any reason.
+    pub fn total(&self) -> u64 {
+        CloseReason::ALL
+            .iter()
+            .map(|reason| self.count(*reason))
+            .sum()
+    }
+
+    fn record(&self, reason: CloseReason) {
+        self.counts[reason as usize].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl fmt::Debug for CloseReasonCounter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map()
+            .entries(
+                CloseReason::ALL
+                    .iter()
+                    .map(|reason| (reason.as_str(), self.count(*reason))),
+            )
+            .finish()
+    }
+}
+
+/// Close reason reported for a connection, shared through its context.
+pub(crate) struct CloseState {
+    counter: CloseReasonCounter,
+    reason: Cell<Option<CloseReason>>,
+}
+
+impl CloseState {
+    /// Returns new close state for a connection whose reason is recorded in `counter`.
+    pub(crate) fn new(counter: &CloseReasonCounter) -> Rc<Self> {
+        Rc::new(Self {
+            counter: counter.clone(),
+            reason: Cell::new(None),
+        })
+    }
+
+    fn report(&self, reason: CloseReason) {
+        if self.reason.get().is_none() {
+            self.reason.set(Some(reason));
+        }
+    }
+}
+
+/// Counts close reason of the connection being handled once dropped along with its future.
+pub(crate) struct CloseGuard {
+    state: Rc<CloseState>,
+    activity: Option<ConnectionActivity>,
+    span: Span,
+}
+
+impl CloseGuard {
+    /// Returns guard for the connection being handled, if close reasons are counted.
+    ///
+    /// The reason is recorded as the `close_reason` field of `span`, the connection's span.
+    pub(crate) fn current(span: &Span) -> Option<Self> {
+        let cx = connection::current()?;
+
+        Some(Self {
+            state: cx.close?,
+            activity: cx.activity,
+            span: span.clone(),
+        })
+    }
+
+    /// Records result of the connection's service, unless a reason was reported already.
+    pub(crate) fn finished<T, E: 'static>(&self, res: &Result<T, E>) {
+        self.state.report(match res {
+            Ok(_) => CloseReason::Completed,
+            Err(err) => CloseReason::from_error(err),
+        });
+    }
+}
+
+impl Drop for CloseGuard {
+    fn drop(&mut self) {
+        // futures dropped before completing were closed by the server
+        let reason = self
+            .state
+            .reason
+            .get()
+            .unwrap_or_else(|| match &self.activity {
+                Some(activity) if activity.is_timed_out() => CloseReason::IdleTimeout,
+                _ => CloseReason::Drain,
+            });
+
+        self.span.record("close_reason", reason.as_str());
+        self.span.in_scope(|| debug!(%reason, "connection closed"));
+        self.state.counter.record(reason);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reasons_from_errors() {
+        let reset = io::Error::from(io::ErrorKind::ConnectionReset);
+        assert_eq!(CloseReason::from_error(&reset), CloseReason::PeerReset);
+
+        let pipe = io::Error::from(io::ErrorKind::BrokenPipe);
+        assert_eq!(CloseReason::from_error(&pipe), CloseReason::PeerReset);
+
+        let other = io::Error::from(io::ErrorKind::InvalidData);
+        assert_eq!(CloseReason::from_error(&other), CloseReason::ServiceError);
+
+        assert_eq!(CloseReason::from_error(&()), CloseReason::ServiceError);
+    }
+
+    #[test]
+    fn counts_reasons() {
+        let counter = CloseReasonCounter::new();
+        let state = CloseState::new(&counter);
+
+        let guard = CloseGuard {
+            state: Rc::clone(&state),
+            activity: None,
+            span: Span::none(),
+        };
+        state.report(CloseReason::HandshakeFailure);
+        guard.finished(&Ok::<_, ()>(()));
+        drop(guard);
+
+        drop(CloseGuard {
+            state: CloseState::new(&counter),
+            activity: None,
+            span: Span::none(),
+        });
+
+        assert_eq!(counter.count(CloseReason::HandshakeFailure), 1);
+        assert_eq!(counter.count(CloseReason::Drain), 1);
+        assert_eq!(counter.count(CloseReason::Completed), 0);
+        assert_eq!(counter.total(), 2);
+    }
+}
@@ -0,0 +1,119 @@
+//! Per-connection context available to services while handling accepted streams.
+
+use std::{future::Future, rc::Rc, sync::Arc, time::Duration};
+
+use actix_rt::task::JoinHandle;
+use actix_utils::clock::Clock;
+
+use crate::{
+    close::{CloseReasonCounter, CloseState},
+    idle::{self, Reaper},
+    preprocess::ConnectionTags,
+    ConnectionActivity,
+};
+
+tokio::task_local! {
+    static CONTEXT: ConnectionContext;
+}
+
+#[derive(Clone)]
+pub(crate) struct ConnectionContext {
+    pub(crate) activity: Option<ConnectionActivity>,
+    pub(crate) tags: Rc<ConnectionTags>,
+    pub(crate) close: Option<Rc<CloseState>>,
+}
+
+/// Returns the context of the connection being handled, if any.
+pub(crate) fn current() -> Option<ConnectionContext> {
+    CONTEXT.try_with(Clone::clone).ok()
+}
+
+/// Per-server state that connections report to, held by each worker.
+///
+/// Kept with the worker rather than in thread-locals, since workers of embedded servers share the
+/// thread they are started on.
+#[derive(Clone)]
+pub(crate) struct ConnectionScope {
+    reaper: Option<Rc<Reaper>>,
+    close_reasons: Option<CloseReasonCounter>,
+}
+
+impl ConnectionScope {
+    /// Starts closing idle connections on the current thread, if an idle timeout is configured.
+    ///
+    /// Returns the scope along with the handle of the task closing idle connections, which runs
+    /// until aborted or until the runtime of the current thread stops.
+    pub(crate) fn start(
+        idle_timeout: Option<Duration>,
+        clock: Arc<dyn Clock>,
+        close_reasons: Option<CloseReasonCounter>,
+    ) -> (Self, Option<JoinHandle<()>>) {
+        let (reaper, handle) = match idle::start(idle_timeout, clock) {
+            Some((reaper, handle)) => (Some(reaper), Some(handle)),
+            None => (None, None),
+        };
+
+        let scope = Self {
+            reaper,
+            close_reasons,
+        };
+
+        (scope, handle)
+    }
+
+    /// Returns state for a newly accepted connection with the tags attached by preprocessors.
+    pub(crate) fn accept(&self, tags: ConnectionTags) -> NewConnection {
+        NewConnection {
+            tags,
+            reaper: self.reaper.clone(),
+            close: self.close_reasons.as_ref().map(CloseState::new),
+        }
+    }
+}
+
+/// Accepted connection that has not been spawned yet.
+pub(crate) struct NewConnection {
+    tags: ConnectionTags,
+    reaper: Option<Rc<Reaper>>,
+    close: Option<Rc<CloseState>>,
+}
+
+/// Spawns the future handling a connection.
+///
+/// The future is created and polled within the scope of the connection's context, which tracks its
+/// activity if idle connections are closed, holds the tags attached by preprocessors, and collects
+/// its close reason if close reasons are counted.
+pub(crate) fn spawn<F, Fut>(conn: NewConnection, make_fut: F)
+where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = ()> + 'static,
+{
+    let NewConnection {
+        tags,
+        reaper,
+        close,
+    } = conn;
+
+    // avoid task-local overhead when there is nothing to expose
+    if reaper.is_none() && close.is_none() && tags.is_empty() {
+        actix_rt::spawn(make_fut());
+        return;
+    }
+
+    let cx = ConnectionContext {
+        activity: reaper.as_ref().map(|reaper| reaper.activity()),
+        tags: Rc::new(tags),
+        close,
+    };
+
+    let fut = CONTEXT.sync_scope(cx.clone(), make_fut);
+    let activity = cx.activity.clone();
+    let fut = CONTEXT.scope(cx, fut);
+
+    match (reaper, activity) {
+        (Some(reaper), Some(activity)) => reaper.spawn(activity, fut),
+        _ => {
+            actix_rt::spawn(fut);
+        }
+    }
+}
@@ -0,0 +1,403 @@
+//! Server running entirely on the current thread.
+
+use std::{
+    fmt,
+    future::Future,
+    io, mem,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_rt::{
+    net::{TcpListener, TcpStream},
+    time::sleep,
+    System,
+};
+use actix_utils::future::{poll_fn, select, Either};
+use futures_core::future::LocalBoxFuture;
+use tracing::{error, info};
+
+use crate::{
+    accept::{connection_error, TIMEOUT_DURATION_ON_ERROR},
+    availability::Availability,
+    builder::ServerBuilder,
+    connection::ConnectionScope,
+    server::{active_connections, ServerCommand},
+    service::InternalServiceFactory,
+    socket::{MioListener, MioStream},
+    waker_queue::WakerQueue,
+    worker::{Conn, ServerWorker, ServerWorkerConfig, WorkerHandleAccept, WorkerHandleServer},
+    ListenerInfo, ServerHandle,
+};
+
+/// Server that runs on the current thread, without an accept thread, worker threads, or signal
+/// handling.
+///
+/// Created by [`ServerBuilder::run_embedded`], for embedding small listeners inside an existing
+/// Tokio application or test harness without spawning threads. Connections are accepted by the
+/// server future itself and served by a single worker running as a task on the current thread,
+/// regardless of [`ServerBuilder::workers`]. Listening for OS signals is always disabled.
+///
+/// Worker and connection tasks are spawned with [`actix_rt::spawn`], so the server must run
+/// within a Tokio [`LocalSet`], such as the one of an Actix [`System`] or `#[actix_rt::test]`.
+///
+/// The server must be awaited or polled in order to start running. It will resolve when the
+/// server has fully shut down, e.g., after [`ServerHandle::stop`].
+///
+/// # Examples
+/// ```
+/// use actix_rt::net::TcpStream;
+/// use actix_server::Server;
+/// use actix_service::fn_service;
+/// use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+///
+/// # #[actix_rt::main]
+/// # async fn main() -> std::io::Result<()> {
+/// let lst = std::net::TcpListener::bind("127.0.0.1:0")?;
+/// let addr = lst.local_addr()?;
+///
+/// let srv = Server::build()
+///     .listen("hello", lst, || {
+///         fn_service(|mut stream: TcpStream| async move { stream.write_all(b"hello").await })
+///     })?
+///     .run_embedded();
+///
+/// let handle = srv.handle();
+/// actix_rt::spawn(srv);
+///
+/// let mut res = String::new();
+/// TcpStream::connect(addr).await?.read_to_string(&mut res).await?;
+/// assert_eq!(res, "hello");
+///
+/// handle.stop(true).await;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`LocalSet`]: tokio::task::LocalSet
+#[must_use = "EmbeddedServer does nothing unless you `.await` or poll it"]
+pub struct EmbeddedServer {
+    handle: ServerHandle,
+    fut: LocalBoxFuture<'static, io::Result<()>>,
+}
+
+impl EmbeddedServer {
+    pub(crate) fn new(builder: ServerBuilder) -> Self {
+        Self {
+            handle: ServerHandle::new(builder.cmd_tx.clone()),
+            fut: Box::pin(run(builder)),
+        }
+    }
+
+    /// Get a `Server` handle that can be used issue commands and change it's state.
+    ///
+    /// See [ServerHandle](ServerHandle) for usage.
+    pub fn handle(&self) -> ServerHandle {
+        self.handle.clone()
+    }
+}
+
+impl fmt::Debug for EmbeddedServer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EmbeddedServer").finish_non_exhaustive()
+    }
+}
+
+impl Future for EmbeddedServer {
+    type Output = io::Result<()>;
+
+    #[inline]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut Pin::into_inner(self).fut).poll(cx)
+    }
+}
+
+/// Index of the single worker of embedded servers.
+const WORKER_IDX: usize = 0;
+
+async fn run(mut builder: ServerBuilder) -> io::Result<()> {
+    let listeners = builder
+        .sockets
+        .iter()
+        .map(|(token, name, lst)| {
+            ListenerInfo::new(name, lst, builder.backlogs.get(token).copied())
+        })
+        .collect::<Vec<_>>();
+
+    for (_, name, lst) in &builder.sockets {
+        info!(
+            r#"starting embedded service: "{}", listening on: {}"#,
+            name,
+            lst.local_addr()
+        );
+    }
+
+    let sockets = mem::take(&mut builder.sockets)
+        .into_iter()
+        .map(|(token, _, lst)| Ok((token, Listener::from_mio(lst)?)))
+        .collect::<io::Result<Vec<_>>>()?;
+
+    // connection state is kept per server since other servers may run on the same thread
+    let (idle_timeout, clock) = builder.worker_config.idle();
+    let (scope, idle) =
+        ConnectionScope::start(idle_timeout, clock, builder.worker_config.close_reasons());
+
+    let mut worker = Worker {
+        waker_queue: WakerQueue::new_task(),
+        config: builder.worker_config,
+        scope,
+        factories: builder.factories,
+        avail: Availability::default(),
+        handles: None,
+    };
+
+    let res = serve(
+        &mut worker,
+        &sockets,
+        &mut builder.cmd_rx,
+        builder.exit,
+        &listeners,
+    )
+    .await;
+
+    if let Some(idle) = idle {
+        idle.abort();
+    }
+
+    res
+}
+
+/// Accepts connections and handles server commands until stopped.
+async fn serve(
+    worker: &mut Worker,
+    sockets: &[(usize, Listener)],
+    cmd_rx: &mut tokio::sync::mpsc::UnboundedReceiver<ServerCommand>,
+    system_stop: bool,
+    listeners: &[ListenerInfo],
+) -> io::Result<()> {
+    worker.start().await?;
+
+    let mut paused = false;
+
+    loop {
+        let cmd = if paused {
+            cmd_rx.recv().await
+        } else {
+            match select(cmd_rx.recv(), worker.accept(sockets)).await {
+                Either::Left { value: cmd } => cmd,
+                Either::Right { value: conn } => {
+                    worker.dispatch(conn).await?;
+                    continue;
+                }
+            }
+        };
+
+        match cmd {
+            Some(ServerCommand::Pause(tx)) => {
+                paused = true;
+                let _ = tx.send(());
+            }
+
+            Some(ServerCommand::Resume(tx)) => {
+                paused = false;
+                let _ = tx.send(());
+            }
+
+            Some(ServerCommand::Listeners(tx)) => {
+                let _ = tx.send(listeners.to_vec());
+            }
+
+            Some(ServerCommand::Connections(tx)) => {
+                let _ = tx.send(active_connections(&worker.config));
+            }
+
+            Some(ServerCommand::Stop {
+                graceful,
+                completion,
+                force_system_stop,
+            }) => {
+                if let Some((_, server)) = worker.handles.take() {
+                    let worker_stop = server.stop(graceful);
+
+                    if graceful {
+                        // wait for worker to shut down
+                        let _ = worker_stop.await;
+                    }
+                }
+
+                if let Some(tx) = completion {
+                    let _ = tx.send(());
+                }
+
+                if system_stop || force_system_stop {
+                    System::try_current().as_ref().map(System::stop);
+                }
+
+                return Ok(());
+            }
+
+            // worker faults are detected when dispatching connections
+            Some(ServerCommand::WorkerFaulted(_)) => {}
+
+            None => return Ok(()),
+        }
+    }
+}
+
+/// The single worker of an embedded server, along with its availability.
+struct Worker {
+    waker_queue: WakerQueue,
+    config: ServerWorkerConfig,
+    scope: ConnectionScope,
+    factories: Vec<Box<dyn InternalServiceFactory>>,
+    avail: Availability,
+    handles: Option<(WorkerHandleAccept, WorkerHandleServer)>,
+}
+
+impl Worker {
+    /// Starts worker task, replacing the previous one if it faulted.
+    async fn start(&mut self) -> io::Result<()> {
+        let factories = self.factories.iter().map(|f| f.clone_factory()).collect();
+
+        let handles = ServerWorker::start_local(
+            WORKER_IDX,
+            factories,
+            self.waker_queue.clone(),
+            self.config.clone(),
+            self.scope.clone(),
+        )
+        .await?;
+
+        self.handles = Some(handles);
+        self.avail.set_available(WORKER_IDX, true);
+
+        Ok(())
+    }
+
+    /// Waits for the worker to become available and accepts a connection from any listener.
+    async fn accept(&mut self, sockets: &[(usize, Listener)]) -> Conn {
+        loop {
+            self.waker_queue.take_available(&mut self.avail);
+
+            let overloaded = match &self.handles {
+                Some((handle, _)) => handle.is_overloaded(),
+                None => true,
+            };
+
+            if !self.avail.get_available(WORKER_IDX) || overloaded {
+                self.waker_queue.woken().await;
+                continue;
+            }
+
+            let res = poll_fn(|cx| {
+                for (token, lst) in sockets {
+                    if let Poll::Ready(res) = lst.poll_accept(cx) {
+                        return Poll::Ready(res.map(|io| Conn {
+                            io,
+                            token: *token,
+                            state: None,
+                            dispatched_at: None,
+                        }));
+                    }
+                }
+
+                Poll::Pending
+            })
+            .await;
+
+            match res {
+                Ok(conn) => return conn,
+                Err(ref err) if connection_error(err) => {}
+                Err(err) => {
+                    error!("error accepting connection: {}", err);
+
+                    // sleep after error, e.g., to let resource exhaustion resolve
+                    sleep(TIMEOUT_DURATION_ON_ERROR).await;
+                }
+            }
+        }
+    }
+
+    /// Sends connection to worker, restarting the worker if it has died.
+    async fn dispatch(&mut self, conn: Conn) -> io::Result<()> {
+        let (handle, _) = self.handles.as_ref().expect("worker is started");
+
+        match handle.send(conn) {
+            Ok(()) => {
+                if !handle.inc_counter() {
+                    self.avail.set_available(WORKER_IDX, false);
+                }
+
+                Ok(())
+            }
+
+            Err(_) => {
+                error!("embedded worker has died; restarting");
+                self.handles = None;
+                self.start().await
+            }
+        }
+    }
+}
+
+/// Listener of an embedded server, registered with the current runtime.
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Uds(actix_rt::net::UnixListener),
+}
+
+impl Listener {
+    fn from_mio(lst: MioListener) -> io::Result<Self> {
+        match lst {
+            #[cfg(unix)]
+            MioListener::Tcp(lst) => {
+                use std::os::unix::io::{FromRawFd as _, IntoRawFd as _};
+
+                // SAFETY: This is an in-place conversion from Mio listener to std listener.
+                let lst = unsafe { std::net::TcpListener::from_raw_fd(lst.into_raw_fd()) };
+                TcpListener::from_std(lst).map(Self::Tcp)
+            }
+
+            #[cfg(windows)]
+            MioListener::Tcp(lst) => {
+                use std::os::windows::io::{FromRawSocket as _, IntoRawSocket as _};
+
+                // SAFETY: This is an in-place conversion from Mio listener to std listener.
+                let lst = unsafe { std::net::TcpListener::from_raw_socket(lst.into_raw_socket()) };
+                TcpListener::from_std(lst).map(Self::Tcp)
+            }
+
+            #[cfg(unix)]
+            MioListener::Uds(lst) => {
+                use std::os::unix::io::{FromRawFd as _, IntoRawFd as _};
+
+                // SAFETY: This is an in-place conversion from Mio listener to std listener.
+                let lst =
+                    unsafe { std::os::unix::net::UnixListener::from_raw_fd(lst.into_raw_fd()) };
+                actix_rt::net::UnixListener::from_std(lst).map(Self::Uds)
+            }
+        }
+    }
+
+    fn poll_accept(&self, cx: &mut Context<'_>) -> Poll<io::Result<MioStream>> {
+        match self {
+            Self::Tcp(lst) => lst.poll_accept(cx).map(|res| {
+                let (stream, _) = res?;
+                into_mio_tcp(stream).map(MioStream::Tcp)
+            }),
+
+            #[cfg(unix)]
+            Self::Uds(lst) => lst.poll_accept(cx).map(|res| {
+                let (stream, _) = res?;
+                let stream = stream.into_std()?;
+                Ok(MioStream::Uds(mio::net::UnixStream::from_std(stream)))
+            }),
+        }
+    }
+}
+
+/// Converts accepted Tokio stream back into a Mio stream, which is what workers are sent.
+fn into_mio_tcp(stream: TcpStream) -> io::Result<mio::net::TcpStream> {
+    Ok(mio::net::TcpStream::from_std(stream.into_std()?))
+}
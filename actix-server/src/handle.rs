@@ -2,7 +2,7 @@ use std::future::Future;
 
 use tokio::sync::{mpsc::UnboundedSender, oneshot};
 
-use crate::server::ServerCommand;
+use crate::{server::ServerCommand, ActiveConnection, ListenerInfo};
 
 /// Server handle.
 #[derive(Debug, Clone)]
@@ -39,6 +39,28 @@ impl ServerHandle {
         }
     }
 
+    /// Returns the addresses and socket options of all listeners.
+    ///
+    /// Options are read from the OS when the server starts, so they reflect what actually took
+    /// effect, e.g., the port assigned when binding to port 0. Resolves to an empty list if the
+    /// server has stopped.
+    pub fn listeners(&self) -> impl Future<Output = Vec<ListenerInfo>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.cmd_tx.send(ServerCommand::Listeners(tx));
+        async { rx.await.unwrap_or_default() }
+    }
+
+    /// Returns info about all active connections, in the order they were received by workers.
+    ///
+    /// Requires tracking connections using
+    /// [`ServerBuilder::track_connections`](crate::ServerBuilder::track_connections). Resolves to an
+    /// empty list if tracking is disabled or the server has stopped.
+    pub fn connections(&self) -> impl Future<Output = Vec<ActiveConnection>> {
+        let (tx, rx) = oneshot::channel();
+        let _ = self.cmd_tx.send(ServerCommand::Connections(tx));
+        async { rx.await.unwrap_or_default() }
+    }
+
     /// Stop incoming connection processing, stop all workers and exit.
     pub fn stop(&self, graceful: bool) -> impl Future<Output = ()> {
         let (tx, rx) = oneshot::channel();
@@ -0,0 +1,293 @@
+//! Hand-off of connections between workers.
+//!
+//! See [`Handoff`] for main docs.
+
+use std::{
+    any::Any,
+    cell::RefCell,
+    collections::HashMap,
+    error::Error,
+    fmt, io,
+    sync::{Arc, RwLock},
+};
+
+use tokio::sync::mpsc::{UnboundedSender, WeakUnboundedSender};
+
+use crate::{
+    preprocess::ConnectionTags,
+    socket::FromStream,
+    waker_queue::WakerQueue,
+    worker::{Conn, Counter},
+};
+
+/// State sent along with a handed-off connection.
+pub(crate) type HandoffState = Box<dyn Any + Send>;
+
+/// Connection senders and counters of all workers, shared by workers to hand off connections.
+#[derive(Clone, Default)]
+pub(crate) struct HandoffRegistry {
+    workers: Arc<RwLock<HashMap<usize, Peer>>>,
+}
+
+struct Peer {
+    // weak so that the registry does not keep the channel of stopped workers open
+    conn_tx: WeakUnboundedSender<Conn>,
+    counter: Counter,
+    waker_queue: WakerQueue,
+}
+
+impl Peer {
+    /// Releases a connection slot reserved on the worker, waking up `Accept` if it was waiting.
+    fn release(&self, idx: usize) {
+        if self.counter.dec() {
+            self.waker_queue.wake_available(idx);
+        }
+    }
+}
+
+impl HandoffRegistry {
+    /// Registers a started worker, replacing the previous worker with same index if it restarted.
+    pub(crate) fn register(
+        &self,
+        idx: usize,
+        conn_tx: &UnboundedSender<Conn>,
+        counter: Counter,
+        waker_queue: WakerQueue,
+    ) {
+        let peer = Peer {
+            conn_tx: conn_tx.downgrade(),
+            counter,
+            waker_queue,
+        };
+
+        self.workers.write().unwrap().insert(idx, peer);
+    }
+}
+
+impl fmt::Debug for HandoffRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HandoffRegistry")
+            .field("workers", &self.workers.read().unwrap().len())
+            .finish()
+    }
+}
+
+/// Handle for transferring the connection being handled to another worker.
+///
+/// Enabled with [`ServerBuilder::connection_handoff`], in which case a handle is available to
+/// services through [`Handoff::current`] while they handle a connection. Services that own their
+/// connections can use it to rebalance load when one worker becomes hot, by detaching the stream
+/// from the current worker and sending it to a less loaded one, along with any state needed to
+/// resume handling it. The receiving worker calls the same listener's service with the stream and
+/// the state can be taken back using [`Handoff::take_state`].
+///
+/// Anything buffered in user space, such as partially read requests or TLS session state, is not
+/// transferred automatically, so connections are easiest to hand off between requests or when
+/// the state is passed along explicitly. [`ConnectionTags`] attached by preprocessors are not
+/// transferred and preprocessors do not run again on the receiving worker.
+///
+/// # Examples
+/// ```
+/// use actix_rt::net::TcpStream;
+/// use actix_server::{Handoff, Server};
+/// use actix_service::fn_service;
+///
+/// # fn build() -> std::io::Result<Server> {
+/// let srv = Server::build()
+///     .workers(4)
+///     .connection_handoff()
+///     .bind("app", ("127.0.0.1", 8080), || {
+///         fn_service(|stream: TcpStream| async move {
+///             let handoff = Handoff::current().unwrap();
+///
+///             // resume a connection handed off by another worker
+///             let requests_served = handoff.take_state::<u64>().unwrap_or(0);
+///
+///             // move connection to least busy worker
+///             let target = (0..handoff.num_workers())
+///                 .min_by_key(|&idx| handoff.connections(idx).unwrap_or(usize::MAX))
+///                 .unwrap();
+///
+///             if target != handoff.current_worker() {
+///                 if handoff.transfer(target, stream, requests_served).is_ok() {
+///                     return Ok(());
+///                 }
+///             }
+///
+///             // ...handle connection
+///
+///             Ok::<_, ()>(())
+///         })
+///     })?
+///     .run();
+/// # Ok(srv)
+/// # }
+/// ```
+///
+/// [`ServerBuilder::connection_handoff`]: crate::ServerBuilder::connection_handoff
+#[derive(Clone)]
+pub struct Handoff {
+    registry: HandoffRegistry,
+    worker: usize,
+    token: usize,
+}
+
+impl Handoff {
+    pub(crate) fn new(registry: HandoffRegistry, worker: usize, token: usize) -> Self {
+        Self {
+            registry,
+            worker,
+            token,
+        }
+    }
+
+    /// Returns a handle for the connection being handled, if connection hand-off is enabled.
+    pub fn current() -> Option<Self> {
+        ConnectionTags::current()?.get::<Self>().cloned()
+    }
+
+    /// Returns the index of the worker handling the connection.
+    pub fn current_worker(&self) -> usize {
+        self.worker
+    }
+
+    /// Returns the number of workers.
+    ///
+    /// Workers are indexed from `0` to `num_workers() - 1`.
+    pub fn num_workers(&self) -> usize {
+        self.registry.workers.read().unwrap().len()
+    }
+
+    /// Returns the number of connections being handled by a worker.
+    ///
+    /// Returns `None` if there is no worker with the given index.
+    pub fn connections(&self, worker: usize) -> Option<usize> {
+        let workers = self.registry.workers.read().unwrap();
+        workers.get(&worker).map(|peer| peer.counter.total())
+    }
+
+    /// Takes the state sent along with the connection by the worker that handed it off.
+    ///
+    /// Returns `None` if the connection was not handed off, if the state was taken already, or if
+    /// it is not of type `T`.
+    pub fn take_state<T: Any>(&self) -> Option<T> {
+        let tags = ConnectionTags::current()?;
+        let mut state = tags.get::<ReceivedState>()?.0.borrow_mut();
+
+        match state.take()?.downcast::<T>() {
+            Ok(val) => Some(*val),
+            Err(other) => {
+                *state = Some(other);
+                None
+            }
+        }
+    }
+
+    /// Transfers the connection's stream to another worker, along with `state`.
+    ///
+    /// The stream is handled by the same listener's service on the target worker. On success, the
+    /// current worker should stop handling the connection, which no longer counts towards its
+    /// connection limit once the service call completes.
+    ///
+    /// # Errors
+    /// Returns the stream back if the target worker does not exist, has reached its connection
+    /// limit, or has services that are not ready. Returns an I/O error if the stream could not be
+    /// detached from the current worker, in which case it is closed.
+    pub fn transfer<Io, S>(
+        &self,
+        worker: usize,
+        stream: Io,
+        state: S,
+    ) -> Result<(), HandoffError<Io>>
+    where
+        Io: FromStream,
+        S: Any + Send,
+    {
+        let workers = self.registry.workers.read().unwrap();
+
+        let (peer, conn_tx) = match workers.get(&worker) {
+            Some(peer) if !peer.counter.is_overloaded() => match peer.conn_tx.upgrade() {
+                Some(conn_tx) if peer.counter.try_inc() => (peer, conn_tx),
+                _ => return Err(HandoffError::Unavailable(stream)),
+            },
+            _ => return Err(HandoffError::Unavailable(stream)),
+        };
+
+        let io = match stream.into_mio() {
+            Ok(io) => io,
+            Err(err) => {
+                peer.release(worker);
+                return Err(HandoffError::Io(err));
+            }
+        };
+
+        let conn = Conn {
+            io,
+            token: self.token,
+            state: Some(Box::new(state)),
+            dispatched_at: None,
+        };
+
+        conn_tx.send(conn).map_err(|err| {
+            peer.release(worker);
+
+            match Io::from_mio(err.0.io) {
+                Ok(stream) => HandoffError::Unavailable(stream),
+                Err(err) => HandoffError::Io(err),
+            }
+        })
+    }
+}
+
+impl fmt::Debug for Handoff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Handoff")
+            .field("worker", &self.worker)
+            .finish_non_exhaustive()
+    }
+}
+
+/// State received with a handed-off connection, stored in its tags until taken.
+pub(crate) struct ReceivedState(RefCell<Option<HandoffState>>);
+
+impl ReceivedState {
+    pub(crate) fn new(state: HandoffState) -> Self {
+        Self(RefCell::new(Some(state)))
+    }
+}
+
+/// Error returned by [`Handoff::transfer`].
+pub enum HandoffError<Io> {
+    /// Target worker can not take the connection, which is returned.
+    Unavailable(Io),
+
+    /// Stream could not be detached from the current worker.
+    Io(io::Error),
+}
+
+impl<Io> fmt::Debug for HandoffError<Io> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unavailable(_) => f.write_str("Unavailable(..)"),
+            Self::Io(err) => f.debug_tuple("Io").field(err).finish(),
+        }
+    }
+}
+
+impl<Io> fmt::Display for HandoffError<Io> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unavailable(_) => f.write_str("target worker is unavailable"),
+            Self::Io(err) => write!(f, "can not detach stream from worker: {err}"),
+        }
+    }
+}
+
+impl<Io> Error for HandoffError<Io> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Unavailable(_) => None,
+            Self::Io(err) => Some(err),
+        }
+    }
+}
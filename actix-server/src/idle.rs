@@ -0,0 +1,188 @@
+//! Closing of idle connections.
+
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    fmt,
+    future::Future,
+    rc::Rc,
+    sync::Arc,
+    time::Duration,
+};
+
+use actix_rt::{task::JoinHandle, time::Instant};
+use actix_utils::clock::Clock;
+use tracing::debug;
+
+use crate::connection;
+
+/// Handle for recording activity on the current connection.
+///
+/// When an idle timeout is configured with
+/// [`ServerBuilder::connection_idle_timeout`](crate::ServerBuilder::connection_idle_timeout),
+/// each worker closes connections that have not recorded any activity for longer than the timeout
+/// by dropping the future handling them. Services record activity by calling
+/// [`touch`](Self::touch), typically whenever they read or write data; connections whose service
+/// never does so are closed once the timeout has elapsed since they were accepted.
+///
+/// The handle is cheap to clone and must be obtained with [`current`](Self::current) while handling
+/// a connection.
+#[derive(Clone)]
+pub struct ConnectionActivity {
+    clock: Arc<dyn Clock>,
+    last_activity: Rc<Cell<Instant>>,
+    timed_out: Rc<Cell<bool>>,
+}
+
+impl ConnectionActivity {
+    fn new(clock: Arc<dyn Clock>) -> Self {
+        let now = clock.now();
+
+        Self {
+            clock,
+            last_activity: Rc::new(Cell::new(now)),
+            timed_out: Rc::new(Cell::new(false)),
+        }
+    }
+
+    /// Returns a handle to the connection being handled.
+    ///
+    /// Available while calling the service with an accepted stream and while its future runs.
+    /// Returns `None` elsewhere or if no idle timeout is configured.
+    pub fn current() -> Option<Self> {
+        connection::current().and_then(|cx| cx.activity)
+    }
+
+    /// Records activity on the connection, resetting its idle time.
+    pub fn touch(&self) {
+        self.last_activity.set(self.clock.now());
+    }
+
+    /// Returns the time since activity was last recorded on the connection.
+    pub fn idle_time(&self) -> Duration {
+        self.clock
+            .now()
+            .saturating_duration_since(self.last_activity.get())
+    }
+
+    /// Returns true if the connection has been closed for being idle.
+    pub(crate) fn is_timed_out(&self) -> bool {
+        self.timed_out.get()
+    }
+}
+
+impl fmt::Debug for ConnectionActivity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionActivity")
+            .field("idle_time", &self.idle_time())
+            .finish()
+    }
+}
+
+/// Per-worker registry of connections that are closed when idle.
+pub(crate) struct Reaper {
+    timeout: Duration,
+    clock: Arc<dyn Clock>,
+    next_id: Cell<u64>,
+    conns: RefCell<HashMap<u64, (ConnectionActivity, JoinHandle<()>)>>,
+}
+
+impl Reaper {
+    /// Returns a new activity handle for a connection, starting out as not idle.
+    pub(crate) fn activity(&self) -> ConnectionActivity {
+        ConnectionActivity::new(self.clock.clone())
+    }
+
+    /// Closes connections idle for longer than the timeout.
+    fn reap(&self) {
+        let idle = {
+            let mut conns = self.conns.borrow_mut();
+
+            let ids = conns
+                .iter()
+                .filter(|(_, (activity, _))| activity.idle_time() > self.timeout)
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>();
+
+            ids.into_iter()
+                .filter_map(|id| conns.remove(&id))
+                .collect::<Vec<_>>()
+        };
+
+        // abort outside of borrow since dropping connection futures deregisters them
+        for (activity, handle) in idle {
+            debug!("closing idle connection");
+            activity.timed_out.set(true);
+            handle.abort();
+        }
+    }
+
+    /// Spawns the future handling a connection, closing it once `activity` has been idle for
+    /// longer than the timeout.
+    pub(crate) fn spawn(
+        self: Rc<Self>,
+        activity: ConnectionActivity,
+        fut: impl Future<Output = ()> + 'static,
+    ) {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+
+        let deregister = Deregister {
+            reaper: self.clone(),
+            id,
+        };
+
+        let handle = actix_rt::spawn(async move {
+            let _deregister = deregister;
+            fut.await;
+        });
+
+        self.conns.borrow_mut().insert(id, (activity, handle));
+    }
+}
+
+/// Removes a connection from the reaper when its future completes or is dropped.
+struct Deregister {
+    reaper: Rc<Reaper>,
+    id: u64,
+}
+
+impl Drop for Deregister {
+    fn drop(&mut self) {
+        self.reaper.conns.borrow_mut().remove(&self.id);
+    }
+}
+
+/// Starts closing idle connections of a worker, if a timeout is configured.
+///
+/// Returns the reaper that connections are registered with, along with the handle of the task
+/// closing idle connections, which runs until aborted or until the worker's runtime stops.
+pub(crate) fn start(
+    idle_timeout: Option<Duration>,
+    clock: Arc<dyn Clock>,
+) -> Option<(Rc<Reaper>, JoinHandle<()>)> {
+    let timeout = idle_timeout?;
+
+    let reaper = Rc::new(Reaper {
+        timeout,
+        clock: clock.clone(),
+        next_id: Cell::new(0),
+        conns: RefCell::new(HashMap::new()),
+    });
+
+    let handle = actix_rt::spawn({
+        let reaper = Rc::clone(&reaper);
+
+        async move {
+            // connections are closed after being idle for between 1 and 1.5 times the timeout
+            let period = (timeout / 2).max(Duration::from_millis(1));
+
+            loop {
+                clock.sleep(period).await;
+                reaper.reap();
+            }
+        }
+    });
+
+    Some((reaper, handle))
+}
@@ -0,0 +1,355 @@
+//! TCP keepalive configuration and dead peer detection.
+
+use std::{
+    error::Error,
+    fmt,
+    future::Future as _,
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_rt::{
+    net::{ActixStream, Ready, TcpStream},
+    time::{sleep, Sleep},
+};
+use socket2::{SockRef, TcpKeepalive};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// TCP keepalive settings.
+///
+/// Once a connection has been idle for `time`, the OS sends up to `retries` probes `interval`
+/// apart and resets the connection if none are answered, so dead peers are detected after
+/// [`timeout`](Self::timeout) of idleness. Interval and retries are left at their OS defaults on
+/// platforms that do not support setting them per socket, e.g., retries on Windows.
+///
+/// Wrap streams in a [`KeepAliveStream`] to have them fail with [`PeerDead`] once detected.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use actix_server::{KeepAlive, Server};
+///
+/// let keepalive = KeepAlive::new(Duration::from_secs(60))
+///     .interval(Duration::from_secs(10))
+///     .retries(3);
+///
+/// let builder = Server::build().preprocess(move |sock| keepalive.apply(&sock.socket()));
+/// # drop(builder);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepAlive {
+    time: Duration,
+    interval: Duration,
+    retries: u32,
+}
+
+impl KeepAlive {
+    /// Constructs settings sending the first probe after `time` of idleness.
+    ///
+    /// Probes are sent every 15 seconds, up to 4 times, by default.
+    pub fn new(time: Duration) -> Self {
+        Self {
+            time,
+            interval: Duration::from_secs(15),
+            retries: 4,
+        }
+    }
+
+    /// Sets time between unanswered probes.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets number of unanswered probes after which the connection is reset.
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Returns idle time after which dead peers are detected, on platforms applying all settings.
+    pub fn timeout(&self) -> Duration {
+        self.time + self.interval * self.retries
+    }
+
+    /// Enables keepalive with these settings on `socket`.
+    pub fn apply(&self, socket: &SockRef<'_>) -> io::Result<()> {
+        let keepalive = TcpKeepalive::new().with_time(self.time);
+
+        #[cfg(any(
+            target_os = "android",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "fuchsia",
+            target_os = "illumos",
+            target_os = "ios",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "netbsd",
+            target_os = "windows",
+        ))]
+        let keepalive = keepalive.with_interval(self.interval);
+
+        #[cfg(any(
+            target_os = "android",
+            target_os = "dragonfly",
+            target_os = "freebsd",
+            target_os = "fuchsia",
+            target_os = "illumos",
+            target_os = "ios",
+            target_os = "linux",
+            target_os = "macos",
+            target_os = "netbsd",
+        ))]
+        let keepalive = keepalive.with_retries(self.retries);
+
+        socket.set_tcp_keepalive(&keepalive)
+    }
+}
+
+/// TCP stream with keepalive enabled that reports dead peers as [`PeerDead`] errors.
+///
+/// Reads, writes, and flushes fail with an error of kind [`TimedOut`](io::ErrorKind::TimedOut)
+/// wrapping [`PeerDead`] once the OS gives up on unanswered keepalive probes. While a read is
+/// pending, the socket is also checked for errors every keepalive interval, so idle connections
+/// waiting for data are cleaned up even on platforms that do not wake readers on reset.
+pub struct KeepAliveStream {
+    io: TcpStream,
+    interval: Duration,
+    timer: Option<Pin<Box<Sleep>>>,
+    dead: bool,
+}
+
+impl KeepAliveStream {
+    /// Enables keepalive using `keepalive` on `io` and wraps it.
+    pub fn new(io: TcpStream, keepalive: &KeepAlive) -> io::Result<Self> {
+        keepalive.apply(&SockRef::from(&io))?;
+
+        Ok(Self {
+            io,
+            interval: keepalive.interval,
+            timer: None,
+            dead: false,
+        })
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &TcpStream {
+        &self.io
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    pub fn get_mut(&mut self) -> &mut TcpStream {
+        &mut self.io
+    }
+
+    /// Returns the underlying stream, leaving keepalive enabled.
+    pub fn into_inner(self) -> TcpStream {
+        self.io
+    }
+
+    /// Replaces errors caused by failed keepalive probes with [`PeerDead`].
+    fn map_err(&mut self, err: io::Error) -> io::Error {
+        if is_keepalive_failure(&err) {
+            self.dead = true;
+        }
+
+        if self.dead {
+            return PeerDead.into();
+        }
+
+        err
+    }
+
+    /// Checks socket for errors every keepalive interval.
+    fn poll_check(&mut self, cx: &mut Context<'_>) -> Poll<io::Error> {
+        loop {
+            let timer = self
+                .timer
+                .get_or_insert_with(|| Box::pin(sleep(self.interval)));
+
+            if timer.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+
+            self.timer = None;
+
+            match self.io.take_error() {
+                Ok(Some(err)) | Err(err) => return Poll::Ready(self.map_err(err)),
+                Ok(None) => {}
+            }
+        }
+    }
+}
+
+fn is_keepalive_failure(err: &io::Error) -> bool {
+    // reset by keepalive is reported as `WSAENETRESET` on Windows
+    #[cfg(windows)]
+    if err.raw_os_error() == Some(10052) {
+        return true;
+    }
+
+    err.kind() == io::ErrorKind::TimedOut
+}
+
+impl fmt::Debug for KeepAliveStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeepAliveStream")
+            .field("io", &self.io)
+            .field("interval", &self.interval)
+            .field("dead", &self.dead)
+            .finish()
+    }
+}
+
+impl AsyncRead for KeepAliveStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if this.dead {
+            return Poll::Ready(Err(PeerDead.into()));
+        }
+
+        match Pin::new(&mut this.io).poll_read(cx, buf) {
+            Poll::Ready(Ok(())) => {
+                this.timer = None;
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(err)) => Poll::Ready(Err(this.map_err(err))),
+            Poll::Pending => this.poll_check(cx).map(Err),
+        }
+    }
+}
+
+impl AsyncWrite for KeepAliveStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if this.dead {
+            return Poll::Ready(Err(PeerDead.into()));
+        }
+
+        Pin::new(&mut this.io)
+            .poll_write(cx, buf)
+            .map_err(|err| this.map_err(err))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        Pin::new(&mut this.io)
+            .poll_flush(cx)
+            .map_err(|err| this.map_err(err))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+impl ActixStream for KeepAliveStream {
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        ActixStream::poll_read_ready(&self.io, cx)
+    }
+
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        ActixStream::poll_write_ready(&self.io, cx)
+    }
+}
+
+/// Error of a [`KeepAliveStream`] whose peer did not answer keepalive probes.
+///
+/// Returned wrapped in an [`io::Error`] of kind [`TimedOut`](io::ErrorKind::TimedOut).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerDead;
+
+impl PeerDead {
+    /// Returns true if `err` was caused by a dead peer.
+    pub fn matches(err: &io::Error) -> bool {
+        err.get_ref().map_or(false, |err| err.is::<PeerDead>())
+    }
+}
+
+impl fmt::Display for PeerDead {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("peer did not respond to keepalive probes")
+    }
+}
+
+impl Error for PeerDead {}
+
+impl From<PeerDead> for io::Error {
+    fn from(err: PeerDead) -> Self {
+        io::Error::new(io::ErrorKind::TimedOut, err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    use super::*;
+
+    async fn tcp_pair() -> (TcpStream, TcpStream) {
+        let lst = actix_rt::net::TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap();
+        let client = TcpStream::connect(lst.local_addr().unwrap()).await.unwrap();
+        let (server, _) = lst.accept().await.unwrap();
+        (client, server)
+    }
+
+    #[actix_rt::test]
+    async fn applies_settings() {
+        let (client, _server) = tcp_pair().await;
+
+        let keepalive = KeepAlive::new(Duration::from_secs(30))
+            .interval(Duration::from_secs(5))
+            .retries(2);
+        assert_eq!(keepalive.timeout(), Duration::from_secs(40));
+
+        let stream = KeepAliveStream::new(client, &keepalive).unwrap();
+        let sock = SockRef::from(stream.get_ref());
+        assert!(sock.keepalive().unwrap());
+
+        #[cfg(target_os = "linux")]
+        {
+            assert_eq!(sock.keepalive_time().unwrap(), Duration::from_secs(30));
+            assert_eq!(sock.keepalive_interval().unwrap(), Duration::from_secs(5));
+            assert_eq!(sock.keepalive_retries().unwrap(), 2);
+        }
+    }
+
+    #[actix_rt::test]
+    async fn reports_dead_peer() {
+        let (client, mut server) = tcp_pair().await;
+        let mut stream =
+            KeepAliveStream::new(client, &KeepAlive::new(Duration::from_secs(30))).unwrap();
+
+        server.write_all(b"hello").await.unwrap();
+        let mut buf = [0; 5];
+        stream.read_exact(&mut buf).await.unwrap();
+
+        // other errors are passed through
+        let err = stream.map_err(io::ErrorKind::ConnectionReset.into());
+        assert!(!PeerDead::matches(&err));
+
+        let err = stream.map_err(io::ErrorKind::TimedOut.into());
+        assert!(PeerDead::matches(&err));
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        let err = stream.read(&mut buf).await.unwrap_err();
+        assert!(PeerDead::matches(&err));
+    }
+}
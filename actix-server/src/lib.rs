@@ -8,23 +8,61 @@
 mod accept;
 mod availability;
 mod builder;
+mod close;
+mod connection;
+#[cfg(feature = "embedded")]
+mod embedded;
 mod handle;
+mod handoff;
+mod idle;
 mod join_all;
+mod keepalive;
+mod net_app;
+#[cfg(unix)]
+mod peer_cred;
+mod preprocess;
+mod registry;
 mod server;
 mod service;
 mod signals;
 mod socket;
+#[cfg(target_os = "linux")]
+mod tcp_info;
 mod test_server;
+#[cfg(target_os = "linux")]
+mod transparent;
 mod waker_queue;
 mod worker;
 
+#[cfg(feature = "bench-harness")]
+pub mod bench;
+pub mod prelude;
+#[cfg(feature = "tcp-proxy")]
+pub mod proxy;
+
+#[cfg(feature = "embedded")]
+pub use self::embedded::EmbeddedServer;
+#[cfg(unix)]
+pub use self::peer_cred::PeerCredentials;
 #[doc(hidden)]
 pub use self::socket::FromStream;
+#[cfg(target_os = "linux")]
+pub use self::tcp_info::TcpInfo;
+#[cfg(target_os = "linux")]
+pub use self::transparent::OriginalDst;
 pub use self::{
     builder::{MpTcp, ServerBuilder},
+    close::{CloseReason, CloseReasonCounter},
     handle::ServerHandle,
+    handoff::{Handoff, HandoffError},
+    idle::ConnectionActivity,
+    keepalive::{KeepAlive, KeepAliveStream, PeerDead},
+    net_app::{NetApp, NetStream},
+    preprocess::{AcceptedSocket, ConnectionTags},
+    registry::{ActiveConnection, TrackedStream},
     server::Server,
     service::ServerServiceFactory,
+    socket::ListenerInfo,
     test_server::TestServer,
 };
 
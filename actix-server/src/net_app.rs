@@ -0,0 +1,529 @@
+use std::{
+    fmt, io,
+    net::{SocketAddr, TcpListener as StdTcpListener, ToSocketAddrs},
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_rt::net::{ActixStream, Ready, TcpStream};
+use actix_service::{Service, ServiceFactory};
+#[cfg(feature = "proxy-protocol")]
+use actix_tls::accept::proxy_protocol::{ProxyProtocolAcceptor, ProxyProtocolService};
+#[cfg(feature = "rustls")]
+use actix_tls::accept::{
+    rustls::{reexports::ServerConfig, Acceptor as RustlsAcceptor, AcceptorService},
+    TlsServerConnInfo as _,
+};
+use futures_core::future::LocalBoxFuture;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+#[cfg(any(feature = "proxy-protocol", feature = "rustls"))]
+use tracing::debug;
+use tracing::error;
+
+#[cfg(any(feature = "proxy-protocol", feature = "rustls"))]
+use crate::CloseReason;
+use crate::{Server, ServerBuilder};
+
+/// High-level builder for TCP servers.
+///
+/// Wires together listeners, optional PROXY protocol and TLS acceptors, and the per-connection
+/// service, so that common servers can be built without assembling acceptor services by hand.
+/// Connections are handed to the service as a [`NetStream`], whatever layers are enabled.
+///
+/// Handshake and service errors are logged through `tracing` within each connection's span. Failed
+/// handshakes are reported as [`CloseReason::HandshakeFailure`](crate::CloseReason) when close
+/// reasons are [counted](ServerBuilder::count_close_reasons).
+///
+/// # Crate Features
+/// - `proxy-protocol`: enables [`proxy_protocol`](Self::proxy_protocol).
+/// - `rustls`: enables [`rustls`](Self::rustls).
+///
+/// # Examples
+/// ```no_run
+/// use actix_server::prelude::*;
+///
+/// #[actix_rt::main]
+/// async fn main() -> std::io::Result<()> {
+///     NetApp::new("greeter")
+///         .bind("127.0.0.1:8080")?
+///         .serve(|| {
+///             fn_service(|stream: NetStream| async move {
+///                 println!("connection from {:?}", stream.client_addr());
+///                 Ok::<_, std::io::Error>(())
+///             })
+///         })?
+///         .await
+/// }
+/// ```
+pub struct NetApp {
+    name: String,
+    builder: ServerBuilder,
+    addrs: Vec<SocketAddr>,
+    listeners: Vec<StdTcpListener>,
+    handshake_timeout: Option<Duration>,
+    layers: Layers,
+}
+
+impl NetApp {
+    /// Constructs builder for server with listeners named `name`.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            builder: Server::build(),
+            addrs: Vec::new(),
+            listeners: Vec::new(),
+            handshake_timeout: None,
+            layers: Layers::default(),
+        }
+    }
+
+    /// Adds listener bound to `addr` once the server is started.
+    ///
+    /// # Errors
+    /// Returns error if `addr` can not be resolved.
+    pub fn bind(mut self, addr: impl ToSocketAddrs) -> io::Result<Self> {
+        self.addrs.extend(addr.to_socket_addrs()?);
+        Ok(self)
+    }
+
+    /// Adds already bound listener.
+    pub fn listen(mut self, lst: StdTcpListener) -> Self {
+        self.listeners.push(lst);
+        self
+    }
+
+    /// Sets number of workers to start.
+    ///
+    /// See [`ServerBuilder::workers`].
+    pub fn workers(mut self, num: usize) -> Self {
+        self.builder = self.builder.workers(num);
+        self
+    }
+
+    /// Applies further configuration to the underlying server builder.
+    ///
+    /// # Examples
+    /// ```
+    /// # use std::time::Duration;
+    /// # use actix_server::NetApp;
+    /// let app = NetApp::new("app").configure(|builder| {
+    ///     builder
+    ///         .backlog(1024)
+    ///         .connection_idle_timeout(Duration::from_secs(60))
+    /// });
+    /// ```
+    pub fn configure(mut self, f: impl FnOnce(ServerBuilder) -> ServerBuilder) -> Self {
+        self.builder = f(self.builder);
+        self
+    }
+
+    /// Sets timeout for reading the PROXY protocol header and completing the TLS handshake.
+    ///
+    /// Uses the defaults of the respective acceptors if not set.
+    pub fn handshake_timeout(mut self, dur: Duration) -> Self {
+        self.handshake_timeout = Some(dur);
+        self
+    }
+
+    /// Reads a PROXY protocol header ahead of each connection.
+    ///
+    /// The client address it carries is exposed through [`NetStream::client_addr`]. Connections
+    /// without a valid header are closed.
+    #[cfg(feature = "proxy-protocol")]
+    pub fn proxy_protocol(mut self) -> Self {
+        self.layers.proxy = Some(ProxyProtocolAcceptor::new());
+        self
+    }
+
+    /// Accepts TLS connections using `rustls` with `config`.
+    ///
+    /// When the PROXY protocol is enabled too, the TLS handshake follows the PROXY header.
+    #[cfg(feature = "rustls")]
+    pub fn rustls(mut self, config: ServerConfig) -> Self {
+        self.layers.tls = Some(RustlsAcceptor::new(config));
+        self
+    }
+
+    /// Binds listeners and starts the server, serving connections with services created by
+    /// `factory`.
+    ///
+    /// # Errors
+    /// Returns error if no listener was added or binding fails.
+    pub fn serve<F, S>(self, factory: F) -> io::Result<Server>
+    where
+        F: Fn() -> S + Send + Clone + 'static,
+        S: ServiceFactory<NetStream, Config = (), Response = ()> + 'static,
+        S::Service: 'static,
+        S::Future: 'static,
+        S::Error: fmt::Debug,
+        S::InitError: fmt::Debug,
+    {
+        let Self {
+            name,
+            mut builder,
+            addrs,
+            listeners,
+            handshake_timeout,
+            mut layers,
+        } = self;
+
+        if addrs.is_empty() && listeners.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no address to bind to or listener added",
+            ));
+        }
+
+        if let Some(dur) = handshake_timeout {
+            layers.set_timeout(dur);
+        }
+
+        let factory = move || NetAppFactory {
+            inner: factory(),
+            layers: layers.clone(),
+        };
+
+        for addr in addrs {
+            builder = builder.bind(&name, addr, factory.clone())?;
+        }
+
+        for lst in listeners {
+            builder = builder.listen(&name, lst, factory.clone())?;
+        }
+
+        Ok(builder.run())
+    }
+}
+
+impl fmt::Debug for NetApp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NetApp")
+            .field("name", &self.name)
+            .field("addrs", &self.addrs)
+            .field("listeners", &self.listeners)
+            .field("handshake_timeout", &self.handshake_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Acceptors run ahead of the connection service.
+#[derive(Clone, Default)]
+struct Layers {
+    #[cfg(feature = "proxy-protocol")]
+    proxy: Option<ProxyProtocolAcceptor>,
+
+    #[cfg(feature = "rustls")]
+    tls: Option<RustlsAcceptor>,
+}
+
+impl Layers {
+    #[allow(unused_variables)]
+    fn set_timeout(&mut self, dur: Duration) {
+        #[cfg(feature = "proxy-protocol")]
+        if let Some(proxy) = &mut self.proxy {
+            proxy.set_timeout(dur);
+        }
+
+        #[cfg(feature = "rustls")]
+        if let Some(tls) = &mut self.tls {
+            tls.set_handshake_timeout(dur);
+        }
+    }
+}
+
+#[derive(Default)]
+struct LayerServices {
+    #[cfg(feature = "proxy-protocol")]
+    proxy: Option<ProxyProtocolService>,
+
+    #[cfg(feature = "rustls")]
+    tls: Option<AcceptorService>,
+}
+
+struct NetAppFactory<S> {
+    inner: S,
+
+    // only read when acceptor features are enabled
+    #[allow(dead_code)]
+    layers: Layers,
+}
+
+impl<S> ServiceFactory<TcpStream> for NetAppFactory<S>
+where
+    S: ServiceFactory<NetStream, Config = (), Response = ()>,
+    S::Service: 'static,
+    S::Future: 'static,
+    S::Error: fmt::Debug,
+    S::InitError: fmt::Debug,
+{
+    type Response = ();
+    type Error = ();
+    type Config = ();
+    type Service = NetAppService<S::Service>;
+    type InitError = ();
+    type Future = LocalBoxFuture<'static, Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        let inner = self.inner.new_service(());
+
+        #[allow(unused_mut)]
+        let mut layers = LayerServices::default();
+
+        // acceptor services are created synchronously; failing to create one must not leave its
+        // layer out, so the worker fails to start instead
+        #[cfg(feature = "proxy-protocol")]
+        if let Some(proxy) = &self.layers.proxy {
+            match ServiceFactory::<NetStream>::new_service(proxy, ()).into_inner() {
+                Ok(proxy) => layers.proxy = Some(proxy),
+                Err(_) => {
+                    error!("can not construct PROXY protocol acceptor service");
+                    return Box::pin(async { Err(()) });
+                }
+            }
+        }
+
+        #[cfg(feature = "rustls")]
+        if let Some(tls) = &self.layers.tls {
+            match ServiceFactory::<NetStream>::new_service(tls, ()).into_inner() {
+                Ok(tls) => layers.tls = Some(tls),
+                Err(_) => {
+                    error!("can not construct TLS acceptor service");
+                    return Box::pin(async { Err(()) });
+                }
+            }
+        }
+
+        Box::pin(async move {
+            let inner = inner
+                .await
+                .map_err(|err| error!("can not construct connection service: {err:?}"))?;
+
+            Ok(NetAppService {
+                inner: Rc::new(inner),
+                layers: Rc::new(layers),
+            })
+        })
+    }
+}
+
+struct NetAppService<S> {
+    inner: Rc<S>,
+    layers: Rc<LayerServices>,
+}
+
+impl<S> Service<TcpStream> for NetAppService<S>
+where
+    S: Service<NetStream, Response = ()> + 'static,
+    S::Future: 'static,
+    S::Error: fmt::Debug,
+{
+    type Response = ();
+    type Error = ();
+    type Future = LocalBoxFuture<'static, Result<(), ()>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        // TLS acceptor is not ready while at its handshake limit
+        #[cfg(feature = "rustls")]
+        if let Some(tls) = &self.layers.tls {
+            if Service::<NetStream>::poll_ready(tls, cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+
+        self.inner
+            .poll_ready(cx)
+            .map_err(|err| error!("connection service failed: {err:?}"))
+    }
+
+    fn call(&self, stream: TcpStream) -> Self::Future {
+        let inner = Rc::clone(&self.inner);
+
+        #[allow(unused_variables)]
+        let layers = Rc::clone(&self.layers);
+
+        Box::pin(async move {
+            #[allow(unused_mut)]
+            let mut stream = NetStream::new(stream);
+
+            #[cfg(feature = "proxy-protocol")]
+            if let Some(proxy) = &layers.proxy {
+                let info = stream.info.clone();
+
+                let proxied = proxy.call(stream).await.map_err(|err| {
+                    debug!("can not read PROXY protocol header: {err}");
+                    CloseReason::HandshakeFailure.report();
+                })?;
+
+                stream = NetStream {
+                    info: ConnInfo {
+                        client_addr: proxied.source_addr().or(info.client_addr),
+                        ..info
+                    },
+                    io: Box::new(proxied),
+                };
+            }
+
+            #[cfg(feature = "rustls")]
+            if let Some(tls) = &layers.tls {
+                let info = stream.info.clone();
+
+                let tls_stream = tls.call(stream).await.map_err(|err| {
+                    debug!("TLS handshake failed: {err}");
+                    CloseReason::HandshakeFailure.report();
+                })?;
+
+                stream = NetStream {
+                    info: ConnInfo {
+                        tls: true,
+                        alpn_protocol: tls_stream.alpn_protocol(),
+                        server_name: tls_stream.server_name(),
+                        ..info
+                    },
+                    io: Box::new(tls_stream),
+                };
+            }
+
+            inner
+                .call(stream)
+                .await
+                .map_err(|err| error!("connection service failed: {err:?}"))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct ConnInfo {
+    peer_addr: Option<SocketAddr>,
+    client_addr: Option<SocketAddr>,
+    tls: bool,
+    alpn_protocol: Option<Vec<u8>>,
+    server_name: Option<String>,
+    #[cfg(target_os = "linux")]
+    fd: std::os::unix::io::RawFd,
+}
+
+/// Connection accepted by a [`NetApp`].
+///
+/// Reads and writes go through the acceptors enabled on the app, e.g., decrypting TLS.
+pub struct NetStream {
+    io: Box<dyn ActixStream>,
+    info: ConnInfo,
+}
+
+impl NetStream {
+    fn new(stream: TcpStream) -> Self {
+        let peer_addr = stream.peer_addr().ok();
+        #[cfg(target_os = "linux")]
+        let fd = std::os::unix::io::AsRawFd::as_raw_fd(&stream);
+
+        Self {
+            io: Box::new(stream),
+            info: ConnInfo {
+                peer_addr,
+                client_addr: peer_addr,
+                #[cfg(target_os = "linux")]
+                fd,
+                ..ConnInfo::default()
+            },
+        }
+    }
+
+    /// Returns address of the directly connected peer.
+    ///
+    /// When behind a proxy, this is the address of the proxy.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.info.peer_addr
+    }
+
+    /// Returns address of the client.
+    ///
+    /// This is the source address from the PROXY protocol header if present, and the peer address
+    /// otherwise.
+    pub fn client_addr(&self) -> Option<SocketAddr> {
+        self.info.client_addr
+    }
+
+    /// Returns true if the connection is encrypted using TLS.
+    pub fn is_tls(&self) -> bool {
+        self.info.tls
+    }
+
+    /// Returns ALPN protocol negotiated during the TLS handshake, if any.
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.info.alpn_protocol.as_deref()
+    }
+
+    /// Returns server name (SNI) sent by the client during the TLS handshake, if any.
+    pub fn server_name(&self) -> Option<&str> {
+        self.info.server_name.as_deref()
+    }
+
+    /// Samples `TCP_INFO` statistics of the underlying TCP connection.
+    #[cfg(target_os = "linux")]
+    pub fn tcp_info(&self) -> io::Result<crate::TcpInfo> {
+        // file descriptor is owned by the innermost stream, which lives as long as `self`
+        crate::TcpInfo::sample(&self.info.fd)
+    }
+}
+
+impl fmt::Debug for NetStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NetStream")
+            .field("peer_addr", &self.info.peer_addr)
+            .field("client_addr", &self.info.client_addr)
+            .field("tls", &self.info.tls)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AsyncRead for NetStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for NetStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+impl ActixStream for NetStream {
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        self.io.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        self.io.poll_write_ready(cx)
+    }
+}
@@ -0,0 +1,164 @@
+//! Credentials of peers connected through Unix domain sockets.
+
+use std::{io, os::unix::io::AsRawFd as _};
+
+use crate::preprocess::ConnectionTags;
+
+/// Credentials of the process on the other end of a Unix domain socket connection.
+///
+/// Read using `SO_PEERCRED` on Linux and Android and `getpeereid` (`LOCAL_PEERCRED`) on macOS and
+/// the BSDs when the connection is accepted, so services can authenticate local clients, e.g., on
+/// control sockets. Not available on other platforms.
+///
+/// # Examples
+/// ```no_run
+/// use actix_server::{PeerCredentials, Server};
+/// use actix_service::fn_service;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let server = Server::build().bind_uds("control", "/run/app/control.sock", || {
+///     fn_service(|_stream: actix_rt::net::UnixStream| async {
+///         match PeerCredentials::current() {
+///             Some(cred) if cred.uid() == 0 => Ok(()),
+///             _ => Err("permission denied"),
+///         }
+///     })
+/// })?;
+/// # drop(server);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PeerCredentials {
+    uid: u32,
+    gid: u32,
+    pid: Option<i32>,
+}
+
+impl PeerCredentials {
+    /// Returns the credentials of the peer of the Unix domain socket connection being handled.
+    ///
+    /// Available while calling the service with an accepted stream and while its future runs.
+    /// Returns `None` elsewhere, for TCP connections, or if the credentials could not be read.
+    pub fn current() -> Option<Self> {
+        ConnectionTags::current()?.get::<Self>().copied()
+    }
+
+    /// Returns effective user ID of the peer process.
+    pub fn uid(&self) -> u32 {
+        self.uid
+    }
+
+    /// Returns effective group ID of the peer process.
+    pub fn gid(&self) -> u32 {
+        self.gid
+    }
+
+    /// Returns process ID of the peer, if the platform reports it.
+    ///
+    /// Only reported on Linux and Android.
+    pub fn pid(&self) -> Option<i32> {
+        self.pid
+    }
+
+    /// Reads credentials of the peer connected to `stream`.
+    pub(crate) fn from_stream(stream: &mio::net::UnixStream) -> io::Result<Self> {
+        read(stream.as_raw_fd())
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn read(fd: std::os::unix::io::RawFd) -> io::Result<PeerCredentials> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+
+    // SAFETY: `cred` and `len` are valid for writes and `len` holds the size of `cred`
+    let ret = unsafe {
+        libc::getsockopt(
+            fd,
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(PeerCredentials {
+        uid: cred.uid,
+        gid: cred.gid,
+        pid: Some(cred.pid),
+    })
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly",
+))]
+fn read(fd: std::os::unix::io::RawFd) -> io::Result<PeerCredentials> {
+    let mut uid = 0;
+    let mut gid = 0;
+
+    // SAFETY: `uid` and `gid` are valid for writes
+    let ret = unsafe { libc::getpeereid(fd, &mut uid, &mut gid) };
+
+    if ret != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(PeerCredentials {
+        uid,
+        gid,
+        pid: None,
+    })
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly",
+)))]
+fn read(_fd: std::os::unix::io::RawFd) -> io::Result<PeerCredentials> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "peer credentials are not supported on this platform",
+    ))
+}
+
+#[cfg(all(
+    test,
+    any(target_os = "linux", target_os = "android", target_os = "macos")
+))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_own_credentials() {
+        let (a, _b) = mio::net::UnixStream::pair().unwrap();
+        let cred = PeerCredentials::from_stream(&a).unwrap();
+
+        // SAFETY: these functions are always successful
+        let (uid, gid) = unsafe { (libc::geteuid(), libc::getegid()) };
+        assert_eq!(cred.uid(), uid);
+        assert_eq!(cred.gid(), gid);
+
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        assert_eq!(cred.pid(), Some(std::process::id() as i32));
+    }
+}
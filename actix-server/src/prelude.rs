@@ -0,0 +1,14 @@
+//! Commonly used types and traits for building TCP services.
+//!
+//! ```
+//! use actix_server::prelude::*;
+//! ```
+
+pub use actix_rt::net::{ActixStream, TcpStream};
+pub use actix_service::{
+    fn_factory, fn_service, Service, ServiceExt as _, ServiceFactory, ServiceFactoryExt as _,
+};
+#[cfg(feature = "rustls")]
+pub use actix_tls::accept::rustls::reexports::ServerConfig as RustlsServerConfig;
+
+pub use crate::{NetApp, NetStream, Server, ServerBuilder, ServerHandle};
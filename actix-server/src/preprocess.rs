@@ -0,0 +1,219 @@
+//! Synchronous preprocessing of accepted sockets.
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt, io,
+    net::SocketAddr,
+    rc::Rc,
+    sync::Arc,
+};
+
+use socket2::SockRef;
+
+use crate::{connection, socket::MioStream};
+
+pub(crate) type Preprocessor = Arc<dyn Fn(&mut AcceptedSocket<'_>) -> io::Result<()> + Send + Sync>;
+
+/// Accepted socket passed to preprocessors registered with
+/// [`ServerBuilder::preprocess`](crate::ServerBuilder::preprocess).
+pub struct AcceptedSocket<'a> {
+    stream: &'a MioStream,
+    #[cfg(unix)]
+    fd: std::os::unix::io::BorrowedFd<'a>,
+    #[cfg(windows)]
+    socket: std::os::windows::io::BorrowedSocket<'a>,
+    listener: &'a str,
+    tags: &'a mut ConnectionTags,
+}
+
+impl<'a> AcceptedSocket<'a> {
+    fn new(stream: &'a MioStream, listener: &'a str, tags: &'a mut ConnectionTags) -> Self {
+        #[cfg(unix)]
+        let fd = {
+            use std::os::unix::io::{AsRawFd as _, BorrowedFd};
+
+            let fd = match stream {
+                MioStream::Tcp(stream) => stream.as_raw_fd(),
+                MioStream::Uds(stream) => stream.as_raw_fd(),
+            };
+
+            // SAFETY: file descriptor is owned by the stream, which outlives the borrow
+            unsafe { BorrowedFd::borrow_raw(fd) }
+        };
+
+        #[cfg(windows)]
+        let socket = {
+            use std::os::windows::io::{AsRawSocket as _, BorrowedSocket};
+
+            let MioStream::Tcp(stream) = stream;
+
+            // SAFETY: socket is owned by the stream, which outlives the borrow
+            unsafe { BorrowedSocket::borrow_raw(stream.as_raw_socket()) }
+        };
+
+        Self {
+            stream,
+            #[cfg(unix)]
+            fd,
+            #[cfg(windows)]
+            socket,
+            listener,
+            tags,
+        }
+    }
+
+    /// Returns the name of the listener that accepted the socket.
+    pub fn listener(&self) -> &str {
+        self.listener
+    }
+
+    /// Returns the peer address of TCP sockets.
+    ///
+    /// Returns `None` for Unix domain sockets or if the address can not be retrieved.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        match self.stream {
+            MioStream::Tcp(stream) => stream.peer_addr().ok(),
+            #[cfg(unix)]
+            MioStream::Uds(_) => None,
+        }
+    }
+
+    /// Returns the credentials of the peer process of Unix domain sockets.
+    ///
+    /// Returns `None` for TCP sockets or if the credentials can not be retrieved.
+    #[cfg(unix)]
+    pub fn peer_credentials(&self) -> Option<crate::PeerCredentials> {
+        self.tags.get().copied()
+    }
+
+    /// Samples `TCP_INFO` statistics of TCP sockets.
+    ///
+    /// Returns an error for Unix domain sockets.
+    #[cfg(target_os = "linux")]
+    pub fn tcp_info(&self) -> io::Result<crate::TcpInfo> {
+        crate::TcpInfo::sample(&self.fd)
+    }
+
+    /// Returns a reference to the socket for reading or changing socket options.
+    pub fn socket(&self) -> SockRef<'_> {
+        #[cfg(unix)]
+        return SockRef::from(&self.fd);
+
+        #[cfg(windows)]
+        return SockRef::from(&self.socket);
+    }
+
+    /// Returns tags that are made available to the service handling the connection.
+    pub fn tags(&mut self) -> &mut ConnectionTags {
+        self.tags
+    }
+}
+
+impl fmt::Debug for AcceptedSocket<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AcceptedSocket")
+            .field("stream", &self.stream)
+            .field("listener", &self.listener)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Type map of values attached to a connection by preprocessors, one value per type.
+///
+/// Services read the tags of the connection they are handling with [`current`](Self::current).
+#[derive(Default)]
+pub struct ConnectionTags {
+    map: HashMap<TypeId, Box<dyn Any>>,
+}
+
+impl ConnectionTags {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the tags of the connection being handled.
+    ///
+    /// Available while calling the service with an accepted stream and while its future runs.
+    /// Returns `None` elsewhere or if preprocessors inserted no tags.
+    pub fn current() -> Option<Rc<ConnectionTags>> {
+        connection::current()
+            .map(|cx| cx.tags)
+            .filter(|tags| !tags.is_empty())
+    }
+
+    /// Inserts a tag, returning the previous tag of the same type.
+    pub fn insert<T: 'static>(&mut self, tag: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(tag))
+            .and_then(|prev| prev.downcast().ok().map(|prev| *prev))
+    }
+
+    /// Returns a reference to the tag of type `T`.
+    pub fn get<T: 'static>(&self) -> Option<&T> {
+        self.map.get(&TypeId::of::<T>())?.downcast_ref()
+    }
+
+    /// Removes and returns the tag of type `T`.
+    pub fn remove<T: 'static>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())?
+            .downcast()
+            .ok()
+            .map(|tag| *tag)
+    }
+
+    /// Returns true if a tag of type `T` is present.
+    pub fn contains<T: 'static>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Returns true if there are no tags.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl fmt::Debug for ConnectionTags {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionTags")
+            .field("len", &self.map.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Runs preprocessors in order, stopping at the first rejection.
+pub(crate) fn run(
+    preprocessors: &[Preprocessor],
+    stream: &MioStream,
+    listener: &str,
+    tags: &mut ConnectionTags,
+) -> io::Result<()> {
+    let mut socket = AcceptedSocket::new(stream, listener, tags);
+
+    preprocessors
+        .iter()
+        .try_for_each(|preprocess| preprocess(&mut socket))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tags() {
+        let mut tags = ConnectionTags::new();
+        assert!(tags.is_empty());
+
+        assert_eq!(tags.insert(1u8), None);
+        assert_eq!(tags.insert(2u8), Some(1));
+        tags.insert("tenant");
+
+        assert_eq!(tags.get::<u8>(), Some(&2));
+        assert_eq!(tags.get::<&str>(), Some(&"tenant"));
+        assert!(!tags.contains::<u16>());
+
+        assert_eq!(tags.remove::<u8>(), Some(2));
+        assert!(!tags.contains::<u8>());
+    }
+}
@@ -0,0 +1,415 @@
+//! Ready-made TCP proxy service.
+//!
+//! See [`Proxy`] for details.
+
+use std::{
+    error::Error,
+    fmt, io,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_rt::{net::TcpStream, time::timeout};
+use actix_service::{Service, ServiceFactory};
+use actix_tls::connect::{ConnectError, ConnectInfo, Connector, ConnectorService, Host};
+use actix_utils::{
+    future::{ready, select, Either, Ready},
+    proxy::copy_bidirectional,
+};
+use futures_core::future::LocalBoxFuture;
+use tokio::sync::watch;
+
+/// Default time allowed for connecting to the upstream.
+const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Service factory for proxying connections to upstreams.
+///
+/// For each accepted connection, the `route` callback chooses the upstream, which is dialed using
+/// the actix-tls [`Connector`]. Bytes are then piped in both directions using
+/// [`copy_bidirectional`], which uses `splice(2)` on Linux, until both sides are closed.
+/// Connections for which `route` returns `None` are closed.
+///
+/// Worker factories construct a proxy per worker; the [`ProxyHandle`] given to all of them
+/// collects their metrics and drains their connections.
+///
+/// # Examples
+/// ```no_run
+/// use actix_server::{
+///     proxy::{Proxy, ProxyHandle},
+///     Server,
+/// };
+/// use actix_tls::connect::ConnectInfo;
+///
+/// # async fn run() -> std::io::Result<()> {
+/// let handle = ProxyHandle::new();
+///
+/// let srv = Server::build()
+///     .bind("proxy", ("0.0.0.0", 8080), {
+///         let handle = handle.clone();
+///
+///         move || Proxy::new(&handle, |_stream| Some(ConnectInfo::new("backend:8080")))
+///     })?
+///     .run();
+///
+/// // on shutdown, give connections 30 seconds to finish before stopping the server
+/// let srv_handle = srv.handle();
+/// actix_rt::spawn(async move {
+///     actix_rt::signal::ctrl_c().await.unwrap();
+///     handle.drain(std::time::Duration::from_secs(30)).await;
+///     srv_handle.stop(true).await;
+/// });
+///
+/// srv.await
+/// # }
+/// ```
+pub struct Proxy<F> {
+    route: Rc<F>,
+    connector: Connector,
+    connect_timeout: Duration,
+    shared: Arc<Shared>,
+}
+
+impl<F> Proxy<F> {
+    /// Constructs proxy routing connections using `route`, reporting to `handle`.
+    pub fn new<R>(handle: &ProxyHandle, route: F) -> Self
+    where
+        F: Fn(&TcpStream) -> Option<ConnectInfo<R>>,
+        R: Host,
+    {
+        Self {
+            route: Rc::new(route),
+            connector: Connector::default(),
+            connect_timeout: DEFAULT_CONNECT_TIMEOUT,
+            shared: Arc::clone(&handle.shared),
+        }
+    }
+
+    /// Sets connector used for dialing upstreams, e.g., to use a custom resolver.
+    pub fn connector(mut self, connector: Connector) -> Self {
+        self.connector = connector;
+        self
+    }
+
+    /// Sets time allowed for connecting to the upstream, including DNS resolution.
+    ///
+    /// Default is 5 seconds.
+    pub fn connect_timeout(mut self, dur: Duration) -> Self {
+        self.connect_timeout = dur;
+        self
+    }
+}
+
+impl<F> fmt::Debug for Proxy<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Proxy")
+            .field("connect_timeout", &self.connect_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, R> ServiceFactory<TcpStream> for Proxy<F>
+where
+    F: Fn(&TcpStream) -> Option<ConnectInfo<R>> + 'static,
+    R: Host + 'static,
+{
+    type Response = ();
+    type Error = ProxyError;
+    type Config = ();
+    type Service = ProxyService<F>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        ready(Ok(ProxyService {
+            route: Rc::clone(&self.route),
+            connector: self.connector.service(),
+            connect_timeout: self.connect_timeout,
+            shared: Arc::clone(&self.shared),
+        }))
+    }
+}
+
+/// Service proxying connections to upstreams, created by [`Proxy`].
+pub struct ProxyService<F> {
+    route: Rc<F>,
+    connector: ConnectorService,
+    connect_timeout: Duration,
+    shared: Arc<Shared>,
+}
+
+impl<F> fmt::Debug for ProxyService<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxyService")
+            .field("connect_timeout", &self.connect_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<F, R> Service<TcpStream> for ProxyService<F>
+where
+    F: Fn(&TcpStream) -> Option<ConnectInfo<R>> + 'static,
+    R: Host + 'static,
+{
+    type Response = ();
+    type Error = ProxyError;
+    type Future = LocalBoxFuture<'static, Result<(), ProxyError>>;
+
+    fn poll_ready(&self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&self, mut stream: TcpStream) -> Self::Future {
+        let route = Rc::clone(&self.route);
+        let connector = self.connector.clone();
+        let connect_timeout = self.connect_timeout;
+        let shared = Arc::clone(&self.shared);
+
+        Box::pin(async move {
+            if shared.draining.load(Ordering::Acquire) {
+                return Err(ProxyError::Draining);
+            }
+
+            let _active = Active::new(&shared);
+            let mut abort = shared.abort.subscribe();
+
+            let proxy = async {
+                let info = route(&stream).ok_or(ProxyError::NoRoute)?;
+
+                let conn = timeout(connect_timeout, connector.call(info))
+                    .await
+                    .map_err(|_| ProxyError::Timeout)?
+                    .map_err(ProxyError::Connect)?;
+                let (mut upstream, _) = conn.into_parts();
+
+                shared.connections.fetch_add(1, Ordering::Relaxed);
+
+                let (sent, received) = copy_bidirectional(&mut stream, &mut upstream)
+                    .await
+                    .map_err(ProxyError::Io)?;
+
+                shared.sent.fetch_add(sent, Ordering::Relaxed);
+                shared.received.fetch_add(received, Ordering::Relaxed);
+
+                Ok(())
+            };
+
+            let aborted = async {
+                while !*abort.borrow_and_update() {
+                    // sender lives as long as `shared`
+                    let _ = abort.changed().await;
+                }
+            };
+
+            let res = match select(proxy, aborted).await {
+                Either::Left { value } => value,
+                Either::Right { .. } => Err(ProxyError::Aborted),
+            };
+
+            if matches!(
+                res,
+                Err(ProxyError::NoRoute | ProxyError::Timeout | ProxyError::Connect(_))
+            ) {
+                shared.failed.fetch_add(1, Ordering::Relaxed);
+            }
+
+            res
+        })
+    }
+}
+
+/// Handle for observing and draining the connections of [`Proxy`] services.
+///
+/// Cheap to clone and can be shared between workers.
+#[derive(Clone)]
+pub struct ProxyHandle {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    active: watch::Sender<usize>,
+    abort: watch::Sender<bool>,
+    draining: AtomicBool,
+    connections: AtomicU64,
+    failed: AtomicU64,
+    sent: AtomicU64,
+    received: AtomicU64,
+}
+
+impl ProxyHandle {
+    /// Constructs new handle.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                active: watch::channel(0).0,
+                abort: watch::channel(false).0,
+                draining: AtomicBool::new(false),
+                connections: AtomicU64::new(0),
+                failed: AtomicU64::new(0),
+                sent: AtomicU64::new(0),
+                received: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Returns snapshot of the metrics of all proxies using this handle.
+    pub fn metrics(&self) -> ProxyMetrics {
+        let shared = &self.shared;
+
+        ProxyMetrics {
+            active: *shared.active.borrow(),
+            connections: shared.connections.load(Ordering::Relaxed),
+            failed: shared.failed.load(Ordering::Relaxed),
+            sent: shared.sent.load(Ordering::Relaxed),
+            received: shared.received.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Returns true if [`drain`](Self::drain) has been called.
+    pub fn is_draining(&self) -> bool {
+        self.shared.draining.load(Ordering::Acquire)
+    }
+
+    /// Stops proxying new connections and waits for active ones to finish.
+    ///
+    /// New connections are closed right away with [`ProxyError::Draining`]. Connections still
+    /// active after `timeout` are aborted with [`ProxyError::Aborted`].
+    ///
+    /// Returns true if all connections finished before the timeout.
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        self.shared.draining.store(true, Ordering::Release);
+
+        let mut active = self.shared.active.subscribe();
+        let idle = async {
+            while *active.borrow_and_update() > 0 {
+                // sender lives as long as `self`
+                let _ = active.changed().await;
+            }
+        };
+
+        let graceful = actix_rt::time::timeout(timeout, idle).await.is_ok();
+
+        if !graceful {
+            self.shared.abort.send_replace(true);
+        }
+
+        graceful
+    }
+}
+
+impl fmt::Debug for ProxyHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxyHandle")
+            .field("metrics", &self.metrics())
+            .field("draining", &self.is_draining())
+            .finish()
+    }
+}
+
+/// Marks a connection as active while alive.
+struct Active<'a>(&'a Shared);
+
+impl<'a> Active<'a> {
+    fn new(shared: &'a Shared) -> Self {
+        shared.active.send_modify(|active| *active += 1);
+        Self(shared)
+    }
+}
+
+impl Drop for Active<'_> {
+    fn drop(&mut self) {
+        self.0.active.send_modify(|active| *active -= 1);
+    }
+}
+
+/// Snapshot of proxy metrics, returned by [`ProxyHandle::metrics`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProxyMetrics {
+    active: usize,
+    connections: u64,
+    failed: u64,
+    sent: u64,
+    received: u64,
+}
+
+impl ProxyMetrics {
+    /// Returns number of connections currently being handled, including ones still dialing.
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// Returns number of connections that were connected to an upstream.
+    pub fn connections(&self) -> u64 {
+        self.connections
+    }
+
+    /// Returns number of connections that could not be routed or connected to an upstream.
+    pub fn failed(&self) -> u64 {
+        self.failed
+    }
+
+    /// Returns number of bytes sent from clients to upstreams.
+    ///
+    /// Only includes connections that were closed cleanly.
+    pub fn sent(&self) -> u64 {
+        self.sent
+    }
+
+    /// Returns number of bytes sent from upstreams to clients.
+    ///
+    /// Only includes connections that were closed cleanly.
+    pub fn received(&self) -> u64 {
+        self.received
+    }
+}
+
+/// Errors of [`ProxyService`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ProxyError {
+    /// Route callback did not choose an upstream.
+    NoRoute,
+
+    /// Connecting to the upstream timed out.
+    Timeout,
+
+    /// Connecting to the upstream failed.
+    Connect(ConnectError),
+
+    /// Reading from or writing to either side failed.
+    Io(io::Error),
+
+    /// Proxy is draining and does not accept new connections.
+    Draining,
+
+    /// Connection was aborted at the end of draining.
+    Aborted,
+}
+
+impl fmt::Display for ProxyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoRoute => f.write_str("no upstream for connection"),
+            Self::Timeout => f.write_str("connecting to upstream timed out"),
+            Self::Connect(_) => f.write_str("can not connect to upstream"),
+            Self::Io(_) => f.write_str("I/O error while proxying"),
+            Self::Draining => f.write_str("proxy is draining"),
+            Self::Aborted => f.write_str("connection aborted while draining"),
+        }
+    }
+}
+
+impl Error for ProxyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Connect(err) => Some(err),
+            Self::Io(err) => Some(err),
+            Self::NoRoute | Self::Timeout | Self::Draining | Self::Aborted => None,
+        }
+    }
+}
@@ -0,0 +1,335 @@
+//! Registry of active connections for introspection.
+//!
+//! See [`ServerHandle::connections`](crate::ServerHandle::connections) for main docs.
+
+use std::{
+    collections::BTreeMap,
+    fmt, io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_rt::time::Instant;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::preprocess::ConnectionTags;
+
+/// Active connections of all workers, shared with the server for answering queries.
+#[derive(Clone, Default)]
+pub(crate) struct ConnectionRegistry {
+    entries: Arc<Mutex<Entries>>,
+}
+
+#[derive(Default)]
+struct Entries {
+    next_id: u64,
+    // ordered by ID, so snapshots list connections in the order they were accepted
+    map: BTreeMap<u64, Entry>,
+}
+
+struct Entry {
+    listener: String,
+    peer_addr: Option<SocketAddr>,
+    worker: usize,
+    since: Instant,
+    bytes: Arc<ByteCounters>,
+}
+
+#[derive(Default)]
+struct ByteCounters {
+    instrumented: AtomicBool,
+    read: AtomicU64,
+    written: AtomicU64,
+}
+
+impl ConnectionRegistry {
+    /// Adds connection accepted by `listener` to the registry until the returned registration is
+    /// dropped.
+    pub(crate) fn register(
+        &self,
+        listener: &str,
+        peer_addr: Option<SocketAddr>,
+        worker: usize,
+        since: Instant,
+    ) -> Registration {
+        let bytes = Arc::new(ByteCounters::default());
+
+        let mut entries = self.entries.lock().unwrap();
+        let id = entries.next_id;
+        entries.next_id += 1;
+
+        entries.map.insert(
+            id,
+            Entry {
+                listener: listener.to_owned(),
+                peer_addr,
+                worker,
+                since,
+                bytes: Arc::clone(&bytes),
+            },
+        );
+
+        Registration {
+            registry: self.clone(),
+            id,
+            bytes,
+        }
+    }
+
+    /// Returns info about all registered connections, with ages as of `now`.
+    pub(crate) fn snapshot(&self, now: Instant) -> Vec<ActiveConnection> {
+        self.entries
+            .lock()
+            .unwrap()
+            .map
+            .values()
+            .map(|entry| {
+                let bytes = &entry.bytes;
+
+                ActiveConnection {
+                    listener: entry.listener.clone(),
+                    peer_addr: entry.peer_addr,
+                    worker: entry.worker,
+                    age: now.saturating_duration_since(entry.since),
+                    bytes: bytes.instrumented.load(Ordering::Relaxed).then(|| {
+                        (
+                            bytes.read.load(Ordering::Relaxed),
+                            bytes.written.load(Ordering::Relaxed),
+                        )
+                    }),
+                }
+            })
+            .collect()
+    }
+}
+
+impl fmt::Debug for ConnectionRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConnectionRegistry")
+            .field("connections", &self.entries.lock().unwrap().map.len())
+            .finish()
+    }
+}
+
+/// Registered connection, stored in its tags. Removes connection from registry when dropped.
+pub(crate) struct Registration {
+    registry: ConnectionRegistry,
+    id: u64,
+    bytes: Arc<ByteCounters>,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        self.registry.entries.lock().unwrap().map.remove(&self.id);
+    }
+}
+
+/// Info about an active connection, returned by
+/// [`ServerHandle::connections`](crate::ServerHandle::connections).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveConnection {
+    listener: String,
+    peer_addr: Option<SocketAddr>,
+    worker: usize,
+    age: Duration,
+    bytes: Option<(u64, u64)>,
+}
+
+impl ActiveConnection {
+    /// Returns the name of the listener that accepted the connection.
+    pub fn listener(&self) -> &str {
+        &self.listener
+    }
+
+    /// Returns the peer address of TCP connections.
+    ///
+    /// Returns `None` for Unix domain sockets or if the address could not be retrieved.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        self.peer_addr
+    }
+
+    /// Returns the index of the worker handling the connection.
+    pub fn worker(&self) -> usize {
+        self.worker
+    }
+
+    /// Returns the time since the connection was received by its worker.
+    pub fn age(&self) -> Duration {
+        self.age
+    }
+
+    /// Returns the number of bytes read from the connection.
+    ///
+    /// Returns `None` unless the service wraps the stream in a [`TrackedStream`].
+    pub fn bytes_read(&self) -> Option<u64> {
+        self.bytes.map(|(read, _)| read)
+    }
+
+    /// Returns the number of bytes written to the connection.
+    ///
+    /// Returns `None` unless the service wraps the stream in a [`TrackedStream`].
+    pub fn bytes_written(&self) -> Option<u64> {
+        self.bytes.map(|(_, written)| written)
+    }
+}
+
+/// Stream that counts bytes read and written for the connection registry.
+///
+/// Services can wrap their streams to have byte counts reported by
+/// [`ServerHandle::connections`](crate::ServerHandle::connections). Streams wrapped outside of a
+/// connection tracked by the registry are passed through without counting.
+///
+/// # Examples
+/// ```
+/// use actix_rt::net::TcpStream;
+/// use actix_server::{Server, TrackedStream};
+/// use actix_service::fn_service;
+/// use tokio::io::AsyncWriteExt as _;
+///
+/// # fn build() -> std::io::Result<Server> {
+/// let srv = Server::build()
+///     .track_connections()
+///     .bind("app", ("127.0.0.1", 8080), || {
+///         fn_service(|stream: TcpStream| async move {
+///             let mut stream = TrackedStream::new(stream);
+///             stream.write_all(b"hello").await
+///         })
+///     })?
+///     .run();
+/// # Ok(srv)
+/// # }
+/// ```
+pub struct TrackedStream<S> {
+    io: S,
+    bytes: Option<Arc<ByteCounters>>,
+}
+
+impl<S> TrackedStream<S> {
+    /// Wraps `io`, counting bytes for the connection being handled, if tracked.
+    pub fn new(io: S) -> Self {
+        let bytes = ConnectionTags::current().and_then(|tags| {
+            let bytes = Arc::clone(&tags.get::<Registration>()?.bytes);
+            bytes.instrumented.store(true, Ordering::Relaxed);
+            Some(bytes)
+        });
+
+        Self { io, bytes }
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.io
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    ///
+    /// Bytes read or written directly are not counted.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.io
+    }
+
+    /// Returns the underlying stream.
+    pub fn into_inner(self) -> S {
+        self.io
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for TrackedStream<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TrackedStream")
+            .field("io", &self.io)
+            .field("tracked", &self.bytes.is_some())
+            .finish()
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for TrackedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        let filled = buf.filled().len();
+
+        let res = Pin::new(&mut this.io).poll_read(cx, buf);
+
+        if let Some(bytes) = &this.bytes {
+            let read = buf.filled().len() - filled;
+            bytes.read.fetch_add(read as u64, Ordering::Relaxed);
+        }
+
+        res
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for TrackedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let res = Pin::new(&mut this.io).poll_write(cx, buf);
+
+        if let (Some(bytes), Poll::Ready(Ok(written))) = (&this.bytes, &res) {
+            bytes.written.fetch_add(*written as u64, Ordering::Relaxed);
+        }
+
+        res
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn register_and_snapshot() {
+        let registry = ConnectionRegistry::default();
+        let start = Instant::now();
+        let peer = "127.0.0.1:1234".parse().unwrap();
+
+        let first = registry.register("a", Some(peer), 0, start);
+        let second = registry.register("b", None, 1, start + Duration::from_secs(1));
+
+        first.bytes.instrumented.store(true, Ordering::Relaxed);
+        first.bytes.read.store(3, Ordering::Relaxed);
+
+        let conns = registry.snapshot(start + Duration::from_secs(5));
+        assert_eq!(conns.len(), 2);
+
+        assert_eq!(conns[0].listener(), "a");
+        assert_eq!(conns[0].peer_addr(), Some(peer));
+        assert_eq!(conns[0].worker(), 0);
+        assert_eq!(conns[0].age(), Duration::from_secs(5));
+        assert_eq!(conns[0].bytes_read(), Some(3));
+        assert_eq!(conns[0].bytes_written(), Some(0));
+
+        assert_eq!(conns[1].listener(), "b");
+        assert_eq!(conns[1].age(), Duration::from_secs(4));
+        assert_eq!(conns[1].bytes_read(), None);
+
+        drop(first);
+        let conns = registry.snapshot(start);
+        assert_eq!(conns.len(), 1);
+        assert_eq!(conns[0].listener(), "b");
+
+        drop(second);
+        assert!(registry.snapshot(start).is_empty());
+    }
+}
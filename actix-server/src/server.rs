@@ -17,8 +17,10 @@ use crate::{
     accept::Accept,
     builder::ServerBuilder,
     join_all::join_all,
+    registry::ActiveConnection,
     service::InternalServiceFactory,
     signals::{SignalKind, Signals},
+    socket::ListenerInfo,
     waker_queue::{WakerInterest, WakerQueue},
     worker::{ServerWorker, ServerWorkerConfig, WorkerHandleServer},
     ServerHandle,
@@ -41,6 +43,12 @@ pub(crate) enum ServerCommand {
     /// Contains return channel to notify caller of successful state change.
     Resume(oneshot::Sender<()>),
 
+    /// Return listener addresses and socket options.
+    Listeners(oneshot::Sender<Vec<ListenerInfo>>),
+
+    /// Return active connections tracked by the connection registry.
+    Connections(oneshot::Sender<Vec<ActiveConnection>>),
+
     /// Stop accepting connections and begin shutdown procedure.
     Stop {
         /// True if shut down should be graceful.
@@ -162,6 +170,7 @@ pub struct ServerInner {
     accept_handle: Option<thread::JoinHandle<()>>,
     worker_config: ServerWorkerConfig,
     services: Vec<Box<dyn InternalServiceFactory>>,
+    listeners: Vec<ListenerInfo>,
     waker_queue: WakerQueue,
     system_stop: bool,
     stopping: bool,
@@ -183,6 +192,14 @@ impl ServerInner {
     }
 
     fn run_sync(mut builder: ServerBuilder) -> io::Result<(Self, ServerEventMultiplexer)> {
+        let listeners = builder
+            .sockets
+            .iter()
+            .map(|(token, name, lst)| {
+                ListenerInfo::new(name, lst, builder.backlogs.get(token).copied())
+            })
+            .collect();
+
         let sockets = mem::take(&mut builder.sockets)
             .into_iter()
             .map(|t| (t.0, t.2))
@@ -220,6 +237,7 @@ impl ServerInner {
             worker_handles,
             worker_config: builder.worker_config,
             services: builder.factories,
+            listeners,
             system_stop: builder.exit,
             stopping: false,
         };
@@ -239,6 +257,14 @@ impl ServerInner {
                 let _ = tx.send(());
             }
 
+            ServerCommand::Listeners(tx) => {
+                let _ = tx.send(self.listeners.clone());
+            }
+
+            ServerCommand::Connections(tx) => {
+                let _ = tx.send(active_connections(&self.worker_config));
+            }
+
             ServerCommand::Stop {
                 graceful,
                 completion,
@@ -295,7 +321,7 @@ impl ServerInner {
                     idx,
                     factories,
                     self.waker_queue.clone(),
-                    self.worker_config,
+                    self.worker_config.clone(),
                 ) {
                     Ok((handle_accept, handle_server)) => {
                         *self
@@ -345,6 +371,14 @@ impl ServerInner {
     }
 }
 
+/// Returns connections tracked by the registry of `config`, if enabled.
+pub(crate) fn active_connections(config: &ServerWorkerConfig) -> Vec<ActiveConnection> {
+    match config.connections() {
+        Some((registry, clock)) => registry.snapshot(clock.now()),
+        None => Vec::new(),
+    }
+}
+
 struct ServerEventMultiplexer {
     cmd_rx: UnboundedReceiver<ServerCommand>,
     signal_fut: Option<Signals>,
@@ -7,9 +7,11 @@ use std::{
 use actix_service::{Service, ServiceFactory as BaseServiceFactory};
 use actix_utils::future::{ready, Ready};
 use futures_core::future::LocalBoxFuture;
-use tracing::error;
+use tracing::{error, field, Instrument as _};
 
 use crate::{
+    close::CloseGuard,
+    connection::{self, NewConnection},
     socket::{FromStream, MioStream},
     worker::WorkerCounterGuard,
 };
@@ -31,7 +33,7 @@ pub(crate) trait InternalServiceFactory: Send {
 
 pub(crate) type BoxedServerService = Box<
     dyn Service<
-        (WorkerCounterGuard, MioStream),
+        (WorkerCounterGuard, MioStream, NewConnection),
         Response = (),
         Error = (),
         Future = Ready<Result<(), ()>>,
@@ -39,20 +41,22 @@ pub(crate) type BoxedServerService = Box<
 >;
 
 pub(crate) struct StreamService<S, I> {
+    name: String,
     service: S,
     _phantom: PhantomData<I>,
 }
 
 impl<S, I> StreamService<S, I> {
-    pub(crate) fn new(service: S) -> Self {
+    pub(crate) fn new(name: String, service: S) -> Self {
         StreamService {
+            name,
             service,
             _phantom: PhantomData,
         }
     }
 }
 
-impl<S, I> Service<(WorkerCounterGuard, MioStream)> for StreamService<S, I>
+impl<S, I> Service<(WorkerCounterGuard, MioStream, NewConnection)> for StreamService<S, I>
 where
     S: Service<I>,
     S::Future: 'static,
@@ -67,13 +71,39 @@ where
         self.service.poll_ready(ctx).map_err(|_| ())
     }
 
-    fn call(&self, (guard, req): (WorkerCounterGuard, MioStream)) -> Self::Future {
+    fn call(
+        &self,
+        (guard, req, conn): (WorkerCounterGuard, MioStream, NewConnection),
+    ) -> Self::Future {
+        // child of the worker span, which is entered while the worker dispatches connections
+        let span = tracing::info_span!(
+            "connection",
+            listener = %self.name,
+            peer = field::Empty,
+            close_reason = field::Empty,
+        );
+
+        // avoid looking up the peer address when nothing is listening
+        if !span.is_disabled() {
+            span.record("peer", field::display(req.peer_addr()));
+        }
+
         ready(match FromStream::from_mio(req) {
             Ok(stream) => {
-                let f = self.service.call(stream);
-                actix_rt::spawn(async move {
-                    let _ = f.await;
-                    drop(guard);
+                connection::spawn(conn, || {
+                    let close = CloseGuard::current(&span);
+                    let f = span.in_scope(|| self.service.call(stream));
+
+                    async move {
+                        let res = f.await;
+
+                        if let Some(close) = &close {
+                            close.finished(&res);
+                        }
+
+                        drop(guard);
+                    }
+                    .instrument(span)
                 });
                 Ok(())
             }
@@ -135,11 +165,12 @@ where
 
     fn create(&self) -> LocalBoxFuture<'static, Result<(usize, BoxedServerService), ()>> {
         let token = self.token;
+        let name = self.name.clone();
         let fut = self.inner.create().new_service(());
         Box::pin(async move {
             match fut.await {
                 Ok(inner) => {
-                    let service = Box::new(StreamService::new(inner)) as _;
+                    let service = Box::new(StreamService::new(name, inner)) as _;
                     Ok((token, service))
                 }
                 Err(_) => Err(()),
@@ -151,6 +151,132 @@ impl fmt::Debug for SocketAddr {
     }
 }
 
+/// Address and socket options of a listener, as reported by the OS when the server started.
+///
+/// Retrieved using [`ServerHandle::listeners`](crate::ServerHandle::listeners).
+#[derive(Debug, Clone)]
+pub struct ListenerInfo {
+    name: String,
+    local_addr: Option<StdSocketAddr>,
+    #[cfg(unix)]
+    uds_path: Option<std::path::PathBuf>,
+    backlog: Option<u32>,
+    reuse_address: Option<bool>,
+    reuse_port: Option<bool>,
+    only_v6: Option<bool>,
+}
+
+impl ListenerInfo {
+    pub(crate) fn new(name: &str, lst: &MioListener, backlog: Option<u32>) -> Self {
+        match lst {
+            MioListener::Tcp(lst) => {
+                #[cfg(unix)]
+                // SAFETY: file descriptor is owned by the listener, which outlives the borrow
+                let fd = unsafe {
+                    use std::os::unix::io::{AsRawFd as _, BorrowedFd};
+                    BorrowedFd::borrow_raw(lst.as_raw_fd())
+                };
+
+                #[cfg(windows)]
+                // SAFETY: socket is owned by the listener, which outlives the borrow
+                let fd = unsafe {
+                    use std::os::windows::io::{AsRawSocket as _, BorrowedSocket};
+                    BorrowedSocket::borrow_raw(lst.as_raw_socket())
+                };
+
+                let sock = socket2::SockRef::from(&fd);
+                let local_addr = lst.local_addr().ok();
+
+                Self {
+                    name: name.to_owned(),
+                    local_addr,
+                    #[cfg(unix)]
+                    uds_path: None,
+                    backlog,
+                    reuse_address: sock.reuse_address().ok(),
+                    reuse_port: reuse_port(&sock),
+                    only_v6: local_addr
+                        .filter(StdSocketAddr::is_ipv6)
+                        .and_then(|_| sock.only_v6().ok()),
+                }
+            }
+
+            #[cfg(unix)]
+            MioListener::Uds(lst) => Self {
+                name: name.to_owned(),
+                local_addr: None,
+                uds_path: lst
+                    .local_addr()
+                    .ok()
+                    .and_then(|addr| addr.as_pathname().map(ToOwned::to_owned)),
+                backlog,
+                reuse_address: None,
+                reuse_port: None,
+                only_v6: None,
+            },
+        }
+    }
+
+    /// Returns the name of the service the listener was bound for.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the local address of TCP listeners.
+    pub fn local_addr(&self) -> Option<StdSocketAddr> {
+        self.local_addr
+    }
+
+    /// Returns the assigned port of TCP listeners, which is useful when binding to port 0.
+    pub fn port(&self) -> Option<u16> {
+        self.local_addr.map(|addr| addr.port())
+    }
+
+    /// Returns the file system path of Unix domain socket listeners, unless they are unnamed.
+    #[cfg(unix)]
+    pub fn uds_path(&self) -> Option<&std::path::Path> {
+        self.uds_path.as_deref()
+    }
+
+    /// Returns the backlog the listener was created with.
+    ///
+    /// The OS does not report the backlog of sockets, so this is `None` for listeners that were
+    /// passed in already listening, e.g., using [`ServerBuilder::listen`]. Note that the OS may
+    /// silently cap the backlog, e.g., to `net.core.somaxconn` on Linux.
+    ///
+    /// [`ServerBuilder::listen`]: crate::ServerBuilder::listen
+    pub fn backlog(&self) -> Option<u32> {
+        self.backlog
+    }
+
+    /// Returns whether `SO_REUSEADDR` is set on TCP listeners.
+    pub fn reuse_address(&self) -> Option<bool> {
+        self.reuse_address
+    }
+
+    /// Returns whether `SO_REUSEPORT` is set on TCP listeners.
+    ///
+    /// Returns `None` on platforms that do not support the option.
+    pub fn reuse_port(&self) -> Option<bool> {
+        self.reuse_port
+    }
+
+    /// Returns whether `IPV6_V6ONLY` is set on IPv6 TCP listeners.
+    pub fn only_v6(&self) -> Option<bool> {
+        self.only_v6
+    }
+}
+
+#[cfg(all(unix, not(any(target_os = "solaris", target_os = "illumos"))))]
+fn reuse_port(sock: &socket2::SockRef<'_>) -> Option<bool> {
+    sock.reuse_port().ok()
+}
+
+#[cfg(not(all(unix, not(any(target_os = "solaris", target_os = "illumos")))))]
+fn reuse_port(_sock: &socket2::SockRef<'_>) -> Option<bool> {
+    None
+}
+
 #[derive(Debug)]
 pub enum MioStream {
     Tcp(mio::net::TcpStream),
@@ -158,9 +284,33 @@ pub enum MioStream {
     Uds(mio::net::UnixStream),
 }
 
-/// Helper trait for converting a Mio stream into a Tokio stream.
+impl MioStream {
+    pub(crate) fn peer_addr(&self) -> SocketAddr {
+        match *self {
+            MioStream::Tcp(ref stream) => stream
+                .peer_addr()
+                .map(SocketAddr::Tcp)
+                .unwrap_or(SocketAddr::Unknown),
+            #[cfg(unix)]
+            MioStream::Uds(ref stream) => stream
+                .peer_addr()
+                .map(SocketAddr::Uds)
+                .unwrap_or(SocketAddr::Unknown),
+        }
+    }
+}
+
+/// Helper trait for converting between Mio and Tokio streams.
 pub trait FromStream: Sized {
     fn from_mio(sock: MioStream) -> io::Result<Self>;
+
+    /// Converts back into a Mio stream, used when handing connections off to another worker.
+    fn into_mio(self) -> io::Result<MioStream> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "stream can not be converted into a Mio stream",
+        ))
+    }
 }
 
 #[cfg(windows)]
@@ -180,6 +330,11 @@ mod win_impl {
                 }
             }
         }
+
+        fn into_mio(self) -> io::Result<MioStream> {
+            let std = self.into_std()?;
+            Ok(MioStream::Tcp(mio::net::TcpStream::from_std(std)))
+        }
     }
 }
 
@@ -205,6 +360,11 @@ mod unix_impl {
                 }
             }
         }
+
+        fn into_mio(self) -> io::Result<MioStream> {
+            let std = self.into_std()?;
+            Ok(MioStream::Tcp(mio::net::TcpStream::from_std(std)))
+        }
     }
 
     // HACK: This is a workaround and we need an efficient way to convert between Mio and Tokio stream
@@ -219,13 +379,22 @@ mod unix_impl {
                 }
             }
         }
+
+        fn into_mio(self) -> io::Result<MioStream> {
+            let std = self.into_std()?;
+            Ok(MioStream::Uds(mio::net::UnixStream::from_std(std)))
+        }
     }
 }
 
+/// Hook called with TCP sockets created by the server builder before they are bound.
+pub(crate) type PreBind = Box<dyn Fn(&socket2::Socket, StdSocketAddr) -> io::Result<()> + Send>;
+
 pub(crate) fn create_mio_tcp_listener(
     addr: StdSocketAddr,
     backlog: u32,
     mptcp: &MpTcp,
+    pre_bind: &[PreBind],
 ) -> io::Result<MioTcpListener> {
     use socket2::{Domain, Protocol, Socket, Type};
 
@@ -252,6 +421,11 @@ pub(crate) fn create_mio_tcp_listener(
 
     socket.set_reuse_address(true)?;
     socket.set_nonblocking(true)?;
+
+    for hook in pre_bind {
+        hook(&socket, addr)?;
+    }
+
     socket.bind(&addr.into())?;
     socket.listen(backlog as i32)?;
 
@@ -269,7 +443,7 @@ mod tests {
         assert_eq!(format!("{}", addr), "127.0.0.1:8080");
 
         let addr: StdSocketAddr = "127.0.0.1:0".parse().unwrap();
-        let lst = create_mio_tcp_listener(addr, 128, &MpTcp::Disabled).unwrap();
+        let lst = create_mio_tcp_listener(addr, 128, &MpTcp::Disabled, &[]).unwrap();
         let lst = MioListener::Tcp(lst);
         assert!(format!("{:?}", lst).contains("TcpListener"));
         assert!(format!("{}", lst).contains("127.0.0.1"));
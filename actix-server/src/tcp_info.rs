@@ -0,0 +1,149 @@
+//! Transport statistics of TCP connections.
+
+use std::{io, os::unix::io::AsRawFd, time::Duration};
+
+/// Snapshot of kernel statistics of a TCP connection, read using `TCP_INFO`.
+///
+/// Useful for logging the transport quality of connections, e.g., when debugging reports of slow
+/// responses. Values are only a point-in-time sample; take a new one to observe changes.
+///
+/// Only available on Linux.
+///
+/// # Examples
+/// ```no_run
+/// use actix_rt::net::TcpStream;
+/// use actix_server::{Server, TcpInfo};
+/// use actix_service::fn_service;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let server = Server::build().bind("app", ("127.0.0.1", 8080), || {
+///     fn_service(|stream: TcpStream| async move {
+///         // ... handle request
+///
+///         if let Ok(info) = TcpInfo::sample(&stream) {
+///             tracing::debug!(rtt = ?info.rtt(), retransmits = info.retransmits(), "request done");
+///         }
+///
+///         Ok::<_, ()>(())
+///     })
+/// })?;
+/// # drop(server);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TcpInfo {
+    rtt: Duration,
+    rtt_var: Duration,
+    rto: Duration,
+    cwnd: u32,
+    mss: u32,
+    unacked: u32,
+    lost: u32,
+    retransmits: u32,
+}
+
+impl TcpInfo {
+    /// Samples statistics of the TCP socket `socket`.
+    ///
+    /// Accepts any TCP socket type, e.g., [`TcpStream`](actix_rt::net::TcpStream) or the
+    /// [`SockRef`](socket2::SockRef) of an [`AcceptedSocket`](crate::AcceptedSocket).
+    ///
+    /// # Errors
+    /// Returns an error if `socket` is not a TCP socket.
+    pub fn sample(socket: &impl AsRawFd) -> io::Result<Self> {
+        // SAFETY: `tcp_info` is plain old data, for which zeroed memory is valid
+        let mut info: libc::tcp_info = unsafe { std::mem::zeroed() };
+        let mut len = std::mem::size_of::<libc::tcp_info>() as libc::socklen_t;
+
+        // SAFETY: `info` and `len` are valid for writes and `len` holds the size of `info`
+        let ret = unsafe {
+            libc::getsockopt(
+                socket.as_raw_fd(),
+                libc::IPPROTO_TCP,
+                libc::TCP_INFO,
+                &mut info as *mut libc::tcp_info as *mut libc::c_void,
+                &mut len,
+            )
+        };
+
+        if ret != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Self {
+            rtt: Duration::from_micros(info.tcpi_rtt.into()),
+            rtt_var: Duration::from_micros(info.tcpi_rttvar.into()),
+            rto: Duration::from_micros(info.tcpi_rto.into()),
+            cwnd: info.tcpi_snd_cwnd,
+            mss: info.tcpi_snd_mss,
+            unacked: info.tcpi_unacked,
+            lost: info.tcpi_lost,
+            retransmits: info.tcpi_total_retrans,
+        })
+    }
+
+    /// Returns smoothed round-trip time.
+    pub fn rtt(&self) -> Duration {
+        self.rtt
+    }
+
+    /// Returns variance of the round-trip time.
+    pub fn rtt_var(&self) -> Duration {
+        self.rtt_var
+    }
+
+    /// Returns current retransmission timeout.
+    pub fn rto(&self) -> Duration {
+        self.rto
+    }
+
+    /// Returns sending congestion window, in segments.
+    pub fn cwnd(&self) -> u32 {
+        self.cwnd
+    }
+
+    /// Returns sending maximum segment size, in bytes.
+    pub fn mss(&self) -> u32 {
+        self.mss
+    }
+
+    /// Returns number of sent segments that are not yet acknowledged.
+    pub fn unacked(&self) -> u32 {
+        self.unacked
+    }
+
+    /// Returns number of sent segments currently considered lost.
+    pub fn lost(&self) -> u32 {
+        self.lost
+    }
+
+    /// Returns total number of segments retransmitted over the lifetime of the connection.
+    pub fn retransmits(&self) -> u32 {
+        self.retransmits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_connected_socket() {
+        let lst = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = std::net::TcpStream::connect(lst.local_addr().unwrap()).unwrap();
+        let (_server, _) = lst.accept().unwrap();
+
+        let info = TcpInfo::sample(&client).unwrap();
+        assert!(info.mss() > 0);
+        assert!(info.cwnd() > 0);
+        assert!(info.rto() > Duration::ZERO);
+        assert_eq!(info.retransmits(), 0);
+    }
+
+    #[test]
+    fn rejects_non_tcp_socket() {
+        let (a, _b) = std::os::unix::net::UnixStream::pair().unwrap();
+        assert!(TcpInfo::sample(&a).is_err());
+    }
+}
@@ -0,0 +1,143 @@
+//! Transparent proxying of TCP connections.
+
+use std::{
+    io,
+    net::SocketAddr,
+    os::unix::io::{AsRawFd, BorrowedFd},
+};
+
+use socket2::{SockRef, Socket};
+
+use crate::preprocess::ConnectionTags;
+
+/// Original destination of a TCP connection accepted by a transparent listener.
+///
+/// Listeners bound with [`ServerBuilder::bind_transparent`] accept connections to any address that
+/// the firewall diverts to them, e.g., using an iptables `TPROXY` or `REDIRECT` target. For each
+/// connection, the address the client originally connected to is looked up when it is accepted:
+/// using `SO_ORIGINAL_DST` for connections redirected with NAT, or the local address of the socket
+/// otherwise, which is the original destination of connections diverted with `TPROXY`.
+///
+/// Only available on Linux.
+///
+/// # Examples
+/// ```no_run
+/// use actix_rt::net::TcpStream;
+/// use actix_server::{OriginalDst, Server};
+/// use actix_service::fn_service;
+///
+/// # fn main() -> std::io::Result<()> {
+/// let server = Server::build().bind_transparent("tproxy", ("0.0.0.0", 8080), || {
+///     fn_service(|_stream: TcpStream| async move {
+///         let dst = OriginalDst::current().expect("accepted by transparent listener");
+///         println!("client connected to {}", dst.addr());
+///
+///         // ... connect to `dst` and forward bytes
+///
+///         Ok::<_, ()>(())
+///     })
+/// })?;
+/// # drop(server);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`ServerBuilder::bind_transparent`]: crate::ServerBuilder::bind_transparent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OriginalDst(SocketAddr);
+
+impl OriginalDst {
+    /// Returns the original destination of the connection being handled.
+    ///
+    /// Available while calling the service with an accepted stream and while its future runs.
+    /// Returns `None` elsewhere, for connections not accepted by a transparent listener, or if the
+    /// original destination could not be looked up.
+    pub fn current() -> Option<Self> {
+        ConnectionTags::current()?.get::<Self>().copied()
+    }
+
+    /// Looks up the original destination of the accepted TCP socket `socket`.
+    ///
+    /// Accepts any TCP socket type, e.g., [`TcpStream`](actix_rt::net::TcpStream) or the
+    /// [`SockRef`] of an [`AcceptedSocket`](crate::AcceptedSocket), so it is also usable with
+    /// listeners set up outside of the server builder.
+    ///
+    /// # Errors
+    /// Returns an error if `socket` is not a TCP socket.
+    pub fn lookup(socket: &impl AsRawFd) -> io::Result<Self> {
+        // SAFETY: file descriptor is owned by `socket`, which outlives the borrow
+        let fd = unsafe { BorrowedFd::borrow_raw(socket.as_raw_fd()) };
+        let socket = SockRef::from(&fd);
+
+        let local = socket
+            .local_addr()?
+            .as_socket()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "not an IP socket"))?;
+
+        let redirected = if local.is_ipv4() {
+            socket.original_dst()
+        } else {
+            socket.original_dst_ipv6()
+        };
+
+        match redirected {
+            Ok(addr) => Ok(Self(addr.as_socket().unwrap_or(local))),
+
+            // connection was not redirected with NAT or connection tracking is not enabled
+            Err(err) if matches!(err.raw_os_error(), Some(libc::ENOENT | libc::ENOPROTOOPT)) => {
+                Ok(Self(local))
+            }
+
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the address the client originally connected to.
+    pub fn addr(&self) -> SocketAddr {
+        self.0
+    }
+}
+
+/// Allows socket about to be bound to `addr` to accept connections to non-local addresses.
+pub(crate) fn set_transparent(socket: &Socket, addr: SocketAddr) -> io::Result<()> {
+    if addr.is_ipv4() {
+        return socket.set_ip_transparent(true);
+    }
+
+    let enable: libc::c_int = 1;
+
+    // SAFETY: `enable` is valid for reads and the length passed is its size
+    let ret = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_IPV6,
+            libc::IPV6_TRANSPARENT,
+            &enable as *const libc::c_int as *const libc::c_void,
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{TcpListener, TcpStream};
+
+    use super::*;
+
+    #[test]
+    fn lookup_without_redirect() {
+        let lst = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = lst.local_addr().unwrap();
+
+        let _client = TcpStream::connect(addr).unwrap();
+        let (server, _) = lst.accept().unwrap();
+
+        assert_eq!(OriginalDst::lookup(&server).unwrap().addr(), addr);
+    }
+}
@@ -6,14 +6,49 @@ use std::{
 
 use mio::{Registry, Token as MioToken, Waker};
 
-use crate::worker::WorkerHandleAccept;
+use crate::{
+    availability::{Availability, SharedAvailability},
+    worker::WorkerHandleAccept,
+};
 
 /// Waker token for `mio::Poll` instance.
 pub(crate) const WAKER_TOKEN: MioToken = MioToken(usize::MAX);
 
 /// `mio::Waker` with a queue for waking up the `Accept`'s `Poll` and contains the `WakerInterest`
 /// the `Poll` would want to look into.
-pub(crate) struct WakerQueue(Arc<(Waker, Mutex<VecDeque<WakerInterest>>)>);
+///
+/// Workers becoming available are tracked separately in a [`SharedAvailability`] so that they
+/// neither lock the queue nor wake up `Accept` more than once until it has looked into them.
+pub(crate) struct WakerQueue(
+    Arc<(
+        AcceptWaker,
+        Mutex<VecDeque<WakerInterest>>,
+        SharedAvailability,
+    )>,
+);
+
+/// Means of waking up `Accept`.
+pub(crate) enum AcceptWaker {
+    /// Wakes up `Accept` thread blocked on its `Poll`.
+    Poll(Waker),
+
+    /// Wakes up accept task of an embedded server.
+    #[cfg(feature = "embedded")]
+    Task(tokio::sync::Notify),
+}
+
+impl AcceptWaker {
+    fn wake(&self) {
+        match self {
+            Self::Poll(waker) => waker
+                .wake()
+                .unwrap_or_else(|e| panic!("can not wake up Accept Poll: {}", e)),
+
+            #[cfg(feature = "embedded")]
+            Self::Task(notify) => notify.notify_one(),
+        }
+    }
+}
 
 impl Clone for WakerQueue {
     fn clone(&self) -> Self {
@@ -22,7 +57,11 @@ impl Clone for WakerQueue {
 }
 
 impl Deref for WakerQueue {
-    type Target = (Waker, Mutex<VecDeque<WakerInterest>>);
+    type Target = (
+        AcceptWaker,
+        Mutex<VecDeque<WakerInterest>>,
+        SharedAvailability,
+    );
 
     fn deref(&self) -> &Self::Target {
         self.0.deref()
@@ -38,21 +77,60 @@ impl WakerQueue {
         let waker = Waker::new(registry, WAKER_TOKEN)?;
         let queue = Mutex::new(VecDeque::with_capacity(16));
 
-        Ok(Self(Arc::new((waker, queue))))
+        Ok(Self(Arc::new((
+            AcceptWaker::Poll(waker),
+            queue,
+            SharedAvailability::default(),
+        ))))
+    }
+
+    /// Construct a waker queue waking up the accept task of an embedded server.
+    ///
+    /// Only workers becoming available are tracked; interests are never pushed to the queue.
+    #[cfg(feature = "embedded")]
+    pub(crate) fn new_task() -> Self {
+        Self(Arc::new((
+            AcceptWaker::Task(tokio::sync::Notify::new()),
+            Mutex::new(VecDeque::new()),
+            SharedAvailability::default(),
+        )))
+    }
+
+    /// Wait until the accept task is woken up.
+    ///
+    /// Never resolves for waker queues not [constructed for tasks](Self::new_task).
+    #[cfg(feature = "embedded")]
+    pub(crate) async fn woken(&self) {
+        match &self.deref().0 {
+            AcceptWaker::Task(notify) => notify.notified().await,
+            AcceptWaker::Poll(_) => std::future::pending().await,
+        }
     }
 
     /// Push a new interest to the queue and wake up the accept poll afterwards.
     pub(crate) fn wake(&self, interest: WakerInterest) {
-        let (waker, queue) = self.deref();
+        let (waker, queue, _) = self.deref();
 
         queue
             .lock()
             .expect("Failed to lock WakerQueue")
             .push_back(interest);
 
-        waker
-            .wake()
-            .unwrap_or_else(|e| panic!("can not wake up Accept Poll: {}", e));
+        waker.wake();
+    }
+
+    /// Mark worker as available and wake up the accept poll unless it already has been.
+    pub(crate) fn wake_available(&self, idx: usize) {
+        let (waker, _, avail) = self.deref();
+
+        if avail.set_available(idx) {
+            waker.wake();
+        }
+    }
+
+    /// Move workers marked as available into `avail` and return true if there were any.
+    pub(crate) fn take_available(&self, avail: &mut Availability) -> bool {
+        self.deref().2.take(avail)
     }
 
     /// Get a MutexGuard of the waker queue.
@@ -62,7 +140,7 @@ impl WakerQueue {
 
     /// Reset the waker queue so it does not grow infinitely.
     pub(crate) fn reset(queue: &mut VecDeque<WakerInterest>) {
-        std::mem::swap(&mut VecDeque::<WakerInterest>::with_capacity(16), queue);
+        *queue = VecDeque::<WakerInterest>::with_capacity(16);
     }
 }
 
@@ -70,9 +148,6 @@ impl WakerQueue {
 ///
 /// These interests should not be confused with `mio::Interest` and mostly not I/O related
 pub(crate) enum WakerInterest {
-    /// `WorkerAvailable` is an interest from `Worker` notifying `Accept` there is a worker
-    /// available and can accept new tasks.
-    WorkerAvailable(usize),
     /// `Pause`, `Resume`, `Stop` Interest are from `ServerBuilder` future. It listens to
     /// `ServerCommand` and notify `Accept` to do exactly these tasks.
     Pause,
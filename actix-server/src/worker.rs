@@ -1,10 +1,11 @@
 use std::{
+    fmt,
     future::Future,
     io, mem,
     pin::Pin,
     rc::Rc,
     sync::{
-        atomic::{AtomicUsize, Ordering},
+        atomic::{AtomicBool, AtomicUsize, Ordering},
         Arc,
     },
     task::{Context, Poll},
@@ -16,17 +17,27 @@ use actix_rt::{
     time::{sleep, Instant, Sleep},
     Arbiter, ArbiterHandle, System,
 };
+use actix_utils::clock::{Clock, RuntimeClock};
 use futures_core::{future::LocalBoxFuture, ready};
 use tokio::sync::{
     mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
     oneshot,
 };
-use tracing::{error, info, trace};
+use tracing::{debug, error, info, trace, Instrument as _};
 
+#[cfg(target_os = "linux")]
+use crate::OriginalDst;
+#[cfg(unix)]
+use crate::PeerCredentials;
 use crate::{
+    close::CloseReasonCounter,
+    connection::ConnectionScope,
+    handoff::{Handoff, HandoffRegistry, HandoffState, ReceivedState},
+    preprocess::{self, ConnectionTags, Preprocessor},
+    registry::ConnectionRegistry,
     service::{BoxedServerService, InternalServiceFactory},
     socket::MioStream,
-    waker_queue::{WakerInterest, WakerQueue},
+    waker_queue::WakerQueue,
 };
 
 /// Stop worker message. Returns `true` on successful graceful shutdown
@@ -40,6 +51,12 @@ pub(crate) struct Stop {
 pub(crate) struct Conn {
     pub io: MioStream,
     pub token: usize,
+
+    /// State sent along with connections handed off by another worker.
+    pub state: Option<HandoffState>,
+
+    /// Time of dispatch by `Accept`, recorded if stale connections are shed.
+    pub dispatched_at: Option<Instant>,
 }
 
 /// Create accept and server worker handles.
@@ -48,11 +65,13 @@ fn handle_pair(
     conn_tx: UnboundedSender<Conn>,
     stop_tx: UnboundedSender<Stop>,
     counter: Counter,
+    clock: Option<Arc<dyn Clock>>,
 ) -> (WorkerHandleAccept, WorkerHandleServer) {
     let accept = WorkerHandleAccept {
         idx,
         conn_tx,
         counter,
+        clock,
     };
 
     let server = WorkerHandleServer { idx, stop_tx };
@@ -72,15 +91,24 @@ fn handle_pair(
 /// unable to accept any work.
 ///
 /// `ServerWorker` always decrement the counter when every work received from `Accept` is done.
-/// On reaching counter limit worker would use `mio::Waker` and `WakerQueue` to wake up `Accept`
-/// and notify it to update cached `Availability` again to mark worker as able to accept work again.
+/// On reaching counter limit worker would mark itself available in `WakerQueue` and wake up
+/// `Accept`, unless already woken up, to update cached `Availability` again to mark worker as able
+/// to accept work again.
 ///
 /// Hence, a wake up would only happen after `Accept` increment it to limit.
 /// And a decrement to limit always wake up `Accept`.
+///
+/// # Overload:
+///
+/// `ServerWorker` also marks itself as overloaded while any of its services is not ready, e.g.,
+/// because a TLS acceptor has reached its handshake limit. `Accept` stops dispatching work to an
+/// overloaded worker, leaving connections in the listener backlog instead of the worker's queue.
+/// Clearing the overload wakes up `Accept` unless the counter is at its limit.
 #[derive(Clone)]
 pub(crate) struct Counter {
     counter: Arc<AtomicUsize>,
     limit: usize,
+    overloaded: Arc<AtomicBool>,
 }
 
 impl Counter {
@@ -88,9 +116,33 @@ impl Counter {
         Self {
             counter: Arc::new(AtomicUsize::new(1)),
             limit,
+            overloaded: Arc::new(AtomicBool::new(false)),
         }
     }
 
+    /// Return true if worker has marked itself as overloaded.
+    #[inline(always)]
+    pub(crate) fn is_overloaded(&self) -> bool {
+        self.overloaded.load(Ordering::Acquire)
+    }
+
+    /// Set overload state and return the previous state.
+    fn set_overloaded(&self, overloaded: bool) -> bool {
+        self.overloaded.swap(overloaded, Ordering::AcqRel)
+    }
+
+    /// Increment counter by 1 unless it has hit limit, returning true if it was incremented.
+    ///
+    /// Unlike [`inc`](Self::inc), never increments past the limit so that the increment seen by
+    /// `Accept` when it hits limit can not be missed.
+    pub(crate) fn try_inc(&self) -> bool {
+        self.counter
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |num| {
+                (num < self.limit).then_some(num + 1)
+            })
+            .is_ok()
+    }
+
     /// Increment counter by 1 and return true when hitting limit
     #[inline(always)]
     pub(crate) fn inc(&self) -> bool {
@@ -103,6 +155,11 @@ impl Counter {
         self.counter.fetch_sub(1, Ordering::Relaxed) == self.limit
     }
 
+    /// Return true if counter is below limit, i.e., a decrement would not cross it.
+    fn is_below_limit(&self) -> bool {
+        self.counter.load(Ordering::Relaxed) < self.limit
+    }
+
     pub(crate) fn total(&self) -> usize {
         self.counter.load(Ordering::SeqCst) - 1
     }
@@ -138,6 +195,22 @@ impl WorkerCounter {
     fn total(&self) -> usize {
         self.inner.1.total()
     }
+
+    /// Mark worker as overloaded so `Accept` stops dispatching work to it.
+    fn overloaded(&self) {
+        self.inner.1.set_overloaded(true);
+    }
+
+    /// Clear overload of worker, waking up `Accept` if it was overloaded.
+    ///
+    /// A worker at its connection limit is left unavailable; `Accept` is woken once a connection
+    /// completes and the counter crosses the limit, as with [`WorkerCounterGuard`].
+    fn relieved(&self) {
+        let (waker_queue, counter) = &*self.inner;
+        if counter.set_overloaded(false) && counter.is_below_limit() {
+            waker_queue.wake_available(self.idx);
+        }
+    }
 }
 
 pub(crate) struct WorkerCounterGuard(WorkerCounter);
@@ -146,7 +219,7 @@ impl Drop for WorkerCounterGuard {
     fn drop(&mut self) {
         let (waker_queue, counter) = &*self.0.inner;
         if counter.dec() {
-            waker_queue.wake(WakerInterest::WorkerAvailable(self.0.idx));
+            waker_queue.wake_available(self.0.idx);
         }
     }
 }
@@ -159,6 +232,7 @@ pub(crate) struct WorkerHandleAccept {
     idx: usize,
     conn_tx: UnboundedSender<Conn>,
     counter: Counter,
+    clock: Option<Arc<dyn Clock>>,
 }
 
 impl WorkerHandleAccept {
@@ -168,7 +242,12 @@ impl WorkerHandleAccept {
     }
 
     #[inline(always)]
-    pub(crate) fn send(&self, conn: Conn) -> Result<(), Conn> {
+    pub(crate) fn send(&self, mut conn: Conn) -> Result<(), Conn> {
+        if let Some(clock) = &self.clock {
+            // keep time of first attempt when dispatch to another worker failed
+            conn.dispatched_at.get_or_insert_with(|| clock.now());
+        }
+
         self.conn_tx.send(conn).map_err(|msg| msg.0)
     }
 
@@ -176,6 +255,11 @@ impl WorkerHandleAccept {
     pub(crate) fn inc_counter(&self) -> bool {
         self.counter.inc()
     }
+
+    #[inline(always)]
+    pub(crate) fn is_overloaded(&self) -> bool {
+        self.counter.is_overloaded()
+    }
 }
 
 /// Handle to worker than can send stop message to worker.
@@ -206,8 +290,18 @@ pub(crate) struct ServerWorker {
     counter: WorkerCounter,
     services: Box<[WorkerService]>,
     factories: Box<[Box<dyn InternalServiceFactory>]>,
+    preprocessors: Box<[Preprocessor]>,
+    handoff: Option<HandoffRegistry>,
+    max_dispatch_age: Option<(Duration, Arc<dyn Clock>)>,
+    connections: Option<(ConnectionRegistry, Arc<dyn Clock>)>,
+    #[cfg(target_os = "linux")]
+    transparent: Box<[usize]>,
     state: WorkerState,
     shutdown_timeout: Duration,
+    scope: ConnectionScope,
+
+    /// Worker runs as a task on the current thread rather than on its own arbiter.
+    local: bool,
 }
 
 struct WorkerService {
@@ -223,9 +317,10 @@ impl WorkerService {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 enum WorkerServiceStatus {
     Available,
+    #[default]
     Unavailable,
     Failed,
     Restarting,
@@ -233,18 +328,46 @@ enum WorkerServiceStatus {
     Stopped,
 }
 
-impl Default for WorkerServiceStatus {
-    fn default() -> Self {
-        Self::Unavailable
-    }
-}
+/// Worker warm-up hook, called with the worker index.
+pub(crate) type Warmup =
+    Arc<dyn Fn(usize) -> LocalBoxFuture<'static, io::Result<()>> + Send + Sync>;
 
 /// Config for worker behavior passed down from server builder.
-#[derive(Debug, Clone, Copy)]
+#[derive(Clone)]
 pub(crate) struct ServerWorkerConfig {
     shutdown_timeout: Duration,
     max_blocking_threads: usize,
     max_concurrent_connections: usize,
+    idle_timeout: Option<Duration>,
+    clock: Arc<dyn Clock>,
+    preprocessors: Vec<Preprocessor>,
+    handoff: Option<HandoffRegistry>,
+    warmup: Option<Warmup>,
+    max_dispatch_age: Option<Duration>,
+    connections: Option<ConnectionRegistry>,
+    close_reasons: Option<CloseReasonCounter>,
+    #[cfg(target_os = "linux")]
+    transparent: Vec<usize>,
+}
+
+impl fmt::Debug for ServerWorkerConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerWorkerConfig")
+            .field("shutdown_timeout", &self.shutdown_timeout)
+            .field("max_blocking_threads", &self.max_blocking_threads)
+            .field(
+                "max_concurrent_connections",
+                &self.max_concurrent_connections,
+            )
+            .field("idle_timeout", &self.idle_timeout)
+            .field("preprocessors", &self.preprocessors.len())
+            .field("handoff", &self.handoff.is_some())
+            .field("warmup", &self.warmup.is_some())
+            .field("max_dispatch_age", &self.max_dispatch_age)
+            .field("connections", &self.connections)
+            .field("close_reasons", &self.close_reasons)
+            .finish()
+    }
 }
 
 impl Default for ServerWorkerConfig {
@@ -255,6 +378,16 @@ impl Default for ServerWorkerConfig {
             shutdown_timeout: Duration::from_secs(30),
             max_blocking_threads,
             max_concurrent_connections: 25600,
+            idle_timeout: None,
+            clock: Arc::new(RuntimeClock::new()),
+            preprocessors: Vec::new(),
+            handoff: None,
+            warmup: None,
+            max_dispatch_age: None,
+            connections: None,
+            close_reasons: None,
+            #[cfg(target_os = "linux")]
+            transparent: Vec::new(),
         }
     }
 }
@@ -271,6 +404,66 @@ impl ServerWorkerConfig {
     pub(crate) fn shutdown_timeout(&mut self, dur: Duration) {
         self.shutdown_timeout = dur;
     }
+
+    #[cfg(feature = "embedded")]
+    pub(crate) fn idle(&self) -> (Option<Duration>, Arc<dyn Clock>) {
+        (self.idle_timeout, Arc::clone(&self.clock))
+    }
+
+    pub(crate) fn idle_timeout(&mut self, dur: Duration) {
+        self.idle_timeout = Some(dur);
+    }
+
+    pub(crate) fn clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    pub(crate) fn preprocess(&mut self, preprocessor: Preprocessor) {
+        self.preprocessors.push(preprocessor);
+    }
+
+    pub(crate) fn connection_handoff(&mut self) {
+        self.handoff.get_or_insert_with(HandoffRegistry::default);
+    }
+
+    pub(crate) fn warmup(&mut self, warmup: Warmup) {
+        self.warmup = Some(warmup);
+    }
+
+    pub(crate) fn max_dispatch_age(&mut self, dur: Duration) {
+        self.max_dispatch_age = Some(dur);
+    }
+
+    pub(crate) fn track_connections(&mut self) {
+        self.connections
+            .get_or_insert_with(ConnectionRegistry::default);
+    }
+
+    /// Returns connection registry, if enabled, along with the clock used for connection ages.
+    pub(crate) fn connections(&self) -> Option<(ConnectionRegistry, Arc<dyn Clock>)> {
+        self.connections
+            .clone()
+            .map(|registry| (registry, Arc::clone(&self.clock)))
+    }
+
+    pub(crate) fn count_close_reasons(&mut self, counter: CloseReasonCounter) {
+        self.close_reasons = Some(counter);
+    }
+
+    #[cfg(feature = "embedded")]
+    pub(crate) fn close_reasons(&self) -> Option<CloseReasonCounter> {
+        self.close_reasons.clone()
+    }
+
+    #[cfg(target_os = "linux")]
+    pub(crate) fn transparent(&mut self, token: usize) {
+        self.transparent.push(token);
+    }
+
+    fn shed_stale(&self) -> Option<(Duration, Arc<dyn Clock>)> {
+        self.max_dispatch_age
+            .map(|max_age| (max_age, Arc::clone(&self.clock)))
+    }
 }
 
 impl ServerWorker {
@@ -286,7 +479,17 @@ impl ServerWorker {
         let (tx2, stop_rx) = unbounded_channel();
 
         let counter = Counter::new(config.max_concurrent_connections);
-        let pair = handle_pair(idx, tx1, tx2, counter.clone());
+
+        if let Some(registry) = &config.handoff {
+            registry.register(idx, &tx1, counter.clone(), waker_queue.clone());
+        }
+
+        let max_dispatch_age = config.shed_stale();
+        let connections = config.connections();
+        let clock = max_dispatch_age
+            .as_ref()
+            .map(|(_, clock)| Arc::clone(clock));
+        let pair = handle_pair(idx, tx1, tx2, counter.clone(), clock);
 
         // get actix system context if it is set
         let actix_system = System::try_current();
@@ -297,6 +500,9 @@ impl ServerWorker {
         // service factories initialization channel
         let (factory_tx, factory_rx) = std::sync::mpsc::sync_channel::<io::Result<()>>(1);
 
+        // parent of the spans of service initialization and accepted connections on this worker
+        let span = tracing::info_span!(parent: None, "worker", worker = idx);
+
         // outline of following code:
         //
         // if system exists
@@ -329,25 +535,12 @@ impl ServerWorker {
                         let ls = tokio::task::LocalSet::new();
 
                         // init services using existing Tokio runtime (so probably on main thread)
-                        let services = rt_handle.block_on(ls.run_until(async {
-                            let mut services = Vec::new();
-
-                            for (idx, factory) in factories.iter().enumerate() {
-                                match factory.create().await {
-                                    Ok((token, svc)) => services.push((idx, token, svc)),
-
-                                    Err(err) => {
-                                        error!("can not start worker: {:?}", err);
-                                        return Err(io::Error::new(
-                                            io::ErrorKind::Other,
-                                            format!("can not start server service {}", idx),
-                                        ));
-                                    }
-                                }
-                            }
-
-                            Ok(services)
-                        }));
+                        let services = rt_handle.block_on(
+                            ls.run_until(
+                                create_services(idx, &factories, config.warmup.as_ref())
+                                    .instrument(span.clone()),
+                            ),
+                        );
 
                         let services = match services {
                             Ok(services) => {
@@ -363,6 +556,13 @@ impl ServerWorker {
                         let worker_services = wrap_worker_services(services);
 
                         let worker_fut = async move {
+                            // task closing idle connections stops along with the worker's runtime
+                            let (scope, _) = ConnectionScope::start(
+                                config.idle_timeout,
+                                config.clock.clone(),
+                                config.close_reasons.clone(),
+                            );
+
                             // spawn to make sure ServerWorker runs as non boxed future.
                             spawn(async move {
                                 ServerWorker {
@@ -371,9 +571,18 @@ impl ServerWorker {
                                     services: worker_services.into_boxed_slice(),
                                     counter: WorkerCounter::new(idx, waker_queue, counter),
                                     factories: factories.into_boxed_slice(),
+                                    preprocessors: config.preprocessors.into_boxed_slice(),
+                                    handoff: config.handoff,
+                                    max_dispatch_age,
+                                    connections,
+                                    #[cfg(target_os = "linux")]
+                                    transparent: config.transparent.into_boxed_slice(),
                                     state: WorkerState::default(),
                                     shutdown_timeout: config.shutdown_timeout,
+                                    scope,
+                                    local: false,
                                 }
+                                .instrument(span)
                                 .await;
 
                                 // wake up outermost task waiting for shutdown
@@ -427,43 +636,59 @@ impl ServerWorker {
                 };
 
                 arbiter.spawn(async move {
-                    // spawn_local to run !Send future tasks.
-                    spawn(async move {
-                        let mut services = Vec::new();
-
-                        for (idx, factory) in factories.iter().enumerate() {
-                            match factory.create().await {
-                                Ok((token, svc)) => services.push((idx, token, svc)),
+                    // task closing idle connections stops along with the worker's runtime
+                    let (scope, _) = ConnectionScope::start(
+                        config.idle_timeout,
+                        config.clock.clone(),
+                        config.close_reasons.clone(),
+                    );
 
+                    // spawn_local to run !Send future tasks.
+                    spawn(
+                        async move {
+                            let services = match create_services(
+                                idx,
+                                &factories,
+                                config.warmup.as_ref(),
+                            )
+                            .await
+                            {
+                                Ok(services) => services,
                                 Err(err) => {
-                                    error!("can not start worker: {:?}", err);
                                     Arbiter::current().stop();
-                                    factory_tx
-                                        .send(Err(io::Error::new(
-                                            io::ErrorKind::Other,
-                                            format!("can not start server service {}", idx),
-                                        )))
-                                        .unwrap();
+                                    factory_tx.send(Err(err)).unwrap();
                                     return;
                                 }
-                            }
-                        }
+                            };
 
-                        factory_tx.send(Ok(())).unwrap();
+                            factory_tx.send(Ok(())).unwrap();
 
-                        let worker_services = wrap_worker_services(services);
+                            let worker_services = wrap_worker_services(services);
 
-                        // spawn to make sure ServerWorker runs as non boxed future.
-                        spawn(ServerWorker {
-                            conn_rx,
-                            stop_rx,
-                            services: worker_services.into_boxed_slice(),
-                            counter: WorkerCounter::new(idx, waker_queue, counter),
-                            factories: factories.into_boxed_slice(),
-                            state: Default::default(),
-                            shutdown_timeout: config.shutdown_timeout,
-                        });
-                    });
+                            // spawn to make sure ServerWorker runs as non boxed future.
+                            spawn(
+                                ServerWorker {
+                                    conn_rx,
+                                    stop_rx,
+                                    services: worker_services.into_boxed_slice(),
+                                    counter: WorkerCounter::new(idx, waker_queue, counter),
+                                    factories: factories.into_boxed_slice(),
+                                    preprocessors: config.preprocessors.into_boxed_slice(),
+                                    handoff: config.handoff,
+                                    max_dispatch_age,
+                                    connections,
+                                    #[cfg(target_os = "linux")]
+                                    transparent: config.transparent.into_boxed_slice(),
+                                    state: Default::default(),
+                                    shutdown_timeout: config.shutdown_timeout,
+                                    scope,
+                                    local: false,
+                                }
+                                .instrument(tracing::Span::current()),
+                            );
+                        }
+                        .instrument(span),
+                    );
                 });
             }
         };
@@ -474,6 +699,63 @@ impl ServerWorker {
         Ok(pair)
     }
 
+    /// Starts worker as a task on the current thread, for embedded servers.
+    #[cfg(feature = "embedded")]
+    pub(crate) async fn start_local(
+        idx: usize,
+        factories: Vec<Box<dyn InternalServiceFactory>>,
+        waker_queue: WakerQueue,
+        config: ServerWorkerConfig,
+        scope: ConnectionScope,
+    ) -> io::Result<(WorkerHandleAccept, WorkerHandleServer)> {
+        trace!("starting embedded server worker {}", idx);
+
+        let (tx1, conn_rx) = unbounded_channel();
+        let (tx2, stop_rx) = unbounded_channel();
+
+        let counter = Counter::new(config.max_concurrent_connections);
+
+        if let Some(registry) = &config.handoff {
+            registry.register(idx, &tx1, counter.clone(), waker_queue.clone());
+        }
+
+        let max_dispatch_age = config.shed_stale();
+        let connections = config.connections();
+        let clock = max_dispatch_age
+            .as_ref()
+            .map(|(_, clock)| Arc::clone(clock));
+        let pair = handle_pair(idx, tx1, tx2, counter.clone(), clock);
+
+        let span = tracing::info_span!(parent: None, "worker", worker = idx);
+
+        let services = create_services(idx, &factories, config.warmup.as_ref())
+            .instrument(span.clone())
+            .await?;
+
+        spawn(
+            ServerWorker {
+                conn_rx,
+                stop_rx,
+                services: wrap_worker_services(services).into_boxed_slice(),
+                counter: WorkerCounter::new(idx, waker_queue, counter),
+                factories: factories.into_boxed_slice(),
+                preprocessors: config.preprocessors.into_boxed_slice(),
+                handoff: config.handoff,
+                max_dispatch_age,
+                connections,
+                #[cfg(target_os = "linux")]
+                transparent: config.transparent.into_boxed_slice(),
+                state: WorkerState::default(),
+                shutdown_timeout: config.shutdown_timeout,
+                scope,
+                local: true,
+            }
+            .instrument(span),
+        );
+
+        Ok(pair)
+    }
+
     fn restart_service(&mut self, idx: usize, factory_id: usize) {
         let factory = &self.factories[factory_id];
         trace!("service {:?} failed, restarting", factory.name(idx));
@@ -541,8 +823,10 @@ impl ServerWorker {
     }
 }
 
+#[derive(Default)]
 enum WorkerState {
     Available,
+    #[default]
     Unavailable,
     Restarting(Restart),
     Shutdown(Shutdown),
@@ -566,15 +850,12 @@ struct Shutdown {
     tx: oneshot::Sender<bool>,
 }
 
-impl Default for WorkerState {
-    fn default() -> Self {
-        Self::Unavailable
-    }
-}
-
 impl Drop for ServerWorker {
     fn drop(&mut self) {
-        Arbiter::try_current().as_ref().map(ArbiterHandle::stop);
+        // local workers run on the arbiter of the thread they were started on, which must keep going
+        if !self.local {
+            Arbiter::try_current().as_ref().map(ArbiterHandle::stop);
+        }
     }
 }
 
@@ -612,6 +893,7 @@ impl Future for ServerWorker {
         match this.state {
             WorkerState::Unavailable => match this.check_readiness(cx) {
                 Ok(true) => {
+                    this.counter.relieved();
                     this.state = WorkerState::Available;
                     self.poll(cx)
                 }
@@ -684,6 +966,7 @@ impl Future for ServerWorker {
                     Ok(true) => {}
                     Ok(false) => {
                         trace!("worker is unavailable");
+                        this.counter.overloaded();
                         this.state = WorkerState::Unavailable;
                         return self.poll(cx);
                     }
@@ -697,10 +980,84 @@ impl Future for ServerWorker {
                 match ready!(this.conn_rx.poll_recv(cx)) {
                     Some(msg) => {
                         let guard = this.counter.guard();
-                        let _ = this.services[msg.token]
-                            .service
-                            .call((guard, msg.io))
-                            .into_inner();
+
+                        if let (Some((max_age, clock)), Some(dispatched_at)) =
+                            (&this.max_dispatch_age, msg.dispatched_at)
+                        {
+                            let age = clock.now().saturating_duration_since(dispatched_at);
+
+                            if age > *max_age {
+                                debug!("closing connection that waited {age:?} for worker");
+                                continue;
+                            }
+                        }
+
+                        let srv = &this.services[msg.token];
+                        let mut tags = ConnectionTags::new();
+
+                        if let Some(registry) = &this.handoff {
+                            let handoff =
+                                Handoff::new(registry.clone(), this.counter.idx, msg.token);
+                            tags.insert(handoff);
+                        }
+
+                        #[cfg(unix)]
+                        if let MioStream::Uds(stream) = &msg.io {
+                            match PeerCredentials::from_stream(stream) {
+                                Ok(cred) => {
+                                    tags.insert(cred);
+                                }
+                                Err(err) => debug!("can not read peer credentials: {err}"),
+                            }
+                        }
+
+                        #[cfg(target_os = "linux")]
+                        if let MioStream::Tcp(stream) = &msg.io {
+                            if this.transparent.contains(&msg.token) {
+                                match OriginalDst::lookup(stream) {
+                                    Ok(dst) => {
+                                        tags.insert(dst);
+                                    }
+                                    Err(err) => {
+                                        debug!("can not look up original destination: {err}")
+                                    }
+                                }
+                            }
+                        }
+
+                        if let Some(state) = msg.state {
+                            // handed off by another worker, which has preprocessed it already
+                            tags.insert(ReceivedState::new(state));
+                        } else if !this.preprocessors.is_empty() {
+                            let name = this.factories[srv.factory_idx].name(msg.token);
+
+                            if let Err(err) =
+                                preprocess::run(&this.preprocessors, &msg.io, name, &mut tags)
+                            {
+                                debug!("connection to {name:?} rejected by preprocessor: {err}");
+                                continue;
+                            }
+                        }
+
+                        if let Some((registry, clock)) = &this.connections {
+                            let name = this.factories[srv.factory_idx].name(msg.token);
+
+                            let peer_addr = match &msg.io {
+                                MioStream::Tcp(stream) => stream.peer_addr().ok(),
+                                #[cfg(unix)]
+                                MioStream::Uds(_) => None,
+                            };
+
+                            tags.insert(registry.register(
+                                name,
+                                peer_addr,
+                                this.counter.idx,
+                                clock.now(),
+                            ));
+                        }
+
+                        let conn = this.scope.accept(tags);
+                        let _ = srv.service.call((guard, msg.io, conn)).into_inner();
                     }
                     None => return Poll::Ready(()),
                 };
@@ -709,6 +1066,51 @@ impl Future for ServerWorker {
     }
 }
 
+/// Creates services of worker `idx` and runs warm-up hook, if any, afterwards.
+async fn create_services(
+    idx: usize,
+    factories: &[Box<dyn InternalServiceFactory>],
+    warmup: Option<&Warmup>,
+) -> io::Result<Vec<(usize, usize, BoxedServerService)>> {
+    let mut services = Vec::new();
+
+    for (idx, factory) in factories.iter().enumerate() {
+        match factory.create().await {
+            Ok((token, svc)) => services.push((idx, token, svc)),
+
+            Err(err) => {
+                error!("can not start worker: {:?}", err);
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("can not start server service {}", idx),
+                ));
+            }
+        }
+    }
+
+    warm_up(idx, warmup).await?;
+
+    Ok(services)
+}
+
+/// Runs warm-up hook, if any, after services of worker `idx` have been created.
+async fn warm_up(idx: usize, warmup: Option<&Warmup>) -> io::Result<()> {
+    let warmup = match warmup {
+        Some(warmup) => warmup,
+        None => return Ok(()),
+    };
+
+    trace!("warming up server worker {}", idx);
+
+    warmup(idx).await.map_err(|err| {
+        error!("can not warm up worker: {:?}", err);
+        io::Error::new(
+            err.kind(),
+            format!("can not warm up server worker {}: {}", idx, err),
+        )
+    })
+}
+
 fn wrap_worker_services(services: Vec<(usize, usize, BoxedServerService)>) -> Vec<WorkerService> {
     services
         .into_iter()
@@ -722,3 +1124,36 @@ fn wrap_worker_services(services: Vec<(usize, usize, BoxedServerService)>) -> Ve
             services
         })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::availability::Availability;
+
+    #[test]
+    fn relieved_at_limit_stays_unavailable() {
+        let poll = mio::Poll::new().unwrap();
+        let waker_queue = WakerQueue::new(poll.registry()).unwrap();
+        let mut avail = Availability::default();
+
+        let counter = Counter::new(2);
+        let worker = WorkerCounter::new(0, waker_queue.clone(), counter.clone());
+
+        assert!(counter.try_inc());
+        assert!(!counter.try_inc());
+        let guard = worker.guard();
+
+        worker.overloaded();
+        worker.relieved();
+        assert!(!counter.is_overloaded());
+        assert!(!waker_queue.take_available(&mut avail));
+
+        // completing the connection crosses the limit and wakes up `Accept`
+        drop(guard);
+        assert!(waker_queue.take_available(&mut avail));
+
+        worker.overloaded();
+        worker.relieved();
+        assert!(waker_queue.take_available(&mut avail));
+    }
+}
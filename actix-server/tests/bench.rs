@@ -0,0 +1,88 @@
+#![cfg(feature = "bench-harness")]
+
+use std::time::Duration;
+
+use actix_rt::net::TcpStream;
+use actix_server::{
+    bench::{self, AcceptRate, BenchHarness, EchoThroughput, HandshakeRate},
+    TestServer,
+};
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+fn harness() -> BenchHarness {
+    BenchHarness::new()
+        .concurrency(4)
+        .duration(Duration::from_secs(10))
+        .iterations(20)
+}
+
+#[actix_rt::test]
+async fn accept_rate() {
+    let srv = TestServer::start(bench::echo);
+
+    let report = harness().run(srv.addr(), AcceptRate::new()).await;
+
+    assert_eq!(report.iterations(), 20);
+    assert_eq!(report.errors(), 0);
+    assert_eq!(report.bytes(), 0);
+    assert!(report.rate() > 0.0);
+    assert!(report.latency(50.0).unwrap() <= report.latency(100.0).unwrap());
+}
+
+#[actix_rt::test]
+async fn echo_throughput() {
+    let srv = TestServer::start(bench::echo);
+
+    let workload = EchoThroughput::new(512).round_trips(10);
+    let report = harness().run(srv.addr(), workload).await;
+
+    assert_eq!(report.iterations(), 20);
+    assert_eq!(report.errors(), 0);
+    assert_eq!(report.bytes(), 20 * 10 * 512);
+    assert!(report.throughput() > 0.0);
+}
+
+#[actix_rt::test]
+async fn handshake_rate() {
+    let srv = TestServer::start(bench::echo);
+
+    // fake handshake exchanging a greeting
+    let workload = HandshakeRate::new(|mut stream: TcpStream| async move {
+        stream.write_all(b"hello").await?;
+        let mut buf = [0; 5];
+        stream.read_exact(&mut buf).await?;
+        Ok(stream)
+    });
+
+    let report = harness().run(srv.addr(), workload).await;
+
+    assert_eq!(report.iterations(), 20);
+    assert_eq!(report.errors(), 0);
+}
+
+#[actix_rt::test]
+async fn counts_errors() {
+    // nothing is listening on this address
+    let addr = TestServer::unused_addr();
+
+    let report = harness().run(addr, AcceptRate::new()).await;
+
+    assert_eq!(report.iterations(), 0);
+    assert_eq!(report.errors(), 20);
+    assert_eq!(report.latency(50.0), None);
+}
+
+#[actix_rt::test]
+async fn stops_after_duration() {
+    let srv = TestServer::start(bench::echo);
+
+    let report = BenchHarness::new()
+        .concurrency(2)
+        .warmup(Duration::from_millis(20))
+        .duration(Duration::from_millis(50))
+        .run(srv.addr(), AcceptRate::new())
+        .await;
+
+    assert!(report.iterations() > 0);
+    assert!(report.elapsed() >= Duration::from_millis(50));
+}
@@ -0,0 +1,193 @@
+#![cfg(feature = "embedded")]
+
+use std::{
+    net,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use actix_rt::{net::TcpStream, time::sleep, Arbiter};
+use actix_server::{CloseReason, CloseReasonCounter, EmbeddedServer, Server};
+use actix_service::fn_service;
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+/// Starts embedded server answering each connection with its number.
+fn start(builder: actix_server::ServerBuilder) -> (EmbeddedServer, net::SocketAddr) {
+    let lst = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = lst.local_addr().unwrap();
+    let num = Arc::new(AtomicUsize::new(0));
+
+    let srv = builder
+        .listen("test", lst, move || {
+            let num = Arc::clone(&num);
+
+            fn_service(move |mut io: TcpStream| {
+                let num = num.fetch_add(1, Ordering::SeqCst) as u8;
+                async move { io.write_u8(num).await }
+            })
+        })
+        .unwrap()
+        .run_embedded();
+
+    (srv, addr)
+}
+
+#[actix_rt::test]
+async fn serves_on_current_thread() {
+    let test_thread = thread::current().id();
+
+    let lst = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = lst.local_addr().unwrap();
+
+    let srv = Server::build()
+        .workers(4)
+        .listen("test", lst, move || {
+            assert_eq!(thread::current().id(), test_thread);
+
+            fn_service(move |mut io: TcpStream| async move {
+                assert_eq!(thread::current().id(), test_thread);
+                io.write_u8(1).await
+            })
+        })
+        .unwrap()
+        .run_embedded();
+
+    let handle = srv.handle();
+    let srv = actix_rt::spawn(srv);
+
+    for _ in 0..3 {
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        assert_eq!(conn.read_u8().await.unwrap(), 1);
+    }
+
+    let listeners = handle.listeners().await;
+    assert_eq!(listeners.len(), 1);
+    assert_eq!(listeners[0].name(), "test");
+
+    handle.stop(true).await;
+    srv.await.unwrap().unwrap();
+}
+
+#[actix_rt::test]
+async fn pauses_and_resumes() {
+    let (srv, addr) = start(Server::build());
+    let handle = srv.handle();
+    actix_rt::spawn(srv);
+
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    assert_eq!(conn.read_u8().await.unwrap(), 0);
+
+    handle.pause().await;
+
+    // connection waits in backlog while paused
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    let paused = tokio::time::timeout(Duration::from_millis(100), conn.read_u8()).await;
+    assert!(paused.is_err());
+
+    handle.resume().await;
+    assert_eq!(conn.read_u8().await.unwrap(), 1);
+
+    handle.stop(false).await;
+
+    // listener is closed once stopped
+    sleep(Duration::from_millis(50)).await;
+    assert!(TcpStream::connect(addr).await.is_err());
+}
+
+#[actix_rt::test]
+async fn applies_back_pressure() {
+    let lst = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = lst.local_addr().unwrap();
+
+    let srv = Server::build()
+        .max_concurrent_connections(2)
+        .listen("test", lst, || {
+            fn_service(|mut io: TcpStream| async move {
+                // hold connection until client closes it
+                let _ = io.read_u8().await;
+                Ok::<_, std::io::Error>(())
+            })
+        })
+        .unwrap()
+        .run_embedded();
+
+    let handle = srv.handle();
+    actix_rt::spawn(srv);
+
+    let conns = [
+        TcpStream::connect(addr).await.unwrap(),
+        TcpStream::connect(addr).await.unwrap(),
+    ];
+    sleep(Duration::from_millis(50)).await;
+
+    // third connection is not served until others are done
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    conn.write_u8(0).await.unwrap();
+    let waiting = tokio::time::timeout(Duration::from_millis(100), conn.read_u8()).await;
+    assert!(waiting.is_err());
+
+    drop(conns);
+    let closed = tokio::time::timeout(Duration::from_secs(1), conn.read_u8()).await;
+    assert!(closed.unwrap().is_err());
+
+    handle.stop(true).await;
+}
+
+#[actix_rt::test]
+async fn stopping_keeps_host_arbiter_running() {
+    let (srv, addr) = start(Server::build());
+    let handle = srv.handle();
+    let srv = actix_rt::spawn(srv);
+
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    assert_eq!(conn.read_u8().await.unwrap(), 0);
+
+    handle.stop(true).await;
+    srv.await.unwrap().unwrap();
+
+    // worker ran on this thread's arbiter, which must still run spawned work
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    assert!(Arbiter::current().spawn(async move {
+        let _ = tx.send(());
+    }));
+    tokio::time::timeout(Duration::from_secs(1), rx)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+#[actix_rt::test]
+async fn servers_on_same_thread_count_close_reasons_separately() {
+    let counter1 = CloseReasonCounter::new();
+    let (srv1, addr1) = start(Server::build().count_close_reasons(counter1.clone()));
+    let handle1 = srv1.handle();
+    actix_rt::spawn(srv1);
+
+    let counter2 = CloseReasonCounter::new();
+    let (srv2, _) = start(Server::build().count_close_reasons(counter2.clone()));
+    let handle2 = srv2.handle();
+    actix_rt::spawn(srv2);
+
+    let mut conn = TcpStream::connect(addr1).await.unwrap();
+    conn.read_u8().await.unwrap();
+    sleep(Duration::from_millis(50)).await;
+
+    assert_eq!(counter1.count(CloseReason::Completed), 1);
+    assert_eq!(counter2.total(), 0);
+
+    // stopping one server leaves the other's counts intact
+    handle2.stop(true).await;
+
+    let mut conn = TcpStream::connect(addr1).await.unwrap();
+    conn.read_u8().await.unwrap();
+    sleep(Duration::from_millis(50)).await;
+
+    assert_eq!(counter1.count(CloseReason::Completed), 2);
+    assert_eq!(counter2.total(), 0);
+
+    handle1.stop(true).await;
+}
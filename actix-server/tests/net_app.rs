@@ -0,0 +1,256 @@
+use std::{io, net};
+
+use actix_rt::net::TcpStream;
+use actix_server::prelude::*;
+#[cfg(any(feature = "proxy-protocol", feature = "rustls"))]
+use actix_server::{CloseReason, CloseReasonCounter};
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+fn app() -> (NetApp, net::SocketAddr) {
+    let lst = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = lst.local_addr().unwrap();
+
+    let app = NetApp::new("test")
+        .workers(1)
+        .configure(|builder| builder.disable_signals())
+        .listen(lst);
+
+    (app, addr)
+}
+
+/// Service writing client address and TLS status to the connection.
+fn describe_client(
+) -> impl ServiceFactory<NetStream, Config = (), Response = (), Error = io::Error, InitError = ()> + Clone
+{
+    fn_service(|mut stream: NetStream| async move {
+        let desc = format!("{:?} {}", stream.client_addr(), stream.is_tls());
+        stream.write_all(desc.as_bytes()).await?;
+        stream.shutdown().await
+    })
+}
+
+async fn read_to_string(mut stream: impl tokio::io::AsyncRead + Unpin) -> String {
+    let mut buf = String::new();
+    stream.read_to_string(&mut buf).await.unwrap();
+    buf
+}
+
+#[actix_rt::test]
+async fn serves_plain_connections() {
+    let (app, addr) = app();
+
+    let srv = app.serve(describe_client).unwrap();
+    let handle = srv.handle();
+    let srv = actix_rt::spawn(srv);
+
+    let conn = TcpStream::connect(addr).await.unwrap();
+    let local_addr = conn.local_addr().unwrap();
+
+    assert_eq!(
+        read_to_string(conn).await,
+        format!("{:?} false", Some(local_addr))
+    );
+
+    handle.stop(false).await;
+    srv.await.unwrap().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[actix_rt::test]
+async fn samples_tcp_info() {
+    let (app, addr) = app();
+
+    let srv = app
+        .serve(|| {
+            fn_service(|mut stream: NetStream| async move {
+                let info = stream.tcp_info()?;
+                stream.write_all(&info.mss().to_be_bytes()).await?;
+                stream.shutdown().await
+            })
+        })
+        .unwrap();
+    let handle = srv.handle();
+    let srv = actix_rt::spawn(srv);
+
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    let mss = conn.read_u32().await.unwrap();
+    assert!(mss > 0);
+
+    handle.stop(false).await;
+    srv.await.unwrap().unwrap();
+}
+
+#[test]
+fn requires_listener() {
+    let err = NetApp::new("test").serve(describe_client).err().unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[cfg(feature = "proxy-protocol")]
+#[actix_rt::test]
+async fn reads_proxy_protocol_header() {
+    let (app, addr) = app();
+
+    let srv = app.proxy_protocol().serve(describe_client).unwrap();
+    let handle = srv.handle();
+    let srv = actix_rt::spawn(srv);
+
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    conn.write_all(b"PROXY TCP4 192.0.2.1 192.0.2.2 4321 80\r\n")
+        .await
+        .unwrap();
+
+    assert_eq!(read_to_string(conn).await, "Some(192.0.2.1:4321) false");
+
+    handle.stop(false).await;
+    srv.await.unwrap().unwrap();
+}
+
+/// Server config with a self-signed certificate for `localhost`.
+#[cfg(feature = "rustls")]
+fn tls_server_config() -> RustlsServerConfig {
+    use std::io::BufReader;
+
+    use tokio_rustls::rustls::{Certificate, PrivateKey};
+
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+    let key = cert.serialize_private_key_pem();
+    let cert = cert.serialize_pem().unwrap();
+
+    let certs = rustls_pemfile::certs(&mut BufReader::new(cert.as_bytes())).unwrap();
+    let mut keys = rustls_pemfile::pkcs8_private_keys(&mut BufReader::new(key.as_bytes())).unwrap();
+
+    RustlsServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(
+            certs.into_iter().map(Certificate).collect(),
+            PrivateKey(keys.remove(0)),
+        )
+        .unwrap()
+}
+
+#[cfg(feature = "rustls")]
+#[actix_rt::test]
+async fn serves_tls_connections() {
+    use std::{sync::Arc, time::SystemTime};
+
+    use tokio_rustls::rustls::{
+        client::{ServerCertVerified, ServerCertVerifier},
+        Certificate, ClientConfig, Error, RootCertStore, ServerName,
+    };
+
+    struct NoCertificateVerification;
+
+    impl ServerCertVerifier for NoCertificateVerification {
+        fn verify_server_cert(
+            &self,
+            _end_entity: &Certificate,
+            _intermediates: &[Certificate],
+            _server_name: &ServerName,
+            _scts: &mut dyn Iterator<Item = &[u8]>,
+            _ocsp_response: &[u8],
+            _now: SystemTime,
+        ) -> Result<ServerCertVerified, Error> {
+            Ok(ServerCertVerified::assertion())
+        }
+    }
+
+    let (app, addr) = app();
+
+    let srv = app
+        .rustls(tls_server_config())
+        .serve(describe_client)
+        .unwrap();
+    let handle = srv.handle();
+    let srv = actix_rt::spawn(srv);
+
+    let mut client_config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(RootCertStore::empty())
+        .with_no_client_auth();
+    client_config
+        .dangerous()
+        .set_certificate_verifier(Arc::new(NoCertificateVerification));
+
+    let conn = TcpStream::connect(addr).await.unwrap();
+    let local_addr = conn.local_addr().unwrap();
+
+    let conn = tokio_rustls::TlsConnector::from(Arc::new(client_config))
+        .connect(ServerName::try_from("localhost").unwrap(), conn)
+        .await
+        .unwrap();
+
+    assert_eq!(
+        read_to_string(conn).await,
+        format!("{:?} true", Some(local_addr))
+    );
+
+    handle.stop(false).await;
+    srv.await.unwrap().unwrap();
+}
+
+/// Waits until `counter` has counted a connection closed for `reason`.
+#[cfg(any(feature = "proxy-protocol", feature = "rustls"))]
+async fn wait_for_close(counter: &CloseReasonCounter, reason: CloseReason) {
+    for _ in 0..100 {
+        if counter.count(reason) > 0 {
+            return;
+        }
+
+        actix_rt::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+
+    panic!("no connection closed for {reason}: {counter:?}");
+}
+
+#[cfg(feature = "proxy-protocol")]
+#[actix_rt::test]
+async fn counts_invalid_proxy_protocol_header() {
+    let (app, addr) = app();
+    let counter = CloseReasonCounter::new();
+
+    let srv = app
+        .proxy_protocol()
+        .configure(|builder| builder.count_close_reasons(counter.clone()))
+        .serve(describe_client)
+        .unwrap();
+    let handle = srv.handle();
+    let srv = actix_rt::spawn(srv);
+
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    conn.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+    assert_eq!(read_to_string(conn).await, "");
+
+    wait_for_close(&counter, CloseReason::HandshakeFailure).await;
+    assert_eq!(counter.total(), 1);
+
+    handle.stop(false).await;
+    srv.await.unwrap().unwrap();
+}
+
+#[cfg(feature = "rustls")]
+#[actix_rt::test]
+async fn counts_failed_tls_handshakes() {
+    let (app, addr) = app();
+    let counter = CloseReasonCounter::new();
+
+    let srv = app
+        .rustls(tls_server_config())
+        .configure(|builder| builder.count_close_reasons(counter.clone()))
+        .serve(describe_client)
+        .unwrap();
+    let handle = srv.handle();
+    let srv = actix_rt::spawn(srv);
+
+    // plain text instead of a TLS client hello
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    conn.write_all(b"GET / HTTP/1.1\r\n\r\n").await.unwrap();
+    let _ = conn.read_to_end(&mut Vec::new()).await;
+
+    wait_for_close(&counter, CloseReason::HandshakeFailure).await;
+    assert_eq!(counter.total(), 1);
+
+    handle.stop(false).await;
+    srv.await.unwrap().unwrap();
+}
@@ -0,0 +1,139 @@
+#![cfg(feature = "tcp-proxy")]
+
+use std::{net, time::Duration};
+
+use actix_rt::net::TcpStream;
+use actix_server::{
+    proxy::{Proxy, ProxyHandle},
+    Server, ServerHandle,
+};
+use actix_service::fn_service;
+use actix_tls::connect::ConnectInfo;
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+/// Starts upstream answering each request, read until EOF, with the request in reverse.
+fn upstream() -> (ServerHandle, net::SocketAddr) {
+    let lst = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = lst.local_addr().unwrap();
+
+    let srv = Server::build()
+        .workers(1)
+        .disable_signals()
+        .listen("upstream", lst, || {
+            fn_service(|mut stream: TcpStream| async move {
+                let mut req = Vec::new();
+                stream.read_to_end(&mut req).await?;
+                req.reverse();
+                stream.write_all(&req).await?;
+                stream.shutdown().await
+            })
+        })
+        .unwrap()
+        .run();
+
+    let handle = srv.handle();
+    actix_rt::spawn(srv);
+    (handle, addr)
+}
+
+/// Starts proxy forwarding connections to `upstream`, or closing them if `None`.
+fn proxy(
+    handle: &ProxyHandle,
+    upstream: Option<net::SocketAddr>,
+) -> (ServerHandle, net::SocketAddr) {
+    let lst = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = lst.local_addr().unwrap();
+
+    let handle = handle.clone();
+    let srv = Server::build()
+        .workers(1)
+        .disable_signals()
+        .listen("proxy", lst, move || {
+            Proxy::new(&handle, move |_: &TcpStream| {
+                upstream.map(|addr| ConnectInfo::with_addr(addr.to_string(), addr))
+            })
+        })
+        .unwrap()
+        .run();
+
+    let srv_handle = srv.handle();
+    actix_rt::spawn(srv);
+    (srv_handle, addr)
+}
+
+#[actix_rt::test]
+async fn proxies_to_upstream() {
+    let (upstream, upstream_addr) = upstream();
+    let handle = ProxyHandle::new();
+    let (srv, addr) = proxy(&handle, Some(upstream_addr));
+
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    conn.write_all(b"hello").await.unwrap();
+    conn.shutdown().await.unwrap();
+
+    let mut res = Vec::new();
+    conn.read_to_end(&mut res).await.unwrap();
+    assert_eq!(res, b"olleh");
+
+    // metrics are updated after response is forwarded
+    actix_rt::time::sleep(Duration::from_millis(50)).await;
+    let metrics = handle.metrics();
+    assert_eq!(metrics.active(), 0);
+    assert_eq!(metrics.connections(), 1);
+    assert_eq!(metrics.failed(), 0);
+    assert_eq!((metrics.sent(), metrics.received()), (5, 5));
+
+    srv.stop(false).await;
+    upstream.stop(false).await;
+}
+
+#[actix_rt::test]
+async fn closes_unrouted_connections() {
+    let handle = ProxyHandle::new();
+    let (srv, addr) = proxy(&handle, None);
+
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    let mut res = Vec::new();
+    conn.read_to_end(&mut res).await.unwrap();
+    assert!(res.is_empty());
+
+    assert_eq!(handle.metrics().failed(), 1);
+
+    srv.stop(false).await;
+}
+
+#[actix_rt::test]
+async fn drain_aborts_lingering_connections() {
+    let (upstream, upstream_addr) = upstream();
+    let handle = ProxyHandle::new();
+    let (srv, addr) = proxy(&handle, Some(upstream_addr));
+
+    // connection stays open since upstream waits for EOF
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    conn.write_all(b"hello").await.unwrap();
+    actix_rt::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(handle.metrics().active(), 1);
+
+    assert!(!handle.drain(Duration::from_millis(100)).await);
+    assert!(handle.is_draining());
+
+    let mut res = Vec::new();
+    conn.read_to_end(&mut res).await.unwrap();
+    assert!(res.is_empty());
+
+    // new connections are closed right away
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    conn.read_to_end(&mut res).await.unwrap();
+    assert!(res.is_empty());
+
+    assert_eq!(handle.metrics().active(), 0);
+
+    srv.stop(false).await;
+    upstream.stop(false).await;
+}
+
+#[actix_rt::test]
+async fn drain_returns_when_idle() {
+    let handle = ProxyHandle::new();
+    assert!(handle.drain(Duration::from_millis(100)).await);
+}
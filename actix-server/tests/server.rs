@@ -535,3 +535,766 @@ fn no_runtime_on_init() {
     })
     .unwrap();
 }
+
+#[actix_rt::test]
+async fn closes_idle_connections() {
+    use actix_server::ConnectionActivity;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    let srv = TestServer::start_with_builder(
+        Server::build().connection_idle_timeout(Duration::from_millis(200)),
+        || {
+            fn_service(|mut io: TcpStream| async move {
+                let activity = ConnectionActivity::current().unwrap();
+
+                // echo bytes, recording activity for each one
+                while let Ok(byte) = io.read_u8().await {
+                    activity.touch();
+                    io.write_u8(byte).await?;
+                }
+
+                Ok::<_, std::io::Error>(())
+            })
+        },
+    );
+
+    // active connections are kept open beyond the timeout
+    let mut active = srv.connect().unwrap();
+    for byte in 0..6 {
+        active.write_u8(byte).await.unwrap();
+        assert_eq!(active.read_u8().await.unwrap(), byte);
+        sleep(Duration::from_millis(100)).await;
+    }
+
+    // connections without activity are closed by the server
+    let mut idle = srv.connect().unwrap();
+    let closed = tokio::time::timeout(Duration::from_secs(2), idle.read_u8()).await;
+    assert!(closed.unwrap().is_err());
+
+    let closed = tokio::time::timeout(Duration::from_secs(2), active.read_u8()).await;
+    assert!(closed.unwrap().is_err());
+
+    // no activity handle outside of connections
+    assert!(ConnectionActivity::current().is_none());
+}
+
+#[actix_rt::test]
+async fn closes_idle_connections_with_mock_clock() {
+    use actix_utils::clock::MockClock;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    let clock = MockClock::new();
+
+    let srv = TestServer::start_with_builder(
+        Server::build()
+            .connection_idle_timeout(Duration::from_secs(60))
+            .clock(clock.clone()),
+        || {
+            fn_service(|mut io: TcpStream| async move {
+                while let Ok(byte) = io.read_u8().await {
+                    io.write_u8(byte).await?;
+                }
+
+                Ok::<_, std::io::Error>(())
+            })
+        },
+    );
+
+    let mut conn = srv.connect().unwrap();
+    conn.write_u8(1).await.unwrap();
+    assert_eq!(conn.read_u8().await.unwrap(), 1);
+
+    // real time passing does not count towards the idle timeout
+    sleep(Duration::from_millis(100)).await;
+    conn.write_u8(2).await.unwrap();
+    assert_eq!(conn.read_u8().await.unwrap(), 2);
+
+    clock.advance(Duration::from_secs(61));
+    let closed = tokio::time::timeout(Duration::from_secs(2), conn.read_u8()).await;
+    assert!(closed.unwrap().is_err());
+}
+
+#[actix_rt::test]
+async fn preprocesses_accepted_sockets() {
+    use actix_server::ConnectionTags;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    let accepted = Arc::new(AtomicUsize::new(0));
+
+    let srv = TestServer::start_with_builder(
+        Server::build()
+            .workers(1)
+            .preprocess(move |sock| {
+                assert_eq!(sock.listener(), "test");
+                assert!(sock.peer_addr().is_some());
+
+                sock.socket().set_nodelay(true)?;
+
+                let num = accepted.fetch_add(1, Ordering::SeqCst) as u8;
+                sock.tags().insert(num);
+                Ok(())
+            })
+            .preprocess(|sock| {
+                // reject every other connection
+                if sock.tags().get::<u8>().unwrap() % 2 == 1 {
+                    return Err(std::io::Error::new(std::io::ErrorKind::Other, "rejected"));
+                }
+
+                Ok(())
+            }),
+        || {
+            fn_service(|mut io: TcpStream| async move {
+                let tags = ConnectionTags::current().unwrap();
+                assert!(io.nodelay().unwrap());
+
+                io.write_u8(*tags.get::<u8>().unwrap()).await?;
+                Ok::<_, std::io::Error>(())
+            })
+        },
+    );
+
+    let mut conn = srv.connect().unwrap();
+    assert_eq!(conn.read_u8().await.unwrap(), 0);
+
+    // rejected connections are closed without calling the service
+    let mut conn = srv.connect().unwrap();
+    assert!(conn.read_u8().await.is_err());
+
+    let mut conn = srv.connect().unwrap();
+    assert_eq!(conn.read_u8().await.unwrap(), 2);
+
+    // no tags outside of connections
+    assert!(ConnectionTags::current().is_none());
+}
+
+#[actix_rt::test]
+async fn skips_overloaded_workers() {
+    use std::{
+        sync::atomic::AtomicBool,
+        task::{Context, Poll},
+    };
+
+    use actix_service::{fn_factory, Service};
+    use actix_utils::counter::Counter;
+    use futures_core::future::LocalBoxFuture;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    /// Echo service that is not ready while the first connection to the server is open on its
+    /// worker, like a TLS acceptor at its handshake limit.
+    struct HoldFirst {
+        conns: Counter,
+        first: Arc<AtomicBool>,
+    }
+
+    impl Service<TcpStream> for HoldFirst {
+        type Response = ();
+        type Error = std::io::Error;
+        type Future = LocalBoxFuture<'static, Result<(), Self::Error>>;
+
+        fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            if self.conns.available(cx) {
+                Poll::Ready(Ok(()))
+            } else {
+                Poll::Pending
+            }
+        }
+
+        fn call(&self, mut io: TcpStream) -> Self::Future {
+            let guard = self
+                .first
+                .swap(false, Ordering::SeqCst)
+                .then(|| self.conns.get());
+
+            Box::pin(async move {
+                let _guard = guard;
+
+                while let Ok(byte) = io.read_u8().await {
+                    io.write_u8(byte).await?;
+                }
+
+                Ok(())
+            })
+        }
+    }
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let first = Arc::new(AtomicBool::new(true));
+
+    let h = thread::spawn(move || {
+        actix_rt::System::new().block_on(async {
+            let srv = Server::build()
+                .workers(2)
+                .disable_signals()
+                .bind("test", addr, move || {
+                    let first = first.clone();
+
+                    fn_factory(move || {
+                        let first = first.clone();
+
+                        async move {
+                            Ok::<_, ()>(HoldFirst {
+                                conns: Counter::new(1),
+                                first,
+                            })
+                        }
+                    })
+                })?
+                .run();
+
+            let _ = tx.send((srv.handle(), actix_rt::System::current()));
+
+            srv.await
+        })
+    });
+
+    let (srv, sys) = rx.recv().unwrap();
+
+    // saturate the worker handling the first connection
+    let mut held = TcpStream::connect(addr).await.unwrap();
+    held.write_u8(0).await.unwrap();
+    assert_eq!(held.read_u8().await.unwrap(), 0);
+
+    // following connections are all dispatched to the other worker
+    for byte in 1..=4 {
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        conn.write_u8(byte).await.unwrap();
+
+        let res = tokio::time::timeout(Duration::from_secs(2), conn.read_u8()).await;
+        assert_eq!(
+            res.expect("connection queued on overloaded worker")
+                .unwrap(),
+            byte
+        );
+    }
+
+    drop(held);
+
+    srv.stop(false).await;
+    sys.stop();
+    h.join().unwrap().unwrap();
+}
+
+#[actix_rt::test]
+async fn reports_listener_options() {
+    let lst = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let lst_addr = lst.local_addr().unwrap();
+
+    let srv = Server::build()
+        .workers(1)
+        .disable_signals()
+        .backlog(64)
+        .bind("bound", "127.0.0.1:0", || {
+            fn_service(|_: TcpStream| async { Ok::<_, ()>(()) })
+        })
+        .unwrap()
+        .listen("listened", lst, || {
+            fn_service(|_: TcpStream| async { Ok::<_, ()>(()) })
+        })
+        .unwrap()
+        .run();
+
+    let handle = srv.handle();
+    let srv = actix_rt::spawn(srv);
+
+    let listeners = handle.listeners().await;
+    assert_eq!(listeners.len(), 2);
+
+    let bound = &listeners[0];
+    assert_eq!(bound.name(), "bound");
+    assert_ne!(bound.port(), Some(0));
+    assert_eq!(bound.backlog(), Some(64));
+    assert_eq!(bound.reuse_address(), Some(true));
+    assert_eq!(bound.only_v6(), None);
+    #[cfg(target_os = "linux")]
+    assert_eq!(bound.reuse_port(), Some(false));
+
+    let listened = &listeners[1];
+    assert_eq!(listened.name(), "listened");
+    assert_eq!(listened.local_addr(), Some(lst_addr));
+    assert_eq!(listened.backlog(), None);
+
+    handle.stop(false).await;
+    srv.await.unwrap().unwrap();
+
+    assert!(handle.listeners().await.is_empty());
+}
+
+#[actix_rt::test]
+async fn hands_off_connections() {
+    use actix_server::{Handoff, HandoffError};
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    let srv = Server::build()
+        .workers(2)
+        .disable_signals()
+        .connection_handoff()
+        .bind("test", "127.0.0.1:0", || {
+            fn_service(|mut stream: TcpStream| async move {
+                let handoff = Handoff::current().unwrap();
+                let worker = handoff.current_worker() as u8;
+
+                match handoff.take_state::<u8>() {
+                    // write previous and current worker once handed off
+                    Some(from) => stream.write_all(&[from, worker]).await.unwrap(),
+
+                    None => {
+                        // unknown workers return the stream
+                        let stream = match handoff.transfer(99, stream, worker) {
+                            Err(HandoffError::Unavailable(stream)) => stream,
+                            res => panic!("unexpected hand-off result: {res:?}"),
+                        };
+
+                        let target = (handoff.current_worker() + 1) % handoff.num_workers();
+                        handoff.transfer(target, stream, worker).unwrap();
+                    }
+                }
+
+                Ok::<_, ()>(())
+            })
+        })
+        .unwrap()
+        .run();
+
+    let handle = srv.handle();
+    let srv = actix_rt::spawn(srv);
+    let addr = handle.listeners().await[0].local_addr().unwrap();
+
+    for _ in 0..2 {
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+
+        let mut buf = [0; 2];
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_ne!(buf[0], buf[1]);
+        assert!(buf[0] < 2 && buf[1] < 2);
+    }
+
+    handle.stop(false).await;
+    srv.await.unwrap().unwrap();
+}
+
+#[test]
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn uds_peer_credentials() {
+    use std::{
+        io::Read as _,
+        os::unix::{fs::MetadataExt as _, net::UnixStream},
+    };
+
+    use actix_server::PeerCredentials;
+    use tokio::io::AsyncWriteExt as _;
+
+    let path = std::env::temp_dir().join(format!("actix-server-cred-{}.sock", std::process::id()));
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn({
+        let path = path.clone();
+
+        move || {
+            actix_rt::System::new().block_on(async {
+                let srv = Server::build()
+                    .workers(1)
+                    .disable_signals()
+                    .bind_uds("test", &path, || {
+                        fn_service(|mut io: actix_rt::net::UnixStream| async move {
+                            let cred = PeerCredentials::current().unwrap();
+                            io.write_all(&cred.uid().to_be_bytes()).await?;
+                            io.write_all(&cred.gid().to_be_bytes()).await?;
+                            Ok::<_, std::io::Error>(())
+                        })
+                    })?
+                    .run();
+
+                tx.send(srv.handle()).unwrap();
+                srv.await
+            })
+        }
+    });
+
+    let srv = rx.recv().unwrap();
+
+    let mut conn = UnixStream::connect(&path).unwrap();
+    let mut buf = [0; 8];
+    conn.read_exact(&mut buf).unwrap();
+
+    // socket file is owned by this process's user, the same one that connected
+    let meta = std::fs::metadata(&path).unwrap();
+    assert_eq!(buf[..4], meta.uid().to_be_bytes());
+    assert_eq!(buf[4..], meta.gid().to_be_bytes());
+
+    let _ = srv.stop(true);
+    h.join().unwrap().unwrap();
+    let _ = std::fs::remove_file(&path);
+}
+
+#[actix_rt::test]
+async fn warms_up_workers_before_accepting() {
+    use std::{rc::Rc, sync::Mutex};
+
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    let warmed = Arc::new(Mutex::new(Vec::new()));
+    let warmed2 = Arc::clone(&warmed);
+    let lst = net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = lst.local_addr().unwrap();
+
+    let srv = Server::build()
+        .workers(2)
+        .disable_signals()
+        .worker_warmup(move |idx| {
+            let warmed = Arc::clone(&warmed2);
+
+            // warm-up futures need not be `Send`
+            let primed = Rc::new(idx);
+
+            async move {
+                sleep(Duration::from_millis(50)).await;
+                warmed.lock().unwrap().push(*primed);
+                Ok(())
+            }
+        })
+        .listen("test", lst, {
+            let warmed = Arc::clone(&warmed);
+
+            move || {
+                let warmed = Arc::clone(&warmed);
+
+                fn_service(move |mut io: TcpStream| {
+                    let num = warmed.lock().unwrap().len() as u8;
+                    async move { io.write_u8(num).await }
+                })
+            }
+        })
+        .unwrap()
+        .run();
+
+    let handle = srv.handle();
+    actix_rt::spawn(srv);
+
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    assert_eq!(conn.read_u8().await.unwrap(), 2);
+
+    let mut warmed = warmed.lock().unwrap().clone();
+    warmed.sort_unstable();
+    assert_eq!(warmed, [0, 1]);
+
+    handle.stop(false).await;
+}
+
+#[actix_rt::test]
+async fn failed_warmup_fails_start() {
+    let res = Server::build()
+        .workers(1)
+        .disable_signals()
+        .worker_warmup(|_| async { Err(std::io::Error::new(std::io::ErrorKind::Other, "cold")) })
+        .bind("test", unused_addr(), || {
+            fn_service(|_: TcpStream| async { Ok::<_, ()>(()) })
+        })
+        .unwrap()
+        .run()
+        .await;
+
+    let err = res.unwrap_err();
+    assert!(err.to_string().contains("cold"), "{}", err);
+}
+
+#[actix_rt::test]
+async fn sheds_stale_connections() {
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    let srv = TestServer::start_with_builder(
+        Server::build().shed_stale_connections(Duration::from_millis(100)),
+        || {
+            fn_service(|mut io: TcpStream| async move {
+                let delay = io.read_u8().await?;
+
+                // block worker so that following connections queue up for it
+                thread::sleep(Duration::from_millis(delay as u64));
+
+                io.write_u8(delay).await
+            })
+        },
+    );
+
+    let mut conn = srv.connect().unwrap();
+    conn.write_u8(250).await.unwrap();
+    sleep(Duration::from_millis(50)).await;
+
+    // waits for worker longer than max age
+    let mut stale = srv.connect().unwrap();
+
+    assert_eq!(conn.read_u8().await.unwrap(), 250);
+    assert!(stale.read_u8().await.is_err());
+
+    let mut conn = srv.connect().unwrap();
+    conn.write_u8(0).await.unwrap();
+    assert_eq!(conn.read_u8().await.unwrap(), 0);
+}
+
+#[test]
+fn pre_bind_hooks() {
+    use std::sync::Mutex;
+
+    let addr = unused_addr();
+    let hooked = Arc::new(Mutex::new(Vec::new()));
+
+    let builder = {
+        let hooked = Arc::clone(&hooked);
+
+        Server::build()
+            .pre_bind(move |_, addr| {
+                hooked.lock().unwrap().push(addr);
+                Ok(())
+            })
+            .pre_bind(|socket, _| socket.set_recv_buffer_size(64 * 1024))
+            .bind("test", addr, || {
+                fn_service(|_: TcpStream| async { Ok::<_, ()>(()) })
+            })
+            .unwrap()
+    };
+    assert_eq!(*hooked.lock().unwrap(), [addr]);
+    drop(builder);
+
+    // errors fail binding
+    let err = Server::build()
+        .pre_bind(|_, _| Err(std::io::Error::new(std::io::ErrorKind::Other, "hooked")))
+        .bind("test", addr, || {
+            fn_service(|_: TcpStream| async { Ok::<_, ()>(()) })
+        })
+        .err()
+        .unwrap();
+    assert_eq!(err.to_string(), "hooked");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn pre_bind_reuse_port() {
+    let addr = unused_addr();
+
+    let bind = || {
+        Server::build()
+            .pre_bind(|socket, _| socket.set_reuse_port(true))
+            .bind("test", addr, || {
+                fn_service(|_: TcpStream| async { Ok::<_, ()>(()) })
+            })
+    };
+
+    // both builders can bind the same port
+    let _first = bind().unwrap();
+    let _second = bind().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn transparent_original_dst() {
+    use std::io::Read as _;
+
+    use actix_server::OriginalDst;
+    use tokio::io::AsyncWriteExt as _;
+
+    let addr = unused_addr();
+    let (tx, rx) = mpsc::channel();
+
+    let h = thread::spawn(move || {
+        actix_rt::System::new().block_on(async {
+            let builder = Server::build()
+                .workers(1)
+                .disable_signals()
+                .bind_transparent("test", addr, || {
+                    fn_service(|mut io: TcpStream| async move {
+                        let dst = OriginalDst::current().unwrap();
+                        io.write_all(dst.addr().to_string().as_bytes()).await
+                    })
+                });
+
+            let srv = match builder {
+                Ok(builder) => builder.run(),
+
+                // transparent sockets require CAP_NET_ADMIN
+                Err(err) if err.kind() == std::io::ErrorKind::PermissionDenied => {
+                    tx.send(None).unwrap();
+                    return Ok(());
+                }
+
+                Err(err) => return Err(err),
+            };
+
+            tx.send(Some(srv.handle())).unwrap();
+            srv.await
+        })
+    });
+
+    let srv = match rx.recv().unwrap() {
+        Some(srv) => srv,
+        None => return h.join().unwrap().unwrap(),
+    };
+
+    let mut conn = net::TcpStream::connect(addr).unwrap();
+    let mut buf = String::new();
+    conn.read_to_string(&mut buf).unwrap();
+    assert_eq!(buf, addr.to_string());
+
+    let _ = srv.stop(true);
+    h.join().unwrap().unwrap();
+}
+
+#[actix_rt::test]
+async fn tracks_connections() {
+    use actix_server::TrackedStream;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    let addr = unused_addr();
+
+    let srv = Server::build()
+        .workers(1)
+        .disable_signals()
+        .track_connections()
+        .bind("test", addr, || {
+            fn_service(|stream: TcpStream| async move {
+                let mut stream = TrackedStream::new(stream);
+                stream.write_all(b"hello").await?;
+
+                // hold connection open until client closes it
+                let mut buf = [0; 8];
+                while stream.read(&mut buf).await? > 0 {}
+
+                Ok::<_, std::io::Error>(())
+            })
+        })
+        .unwrap()
+        .run();
+
+    let handle = srv.handle();
+    let srv = actix_rt::spawn(srv);
+
+    assert!(handle.connections().await.is_empty());
+
+    let mut conn = TcpStream::connect(addr).await.unwrap();
+    conn.write_all(b"abc").await.unwrap();
+    let mut buf = [0; 5];
+    conn.read_exact(&mut buf).await.unwrap();
+
+    let conns = handle.connections().await;
+    assert_eq!(conns.len(), 1);
+    assert_eq!(conns[0].listener(), "test");
+    assert_eq!(conns[0].peer_addr(), Some(conn.local_addr().unwrap()));
+    assert_eq!(conns[0].worker(), 0);
+
+    // counters are updated by the worker thread after writing and reading
+    let mut counted = false;
+    for _ in 0..50 {
+        let conns = handle.connections().await;
+
+        if conns[0].bytes_written() == Some(5) && conns[0].bytes_read() == Some(3) {
+            counted = true;
+            break;
+        }
+
+        sleep(Duration::from_millis(20)).await;
+    }
+    assert!(counted, "bytes of tracked stream are counted");
+
+    drop(conn);
+
+    let mut closed = false;
+    for _ in 0..50 {
+        if handle.connections().await.is_empty() {
+            closed = true;
+            break;
+        }
+
+        sleep(Duration::from_millis(20)).await;
+    }
+    assert!(closed, "closed connection is removed from registry");
+
+    handle.stop(false).await;
+    srv.await.unwrap().unwrap();
+
+    assert!(handle.connections().await.is_empty());
+}
+
+#[actix_rt::test]
+async fn counts_close_reasons() {
+    use std::io;
+
+    use actix_server::{CloseReason, CloseReasonCounter, ConnectionActivity};
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    let addr = unused_addr();
+    let counter = CloseReasonCounter::new();
+
+    let srv = Server::build()
+        .workers(1)
+        .disable_signals()
+        .connection_idle_timeout(Duration::from_millis(200))
+        .count_close_reasons(counter.clone())
+        .bind("test", addr, || {
+            fn_service(|mut stream: TcpStream| async move {
+                // acknowledge command byte once connection is being handled
+                let cmd = stream.read_u8().await?;
+                stream.write_u8(cmd).await?;
+
+                match cmd {
+                    b'r' => Err(io::ErrorKind::ConnectionReset.into()),
+                    b'e' => Err(io::ErrorKind::InvalidData.into()),
+                    b'h' => {
+                        assert!(CloseReason::HandshakeFailure.report());
+                        Err(io::ErrorKind::InvalidData.into())
+                    }
+                    b'i' => std::future::pending().await,
+                    b'd' => loop {
+                        ConnectionActivity::current().unwrap().touch();
+                        sleep(Duration::from_millis(20)).await;
+                    },
+                    _ => Ok::<_, io::Error>(()),
+                }
+            })
+        })
+        .unwrap()
+        .run();
+
+    let handle = srv.handle();
+    let srv = actix_rt::spawn(srv);
+
+    async fn send(addr: net::SocketAddr, cmd: u8) -> TcpStream {
+        let mut conn = TcpStream::connect(addr).await.unwrap();
+        conn.write_u8(cmd).await.unwrap();
+        assert_eq!(conn.read_u8().await.unwrap(), cmd);
+        conn
+    }
+
+    async fn wait_for(counter: &CloseReasonCounter, reason: CloseReason, count: u64) {
+        for _ in 0..100 {
+            if counter.count(reason) == count {
+                return;
+            }
+
+            sleep(Duration::from_millis(20)).await;
+        }
+
+        panic!("expected {count} connections closed for {reason}, got {counter:?}");
+    }
+
+    for cmd in *b"crehh" {
+        send(addr, cmd).await;
+    }
+    let _idle = send(addr, b'i').await;
+
+    wait_for(&counter, CloseReason::Completed, 1).await;
+    wait_for(&counter, CloseReason::PeerReset, 1).await;
+    wait_for(&counter, CloseReason::ServiceError, 1).await;
+    wait_for(&counter, CloseReason::HandshakeFailure, 2).await;
+    wait_for(&counter, CloseReason::IdleTimeout, 1).await;
+
+    // connections still handled are dropped by forced shutdown
+    let _active = send(addr, b'd').await;
+    handle.stop(false).await;
+    srv.await.unwrap().unwrap();
+
+    wait_for(&counter, CloseReason::Drain, 1).await;
+    assert_eq!(counter.total(), 7);
+
+    // reasons can only be reported while handling connections
+    assert!(!CloseReason::HandshakeFailure.report());
+}
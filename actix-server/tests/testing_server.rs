@@ -69,5 +69,7 @@ async fn new_with_builder() {
     srv.connect().unwrap();
 
     // connect to alt service defined in custom ServerBuilder
-    TcpStream::from_std(net::TcpStream::connect(alt_addr).unwrap()).unwrap();
+    let alt_conn = net::TcpStream::connect(alt_addr).unwrap();
+    alt_conn.set_nonblocking(true).unwrap();
+    TcpStream::from_std(alt_conn).unwrap();
 }
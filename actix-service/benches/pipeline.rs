@@ -0,0 +1,47 @@
+use std::time::{Duration, Instant};
+
+use actix_rt::SystemRunner;
+use actix_service::{fn_factory, fn_service, Service, ServiceExt as _, ServiceFactory as _};
+use actix_utils::future::ok;
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+/// Calls `service` `iters` times and returns the total time taken.
+async fn call_many<S: Service<u64>>(service: &S, iters: u64) -> Duration {
+    let start = Instant::now();
+
+    for i in 0..iters {
+        let _ = black_box(service.call(black_box(i)).await);
+    }
+
+    start.elapsed()
+}
+
+#[actix_rt::bench]
+fn bench_pipeline(c: &mut Criterion, sys: &SystemRunner) {
+    let mut group = c.benchmark_group("service call");
+
+    let single = fn_service(|req: u64| ok::<_, ()>(req + 1));
+    group.bench_function("fn_service", |b| {
+        b.iter_custom(|iters| sys.block_on(call_many(&single, iters)));
+    });
+
+    let chained = fn_service(|req: u64| ok::<_, ()>(req + 1))
+        .and_then(fn_service(|req: u64| ok(req * 2)))
+        .map(|res| res - 1);
+    group.bench_function("and_then + map", |b| {
+        b.iter_custom(|iters| sys.block_on(call_many(&chained, iters)));
+    });
+
+    // services created by factories are usually only available asynchronously
+    let factory =
+        fn_factory(|| ok::<_, ()>(fn_service(|req: u64| async move { Ok::<_, ()>(req) })));
+    let from_factory = sys.block_on(factory.new_service(())).unwrap();
+    group.bench_function("from factory", |b| {
+        b.iter_custom(|iters| sys.block_on(call_many(&from_factory, iters)));
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_pipeline);
+criterion_main!(benches);
@@ -0,0 +1,536 @@
+//! Fault injection for testing how callers handle failing services.
+//!
+//! See [`FaultInject`] for details.
+
+use alloc::{boxed::Box, collections::VecDeque, rc::Rc};
+use core::{
+    cell::RefCell,
+    fmt,
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use pin_project_lite::pin_project;
+
+use super::{Service, Transform};
+use crate::{ok, Ready};
+
+type Sleep = Pin<Box<dyn Future<Output = ()>>>;
+
+/// Fault injected into a single call.
+pub enum Fault<E> {
+    /// Calls the inner service, delaying its response by at least the given duration.
+    Delay(Duration),
+
+    /// Responds with the error without calling the inner service.
+    Error(E),
+
+    /// Drops the request without calling the inner service; the response never completes.
+    Drop,
+}
+
+impl<E> fmt::Debug for Fault<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Delay(dur) => f.debug_tuple("Delay").field(dur).finish(),
+            Self::Error(_) => f.write_str("Error(..)"),
+            Self::Drop => f.write_str("Drop"),
+        }
+    }
+}
+
+/// Probabilities of faults injected into calls that have no scripted fault.
+///
+/// Faults are picked using a small pseudo-random number generator with a fixed default seed, so
+/// that test runs are reproducible.
+pub struct RandomFaults<E> {
+    delay: Option<(f64, Duration, Duration)>,
+    error: Option<(f64, Rc<dyn Fn() -> E>)>,
+    drop: f64,
+    seed: u64,
+}
+
+impl<E> RandomFaults<E> {
+    /// Constructs configuration that injects no faults.
+    pub fn new() -> Self {
+        Self {
+            delay: None,
+            error: None,
+            drop: 0.0,
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+
+    /// Delays responses by a duration picked uniformly from `min..=max` with the given probability.
+    ///
+    /// # Panics
+    /// Panics if `probability` is not in `0.0..=1.0` or if `max` is less than `min`.
+    pub fn delay(mut self, probability: f64, min: Duration, max: Duration) -> Self {
+        assert_probability(probability);
+        assert!(min <= max, "minimum delay must not exceed maximum delay");
+        self.delay = Some((probability, min, max));
+        self
+    }
+
+    /// Responds with errors created by `err` with the given probability.
+    ///
+    /// # Panics
+    /// Panics if `probability` is not in `0.0..=1.0`.
+    pub fn error(mut self, probability: f64, err: impl Fn() -> E + 'static) -> Self {
+        assert_probability(probability);
+        self.error = Some((probability, Rc::new(err)));
+        self
+    }
+
+    /// Drops requests with the given probability.
+    ///
+    /// # Panics
+    /// Panics if `probability` is not in `0.0..=1.0`.
+    pub fn drop(mut self, probability: f64) -> Self {
+        assert_probability(probability);
+        self.drop = probability;
+        self
+    }
+
+    /// Seeds the random number generator used to pick faults.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+}
+
+impl<E> Default for RandomFaults<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E> fmt::Debug for RandomFaults<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RandomFaults")
+            .field("delay", &self.delay)
+            .field("error", &self.error.as_ref().map(|(p, _)| p))
+            .field("drop", &self.drop)
+            .finish()
+    }
+}
+
+fn assert_probability(probability: f64) {
+    assert!(
+        (0.0..=1.0).contains(&probability),
+        "probability must be between 0 and 1"
+    );
+}
+
+/// Handle for controlling the faults injected by [`FaultInject`] services.
+///
+/// Clones of a handle control the same services. The handle also implements [`Transform`], so it
+/// can be applied to a service factory using [`apply`](crate::apply), in which case all services
+/// created by the factory share its faults.
+pub struct FaultHandle<E> {
+    inner: Rc<RefCell<FaultState<E>>>,
+}
+
+struct FaultState<E> {
+    sleep: Rc<dyn Fn(Duration) -> Sleep>,
+    script: VecDeque<Fault<E>>,
+    random: RandomFaults<E>,
+    rng: XorShift,
+    injected: usize,
+}
+
+impl<E> FaultHandle<E> {
+    /// Constructs handle that injects no faults until configured.
+    ///
+    /// Delays are created using `sleep`, typically the sleep function of the runtime in use, such
+    /// as `actix_rt::time::sleep`.
+    pub fn new<F, Fut>(sleep: F) -> Self
+    where
+        F: Fn(Duration) -> Fut + 'static,
+        Fut: Future<Output = ()> + 'static,
+    {
+        let random = RandomFaults::new();
+
+        Self {
+            inner: Rc::new(RefCell::new(FaultState {
+                sleep: Rc::new(move |dur| Box::pin(sleep(dur))),
+                script: VecDeque::new(),
+                rng: XorShift::new(random.seed),
+                random,
+                injected: 0,
+            })),
+        }
+    }
+
+    /// Queues a fault to inject into the next call without one.
+    ///
+    /// Scripted faults are injected in the order they were pushed, before any random faults.
+    pub fn push(&self, fault: Fault<E>) {
+        self.inner.borrow_mut().script.push_back(fault);
+    }
+
+    /// Sets the probabilities of faults injected into calls with no scripted fault.
+    pub fn set_random(&self, random: RandomFaults<E>) {
+        let mut state = self.inner.borrow_mut();
+        state.rng = XorShift::new(random.seed);
+        state.random = random;
+    }
+
+    /// Removes all scripted and random faults, passing calls through to the inner service.
+    pub fn clear(&self) {
+        let mut state = self.inner.borrow_mut();
+        state.script.clear();
+        state.random = RandomFaults::new();
+    }
+
+    /// Returns the number of calls that faults were injected into.
+    pub fn injected(&self) -> usize {
+        self.inner.borrow().injected
+    }
+
+    /// Picks the fault to inject into a call, if any.
+    fn next_fault(&self) -> Option<Fault<E>> {
+        let mut state = self.inner.borrow_mut();
+
+        let fault = match state.script.pop_front() {
+            Some(fault) => Some(fault),
+            None => state.random_fault(),
+        };
+
+        if fault.is_some() {
+            state.injected += 1;
+        }
+
+        fault
+    }
+}
+
+impl<E> FaultState<E> {
+    fn random_fault(&mut self) -> Option<Fault<E>> {
+        if self.random.drop > 0.0 && self.rng.next_f64() < self.random.drop {
+            return Some(Fault::Drop);
+        }
+
+        if let Some((probability, err)) = &self.random.error {
+            let (probability, err) = (*probability, err.clone());
+
+            if self.rng.next_f64() < probability {
+                return Some(Fault::Error(err()));
+            }
+        }
+
+        if let Some((probability, min, max)) = self.random.delay {
+            if self.rng.next_f64() < probability {
+                let delay = min + (max - min).mul_f64(self.rng.next_f64());
+                return Some(Fault::Delay(delay));
+            }
+        }
+
+        None
+    }
+}
+
+impl<E> Clone for FaultHandle<E> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<E> fmt::Debug for FaultHandle<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.inner.borrow();
+
+        f.debug_struct("FaultHandle")
+            .field("script", &state.script)
+            .field("random", &state.random)
+            .field("injected", &state.injected)
+            .finish()
+    }
+}
+
+impl<S, Req> Transform<S, Req> for FaultHandle<S::Error>
+where
+    S: Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Transform = FaultInject<S, Req>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ok(FaultInject::new(service, self.clone()))
+    }
+}
+
+/// Service wrapper injecting latency, errors, and dropped calls, for testing how callers such as
+/// retry loops or circuit breakers handle a failing service.
+///
+/// Faults are controlled by a [`FaultHandle`], either scripted for individual calls or picked at
+/// random with configured probabilities. Readiness is always that of the inner service.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use actix_service::{
+///     fault::{Fault, FaultHandle, FaultInject},
+///     fn_service, Service as _,
+/// };
+///
+/// # actix_rt::System::new().block_on(async {
+/// let handle = FaultHandle::new(actix_rt::time::sleep);
+/// let service = FaultInject::new(
+///     fn_service(|req: u32| async move { Ok::<_, &str>(req * 2) }),
+///     handle.clone(),
+/// );
+///
+/// handle.push(Fault::Error("unavailable"));
+/// handle.push(Fault::Delay(Duration::from_millis(10)));
+///
+/// assert_eq!(service.call(1).await, Err("unavailable"));
+/// assert_eq!(service.call(2).await, Ok(4));
+/// assert_eq!(service.call(3).await, Ok(6));
+/// assert_eq!(handle.injected(), 2);
+/// # });
+/// ```
+pub struct FaultInject<S, Req>
+where
+    S: Service<Req>,
+{
+    service: S,
+    handle: FaultHandle<S::Error>,
+    _phantom: PhantomData<fn(Req)>,
+}
+
+impl<S, Req> FaultInject<S, Req>
+where
+    S: Service<Req>,
+{
+    /// Wraps `service`, injecting faults controlled by `handle`.
+    pub fn new(service: S, handle: FaultHandle<S::Error>) -> Self {
+        Self {
+            service,
+            handle,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns handle controlling the injected faults.
+    pub fn handle(&self) -> &FaultHandle<S::Error> {
+        &self.handle
+    }
+}
+
+impl<S, Req> fmt::Debug for FaultInject<S, Req>
+where
+    S: Service<Req> + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FaultInject")
+            .field("service", &self.service)
+            .field("handle", &self.handle)
+            .finish()
+    }
+}
+
+impl<S, Req> Service<Req> for FaultInject<S, Req>
+where
+    S: Service<Req>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = FaultInjectFuture<S, Req>;
+
+    crate::forward_ready!(service);
+
+    fn call(&self, req: Req) -> Self::Future {
+        let state = match self.handle.next_fault() {
+            None => State::Call {
+                fut: self.service.call(req),
+                delay: None,
+            },
+
+            Some(Fault::Delay(dur)) => {
+                let sleep = self.handle.inner.borrow().sleep.clone();
+
+                State::Call {
+                    fut: self.service.call(req),
+                    delay: Some(sleep(dur)),
+                }
+            }
+
+            Some(Fault::Error(err)) => State::Error { err: Some(err) },
+
+            Some(Fault::Drop) => State::Dropped,
+        };
+
+        FaultInjectFuture { state }
+    }
+}
+
+pin_project! {
+    /// Response future of [`FaultInject`].
+    pub struct FaultInjectFuture<S, Req>
+    where
+        S: Service<Req>,
+    {
+        #[pin]
+        state: State<S, Req>,
+    }
+}
+
+pin_project! {
+    #[project = StateProj]
+    enum State<S, Req>
+    where
+        S: Service<Req>,
+    {
+        // inner service was called, with a delay before its response is polled
+        Call {
+            #[pin]
+            fut: S::Future,
+            delay: Option<Sleep>,
+        },
+        Error {
+            err: Option<S::Error>,
+        },
+        Dropped,
+    }
+}
+
+impl<S, Req> Future for FaultInjectFuture<S, Req>
+where
+    S: Service<Req>,
+{
+    type Output = Result<S::Response, S::Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().state.project() {
+            StateProj::Call { fut, delay } => {
+                if let Some(sleep) = delay {
+                    match sleep.as_mut().poll(cx) {
+                        Poll::Ready(()) => *delay = None,
+                        Poll::Pending => return Poll::Pending,
+                    }
+                }
+
+                fut.poll(cx)
+            }
+
+            StateProj::Error { err } => {
+                Poll::Ready(Err(err.take().expect("polled after completion")))
+            }
+
+            StateProj::Dropped => Poll::Pending,
+        }
+    }
+}
+
+/// Small non-cryptographic PRNG (xorshift64*), sufficient for picking faults.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // state must never be zero
+        Self(seed | 1)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Returns a number uniformly distributed in `0.0..1.0`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use futures_util::future::lazy;
+
+    use super::*;
+    use crate::{apply, fn_factory, fn_service, ServiceFactory};
+
+    fn double() -> impl Service<u32, Response = u32, Error = &'static str> {
+        fn_service(|req: u32| async move { Ok(req * 2) })
+    }
+
+    #[actix_rt::test]
+    async fn scripted_faults() {
+        let handle = FaultHandle::new(actix_rt::time::sleep);
+        let service = FaultInject::new(double(), handle.clone());
+
+        handle.push(Fault::Error("boom"));
+        handle.push(Fault::Drop);
+        handle.push(Fault::Delay(Duration::from_millis(20)));
+
+        assert_eq!(service.call(1).await, Err("boom"));
+
+        let mut dropped = Box::pin(service.call(2));
+        assert!(lazy(|cx| dropped.as_mut().poll(cx)).await.is_pending());
+
+        let start = actix_rt::time::Instant::now();
+        assert_eq!(service.call(3).await, Ok(6));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        assert_eq!(service.call(4).await, Ok(8));
+        assert_eq!(handle.injected(), 3);
+    }
+
+    #[actix_rt::test]
+    async fn random_faults_are_reproducible() {
+        async fn run(seed: u64) -> Vec<Result<u32, &'static str>> {
+            let handle = FaultHandle::new(actix_rt::time::sleep);
+            handle.set_random(RandomFaults::new().error(0.5, || "boom").seed(seed));
+
+            let service = FaultInject::new(double(), handle);
+
+            let mut res = Vec::new();
+            for req in 0..32 {
+                res.push(service.call(req).await);
+            }
+            res
+        }
+
+        let res = run(7).await;
+        let errors = res.iter().filter(|res| res.is_err()).count();
+        assert!(errors > 0 && errors < 32);
+        assert_eq!(res, run(7).await);
+    }
+
+    #[actix_rt::test]
+    async fn applied_to_factory() {
+        let handle = FaultHandle::new(actix_rt::time::sleep);
+        let factory = apply(
+            handle.clone(),
+            fn_factory(|| async { Ok::<_, ()>(double()) }),
+        );
+
+        let a = factory.new_service(()).await.unwrap();
+        let b = factory.new_service(()).await.unwrap();
+
+        handle.push(Fault::Error("boom"));
+        assert_eq!(b.call(1).await, Err("boom"));
+        assert_eq!(a.call(1).await, Ok(2));
+
+        handle.set_random(RandomFaults::new().error(1.0, || "always"));
+        assert_eq!(a.call(1).await, Err("always"));
+
+        handle.clear();
+        assert_eq!(a.call(1).await, Ok(2));
+    }
+}
@@ -21,6 +21,7 @@ mod apply;
 mod apply_cfg;
 pub mod boxed;
 mod ext;
+pub mod fault;
 mod fn_service;
 mod macros;
 mod map;
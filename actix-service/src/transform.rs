@@ -229,6 +229,7 @@ mod tests {
     use crate::Service;
 
     // pseudo-doctest for Transform trait
+    #[allow(dead_code)]
     pub struct TimeoutTransform {
         timeout: Duration,
     }
@@ -250,6 +251,7 @@ mod tests {
     }
 
     // pseudo-doctest for Transform trait
+    #[allow(dead_code)]
     pub struct Timeout<S> {
         service: S,
         _timeout: Duration,
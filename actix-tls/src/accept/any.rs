@@ -0,0 +1,134 @@
+//! TLS stream that is generic over the acceptor backend.
+
+use std::{
+    io::{self, IoSlice},
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_rt::net::{ActixStream, Ready};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::TlsServerConnInfo;
+
+/// TLS stream accepted by any of the enabled backends.
+///
+/// Allows applications that choose a TLS backend at runtime to handle accepted streams with a
+/// single, non-generic type. Each backend's stream converts into this type with [`From`], so
+/// acceptors can be mapped to it using, for example, [`ServiceFactoryExt::map`].
+///
+/// [`ServiceFactoryExt::map`]: actix_service::ServiceFactoryExt::map
+// one stream is held per connection so avoiding boxing is worth the size difference
+#[allow(clippy::large_enum_variant)]
+pub enum AnyTlsStream<IO> {
+    /// Stream accepted by the `rustls` backend.
+    #[cfg(feature = "rustls")]
+    Rustls(super::rustls::TlsStream<IO>),
+
+    /// Stream accepted by the `openssl` backend.
+    #[cfg(feature = "openssl")]
+    Openssl(super::openssl::TlsStream<IO>),
+
+    /// Stream accepted by the `native-tls` backend.
+    #[cfg(feature = "native-tls")]
+    NativeTls(super::native_tls::TlsStream<IO>),
+}
+
+/// Evaluates an expression on the stream of whichever backend variant is active.
+macro_rules! dispatch {
+    ($this:expr, $stream:ident => $expr:expr) => {
+        match $this {
+            #[cfg(feature = "rustls")]
+            AnyTlsStream::Rustls($stream) => $expr,
+            #[cfg(feature = "openssl")]
+            AnyTlsStream::Openssl($stream) => $expr,
+            #[cfg(feature = "native-tls")]
+            AnyTlsStream::NativeTls($stream) => $expr,
+        }
+    };
+}
+
+#[cfg(feature = "rustls")]
+impl<IO> From<super::rustls::TlsStream<IO>> for AnyTlsStream<IO> {
+    fn from(stream: super::rustls::TlsStream<IO>) -> Self {
+        Self::Rustls(stream)
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl<IO> From<super::openssl::TlsStream<IO>> for AnyTlsStream<IO> {
+    fn from(stream: super::openssl::TlsStream<IO>) -> Self {
+        Self::Openssl(stream)
+    }
+}
+
+#[cfg(feature = "native-tls")]
+impl<IO> From<super::native_tls::TlsStream<IO>> for AnyTlsStream<IO> {
+    fn from(stream: super::native_tls::TlsStream<IO>) -> Self {
+        Self::NativeTls(stream)
+    }
+}
+
+impl<IO: ActixStream> AsyncRead for AnyTlsStream<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        dispatch!(self.get_mut(), stream => Pin::new(stream).poll_read(cx, buf))
+    }
+}
+
+impl<IO: ActixStream> AsyncWrite for AnyTlsStream<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        dispatch!(self.get_mut(), stream => Pin::new(stream).poll_write(cx, buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        dispatch!(self.get_mut(), stream => Pin::new(stream).poll_flush(cx))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        dispatch!(self.get_mut(), stream => Pin::new(stream).poll_shutdown(cx))
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        dispatch!(self.get_mut(), stream => Pin::new(stream).poll_write_vectored(cx, bufs))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        dispatch!(self, stream => stream.is_write_vectored())
+    }
+}
+
+impl<IO: ActixStream> ActixStream for AnyTlsStream<IO> {
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        dispatch!(self, stream => stream.poll_read_ready(cx))
+    }
+
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        dispatch!(self, stream => stream.poll_write_ready(cx))
+    }
+}
+
+impl<IO: ActixStream> TlsServerConnInfo for AnyTlsStream<IO> {
+    fn alpn_protocol(&self) -> Option<Vec<u8>> {
+        dispatch!(self, stream => stream.alpn_protocol())
+    }
+
+    fn server_name(&self) -> Option<String> {
+        dispatch!(self, stream => stream.server_name())
+    }
+
+    fn peer_certificate(&self) -> Option<Vec<u8>> {
+        dispatch!(self, stream => stream.peer_certificate())
+    }
+}
@@ -18,6 +18,16 @@ pub mod rustls;
 #[cfg(feature = "native-tls")]
 pub mod native_tls;
 
+pub mod peek;
+pub mod pipeline;
+pub mod proxy_protocol;
+
+#[cfg(any(feature = "openssl", feature = "rustls", feature = "native-tls"))]
+mod any;
+
+#[cfg(any(feature = "openssl", feature = "rustls", feature = "native-tls"))]
+pub use self::any::AnyTlsStream;
+
 pub(crate) static MAX_CONN: AtomicUsize = AtomicUsize::new(256);
 
 #[cfg(any(feature = "openssl", feature = "rustls", feature = "native-tls"))]
@@ -38,6 +48,26 @@ pub fn max_concurrent_tls_connect(num: usize) {
     MAX_CONN.store(num, Ordering::Relaxed);
 }
 
+/// Information about an accepted TLS connection that is exposed uniformly by all backends.
+///
+/// Implemented by the streams returned from each acceptor in this crate and by [`AnyTlsStream`].
+/// Values a backend can not provide are returned as `None`.
+#[cfg(any(feature = "openssl", feature = "rustls", feature = "native-tls"))]
+pub trait TlsServerConnInfo {
+    /// Returns the protocol negotiated through ALPN, if any.
+    ///
+    /// Always `None` for `native-tls` since its ALPN support is not enabled by this crate.
+    fn alpn_protocol(&self) -> Option<Vec<u8>>;
+
+    /// Returns the server name requested by the client through SNI, if any.
+    ///
+    /// Always `None` for `native-tls`, which does not expose it.
+    fn server_name(&self) -> Option<String>;
+
+    /// Returns the DER encoded end-entity certificate presented by the client, if any.
+    fn peer_certificate(&self) -> Option<Vec<u8>>;
+}
+
 /// TLS handshake error, TLS timeout, or inner service error.
 ///
 /// All TLS acceptors from this crate will return the `SvcErr` type parameter as [`Infallible`],
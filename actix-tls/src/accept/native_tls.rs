@@ -23,7 +23,7 @@ use futures_core::future::LocalBoxFuture;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 use tokio_native_tls::{native_tls::Error, TlsAcceptor};
 
-use super::{TlsError, DEFAULT_TLS_HANDSHAKE_TIMEOUT, MAX_CONN_COUNTER};
+use super::{TlsError, TlsServerConnInfo, DEFAULT_TLS_HANDSHAKE_TIMEOUT, MAX_CONN_COUNTER};
 
 pub mod reexports {
     //! Re-exports from `native-tls` that are useful for acceptors.
@@ -87,6 +87,20 @@ impl<IO: ActixStream> ActixStream for TlsStream<IO> {
     }
 }
 
+impl<IO: ActixStream> TlsServerConnInfo for TlsStream<IO> {
+    fn alpn_protocol(&self) -> Option<Vec<u8>> {
+        None
+    }
+
+    fn server_name(&self) -> Option<String> {
+        None
+    }
+
+    fn peer_certificate(&self) -> Option<Vec<u8>> {
+        self.get_ref().peer_certificate().ok()??.to_der().ok()
+    }
+}
+
 /// Accept TLS connections via the `native-tls` crate.
 pub struct Acceptor {
     acceptor: TlsAcceptor,
@@ -20,11 +20,11 @@ use actix_utils::{
     counter::{Counter, CounterGuard},
     future::{ready, Ready as FutReady},
 };
-use openssl::ssl::{Error, Ssl, SslAcceptor};
+use openssl::ssl::{Error, NameType, Ssl, SslAcceptor};
 use pin_project_lite::pin_project;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
 
-use super::{TlsError, DEFAULT_TLS_HANDSHAKE_TIMEOUT, MAX_CONN_COUNTER};
+use super::{TlsError, TlsServerConnInfo, DEFAULT_TLS_HANDSHAKE_TIMEOUT, MAX_CONN_COUNTER};
 
 pub mod reexports {
     //! Re-exports from `openssl` that are useful for acceptors.
@@ -90,6 +90,22 @@ impl<IO: ActixStream> ActixStream for TlsStream<IO> {
     }
 }
 
+impl<IO: ActixStream> TlsServerConnInfo for TlsStream<IO> {
+    fn alpn_protocol(&self) -> Option<Vec<u8>> {
+        self.ssl().selected_alpn_protocol().map(ToOwned::to_owned)
+    }
+
+    fn server_name(&self) -> Option<String> {
+        self.ssl()
+            .servername(NameType::HOST_NAME)
+            .map(ToOwned::to_owned)
+    }
+
+    fn peer_certificate(&self) -> Option<Vec<u8>> {
+        self.ssl().peer_certificate()?.to_der().ok()
+    }
+}
+
 /// Accept TLS connections via the `openssl` crate.
 pub struct Acceptor {
     acceptor: SslAcceptor,
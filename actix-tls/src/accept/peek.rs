@@ -0,0 +1,472 @@
+//! Protocol detection by peeking at the start of accepted connections.
+//!
+//! See [`Peek`] for main service factory docs.
+
+use std::{
+    convert::Infallible,
+    error::Error,
+    fmt,
+    future::Future,
+    io::{self, IoSlice},
+    pin::Pin,
+    rc::Rc,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_rt::{
+    net::{ActixStream, Ready},
+    time::{sleep, Sleep},
+};
+use actix_service::{Service, ServiceFactory};
+use actix_utils::future::{ready, Ready as FutReady};
+use futures_core::future::LocalBoxFuture;
+use pin_project_lite::pin_project;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+const DEFAULT_PEEK_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Returns true if `peeked` looks like the start of a TLS handshake.
+///
+/// Checks for the TLS handshake record type and a TLS/SSLv3 major version, so at least one byte
+/// should be peeked. Useful as a [`Peek::route`] predicate for serving TLS and plaintext
+/// connections on the same port.
+pub fn is_tls_handshake(peeked: &[u8]) -> bool {
+    matches!(peeked, [0x16] | [0x16, 0x03, ..])
+}
+
+/// Accepted stream whose peeked bytes are read again before any further data.
+pub struct PeekedStream<IO> {
+    io: IO,
+    peeked: Vec<u8>,
+    /// Number of peeked bytes that have been read back.
+    read: usize,
+}
+
+impl<IO> PeekedStream<IO> {
+    /// Constructs stream that yields `peeked` before reading from `io`.
+    pub fn new(io: IO, peeked: Vec<u8>) -> Self {
+        Self {
+            io,
+            peeked,
+            read: 0,
+        }
+    }
+
+    /// Returns the peeked bytes that have not been read back yet.
+    pub fn peeked(&self) -> &[u8] {
+        &self.peeked[self.read..]
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &IO {
+        &self.io
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    ///
+    /// Reading directly from the underlying stream skips any peeked bytes not yet read back.
+    pub fn get_mut(&mut self) -> &mut IO {
+        &mut self.io
+    }
+
+    /// Returns the underlying stream and the peeked bytes that have not been read back yet.
+    pub fn into_parts(mut self) -> (IO, Vec<u8>) {
+        self.peeked.drain(..self.read);
+        (self.io, self.peeked)
+    }
+}
+
+impl<IO: fmt::Debug> fmt::Debug for PeekedStream<IO> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PeekedStream")
+            .field("io", &self.io)
+            .field("peeked", &self.peeked())
+            .finish()
+    }
+}
+
+impl<IO: ActixStream> AsyncRead for PeekedStream<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        if this.read < this.peeked.len() {
+            let peeked = &this.peeked[this.read..];
+            let len = peeked.len().min(buf.remaining());
+            buf.put_slice(&peeked[..len]);
+            this.read += len;
+
+            if this.read == this.peeked.len() {
+                // release the buffer once all peeked bytes are read back
+                this.peeked = Vec::new();
+                this.read = 0;
+            }
+
+            return Poll::Ready(Ok(()));
+        }
+
+        Pin::new(&mut this.io).poll_read(cx, buf)
+    }
+}
+
+impl<IO: ActixStream> AsyncWrite for PeekedStream<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+}
+
+impl<IO: ActixStream> ActixStream for PeekedStream<IO> {
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        if self.peeked().is_empty() {
+            IO::poll_read_ready(&self.io, cx)
+        } else {
+            Poll::Ready(Ok(Ready::READABLE))
+        }
+    }
+
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        IO::poll_write_ready(&self.io, cx)
+    }
+}
+
+/// Peek at the first bytes of accepted connections without consuming them.
+///
+/// Responds with a [`PeekedStream`] holding up to the configured number of bytes, fewer only if
+/// the peer closes its end of the connection first. Use [`route`](Self::route) to pass streams to
+/// one of two services depending on the peeked bytes, e.g., to serve TLS and plaintext connections
+/// on a single port:
+///
+/// ```ignore
+/// let factory = Peek::new(1).route(is_tls_handshake, tls_service, plaintext_service);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Peek {
+    len: usize,
+    timeout: Duration,
+}
+
+impl Peek {
+    /// Constructs service factory that peeks at the first `len` bytes of each stream.
+    ///
+    /// # Panics
+    /// Panics if `len` is zero.
+    pub fn new(len: usize) -> Self {
+        assert!(len > 0, "peek length must be non-zero");
+
+        Self {
+            len,
+            timeout: DEFAULT_PEEK_TIMEOUT,
+        }
+    }
+
+    /// Limit the amount of time that the service will wait for the peer to send enough bytes.
+    ///
+    /// Default timeout is 3 seconds.
+    pub fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Routes peeked streams to `matched` if `predicate` returns true for the peeked bytes, or to
+    /// `unmatched` otherwise.
+    pub fn route<P, A, B>(self, predicate: P, matched: A, unmatched: B) -> PeekRouter<P, A, B>
+    where
+        P: Fn(&[u8]) -> bool,
+    {
+        PeekRouter {
+            peek: self,
+            predicate: Rc::new(predicate),
+            matched,
+            unmatched,
+        }
+    }
+}
+
+impl<IO: ActixStream> ServiceFactory<IO> for Peek {
+    type Response = PeekedStream<IO>;
+    type Error = PeekError<Infallible>;
+    type Config = ();
+    type Service = PeekService;
+    type InitError = ();
+    type Future = FutReady<Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        ready(Ok(PeekService {
+            len: self.len,
+            timeout: self.timeout,
+        }))
+    }
+}
+
+/// Service that peeks at the start of streams.
+#[derive(Debug, Clone)]
+pub struct PeekService {
+    len: usize,
+    timeout: Duration,
+}
+
+impl<IO: ActixStream> Service<IO> for PeekService {
+    type Response = PeekedStream<IO>;
+    type Error = PeekError<Infallible>;
+    type Future = PeekFut<IO>;
+
+    actix_service::always_ready!();
+
+    fn call(&self, io: IO) -> Self::Future {
+        PeekFut {
+            io: Some(io),
+            buf: vec![0; self.len],
+            filled: 0,
+            timeout: sleep(self.timeout),
+        }
+    }
+}
+
+pin_project! {
+    /// Peek future for [`PeekService`].
+    #[doc(hidden)]
+    pub struct PeekFut<IO> {
+        io: Option<IO>,
+        buf: Vec<u8>,
+        filled: usize,
+        #[pin]
+        timeout: Sleep,
+    }
+}
+
+impl<IO: ActixStream> Future for PeekFut<IO> {
+    type Output = Result<PeekedStream<IO>, PeekError<Infallible>>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let io = this.io.as_mut().expect("PeekFut polled after completion");
+
+        while *this.filled < this.buf.len() {
+            let mut buf = ReadBuf::new(&mut this.buf[*this.filled..]);
+
+            match Pin::new(&mut *io).poll_read(cx, &mut buf) {
+                // peer closed its end before sending enough bytes
+                Poll::Ready(Ok(())) if buf.filled().is_empty() => break,
+                Poll::Ready(Ok(())) => *this.filled += buf.filled().len(),
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(PeekError::Io(err))),
+                Poll::Pending => {
+                    return this.timeout.poll(cx).map(|_| Err(PeekError::Timeout));
+                }
+            }
+        }
+
+        let mut peeked = std::mem::take(this.buf);
+        peeked.truncate(*this.filled);
+
+        Poll::Ready(Ok(PeekedStream::new(this.io.take().unwrap(), peeked)))
+    }
+}
+
+/// Service factory routing peeked streams to one of two services.
+///
+/// Created by [`Peek::route`].
+pub struct PeekRouter<P, A, B> {
+    peek: Peek,
+    predicate: Rc<P>,
+    matched: A,
+    unmatched: B,
+}
+
+impl<P, A: Clone, B: Clone> Clone for PeekRouter<P, A, B> {
+    fn clone(&self) -> Self {
+        Self {
+            peek: self.peek.clone(),
+            predicate: self.predicate.clone(),
+            matched: self.matched.clone(),
+            unmatched: self.unmatched.clone(),
+        }
+    }
+}
+
+impl<IO, P, A, B> ServiceFactory<IO> for PeekRouter<P, A, B>
+where
+    IO: ActixStream + 'static,
+    P: Fn(&[u8]) -> bool + 'static,
+    A: ServiceFactory<PeekedStream<IO>, Config = ()>,
+    A::Service: 'static,
+    A::Future: 'static,
+    B: ServiceFactory<
+        PeekedStream<IO>,
+        Config = (),
+        Response = A::Response,
+        Error = A::Error,
+        InitError = A::InitError,
+    >,
+    B::Service: 'static,
+    B::Future: 'static,
+{
+    type Response = A::Response;
+    type Error = PeekError<A::Error>;
+    type Config = ();
+    type Service = PeekRouterService<P, A::Service, B::Service>;
+    type InitError = A::InitError;
+    type Future = LocalBoxFuture<'static, Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        let peek = PeekService {
+            len: self.peek.len,
+            timeout: self.peek.timeout,
+        };
+        let predicate = self.predicate.clone();
+        let matched = self.matched.new_service(());
+        let unmatched = self.unmatched.new_service(());
+
+        Box::pin(async move {
+            Ok(PeekRouterService {
+                peek,
+                predicate,
+                matched: Rc::new(matched.await?),
+                unmatched: Rc::new(unmatched.await?),
+            })
+        })
+    }
+}
+
+/// Service routing peeked streams to one of two services.
+pub struct PeekRouterService<P, A, B> {
+    peek: PeekService,
+    predicate: Rc<P>,
+    matched: Rc<A>,
+    unmatched: Rc<B>,
+}
+
+impl<IO, P, A, B> Service<IO> for PeekRouterService<P, A, B>
+where
+    IO: ActixStream + 'static,
+    P: Fn(&[u8]) -> bool + 'static,
+    A: Service<PeekedStream<IO>> + 'static,
+    B: Service<PeekedStream<IO>, Response = A::Response, Error = A::Error> + 'static,
+{
+    type Response = A::Response;
+    type Error = PeekError<A::Error>;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let matched = self.matched.poll_ready(cx).map_err(PeekError::Service)?;
+        let unmatched = self.unmatched.poll_ready(cx).map_err(PeekError::Service)?;
+
+        if matched.is_ready() && unmatched.is_ready() {
+            Poll::Ready(Ok(()))
+        } else {
+            Poll::Pending
+        }
+    }
+
+    fn call(&self, io: IO) -> Self::Future {
+        let peek = self.peek.call(io);
+        let predicate = self.predicate.clone();
+        let matched = self.matched.clone();
+        let unmatched = self.unmatched.clone();
+
+        Box::pin(async move {
+            let stream = peek.await.map_err(PeekError::into_service_error)?;
+
+            let res = if predicate(stream.peeked()) {
+                matched.call(stream).await
+            } else {
+                unmatched.call(stream).await
+            };
+
+            res.map_err(PeekError::Service)
+        })
+    }
+}
+
+/// Peek I/O error, peek timeout, or inner service error.
+///
+/// The [`Peek`] service returns the `SvcErr` type parameter as [`Infallible`], which can be cast
+/// to your own service type, inferred or otherwise, using [`into_service_error`].
+///
+/// [`into_service_error`]: Self::into_service_error
+#[derive(Debug)]
+pub enum PeekError<SvcErr> {
+    /// Peer did not send enough bytes in time.
+    Timeout,
+
+    /// Wraps I/O errors encountered while peeking.
+    Io(io::Error),
+
+    /// Wraps service errors.
+    Service(SvcErr),
+}
+
+impl PeekError<Infallible> {
+    /// Casts the infallible service error type returned from [`Peek`] into caller's type.
+    pub fn into_service_error<SvcErr>(self) -> PeekError<SvcErr> {
+        match self {
+            Self::Timeout => PeekError::Timeout,
+            Self::Io(err) => PeekError::Io(err),
+            Self::Service(err) => match err {},
+        }
+    }
+}
+
+impl<SvcErr> fmt::Display for PeekError<SvcErr> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout => f.write_str("Peeking at stream has timed-out"),
+            Self::Io(_) => f.write_str("Peeking at stream failed"),
+            Self::Service(_) => f.write_str("Service error"),
+        }
+    }
+}
+
+impl<SvcErr> Error for PeekError<SvcErr>
+where
+    SvcErr: Error + 'static,
+{
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PeekError::Io(err) => Some(err),
+            PeekError::Service(err) => Some(err),
+            PeekError::Timeout => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tls_handshake_detection() {
+        assert!(is_tls_handshake(b"\x16"));
+        assert!(is_tls_handshake(b"\x16\x03\x01\x02\x00"));
+        assert!(!is_tls_handshake(b""));
+        assert!(!is_tls_handshake(b"\x16\x01"));
+        assert!(!is_tls_handshake(b"GET / HTTP/1.1"));
+    }
+}
@@ -0,0 +1,283 @@
+//! Declarative composition of accept-side stages.
+//!
+//! See [`AcceptorPipeline`] for main docs.
+
+use std::{error::Error, fmt, marker::PhantomData};
+
+use actix_service::{Service, ServiceFactory, ServiceFactoryExt as _};
+use actix_utils::future::{ok, Ready};
+
+/// Builder for chains of acceptor stages, ending in the service handling connections.
+///
+/// Accept-side stages, such as [`ProxyProtocolAcceptor`], [`Peek`], and the TLS acceptors, each
+/// take a stream and respond with a wrapping stream. Chaining them by hand requires mapping each
+/// stage's error type and nesting `and_then` calls, which gets hard to read for more than two
+/// stages. The pipeline lists stages in the order streams pass through them instead:
+///
+/// ```ignore
+/// AcceptorPipeline::new()                      // TcpStream
+///     .stage(ProxyProtocolAcceptor::new())     // -> ProxiedStream<TcpStream>
+///     .stage(Peek::new(1))                     // -> PeekedStream<ProxiedStream<TcpStream>>
+///     .stage(rustls::Acceptor::new(config))    // -> TlsStream<PeekedStream<..>>
+///     .finish(service)                         // handles TlsStream<PeekedStream<..>>
+/// ```
+///
+/// Each stage must accept the stream type responded with by the previous stage, so ordering
+/// mistakes are caught at compile time. Errors of all stages are boxed into a
+/// [`PipelineError::Stage`], while errors of the final service are kept as
+/// [`PipelineError::Service`].
+///
+/// Pipelines are service factories themselves, responding with the stream of the last stage, so
+/// they can also be nested, e.g., as branches of [`Peek::route`].
+///
+/// # Examples
+/// ```
+/// use actix_rt::net::TcpStream;
+/// use actix_server::Server;
+/// use actix_service::fn_service;
+/// use actix_tls::accept::{
+///     peek::{Peek, PeekedStream},
+///     pipeline::AcceptorPipeline,
+///     proxy_protocol::{ProxiedStream, ProxyProtocolAcceptor},
+/// };
+///
+/// # fn build() -> std::io::Result<Server> {
+/// let srv = Server::build()
+///     .bind("app", ("127.0.0.1", 8080), || {
+///         AcceptorPipeline::<TcpStream, _>::new()
+///             .stage(ProxyProtocolAcceptor::new())
+///             .stage(Peek::new(1))
+///             .finish(fn_service(
+///                 |stream: PeekedStream<ProxiedStream<TcpStream>>| async move {
+///                     println!("client: {:?}", stream.get_ref().source_addr());
+///                     Ok::<_, std::io::Error>(())
+///                 },
+///             ))
+///     })?
+///     .run();
+/// # Ok(srv)
+/// # }
+/// ```
+///
+/// [`ProxyProtocolAcceptor`]: super::proxy_protocol::ProxyProtocolAcceptor
+/// [`Peek`]: super::peek::Peek
+/// [`Peek::route`]: super::peek::Peek::route
+pub struct AcceptorPipeline<IO, F> {
+    factory: F,
+    _io: PhantomData<fn(IO)>,
+}
+
+impl<IO: 'static> AcceptorPipeline<IO, Identity> {
+    /// Constructs empty pipeline for streams of type `IO`.
+    pub fn new() -> Self {
+        Self {
+            factory: Identity,
+            _io: PhantomData,
+        }
+    }
+}
+
+impl<IO: 'static> Default for AcceptorPipeline<IO, Identity> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<IO, F> AcceptorPipeline<IO, F>
+where
+    IO: 'static,
+    F: ServiceFactory<IO, Config = (), Error = Box<dyn Error>, InitError = ()>,
+{
+    /// Appends `stage`, which is passed the streams responded with by the previous stage.
+    pub fn stage<S>(
+        self,
+        stage: S,
+    ) -> AcceptorPipeline<
+        IO,
+        impl ServiceFactory<
+            IO,
+            Response = S::Response,
+            Error = Box<dyn Error>,
+            Config = (),
+            InitError = (),
+        >,
+    >
+    where
+        S: ServiceFactory<F::Response, Config = (), InitError = ()>,
+        S::Error: Error + 'static,
+    {
+        AcceptorPipeline {
+            factory: self.factory.and_then(stage.map_err(box_error)),
+            _io: PhantomData,
+        }
+    }
+
+    /// Completes pipeline with `service`, which handles the streams responded with by the last
+    /// stage.
+    pub fn finish<S>(
+        self,
+        service: S,
+    ) -> impl ServiceFactory<
+        IO,
+        Response = S::Response,
+        Error = PipelineError<S::Error>,
+        Config = (),
+        InitError = (),
+    >
+    where
+        S: ServiceFactory<F::Response, Config = (), InitError = ()>,
+    {
+        self.factory
+            .map_err(PipelineError::Stage)
+            .and_then(service.map_err(PipelineError::Service))
+    }
+}
+
+impl<IO, F> ServiceFactory<IO> for AcceptorPipeline<IO, F>
+where
+    F: ServiceFactory<IO, Config = ()>,
+{
+    type Response = F::Response;
+    type Error = F::Error;
+    type Config = ();
+    type Service = F::Service;
+    type InitError = F::InitError;
+    type Future = F::Future;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        self.factory.new_service(())
+    }
+}
+
+impl<IO, F: Clone> Clone for AcceptorPipeline<IO, F> {
+    fn clone(&self) -> Self {
+        Self {
+            factory: self.factory.clone(),
+            _io: PhantomData,
+        }
+    }
+}
+
+impl<IO, F> fmt::Debug for AcceptorPipeline<IO, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AcceptorPipeline").finish_non_exhaustive()
+    }
+}
+
+fn box_error<E: Error + 'static>(err: E) -> Box<dyn Error> {
+    Box::new(err)
+}
+
+/// Start of an [`AcceptorPipeline`], passing streams through as is.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Identity;
+
+impl<IO> ServiceFactory<IO> for Identity {
+    type Response = IO;
+    type Error = Box<dyn Error>;
+    type Config = ();
+    type Service = Identity;
+    type InitError = ();
+    type Future = Ready<Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        ok(Identity)
+    }
+}
+
+impl<IO> Service<IO> for Identity {
+    type Response = IO;
+    type Error = Box<dyn Error>;
+    type Future = Ready<Result<IO, Self::Error>>;
+
+    actix_service::always_ready!();
+
+    fn call(&self, io: IO) -> Self::Future {
+        ok(io)
+    }
+}
+
+/// Error of an acceptor stage or the final service of an [`AcceptorPipeline`].
+#[derive(Debug)]
+pub enum PipelineError<SvcErr> {
+    /// Wraps errors of acceptor stages, e.g., TLS handshake errors.
+    Stage(Box<dyn Error>),
+
+    /// Wraps errors of the final service.
+    Service(SvcErr),
+}
+
+impl<SvcErr> fmt::Display for PipelineError<SvcErr> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Stage(err) => write!(f, "Acceptor stage error: {err}"),
+            Self::Service(_) => f.write_str("Service error"),
+        }
+    }
+}
+
+impl<SvcErr: Error + 'static> Error for PipelineError<SvcErr> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Stage(err) => Some(&**err),
+            Self::Service(err) => Some(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use actix_service::fn_service;
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct Rejected;
+
+    impl fmt::Display for Rejected {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("rejected")
+        }
+    }
+
+    impl Error for Rejected {}
+
+    #[actix_rt::test]
+    async fn chains_stages() {
+        let factory = AcceptorPipeline::<u32, _>::new()
+            .stage(fn_service(|n: u32| async move {
+                if n == 0 {
+                    Err(Rejected)
+                } else {
+                    Ok(n.to_string())
+                }
+            }))
+            .stage(fn_service(|s: String| async move {
+                Ok::<_, io::Error>(format!("<{s}>"))
+            }))
+            .finish(fn_service(|s: String| async move {
+                if s.len() > 4 {
+                    Err(s.len())
+                } else {
+                    Ok(s)
+                }
+            }));
+
+        let service = factory.new_service(()).await.unwrap();
+
+        assert_eq!(service.call(7).await.unwrap(), "<7>");
+
+        match service.call(0).await {
+            Err(PipelineError::Stage(err)) => assert_eq!(err.to_string(), "rejected"),
+            res => panic!("expected stage error, got {res:?}"),
+        }
+
+        match service.call(100).await {
+            Err(PipelineError::Service(len)) => assert_eq!(len, 5),
+            res => panic!("expected service error, got {res:?}"),
+        }
+    }
+}
@@ -0,0 +1,690 @@
+//! PROXY protocol header parsing for connections accepted behind a load balancer.
+//!
+//! See [`ProxyProtocolAcceptor`] for main service factory docs.
+
+use std::{
+    error::Error,
+    fmt,
+    future::Future,
+    io::{self, IoSlice},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_rt::{
+    net::{ActixStream, Ready},
+    time::{sleep, Sleep},
+};
+use actix_service::{Service, ServiceFactory};
+use actix_utils::future::{ready, Ready as FutReady};
+use pin_project_lite::pin_project;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use super::peek::PeekedStream;
+
+const DEFAULT_HEADER_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Maximum length of a v1 header, including the trailing CRLF.
+const V1_MAX_LEN: usize = 107;
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+
+/// Length of the fixed part of a v2 header, up to and including the address length.
+const V2_FIXED_LEN: usize = 16;
+
+/// Version of the PROXY protocol used by a header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProxyVersion {
+    /// Human-readable text header.
+    V1,
+
+    /// Binary header.
+    V2,
+}
+
+/// Connection information sent by a proxy in a PROXY protocol header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProxyHeader {
+    version: ProxyVersion,
+    addrs: Option<(SocketAddr, SocketAddr)>,
+    tlvs: Vec<(u8, Vec<u8>)>,
+}
+
+impl ProxyHeader {
+    /// Returns the version of the PROXY protocol used by the header.
+    pub fn version(&self) -> ProxyVersion {
+        self.version
+    }
+
+    /// Returns the address of the client that connected to the proxy.
+    ///
+    /// Returns `None` for connections the proxy established on its own, such as health checks, and
+    /// for addresses that are not TCP over IPv4 or IPv6.
+    pub fn source_addr(&self) -> Option<SocketAddr> {
+        self.addrs.map(|(src, _)| src)
+    }
+
+    /// Returns the address on the proxy that the client connected to.
+    ///
+    /// Returns `None` in the same cases as [`source_addr`](Self::source_addr).
+    pub fn destination_addr(&self) -> Option<SocketAddr> {
+        self.addrs.map(|(_, dst)| dst)
+    }
+
+    /// Returns the value of the first type-length-value field of type `kind`.
+    ///
+    /// Only v2 headers carry TLVs, which hold additional information such as the ALPN protocol or
+    /// server name negotiated by a TLS-terminating proxy, or vendor specific data.
+    pub fn tlv(&self, kind: u8) -> Option<&[u8]> {
+        self.tlvs
+            .iter()
+            .find(|(k, _)| *k == kind)
+            .map(|(_, value)| value.as_slice())
+    }
+
+    /// Returns iterator over the type and value of all type-length-value fields.
+    pub fn tlvs(&self) -> impl Iterator<Item = (u8, &[u8])> {
+        self.tlvs
+            .iter()
+            .map(|(kind, value)| (*kind, value.as_slice()))
+    }
+}
+
+/// Accepted stream with the PROXY protocol header sent ahead of its data.
+///
+/// Reading from the stream yields the data sent after the header.
+pub struct ProxiedStream<IO> {
+    io: PeekedStream<IO>,
+    header: ProxyHeader,
+}
+
+impl<IO> ProxiedStream<IO> {
+    /// Returns the PROXY protocol header sent ahead of the stream's data.
+    pub fn proxy_header(&self) -> &ProxyHeader {
+        &self.header
+    }
+
+    /// Returns the address of the client that connected to the proxy, if it was sent.
+    ///
+    /// See [`ProxyHeader::source_addr`].
+    pub fn source_addr(&self) -> Option<SocketAddr> {
+        self.header.source_addr()
+    }
+
+    /// Returns the address on the proxy that the client connected to, if it was sent.
+    ///
+    /// See [`ProxyHeader::destination_addr`].
+    pub fn destination_addr(&self) -> Option<SocketAddr> {
+        self.header.destination_addr()
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &IO {
+        self.io.get_ref()
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    ///
+    /// Reading directly from the underlying stream skips any data that was read along with the
+    /// header but not read back yet.
+    pub fn get_mut(&mut self) -> &mut IO {
+        self.io.get_mut()
+    }
+
+    /// Returns the stream, yielding data read along with the header first, and the header.
+    pub fn into_parts(self) -> (PeekedStream<IO>, ProxyHeader) {
+        (self.io, self.header)
+    }
+}
+
+impl<IO: fmt::Debug> fmt::Debug for ProxiedStream<IO> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxiedStream")
+            .field("io", &self.io)
+            .field("header", &self.header)
+            .finish()
+    }
+}
+
+impl<IO: ActixStream> AsyncRead for ProxiedStream<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_read(cx, buf)
+    }
+}
+
+impl<IO: ActixStream> AsyncWrite for ProxiedStream<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.get_mut().io).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+}
+
+impl<IO: ActixStream> ActixStream for ProxiedStream<IO> {
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        self.io.poll_read_ready(cx)
+    }
+
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        self.io.poll_write_ready(cx)
+    }
+}
+
+/// Read the PROXY protocol header sent by a load balancer ahead of each accepted connection.
+///
+/// Accepts both the v1 (text) and v2 (binary) formats and responds with a [`ProxiedStream`]
+/// exposing the original client address. Connections that do not start with a valid header are
+/// rejected, so the acceptor must only be used on listeners that are reachable exclusively through
+/// the proxy; otherwise clients could spoof their address.
+///
+/// The acceptor can be composed ahead of a TLS acceptor from this crate, whose stream then wraps
+/// the proxied stream:
+///
+/// ```ignore
+/// let factory = ProxyProtocolAcceptor::new()
+///     .map_err(|err| log::error!("{err}"))
+///     .and_then(tls_acceptor.map_err(|err| log::error!("{err:?}")))
+///     .and_then(|tls_stream: TlsStream<ProxiedStream<TcpStream>>| async move {
+///         let client_addr = tls_stream.get_ref().0.source_addr();
+///         // ...
+///     });
+/// ```
+#[derive(Debug, Clone)]
+pub struct ProxyProtocolAcceptor {
+    timeout: Duration,
+}
+
+impl ProxyProtocolAcceptor {
+    /// Constructs PROXY protocol acceptor service factory.
+    pub fn new() -> Self {
+        Self {
+            timeout: DEFAULT_HEADER_TIMEOUT,
+        }
+    }
+
+    /// Limit the amount of time that the acceptor will wait for the header to be received.
+    ///
+    /// Default timeout is 3 seconds.
+    pub fn set_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl Default for ProxyProtocolAcceptor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<IO: ActixStream> ServiceFactory<IO> for ProxyProtocolAcceptor {
+    type Response = ProxiedStream<IO>;
+    type Error = ProxyProtocolError;
+    type Config = ();
+    type Service = ProxyProtocolService;
+    type InitError = ();
+    type Future = FutReady<Result<Self::Service, Self::InitError>>;
+
+    fn new_service(&self, _: ()) -> Self::Future {
+        ready(Ok(ProxyProtocolService {
+            timeout: self.timeout,
+        }))
+    }
+}
+
+/// Service that reads the PROXY protocol header of streams.
+#[derive(Debug, Clone)]
+pub struct ProxyProtocolService {
+    timeout: Duration,
+}
+
+impl<IO: ActixStream> Service<IO> for ProxyProtocolService {
+    type Response = ProxiedStream<IO>;
+    type Error = ProxyProtocolError;
+    type Future = ProxyProtocolFut<IO>;
+
+    actix_service::always_ready!();
+
+    fn call(&self, io: IO) -> Self::Future {
+        ProxyProtocolFut {
+            io: Some(io),
+            buf: Vec::new(),
+            timeout: sleep(self.timeout),
+        }
+    }
+}
+
+pin_project! {
+    /// Header reading future for [`ProxyProtocolService`].
+    #[doc(hidden)]
+    pub struct ProxyProtocolFut<IO> {
+        io: Option<IO>,
+        buf: Vec<u8>,
+        #[pin]
+        timeout: Sleep,
+    }
+}
+
+impl<IO: ActixStream> Future for ProxyProtocolFut<IO> {
+    type Output = Result<ProxiedStream<IO>, ProxyProtocolError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let io = this
+            .io
+            .as_mut()
+            .expect("ProxyProtocolFut polled after completion");
+
+        let (header, len) = loop {
+            if let Some(parsed) = parse_header(this.buf).map_err(ProxyProtocolError::Malformed)? {
+                break parsed;
+            }
+
+            let mut chunk = [0; 512];
+            let mut buf = ReadBuf::new(&mut chunk);
+
+            match Pin::new(&mut *io).poll_read(cx, &mut buf) {
+                Poll::Ready(Ok(())) if buf.filled().is_empty() => {
+                    let err = io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "connection closed before PROXY protocol header was received",
+                    );
+                    return Poll::Ready(Err(ProxyProtocolError::Io(err)));
+                }
+                Poll::Ready(Ok(())) => this.buf.extend_from_slice(buf.filled()),
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(ProxyProtocolError::Io(err))),
+                Poll::Pending => {
+                    return this
+                        .timeout
+                        .poll(cx)
+                        .map(|_| Err(ProxyProtocolError::Timeout));
+                }
+            }
+        };
+
+        // data read past the header is yielded by the stream again
+        let rest = this.buf.split_off(len);
+        let io = PeekedStream::new(this.io.take().unwrap(), rest);
+
+        Poll::Ready(Ok(ProxiedStream { io, header }))
+    }
+}
+
+/// Parses a header from the start of `buf`, returning the header and its length, or `None` if more
+/// data is needed.
+fn parse_header(buf: &[u8]) -> Result<Option<(ProxyHeader, usize)>, &'static str> {
+    let prefix_len = buf.len().min(V1_PREFIX.len());
+    if buf[..prefix_len] == V1_PREFIX[..prefix_len] {
+        return if buf.len() < V1_PREFIX.len() {
+            Ok(None)
+        } else {
+            parse_v1(buf)
+        };
+    }
+
+    let prefix_len = buf.len().min(V2_SIGNATURE.len());
+    if buf[..prefix_len] == V2_SIGNATURE[..prefix_len] {
+        return parse_v2(buf);
+    }
+
+    Err("missing PROXY protocol signature")
+}
+
+fn parse_v1(buf: &[u8]) -> Result<Option<(ProxyHeader, usize)>, &'static str> {
+    let end = match buf.windows(2).position(|window| window == b"\r\n") {
+        Some(end) => end,
+        None if buf.len() >= V1_MAX_LEN => return Err("v1 header is too long"),
+        None => return Ok(None),
+    };
+
+    if end + 2 > V1_MAX_LEN {
+        return Err("v1 header is too long");
+    }
+
+    let line = std::str::from_utf8(&buf[V1_PREFIX.len()..end]).map_err(|_| "invalid v1 header")?;
+    let mut parts = line.split(' ');
+
+    let addrs = match parts.next() {
+        // remaining fields, if any, must be ignored
+        Some("UNKNOWN") => None,
+
+        Some(proto @ ("TCP4" | "TCP6")) => {
+            let mut next = || parts.next().ok_or("missing v1 header field");
+
+            let src_ip = next()?
+                .parse::<IpAddr>()
+                .map_err(|_| "invalid v1 address")?;
+            let dst_ip = next()?
+                .parse::<IpAddr>()
+                .map_err(|_| "invalid v1 address")?;
+            let src_port = parse_v1_port(next()?)?;
+            let dst_port = parse_v1_port(next()?)?;
+
+            if parts.next().is_some() {
+                return Err("unexpected v1 header field");
+            }
+
+            let is_v4 = proto == "TCP4";
+            if src_ip.is_ipv4() != is_v4 || dst_ip.is_ipv4() != is_v4 {
+                return Err("v1 address does not match protocol");
+            }
+
+            Some((
+                SocketAddr::new(src_ip, src_port),
+                SocketAddr::new(dst_ip, dst_port),
+            ))
+        }
+
+        _ => return Err("unsupported v1 protocol"),
+    };
+
+    let header = ProxyHeader {
+        version: ProxyVersion::V1,
+        addrs,
+        tlvs: Vec::new(),
+    };
+
+    Ok(Some((header, end + 2)))
+}
+
+fn parse_v1_port(port: &str) -> Result<u16, &'static str> {
+    // leading zeros and signs are not allowed
+    if port.is_empty()
+        || (port.len() > 1 && port.starts_with('0'))
+        || !port.bytes().all(|b| b.is_ascii_digit())
+    {
+        return Err("invalid v1 port");
+    }
+
+    port.parse().map_err(|_| "invalid v1 port")
+}
+
+fn parse_v2(buf: &[u8]) -> Result<Option<(ProxyHeader, usize)>, &'static str> {
+    if buf.len() < V2_FIXED_LEN {
+        return Ok(None);
+    }
+
+    let ver_cmd = buf[12];
+    let family = buf[13];
+    let len = u16::from_be_bytes([buf[14], buf[15]]) as usize;
+
+    if ver_cmd >> 4 != 2 {
+        return Err("unsupported v2 version");
+    }
+
+    let is_local = match ver_cmd & 0x0f {
+        0x0 => true,
+        0x1 => false,
+        _ => return Err("unsupported v2 command"),
+    };
+
+    if buf.len() < V2_FIXED_LEN + len {
+        return Ok(None);
+    }
+
+    let body = &buf[V2_FIXED_LEN..V2_FIXED_LEN + len];
+
+    let addrs_len = match family {
+        // unspecified
+        0x00 => 0,
+        // TCP and UDP over IPv4
+        0x11 | 0x12 => 12,
+        // TCP and UDP over IPv6
+        0x21 | 0x22 => 36,
+        // stream and datagram over Unix sockets
+        0x31 | 0x32 => 216,
+        _ => return Err("unsupported v2 address family"),
+    };
+
+    if body.len() < addrs_len {
+        return Err("v2 addresses are truncated");
+    }
+
+    let (addrs, tlvs) = body.split_at(addrs_len);
+
+    let addrs = match family {
+        // addresses of local connections must be ignored
+        _ if is_local => None,
+
+        0x11 => {
+            let src = Ipv4Addr::new(addrs[0], addrs[1], addrs[2], addrs[3]);
+            let dst = Ipv4Addr::new(addrs[4], addrs[5], addrs[6], addrs[7]);
+            let src_port = u16::from_be_bytes([addrs[8], addrs[9]]);
+            let dst_port = u16::from_be_bytes([addrs[10], addrs[11]]);
+
+            Some((
+                SocketAddr::new(src.into(), src_port),
+                SocketAddr::new(dst.into(), dst_port),
+            ))
+        }
+
+        0x21 => {
+            let src = <[u8; 16]>::try_from(&addrs[..16]).unwrap();
+            let dst = <[u8; 16]>::try_from(&addrs[16..32]).unwrap();
+            let src_port = u16::from_be_bytes([addrs[32], addrs[33]]);
+            let dst_port = u16::from_be_bytes([addrs[34], addrs[35]]);
+
+            Some((
+                SocketAddr::new(Ipv6Addr::from(src).into(), src_port),
+                SocketAddr::new(Ipv6Addr::from(dst).into(), dst_port),
+            ))
+        }
+
+        _ => None,
+    };
+
+    let header = ProxyHeader {
+        version: ProxyVersion::V2,
+        addrs,
+        tlvs: parse_v2_tlvs(tlvs)?,
+    };
+
+    Ok(Some((header, V2_FIXED_LEN + len)))
+}
+
+fn parse_v2_tlvs(mut buf: &[u8]) -> Result<Vec<(u8, Vec<u8>)>, &'static str> {
+    let mut tlvs = Vec::new();
+
+    while !buf.is_empty() {
+        if buf.len() < 3 {
+            return Err("v2 TLV is truncated");
+        }
+
+        let kind = buf[0];
+        let len = u16::from_be_bytes([buf[1], buf[2]]) as usize;
+
+        if buf.len() < 3 + len {
+            return Err("v2 TLV is truncated");
+        }
+
+        tlvs.push((kind, buf[3..3 + len].to_vec()));
+        buf = &buf[3 + len..];
+    }
+
+    Ok(tlvs)
+}
+
+/// PROXY protocol header I/O error, timeout, or malformed header.
+#[derive(Debug)]
+pub enum ProxyProtocolError {
+    /// Header was not received in time.
+    Timeout,
+
+    /// Wraps I/O errors encountered while reading the header.
+    Io(io::Error),
+
+    /// Header is missing or malformed; contains the reason it was rejected.
+    Malformed(&'static str),
+}
+
+impl fmt::Display for ProxyProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Timeout => f.write_str("Reading PROXY protocol header has timed-out"),
+            Self::Io(_) => f.write_str("Reading PROXY protocol header failed"),
+            Self::Malformed(reason) => write!(f, "Malformed PROXY protocol header: {reason}"),
+        }
+    }
+}
+
+impl Error for ProxyProtocolError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Timeout | Self::Malformed(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(addr: &str) -> Option<SocketAddr> {
+        Some(addr.parse().unwrap())
+    }
+
+    fn v2(ver_cmd: u8, family: u8, body: &[u8]) -> Vec<u8> {
+        let mut buf = V2_SIGNATURE.to_vec();
+        buf.extend_from_slice(&[ver_cmd, family]);
+        buf.extend_from_slice(&(body.len() as u16).to_be_bytes());
+        buf.extend_from_slice(body);
+        buf
+    }
+
+    #[test]
+    fn parses_v1() {
+        let buf = b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\nGET /";
+        let (header, len) = parse_header(buf).unwrap().unwrap();
+        assert_eq!(header.version(), ProxyVersion::V1);
+        assert_eq!(header.source_addr(), addr("192.0.2.1:56324"));
+        assert_eq!(header.destination_addr(), addr("198.51.100.1:443"));
+        assert_eq!(&buf[len..], b"GET /");
+
+        let buf = b"PROXY TCP6 2001:db8::1 2001:db8::2 1 65535\r\n";
+        let (header, _) = parse_header(buf).unwrap().unwrap();
+        assert_eq!(header.source_addr(), addr("[2001:db8::1]:1"));
+
+        let buf = b"PROXY UNKNOWN ffff::1 ffff::2 1 2\r\n";
+        let (header, len) = parse_header(buf).unwrap().unwrap();
+        assert_eq!(header.source_addr(), None);
+        assert_eq!(len, buf.len());
+    }
+
+    #[test]
+    fn parses_v2() {
+        let mut body = vec![192, 0, 2, 1, 198, 51, 100, 1];
+        body.extend_from_slice(&56324u16.to_be_bytes());
+        body.extend_from_slice(&443u16.to_be_bytes());
+        // ALPN TLV
+        body.extend_from_slice(&[0x01, 0x00, 0x02, b'h', b'2']);
+
+        let mut buf = v2(0x21, 0x11, &body);
+        let header_len = buf.len();
+        buf.extend_from_slice(b"data");
+
+        let (header, len) = parse_header(&buf).unwrap().unwrap();
+        assert_eq!(header.version(), ProxyVersion::V2);
+        assert_eq!(header.source_addr(), addr("192.0.2.1:56324"));
+        assert_eq!(header.destination_addr(), addr("198.51.100.1:443"));
+        assert_eq!(header.tlv(0x01), Some(&b"h2"[..]));
+        assert_eq!(header.tlvs().count(), 1);
+        assert_eq!(len, header_len);
+
+        let mut body = Ipv6Addr::LOCALHOST.octets().to_vec();
+        body.extend_from_slice(&Ipv6Addr::UNSPECIFIED.octets());
+        body.extend_from_slice(&[0, 80, 0, 81]);
+        let (header, _) = parse_header(&v2(0x21, 0x21, &body)).unwrap().unwrap();
+        assert_eq!(header.source_addr(), addr("[::1]:80"));
+        assert_eq!(header.destination_addr(), addr("[::]:81"));
+
+        // addresses of local connections are ignored
+        let (header, _) = parse_header(&v2(0x20, 0x11, &[0; 12])).unwrap().unwrap();
+        assert_eq!(header.source_addr(), None);
+    }
+
+    #[test]
+    fn incomplete_headers() {
+        let v1 = b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\n";
+        let v2 = v2(0x21, 0x11, &[0; 12]);
+
+        for len in 0..v1.len() {
+            assert_eq!(parse_header(&v1[..len]), Ok(None));
+        }
+
+        for len in 0..v2.len() {
+            assert_eq!(parse_header(&v2[..len]), Ok(None));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_headers() {
+        let too_long = [V1_PREFIX, &[b'A'; V1_MAX_LEN]].concat();
+
+        let malformed: &[&[u8]] = &[
+            b"GET / HTTP/1.1\r\n",
+            b"PROXY UDP4 192.0.2.1 198.51.100.1 1 2\r\n",
+            b"PROXY TCP4 192.0.2.1 198.51.100.1 1\r\n",
+            b"PROXY TCP4 192.0.2.1 198.51.100.1 1 2 3\r\n",
+            b"PROXY TCP4 2001:db8::1 198.51.100.1 1 2\r\n",
+            b"PROXY TCP4 192.0.2.1 198.51.100.1 01 2\r\n",
+            b"PROXY TCP4 192.0.2.1 198.51.100.1 1 65536\r\n",
+            &too_long,
+        ];
+
+        for buf in malformed {
+            assert!(
+                parse_header(buf).is_err(),
+                "{:?}",
+                String::from_utf8_lossy(buf)
+            );
+        }
+
+        // unsupported version and command
+        assert!(parse_header(&v2(0x11, 0x11, &[0; 12])).is_err());
+        assert!(parse_header(&v2(0x22, 0x11, &[0; 12])).is_err());
+        // truncated addresses and TLV
+        assert!(parse_header(&v2(0x21, 0x11, &[0; 8])).is_err());
+        assert!(parse_header(&v2(0x21, 0x11, &[0; 14])).is_err());
+    }
+
+    #[test]
+    fn error_display() {
+        let err = ProxyProtocolError::Malformed("v1 header is too long");
+        assert_eq!(
+            err.to_string(),
+            "Malformed PROXY protocol header: v1 header is too long"
+        );
+    }
+}
@@ -3,11 +3,13 @@
 //! See [`Acceptor`] for main service factory docs.
 
 use std::{
+    collections::{BTreeMap, HashMap},
     convert::Infallible,
+    fmt,
     future::Future,
     io::{self, IoSlice},
     pin::Pin,
-    sync::Arc,
+    sync::{Arc, Mutex, RwLock},
     task::{Context, Poll},
     time::Duration,
 };
@@ -18,19 +20,31 @@ use actix_rt::{
 };
 use actix_service::{Service, ServiceFactory};
 use actix_utils::{
+    budget::{BudgetExceeded, MemoryBudget, Reservation},
     counter::{Counter, CounterGuard},
     future::{ready, Ready as FutReady},
 };
 use pin_project_lite::pin_project;
 use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
-use tokio_rustls::{rustls::ServerConfig, Accept, TlsAcceptor};
+use tokio_rustls::{
+    rustls::{
+        server::{self, ClientHello, ResolvesServerCert},
+        sign::{self, CertifiedKey, SignError},
+        Certificate, PrivateKey, ServerConfig,
+    },
+    Accept, LazyConfigAcceptor, TlsAcceptor,
+};
 
-use super::{TlsError, DEFAULT_TLS_HANDSHAKE_TIMEOUT, MAX_CONN_COUNTER};
+use super::{TlsError, TlsServerConnInfo, DEFAULT_TLS_HANDSHAKE_TIMEOUT, MAX_CONN_COUNTER};
 
 pub mod reexports {
     //! Re-exports from `rustls` that are useful for acceptors.
 
-    pub use tokio_rustls::rustls::ServerConfig;
+    pub use tokio_rustls::rustls::{
+        server::ResolvesServerCert,
+        sign::{CertifiedKey, SignError},
+        Certificate, PrivateKey, ServerConfig,
+    };
 }
 
 /// Wraps a `rustls` based async TLS stream in order to implement [`ActixStream`].
@@ -89,21 +103,103 @@ impl<IO: ActixStream> ActixStream for TlsStream<IO> {
     }
 }
 
+impl<IO> TlsStream<IO> {
+    /// Limits the TLS buffers of this stream to `limit` bytes, accounted against `budget`.
+    ///
+    /// Caps the buffers holding unsent plaintext and TLS records at `limit` bytes and reserves
+    /// that many bytes from `budget` up front, so that the returned reservation should be held for
+    /// as long as the stream is in use. Writes are accepted only partially while the buffers are
+    /// full instead of growing them. Incoming data is already bounded by `rustls` to a single TLS
+    /// record.
+    ///
+    /// Returns an error, leaving the stream unchanged, if `limit` bytes cannot be reserved.
+    ///
+    /// Only the `rustls` backend exposes its buffer sizes; the `openssl` and `native-tls` backends
+    /// do not support memory budgets.
+    pub fn reserve_buffers(
+        &mut self,
+        budget: &MemoryBudget,
+        limit: usize,
+    ) -> Result<Reservation, BudgetExceeded> {
+        let reservation = budget.reserve(limit)?;
+        self.get_mut().1.set_buffer_limit(Some(limit));
+        Ok(reservation)
+    }
+}
+
+impl<IO: ActixStream> TlsServerConnInfo for TlsStream<IO> {
+    fn alpn_protocol(&self) -> Option<Vec<u8>> {
+        self.get_ref().1.alpn_protocol().map(ToOwned::to_owned)
+    }
+
+    fn server_name(&self) -> Option<String> {
+        self.get_ref().1.sni_hostname().map(ToOwned::to_owned)
+    }
+
+    fn peer_certificate(&self) -> Option<Vec<u8>> {
+        let certs = self.get_ref().1.peer_certificates()?;
+        certs.first().map(|cert| cert.0.clone())
+    }
+}
+
 /// Accept TLS connections via the `rustls` crate.
 pub struct Acceptor {
-    config: Arc<ServerConfig>,
+    config: Configs,
     handshake_timeout: Duration,
 }
 
+/// Source of the server configs used for handshakes.
+#[derive(Clone)]
+enum Configs {
+    Single(Arc<ServerConfig>),
+    Cache(ServerConfigCache),
+}
+
 impl Acceptor {
     /// Constructs `rustls` based acceptor service factory.
     pub fn new(config: ServerConfig) -> Self {
         Acceptor {
-            config: Arc::new(config),
+            config: Configs::Single(Arc::new(config)),
+            handshake_timeout: DEFAULT_TLS_HANDSHAKE_TIMEOUT,
+        }
+    }
+
+    /// Constructs `rustls` based acceptor service factory that takes the server config for each
+    /// handshake from `cache`.
+    ///
+    /// The client's hello message is read before choosing a config, so that handshakes for
+    /// different server names (SNI) can use entirely different configs. Handshakes for which the
+    /// cache returns no config are aborted.
+    ///
+    /// See [`ServerConfigCache`] for details.
+    pub fn with_config_cache(cache: ServerConfigCache) -> Self {
+        Acceptor {
+            config: Configs::Cache(cache),
             handshake_timeout: DEFAULT_TLS_HANDSHAKE_TIMEOUT,
         }
     }
 
+    /// Constructs `rustls` based acceptor service factory that selects the certificate to serve for
+    /// each handshake using `resolver`.
+    ///
+    /// The server config uses the safe default protocol versions and cipher suites of `rustls` and
+    /// does not request client certificates. To customize it, for example to set ALPN protocols,
+    /// build a config using [`ConfigBuilder::with_cert_resolver`] and pass it to [`new`].
+    ///
+    /// See [`ReloadableCertResolver`] for a resolver supporting per-hostname certificates that can
+    /// be replaced at runtime.
+    ///
+    /// [`ConfigBuilder::with_cert_resolver`]: tokio_rustls::rustls::ConfigBuilder::with_cert_resolver
+    /// [`new`]: Self::new
+    pub fn new_with_resolver(resolver: Arc<dyn ResolvesServerCert>) -> Self {
+        let config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_cert_resolver(resolver);
+
+        Self::new(config)
+    }
+
     /// Limit the amount of time that the acceptor will wait for a TLS handshake to complete.
     ///
     /// Default timeout is 3 seconds.
@@ -133,7 +229,7 @@ impl<IO: ActixStream> ServiceFactory<IO> for Acceptor {
     fn new_service(&self, _: ()) -> Self::Future {
         let res = MAX_CONN_COUNTER.with(|conns| {
             Ok(AcceptorService {
-                acceptor: self.config.clone().into(),
+                config: self.config.clone(),
                 conns: conns.clone(),
                 handshake_timeout: self.handshake_timeout,
             })
@@ -145,7 +241,7 @@ impl<IO: ActixStream> ServiceFactory<IO> for Acceptor {
 
 /// Rustls based acceptor service.
 pub struct AcceptorService {
-    acceptor: TlsAcceptor,
+    config: Configs,
     conns: Counter,
     handshake_timeout: Duration,
 }
@@ -164,8 +260,18 @@ impl<IO: ActixStream> Service<IO> for AcceptorService {
     }
 
     fn call(&self, req: IO) -> Self::Future {
+        let state = match &self.config {
+            Configs::Single(config) => {
+                AcceptState::Handshake(TlsAcceptor::from(config.clone()).accept(req))
+            }
+            Configs::Cache(cache) => AcceptState::ClientHello {
+                fut: LazyConfigAcceptor::new(server::Acceptor::default(), req),
+                cache: cache.clone(),
+            },
+        };
+
         AcceptFut {
-            fut: self.acceptor.accept(req),
+            state,
             timeout: sleep(self.handshake_timeout),
             _guard: self.conns.get(),
         }
@@ -176,22 +282,500 @@ pin_project! {
     /// Accept future for Rustls service.
     #[doc(hidden)]
     pub struct AcceptFut<IO: ActixStream> {
-        fut: Accept<IO>,
+        state: AcceptState<IO>,
         #[pin]
         timeout: Sleep,
         _guard: CounterGuard,
     }
 }
 
+enum AcceptState<IO> {
+    /// Reading client hello in order to look up server config in cache.
+    ClientHello {
+        fut: LazyConfigAcceptor<IO>,
+        cache: ServerConfigCache,
+    },
+
+    Handshake(Accept<IO>),
+}
+
 impl<IO: ActixStream> Future for AcceptFut<IO> {
     type Output = Result<TlsStream<IO>, TlsError<io::Error, Infallible>>;
 
     fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        let mut this = self.project();
-        match Pin::new(&mut this.fut).poll(cx) {
-            Poll::Ready(Ok(stream)) => Poll::Ready(Ok(TlsStream(stream))),
-            Poll::Ready(Err(err)) => Poll::Ready(Err(TlsError::Tls(err))),
-            Poll::Pending => this.timeout.poll(cx).map(|_| Err(TlsError::Timeout)),
+        let this = self.project();
+
+        loop {
+            match this.state {
+                AcceptState::ClientHello { fut, cache } => match Pin::new(fut).poll(cx) {
+                    Poll::Ready(Ok(start)) => {
+                        let config =
+                            cache
+                                .get(start.client_hello().server_name())
+                                .ok_or_else(|| {
+                                    io::Error::new(
+                                        io::ErrorKind::InvalidData,
+                                        "no server config for requested server name",
+                                    )
+                                });
+
+                        match config {
+                            Ok(config) => {
+                                *this.state = AcceptState::Handshake(start.into_stream(config));
+                            }
+                            Err(err) => return Poll::Ready(Err(TlsError::Tls(err))),
+                        }
+                    }
+                    Poll::Ready(Err(err)) => return Poll::Ready(Err(TlsError::Tls(err))),
+                    Poll::Pending => break,
+                },
+
+                AcceptState::Handshake(fut) => match Pin::new(fut).poll(cx) {
+                    Poll::Ready(Ok(stream)) => return Poll::Ready(Ok(TlsStream(stream))),
+                    Poll::Ready(Err(err)) => return Poll::Ready(Err(TlsError::Tls(err))),
+                    Poll::Pending => break,
+                },
+            }
+        }
+
+        this.timeout.poll(cx).map(|_| Err(TlsError::Timeout))
+    }
+}
+
+/// Cache of server configs, built lazily on the first handshake for each server name.
+///
+/// Servers hosting many (SNI) server names with different certificates, or different settings
+/// per name, would otherwise need to build a full [`ServerConfig`] for each of them up front. The
+/// cache calls a builder function with the server name of a handshake instead, the first time that
+/// name is requested, and keeps the resulting config for later handshakes. Once the cache holds
+/// `capacity` configs, the least recently used one is evicted to make room for a new one; it is
+/// rebuilt the next time its name is requested.
+///
+/// Server names are passed to the builder lowercased, or as `None` for clients that do not send a
+/// server name. Builders return `None` to abort handshakes for unknown names; these results are
+/// not cached. Hit, miss, and eviction counts are available from [`stats`](Self::stats).
+///
+/// The cache is a cheap to clone handle. Clones share the same configs, so one cache can be shared
+/// between the acceptors of all workers, and a handle kept outside of the server can
+/// [`invalidate`](Self::invalidate) configs, e.g., after renewing certificates.
+///
+/// # Examples
+/// ```
+/// use actix_tls::accept::rustls::{reexports::ServerConfig, Acceptor, ServerConfigCache};
+///
+/// # fn load_config(server_name: &str) -> Option<ServerConfig> { None }
+/// let cache = ServerConfigCache::new(1000, |server_name| {
+///     // e.g., load certificate of `server_name` from disk
+///     load_config(server_name?)
+/// });
+///
+/// // share cache between all acceptors, e.g. in each worker's service factory
+/// let acceptor = Acceptor::with_config_cache(cache.clone());
+///
+/// println!("cache hits: {}", cache.stats().hits());
+/// ```
+#[derive(Clone)]
+pub struct ServerConfigCache {
+    inner: Arc<CacheInner>,
+}
+
+type BuildConfig = dyn Fn(Option<&str>) -> Option<ServerConfig> + Send + Sync;
+
+struct CacheInner {
+    capacity: usize,
+    build: Box<BuildConfig>,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    tick: u64,
+    entries: HashMap<Option<String>, (Arc<ServerConfig>, u64)>,
+    // server names by the tick they were last used at, least recently used first
+    recency: BTreeMap<u64, Option<String>>,
+    stats: ConfigCacheStats,
+}
+
+impl CacheState {
+    /// Returns cached config for `key`, marking it as most recently used.
+    fn get(&mut self, key: &Option<String>) -> Option<Arc<ServerConfig>> {
+        self.tick += 1;
+
+        let (config, used) = self.entries.get_mut(key)?;
+        let key = self
+            .recency
+            .remove(used)
+            .expect("cached configs are ordered");
+        self.recency.insert(self.tick, key);
+        *used = self.tick;
+
+        Some(Arc::clone(config))
+    }
+
+    /// Caches `config` for `key`, evicting least recently used configs over `capacity`.
+    fn insert(&mut self, key: Option<String>, config: Arc<ServerConfig>, capacity: usize) {
+        self.tick += 1;
+
+        // config may have been built concurrently for another handshake
+        if let Some((_, used)) = self.entries.insert(key.clone(), (config, self.tick)) {
+            self.recency.remove(&used);
+        }
+        self.recency.insert(self.tick, key);
+
+        while self.entries.len() > capacity {
+            let oldest = *self
+                .recency
+                .keys()
+                .next()
+                .expect("cached configs are ordered");
+            let key = self.recency.remove(&oldest).unwrap();
+            self.entries.remove(&key);
+            self.stats.evictions += 1;
+        }
+    }
+}
+
+impl ServerConfigCache {
+    /// Constructs cache holding up to `capacity` configs, built by `build` for each server name.
+    ///
+    /// # Panics
+    /// Panics if `capacity` is 0.
+    pub fn new<F>(capacity: usize, build: F) -> Self
+    where
+        F: Fn(Option<&str>) -> Option<ServerConfig> + Send + Sync + 'static,
+    {
+        assert!(capacity > 0, "config cache capacity must be at least 1");
+
+        Self {
+            inner: Arc::new(CacheInner {
+                capacity,
+                build: Box::new(build),
+                state: Mutex::new(CacheState::default()),
+            }),
+        }
+    }
+
+    /// Returns the config for handshakes requesting `server_name`, building it if not cached.
+    ///
+    /// Pass `None` to get the config for clients that do not send a server name. Server names are
+    /// matched ignoring ASCII case.
+    ///
+    /// The builder is called without holding a lock on the cache, so handshakes for other names,
+    /// also on other workers, are not blocked while a config is built.
+    pub fn get(&self, server_name: Option<&str>) -> Option<Arc<ServerConfig>> {
+        let key = server_name.map(str::to_ascii_lowercase);
+
+        {
+            let mut state = self.inner.state.lock().unwrap();
+
+            if let Some(config) = state.get(&key) {
+                state.stats.hits += 1;
+                return Some(config);
+            }
+
+            state.stats.misses += 1;
+        }
+
+        let config = Arc::new((self.inner.build)(key.as_deref())?);
+
+        self.inner
+            .state
+            .lock()
+            .unwrap()
+            .insert(key, Arc::clone(&config), self.inner.capacity);
+
+        Some(config)
+    }
+
+    /// Removes the cached config for `server_name`, returning whether one was cached.
+    ///
+    /// The config is rebuilt on the next handshake requesting `server_name`. Established
+    /// connections keep using the config they were set up with.
+    pub fn invalidate(&self, server_name: Option<&str>) -> bool {
+        let key = server_name.map(str::to_ascii_lowercase);
+        let mut state = self.inner.state.lock().unwrap();
+
+        match state.entries.remove(&key) {
+            Some((_, used)) => {
+                state.recency.remove(&used);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes all cached configs.
+    pub fn clear(&self) {
+        let mut state = self.inner.state.lock().unwrap();
+        state.entries.clear();
+        state.recency.clear();
+    }
+
+    /// Returns counters of cache lookups and the number of cached configs.
+    pub fn stats(&self) -> ConfigCacheStats {
+        let state = self.inner.state.lock().unwrap();
+
+        ConfigCacheStats {
+            entries: state.entries.len(),
+            ..state.stats
         }
     }
 }
+
+impl fmt::Debug for ServerConfigCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ServerConfigCache")
+            .field("capacity", &self.inner.capacity)
+            .field("stats", &self.stats())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Counters of [`ServerConfigCache`] behavior, returned by [`ServerConfigCache::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConfigCacheStats {
+    hits: u64,
+    misses: u64,
+    evictions: u64,
+    entries: usize,
+}
+
+impl ConfigCacheStats {
+    /// Returns the number of lookups answered with a cached config.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Returns the number of lookups that called the builder, including those for which it
+    /// returned no config.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Returns the number of configs evicted to stay within the cache capacity.
+    ///
+    /// Configs removed with [`ServerConfigCache::invalidate`] or [`ServerConfigCache::clear`] are
+    /// not counted.
+    pub fn evictions(&self) -> u64 {
+        self.evictions
+    }
+
+    /// Returns the number of cached configs.
+    pub fn entries(&self) -> usize {
+        self.entries
+    }
+}
+
+/// Certificate resolver serving per-hostname certificates that can be replaced at runtime.
+///
+/// Certificates are chosen by the server name (SNI) sent by the client, falling back to a default
+/// certificate, if one is set, for unknown names and clients that send no server name. Handshakes
+/// for which no certificate is found are aborted.
+///
+/// The resolver is a cheap to clone handle. Clones share the same certificates, so a handle kept
+/// outside of the server can register new hostnames or swap in renewed certificates while workers
+/// are running; changes apply atomically to subsequent handshakes, while established connections
+/// keep using the certificate they were set up with.
+///
+/// # Examples
+/// ```
+/// use std::sync::Arc;
+///
+/// use actix_tls::accept::rustls::{
+///     reexports::{Certificate, PrivateKey},
+///     Acceptor, ReloadableCertResolver,
+/// };
+///
+/// # fn load() -> (Vec<Certificate>, PrivateKey) { unimplemented!() }
+/// # fn run() -> Result<(), Box<dyn std::error::Error>> {
+/// let resolver = ReloadableCertResolver::new();
+///
+/// let (certs, key) = load();
+/// resolver.insert("example.com", certs, key)?;
+///
+/// // share resolver between all acceptors, e.g. in each worker's service factory
+/// let acceptor = Acceptor::new_with_resolver(Arc::new(resolver.clone()));
+///
+/// // later, swap in a renewed certificate without restarting workers
+/// let (certs, key) = load();
+/// resolver.insert("example.com", certs, key)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Default)]
+pub struct ReloadableCertResolver {
+    store: Arc<RwLock<CertStore>>,
+}
+
+#[derive(Default)]
+struct CertStore {
+    default: Option<Arc<CertifiedKey>>,
+    by_name: HashMap<String, Arc<CertifiedKey>>,
+}
+
+impl ReloadableCertResolver {
+    /// Constructs new resolver without any certificates.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replaces the default certificate chain and private key.
+    ///
+    /// The default certificate is served to clients requesting a hostname without a certificate of
+    /// its own and to clients that do not send a server name.
+    ///
+    /// # Errors
+    /// Returns an error, leaving the served certificates unchanged, if the type of `key` is not
+    /// supported.
+    pub fn update(&self, certs: Vec<Certificate>, key: PrivateKey) -> Result<(), SignError> {
+        let certified_key = certified_key(certs, &key)?;
+        self.store.write().unwrap().default = Some(certified_key);
+        Ok(())
+    }
+
+    /// Removes the default certificate, returning whether one was set.
+    pub fn clear_default(&self) -> bool {
+        self.store.write().unwrap().default.take().is_some()
+    }
+
+    /// Sets the certificate chain and private key served for `server_name`, replacing any chain
+    /// already set for it.
+    ///
+    /// Server names are matched exactly, ignoring ASCII case.
+    ///
+    /// # Errors
+    /// Returns an error, leaving the served certificates unchanged, if the type of `key` is not
+    /// supported.
+    pub fn insert(
+        &self,
+        server_name: impl AsRef<str>,
+        certs: Vec<Certificate>,
+        key: PrivateKey,
+    ) -> Result<(), SignError> {
+        let certified_key = certified_key(certs, &key)?;
+
+        self.store
+            .write()
+            .unwrap()
+            .by_name
+            .insert(server_name.as_ref().to_ascii_lowercase(), certified_key);
+
+        Ok(())
+    }
+
+    /// Removes the certificate served for `server_name`, returning whether one was set.
+    pub fn remove(&self, server_name: impl AsRef<str>) -> bool {
+        self.store
+            .write()
+            .unwrap()
+            .by_name
+            .remove(&server_name.as_ref().to_ascii_lowercase())
+            .is_some()
+    }
+
+    /// Returns the certificate that would be served to a client requesting `server_name`.
+    ///
+    /// Pass `None` to look up the certificate served to clients that do not send a server name.
+    pub fn lookup(&self, server_name: Option<&str>) -> Option<Arc<CertifiedKey>> {
+        let store = self.store.read().unwrap();
+
+        server_name
+            .and_then(|name| store.by_name.get(&name.to_ascii_lowercase()))
+            .or(store.default.as_ref())
+            .cloned()
+    }
+}
+
+impl ResolvesServerCert for ReloadableCertResolver {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.lookup(client_hello.server_name())
+    }
+}
+
+impl fmt::Debug for ReloadableCertResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let store = self.store.read().unwrap();
+
+        f.debug_struct("ReloadableCertResolver")
+            .field("default", &store.default.is_some())
+            .field("server_names", &store.by_name.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+fn certified_key(
+    certs: Vec<Certificate>,
+    key: &PrivateKey,
+) -> Result<Arc<CertifiedKey>, SignError> {
+    let key = sign::any_supported_type(key)?;
+    Ok(Arc::new(CertifiedKey::new(certs, key)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ServerConfig {
+        ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_cert_resolver(Arc::new(ReloadableCertResolver::new()))
+    }
+
+    #[test]
+    fn config_cache_evicts_least_recently_used() {
+        let built = Arc::new(Mutex::new(Vec::new()));
+
+        let cache = ServerConfigCache::new(2, {
+            let built = Arc::clone(&built);
+
+            move |name| {
+                built.lock().unwrap().push(name.map(ToOwned::to_owned));
+                (name != Some("unknown.test")).then(config)
+            }
+        });
+
+        let a = cache.get(Some("a.test")).unwrap();
+        cache.get(Some("b.test")).unwrap();
+        assert!(Arc::ptr_eq(&a, &cache.get(Some("A.Test")).unwrap()));
+
+        // b is least recently used
+        cache.get(None).unwrap();
+        assert!(cache.get(Some("unknown.test")).is_none());
+        assert!(cache.get(Some("unknown.test")).is_none());
+        cache.get(Some("a.test")).unwrap();
+        cache.get(Some("b.test")).unwrap();
+
+        assert_eq!(
+            *built.lock().unwrap(),
+            [
+                Some("a.test"),
+                Some("b.test"),
+                None,
+                Some("unknown.test"),
+                Some("unknown.test"),
+                Some("b.test"),
+            ]
+            .map(|name| name.map(ToOwned::to_owned))
+        );
+
+        let stats = cache.stats();
+        assert_eq!(stats.hits(), 2);
+        assert_eq!(stats.misses(), 6);
+        assert_eq!(stats.evictions(), 2);
+        assert_eq!(stats.entries(), 2);
+
+        assert!(cache.invalidate(Some("B.TEST")));
+        assert!(!cache.invalidate(Some("b.test")));
+        assert_eq!(cache.stats().entries(), 1);
+
+        cache.clear();
+        assert_eq!(cache.stats().entries(), 0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn config_cache_zero_capacity() {
+        ServerConfigCache::new(0, |_| Some(config()));
+    }
+}
@@ -0,0 +1,217 @@
+//! The [`DnsCache`] type.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use actix_rt::time::Instant;
+use tokio::sync::OnceCell;
+use tracing::trace;
+
+/// Thread-safe cache of DNS lookup results.
+///
+/// A cache is a cheap to clone handle that can be shared between the resolvers of all server
+/// workers or arbiters by attaching clones of it with [`Resolver::with_cache`]. Concurrent lookups
+/// of the same host and port, including lookups from different threads, are deduplicated so that
+/// only one of them queries the underlying resolver while the others wait for its result.
+///
+/// Successful lookups are cached for the configured time-to-live. Failed lookups are not cached.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use actix_tls::connect::{Connector, DnsCache, Resolver};
+///
+/// let cache = DnsCache::new(Duration::from_secs(60));
+///
+/// // clone the cache into the connector of each worker
+/// let connector = Connector::new(Resolver::default().with_cache(cache.clone()));
+/// # let _ = connector;
+/// ```
+///
+/// [`Resolver::with_cache`]: super::Resolver::with_cache
+#[derive(Clone)]
+pub struct DnsCache {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    ttl: Duration,
+    entries: Mutex<HashMap<(String, u16), Arc<Entry>>>,
+}
+
+/// Cached lookup result, which is empty while the first lookup is in-flight or after it failed.
+#[derive(Default)]
+struct Entry {
+    addrs: OnceCell<(Vec<SocketAddr>, Instant)>,
+}
+
+impl Entry {
+    fn is_expired(&self, now: Instant) -> bool {
+        matches!(self.addrs.get(), Some((_, expires)) if *expires <= now)
+    }
+}
+
+impl DnsCache {
+    /// Constructs a new cache that keeps lookup results for `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                ttl,
+                entries: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// Removes all cached lookup results.
+    pub fn clear(&self) {
+        self.inner.entries.lock().unwrap().clear();
+    }
+
+    /// Returns the cached addresses of `host` and `port`, calling `lookup` if they are not cached.
+    ///
+    /// If another lookup of the same host and port is in-flight, waits for its result instead.
+    pub(crate) async fn lookup<F, Fut, E>(
+        &self,
+        host: &str,
+        port: u16,
+        lookup: F,
+    ) -> Result<Vec<SocketAddr>, E>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<Vec<SocketAddr>, E>>,
+    {
+        let entry = self.entry(host, port);
+
+        let (addrs, _) = entry
+            .addrs
+            .get_or_try_init(|| async {
+                let addrs = lookup().await?;
+                Ok((addrs, Instant::now() + self.inner.ttl))
+            })
+            .await?;
+
+        Ok(addrs.clone())
+    }
+
+    /// Returns the entry for `host` and `port`, replacing it if it has expired.
+    fn entry(&self, host: &str, port: u16) -> Arc<Entry> {
+        let now = Instant::now();
+        let mut entries = self.inner.entries.lock().unwrap();
+
+        if let Some(entry) = entries.get(&(host.to_owned(), port)) {
+            if !entry.is_expired(now) {
+                trace!("DNS cache: hit for host {:?}", host);
+                return Arc::clone(entry);
+            }
+        }
+
+        // drop expired entries and failed lookups no one is waiting on to bound the cache size
+        entries.retain(|_, entry| {
+            if entry.addrs.initialized() {
+                !entry.is_expired(now)
+            } else {
+                Arc::strong_count(entry) > 1
+            }
+        });
+
+        let entry = Arc::new(Entry::default());
+        entries.insert((host.to_owned(), port), Arc::clone(&entry));
+        entry
+    }
+}
+
+impl fmt::Debug for DnsCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DnsCache")
+            .field("ttl", &self.inner.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io,
+        sync::atomic::{AtomicUsize, Ordering},
+        thread,
+    };
+
+    use super::*;
+
+    fn addr() -> SocketAddr {
+        "127.0.0.1:80".parse().unwrap()
+    }
+
+    #[test]
+    fn deduplicates_lookups_across_threads() {
+        let cache = DnsCache::new(Duration::from_secs(60));
+        let lookups = Arc::new(AtomicUsize::new(0));
+
+        let handles = (0..4)
+            .map(|_| {
+                let cache = cache.clone();
+                let lookups = Arc::clone(&lookups);
+
+                thread::spawn(move || {
+                    actix_rt::System::new().block_on(async move {
+                        cache
+                            .lookup("example.com", 80, || async {
+                                lookups.fetch_add(1, Ordering::SeqCst);
+                                actix_rt::time::sleep(Duration::from_millis(100)).await;
+                                Ok::<_, io::Error>(vec![addr()])
+                            })
+                            .await
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for handle in handles {
+            assert_eq!(handle.join().unwrap().unwrap(), vec![addr()]);
+        }
+
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+    }
+
+    #[actix_rt::test]
+    async fn expires_and_skips_failures() {
+        let cache = DnsCache::new(Duration::from_millis(50));
+        let lookups = AtomicUsize::new(0);
+
+        let lookup = || async {
+            lookups.fetch_add(1, Ordering::SeqCst);
+            Ok::<_, io::Error>(vec![addr()])
+        };
+
+        // failures are not cached
+        let res = cache
+            .lookup("example.com", 80, || async {
+                Err(io::Error::new(io::ErrorKind::Other, "lookup failed"))
+            })
+            .await;
+        assert!(res.is_err());
+
+        cache.lookup("example.com", 80, lookup).await.unwrap();
+        cache.lookup("example.com", 80, lookup).await.unwrap();
+        assert_eq!(lookups.load(Ordering::SeqCst), 1);
+
+        // ports are cached separately
+        cache.lookup("example.com", 443, lookup).await.unwrap();
+        assert_eq!(lookups.load(Ordering::SeqCst), 2);
+
+        actix_rt::time::sleep(Duration::from_millis(100)).await;
+        cache.lookup("example.com", 80, lookup).await.unwrap();
+        assert_eq!(lookups.load(Ordering::SeqCst), 3);
+
+        cache.clear();
+        cache.lookup("example.com", 80, lookup).await.unwrap();
+        assert_eq!(lookups.load(Ordering::SeqCst), 4);
+    }
+}
@@ -4,8 +4,9 @@ use std::{
     net::SocketAddr,
 };
 
-#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash)]
 pub(crate) enum ConnectAddrs {
+    #[default]
     None,
     One(SocketAddr),
     // TODO: consider using smallvec
@@ -22,12 +23,6 @@ impl ConnectAddrs {
     }
 }
 
-impl Default for ConnectAddrs {
-    fn default() -> Self {
-        Self::None
-    }
-}
-
 impl From<Option<SocketAddr>> for ConnectAddrs {
     fn from(addr: Option<SocketAddr>) -> Self {
         match addr {
@@ -1,10 +1,11 @@
-use super::Host;
+use super::{Host, SrvTarget};
 
 /// Wraps underlying I/O and the connection request that initiated it.
 #[derive(Debug)]
 pub struct Connection<R, IO> {
     pub(crate) req: R,
     pub(crate) io: IO,
+    pub(crate) srv_target: Option<SrvTarget>,
 }
 
 impl_more::impl_deref_and_mut!(<R, IO> in Connection<R, IO> => io: IO);
@@ -12,7 +13,11 @@ impl_more::impl_deref_and_mut!(<R, IO> in Connection<R, IO> => io: IO);
 impl<R, IO> Connection<R, IO> {
     /// Construct new `Connection` from request and IO parts.
     pub fn new(req: R, io: IO) -> Self {
-        Self { req, io }
+        Self {
+            req,
+            io,
+            srv_target: None,
+        }
     }
 }
 
@@ -24,7 +29,13 @@ impl<R, IO> Connection<R, IO> {
 
     /// Replaces underlying IO, returning old IO and new `Connection`.
     pub fn replace_io<IO2>(self, io: IO2) -> (IO, Connection<R, IO2>) {
-        (self.io, Connection { io, req: self.req })
+        let conn = Connection {
+            io,
+            req: self.req,
+            srv_target: self.srv_target,
+        };
+
+        (self.io, conn)
     }
 
     /// Returns a shared reference to the underlying IO.
@@ -41,6 +52,12 @@ impl<R, IO> Connection<R, IO> {
     pub fn request(&self) -> &R {
         &self.req
     }
+
+    /// Returns the SRV target that the connection was established to, if connected using
+    /// [`ConnectInfo::set_srv_targets`](super::ConnectInfo::set_srv_targets).
+    pub fn srv_target(&self) -> Option<&SrvTarget> {
+        self.srv_target.as_ref()
+    }
 }
 
 impl<R: Host, IO> Connection<R, IO> {
@@ -1,5 +1,7 @@
 use std::{
     future::Future,
+    mem,
+    ops::RangeInclusive,
     pin::Pin,
     task::{Context, Poll},
 };
@@ -7,13 +9,15 @@ use std::{
 use actix_rt::net::TcpStream;
 use actix_service::{Service, ServiceFactory};
 use actix_utils::future::{ok, Ready};
-use futures_core::ready;
+use futures_core::{future::LocalBoxFuture, ready};
+use tracing::trace;
 
 use super::{
     error::ConnectError,
     resolver::{Resolver, ResolverService},
-    tcp::{TcpConnector, TcpConnectorService},
-    ConnectInfo, Connection, Host,
+    srv,
+    tcp::{HappyEyeballs, TcpConnector, TcpConnectorService},
+    ConnectInfo, Connection, ConnectorBuilder, Host,
 };
 
 /// Combined resolver and TCP connector service factory.
@@ -23,21 +27,54 @@ use super::{
 #[derive(Clone, Default)]
 pub struct Connector {
     resolver: Resolver,
+    tcp: TcpConnector,
 }
 
 impl Connector {
     /// Constructs new connector factory with the given resolver.
     pub fn new(resolver: Resolver) -> Self {
-        Connector { resolver }
+        Connector {
+            resolver,
+            tcp: TcpConnector::default(),
+        }
+    }
+
+    /// Races connection attempts to resolved addresses using Happy Eyeballs (RFC 8305).
+    ///
+    /// Applies to the TCP connection established before any TLS handshake, so it also takes
+    /// effect when the connector is wrapped in one of the TLS connectors.
+    ///
+    /// By default, addresses are tried sequentially in the order they were resolved.
+    pub fn happy_eyeballs(mut self, config: HappyEyeballs) -> Self {
+        self.tcp = self.tcp.happy_eyeballs(config);
+        self
+    }
+
+    /// Binds outgoing connections to a local port in `range`.
+    ///
+    /// See [`TcpConnector::local_port_range`] for details.
+    ///
+    /// # Panics
+    /// Panics if `range` is empty or includes port 0.
+    pub fn local_port_range(mut self, range: RangeInclusive<u16>) -> Self {
+        self.tcp = self.tcp.local_port_range(range);
+        self
     }
 
     /// Build connector service.
     pub fn service(&self) -> ConnectorService {
         ConnectorService {
-            tcp: TcpConnector::default().service(),
+            tcp: self.tcp.service(),
             resolver: self.resolver.service(),
         }
     }
+
+    /// Starts building a connector service that is wrapped in [`ConnectLayer`]s.
+    ///
+    /// [`ConnectLayer`]: super::ConnectLayer
+    pub fn builder(&self) -> ConnectorBuilder<ConnectorService> {
+        ConnectorBuilder::new(self.service())
+    }
 }
 
 impl<R: Host> ServiceFactory<ConnectInfo<R>> for Connector {
@@ -71,17 +108,73 @@ impl<R: Host> Service<ConnectInfo<R>> for ConnectorService {
     actix_service::always_ready!();
 
     fn call(&self, req: ConnectInfo<R>) -> Self::Future {
-        ConnectServiceResponse {
-            fut: ConnectFut::Resolve(self.resolver.call(req)),
-            tcp: self.tcp,
+        let fut = if req.srv_targets.is_empty() || req.addr.is_resolved() {
+            ConnectFut::Resolve(self.resolver.call(req))
+        } else {
+            ConnectFut::Srv(Box::pin(connect_srv(self.resolver.clone(), self.tcp, req)))
+        };
+
+        ConnectServiceResponse { fut, tcp: self.tcp }
+    }
+}
+
+/// Connects to the first reachable SRV target of `req`, trying targets in SRV order.
+async fn connect_srv<R: Host>(
+    resolver: ResolverService,
+    tcp: TcpConnectorService,
+    mut req: ConnectInfo<R>,
+) -> Result<Connection<R, TcpStream>, ConnectError> {
+    let targets = srv::order(mem::take(&mut req.srv_targets), srv::random);
+    let mut last_err = ConnectError::NoRecords;
+
+    for target in targets {
+        if target.is_unavailable() {
+            continue;
+        }
+
+        let mut info = ConnectInfo::new(target.target().to_owned()).set_port(target.port());
+        info.local_addr = req.local_addr;
+
+        let res = match resolver.call(info).await {
+            Ok(info) => tcp.call(info).await,
+            Err(err) => Err(err),
+        };
+
+        match res {
+            Ok(conn) => {
+                trace!(
+                    "TCP connector: connected to {:?} using SRV target {:?}",
+                    req.hostname(),
+                    target.target()
+                );
+
+                let (io, _) = conn.into_parts();
+                let mut conn = Connection::new(req.request, io);
+                conn.srv_target = Some(target);
+
+                return Ok(conn);
+            }
+
+            Err(err) => {
+                trace!(
+                    "TCP connector: failed to connect to SRV target {:?}: {}",
+                    target.target(),
+                    err
+                );
+
+                last_err = err;
+            }
         }
     }
+
+    Err(last_err)
 }
 
 /// Chains futures of resolve and connect steps.
 pub(crate) enum ConnectFut<R: Host> {
     Resolve(<ResolverService as Service<ConnectInfo<R>>>::Future),
     Connect(<TcpConnectorService as Service<ConnectInfo<R>>>::Future),
+    Srv(LocalBoxFuture<'static, Result<Connection<R, TcpStream>, ConnectError>>),
 }
 
 /// Container for the intermediate states of [`ConnectFut`].
@@ -103,6 +196,10 @@ impl<R: Host> ConnectFut<R> {
             ConnectFut::Connect(ref mut fut) => {
                 Pin::new(fut).poll(cx).map_ok(ConnectFutState::Connected)
             }
+
+            ConnectFut::Srv(ref mut fut) => {
+                fut.as_mut().poll(cx).map_ok(ConnectFutState::Connected)
+            }
         }
     }
 }
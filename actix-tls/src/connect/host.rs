@@ -1,5 +1,7 @@
 //! The [`Host`] trait.
 
+use std::borrow::Cow;
+
 /// An interface for types where host parts (hostname and port) can be derived.
 ///
 /// The [WHATWG URL Standard] defines the terminology used for this trait and its methods.
@@ -49,6 +51,27 @@ impl Host for &'static str {
     }
 }
 
+/// Converts `hostname` to its ASCII form for DNS resolution and SNI.
+///
+/// With the `idna` feature, internationalized domain names are converted to punycode, e.g.,
+/// `bücher.example` becomes `xn--bcher-kva.example`, and `None` is returned for names that fail
+/// IDNA validation. Without it, hostnames are passed through as is.
+pub(crate) fn ascii_hostname(hostname: &str) -> Option<Cow<'_, str>> {
+    if hostname.is_ascii() {
+        return Some(Cow::Borrowed(hostname));
+    }
+
+    #[cfg(feature = "idna")]
+    {
+        idna::domain_to_ascii(hostname).ok().map(Cow::Owned)
+    }
+
+    #[cfg(not(feature = "idna"))]
+    {
+        Some(Cow::Borrowed(hostname))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -68,4 +91,30 @@ mod tests {
         assert_connection_info_eq!("example.com:false", "example.com", None);
         assert_connection_info_eq!("example.com:false:false", "example.com", None);
     }
+
+    #[test]
+    fn ascii_hostnames_are_unchanged() {
+        assert!(matches!(
+            ascii_hostname("sub.example.com"),
+            Some(Cow::Borrowed("sub.example.com"))
+        ));
+    }
+
+    #[cfg(feature = "idna")]
+    #[test]
+    fn idn_hostnames_are_converted() {
+        assert_eq!(
+            ascii_hostname("bücher.example").unwrap(),
+            "xn--bcher-kva.example"
+        );
+        assert_eq!(
+            ascii_hostname("BÜCHER.example").unwrap(),
+            "xn--bcher-kva.example"
+        );
+        assert_eq!(
+            ascii_hostname("例え.テスト").unwrap(),
+            "xn--r8jz45g.xn--zckzah"
+        );
+        assert!(ascii_hostname("xn--bücher.example").is_none());
+    }
 }
@@ -10,7 +10,7 @@ use std::{
 
 use super::{
     connect_addrs::{ConnectAddrs, ConnectAddrsIter},
-    Host,
+    Host, SrvTarget,
 };
 
 /// Connection request information.
@@ -22,6 +22,7 @@ pub struct ConnectInfo<R> {
     pub(crate) port: u16,
     pub(crate) addr: ConnectAddrs,
     pub(crate) local_addr: Option<IpAddr>,
+    pub(crate) srv_targets: Vec<SrvTarget>,
 }
 
 impl<R: Host> ConnectInfo<R> {
@@ -34,6 +35,7 @@ impl<R: Host> ConnectInfo<R> {
             port: port.unwrap_or(0),
             addr: ConnectAddrs::None,
             local_addr: None,
+            srv_targets: Vec::new(),
         }
     }
 
@@ -47,6 +49,7 @@ impl<R: Host> ConnectInfo<R> {
             port: 0,
             addr: ConnectAddrs::One(addr),
             local_addr: None,
+            srv_targets: Vec::new(),
         }
     }
 
@@ -87,6 +90,20 @@ impl<R: Host> ConnectInfo<R> {
         self
     }
 
+    /// Set SRV record targets to connect to instead of resolving the request hostname.
+    ///
+    /// Overrides DNS resolution for this request: the [`Connector`](super::Connector) tries the
+    /// targets in SRV priority and weight order until connecting to one of them succeeds. See
+    /// [`SrvTarget`] for details. Ignored if addresses are already set, as well as by resolver
+    /// and TCP connector services used on their own.
+    pub fn set_srv_targets<I>(mut self, targets: I) -> Self
+    where
+        I: IntoIterator<Item = SrvTarget>,
+    {
+        self.srv_targets = targets.into_iter().collect();
+        self
+    }
+
     /// Returns a reference to the connection request.
     pub fn request(&self) -> &R {
         &self.request
@@ -120,12 +137,8 @@ impl<R: Host> ConnectInfo<R> {
     /// ```
     pub fn addrs(
         &self,
-    ) -> impl Iterator<Item = SocketAddr>
-           + ExactSizeIterator
-           + iter::FusedIterator
-           + Clone
-           + fmt::Debug
-           + '_ {
+    ) -> impl ExactSizeIterator<Item = SocketAddr> + iter::FusedIterator + Clone + fmt::Debug + '_
+    {
         match self.addr {
             ConnectAddrs::None => ConnectAddrsIter::None,
             ConnectAddrs::One(addr) => ConnectAddrsIter::One(addr),
@@ -151,12 +164,8 @@ impl<R: Host> ConnectInfo<R> {
     /// ```
     pub fn take_addrs(
         &mut self,
-    ) -> impl Iterator<Item = SocketAddr>
-           + ExactSizeIterator
-           + iter::FusedIterator
-           + Clone
-           + fmt::Debug
-           + 'static {
+    ) -> impl ExactSizeIterator<Item = SocketAddr> + iter::FusedIterator + Clone + fmt::Debug + 'static
+    {
         match mem::take(&mut self.addr) {
             ConnectAddrs::None => ConnectAddrsIter::None,
             ConnectAddrs::One(addr) => ConnectAddrsIter::One(addr),
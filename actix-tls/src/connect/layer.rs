@@ -0,0 +1,76 @@
+//! Composition of connector middleware.
+//!
+//! See [`ConnectorBuilder`] for main docs.
+
+/// Wraps a connector service in another service, adding behavior such as retries, proxying,
+/// or metrics.
+///
+/// Layers are applied synchronously when a connector stack is built with [`ConnectorBuilder`].
+/// One-off wrappers can be applied with a closure using [`ConnectorBuilder::layer_fn`] instead.
+pub trait ConnectLayer<S> {
+    /// The wrapped service.
+    type Service;
+
+    /// Wraps `service`.
+    fn layer(&self, service: S) -> Self::Service;
+}
+
+/// Builder for a connector service wrapped in a stack of [`ConnectLayer`]s.
+///
+/// Each layer wraps the service built so far, so layers added later are further out and see
+/// requests first.
+///
+/// # Examples
+/// ```
+/// use actix_service::{Service as _, ServiceExt as _};
+/// use actix_tls::connect::{ConnectError, ConnectInfo, Connector};
+///
+/// # async fn connect() {
+/// let connector = Connector::default()
+///     .builder()
+///     .layer_fn(|svc| svc.map_err(|err: ConnectError| err.to_string()))
+///     .finish();
+///
+/// let res = connector.call(ConnectInfo::new("example.com").set_port(80)).await;
+/// # let _ = res;
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConnectorBuilder<S> {
+    service: S,
+}
+
+impl<S> ConnectorBuilder<S> {
+    /// Constructs a builder starting from the given connector service.
+    ///
+    /// Use [`Connector::builder`](super::Connector::builder) to start from the default
+    /// resolver and TCP connector service.
+    pub fn new(service: S) -> Self {
+        Self { service }
+    }
+
+    /// Wraps the connector built so far in `layer`.
+    pub fn layer<L>(self, layer: L) -> ConnectorBuilder<L::Service>
+    where
+        L: ConnectLayer<S>,
+    {
+        ConnectorBuilder {
+            service: layer.layer(self.service),
+        }
+    }
+
+    /// Wraps the connector built so far using the given closure.
+    pub fn layer_fn<F, Out>(self, layer: F) -> ConnectorBuilder<Out>
+    where
+        F: FnOnce(S) -> Out,
+    {
+        ConnectorBuilder {
+            service: layer(self.service),
+        }
+    }
+
+    /// Returns the connector service wrapped in all added layers.
+    pub fn finish(self) -> S {
+        self.service
+    }
+}
@@ -11,14 +11,17 @@
 //!
 //! [`TcpStream`]: actix_rt::net::TcpStream
 
+mod cache;
 mod connect_addrs;
 mod connection;
 mod connector;
 mod error;
 mod host;
 mod info;
+mod layer;
 mod resolve;
 mod resolver;
+mod srv;
 pub mod tcp;
 
 #[cfg(feature = "uri")]
@@ -34,11 +37,15 @@ pub mod rustls;
 pub mod native_tls;
 
 pub use self::{
+    cache::DnsCache,
     connection::Connection,
     connector::{Connector, ConnectorService},
     error::ConnectError,
     host::Host,
     info::ConnectInfo,
+    layer::{ConnectLayer, ConnectorBuilder},
     resolve::Resolve,
     resolver::{Resolver, ResolverService},
+    srv::SrvTarget,
+    tcp::HappyEyeballs,
 };
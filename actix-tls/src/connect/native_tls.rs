@@ -2,7 +2,7 @@
 //!
 //! See [`TlsConnector`] for main connector service factory docs.
 
-use std::io;
+use std::{borrow::Cow, io};
 
 use actix_rt::net::ActixStream;
 use actix_service::{Service, ServiceFactory};
@@ -14,7 +14,7 @@ use tokio_native_tls::{
 };
 use tracing::trace;
 
-use crate::connect::{Connection, Host};
+use crate::connect::{host::ascii_hostname, Connection, Host};
 
 pub mod reexports {
     //! Re-exports from `native-tls` and `tokio-native-tls` that are useful for connectors.
@@ -74,8 +74,12 @@ where
 
         Box::pin(async move {
             trace!("TLS handshake start for: {:?}", stream.hostname());
+            // names failing IDNA validation are passed as is and fail certificate verification
+            let host =
+                ascii_hostname(stream.hostname()).unwrap_or(Cow::Borrowed(stream.hostname()));
+
             connector
-                .connect(stream.hostname(), io)
+                .connect(&host, io)
                 .await
                 .map(|res| {
                     trace!("TLS handshake success: {:?}", stream.hostname());
@@ -3,6 +3,7 @@
 //! See [`TlsConnector`] for main connector service factory docs.
 
 use std::{
+    borrow::Cow,
     future::Future,
     io,
     pin::Pin,
@@ -17,7 +18,7 @@ use openssl::ssl::SslConnector;
 use tokio_openssl::SslStream as AsyncSslStream;
 use tracing::trace;
 
-use crate::connect::{Connection, Host};
+use crate::connect::{host::ascii_hostname, Connection, Host};
 
 pub mod reexports {
     //! Re-exports from `openssl` and `tokio-openssl` that are useful for connectors.
@@ -98,7 +99,8 @@ where
         trace!("TLS handshake start for: {:?}", stream.hostname());
 
         let (io, stream) = stream.replace_io(());
-        let host = stream.hostname();
+        // names failing IDNA validation are passed as is and fail certificate verification
+        let host = ascii_hostname(stream.hostname()).unwrap_or(Cow::Borrowed(stream.hostname()));
 
         let config = self
             .connector
@@ -106,7 +108,7 @@ where
             .expect("SSL connect configuration was invalid.");
 
         let ssl = config
-            .into_ssl(host)
+            .into_ssl(&host)
             .expect("SSL connect configuration was invalid.");
 
         ConnectFut {
@@ -14,7 +14,7 @@ use actix_utils::future::{ok, Ready};
 use futures_core::{future::LocalBoxFuture, ready};
 use tracing::trace;
 
-use super::{ConnectError, ConnectInfo, Host, Resolve};
+use super::{host::ascii_hostname, ConnectError, ConnectInfo, DnsCache, Host, Resolve};
 
 /// DNS resolver service factory.
 #[derive(Clone, Default)]
@@ -30,6 +30,14 @@ impl Resolver {
         }
     }
 
+    /// Caches lookup results of this resolver in `cache`.
+    ///
+    /// The cache may be shared with other resolvers, including resolvers on other threads.
+    pub fn with_cache(mut self, cache: DnsCache) -> Self {
+        self.resolver.cache = Some(cache);
+        self
+    }
+
     /// Returns a new resolver service.
     pub fn service(&self) -> ResolverService {
         self.resolver.clone()
@@ -49,27 +57,23 @@ impl<R: Host> ServiceFactory<ConnectInfo<R>> for Resolver {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 enum ResolverKind {
     /// Built-in DNS resolver.
     ///
     /// See [`std::net::ToSocketAddrs`] trait.
+    #[default]
     Default,
 
     /// Custom, user-provided DNS resolver.
     Custom(Rc<dyn Resolve>),
 }
 
-impl Default for ResolverKind {
-    fn default() -> Self {
-        Self::Default
-    }
-}
-
 /// DNS resolver service.
 #[derive(Clone, Default)]
 pub struct ResolverService {
     kind: ResolverKind,
+    cache: Option<DnsCache>,
 }
 
 impl ResolverService {
@@ -77,20 +81,39 @@ impl ResolverService {
     pub fn custom(resolver: impl Resolve + 'static) -> Self {
         Self {
             kind: ResolverKind::Custom(Rc::new(resolver)),
+            cache: None,
         }
     }
 
     /// Resolve DNS with default resolver.
-    fn default_lookup<R: Host>(
-        req: &ConnectInfo<R>,
-    ) -> JoinHandle<io::Result<IntoIter<SocketAddr>>> {
+    fn default_lookup(host: &str, port: u16) -> JoinHandle<io::Result<IntoIter<SocketAddr>>> {
         // reconstruct host; concatenate hostname and port together
-        let host = format!("{}:{}", req.hostname(), req.port());
+        let host = format!("{}:{}", host, port);
 
         // run blocking DNS lookup in thread pool since DNS lookups can take upwards of seconds on
         // some platforms if conditions are poor and OS-level cache is not populated
         spawn_blocking(move || std::net::ToSocketAddrs::to_socket_addrs(&host))
     }
+
+    /// Resolve DNS with the configured resolver, for storing the result in the cache.
+    async fn lookup(
+        kind: ResolverKind,
+        host: &str,
+        port: u16,
+    ) -> Result<Vec<SocketAddr>, ConnectError> {
+        match kind {
+            ResolverKind::Default => match Self::default_lookup(host, port).await {
+                Ok(Ok(addrs)) => Ok(addrs.collect()),
+                Ok(Err(err)) => Err(ConnectError::Resolver(Box::new(err))),
+                Err(err) => Err(ConnectError::Io(err.into())),
+            },
+
+            ResolverKind::Custom(resolver) => resolver
+                .lookup(host, port)
+                .await
+                .map_err(ConnectError::Resolver),
+        }
+    }
 }
 
 impl<R: Host> Service<ConnectInfo<R>> for ResolverService {
@@ -112,9 +135,39 @@ impl<R: Host> Service<ConnectInfo<R>> for ResolverService {
         } else {
             trace!("DNS resolver: resolving host {:?}", req.hostname());
 
+            let hostname = match ascii_hostname(req.hostname()) {
+                Some(hostname) => hostname.into_owned(),
+                None => {
+                    return ResolverFut::LookupCustom(Box::pin(async {
+                        Err(ConnectError::InvalidInput)
+                    }))
+                }
+            };
+
+            if let Some(cache) = &self.cache {
+                let cache = cache.clone();
+                let kind = self.kind.clone();
+
+                return ResolverFut::LookupCustom(Box::pin(async move {
+                    let addrs = cache
+                        .lookup(&hostname, req.port(), || {
+                            Self::lookup(kind, &hostname, req.port())
+                        })
+                        .await?;
+
+                    let req = req.set_addrs(addrs);
+
+                    if req.addr.is_unresolved() {
+                        Err(ConnectError::NoRecords)
+                    } else {
+                        Ok(req)
+                    }
+                }));
+            }
+
             match &self.kind {
                 ResolverKind::Default => {
-                    let fut = Self::default_lookup(&req);
+                    let fut = Self::default_lookup(&hostname, req.port());
                     ResolverFut::LookUp(fut, Some(req))
                 }
 
@@ -123,7 +176,7 @@ impl<R: Host> Service<ConnectInfo<R>> for ResolverService {
 
                     ResolverFut::LookupCustom(Box::pin(async move {
                         let addrs = resolver
-                            .lookup(req.hostname(), req.port())
+                            .lookup(&hostname, req.port())
                             .await
                             .map_err(ConnectError::Resolver)?;
 
@@ -23,7 +23,7 @@ use tokio_rustls::{
 use tracing::trace;
 use webpki_roots::TLS_SERVER_ROOTS;
 
-use crate::connect::{Connection, Host};
+use crate::connect::{host::ascii_hostname, Connection, Host};
 
 pub mod reexports {
     //! Re-exports from `rustls` and `webpki_roots` that are useful for connectors.
@@ -105,7 +105,11 @@ where
         trace!("TLS handshake start for: {:?}", connection.hostname());
         let (stream, connection) = connection.replace_io(());
 
-        match ServerName::try_from(connection.hostname()) {
+        let server_name = ascii_hostname(connection.hostname())
+            .ok_or(())
+            .and_then(|hostname| ServerName::try_from(&*hostname).map_err(|_| ()));
+
+        match server_name {
             Ok(host) => ConnectFut::Future {
                 connect: RustlsTlsConnector::from(self.connector.clone()).connect(host, stream),
                 connection: Some(connection),
@@ -117,6 +121,7 @@ where
 
 /// Connect future for Rustls service.
 #[doc(hidden)]
+#[allow(clippy::large_enum_variant)]
 pub enum ConnectFut<R, IO> {
     /// See issue <https://github.com/briansmith/webpki/issues/54>
     InvalidDns,
@@ -0,0 +1,187 @@
+//! SRV record targets.
+
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher as _, Hasher as _},
+};
+
+/// Target of a DNS SRV record, for connecting to a service instead of a single host.
+///
+/// Set with [`ConnectInfo::set_srv_targets`](super::ConnectInfo::set_srv_targets) to override
+/// DNS resolution of the request's hostname for a single request. The [`Connector`] tries the
+/// targets in the order defined by RFC 2782: by ascending priority and, among targets with the
+/// same priority, in a random order weighted by their weights. Each target's hostname is resolved
+/// using the connector's resolver and, if connecting to all of its addresses fails, the next
+/// target is tried. The target that served the connection is available from
+/// [`Connection::srv_target`](super::Connection::srv_target).
+///
+/// Looking up SRV records is left to the caller since the resolver only resolves addresses; they
+/// can be taken from any DNS client.
+///
+/// # Examples
+/// ```no_run
+/// use actix_service::Service as _;
+/// use actix_tls::connect::{ConnectInfo, Connector, SrvTarget};
+///
+/// # async fn connect() -> Result<(), actix_tls::connect::ConnectError> {
+/// // records of _xmpp-client._tcp.example.com
+/// let targets = [
+///     SrvTarget::new(10, 60, 5222, "primary.example.com"),
+///     SrvTarget::new(10, 40, 5222, "secondary.example.com"),
+///     SrvTarget::new(20, 0, 5222, "backup.example.com"),
+/// ];
+///
+/// let connector = Connector::default().service();
+/// let info = ConnectInfo::new("example.com").set_srv_targets(targets);
+///
+/// let conn = connector.call(info).await?;
+/// println!("connected to {:?}", conn.srv_target());
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`Connector`]: super::Connector
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SrvTarget {
+    priority: u16,
+    weight: u16,
+    port: u16,
+    target: String,
+}
+
+impl SrvTarget {
+    /// Constructs target from the fields of an SRV record.
+    pub fn new(priority: u16, weight: u16, port: u16, target: impl Into<String>) -> Self {
+        Self {
+            priority,
+            weight,
+            port,
+            target: target.into(),
+        }
+    }
+
+    /// Returns priority of the target; lower values are tried first.
+    pub fn priority(&self) -> u16 {
+        self.priority
+    }
+
+    /// Returns weight of the target relative to other targets with the same priority.
+    pub fn weight(&self) -> u16 {
+        self.weight
+    }
+
+    /// Returns port of the service on the target.
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Returns hostname of the target.
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Returns true if the target signals that the service is not available, i.e., it is `.`.
+    pub(crate) fn is_unavailable(&self) -> bool {
+        self.target == "."
+    }
+}
+
+/// Orders `targets` for connection attempts, using `random(max)` to pick a number in `0..=max`.
+///
+/// Targets are sorted by priority and, within a priority, repeatedly selected with a probability
+/// proportional to their weight, as described in RFC 2782.
+pub(crate) fn order(
+    mut targets: Vec<SrvTarget>,
+    mut random: impl FnMut(u32) -> u32,
+) -> Vec<SrvTarget> {
+    // stable sort keeps zero weight targets placed first by the partition below in order
+    targets.sort_by_key(|target| (target.priority, target.weight != 0));
+
+    let mut ordered = Vec::with_capacity(targets.len());
+    let mut targets = targets.into_iter().peekable();
+
+    while let Some(first) = targets.next() {
+        let priority = first.priority;
+        let mut group = vec![first];
+
+        while let Some(target) = targets.next_if(|target| target.priority == priority) {
+            group.push(target);
+        }
+
+        while !group.is_empty() {
+            let total = group.iter().map(|target| u32::from(target.weight)).sum();
+            let selected = random(total);
+
+            let mut sum = 0;
+            let idx = group
+                .iter()
+                .position(|target| {
+                    sum += u32::from(target.weight);
+                    sum >= selected
+                })
+                .unwrap_or(group.len() - 1);
+
+            ordered.push(group.remove(idx));
+        }
+    }
+
+    ordered
+}
+
+/// Returns a random number in `0..=max`.
+pub(crate) fn random(max: u32) -> u32 {
+    // keys of each new `RandomState` differ, making its hashes a cheap source of randomness
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u32(max);
+
+    (hasher.finish() % (u64::from(max) + 1)) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn targets(targets: &[(u16, u16, &str)]) -> Vec<SrvTarget> {
+        targets
+            .iter()
+            .map(|&(priority, weight, target)| SrvTarget::new(priority, weight, 80, target))
+            .collect()
+    }
+
+    fn names(targets: &[SrvTarget]) -> Vec<&str> {
+        targets.iter().map(SrvTarget::target).collect()
+    }
+
+    #[test]
+    fn orders_by_priority() {
+        let ordered = order(targets(&[(20, 0, "c"), (10, 0, "a"), (15, 0, "b")]), |_| 0);
+        assert_eq!(names(&ordered), ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn orders_by_weight() {
+        let input = targets(&[(10, 10, "a"), (10, 30, "b"), (10, 0, "z"), (20, 5, "c")]);
+
+        // lowest number selects zero weight target, which is placed first
+        let ordered = order(input.clone(), |_| 0);
+        assert_eq!(names(&ordered), ["z", "a", "b", "c"]);
+
+        // highest number selects last target
+        let ordered = order(input.clone(), |max| max);
+        assert_eq!(names(&ordered), ["b", "a", "z", "c"]);
+
+        // running sums of weights are 0, 10, 40
+        let mut picks = vec![11, 5, 0].into_iter();
+        let ordered = order(input, |_| picks.next().unwrap_or(0));
+        assert_eq!(names(&ordered), ["b", "a", "z", "c"]);
+    }
+
+    #[test]
+    fn random_in_range() {
+        assert_eq!(random(0), 0);
+
+        for _ in 0..100 {
+            assert!(random(3) <= 3);
+        }
+    }
+}
@@ -6,29 +6,163 @@ use std::{
     collections::VecDeque,
     future::Future,
     io,
-    net::{IpAddr, SocketAddr, SocketAddrV4, SocketAddrV6},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    ops::RangeInclusive,
     pin::Pin,
+    sync::atomic::{AtomicU32, Ordering},
     task::{Context, Poll},
+    time::Duration,
 };
 
-use actix_rt::net::{TcpSocket, TcpStream};
+use actix_rt::{
+    net::{TcpSocket, TcpStream},
+    time::{sleep, Instant, Sleep},
+};
 use actix_service::{Service, ServiceFactory};
 use actix_utils::future::{ok, Ready};
-use futures_core::ready;
+use futures_core::{future::BoxFuture, ready};
 use tokio_util::sync::ReusableBoxFuture;
 use tracing::{error, trace};
 
 use super::{connect_addrs::ConnectAddrs, error::ConnectError, ConnectInfo, Connection, Host};
 
+/// Happy Eyeballs (RFC 8305) configuration for racing connection attempts.
+///
+/// When enabled, resolved addresses are interleaved by address family, starting with the family
+/// of the first address, and a new connection attempt is started every `attempt_delay` (or as soon
+/// as the previous attempt fails) while earlier attempts are still in-flight. The first attempt to
+/// succeed is used and the others are dropped. This avoids waiting for a full TCP connect timeout
+/// on hosts with broken IPv6 (or IPv4) connectivity before falling back to the other family.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use actix_tls::connect::{Connector, HappyEyeballs};
+///
+/// let connector = Connector::default().happy_eyeballs(
+///     HappyEyeballs::new()
+///         .attempt_delay(Duration::from_millis(100))
+///         .timeout(Duration::from_secs(5)),
+/// );
+/// # let _ = connector;
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct HappyEyeballs {
+    attempt_delay: Duration,
+    timeout: Option<Duration>,
+}
+
+impl HappyEyeballs {
+    /// Constructs configuration using the recommended attempt delay of 250ms and no timeout.
+    pub fn new() -> Self {
+        Self {
+            attempt_delay: Duration::from_millis(250),
+            timeout: None,
+        }
+    }
+
+    /// Sets the delay after which the next connection attempt is started if no attempt has
+    /// completed yet.
+    pub fn attempt_delay(mut self, attempt_delay: Duration) -> Self {
+        self.attempt_delay = attempt_delay;
+        self
+    }
+
+    /// Sets the overall time limit for establishing a connection across all attempts.
+    ///
+    /// By default, there is no overall time limit.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+}
+
+impl Default for HappyEyeballs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Range of local ports that outgoing connections are bound to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PortRange {
+    first: u16,
+    len: u32,
+}
+
+impl PortRange {
+    fn new(range: RangeInclusive<u16>) -> Self {
+        let (first, last) = range.into_inner();
+        assert!(first > 0, "local port range must not include port 0");
+        assert!(first <= last, "local port range must not be empty");
+
+        Self {
+            first,
+            len: u32::from(last - first) + 1,
+        }
+    }
+
+    /// Returns all ports of the range, starting at a different one for each call so concurrent
+    /// connections do not keep trying the same ports.
+    fn ports(self) -> impl Iterator<Item = u16> {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+
+        let start = NEXT.fetch_add(1, Ordering::Relaxed) % self.len;
+
+        (0..self.len).map(move |idx| self.first + ((start + idx) % self.len) as u16)
+    }
+}
+
+/// Local address and ports that outgoing connections are bound to.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy)]
+pub struct LocalBind {
+    ip: Option<IpAddr>,
+    ports: Option<PortRange>,
+}
+
 /// TCP connector service factory.
 #[derive(Debug, Clone, Copy, Default)]
 #[non_exhaustive]
-pub struct TcpConnector;
+pub struct TcpConnector {
+    happy_eyeballs: Option<HappyEyeballs>,
+    local_ports: Option<PortRange>,
+}
 
 impl TcpConnector {
+    /// Races connection attempts to resolved addresses using Happy Eyeballs.
+    ///
+    /// By default, addresses are tried sequentially in the order they were resolved.
+    pub fn happy_eyeballs(mut self, config: HappyEyeballs) -> Self {
+        self.happy_eyeballs = Some(config);
+        self
+    }
+
+    /// Binds outgoing connections to a local port in `range`, e.g., for firewall rules or NAT
+    /// policies that only allow known source ports.
+    ///
+    /// A port is picked for each connection attempt, starting at a different port of the range for
+    /// each connection and moving on to the next port while ports are in use. Connecting fails
+    /// with an error of kind [`AddrInUse`](io::ErrorKind::AddrInUse) if all ports of the range are
+    /// in use. Connections are bound to the unspecified address of the resolved address's family,
+    /// unless a local address is set using [`ConnectInfo::set_local_addr`].
+    ///
+    /// By default, the OS picks an ephemeral port.
+    ///
+    /// # Panics
+    /// Panics if `range` is empty or includes port 0.
+    pub fn local_port_range(mut self, range: RangeInclusive<u16>) -> Self {
+        self.local_ports = Some(PortRange::new(range));
+        self
+    }
+
     /// Returns a new TCP connector service.
     pub fn service(&self) -> TcpConnectorService {
-        TcpConnectorService::default()
+        TcpConnectorService {
+            happy_eyeballs: self.happy_eyeballs,
+            local_ports: self.local_ports,
+        }
     }
 }
 
@@ -48,7 +182,10 @@ impl<R: Host> ServiceFactory<ConnectInfo<R>> for TcpConnector {
 /// TCP connector service.
 #[derive(Debug, Copy, Clone, Default)]
 #[non_exhaustive]
-pub struct TcpConnectorService;
+pub struct TcpConnectorService {
+    happy_eyeballs: Option<HappyEyeballs>,
+    local_ports: Option<PortRange>,
+}
 
 impl<R: Host> Service<ConnectInfo<R>> for TcpConnectorService {
     type Response = Connection<R, TcpStream>;
@@ -67,7 +204,15 @@ impl<R: Host> Service<ConnectInfo<R>> for TcpConnectorService {
             ..
         } = req;
 
-        TcpConnectorFut::new(req, port, local_addr, addr)
+        let local = LocalBind {
+            ip: local_addr,
+            ports: self.local_ports,
+        };
+
+        match self.happy_eyeballs {
+            Some(config) => TcpConnectorFut::race(req, port, local, addr, config),
+            None => TcpConnectorFut::new(req, port, local, addr),
+        }
     }
 }
 
@@ -77,21 +222,21 @@ pub enum TcpConnectorFut<R> {
     Response {
         req: Option<R>,
         port: u16,
-        local_addr: Option<IpAddr>,
+        local: LocalBind,
         addrs: Option<VecDeque<SocketAddr>>,
         stream: ReusableBoxFuture<'static, Result<TcpStream, io::Error>>,
     },
 
+    Race {
+        req: Option<R>,
+        race: Race,
+    },
+
     Error(Option<ConnectError>),
 }
 
 impl<R: Host> TcpConnectorFut<R> {
-    pub(crate) fn new(
-        req: R,
-        port: u16,
-        local_addr: Option<IpAddr>,
-        addr: ConnectAddrs,
-    ) -> TcpConnectorFut<R> {
+    fn new(req: R, port: u16, local: LocalBind, addr: ConnectAddrs) -> TcpConnectorFut<R> {
         if addr.is_unresolved() {
             error!("TCP connector: unresolved connection address");
             return TcpConnectorFut::Error(Some(ConnectError::Unresolved));
@@ -109,9 +254,9 @@ impl<R: Host> TcpConnectorFut<R> {
             ConnectAddrs::One(addr) => TcpConnectorFut::Response {
                 req: Some(req),
                 port,
-                local_addr,
+                local,
                 addrs: None,
-                stream: ReusableBoxFuture::new(connect(addr, local_addr)),
+                stream: ReusableBoxFuture::new(connect(addr, local)),
             },
 
             // When resolver returns multiple socket addr for request they would be popped from
@@ -122,13 +267,45 @@ impl<R: Host> TcpConnectorFut<R> {
                 TcpConnectorFut::Response {
                     req: Some(req),
                     port,
-                    local_addr,
+                    local,
                     addrs: Some(addrs),
-                    stream: ReusableBoxFuture::new(connect(addr, local_addr)),
+                    stream: ReusableBoxFuture::new(connect(addr, local)),
                 }
             }
         }
     }
+
+    /// Constructs future that races connection attempts to all resolved addresses.
+    fn race(
+        req: R,
+        port: u16,
+        local: LocalBind,
+        addr: ConnectAddrs,
+        config: HappyEyeballs,
+    ) -> TcpConnectorFut<R> {
+        let addrs = match addr {
+            ConnectAddrs::None => {
+                error!("TCP connector: unresolved connection address");
+                return TcpConnectorFut::Error(Some(ConnectError::Unresolved));
+            }
+
+            // nothing to race
+            ConnectAddrs::One(_) => return TcpConnectorFut::new(req, port, local, addr),
+
+            ConnectAddrs::Multi(addrs) => interleave(addrs),
+        };
+
+        trace!(
+            "TCP connector: racing connections to {} on port {}",
+            req.hostname(),
+            port
+        );
+
+        TcpConnectorFut::Race {
+            req: Some(req),
+            race: Race::new(addrs, local, config),
+        }
+    }
 }
 
 impl<R: Host> Future for TcpConnectorFut<R> {
@@ -138,10 +315,23 @@ impl<R: Host> Future for TcpConnectorFut<R> {
         match self.get_mut() {
             TcpConnectorFut::Error(err) => Poll::Ready(Err(err.take().unwrap())),
 
+            TcpConnectorFut::Race { req, race } => {
+                let sock = ready!(Pin::new(race).poll(cx)).map_err(ConnectError::Io)?;
+                let req = req.take().unwrap();
+
+                trace!(
+                    "TCP connector: successfully connected to {:?} - {:?}",
+                    req.hostname(),
+                    sock.peer_addr()
+                );
+
+                Poll::Ready(Ok(Connection::new(req, sock)))
+            }
+
             TcpConnectorFut::Response {
                 req,
                 port,
-                local_addr,
+                local,
                 addrs,
                 stream,
             } => loop {
@@ -166,7 +356,7 @@ impl<R: Host> Future for TcpConnectorFut<R> {
                         );
 
                         if let Some(addr) = addrs.as_mut().and_then(|addrs| addrs.pop_front()) {
-                            stream.set(connect(addr, *local_addr));
+                            stream.set(connect(addr, *local));
                         } else {
                             return Poll::Ready(Err(ConnectError::Io(err)));
                         }
@@ -177,28 +367,246 @@ impl<R: Host> Future for TcpConnectorFut<R> {
     }
 }
 
-async fn connect(addr: SocketAddr, local_addr: Option<IpAddr>) -> io::Result<TcpStream> {
-    // use local addr if connect asks for it
-    match local_addr {
-        Some(ip_addr) => {
-            let socket = match ip_addr {
-                IpAddr::V4(ip_addr) => {
-                    let socket = TcpSocket::new_v4()?;
-                    let addr = SocketAddr::V4(SocketAddrV4::new(ip_addr, 0));
-                    socket.bind(addr)?;
-                    socket
+/// Reorders addresses to alternate between address families, starting with the first address's.
+fn interleave(addrs: VecDeque<SocketAddr>) -> VecDeque<SocketAddr> {
+    let prefer_v6 = addrs.front().map_or(false, SocketAddr::is_ipv6);
+
+    let (mut preferred, mut other): (VecDeque<_>, VecDeque<_>) = addrs
+        .into_iter()
+        .partition(|addr| addr.is_ipv6() == prefer_v6);
+
+    let mut interleaved = VecDeque::with_capacity(preferred.len() + other.len());
+
+    loop {
+        match (preferred.pop_front(), other.pop_front()) {
+            (None, None) => return interleaved,
+            (first, second) => interleaved.extend(first.into_iter().chain(second)),
+        }
+    }
+}
+
+/// Future racing staggered connection attempts, resolving to the first established stream.
+#[doc(hidden)]
+pub struct Race {
+    addrs: VecDeque<SocketAddr>,
+    local: LocalBind,
+    attempt_delay: Duration,
+    attempts: Vec<BoxFuture<'static, io::Result<TcpStream>>>,
+    next_attempt: Pin<Box<Sleep>>,
+    deadline: Option<Pin<Box<Sleep>>>,
+    last_err: Option<io::Error>,
+}
+
+impl Race {
+    fn new(addrs: VecDeque<SocketAddr>, local: LocalBind, config: HappyEyeballs) -> Self {
+        let mut race = Self {
+            addrs,
+            local,
+            attempt_delay: config.attempt_delay,
+            attempts: Vec::new(),
+            next_attempt: Box::pin(sleep(config.attempt_delay)),
+            deadline: config.timeout.map(|timeout| Box::pin(sleep(timeout))),
+            last_err: None,
+        };
+
+        race.start_attempt();
+        race
+    }
+
+    /// Starts a connection attempt to the next address, if any, and resets the attempt delay.
+    fn start_attempt(&mut self) -> bool {
+        match self.addrs.pop_front() {
+            Some(addr) => {
+                trace!("TCP connector: attempting connection to {}", addr);
+
+                self.attempts.push(Box::pin(connect(addr, self.local)));
+                self.next_attempt
+                    .as_mut()
+                    .reset(Instant::now() + self.attempt_delay);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+impl Future for Race {
+    type Output = io::Result<TcpStream>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        if let Some(deadline) = &mut this.deadline {
+            if deadline.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "timed out racing connection attempts",
+                )));
+            }
+        }
+
+        loop {
+            let mut idx = 0;
+
+            while idx < this.attempts.len() {
+                match this.attempts[idx].as_mut().poll(cx) {
+                    Poll::Ready(Ok(sock)) => return Poll::Ready(Ok(sock)),
+                    Poll::Ready(Err(err)) => {
+                        trace!("TCP connector: connection attempt failed: {}", err);
+                        drop(this.attempts.swap_remove(idx));
+                        this.last_err = Some(err);
+                    }
+                    Poll::Pending => idx += 1,
                 }
-                IpAddr::V6(ip_addr) => {
-                    let socket = TcpSocket::new_v6()?;
-                    let addr = SocketAddr::V6(SocketAddrV6::new(ip_addr, 0, 0, 0));
-                    socket.bind(addr)?;
-                    socket
+            }
+
+            // start next attempt early if all in-flight attempts failed
+            if this.attempts.is_empty() {
+                if this.start_attempt() {
+                    continue;
                 }
-            };
 
-            socket.connect(addr).await
+                let err = this.last_err.take().unwrap_or_else(|| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "no addresses to connect to")
+                });
+
+                return Poll::Ready(Err(err));
+            }
+
+            if this.addrs.is_empty() || this.next_attempt.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+
+            this.start_attempt();
         }
+    }
+}
+
+async fn connect(addr: SocketAddr, local: LocalBind) -> io::Result<TcpStream> {
+    if let Some(ports) = local.ports {
+        return connect_from_ports(addr, local.ip, ports).await;
+    }
 
+    // use local addr if connect asks for it
+    match local.ip {
+        Some(ip) => bind_socket(SocketAddr::new(ip, 0))?.connect(addr).await,
         None => TcpStream::connect(addr).await,
     }
 }
+
+/// Connects to `addr` from the first free port of `ports`.
+async fn connect_from_ports(
+    addr: SocketAddr,
+    ip: Option<IpAddr>,
+    ports: PortRange,
+) -> io::Result<TcpStream> {
+    let ip = ip.unwrap_or(match addr {
+        SocketAddr::V4(_) => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+        SocketAddr::V6(_) => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+    });
+
+    for port in ports.ports() {
+        let res = match bind_socket(SocketAddr::new(ip, port)) {
+            Ok(socket) => socket.connect(addr).await,
+            Err(err) => Err(err),
+        };
+
+        match res {
+            Ok(stream) => return Ok(stream),
+
+            // port is bound by another socket or already connected to `addr`
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    io::ErrorKind::AddrInUse | io::ErrorKind::AddrNotAvailable
+                ) =>
+            {
+                trace!("TCP connector: local port {} is in use: {}", port, err);
+            }
+
+            Err(err) => return Err(err),
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::AddrInUse,
+        "all ports of local port range are in use",
+    ))
+}
+
+fn bind_socket(local_addr: SocketAddr) -> io::Result<TcpSocket> {
+    let socket = match local_addr {
+        SocketAddr::V4(_) => TcpSocket::new_v4()?,
+        SocketAddr::V6(_) => TcpSocket::new_v6()?,
+    };
+
+    // allows reusing ports of closed connections still in TIME_WAIT state; does not allow stealing
+    // ports of other sockets on Windows without this option
+    #[cfg(unix)]
+    if local_addr.port() != 0 {
+        socket.set_reuseaddr(true)?;
+    }
+
+    socket.bind(local_addr)?;
+    Ok(socket)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn port_range() {
+        let range = PortRange::new(1000..=1004);
+
+        let mut ports = range.ports().collect::<Vec<_>>();
+        ports.sort_unstable();
+        assert_eq!(ports, [1000, 1001, 1002, 1003, 1004]);
+
+        // next call starts at another port
+        assert_ne!(range.ports().next(), range.ports().next());
+
+        let single = PortRange::new(u16::MAX..=u16::MAX);
+        assert_eq!(single.ports().collect::<Vec<_>>(), [u16::MAX]);
+
+        let full = PortRange::new(1..=u16::MAX);
+        assert_eq!(full.ports().count(), usize::from(u16::MAX));
+    }
+
+    #[test]
+    #[should_panic]
+    #[allow(clippy::reversed_empty_ranges)]
+    fn empty_port_range() {
+        PortRange::new(2000..=1999);
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_port_range() {
+        PortRange::new(0..=1000);
+    }
+
+    #[test]
+    fn interleaves_address_families() {
+        let addrs = [
+            "[::1]:1",
+            "[::1]:2",
+            "[::1]:3",
+            "127.0.0.1:1",
+            "127.0.0.1:2",
+        ]
+        .iter()
+        .map(|addr| addr.parse().unwrap())
+        .collect();
+
+        let ports = interleave(addrs)
+            .into_iter()
+            .map(|addr| (addr.is_ipv6(), addr.port()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            ports,
+            [(true, 1), (false, 1), (true, 2), (false, 2), (true, 3)]
+        );
+    }
+}
@@ -15,3 +15,5 @@ pub mod accept;
 
 #[cfg(feature = "connect")]
 pub mod connect;
+
+pub mod test_util;
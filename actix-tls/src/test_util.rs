@@ -0,0 +1,412 @@
+//! Utilities for testing TLS handshake error paths.
+//!
+//! [`FakePeer`] plays a scripted, deliberately misbehaving TLS client or server over any stream,
+//! such as one half of an [in-memory duplex](actix_utils::duplex) or a TCP connection. Scripts can
+//! send malformed handshake messages, stall in the middle of a handshake, or close the connection
+//! abruptly, making it possible to test how acceptors and connectors handle these conditions,
+//! including their handshake timeouts, deterministically.
+//!
+//! [`client_hello`], [`malformed_client_hello`], and [`malformed_server_hello`] build common TLS
+//! records to send; [`record`] builds arbitrary ones.
+//!
+//! # Examples
+//! ```
+//! use actix_tls::test_util::{client_hello, ContentType, FakePeer};
+//! use actix_utils::duplex::duplex;
+//! use tokio::io::AsyncReadExt as _;
+//!
+//! # actix_rt::System::new().block_on(async {
+//! let (client, mut server) = duplex(16 * 1024);
+//!
+//! // start a handshake and hang up
+//! let peer = FakePeer::new().send(client_hello(Some("localhost"))).close();
+//! let peer = actix_rt::spawn(peer.run(client));
+//!
+//! // in a real test, `server` would be handed to the acceptor under test
+//! let mut header = [0; 5];
+//! server.read_exact(&mut header).await.unwrap();
+//! assert_eq!(ContentType::from(header[0]), ContentType::Handshake);
+//!
+//! assert!(peer.await.unwrap().unwrap().is_empty());
+//! # });
+//! ```
+
+use std::{fmt, future::pending, io, sync::Arc, time::Duration};
+
+use actix_utils::clock::{Clock, RuntimeClock};
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _};
+
+/// Maximum record payload length allowed by TLS, including expansion by encryption.
+const MAX_RECORD_LEN: usize = (1 << 14) + 2048;
+
+/// TLS record content type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentType {
+    /// Change cipher spec protocol message.
+    ChangeCipherSpec,
+
+    /// Alert protocol message.
+    Alert,
+
+    /// Handshake protocol message.
+    Handshake,
+
+    /// Application data.
+    ApplicationData,
+
+    /// Unrecognized content type.
+    Unknown(u8),
+}
+
+impl From<u8> for ContentType {
+    fn from(byte: u8) -> Self {
+        match byte {
+            20 => Self::ChangeCipherSpec,
+            21 => Self::Alert,
+            22 => Self::Handshake,
+            23 => Self::ApplicationData,
+            byte => Self::Unknown(byte),
+        }
+    }
+}
+
+impl From<ContentType> for u8 {
+    fn from(content_type: ContentType) -> Self {
+        match content_type {
+            ContentType::ChangeCipherSpec => 20,
+            ContentType::Alert => 21,
+            ContentType::Handshake => 22,
+            ContentType::ApplicationData => 23,
+            ContentType::Unknown(byte) => byte,
+        }
+    }
+}
+
+/// TLS record received by a [`FakePeer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Record {
+    content_type: ContentType,
+    version: u16,
+    payload: Vec<u8>,
+}
+
+impl Record {
+    /// Returns content type of record.
+    pub fn content_type(&self) -> ContentType {
+        self.content_type
+    }
+
+    /// Returns protocol version from record header.
+    pub fn version(&self) -> u16 {
+        self.version
+    }
+
+    /// Returns record payload.
+    pub fn payload(&self) -> &[u8] {
+        &self.payload
+    }
+
+    /// Returns handshake message type if record contains a handshake message.
+    pub fn handshake_type(&self) -> Option<u8> {
+        match self.content_type {
+            ContentType::Handshake => self.payload.first().copied(),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Action {
+    Send(Vec<u8>),
+    ReadRecord,
+    Stall(Duration),
+    StallForever,
+    Close,
+}
+
+/// Scripted TLS peer for testing handshake error paths.
+///
+/// Actions are performed in the order they were added. Once the script has completed without
+/// [closing](Self::close) the connection, the peer keeps reading records until the other side
+/// closes it, so that, e.g., alerts sent in response to malformed messages are observed.
+///
+/// See the [module documentation](self) for an example.
+pub struct FakePeer {
+    actions: Vec<Action>,
+    clock: Arc<dyn Clock>,
+}
+
+impl FakePeer {
+    /// Constructs peer with an empty script.
+    pub fn new() -> Self {
+        Self {
+            actions: Vec::new(),
+            clock: Arc::new(RuntimeClock::new()),
+        }
+    }
+
+    /// Sets clock used for [stalling](Self::stall).
+    ///
+    /// Uses the runtime's timer by default.
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Sends raw `bytes`.
+    ///
+    /// Bytes do not need to form complete records; sending part of a record followed by a
+    /// [stall](Self::stall) simulates a peer stalling mid-record.
+    pub fn send(mut self, bytes: impl Into<Vec<u8>>) -> Self {
+        self.actions.push(Action::Send(bytes.into()));
+        self
+    }
+
+    /// Waits for a complete TLS record from the other side.
+    ///
+    /// Received records are returned from [`run`](Self::run).
+    pub fn read_record(mut self) -> Self {
+        self.actions.push(Action::ReadRecord);
+        self
+    }
+
+    /// Stops responding for `dur`, keeping the connection open.
+    pub fn stall(mut self, dur: Duration) -> Self {
+        self.actions.push(Action::Stall(dur));
+        self
+    }
+
+    /// Stops responding for good, keeping the connection open and leaving incoming data unread.
+    ///
+    /// The peer never completes after reaching this action; drop it to close the connection.
+    pub fn stall_forever(mut self) -> Self {
+        self.actions.push(Action::StallForever);
+        self
+    }
+
+    /// Closes the connection abruptly without a TLS close notification or shutdown.
+    ///
+    /// Remaining actions are skipped.
+    pub fn close(mut self) -> Self {
+        self.actions.push(Action::Close);
+        self
+    }
+
+    /// Runs script over `io`, returning all records received from the other side.
+    ///
+    /// # Errors
+    /// Returns error if sending fails or if the connection is closed while waiting for a record.
+    pub async fn run<IO>(self, mut io: IO) -> io::Result<Vec<Record>>
+    where
+        IO: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut records = Vec::new();
+
+        for action in self.actions {
+            match action {
+                Action::Send(bytes) => {
+                    io.write_all(&bytes).await?;
+                    io.flush().await?;
+                }
+
+                Action::ReadRecord => match read_record(&mut io).await? {
+                    Some(rec) => records.push(rec),
+                    None => return Err(io::ErrorKind::UnexpectedEof.into()),
+                },
+
+                Action::Stall(dur) => self.clock.sleep(dur).await,
+
+                Action::StallForever => pending::<()>().await,
+
+                Action::Close => {
+                    drop(io);
+                    return Ok(records);
+                }
+            }
+        }
+
+        while let Some(rec) = read_record_or_closed(&mut io).await? {
+            records.push(rec);
+        }
+
+        Ok(records)
+    }
+}
+
+impl Default for FakePeer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for FakePeer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FakePeer")
+            .field("actions", &self.actions)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Reads record, returning `None` if connection was closed cleanly before the record started.
+async fn read_record<IO: AsyncRead + Unpin>(io: &mut IO) -> io::Result<Option<Record>> {
+    let mut header = [0; 5];
+
+    let n = io.read(&mut header).await?;
+    if n == 0 {
+        return Ok(None);
+    }
+    io.read_exact(&mut header[n..]).await?;
+
+    let len = u16::from_be_bytes([header[3], header[4]]) as usize;
+    if len > MAX_RECORD_LEN {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "received record exceeds maximum length",
+        ));
+    }
+
+    let mut payload = vec![0; len];
+    io.read_exact(&mut payload).await?;
+
+    Ok(Some(Record {
+        content_type: ContentType::from(header[0]),
+        version: u16::from_be_bytes([header[1], header[2]]),
+        payload,
+    }))
+}
+
+/// Reads record like [`read_record`], also treating reset connections as closed.
+async fn read_record_or_closed<IO: AsyncRead + Unpin>(io: &mut IO) -> io::Result<Option<Record>> {
+    match read_record(io).await {
+        Err(err)
+            if matches!(
+                err.kind(),
+                io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted
+            ) =>
+        {
+            Ok(None)
+        }
+        res => res,
+    }
+}
+
+/// Builds TLS record of `content_type` containing `payload`.
+///
+/// The record header carries the TLS 1.2 version and the length of `payload`, which is truncated
+/// to 65535 bytes.
+pub fn record(content_type: ContentType, payload: &[u8]) -> Vec<u8> {
+    let payload = &payload[..payload.len().min(u16::MAX as usize)];
+
+    let mut rec = Vec::with_capacity(5 + payload.len());
+    rec.push(content_type.into());
+    rec.extend_from_slice(&[0x03, 0x03]);
+    rec.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    rec.extend_from_slice(payload);
+    rec
+}
+
+/// Builds handshake message of `msg_type` containing `body`.
+fn handshake(msg_type: u8, body: &[u8]) -> Vec<u8> {
+    let mut msg = vec![msg_type];
+    msg.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+    msg.extend_from_slice(body);
+    msg
+}
+
+/// Appends `data` prefixed by its length as big-endian `u16`.
+fn put_u16_prefixed(buf: &mut Vec<u8>, data: &[u8]) {
+    buf.extend_from_slice(&(data.len() as u16).to_be_bytes());
+    buf.extend_from_slice(data);
+}
+
+/// Builds well-formed TLS 1.2 ClientHello record, optionally indicating `server_name` (SNI).
+///
+/// Offers ECDHE key exchange with AES-GCM and ChaCha20-Poly1305 cipher suites, which common server
+/// configurations accept. Useful for driving the server into the middle of a handshake, e.g., to
+/// then stall or close the connection.
+pub fn client_hello(server_name: Option<&str>) -> Vec<u8> {
+    record(
+        ContentType::Handshake,
+        &handshake(1, &client_hello_body(server_name)),
+    )
+}
+
+fn client_hello_body(server_name: Option<&str>) -> Vec<u8> {
+    let mut body = Vec::new();
+
+    // client version, random, and empty session ID
+    body.extend_from_slice(&[0x03, 0x03]);
+    body.extend_from_slice(&[0x42; 32]);
+    body.push(0);
+
+    // ECDHE-{ECDSA,RSA}-{AES128-GCM-SHA256,AES256-GCM-SHA384,CHACHA20-POLY1305}
+    put_u16_prefixed(
+        &mut body,
+        &[
+            0xc0, 0x2b, 0xc0, 0x2f, 0xc0, 0x2c, 0xc0, 0x30, 0xcc, 0xa9, 0xcc, 0xa8,
+        ],
+    );
+
+    // null compression only
+    body.extend_from_slice(&[1, 0]);
+
+    let mut exts = Vec::new();
+
+    if let Some(name) = server_name {
+        let mut entry = vec![0];
+        put_u16_prefixed(&mut entry, name.as_bytes());
+
+        let mut list = Vec::new();
+        put_u16_prefixed(&mut list, &entry);
+
+        exts.extend_from_slice(&[0x00, 0x00]);
+        put_u16_prefixed(&mut exts, &list);
+    }
+
+    // supported groups: x25519, secp256r1, secp384r1
+    exts.extend_from_slice(&[0x00, 0x0a]);
+    put_u16_prefixed(&mut exts, &[0x00, 0x06, 0x00, 0x1d, 0x00, 0x17, 0x00, 0x18]);
+
+    // uncompressed EC point format
+    exts.extend_from_slice(&[0x00, 0x0b]);
+    put_u16_prefixed(&mut exts, &[1, 0]);
+
+    // signature algorithms: ECDSA, RSA-PSS, and RSA PKCS#1 with SHA-256 and SHA-384
+    exts.extend_from_slice(&[0x00, 0x0d]);
+    put_u16_prefixed(
+        &mut exts,
+        &[
+            0x00, 0x0c, 0x04, 0x03, 0x05, 0x03, 0x08, 0x04, 0x08, 0x05, 0x04, 0x01, 0x05, 0x01,
+        ],
+    );
+
+    // extended master secret
+    exts.extend_from_slice(&[0x00, 0x17, 0x00, 0x00]);
+
+    // empty renegotiation info
+    exts.extend_from_slice(&[0xff, 0x01]);
+    put_u16_prefixed(&mut exts, &[0]);
+
+    put_u16_prefixed(&mut body, &exts);
+
+    body
+}
+
+/// Builds ClientHello record with a session ID length exceeding the maximum of 32 bytes.
+pub fn malformed_client_hello() -> Vec<u8> {
+    let mut body = client_hello_body(None);
+
+    // session ID length follows client version and random
+    body[34] = 0xff;
+
+    record(ContentType::Handshake, &handshake(1, &body))
+}
+
+/// Builds ServerHello record with a body that is not a valid ServerHello.
+pub fn malformed_server_hello() -> Vec<u8> {
+    // server version followed by a random that is cut short
+    record(
+        ContentType::Handshake,
+        &handshake(2, &[0x03, 0x03, 0x42, 0x42]),
+    )
+}
@@ -126,3 +126,40 @@ async fn accepts_connections() {
 
     stream.flush().expect("TLS handshake failed");
 }
+
+#[actix_rt::test]
+async fn accepts_connections_over_duplex() {
+    use actix_service::{Service, ServiceFactory};
+    use actix_utils::{
+        duplex::{DuplexBuilder, DuplexStream},
+        future::join,
+    };
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    let (cert, key) = new_cert_and_key();
+
+    let acceptor = Acceptor::new(openssl_acceptor(cert.clone(), key.clone()));
+    let acceptor = ServiceFactory::<DuplexStream>::new_service(&acceptor, ())
+        .await
+        .unwrap();
+
+    // small chunks exercise partial reads and writes during the handshake
+    let (client, server) = DuplexBuilder::new().chunk_size(7).build();
+
+    let connector = tokio_rustls::TlsConnector::from(Arc::new(rustls_connector(cert, key)));
+    let server_name = ServerName::try_from("localhost").unwrap();
+
+    let (client, server) = join(
+        connector.connect(server_name, client),
+        Service::<DuplexStream>::call(&acceptor, server),
+    )
+    .await;
+    let (mut client, mut server) = (client.unwrap(), server.unwrap());
+
+    client.write_all(b"ping").await.unwrap();
+    client.flush().await.unwrap();
+
+    let mut buf = [0; 4];
+    server.read_exact(&mut buf).await.unwrap();
+    assert_eq!(&buf, b"ping");
+}
@@ -0,0 +1,67 @@
+//! Route connections to different services by peeking at their first bytes.
+
+#![cfg(feature = "accept")]
+
+use actix_rt::net::TcpStream;
+use actix_server::TestServer;
+use actix_service::fn_service;
+use actix_tls::accept::peek::{is_tls_handshake, Peek, PeekedStream};
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+/// Echoes the first line read from the stream, prefixed with `tag`.
+fn echo_line(
+    tag: &'static str,
+) -> impl actix_service::ServiceFactory<
+    PeekedStream<TcpStream>,
+    Config = (),
+    Response = (),
+    Error = std::io::Error,
+    InitError = (),
+> + Clone {
+    fn_service(move |mut stream: PeekedStream<TcpStream>| async move {
+        let mut line = Vec::new();
+
+        loop {
+            let byte = stream.read_u8().await?;
+            if byte == b'\n' {
+                break;
+            }
+            line.push(byte);
+        }
+
+        stream.write_all(tag.as_bytes()).await?;
+        stream.write_all(&line).await?;
+        stream.shutdown().await
+    })
+}
+
+async fn roundtrip(addr: std::net::SocketAddr, req: &[u8]) -> Vec<u8> {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(req).await.unwrap();
+
+    let mut res = Vec::new();
+    stream.read_to_end(&mut res).await.unwrap();
+    res
+}
+
+#[actix_rt::test]
+async fn routes_by_peeked_bytes() {
+    let srv = TestServer::start(|| {
+        Peek::new(2).route(is_tls_handshake, echo_line("tls:"), echo_line("plain:"))
+    });
+
+    // peeked bytes are read again by the routed service
+    let res = roundtrip(srv.addr(), b"\x16\x03hello\n").await;
+    assert_eq!(res, b"tls:\x16\x03hello");
+
+    let res = roundtrip(srv.addr(), b"GET /\n").await;
+    assert_eq!(res, b"plain:GET /");
+
+    // short streams are routed once the peer closes its end
+    let mut stream = TcpStream::connect(srv.addr()).await.unwrap();
+    stream.write_all(b"\n").await.unwrap();
+    stream.shutdown().await.unwrap();
+    let mut res = Vec::new();
+    stream.read_to_end(&mut res).await.unwrap();
+    assert_eq!(res, b"plain:");
+}
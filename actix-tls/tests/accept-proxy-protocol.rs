@@ -0,0 +1,97 @@
+//! Read client addresses from PROXY protocol headers sent ahead of connections.
+
+#![cfg(feature = "accept")]
+
+use actix_rt::net::TcpStream;
+use actix_server::TestServer;
+use actix_service::{fn_service, ServiceFactoryExt as _};
+use actix_tls::accept::proxy_protocol::{ProxiedStream, ProxyProtocolAcceptor};
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+async fn roundtrip(addr: std::net::SocketAddr, req: &[u8]) -> Vec<u8> {
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    stream.write_all(req).await.unwrap();
+
+    let mut res = Vec::new();
+    stream.read_to_end(&mut res).await.unwrap();
+    res
+}
+
+#[actix_rt::test]
+async fn exposes_client_address() {
+    let srv = TestServer::start(|| {
+        ProxyProtocolAcceptor::new()
+            .map_err(|err| println!("PROXY protocol error: {err}"))
+            .and_then(fn_service(
+                |mut stream: ProxiedStream<TcpStream>| async move {
+                    let mut data = [0; 4];
+                    stream.read_exact(&mut data).await.unwrap();
+
+                    let res = format!(
+                        "{:?} {}",
+                        stream.source_addr(),
+                        String::from_utf8_lossy(&data)
+                    );
+                    stream.write_all(res.as_bytes()).await.unwrap();
+                    stream.shutdown().await.unwrap();
+                    Ok(())
+                },
+            ))
+    });
+
+    let res = roundtrip(
+        srv.addr(),
+        b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\nping",
+    )
+    .await;
+    assert_eq!(res, b"Some(192.0.2.1:56324) ping");
+
+    let mut req = b"\r\n\r\n\0\r\nQUIT\n\x21\x21\x00\x24".to_vec();
+    req.extend_from_slice(&[0x20, 0x01, 0x0d, 0xb8, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1]);
+    req.extend_from_slice(&[0; 16]);
+    req.extend_from_slice(&[0x1f, 0x90, 0x01, 0xbb]);
+    req.extend_from_slice(b"pong");
+    let res = roundtrip(srv.addr(), &req).await;
+    assert_eq!(res, b"Some([2001:db8::1]:8080) pong");
+
+    // connections without a header are closed
+    let res = roundtrip(srv.addr(), b"GET / HTTP/1.1\r\n\r\n").await;
+    assert!(res.is_empty());
+}
+
+#[actix_rt::test]
+async fn pipeline_stages() {
+    use actix_tls::accept::{
+        peek::{Peek, PeekedStream},
+        pipeline::AcceptorPipeline,
+    };
+
+    let srv = TestServer::start(|| {
+        AcceptorPipeline::<TcpStream, _>::new()
+            .stage(ProxyProtocolAcceptor::new())
+            .stage(Peek::new(4))
+            .finish(fn_service(
+                |mut stream: PeekedStream<ProxiedStream<TcpStream>>| async move {
+                    let res = format!(
+                        "{:?} {}",
+                        stream.get_ref().source_addr(),
+                        String::from_utf8_lossy(stream.peeked())
+                    );
+                    stream.write_all(res.as_bytes()).await?;
+                    stream.shutdown().await
+                },
+            ))
+            .map_err(|err| println!("pipeline error: {err}"))
+    });
+
+    let res = roundtrip(
+        srv.addr(),
+        b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\nping",
+    )
+    .await;
+    assert_eq!(res, b"Some(192.0.2.1:56324) ping");
+
+    // stage errors close connections
+    let res = roundtrip(srv.addr(), b"GET / HTTP/1.1\r\n\r\n").await;
+    assert!(res.is_empty());
+}
@@ -9,16 +9,22 @@
 
 extern crate tls_openssl as openssl;
 
-use std::io::{BufReader, Write};
+use std::{
+    io::{BufReader, Read as _, Write},
+    sync::Arc,
+};
 
 use actix_rt::net::TcpStream;
 use actix_server::TestServer;
 use actix_service::ServiceFactoryExt as _;
 use actix_tls::{
-    accept::rustls::{Acceptor, TlsStream},
+    accept::{
+        rustls::{Acceptor, ReloadableCertResolver, ServerConfigCache, TlsStream},
+        AnyTlsStream, TlsServerConnInfo as _,
+    },
     connect::openssl::reexports::SslConnector,
 };
-use actix_utils::future::ok;
+use actix_utils::{budget::MemoryBudget, future::ok};
 use rustls_pemfile::{certs, pkcs8_private_keys};
 use tls_openssl::ssl::SslVerifyMode;
 use tokio_rustls::rustls::{self, Certificate, PrivateKey, ServerConfig};
@@ -34,19 +40,23 @@ fn new_cert_and_key() -> (String, String) {
     (cert, key)
 }
 
-fn rustls_server_config(cert: String, key: String) -> rustls::ServerConfig {
-    // Load TLS key and cert files
+fn parse_cert_and_key(cert: &str, key: &str) -> (Vec<Certificate>, PrivateKey) {
+    let certs = certs(&mut BufReader::new(cert.as_bytes())).unwrap();
+    let mut keys = pkcs8_private_keys(&mut BufReader::new(key.as_bytes())).unwrap();
 
-    let cert = &mut BufReader::new(cert.as_bytes());
-    let key = &mut BufReader::new(key.as_bytes());
+    (
+        certs.into_iter().map(Certificate).collect(),
+        PrivateKey(keys.remove(0)),
+    )
+}
 
-    let cert_chain = certs(cert).unwrap().into_iter().map(Certificate).collect();
-    let mut keys = pkcs8_private_keys(key).unwrap();
+fn rustls_server_config(cert: String, key: String) -> rustls::ServerConfig {
+    let (cert_chain, key) = parse_cert_and_key(&cert, &key);
 
     let mut config = ServerConfig::builder()
         .with_safe_defaults()
         .with_no_client_auth()
-        .with_single_cert(cert_chain, PrivateKey(keys.remove(0)))
+        .with_single_cert(cert_chain, key)
         .unwrap();
 
     config.alpn_protocols = vec![b"http/1.1".to_vec()];
@@ -83,7 +93,14 @@ async fn accepts_connections() {
 
             tls_acceptor
                 .map_err(|err| println!("Rustls error: {:?}", err))
-                .and_then(move |_stream: TlsStream<TcpStream>| ok(()))
+                .and_then(move |mut stream: TlsStream<TcpStream>| {
+                    let budget = MemoryBudget::new(32 * 1024);
+                    let _res = stream.reserve_buffers(&budget, 16 * 1024).unwrap();
+                    assert_eq!(budget.used(), 16 * 1024);
+                    assert!(stream.reserve_buffers(&budget, 32 * 1024).is_err());
+
+                    ok(())
+                })
         }
     });
 
@@ -104,3 +121,213 @@ async fn accepts_connections() {
 
     stream.flush().expect("TLS handshake failed");
 }
+
+#[actix_rt::test]
+async fn accepts_connections_as_any_stream() {
+    use tokio::io::AsyncWriteExt as _;
+
+    let (cert, key) = new_cert_and_key();
+
+    let srv = TestServer::start({
+        let cert = cert.clone();
+        let key = key.clone();
+
+        move || {
+            let tls_acceptor = Acceptor::new(rustls_server_config(cert.clone(), key.clone()));
+
+            tls_acceptor
+                .map(AnyTlsStream::from)
+                .map_err(|err| println!("Rustls error: {:?}", err))
+                .and_then(move |mut stream: AnyTlsStream<TcpStream>| async move {
+                    assert!(matches!(stream, AnyTlsStream::Rustls(_)));
+                    assert_eq!(stream.alpn_protocol().unwrap(), b"http/1.1");
+                    assert_eq!(stream.server_name().unwrap(), "localhost");
+                    // client certificates are not requested
+                    assert!(stream.peer_certificate().is_none());
+
+                    stream.write_all(b"ok").await.unwrap();
+                    stream.shutdown().await.unwrap();
+                    Ok(())
+                })
+        }
+    });
+
+    let sock = srv
+        .connect()
+        .expect("cannot connect to test server")
+        .into_std()
+        .unwrap();
+    sock.set_nonblocking(false).unwrap();
+
+    let connector = openssl_connector(cert, key);
+
+    let mut stream = connector
+        .connect("localhost", sock)
+        .expect("TLS handshake failed");
+
+    let mut buf = [0; 2];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"ok");
+}
+
+#[actix_rt::test]
+async fn resolves_and_reloads_certificates() {
+    use openssl::x509::X509;
+
+    fn der(pem: &str) -> Vec<u8> {
+        X509::from_pem(pem.as_bytes()).unwrap().to_der().unwrap()
+    }
+
+    let (cert_a, key_a) = new_cert_and_key();
+    let (cert_b, key_b) = new_cert_and_key();
+
+    let resolver = ReloadableCertResolver::new();
+    let (certs, key) = parse_cert_and_key(&cert_a, &key_a);
+    resolver.insert("a.test", certs, key).unwrap();
+    assert!(resolver.lookup(Some("A.TEST")).is_some());
+    assert!(resolver.lookup(None).is_none());
+
+    let srv = TestServer::start({
+        let resolver = resolver.clone();
+
+        move || {
+            Acceptor::new_with_resolver(Arc::new(resolver.clone()))
+                .map_err(|err| println!("Rustls error: {:?}", err))
+                .and_then(|_: TlsStream<TcpStream>| ok(()))
+        }
+    });
+
+    let handshake = |server_name: &str| {
+        let sock = srv.connect().unwrap().into_std().unwrap();
+        sock.set_nonblocking(false).unwrap();
+
+        let (cert, key) = new_cert_and_key();
+        openssl_connector(cert, key)
+            .connect(server_name, sock)
+            .ok()
+            .map(|stream| stream.ssl().peer_certificate().unwrap().to_der().unwrap())
+    };
+
+    assert_eq!(handshake("a.test").unwrap(), der(&cert_a));
+
+    // unknown names are rejected until a default is set
+    assert!(handshake("b.test").is_none());
+    let (certs, key) = parse_cert_and_key(&cert_b, &key_b);
+    resolver.update(certs, key).unwrap();
+    assert_eq!(handshake("b.test").unwrap(), der(&cert_b));
+
+    // registered certificates can be swapped while the server is running
+    let (certs, key) = parse_cert_and_key(&cert_b, &key_b);
+    resolver.insert("a.test", certs, key).unwrap();
+    assert_eq!(handshake("a.test").unwrap(), der(&cert_b));
+
+    assert!(resolver.remove("a.test"));
+    assert!(resolver.clear_default());
+    assert!(handshake("a.test").is_none());
+}
+
+#[actix_rt::test]
+async fn builds_configs_from_cache() {
+    use openssl::x509::X509;
+
+    fn der(pem: &str) -> Vec<u8> {
+        X509::from_pem(pem.as_bytes()).unwrap().to_der().unwrap()
+    }
+
+    let (cert_a, key_a) = new_cert_and_key();
+    let (cert_b, key_b) = new_cert_and_key();
+
+    let cache = ServerConfigCache::new(1, {
+        let (cert_a, key_a) = (cert_a.clone(), key_a.clone());
+        let (cert_b, key_b) = (cert_b.clone(), key_b.clone());
+
+        move |name| match name? {
+            "a.test" => Some(rustls_server_config(cert_a.clone(), key_a.clone())),
+            "b.test" => Some(rustls_server_config(cert_b.clone(), key_b.clone())),
+            _ => None,
+        }
+    });
+
+    let srv = TestServer::start({
+        let cache = cache.clone();
+
+        move || {
+            Acceptor::with_config_cache(cache.clone())
+                .map_err(|err| println!("Rustls error: {:?}", err))
+                .and_then(|_: TlsStream<TcpStream>| ok(()))
+        }
+    });
+
+    let handshake = |server_name: &str| {
+        let sock = srv.connect().unwrap().into_std().unwrap();
+        sock.set_nonblocking(false).unwrap();
+
+        let (cert, key) = new_cert_and_key();
+        openssl_connector(cert, key)
+            .connect(server_name, sock)
+            .ok()
+            .map(|stream| stream.ssl().peer_certificate().unwrap().to_der().unwrap())
+    };
+
+    assert_eq!(handshake("a.test").unwrap(), der(&cert_a));
+    assert_eq!(handshake("a.test").unwrap(), der(&cert_a));
+    assert_eq!(handshake("b.test").unwrap(), der(&cert_b));
+    assert!(handshake("c.test").is_none());
+
+    let stats = cache.stats();
+    assert_eq!(stats.hits(), 1);
+    assert_eq!(stats.misses(), 3);
+    assert_eq!(stats.evictions(), 1);
+    assert_eq!(stats.entries(), 1);
+}
+
+#[actix_rt::test]
+async fn accepts_proxied_connections() {
+    use actix_tls::accept::proxy_protocol::{ProxiedStream, ProxyProtocolAcceptor};
+    use tokio::io::AsyncWriteExt as _;
+
+    let (cert, key) = new_cert_and_key();
+
+    let srv = TestServer::start({
+        let cert = cert.clone();
+        let key = key.clone();
+
+        move || {
+            let tls_acceptor = Acceptor::new(rustls_server_config(cert.clone(), key.clone()));
+
+            ProxyProtocolAcceptor::new()
+                .map_err(|err| println!("PROXY protocol error: {err}"))
+                .and_then(tls_acceptor.map_err(|err| println!("Rustls error: {:?}", err)))
+                .and_then(
+                    |mut stream: TlsStream<ProxiedStream<TcpStream>>| async move {
+                        let client_addr = stream.get_ref().0.source_addr().unwrap();
+                        stream
+                            .write_all(client_addr.to_string().as_bytes())
+                            .await
+                            .unwrap();
+                        stream.shutdown().await.unwrap();
+                        Ok(())
+                    },
+                )
+        }
+    });
+
+    let mut sock = srv
+        .connect()
+        .expect("cannot connect to test server")
+        .into_std()
+        .unwrap();
+    sock.set_nonblocking(false).unwrap();
+
+    sock.write_all(b"PROXY TCP6 2001:db8::1 2001:db8::2 56324 443\r\n")
+        .unwrap();
+
+    let connector = openssl_connector(cert, key);
+    let mut stream = connector
+        .connect("localhost", sock)
+        .expect("TLS handshake failed");
+
+    let mut res = String::new();
+    stream.read_to_string(&mut res).unwrap();
+    assert_eq!(res, "[2001:db8::1]:56324");
+}
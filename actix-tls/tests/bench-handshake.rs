@@ -0,0 +1,91 @@
+//! Measure handshake rate of Rustls acceptor using the server benchmark harness.
+
+#![cfg(all(feature = "accept", feature = "rustls"))]
+
+use std::{io::BufReader, sync::Arc, time::Duration};
+
+use actix_rt::net::TcpStream;
+use actix_server::{
+    bench::{BenchHarness, HandshakeRate},
+    TestServer,
+};
+use actix_service::ServiceFactoryExt as _;
+use actix_tls::accept::rustls::{Acceptor, TlsStream};
+use actix_utils::future::ok;
+use rustls_pemfile::{certs, pkcs8_private_keys};
+use tokio_rustls::rustls::{
+    client::{ServerCertVerified, ServerCertVerifier},
+    Certificate, ClientConfig, Error, PrivateKey, RootCertStore, ServerConfig, ServerName,
+};
+
+fn rustls_server_config() -> ServerConfig {
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+
+    let key = cert.serialize_private_key_pem();
+    let cert = cert.serialize_pem().unwrap();
+
+    let certs = certs(&mut BufReader::new(cert.as_bytes())).unwrap();
+    let mut keys = pkcs8_private_keys(&mut BufReader::new(key.as_bytes())).unwrap();
+
+    ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(
+            certs.into_iter().map(Certificate).collect(),
+            PrivateKey(keys.remove(0)),
+        )
+        .unwrap()
+}
+
+struct NoCertificateVerification;
+
+impl ServerCertVerifier for NoCertificateVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &Certificate,
+        _intermediates: &[Certificate],
+        _server_name: &ServerName,
+        _scts: &mut dyn Iterator<Item = &[u8]>,
+        _ocsp_response: &[u8],
+        _now: std::time::SystemTime,
+    ) -> Result<ServerCertVerified, Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+}
+
+#[actix_rt::test]
+async fn rustls_handshake_rate() {
+    let config = rustls_server_config();
+
+    let srv = TestServer::start(move || {
+        Acceptor::new(config.clone())
+            .map_err(|_| ())
+            .and_then(|_stream: TlsStream<TcpStream>| ok(()))
+    });
+
+    let mut client_config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(RootCertStore::empty())
+        .with_no_client_auth();
+    client_config
+        .dangerous()
+        .set_certificate_verifier(Arc::new(NoCertificateVerification));
+
+    let connector = tokio_rustls::TlsConnector::from(Arc::new(client_config));
+
+    let workload = HandshakeRate::new(move |stream: TcpStream| {
+        let server_name = ServerName::try_from("localhost").unwrap();
+        connector.connect(server_name, stream)
+    });
+
+    let report = BenchHarness::new()
+        .concurrency(4)
+        .duration(Duration::from_secs(10))
+        .iterations(20)
+        .run(srv.addr(), workload)
+        .await;
+
+    assert_eq!(report.iterations(), 20);
+    assert_eq!(report.errors(), 0);
+    assert!(report.rate() > 0.0);
+}
@@ -0,0 +1,145 @@
+//! Use fake TLS peers to test handshake error paths of Rustls acceptor and connector.
+
+#![cfg(all(feature = "accept", feature = "connect", feature = "rustls"))]
+
+use std::{io::BufReader, sync::Arc, time::Duration};
+
+use actix_service::{Service as _, ServiceFactory};
+use actix_tls::{
+    accept::{rustls::Acceptor, TlsError},
+    connect::{rustls::TlsConnector, Connection},
+    test_util::{
+        client_hello, malformed_client_hello, malformed_server_hello, ContentType, FakePeer,
+    },
+};
+use actix_utils::duplex::{duplex, DuplexStream};
+use rustls_pemfile::{certs, pkcs8_private_keys};
+use tokio_rustls::rustls::{Certificate, ClientConfig, PrivateKey, RootCertStore, ServerConfig};
+
+fn rustls_acceptor(timeout: Duration) -> Acceptor {
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_owned()]).unwrap();
+
+    let key = cert.serialize_private_key_pem();
+    let cert = cert.serialize_pem().unwrap();
+
+    let certs = certs(&mut BufReader::new(cert.as_bytes())).unwrap();
+    let mut keys = pkcs8_private_keys(&mut BufReader::new(key.as_bytes())).unwrap();
+
+    let config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(
+            certs.into_iter().map(Certificate).collect(),
+            PrivateKey(keys.remove(0)),
+        )
+        .unwrap();
+
+    let mut acceptor = Acceptor::new(config);
+    acceptor.set_handshake_timeout(timeout);
+    acceptor
+}
+
+fn rustls_client_config() -> Arc<ClientConfig> {
+    let config = ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(RootCertStore::empty())
+        .with_no_client_auth();
+
+    Arc::new(config)
+}
+
+#[actix_rt::test]
+async fn acceptor_times_out_on_stalled_client() {
+    let acceptor = rustls_acceptor(Duration::from_millis(100));
+    let acceptor = ServiceFactory::<DuplexStream>::new_service(&acceptor, ())
+        .await
+        .unwrap();
+
+    let (client, server) = duplex(16 * 1024);
+
+    // stall in the middle of the ClientHello record
+    let hello = client_hello(Some("localhost"));
+    let peer = FakePeer::new().send(&hello[..20]).stall_forever();
+    let peer = actix_rt::spawn(peer.run(client));
+
+    let err = acceptor.call(server).await.err().unwrap();
+    assert!(matches!(err, TlsError::Timeout));
+
+    peer.abort();
+}
+
+#[actix_rt::test]
+async fn acceptor_rejects_malformed_client_hello() {
+    let acceptor = rustls_acceptor(Duration::from_secs(10));
+    let acceptor = ServiceFactory::<DuplexStream>::new_service(&acceptor, ())
+        .await
+        .unwrap();
+
+    let (client, server) = duplex(16 * 1024);
+
+    let peer = FakePeer::new().send(malformed_client_hello());
+    let peer = actix_rt::spawn(peer.run(client));
+
+    let err = acceptor.call(server).await.err().unwrap();
+    assert!(matches!(err, TlsError::Tls(_)));
+
+    // acceptor sends alert before closing the connection
+    let records = peer.await.unwrap().unwrap();
+    assert_eq!(records.len(), 1);
+    assert_eq!(records[0].content_type(), ContentType::Alert);
+}
+
+#[actix_rt::test]
+async fn acceptor_handles_abrupt_close_mid_handshake() {
+    let acceptor = rustls_acceptor(Duration::from_secs(10));
+    let acceptor = ServiceFactory::<DuplexStream>::new_service(&acceptor, ())
+        .await
+        .unwrap();
+
+    let (client, server) = duplex(16 * 1024);
+
+    let peer = FakePeer::new()
+        .send(client_hello(Some("localhost")))
+        .read_record()
+        .close();
+    let peer = actix_rt::spawn(peer.run(client));
+
+    let err = acceptor.call(server).await.err().unwrap();
+    assert!(matches!(err, TlsError::Tls(_)));
+
+    // well-formed ClientHello is answered with ServerHello
+    let records = peer.await.unwrap().unwrap();
+    assert_eq!(records[0].handshake_type(), Some(2));
+}
+
+#[actix_rt::test]
+async fn connector_rejects_malformed_server_hello() {
+    let connector = TlsConnector::service(rustls_client_config());
+
+    let (client, server) = duplex(16 * 1024);
+
+    let peer = FakePeer::new().read_record().send(malformed_server_hello());
+    let peer = actix_rt::spawn(peer.run(server));
+
+    let res = connector.call(Connection::new("localhost", client)).await;
+    assert!(res.is_err());
+
+    // ClientHello is received before the connector gives up
+    let records = peer.await.unwrap().unwrap();
+    assert_eq!(records[0].handshake_type(), Some(1));
+}
+
+#[actix_rt::test]
+async fn connector_handles_abrupt_close() {
+    let connector = TlsConnector::service(rustls_client_config());
+
+    let (client, server) = duplex(16 * 1024);
+
+    let peer = FakePeer::new().read_record().close();
+    let peer = actix_rt::spawn(peer.run(server));
+
+    let res = connector.call(Connection::new("localhost", client)).await;
+    assert!(res.is_err());
+
+    peer.await.unwrap().unwrap();
+}
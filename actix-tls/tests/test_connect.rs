@@ -157,3 +157,195 @@ async fn test_local_addr() {
 
     assert_eq!(con.local_addr().unwrap().ip(), local)
 }
+
+#[actix_rt::test]
+async fn connector_layers() {
+    use std::{cell::Cell, rc::Rc};
+
+    use actix_service::ServiceExt as _;
+    use actix_tls::connect::ConnectLayer;
+
+    /// Counts calls to the wrapped connector.
+    struct CountCalls(Rc<Cell<usize>>);
+
+    impl<S> ConnectLayer<S> for CountCalls {
+        type Service = CountCallsService<S>;
+
+        fn layer(&self, service: S) -> Self::Service {
+            CountCallsService {
+                service,
+                calls: self.0.clone(),
+            }
+        }
+    }
+
+    struct CountCallsService<S> {
+        service: S,
+        calls: Rc<Cell<usize>>,
+    }
+
+    impl<S, R> Service<R> for CountCallsService<S>
+    where
+        S: Service<R>,
+    {
+        type Response = S::Response;
+        type Error = S::Error;
+        type Future = S::Future;
+
+        actix_service::forward_ready!(service);
+
+        fn call(&self, req: R) -> Self::Future {
+            self.calls.set(self.calls.get() + 1);
+            self.service.call(req)
+        }
+    }
+
+    let srv = TestServer::start(|| fn_service(|_| async { Ok::<_, ()>(()) }));
+
+    let calls = Rc::new(Cell::new(0));
+
+    let connector = Connector::default()
+        .builder()
+        .layer(CountCalls(calls.clone()))
+        .layer_fn(|svc| svc.map_err(|err: ConnectError| err.to_string()))
+        .finish();
+
+    let info = ConnectInfo::with_addr("10", srv.addr());
+    let conn = connector.call(info).await.unwrap();
+    assert_eq!(conn.peer_addr().unwrap(), srv.addr());
+    assert_eq!(calls.get(), 1);
+
+    let info = ConnectInfo::new("unknown.invalid").set_port(1);
+    assert!(connector.call(info).await.is_err());
+    assert_eq!(calls.get(), 2);
+}
+
+#[actix_rt::test]
+async fn happy_eyeballs() {
+    use std::time::{Duration, Instant};
+
+    use actix_rt::{net::TcpSocket, time::timeout};
+    use actix_tls::connect::HappyEyeballs;
+
+    let srv = TestServer::start(|| fn_service(|_| async { Ok::<_, ()>(()) }));
+
+    // listener with a full accept queue, which drops further connection attempts like an address
+    // with broken connectivity would
+    let lst = TcpSocket::new_v4().unwrap();
+    lst.bind("127.0.0.1:0".parse().unwrap()).unwrap();
+    let lst = lst.listen(0).unwrap();
+    let hanging = lst.local_addr().unwrap();
+
+    let mut queued = Vec::new();
+    while let Ok(conn) = timeout(Duration::from_millis(100), TcpStream::connect(hanging)).await {
+        queued.push(conn.unwrap());
+    }
+
+    let connector = Connector::default()
+        .happy_eyeballs(
+            HappyEyeballs::new()
+                .attempt_delay(Duration::from_millis(50))
+                .timeout(Duration::from_secs(5)),
+        )
+        .service();
+
+    let start = Instant::now();
+    let info = ConnectInfo::new("10").set_addrs([hanging, srv.addr()]);
+    let conn = connector.call(info).await.unwrap();
+    assert_eq!(conn.peer_addr().unwrap(), srv.addr());
+    assert!(start.elapsed() < Duration::from_secs(1));
+
+    let connector = Connector::default()
+        .happy_eyeballs(HappyEyeballs::new().timeout(Duration::from_millis(100)))
+        .service();
+
+    let info = ConnectInfo::new("10").set_addrs([hanging, hanging]);
+    match connector.call(info).await {
+        Err(ConnectError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::TimedOut),
+        res => panic!("expected timeout, got {:?}", res.map(|_| ())),
+    }
+}
+
+#[actix_rt::test]
+async fn local_port_range() {
+    let srv = TestServer::start(|| fn_service(|_| async { Ok::<_, ()>(()) }));
+
+    // port that was free a moment ago
+    let free = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let connector = Connector::default().local_port_range(free..=free).service();
+
+    let conn = connector
+        .call(ConnectInfo::with_addr("10", srv.addr()))
+        .await
+        .unwrap();
+    assert_eq!(conn.local_addr().unwrap().port(), free);
+    drop(conn);
+
+    // all ports of range in use
+    let lst = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let used = lst.local_addr().unwrap().port();
+
+    let connector = Connector::default().local_port_range(used..=used).service();
+
+    match connector
+        .call(ConnectInfo::with_addr("10", srv.addr()))
+        .await
+    {
+        Err(ConnectError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::AddrInUse),
+        res => panic!("expected port in use, got {:?}", res.map(|_| ())),
+    }
+}
+
+#[actix_rt::test]
+async fn srv_targets() {
+    use actix_tls::connect::SrvTarget;
+
+    let srv = TestServer::start(|| fn_service(|_| async { Ok::<_, ()>(()) }));
+
+    // port that nothing listens on anymore
+    let closed = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+
+    let connector = Connector::default().service();
+
+    // falls back to lower priority target once connecting to preferred target failed
+    let info = ConnectInfo::new("service.example").set_srv_targets([
+        SrvTarget::new(20, 0, srv.port(), "127.0.0.1"),
+        SrvTarget::new(10, 0, closed, "127.0.0.1"),
+    ]);
+
+    let conn = connector.call(info).await.unwrap();
+    assert_eq!(conn.peer_addr().unwrap(), srv.addr());
+    assert_eq!(conn.hostname(), "service.example");
+
+    let target = conn.srv_target().unwrap();
+    assert_eq!(target.priority(), 20);
+    assert_eq!(target.port(), srv.port());
+
+    // service is decidedly not available
+    let info = ConnectInfo::new("service.example").set_srv_targets([SrvTarget::new(0, 0, 0, ".")]);
+    match connector.call(info).await {
+        Err(ConnectError::NoRecords) => {}
+        res => panic!("expected no records, got {:?}", res.map(|_| ())),
+    }
+
+    // error of last target is returned
+    let info = ConnectInfo::new("service.example").set_srv_targets([SrvTarget::new(
+        10,
+        0,
+        closed,
+        "127.0.0.1",
+    )]);
+    match connector.call(info).await {
+        Err(ConnectError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::ConnectionRefused),
+        res => panic!("expected connection refused, got {:?}", res.map(|_| ())),
+    }
+}
@@ -89,3 +89,83 @@ async fn custom_resolver_connect() {
         .unwrap();
     assert_eq!(con.peer_addr().unwrap(), srv.addr());
 }
+
+#[actix_rt::test]
+async fn cached_resolver_connect() {
+    use std::{cell::Cell, rc::Rc, time::Duration};
+
+    use actix_tls::connect::DnsCache;
+
+    /// Resolves to localhost, counting lookups.
+    struct CountingResolver(Rc<Cell<usize>>);
+
+    impl Resolve for CountingResolver {
+        fn lookup<'a>(
+            &'a self,
+            _host: &'a str,
+            port: u16,
+        ) -> LocalBoxFuture<'a, Result<Vec<SocketAddr>, Box<dyn std::error::Error>>> {
+            self.0.set(self.0.get() + 1);
+
+            Box::pin(async move { Ok(vec![SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port)]) })
+        }
+    }
+
+    let srv = TestServer::start(|| fn_service(|_io: TcpStream| async { Ok::<_, io::Error>(()) }));
+
+    let lookups = Rc::new(Cell::new(0));
+    let cache = DnsCache::new(Duration::from_secs(60));
+
+    // connectors sharing a cache only look up each host once
+    for _ in 0..2 {
+        let resolver =
+            Resolver::custom(CountingResolver(lookups.clone())).with_cache(cache.clone());
+        let conn = Connector::new(resolver).service();
+
+        for _ in 0..2 {
+            let con = conn
+                .call(ConnectInfo::new("example.com").set_port(srv.port()))
+                .await
+                .unwrap();
+            assert_eq!(con.peer_addr().unwrap(), srv.addr());
+        }
+    }
+
+    assert_eq!(lookups.get(), 1);
+}
+
+#[cfg(feature = "idna")]
+#[actix_rt::test]
+async fn idn_hostnames_are_resolved_as_punycode() {
+    use std::{cell::RefCell, rc::Rc};
+
+    /// Records looked up hostnames and resolves them to localhost.
+    struct RecordingResolver(Rc<RefCell<Vec<String>>>);
+
+    impl Resolve for RecordingResolver {
+        fn lookup<'a>(
+            &'a self,
+            host: &'a str,
+            port: u16,
+        ) -> LocalBoxFuture<'a, Result<Vec<SocketAddr>, Box<dyn std::error::Error>>> {
+            self.0.borrow_mut().push(host.to_owned());
+            Box::pin(async move { Ok(vec![SocketAddr::new(Ipv4Addr::LOCALHOST.into(), port)]) })
+        }
+    }
+
+    let hosts = Rc::new(RefCell::new(Vec::new()));
+    let resolver = Resolver::custom(RecordingResolver(Rc::clone(&hosts))).service();
+
+    let req = resolver
+        .call(ConnectInfo::new("bücher.example").set_port(8080))
+        .await
+        .unwrap();
+    assert_eq!(req.hostname(), "bücher.example");
+    assert_eq!(hosts.borrow().as_slice(), ["xn--bcher-kva.example"]);
+
+    let err = resolver
+        .call(ConnectInfo::new("xn--bücher.example"))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ConnectError::InvalidInput));
+}
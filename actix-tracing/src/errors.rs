@@ -0,0 +1,84 @@
+//! Error events with user-defined levels.
+
+use core::fmt;
+
+use tracing::{Level, Span};
+
+use crate::RecordOutcome;
+
+/// Outcome recorder that emits an event for each failed call, at a level chosen per error.
+///
+/// The mapping returns the level of the event for an error, or `None` to not record it. This
+/// allows expected failures, such as clients disconnecting, to be recorded at `DEBUG` while
+/// internal errors are recorded at `ERROR`.
+///
+/// Events are emitted within the call's span with an `error` field containing the error's `Debug`
+/// representation. As with other recorders, nothing is recorded for calls without a span.
+///
+/// Created by [`error_events`], or added to a traced service with
+/// [`TracingService::record_errors`](crate::TracingService::record_errors).
+#[derive(Clone)]
+pub struct ErrorEvents<M> {
+    map: M,
+}
+
+/// Creates an [`ErrorEvents`] recorder using `map` to choose the level of each error.
+///
+/// ```ignore
+/// let traced_service = trace_with_outcome(
+///     web_service,
+///     |req: &Request| Some(span!(Level::INFO, "request")),
+///     error_events(|err: &Error| match err {
+///         Error::Disconnected => Some(Level::DEBUG),
+///         Error::Timeout => Some(Level::WARN),
+///         _ => Some(Level::ERROR),
+///     }),
+/// );
+/// ```
+pub fn error_events<Err, M>(map: M) -> ErrorEvents<M>
+where
+    M: Fn(&Err) -> Option<Level>,
+{
+    ErrorEvents::new(map)
+}
+
+impl<M> ErrorEvents<M> {
+    pub(crate) fn new(map: M) -> Self {
+        Self { map }
+    }
+}
+
+impl<M> fmt::Debug for ErrorEvents<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ErrorEvents").finish_non_exhaustive()
+    }
+}
+
+impl<M, Res, Err> RecordOutcome<Res, Err> for ErrorEvents<M>
+where
+    M: Fn(&Err) -> Option<Level>,
+    Err: fmt::Debug,
+{
+    fn record_outcome(&self, span: &Span, outcome: Result<&Res, &Err>) {
+        let err = match outcome {
+            Ok(_) => return,
+            Err(err) => err,
+        };
+
+        // event levels must be known statically
+        match (self.map)(err) {
+            Some(Level::ERROR) => {
+                tracing::error!(parent: span, error = ?err, "service call failed")
+            }
+            Some(Level::WARN) => tracing::warn!(parent: span, error = ?err, "service call failed"),
+            Some(Level::INFO) => tracing::info!(parent: span, error = ?err, "service call failed"),
+            Some(Level::DEBUG) => {
+                tracing::debug!(parent: span, error = ?err, "service call failed")
+            }
+            Some(Level::TRACE) => {
+                tracing::trace!(parent: span, error = ?err, "service call failed")
+            }
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//! Spans around service construction.
+
+use core::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Context, Poll},
+};
+use std::{sync::Arc, time::Instant};
+
+use actix_service::{IntoServiceFactory, ServiceFactory};
+use pin_project_lite::pin_project;
+use tracing::field;
+use tracing_futures::{Instrument as _, Instrumented};
+
+/// A `ServiceFactory` that runs each service construction within a tracing span.
+///
+/// Every call to `new_service` creates an `INFO` span named `new_service` with the following
+/// fields:
+///
+/// - `service`: the name given to the factory;
+/// - `instance`: sequential index of the service being created, starting at 0 and shared between
+///   clones of the factory. Servers create one service per worker, so this tells workers apart;
+/// - `config`: `Debug` representation of the service config;
+/// - `elapsed_ms`: time taken to create the service, recorded once it completes;
+/// - `error`: `Debug` representation of the init error, recorded if creation fails.
+///
+/// Failures additionally emit an `ERROR` event within the span.
+///
+/// Created by [`trace_factory`].
+pub struct TracingFactory<SF> {
+    factory: SF,
+    name: &'static str,
+    instances: Arc<AtomicUsize>,
+}
+
+impl<SF> TracingFactory<SF> {
+    /// Wraps `factory` so that creating services is traced under the given name.
+    pub fn new(factory: SF, name: &'static str) -> Self {
+        Self {
+            factory,
+            name,
+            instances: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+impl<SF: Clone> Clone for TracingFactory<SF> {
+    fn clone(&self) -> Self {
+        Self {
+            factory: self.factory.clone(),
+            name: self.name,
+            instances: self.instances.clone(),
+        }
+    }
+}
+
+impl<SF> fmt::Debug for TracingFactory<SF> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TracingFactory")
+            .field("name", &self.name)
+            .field("instances", &self.instances.load(Ordering::Relaxed))
+            .finish_non_exhaustive()
+    }
+}
+
+impl<SF, Req> ServiceFactory<Req> for TracingFactory<SF>
+where
+    SF: ServiceFactory<Req>,
+    SF::Config: fmt::Debug,
+    SF::InitError: fmt::Debug,
+{
+    type Response = SF::Response;
+    type Error = SF::Error;
+    type Config = SF::Config;
+    type Service = SF::Service;
+    type InitError = SF::InitError;
+    type Future = TracingFactoryFuture<SF::Future>;
+
+    fn new_service(&self, cfg: Self::Config) -> Self::Future {
+        let instance = self.instances.fetch_add(1, Ordering::Relaxed);
+
+        let span = tracing::info_span!(
+            "new_service",
+            service = self.name,
+            instance,
+            config = ?cfg,
+            elapsed_ms = field::Empty,
+            error = field::Empty,
+        );
+
+        let start = Instant::now();
+        let fut = span.in_scope(|| self.factory.new_service(cfg));
+
+        TracingFactoryFuture {
+            fut: fut.instrument(span),
+            start,
+        }
+    }
+}
+
+pin_project! {
+    /// Future returned by [`TracingFactory`], recording the outcome of service construction.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct TracingFactoryFuture<Fut> {
+        #[pin]
+        fut: Instrumented<Fut>,
+        start: Instant,
+    }
+}
+
+impl<Fut, Svc, Err> Future for TracingFactoryFuture<Fut>
+where
+    Fut: Future<Output = Result<Svc, Err>>,
+    Err: fmt::Debug,
+{
+    type Output = Fut::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        let res = match this.fut.as_mut().poll(cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        let span = this.fut.span();
+        span.record("elapsed_ms", this.start.elapsed().as_millis() as u64);
+
+        if let Err(err) = &res {
+            span.record("error", field::debug(err));
+            span.in_scope(|| tracing::error!(error = ?err, "service initialization failed"));
+        }
+
+        Poll::Ready(res)
+    }
+}
+
+/// Wraps the provided service factory so that each service construction runs within a span.
+///
+/// See [`TracingFactory`] for the recorded span fields.
+///
+/// ```ignore
+/// let factory = trace_factory(trace(web_service, make_span), "web");
+/// ```
+pub fn trace_factory<SF, Req, I>(service_factory: I, name: &'static str) -> TracingFactory<SF>
+where
+    I: IntoServiceFactory<SF, Req>,
+    SF: ServiceFactory<Req>,
+{
+    TracingFactory::new(service_factory.into_factory(), name)
+}
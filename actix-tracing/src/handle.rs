@@ -0,0 +1,123 @@
+//! Runtime control of traced services.
+
+use core::{
+    fmt,
+    sync::atomic::{AtomicBool, AtomicU8, Ordering},
+};
+use std::sync::Arc;
+
+use tracing::Level;
+
+/// Handle for turning tracing of services on and off, or changing its level, at runtime.
+///
+/// Services share a handle by being configured with clones of it, using
+/// [`TracingService::with_handle`](crate::TracingService::with_handle) or the matching transform
+/// method. Changes take effect from the next call of each service, without rebuilding the service
+/// stack; calls in progress are unaffected.
+///
+/// While disabled, services neither call their span closure nor record outcomes. Metrics are still
+/// emitted.
+///
+/// # Examples
+/// ```
+/// use actix_tracing::TracingHandle;
+/// use tracing::Level;
+///
+/// let handle = TracingHandle::new();
+/// assert!(handle.is_enabled());
+///
+/// // trace everything during an incident
+/// handle.set_level(Some(Level::TRACE));
+///
+/// // and back to each service's own level afterwards
+/// handle.set_level(None);
+///
+/// assert_eq!(handle.level(), None);
+/// ```
+#[derive(Clone)]
+pub struct TracingHandle {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    enabled: AtomicBool,
+    level: AtomicU8,
+}
+
+/// No level override.
+const NO_LEVEL: u8 = 0;
+
+impl TracingHandle {
+    /// Constructs new handle, enabled and without a level override.
+    pub fn new() -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                enabled: AtomicBool::new(true),
+                level: AtomicU8::new(NO_LEVEL),
+            }),
+        }
+    }
+
+    /// Enables tracing of services using this handle.
+    pub fn enable(&self) {
+        self.set_enabled(true);
+    }
+
+    /// Disables tracing of services using this handle.
+    pub fn disable(&self) {
+        self.set_enabled(false);
+    }
+
+    /// Enables or disables tracing of services using this handle.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.inner.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Returns true if tracing is enabled.
+    pub fn is_enabled(&self) -> bool {
+        self.inner.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Overrides the levels of services using this handle, or restores their own levels if `None`.
+    ///
+    /// See [`TracingService::level`](crate::TracingService::level).
+    pub fn set_level(&self, level: Option<Level>) {
+        let level = match level {
+            None => NO_LEVEL,
+            Some(Level::ERROR) => 1,
+            Some(Level::WARN) => 2,
+            Some(Level::INFO) => 3,
+            Some(Level::DEBUG) => 4,
+            Some(Level::TRACE) => 5,
+        };
+
+        self.inner.level.store(level, Ordering::Relaxed);
+    }
+
+    /// Returns the level override, if set.
+    pub fn level(&self) -> Option<Level> {
+        match self.inner.level.load(Ordering::Relaxed) {
+            1 => Some(Level::ERROR),
+            2 => Some(Level::WARN),
+            3 => Some(Level::INFO),
+            4 => Some(Level::DEBUG),
+            5 => Some(Level::TRACE),
+            _ => None,
+        }
+    }
+}
+
+impl Default for TracingHandle {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for TracingHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TracingHandle")
+            .field("enabled", &self.is_enabled())
+            .field("level", &self.level())
+            .finish()
+    }
+}
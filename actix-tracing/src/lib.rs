@@ -1,90 +1,385 @@
 //! Actix tracing - support for tokio tracing with Actix services.
+//!
+//! # Server Integration
+//! [`actix-server`](https://docs.rs/actix-server) runs each worker within a `worker` span, recording
+//! the worker's index, and each accepted connection within a `connection` span that is a child of
+//! its worker's span, recording the listener name and peer address. Spans created by [`trace`] and
+//! [`trace_factory`] for services run by the server are nested in these, so traces form the tree
+//! `worker` → `connection` → request span → `future`, and `worker` → `new_service`.
 
 #![deny(rust_2018_idioms, nonstandard_style)]
 #![warn(future_incompatible)]
 #![doc(html_logo_url = "https://actix.rs/img/logo.png")]
 #![doc(html_favicon_url = "https://actix.rs/favicon.ico")]
+#![cfg_attr(docsrs, feature(doc_auto_cfg))]
 
-use core::marker::PhantomData;
+use core::{
+    future::Future,
+    marker::PhantomData,
+    pin::Pin,
+    task::{Context, Poll},
+};
 
 use actix_service::{
     apply, ApplyTransform, IntoServiceFactory, Service, ServiceFactory, Transform,
 };
 use actix_utils::future::{ok, Either, Ready};
+use pin_project_lite::pin_project;
+use tracing::{Level, Span};
 use tracing_futures::{Instrument, Instrumented};
 
+mod errors;
+mod factory;
+mod handle;
+pub mod metrics;
+#[cfg(feature = "opentelemetry")]
+pub mod otel;
+mod sampling;
+
+pub use self::{
+    errors::{error_events, ErrorEvents},
+    factory::{trace_factory, TracingFactory, TracingFactoryFuture},
+    handle::TracingHandle,
+};
+use self::{
+    metrics::{CallMetrics, MetricsConfig},
+    sampling::Sampling,
+};
+
+/// Options shared by a traced service and the transform creating it.
+#[derive(Debug, Clone)]
+struct Config {
+    metrics: MetricsConfig,
+    sampling: Sampling,
+    handle: Option<TracingHandle>,
+}
+
+impl Config {
+    fn new() -> Self {
+        Self {
+            metrics: MetricsConfig::disabled(),
+            sampling: Sampling::new(),
+            handle: None,
+        }
+    }
+
+    /// Returns the level to trace the next call at, or `None` if tracing is disabled.
+    fn level(&self) -> Option<Level> {
+        match &self.handle {
+            Some(handle) if !handle.is_enabled() => None,
+            Some(handle) => Some(handle.level().unwrap_or_else(|| self.sampling.level())),
+            None => Some(self.sampling.level()),
+        }
+    }
+}
+
+/// Records the outcome of a service call on the span created for the request.
+///
+/// Implemented for `()`, which records nothing, for closures taking the span and a reference to
+/// the result of the call, for [`ErrorEvents`], and for pairs of recorders, which record both.
+/// Fields to be recorded must be declared when the span is created, using
+/// [`tracing::field::Empty`] for those that are only known once the call completes.
+pub trait RecordOutcome<Res, Err> {
+    /// Records the outcome of a call on `span`.
+    fn record_outcome(&self, span: &Span, outcome: Result<&Res, &Err>);
+}
+
+impl<Res, Err> RecordOutcome<Res, Err> for () {
+    fn record_outcome(&self, _: &Span, _: Result<&Res, &Err>) {}
+}
+
+impl<F, Res, Err> RecordOutcome<Res, Err> for F
+where
+    F: Fn(&Span, Result<&Res, &Err>),
+{
+    fn record_outcome(&self, span: &Span, outcome: Result<&Res, &Err>) {
+        (self)(span, outcome)
+    }
+}
+
+impl<A, B, Res, Err> RecordOutcome<Res, Err> for (A, B)
+where
+    A: RecordOutcome<Res, Err>,
+    B: RecordOutcome<Res, Err>,
+{
+    fn record_outcome(&self, span: &Span, outcome: Result<&Res, &Err>) {
+        self.0.record_outcome(span, outcome);
+        self.1.record_outcome(span, outcome);
+    }
+}
+
 /// A `Service` implementation that automatically enters/exits tracing spans
 /// for the wrapped inner service.
 #[derive(Clone)]
-pub struct TracingService<S, F> {
+pub struct TracingService<S, F, R = ()> {
     inner: S,
     make_span: F,
+    record: R,
+    config: Config,
 }
 
 impl<S, F> TracingService<S, F> {
+    /// Wraps `inner` so that each call runs within the span returned by `make_span`.
     pub fn new(inner: S, make_span: F) -> Self {
-        TracingService { inner, make_span }
+        TracingService {
+            inner,
+            make_span,
+            record: (),
+            config: Config::new(),
+        }
+    }
+}
+
+impl<S, F, R> TracingService<S, F, R> {
+    /// Sets a recorder that is called with the result of each call on the span created for it.
+    ///
+    /// The recorder is cloned into every call's future.
+    pub fn record_outcome<R2>(self, record: R2) -> TracingService<S, F, R2> {
+        TracingService {
+            inner: self.inner,
+            make_span: self.make_span,
+            record,
+            config: self.config,
+        }
+    }
+
+    /// Emits an event for each failed call, at the level returned by `map` for the error.
+    ///
+    /// The events are recorded in addition to the current recorder. See [`ErrorEvents`].
+    pub fn record_errors<M>(self, map: M) -> TracingService<S, F, (R, ErrorEvents<M>)> {
+        TracingService {
+            inner: self.inner,
+            make_span: self.make_span,
+            record: (self.record, ErrorEvents::new(map)),
+            config: self.config,
+        }
+    }
+
+    /// Enables call latency and error metrics, labelled with the given service name.
+    ///
+    /// See the [`metrics`] module for the emitted metrics.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, name: &'static str) -> Self {
+        self.config.metrics = MetricsConfig::new(name);
+        self
+    }
+
+    /// Sets the level of this service's calls, `INFO` by default.
+    ///
+    /// Calls are only traced when the level is enabled by the maximum level of the installed
+    /// subscribers; otherwise the span closure is not called at all. The level also applies to the
+    /// child span tracking each call's future. The level of the span returned by the closure is
+    /// chosen by the closure.
+    pub fn level(mut self, level: Level) -> Self {
+        self.config.sampling.set_level(level);
+        self
+    }
+
+    /// Sets the fraction of calls that are traced, between `0.0` and `1.0` (the default).
+    ///
+    /// Calls are picked at random. Calls that are not picked run without a span and the span
+    /// closure is not called for them.
+    ///
+    /// # Panics
+    /// Panics if `rate` is not between `0.0` and `1.0`.
+    pub fn sample_rate(mut self, rate: f64) -> Self {
+        self.config.sampling.set_rate(rate);
+        self
+    }
+
+    /// Controls this service's tracing through the given handle.
+    ///
+    /// Disabling the handle turns off span creation and outcome recording, and setting its level
+    /// overrides the level set with [`level`](Self::level). See [`TracingHandle`].
+    pub fn with_handle(mut self, handle: TracingHandle) -> Self {
+        self.config.handle = Some(handle);
+        self
     }
 }
 
-impl<S, Req, F> Service<Req> for TracingService<S, F>
+impl<S, Req, F, R> Service<Req> for TracingService<S, F, R>
 where
     S: Service<Req>,
     F: Fn(&Req) -> Option<tracing::Span>,
+    R: RecordOutcome<S::Response, S::Error> + Clone,
 {
     type Response = S::Response;
     type Error = S::Error;
-    type Future = Either<S::Future, Instrumented<S::Future>>;
+    type Future = TracingFuture<S::Future, R>;
 
     actix_service::forward_ready!(inner);
 
     fn call(&self, req: Req) -> Self::Future {
-        let span = (self.make_span)(&req);
-        let _enter = span.as_ref().map(|s| s.enter());
+        let metrics = self.config.metrics.start();
+
+        let level = self
+            .config
+            .level()
+            .filter(|&level| self.config.sampling.should_trace(level));
+
+        let span = level.and_then(|_| (self.make_span)(&req));
 
-        let fut = self.inner.call(req);
+        let fut = {
+            let _enter = span.as_ref().map(|s| s.enter());
+            self.inner.call(req)
+        };
 
         // make a child span to track the future's execution
-        if let Some(span) = span
-            .clone()
-            .map(|span| tracing::span!(parent: &span, tracing::Level::INFO, "future"))
-        {
+        let fut = if let Some((span, level)) = span.as_ref().zip(level) {
+            let span = sampling::future_span(level, span);
             Either::right(fut.instrument(span))
         } else {
             Either::left(fut)
+        };
+
+        TracingFuture {
+            fut,
+            span,
+            record: self.record.clone(),
+            metrics,
         }
     }
 }
 
+pin_project! {
+    /// Future returned by [`TracingService`], recording the call's outcome once it completes.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct TracingFuture<Fut, R> {
+        #[pin]
+        fut: Either<Fut, Instrumented<Fut>>,
+        span: Option<Span>,
+        record: R,
+        metrics: CallMetrics,
+    }
+}
+
+impl<Fut, R, Res, Err> Future for TracingFuture<Fut, R>
+where
+    Fut: Future<Output = Result<Res, Err>>,
+    R: RecordOutcome<Res, Err>,
+{
+    type Output = Fut::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        let res = match this.fut.poll(cx) {
+            Poll::Ready(res) => res,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        this.metrics.finish(res.is_err());
+
+        if let Some(span) = this.span.take() {
+            this.record.record_outcome(&span, res.as_ref());
+        }
+
+        Poll::Ready(res)
+    }
+}
+
 /// A `Transform` implementation that wraps services with a [`TracingService`].
-pub struct TracingTransform<S, U, F> {
+pub struct TracingTransform<S, U, F, R = ()> {
     make_span: F,
+    record: R,
+    config: Config,
     _p: PhantomData<fn(S, U)>,
 }
 
 impl<S, U, F> TracingTransform<S, U, F> {
+    /// Constructs new transform that runs each call within the span returned by `make_span`.
     pub fn new(make_span: F) -> Self {
         TracingTransform {
             make_span,
+            record: (),
+            config: Config::new(),
+            _p: PhantomData,
+        }
+    }
+}
+
+impl<S, U, F, R> TracingTransform<S, U, F, R> {
+    /// Sets a recorder that is called with the result of each call on the span created for it.
+    ///
+    /// See [`TracingService::record_outcome`].
+    pub fn record_outcome<R2>(self, record: R2) -> TracingTransform<S, U, F, R2> {
+        TracingTransform {
+            make_span: self.make_span,
+            record,
+            config: self.config,
+            _p: PhantomData,
+        }
+    }
+
+    /// Emits an event for each failed call, at the level returned by `map` for the error.
+    ///
+    /// See [`TracingService::record_errors`].
+    pub fn record_errors<M>(self, map: M) -> TracingTransform<S, U, F, (R, ErrorEvents<M>)> {
+        TracingTransform {
+            make_span: self.make_span,
+            record: (self.record, ErrorEvents::new(map)),
+            config: self.config,
             _p: PhantomData,
         }
     }
+
+    /// Enables call latency and error metrics, labelled with the given service name.
+    ///
+    /// See [`TracingService::with_metrics`].
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, name: &'static str) -> Self {
+        self.config.metrics = MetricsConfig::new(name);
+        self
+    }
+
+    /// Sets the level of the service's calls, `INFO` by default.
+    ///
+    /// See [`TracingService::level`].
+    pub fn level(mut self, level: Level) -> Self {
+        self.config.sampling.set_level(level);
+        self
+    }
+
+    /// Sets the fraction of calls that are traced, between `0.0` and `1.0` (the default).
+    ///
+    /// See [`TracingService::sample_rate`].
+    ///
+    /// # Panics
+    /// Panics if `rate` is not between `0.0` and `1.0`.
+    pub fn sample_rate(mut self, rate: f64) -> Self {
+        self.config.sampling.set_rate(rate);
+        self
+    }
+
+    /// Controls the service's tracing through the given handle.
+    ///
+    /// See [`TracingService::with_handle`].
+    pub fn with_handle(mut self, handle: TracingHandle) -> Self {
+        self.config.handle = Some(handle);
+        self
+    }
 }
 
-impl<S, Req, U, F> Transform<S, Req> for TracingTransform<S, U, F>
+impl<S, Req, U, F, R> Transform<S, Req> for TracingTransform<S, U, F, R>
 where
     S: Service<Req>,
     U: ServiceFactory<Req, Response = S::Response, Error = S::Error, Service = S>,
     F: Fn(&Req) -> Option<tracing::Span> + Clone,
+    R: RecordOutcome<S::Response, S::Error> + Clone,
 {
     type Response = S::Response;
     type Error = S::Error;
-    type Transform = TracingService<S, F>;
+    type Transform = TracingService<S, F, R>;
     type InitError = U::InitError;
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ok(TracingService::new(service, self.make_span.clone()))
+        ok(TracingService {
+            inner: service,
+            make_span: self.make_span.clone(),
+            record: self.record.clone(),
+            config: self.config.clone(),
+        })
     }
 }
 
@@ -116,22 +411,70 @@ where
     )
 }
 
+/// Like [`trace`], additionally recording the outcome of each call on its span.
+///
+/// Fields extracted from the request are added when creating the span; fields describing the
+/// outcome must be declared up front as [`Empty`](tracing::field::Empty) and are filled in by
+/// `record` once the call completes.
+///
+/// For example:
+/// ```ignore
+/// let traced_service = trace_with_outcome(
+///     web_service,
+///     |req: &Request| {
+///         Some(span!(
+///             Level::INFO,
+///             "request",
+///             method = %req.method,
+///             request_id = req.id,
+///             status = field::Empty,
+///             error = field::Empty,
+///         ))
+///     },
+///     |span: &Span, res: Result<&Response, &Error>| match res {
+///         Ok(res) => {
+///             span.record("status", res.status);
+///         }
+///         Err(err) => {
+///             span.record("error", &field::display(err));
+///         }
+///     },
+/// );
+/// ```
+pub fn trace_with_outcome<S, Req, I, F, R>(
+    service_factory: I,
+    make_span: F,
+    record: R,
+) -> ApplyTransform<TracingTransform<S::Service, S, F, R>, S, Req>
+where
+    I: IntoServiceFactory<S, Req>,
+    S: ServiceFactory<Req>,
+    F: Fn(&Req) -> Option<tracing::Span> + Clone,
+    R: RecordOutcome<S::Response, S::Error> + Clone,
+{
+    apply(
+        TracingTransform::new(make_span).record_outcome(record),
+        service_factory.into_factory(),
+    )
+}
+
 #[cfg(test)]
 mod test {
     use std::{
         cell::RefCell,
         collections::{BTreeMap, BTreeSet},
+        fmt,
         sync::{Arc, RwLock},
     };
 
     use actix_service::{fn_factory, fn_service};
     use slab::Slab;
-    use tracing::{span, Event, Level, Metadata, Subscriber};
+    use tracing::{field, span, Event, Level, Metadata, Subscriber};
 
     use super::*;
 
     thread_local! {
-        static SPAN: RefCell<Vec<span::Id>> = RefCell::new(Vec::new());
+        static SPAN: RefCell<Vec<span::Id>> = const { RefCell::new(Vec::new()) };
     }
 
     #[derive(Default)]
@@ -139,6 +482,16 @@ mod test {
         entered_spans: BTreeSet<u64>,
         exited_spans: BTreeSet<u64>,
         events_count: BTreeMap<u64, usize>,
+        event_levels: Vec<Level>,
+        recorded: BTreeMap<u64, Vec<(&'static str, String)>>,
+    }
+
+    struct RecordVisitor<'a>(&'a mut Vec<(&'static str, String)>);
+
+    impl field::Visit for RecordVisitor<'_> {
+        fn record_debug(&mut self, field: &field::Field, value: &dyn fmt::Debug) {
+            self.0.push((field.name(), format!("{:?}", value)));
+        }
     }
 
     #[derive(Default)]
@@ -162,7 +515,11 @@ mod test {
             span::Id::from_u64(id as u64 + 1)
         }
 
-        fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+        fn record(&self, span: &span::Id, values: &span::Record<'_>) {
+            let mut inner = self.inner.write().unwrap();
+            let recorded = inner.stats.recorded.entry(span.into_u64()).or_default();
+            values.record(&mut RecordVisitor(recorded));
+        }
 
         fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
 
@@ -173,14 +530,9 @@ mod test {
                 .or_else(|| SPAN.with(|current_span| current_span.borrow().last().cloned()))
                 .unwrap();
 
-            *self
-                .inner
-                .write()
-                .unwrap()
-                .stats
-                .events_count
-                .entry(id.into_u64())
-                .or_insert(0) += 1;
+            let mut inner = self.inner.write().unwrap();
+            *inner.stats.events_count.entry(id.into_u64()).or_insert(0) += 1;
+            inner.stats.event_levels.push(*event.metadata().level());
         }
 
         fn enter(&self, span: &span::Id) {
@@ -252,4 +604,111 @@ mod test {
             .contains(&id));
         assert_eq!(subscriber.inner.read().unwrap().stats.events_count[&id], 1);
     }
+
+    #[actix_rt::test]
+    async fn records_outcome() {
+        let service_factory = fn_factory(|| {
+            ok::<_, ()>(fn_service(|req: u32| async move {
+                if req % 2 == 0 {
+                    Ok(req * 10)
+                } else {
+                    Err("odd request")
+                }
+            }))
+        });
+
+        let subscriber = TestSubscriber::default();
+        let _guard = tracing::subscriber::set_default(subscriber.clone());
+
+        let spans = Arc::new(RwLock::new(Vec::new()));
+        let trace_service_factory = trace_with_outcome(
+            service_factory,
+            {
+                let spans = spans.clone();
+                move |req: &u32| {
+                    let span = span!(Level::INFO, "req", id = *req, res = field::Empty);
+                    spans.write().unwrap().push(span.clone());
+                    Some(span)
+                }
+            },
+            |span: &Span, res: Result<&u32, &&str>| match res {
+                Ok(res) => {
+                    span.record("res", res);
+                }
+                Err(err) => {
+                    span.record("res", err);
+                }
+            },
+        );
+        let service = trace_service_factory.new_service(()).await.unwrap();
+
+        assert_eq!(service.call(2).await, Ok(20));
+        assert_eq!(service.call(3).await, Err("odd request"));
+
+        let spans = spans.read().unwrap();
+        let inner = subscriber.inner.read().unwrap();
+        let recorded = |span: &Span| inner.stats.recorded[&span.id().unwrap().into_u64()].clone();
+
+        assert_eq!(recorded(&spans[0]), [("res", "20".to_owned())]);
+        assert_eq!(recorded(&spans[1]), [("res", "\"odd request\"".to_owned())]);
+    }
+
+    #[actix_rt::test]
+    async fn records_errors_at_mapped_level() {
+        let service_factory = fn_factory(|| {
+            ok::<_, ()>(fn_service(|req: &'static str| async move {
+                match req {
+                    "ok" => Ok(()),
+                    err => Err(err),
+                }
+            }))
+        });
+
+        let subscriber = TestSubscriber::default();
+        let _guard = tracing::subscriber::set_default(subscriber.clone());
+
+        let svc = service_factory.new_service(()).await.unwrap();
+        let service = TracingService::new(svc, |_: &&str| Some(span!(Level::INFO, "req")))
+            .record_errors(|err: &&str| match *err {
+                "disconnected" => Some(Level::DEBUG),
+                "ignored" => None,
+                _ => Some(Level::ERROR),
+            });
+
+        for req in ["ok", "disconnected", "ignored", "internal"] {
+            let _ = service.call(req).await;
+        }
+
+        let inner = subscriber.inner.read().unwrap();
+        assert_eq!(inner.stats.event_levels, [Level::DEBUG, Level::ERROR]);
+    }
+
+    #[actix_rt::test]
+    async fn factory_span() {
+        let subscriber = TestSubscriber::default();
+        let _guard = tracing::subscriber::set_default(subscriber.clone());
+
+        let factory = trace_factory(
+            fn_factory(|| async {
+                Err::<actix_service::boxed::BoxService<(), (), ()>, _>("no database")
+            }),
+            "db",
+        );
+
+        assert_eq!(factory.new_service(()).await.err(), Some("no database"));
+        let id = 1;
+
+        {
+            let inner = subscriber.inner.read().unwrap();
+            let recorded = &inner.stats.recorded[&id];
+            assert_eq!(recorded[0].0, "elapsed_ms");
+            assert_eq!(recorded[1], ("error", "\"no database\"".to_owned()));
+            assert_eq!(inner.stats.events_count[&id], 1);
+            assert!(inner.stats.entered_spans.contains(&id));
+        }
+
+        // clones share the instance counter
+        let _ = factory.clone().new_service(()).await;
+        assert!(format!("{:?}", factory).contains("instances: 2"));
+    }
 }
@@ -0,0 +1,161 @@
+//! Call latency and error metrics.
+//!
+//! When the `metrics` crate feature is enabled, traced services configured with a metrics name
+//! emit the following through the [`metrics`](https://docs.rs/metrics) facade, each labelled with
+//! `service = <name>`:
+//!
+//! - [`CALL_DURATION`]: histogram of call latency in seconds, from the start of `call` until the
+//!   returned future completes;
+//! - [`CALLS`]: counter of completed calls;
+//! - [`CALL_ERRORS`]: counter of calls that completed with an error.
+//!
+//! Metrics are emitted for every call, including those for which no span was created. Calls whose
+//! future is dropped before completion are not counted.
+
+/// Name of the call latency histogram.
+pub const CALL_DURATION: &str = "actix_service_call_duration_seconds";
+
+/// Name of the completed calls counter.
+pub const CALLS: &str = "actix_service_calls_total";
+
+/// Name of the failed calls counter.
+pub const CALL_ERRORS: &str = "actix_service_call_errors_total";
+
+#[cfg(feature = "metrics")]
+mod imp {
+    use std::time::Instant;
+
+    use super::{CALLS, CALL_DURATION, CALL_ERRORS};
+
+    /// Per-service metrics configuration.
+    #[derive(Debug, Clone, Copy)]
+    pub(crate) struct MetricsConfig {
+        name: Option<&'static str>,
+    }
+
+    impl MetricsConfig {
+        pub(crate) fn disabled() -> Self {
+            Self { name: None }
+        }
+
+        pub(crate) fn new(name: &'static str) -> Self {
+            Self { name: Some(name) }
+        }
+
+        /// Starts measuring a call.
+        pub(crate) fn start(&self) -> CallMetrics {
+            CallMetrics {
+                start: self.name.map(|name| (name, Instant::now())),
+            }
+        }
+    }
+
+    /// Measurement of a single call.
+    #[derive(Debug)]
+    pub(crate) struct CallMetrics {
+        start: Option<(&'static str, Instant)>,
+    }
+
+    impl CallMetrics {
+        /// Emits metrics for the completed call. Subsequent calls have no effect.
+        pub(crate) fn finish(&mut self, is_err: bool) {
+            if let Some((name, start)) = self.start.take() {
+                ::metrics::histogram!(CALL_DURATION, start.elapsed(), "service" => name);
+                ::metrics::increment_counter!(CALLS, "service" => name);
+
+                if is_err {
+                    ::metrics::increment_counter!(CALL_ERRORS, "service" => name);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+mod imp {
+    #[derive(Debug, Clone, Copy)]
+    pub(crate) struct MetricsConfig;
+
+    impl MetricsConfig {
+        pub(crate) fn disabled() -> Self {
+            Self
+        }
+
+        pub(crate) fn start(&self) -> CallMetrics {
+            CallMetrics
+        }
+    }
+
+    #[derive(Debug)]
+    pub(crate) struct CallMetrics;
+
+    impl CallMetrics {
+        pub(crate) fn finish(&mut self, _is_err: bool) {}
+    }
+}
+
+pub(crate) use self::imp::{CallMetrics, MetricsConfig};
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use actix_service::{
+        apply, fn_factory, fn_service, ApplyTransform, Service as _, ServiceFactory,
+    };
+    use actix_utils::future::ok;
+    use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+    use tracing::Span;
+
+    use super::*;
+    use crate::TracingTransform;
+
+    type MakeSpan = fn(&bool) -> Option<Span>;
+
+    fn metered<SF>(
+        factory: SF,
+    ) -> ApplyTransform<TracingTransform<SF::Service, SF, MakeSpan>, SF, bool>
+    where
+        SF: ServiceFactory<bool>,
+    {
+        let transform = TracingTransform::new((|_| None) as MakeSpan);
+        apply(transform.with_metrics("svc"), factory)
+    }
+
+    #[actix_rt::test]
+    async fn emits_call_metrics() {
+        let recorder = DebuggingRecorder::new();
+        let snapshotter = recorder.snapshotter();
+        recorder.install().unwrap();
+
+        let factory = fn_factory(|| {
+            ok::<_, ()>(fn_service(|req: bool| async move {
+                if req {
+                    Ok(())
+                } else {
+                    Err(())
+                }
+            }))
+        });
+        let service = metered(factory).new_service(()).await.unwrap();
+
+        service.call(true).await.unwrap();
+        service.call(false).await.unwrap_err();
+        service.call(true).await.unwrap();
+
+        let snapshot = snapshotter.snapshot().into_vec();
+        let metric = |name: &str| {
+            snapshot
+                .iter()
+                .find(|(key, ..)| key.key().name() == name)
+                .map(|(key, _, _, value)| {
+                    let labels = key.key().labels().map(|l| (l.key(), l.value()));
+                    assert!(labels.eq([("service", "svc")]));
+                    value
+                })
+                .unwrap()
+        };
+
+        assert_eq!(metric(CALLS), &DebugValue::Counter(3));
+        assert_eq!(metric(CALL_ERRORS), &DebugValue::Counter(1));
+        assert!(matches!(metric(CALL_DURATION), DebugValue::Histogram(h) if h.len() == 3));
+    }
+}
@@ -0,0 +1,314 @@
+//! OpenTelemetry trace context propagation.
+//!
+//! Services that receive requests wrap their span closure with [`server`], which continues the
+//! distributed trace described by the request's [W3C Trace Context] headers and marks the span as
+//! a server span. Services that send requests wrap their span closure with [`client`] and their
+//! service factory with [`inject`], which writes the current trace context into each outgoing
+//! request.
+//!
+//! Requests expose their header-like metadata by implementing [`Extractor`] (for incoming
+//! requests) or [`Injector`] (for outgoing requests). Spans are exported to OpenTelemetry by a
+//! [`tracing_opentelemetry`] layer installed in the subscriber; without it, extraction and
+//! injection have no effect.
+//!
+//! Span kinds are recorded in the `otel.kind` field following OpenTelemetry semantic conventions.
+//! Since `tracing` can only record fields that spans declare up front, span closures should
+//! declare `otel.kind = tracing::field::Empty`.
+//!
+//! ```ignore
+//! let factory = trace(
+//!     server_factory,
+//!     otel::server(|req: &Request| {
+//!         Some(span!(Level::INFO, "request", otel.kind = field::Empty))
+//!     }),
+//! );
+//! ```
+//!
+//! [W3C Trace Context]: https://www.w3.org/TR/trace-context/
+
+use core::str::FromStr as _;
+
+use actix_service::{apply_fn_factory, IntoServiceFactory, Service, ServiceFactory};
+pub use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry::{
+    trace::{SpanContext, SpanId, TraceContextExt as _, TraceFlags, TraceId, TraceState},
+    Context,
+};
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt as _;
+
+const TRACEPARENT_HEADER: &str = "traceparent";
+const TRACESTATE_HEADER: &str = "tracestate";
+
+/// Span kinds of OpenTelemetry semantic conventions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SpanKind {
+    /// Span covers handling of a request received from a remote client.
+    Server,
+
+    /// Span covers a request sent to a remote server.
+    Client,
+}
+
+impl SpanKind {
+    /// Returns the value of the `otel.kind` field for this kind.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SpanKind::Server => "server",
+            SpanKind::Client => "client",
+        }
+    }
+
+    /// Records this kind in the `otel.kind` field of `span`.
+    ///
+    /// Has no effect if the span does not declare the field.
+    pub fn record(&self, span: &Span) {
+        span.record("otel.kind", self.as_str());
+    }
+}
+
+/// Wraps a span closure so that spans continue the trace propagated in the request's headers and
+/// are marked as server spans.
+///
+/// Requests without valid trace context headers start a new trace.
+pub fn server<Req, F>(make_span: F) -> impl Fn(&Req) -> Option<Span> + Clone
+where
+    Req: Extractor,
+    F: Fn(&Req) -> Option<Span> + Clone,
+{
+    move |req: &Req| {
+        let span = make_span(req)?;
+
+        let cx = extract_context(req);
+        if cx.has_active_span() {
+            span.set_parent(cx);
+        }
+
+        SpanKind::Server.record(&span);
+        Some(span)
+    }
+}
+
+/// Wraps a span closure so that spans are marked as client spans.
+///
+/// Combine with [`inject`] to propagate the span's trace context in outgoing requests.
+pub fn client<Req, F>(make_span: F) -> impl Fn(&Req) -> Option<Span> + Clone
+where
+    F: Fn(&Req) -> Option<Span> + Clone,
+{
+    move |req: &Req| {
+        let span = make_span(req)?;
+        SpanKind::Client.record(&span);
+        Some(span)
+    }
+}
+
+/// Wraps a service factory so that trace context of the current span is injected into each
+/// request before it is passed to the service.
+///
+/// When wrapped by [`trace`](crate::trace), the current span is the one created for the request.
+pub fn inject<I, SF, Req>(
+    service_factory: I,
+) -> impl ServiceFactory<
+    Req,
+    Response = SF::Response,
+    Error = SF::Error,
+    Config = SF::Config,
+    InitError = SF::InitError,
+>
+where
+    I: IntoServiceFactory<SF, Req>,
+    SF: ServiceFactory<Req>,
+    Req: Injector,
+{
+    apply_fn_factory(service_factory, |mut req: Req, svc: &SF::Service| {
+        inject_context(&Span::current().context(), &mut req);
+        svc.call(req)
+    })
+}
+
+/// Extracts remote trace context from W3C `traceparent` and `tracestate` headers.
+///
+/// Returns an empty context if the headers are missing or invalid.
+pub fn extract_context(carrier: &dyn Extractor) -> Context {
+    match carrier
+        .get(TRACEPARENT_HEADER)
+        .and_then(|traceparent| parse_traceparent(traceparent.trim()))
+    {
+        Some((trace_id, span_id, flags)) => {
+            let state = carrier
+                .get(TRACESTATE_HEADER)
+                .and_then(|state| TraceState::from_str(state.trim()).ok())
+                .unwrap_or_default();
+
+            let span_context = SpanContext::new(trace_id, span_id, flags, true, state);
+            Context::new().with_remote_span_context(span_context)
+        }
+
+        None => Context::new(),
+    }
+}
+
+/// Injects trace context into W3C `traceparent` and `tracestate` headers.
+///
+/// Nothing is injected if the context has no valid span.
+pub fn inject_context(cx: &Context, carrier: &mut dyn Injector) {
+    let span = cx.span();
+    let span_context = span.span_context();
+
+    if !span_context.is_valid() {
+        return;
+    }
+
+    let traceparent = format!(
+        "00-{}-{}-{:02x}",
+        span_context.trace_id(),
+        span_context.span_id(),
+        span_context.trace_flags().to_u8() & TraceFlags::SAMPLED.to_u8(),
+    );
+    carrier.set(TRACEPARENT_HEADER, traceparent);
+
+    let state = span_context.trace_state().header();
+    if !state.is_empty() {
+        carrier.set(TRACESTATE_HEADER, state);
+    }
+}
+
+/// Parses a `traceparent` header value of the form `version-trace_id-parent_id-flags`.
+fn parse_traceparent(value: &str) -> Option<(TraceId, SpanId, TraceFlags)> {
+    let mut parts = value.split('-');
+
+    let version = parse_hex_field(parts.next()?, 2)?;
+    let trace_id = parts.next()?;
+    let span_id = parts.next()?;
+    let flags = parse_hex_field(parts.next()?, 2)?;
+
+    // version 0xff is invalid; version 0 has exactly four fields while later versions may add more
+    if version == 0xff || (version == 0 && parts.next().is_some()) {
+        return None;
+    }
+
+    let trace_id = TraceId::from_hex(check_hex(trace_id, 32)?).ok()?;
+    let span_id = SpanId::from_hex(check_hex(span_id, 16)?).ok()?;
+
+    if trace_id == TraceId::INVALID || span_id == SpanId::INVALID {
+        return None;
+    }
+
+    let flags = TraceFlags::new(flags as u8) & TraceFlags::SAMPLED;
+    Some((trace_id, span_id, flags))
+}
+
+/// Returns `field` if it consists of exactly `len` lowercase hex digits.
+fn check_hex(field: &str, len: usize) -> Option<&str> {
+    let valid = field.len() == len
+        && field
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b));
+
+    valid.then_some(field)
+}
+
+fn parse_hex_field(field: &str, len: usize) -> Option<u32> {
+    u32::from_str_radix(check_hex(field, len)?, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    const TRACE_ID: &str = "4bf92f3577b34da6a3ce929d0e0e4736";
+    const SPAN_ID: &str = "00f067aa0ba902b7";
+
+    fn headers(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn round_trip() {
+        let incoming = headers(&[
+            ("traceparent", &format!("00-{TRACE_ID}-{SPAN_ID}-01")),
+            ("tracestate", "vendor=value"),
+        ]);
+
+        let cx = extract_context(&incoming);
+        let span = cx.span();
+        let span_context = span.span_context();
+        assert!(span_context.is_remote());
+        assert!(span_context.is_sampled());
+        assert_eq!(span_context.trace_id().to_string(), TRACE_ID);
+        assert_eq!(span_context.span_id().to_string(), SPAN_ID);
+
+        let mut outgoing = HashMap::new();
+        inject_context(&cx, &mut outgoing);
+        assert_eq!(outgoing, incoming);
+    }
+
+    #[test]
+    fn invalid_traceparent_ignored() {
+        let invalid = [
+            format!("ff-{TRACE_ID}-{SPAN_ID}-01"),
+            format!("00-{TRACE_ID}-{SPAN_ID}-01-extra"),
+            format!("00-{}-{SPAN_ID}-01", "0".repeat(32)),
+            format!("00-{TRACE_ID}-{}-01", "0".repeat(16)),
+            format!("00-{}-{SPAN_ID}-01", TRACE_ID.to_uppercase()),
+            format!("00-{TRACE_ID}-{SPAN_ID}"),
+            "garbage".to_owned(),
+        ];
+
+        for traceparent in &invalid {
+            let cx = extract_context(&headers(&[("traceparent", traceparent)]));
+            assert!(!cx.has_active_span(), "accepted {traceparent}");
+        }
+
+        // future versions may append fields
+        let cx = extract_context(&headers(&[(
+            "traceparent",
+            &format!("01-{TRACE_ID}-{SPAN_ID}-00-extra"),
+        )]));
+        assert!(cx.has_active_span());
+        assert!(!cx.span().span_context().is_sampled());
+    }
+
+    #[test]
+    fn nothing_injected_without_span() {
+        let mut outgoing = HashMap::new();
+        inject_context(&Context::new(), &mut outgoing);
+        assert!(outgoing.is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn compose_with_trace() {
+        use actix_service::{fn_service, ServiceFactory as _};
+        use actix_utils::future::ok;
+        use tracing::{field, info_span};
+
+        let client = crate::trace(
+            inject(fn_service(|req: HashMap<String, String>| ok::<_, ()>(req))),
+            client(|_: &HashMap<String, String>| {
+                Some(info_span!("client", otel.kind = field::Empty))
+            }),
+        );
+        let client = client.new_service(()).await.unwrap();
+
+        // no OpenTelemetry layer is installed, so there is no context to inject
+        assert!(client.call(HashMap::new()).await.unwrap().is_empty());
+
+        let server = crate::trace(
+            fn_service(|_: HashMap<String, String>| ok::<_, ()>(())),
+            server(|_: &HashMap<String, String>| {
+                Some(info_span!("server", otel.kind = field::Empty))
+            }),
+        );
+        let server = server.new_service(()).await.unwrap();
+
+        let req = headers(&[("traceparent", &format!("00-{TRACE_ID}-{SPAN_ID}-01"))]);
+        server.call(req).await.unwrap();
+    }
+}
@@ -0,0 +1,91 @@
+//! Per-service span level and sampling.
+
+use std::{
+    cell::Cell,
+    collections::hash_map::RandomState,
+    hash::{BuildHasher as _, Hasher as _},
+};
+
+use tracing::{level_filters::LevelFilter, Level, Span};
+
+/// Decides which calls of a traced service get a span.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Sampling {
+    level: Level,
+    /// Calls are sampled when a random number falls below this; `None` samples every call.
+    threshold: Option<u64>,
+}
+
+impl Sampling {
+    pub(crate) fn new() -> Self {
+        Self {
+            level: Level::INFO,
+            threshold: None,
+        }
+    }
+
+    pub(crate) fn level(&self) -> Level {
+        self.level
+    }
+
+    pub(crate) fn set_level(&mut self, level: Level) {
+        self.level = level;
+    }
+
+    /// # Panics
+    /// Panics if `rate` is not within `0.0..=1.0`.
+    pub(crate) fn set_rate(&mut self, rate: f64) {
+        assert!(
+            (0.0..=1.0).contains(&rate),
+            "sample rate must be between 0 and 1"
+        );
+
+        self.threshold = if rate >= 1.0 {
+            None
+        } else {
+            Some((rate * u64::MAX as f64) as u64)
+        };
+    }
+
+    /// Returns true if a span should be created for the next call, traced at `level`.
+    ///
+    /// Calls are skipped if the level is more verbose than any subscriber enables or if they are
+    /// not picked by the sample rate.
+    pub(crate) fn should_trace(&self, level: Level) -> bool {
+        if level > LevelFilter::current() {
+            return false;
+        }
+
+        match self.threshold {
+            None => true,
+            Some(threshold) => next_random() < threshold,
+        }
+    }
+}
+
+/// Creates the span tracking a call's future, as a child of the call's span.
+pub(crate) fn future_span(level: Level, parent: &Span) -> Span {
+    match level {
+        Level::ERROR => tracing::error_span!(parent: parent, "future"),
+        Level::WARN => tracing::warn_span!(parent: parent, "future"),
+        Level::INFO => tracing::info_span!(parent: parent, "future"),
+        Level::DEBUG => tracing::debug_span!(parent: parent, "future"),
+        Level::TRACE => tracing::trace_span!(parent: parent, "future"),
+    }
+}
+
+/// Returns a pseudo-random number from a per-thread xorshift64* generator.
+fn next_random() -> u64 {
+    thread_local! {
+        static STATE: Cell<u64> = Cell::new(RandomState::new().build_hasher().finish() | 1);
+    }
+
+    STATE.with(|state| {
+        let mut x = state.get();
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        state.set(x);
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    })
+}
@@ -0,0 +1,117 @@
+use std::cell::Cell;
+
+use actix_service::{fn_service, Service as _, ServiceFactory as _};
+use actix_tracing::{TracingHandle, TracingService};
+use actix_utils::future::ok;
+use tracing::{level_filters::LevelFilter, span, Event, Level, Metadata, Span, Subscriber};
+
+/// Subscriber that enables spans up to a maximum level.
+struct MaxLevel(LevelFilter);
+
+impl Subscriber for MaxLevel {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= self.0
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(self.0)
+    }
+
+    fn new_span(&self, _span: &span::Attributes<'_>) -> span::Id {
+        span::Id::from_u64(1)
+    }
+
+    fn record(&self, _span: &span::Id, _values: &span::Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &span::Id, _follows: &span::Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &span::Id) {}
+
+    fn exit(&self, _span: &span::Id) {}
+}
+
+#[actix_rt::test]
+async fn level_and_sampling() {
+    let _guard = tracing::subscriber::set_default(MaxLevel(LevelFilter::INFO));
+
+    let spans = Cell::new(0);
+    let make_span = |_: &()| {
+        spans.set(spans.get() + 1);
+        Some(Span::none())
+    };
+    let inner = || fn_service(|_: ()| ok::<_, ()>(()));
+
+    // TRACE is more verbose than the installed subscriber enables
+    let service =
+        TracingService::new(inner().new_service(()).await.unwrap(), make_span).level(Level::TRACE);
+    service.call(()).await.unwrap();
+    assert_eq!(spans.get(), 0);
+
+    let service =
+        TracingService::new(inner().new_service(()).await.unwrap(), make_span).level(Level::WARN);
+    service.call(()).await.unwrap();
+    assert_eq!(spans.get(), 1);
+
+    let service =
+        TracingService::new(inner().new_service(()).await.unwrap(), make_span).sample_rate(0.0);
+    for _ in 0..100 {
+        service.call(()).await.unwrap();
+    }
+    assert_eq!(spans.get(), 1);
+
+    spans.set(0);
+    let service =
+        TracingService::new(inner().new_service(()).await.unwrap(), make_span).sample_rate(0.25);
+    for _ in 0..4000 {
+        service.call(()).await.unwrap();
+    }
+    assert!(
+        (800..1200).contains(&spans.get()),
+        "sampled {} calls",
+        spans.get()
+    );
+}
+
+#[actix_rt::test]
+async fn handle_toggles_tracing() {
+    let _guard = tracing::subscriber::set_default(MaxLevel(LevelFilter::INFO));
+
+    let spans = Cell::new(0);
+    let make_span = |_: &()| {
+        spans.set(spans.get() + 1);
+        Some(Span::none())
+    };
+    let inner = fn_service(|_: ()| ok::<_, ()>(()))
+        .new_service(())
+        .await
+        .unwrap();
+
+    let handle = TracingHandle::new();
+    let service = TracingService::new(inner, make_span)
+        .level(Level::TRACE)
+        .with_handle(handle.clone());
+
+    // service's own level is not enabled
+    service.call(()).await.unwrap();
+    assert_eq!(spans.get(), 0);
+
+    handle.set_level(Some(Level::INFO));
+    service.call(()).await.unwrap();
+    assert_eq!(spans.get(), 1);
+
+    handle.disable();
+    service.call(()).await.unwrap();
+    assert_eq!(spans.get(), 1);
+
+    handle.enable();
+    service.call(()).await.unwrap();
+    assert_eq!(spans.get(), 2);
+}
+
+#[test]
+#[should_panic]
+fn invalid_sample_rate() {
+    let _ = TracingService::new((), |_: &()| None::<Span>).sample_rate(1.5);
+}
@@ -0,0 +1,44 @@
+use std::{sync::mpsc, time::Duration};
+
+use actix_rt::net::TcpStream;
+use actix_server::TestServer;
+use actix_service::fn_service;
+use actix_tracing::trace;
+use actix_utils::future::ok;
+use tracing::Span;
+use tracing_subscriber::{registry::LookupSpan as _, Registry};
+
+/// Returns the names of the current span and its ancestors, innermost first.
+fn current_scope() -> Vec<&'static str> {
+    let id = Span::current().id().unwrap();
+
+    tracing::dispatcher::get_default(|dispatch| {
+        let registry = dispatch.downcast_ref::<Registry>().unwrap();
+        let span = registry.span(&id).unwrap();
+        span.scope().map(|span| span.name()).collect()
+    })
+}
+
+#[test]
+fn connection_spans_nest_under_worker() {
+    tracing::subscriber::set_global_default(Registry::default()).unwrap();
+
+    let (tx, rx) = mpsc::channel();
+
+    let srv = TestServer::start(move || {
+        let tx = tx.clone();
+
+        trace(
+            fn_service(move |_: TcpStream| {
+                tx.send(current_scope()).unwrap();
+                ok::<_, ()>(())
+            }),
+            |_: &TcpStream| Some(tracing::info_span!("request")),
+        )
+    });
+
+    std::net::TcpStream::connect(srv.addr()).unwrap();
+
+    let scope = rx.recv_timeout(Duration::from_secs(5)).unwrap();
+    assert_eq!(scope, ["request", "connection", "worker"]);
+}
@@ -0,0 +1,437 @@
+//! Exponential backoff with jitter.
+//!
+//! See [`ExponentialBackoff`] for configuration and [`retry`] for a ready-made retry loop.
+
+use core::{fmt, future::Future, time::Duration};
+use std::{
+    collections::hash_map::RandomState,
+    hash::{BuildHasher as _, Hasher as _},
+};
+
+use actix_rt::time::{sleep, Sleep};
+
+use crate::clock::{self, Clock, RuntimeClock};
+
+/// Strategy for randomizing backoff delays.
+///
+/// Randomization spreads out retries from many clients that failed at the same time, avoiding
+/// synchronized bursts of load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum Jitter {
+    /// Use the exponential delay as-is.
+    None,
+
+    /// Pick a delay uniformly between zero and the exponential delay.
+    #[default]
+    Full,
+
+    /// Pick a delay uniformly between half the exponential delay and the full delay.
+    Equal,
+
+    /// Pick a delay uniformly between the base delay and three times the previous delay.
+    ///
+    /// Delays grow based on the previous (randomized) delay rather than the attempt number, so the
+    /// factor setting is not used.
+    Decorrelated,
+}
+
+/// Iterator of exponentially increasing delays between retry attempts.
+///
+/// The undithered delay for attempt `n` (starting at zero) is `base * factor^n`, limited to the
+/// maximum delay, after which the [`Jitter`] strategy is applied. By default the factor is 2, the
+/// maximum delay is one minute, full jitter is used, and the number of retries is unlimited.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use actix_utils::backoff::{ExponentialBackoff, Jitter};
+///
+/// let delays = ExponentialBackoff::new(Duration::from_millis(100))
+///     .max_delay(Duration::from_millis(500))
+///     .max_retries(5)
+///     .jitter(Jitter::None)
+///     .collect::<Vec<_>>();
+///
+/// assert_eq!(
+///     delays,
+///     [100, 200, 400, 500, 500].map(Duration::from_millis),
+/// );
+/// ```
+#[derive(Clone)]
+pub struct ExponentialBackoff {
+    base: Duration,
+    factor: f64,
+    max_delay: Duration,
+    max_retries: Option<usize>,
+    jitter: Jitter,
+    attempt: usize,
+    prev: Duration,
+    rng: XorShift,
+}
+
+impl ExponentialBackoff {
+    /// Constructs new backoff starting from `base` delay.
+    pub fn new(base: Duration) -> Self {
+        Self {
+            base,
+            factor: 2.0,
+            max_delay: Duration::from_secs(60),
+            max_retries: None,
+            jitter: Jitter::default(),
+            attempt: 0,
+            prev: base,
+            rng: XorShift::from_entropy(),
+        }
+    }
+
+    /// Sets multiplier applied to the delay after each attempt.
+    ///
+    /// # Panics
+    /// Panics if `factor` is not a finite number greater than or equal to 1.
+    pub fn factor(mut self, factor: f64) -> Self {
+        assert!(
+            factor.is_finite() && factor >= 1.0,
+            "backoff factor must be finite and at least 1"
+        );
+        self.factor = factor;
+        self
+    }
+
+    /// Sets upper limit of any single delay.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    /// Sets maximum number of delays yielded, after which the iterator is exhausted.
+    pub fn max_retries(mut self, max_retries: usize) -> Self {
+        self.max_retries = Some(max_retries);
+        self
+    }
+
+    /// Sets jitter strategy.
+    pub fn jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Seeds the random number generator used for jitter, making delays reproducible.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = XorShift::new(seed);
+        self
+    }
+
+    /// Returns the number of delays yielded so far.
+    pub fn attempt(&self) -> usize {
+        self.attempt
+    }
+
+    /// Starts the backoff sequence over, for example after a successful attempt.
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+        self.prev = self.base;
+    }
+
+    /// Returns a timer for the next delay, or `None` if retries are exhausted.
+    pub fn next_sleep(&mut self) -> Option<Sleep> {
+        self.next().map(sleep)
+    }
+
+    /// Returns a timer for the next delay as measured by `clock`, or `None` if retries are
+    /// exhausted.
+    pub fn next_sleep_with<C: Clock + ?Sized>(&mut self, clock: &C) -> Option<clock::Sleep> {
+        self.next().map(|delay| clock.sleep(delay))
+    }
+
+    /// Computes undithered delay for current attempt.
+    fn exponential_delay(&self) -> Duration {
+        let exp = i32::try_from(self.attempt).unwrap_or(i32::MAX);
+        let secs = self.base.as_secs_f64() * self.factor.powi(exp);
+
+        if secs.is_finite() && secs < self.max_delay.as_secs_f64() {
+            Duration::from_secs_f64(secs)
+        } else {
+            self.max_delay
+        }
+    }
+
+    /// Picks a duration uniformly from `low..=high`.
+    fn between(&mut self, low: Duration, high: Duration) -> Duration {
+        if high <= low {
+            return low;
+        }
+
+        low + (high - low).mul_f64(self.rng.next_f64())
+    }
+}
+
+impl Iterator for ExponentialBackoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if matches!(self.max_retries, Some(max) if self.attempt >= max) {
+            return None;
+        }
+
+        let delay = match self.jitter {
+            Jitter::None => self.exponential_delay(),
+            Jitter::Full => self.between(Duration::ZERO, self.exponential_delay()),
+            Jitter::Equal => {
+                let delay = self.exponential_delay();
+                self.between(delay / 2, delay)
+            }
+            Jitter::Decorrelated => {
+                let high = self.prev.saturating_mul(3);
+                self.between(self.base, high).min(self.max_delay)
+            }
+        };
+
+        self.attempt += 1;
+        self.prev = delay;
+
+        Some(delay)
+    }
+}
+
+impl fmt::Debug for ExponentialBackoff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ExponentialBackoff")
+            .field("base", &self.base)
+            .field("factor", &self.factor)
+            .field("max_delay", &self.max_delay)
+            .field("max_retries", &self.max_retries)
+            .field("jitter", &self.jitter)
+            .field("attempt", &self.attempt)
+            .finish()
+    }
+}
+
+/// Calls `f` until its future succeeds, sleeping for backoff delays in between failed attempts.
+///
+/// Resolves to the error of the last attempt once `backoff` is exhausted.
+///
+/// # Examples
+/// ```
+/// use std::{cell::Cell, time::Duration};
+///
+/// use actix_utils::backoff::{retry, ExponentialBackoff};
+///
+/// # actix_rt::System::new().block_on(async {
+/// let attempts = Cell::new(0);
+///
+/// let backoff = ExponentialBackoff::new(Duration::from_millis(1)).max_retries(5);
+/// let res = retry(backoff, || async {
+///     attempts.set(attempts.get() + 1);
+///
+///     if attempts.get() < 3 {
+///         Err("not yet")
+///     } else {
+///         Ok(attempts.get())
+///     }
+/// })
+/// .await;
+///
+/// assert_eq!(res, Ok(3));
+/// # });
+/// ```
+pub async fn retry<F, Fut, T, E>(backoff: ExponentialBackoff, f: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    retry_with(RuntimeClock, backoff, f).await
+}
+
+/// Calls `f` until its future succeeds, sleeping for backoff delays as measured by `clock` in
+/// between failed attempts.
+///
+/// See [`retry`] for details.
+pub async fn retry_with<C, F, Fut, T, E>(
+    clock: C,
+    mut backoff: ExponentialBackoff,
+    mut f: F,
+) -> Result<T, E>
+where
+    C: Clock,
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    loop {
+        match f().await {
+            Ok(res) => return Ok(res),
+            Err(err) => match backoff.next_sleep_with(&clock) {
+                Some(delay) => delay.await,
+                None => return Err(err),
+            },
+        }
+    }
+}
+
+/// Small non-cryptographic PRNG (xorshift64*), sufficient for jitter.
+#[derive(Clone)]
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // state must never be zero
+        Self(seed | 1)
+    }
+
+    fn from_entropy() -> Self {
+        Self::new(RandomState::new().build_hasher().finish())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.0 = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Returns a number uniformly distributed in `0.0..=1.0`.
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / ((1u64 << 53) - 1) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, rc::Rc};
+
+    use super::*;
+    use crate::clock::MockClock;
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[test]
+    fn exponential_without_jitter() {
+        let delays = ExponentialBackoff::new(ms(10))
+            .factor(3.0)
+            .max_delay(ms(200))
+            .jitter(Jitter::None)
+            .take(5)
+            .collect::<Vec<_>>();
+
+        assert_eq!(delays, [ms(10), ms(30), ms(90), ms(200), ms(200)]);
+    }
+
+    #[test]
+    fn max_retries_and_reset() {
+        let mut backoff = ExponentialBackoff::new(ms(1))
+            .max_retries(2)
+            .jitter(Jitter::None);
+
+        assert_eq!(backoff.next(), Some(ms(1)));
+        assert_eq!(backoff.next(), Some(ms(2)));
+        assert_eq!(backoff.next(), None);
+        assert_eq!(backoff.attempt(), 2);
+
+        backoff.reset();
+        assert_eq!(backoff.next(), Some(ms(1)));
+    }
+
+    #[test]
+    fn huge_attempt_count_is_capped() {
+        let mut backoff = ExponentialBackoff::new(ms(1))
+            .max_delay(ms(50))
+            .jitter(Jitter::None);
+
+        let last = backoff.by_ref().take(2000).last().unwrap();
+        assert_eq!(last, ms(50));
+    }
+
+    #[test]
+    fn jitter_bounds() {
+        for seed in 0..50 {
+            let capped = |n: u32| ms(10 * 2u64.pow(n)).min(ms(100));
+
+            let full = ExponentialBackoff::new(ms(10))
+                .max_delay(ms(100))
+                .seed(seed)
+                .take(6);
+            for (n, delay) in full.enumerate() {
+                assert!(delay <= capped(n as u32));
+            }
+
+            let equal = ExponentialBackoff::new(ms(10))
+                .max_delay(ms(100))
+                .jitter(Jitter::Equal)
+                .seed(seed)
+                .take(6);
+            for (n, delay) in equal.enumerate() {
+                assert!(delay >= capped(n as u32) / 2 && delay <= capped(n as u32));
+            }
+
+            let mut prev = ms(10);
+            let decorrelated = ExponentialBackoff::new(ms(10))
+                .max_delay(ms(100))
+                .jitter(Jitter::Decorrelated)
+                .seed(seed)
+                .take(6);
+            for delay in decorrelated {
+                assert!(delay >= ms(10) && delay <= (prev * 3).min(ms(100)));
+                prev = delay;
+            }
+        }
+    }
+
+    #[test]
+    fn seeded_is_reproducible() {
+        let a = ExponentialBackoff::new(ms(10)).seed(42).take(5);
+        let b = ExponentialBackoff::new(ms(10)).seed(42).take(5);
+        assert!(a.eq(b));
+    }
+
+    #[test]
+    #[should_panic]
+    fn factor_below_one_panics() {
+        let _ = ExponentialBackoff::new(ms(1)).factor(0.5);
+    }
+
+    #[actix_rt::test]
+    async fn retry_gives_up() {
+        let attempts = Cell::new(0);
+
+        let backoff = ExponentialBackoff::new(ms(1)).max_retries(2);
+        let res = retry(backoff, || async {
+            attempts.set(attempts.get() + 1);
+            Err::<(), _>(attempts.get())
+        })
+        .await;
+
+        // one initial attempt plus two retries
+        assert_eq!(res, Err(3));
+    }
+
+    #[actix_rt::test]
+    async fn retry_with_mock_clock() {
+        let clock = MockClock::new();
+        let attempts = Rc::new(Cell::new(0));
+
+        let backoff = ExponentialBackoff::new(Duration::from_secs(60))
+            .jitter(Jitter::None)
+            .max_retries(1);
+        let handle = actix_rt::spawn({
+            let attempts = Rc::clone(&attempts);
+
+            retry_with(clock.clone(), backoff, move || {
+                attempts.set(attempts.get() + 1);
+                async { Err::<(), ()>(()) }
+            })
+        });
+
+        actix_rt::task::yield_now().await;
+        assert_eq!(attempts.get(), 1);
+        assert_eq!(clock.pending_timers(), 1);
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(handle.await.unwrap(), Err(()));
+        assert_eq!(attempts.get(), 2);
+    }
+}
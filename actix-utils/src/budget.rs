@@ -0,0 +1,238 @@
+//! Per-connection memory budget accounting.
+//!
+//! A [`MemoryBudget`] caps the number of bytes that buffers belonging to one connection, such as
+//! framed transport and TLS buffers, may hold at once. Each buffer owner holds a [`Reservation`]
+//! against the budget and resizes it as its buffers grow or shrink; growing beyond the cap fails
+//! with [`BudgetExceeded`], giving servers a systematic defense against peers that try to make
+//! them buffer excessive amounts of data.
+
+use core::{
+    fmt,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use std::{error::Error, io, sync::Arc};
+
+/// Memory budget shared by the buffers of one connection.
+///
+/// The budget can be cloned cheaply, with all clones sharing the same cap and usage. It is
+/// thread-safe so that types holding reservations stay `Send`.
+///
+/// # Examples
+/// ```
+/// use actix_utils::budget::MemoryBudget;
+///
+/// let budget = MemoryBudget::new(1024);
+///
+/// let mut res = budget.reserve(512).unwrap();
+/// assert_eq!(budget.remaining(), 512);
+/// assert!(budget.reserve(1024).is_err());
+///
+/// res.resize(1024).unwrap();
+/// assert_eq!(budget.remaining(), 0);
+///
+/// drop(res);
+/// assert_eq!(budget.used(), 0);
+/// ```
+#[derive(Clone)]
+pub struct MemoryBudget(Arc<Inner>);
+
+struct Inner {
+    cap: usize,
+    used: AtomicUsize,
+}
+
+impl MemoryBudget {
+    /// Constructs new budget allowing up to `cap` bytes to be reserved at once.
+    pub fn new(cap: usize) -> Self {
+        Self(Arc::new(Inner {
+            cap,
+            used: AtomicUsize::new(0),
+        }))
+    }
+
+    /// Returns the maximum number of bytes that can be reserved at once.
+    pub fn cap(&self) -> usize {
+        self.0.cap
+    }
+
+    /// Returns the number of currently reserved bytes.
+    pub fn used(&self) -> usize {
+        self.0.used.load(Ordering::Acquire)
+    }
+
+    /// Returns the number of bytes that can still be reserved.
+    pub fn remaining(&self) -> usize {
+        self.cap().saturating_sub(self.used())
+    }
+
+    /// Reserves `size` bytes, returning a reservation that releases them when dropped.
+    ///
+    /// Returns an error, without reserving anything, if the reservation would exceed the cap.
+    pub fn reserve(&self, size: usize) -> Result<Reservation, BudgetExceeded> {
+        self.acquire(size)?;
+
+        Ok(Reservation {
+            budget: self.clone(),
+            size,
+        })
+    }
+
+    fn acquire(&self, size: usize) -> Result<(), BudgetExceeded> {
+        let cap = self.0.cap;
+
+        self.0
+            .used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                used.checked_add(size).filter(|&total| total <= cap)
+            })
+            .map(|_| ())
+            .map_err(|used| BudgetExceeded {
+                requested: size,
+                used,
+                cap,
+            })
+    }
+
+    fn release(&self, size: usize) {
+        self.0.used.fetch_sub(size, Ordering::AcqRel);
+    }
+}
+
+impl fmt::Debug for MemoryBudget {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemoryBudget")
+            .field("cap", &self.cap())
+            .field("used", &self.used())
+            .finish()
+    }
+}
+
+/// Bytes reserved against a [`MemoryBudget`], released when dropped.
+pub struct Reservation {
+    budget: MemoryBudget,
+    size: usize,
+}
+
+impl Reservation {
+    /// Returns the number of reserved bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the budget the bytes are reserved against.
+    pub fn budget(&self) -> &MemoryBudget {
+        &self.budget
+    }
+
+    /// Grows or shrinks the reservation to `size` bytes.
+    ///
+    /// Returns an error, leaving the reservation unchanged, if growing it would exceed the cap.
+    pub fn resize(&mut self, size: usize) -> Result<(), BudgetExceeded> {
+        if size > self.size {
+            self.budget.acquire(size - self.size)?;
+        } else {
+            self.budget.release(self.size - size);
+        }
+
+        self.size = size;
+        Ok(())
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.budget.release(self.size);
+    }
+}
+
+impl fmt::Debug for Reservation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Reservation")
+            .field("size", &self.size)
+            .field("budget", &self.budget)
+            .finish()
+    }
+}
+
+/// Error returned when a reservation would exceed the cap of its [`MemoryBudget`].
+///
+/// Converts into an [`io::Error`] of kind [`Other`](io::ErrorKind::Other) wrapping this error,
+/// which can be recovered using [`io::Error::get_ref`] and downcasting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct BudgetExceeded {
+    /// Number of bytes that were requested.
+    pub requested: usize,
+
+    /// Number of bytes that were already reserved.
+    pub used: usize,
+
+    /// Cap of the budget.
+    pub cap: usize,
+}
+
+impl fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "memory budget exceeded: requested {} bytes with {} of {} bytes in use",
+            self.requested, self.used, self.cap
+        )
+    }
+}
+
+impl Error for BudgetExceeded {}
+
+impl From<BudgetExceeded> for io::Error {
+    fn from(err: BudgetExceeded) -> Self {
+        io::Error::new(io::ErrorKind::Other, err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static_assertions::assert_impl_all!(MemoryBudget: Send, Sync, Clone);
+    static_assertions::assert_impl_all!(Reservation: Send, Sync);
+
+    #[test]
+    fn reservations_share_cap() {
+        let budget = MemoryBudget::new(100);
+
+        let mut a = budget.reserve(60).unwrap();
+        let err = budget.reserve(50).unwrap_err();
+        assert_eq!(
+            err,
+            BudgetExceeded {
+                requested: 50,
+                used: 60,
+                cap: 100
+            }
+        );
+
+        // failed growth leaves reservation unchanged
+        let b = budget.reserve(40).unwrap();
+        assert!(a.resize(61).is_err());
+        assert_eq!(a.size(), 60);
+
+        drop(b);
+        a.resize(100).unwrap();
+        assert_eq!(budget.remaining(), 0);
+
+        a.resize(10).unwrap();
+        assert_eq!(budget.used(), 10);
+
+        drop(a);
+        assert_eq!(budget.used(), 0);
+    }
+
+    #[test]
+    fn converts_to_io_error() {
+        let err = MemoryBudget::new(0).reserve(1).unwrap_err();
+        let io_err = io::Error::from(err);
+
+        let inner = io_err.get_ref().unwrap().downcast_ref::<BudgetExceeded>();
+        assert_eq!(inner, Some(&err));
+    }
+}
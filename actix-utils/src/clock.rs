@@ -0,0 +1,358 @@
+//! Pluggable source of time for timers.
+//!
+//! Timing utilities such as [`timeout_with`](crate::future::timeout_with),
+//! [`Deadline::with_clock`](crate::deadline::Deadline::with_clock), and
+//! [`retry_with`](crate::backoff::retry_with) read the current time and create timers through a
+//! [`Clock`]. By default they use [`RuntimeClock`], which is backed by the Actix (Tokio) runtime's
+//! timer. Tests can use a [`MockClock`] instead, which only moves forward when advanced manually
+//! and, unlike pausing Tokio's time, does not affect any other timers on the runtime.
+
+use core::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use actix_rt::time::{sleep_until as rt_sleep_until, Instant, Sleep as RtSleep};
+use pin_project_lite::pin_project;
+
+/// Source of the current time and of timers completing at a given instant.
+///
+/// Clocks are shared between threads, so implementations must be `Send` and `Sync`.
+pub trait Clock: Send + Sync {
+    /// Returns the current time.
+    fn now(&self) -> Instant;
+
+    /// Returns a timer that completes once the clock reaches `deadline`.
+    fn sleep_until(&self, deadline: Instant) -> Sleep;
+
+    /// Returns a timer that completes once `dur` has elapsed on the clock.
+    fn sleep(&self, dur: Duration) -> Sleep {
+        self.sleep_until(self.now() + dur)
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Sleep {
+        (**self).sleep_until(deadline)
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn now(&self) -> Instant {
+        (**self).now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Sleep {
+        (**self).sleep_until(deadline)
+    }
+}
+
+/// Clock backed by the Actix (Tokio) runtime's timer.
+///
+/// Follows the runtime's notion of time, including when it is paused or advanced in tests.
+///
+/// # Panics
+/// Creating timers panics if called outside of an Actix (Tokio) runtime with time enabled.
+#[derive(Debug, Clone, Copy, Default)]
+#[non_exhaustive]
+pub struct RuntimeClock;
+
+impl RuntimeClock {
+    /// Constructs new runtime clock.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Clock for RuntimeClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Sleep {
+        Sleep {
+            deadline,
+            inner: SleepInner::Runtime {
+                sleep: rt_sleep_until(deadline),
+            },
+        }
+    }
+}
+
+pin_project! {
+    /// Timer created by a [`Clock`].
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Sleep {
+        deadline: Instant,
+        #[pin]
+        inner: SleepInner,
+    }
+}
+
+pin_project! {
+    #[project = SleepProj]
+    enum SleepInner {
+        Runtime {
+            #[pin]
+            sleep: RtSleep,
+        },
+        Mock {
+            sleep: MockSleep,
+        },
+        Custom {
+            fut: Pin<Box<dyn Future<Output = ()> + Send>>,
+        },
+    }
+}
+
+impl Sleep {
+    /// Constructs timer for a custom clock from a future that completes at `deadline`.
+    pub fn from_future<F>(deadline: Instant, fut: F) -> Self
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        Self {
+            deadline,
+            inner: SleepInner::Custom { fut: Box::pin(fut) },
+        }
+    }
+
+    /// Returns the instant at which the timer completes.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().inner.project() {
+            SleepProj::Runtime { sleep } => sleep.poll(cx),
+            SleepProj::Mock { sleep } => sleep.poll(cx),
+            SleepProj::Custom { fut } => fut.as_mut().poll(cx),
+        }
+    }
+}
+
+impl fmt::Debug for Sleep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Sleep")
+            .field("deadline", &self.deadline)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Clock that only moves forward when advanced manually, for use in tests.
+///
+/// Timers created by a mock clock complete once the clock has been advanced past their deadline,
+/// independently of the runtime's timer. Clones share the same time.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use actix_utils::{
+///     clock::{Clock as _, MockClock},
+///     future::{ready, timeout_with},
+/// };
+///
+/// # actix_rt::System::new().block_on(async {
+/// let clock = MockClock::new();
+///
+/// let timer = clock.sleep(Duration::from_secs(60));
+/// clock.advance(Duration::from_secs(60));
+/// timer.await;
+///
+/// let res = timeout_with(&clock, Duration::from_secs(1), ready(42)).await;
+/// assert_eq!(res, Ok(42));
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct MockClock {
+    inner: Arc<Mutex<MockState>>,
+}
+
+struct MockState {
+    now: Instant,
+    next_id: u64,
+    timers: HashMap<u64, (Instant, Waker)>,
+}
+
+impl MockClock {
+    /// Constructs new mock clock starting at the current time.
+    pub fn new() -> Self {
+        Self::starting_at(Instant::now())
+    }
+
+    /// Constructs new mock clock starting at `now`.
+    pub fn starting_at(now: Instant) -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(MockState {
+                now,
+                next_id: 0,
+                timers: HashMap::new(),
+            })),
+        }
+    }
+
+    /// Moves the clock forward by `dur`, waking tasks waiting on timers that have completed.
+    ///
+    /// Woken tasks run the next time the executor polls them, e.g., after the advancing task
+    /// yields.
+    pub fn advance(&self, dur: Duration) {
+        let wakers = {
+            let mut state = self.inner.lock().unwrap();
+            state.now += dur;
+
+            let now = state.now;
+            let expired = state
+                .timers
+                .iter()
+                .filter(|(_, (deadline, _))| *deadline <= now)
+                .map(|(id, _)| *id)
+                .collect::<Vec<_>>();
+
+            expired
+                .into_iter()
+                .filter_map(|id| state.timers.remove(&id))
+                .map(|(_, waker)| waker)
+                .collect::<Vec<_>>()
+        };
+
+        // wake outside of lock since woken tasks may run inline and use the clock
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Returns the number of timers that have not completed yet and are being waited on.
+    pub fn pending_timers(&self) -> usize {
+        self.inner.lock().unwrap().timers.len()
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.inner.lock().unwrap().now
+    }
+
+    fn sleep_until(&self, deadline: Instant) -> Sleep {
+        let id = {
+            let mut state = self.inner.lock().unwrap();
+            let id = state.next_id;
+            state.next_id += 1;
+            id
+        };
+
+        Sleep {
+            deadline,
+            inner: SleepInner::Mock {
+                sleep: MockSleep {
+                    clock: self.clone(),
+                    id,
+                    deadline,
+                },
+            },
+        }
+    }
+}
+
+impl fmt::Debug for MockClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.inner.lock().unwrap();
+
+        f.debug_struct("MockClock")
+            .field("now", &state.now)
+            .field("pending_timers", &state.timers.len())
+            .finish()
+    }
+}
+
+/// Timer of a [`MockClock`].
+struct MockSleep {
+    clock: MockClock,
+    id: u64,
+    deadline: Instant,
+}
+
+impl MockSleep {
+    fn poll(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut state = self.clock.inner.lock().unwrap();
+
+        if state.now >= self.deadline {
+            state.timers.remove(&self.id);
+            Poll::Ready(())
+        } else {
+            state
+                .timers
+                .insert(self.id, (self.deadline, cx.waker().clone()));
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for MockSleep {
+    fn drop(&mut self) {
+        if let Ok(mut state) = self.clock.inner.lock() {
+            state.timers.remove(&self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::task::noop_waker_ref;
+    use static_assertions::assert_impl_all;
+
+    use super::*;
+
+    assert_impl_all!(Sleep: Send);
+    assert_impl_all!(MockClock: Send, Sync, Clone);
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[test]
+    fn mock_timers_complete_when_advanced() {
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        let clock = MockClock::new();
+        let start = clock.now();
+
+        let mut short = Box::pin(clock.sleep(ms(10)));
+        let mut long = Box::pin(clock.sleep(ms(100)));
+
+        assert!(short.as_mut().poll(&mut cx).is_pending());
+        assert!(long.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(clock.pending_timers(), 2);
+
+        clock.advance(ms(10));
+        assert_eq!(clock.now() - start, ms(10));
+        assert!(short.as_mut().poll(&mut cx).is_ready());
+        assert!(long.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(clock.pending_timers(), 1);
+
+        // dropped timers are no longer tracked
+        drop(long);
+        assert_eq!(clock.pending_timers(), 0);
+    }
+}
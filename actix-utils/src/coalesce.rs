@@ -0,0 +1,389 @@
+//! Write coalescing for streams.
+//!
+//! See [`Coalesced`] for details.
+
+use core::{
+    fmt,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use std::io::{self, IoSlice};
+
+use actix_rt::net::{ActixStream, Ready};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Default buffer capacity of [`Coalesced`].
+const DEFAULT_CAPACITY: usize = 8 * 1024;
+
+/// Stream wrapper coalescing small writes into larger ones.
+///
+/// Writes are collected in a buffer and passed to the underlying stream once the buffered data
+/// reaches the flush threshold, when the stream is flushed or shut down, or, by default, when the
+/// stream is read from. The latter flushes responses of request/response protocols as soon as the
+/// service goes idle waiting for the next request, without explicit flushes after each frame.
+///
+/// Writes that do not fit in the buffer flush it first and writes at least as large as its
+/// capacity bypass it.
+///
+/// This is useful for services writing many small frames directly to a stream, outside of
+/// `Framed`, which does its own buffering.
+///
+/// # Examples
+/// ```
+/// use actix_utils::{coalesce::Coalesced, duplex::duplex};
+/// use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+///
+/// # actix_rt::System::new().block_on(async {
+/// let (client, mut server) = duplex(1024);
+/// let mut client = Coalesced::new(client).flush_threshold(512);
+///
+/// // buffered until the client waits for a response
+/// client.write_all(b"SET key ").await.unwrap();
+/// client.write_all(b"value\n").await.unwrap();
+///
+/// let respond = async {
+///     let mut buf = [0; 14];
+///     server.read_exact(&mut buf).await.unwrap();
+///     server.write_all(b"OK\n").await.unwrap();
+/// };
+///
+/// let read = async {
+///     let mut buf = [0; 3];
+///     client.read_exact(&mut buf).await.unwrap();
+/// };
+///
+/// actix_utils::future::join(respond, read).await;
+/// # });
+/// ```
+pub struct Coalesced<IO> {
+    io: IO,
+    buf: Vec<u8>,
+    written: usize,
+    capacity: usize,
+    threshold: usize,
+    flush_on_read: bool,
+}
+
+impl<IO> Coalesced<IO> {
+    /// Wraps `io`, buffering writes up to 8KiB and flushing them when the buffer is full.
+    pub fn new(io: IO) -> Self {
+        Self {
+            io,
+            buf: Vec::new(),
+            written: 0,
+            capacity: DEFAULT_CAPACITY,
+            threshold: DEFAULT_CAPACITY,
+            flush_on_read: true,
+        }
+    }
+
+    /// Sets buffer capacity, also lowering the flush threshold to `capacity` if it is larger.
+    ///
+    /// # Panics
+    /// Panics if `capacity` is zero.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        assert!(capacity > 0, "capacity must be non-zero");
+        self.capacity = capacity;
+        self.threshold = self.threshold.min(capacity);
+        self
+    }
+
+    /// Sets number of buffered bytes at which buffered data is written to the underlying stream.
+    ///
+    /// Thresholds larger than the capacity are capped to it. Default is the buffer capacity.
+    ///
+    /// # Panics
+    /// Panics if `threshold` is zero.
+    pub fn flush_threshold(mut self, threshold: usize) -> Self {
+        assert!(threshold > 0, "flush threshold must be non-zero");
+        self.threshold = threshold.min(self.capacity);
+        self
+    }
+
+    /// Sets whether buffered data is written to the underlying stream before reading from it.
+    ///
+    /// Enabled by default.
+    pub fn flush_on_read(mut self, enabled: bool) -> Self {
+        self.flush_on_read = enabled;
+        self
+    }
+
+    /// Returns data that is buffered but not yet written to the underlying stream.
+    pub fn buffered(&self) -> &[u8] {
+        &self.buf[self.written..]
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &IO {
+        &self.io
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    ///
+    /// Writing directly to the underlying stream may reorder data with buffered writes.
+    pub fn get_mut(&mut self) -> &mut IO {
+        &mut self.io
+    }
+
+    /// Returns the underlying stream, discarding buffered data.
+    ///
+    /// Flush the stream first to avoid losing data.
+    pub fn into_inner(self) -> IO {
+        self.io
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> Coalesced<IO> {
+    /// Writes out buffered data to the underlying stream, without flushing it.
+    fn poll_write_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.written < self.buf.len() {
+            let n = match Pin::new(&mut self.io).poll_write(cx, &self.buf[self.written..]) {
+                Poll::Ready(Ok(0)) => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+                Poll::Ready(Ok(n)) => n,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            };
+
+            self.written += n;
+        }
+
+        self.buf.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+
+    /// Adds `data` to the buffer and writes it out if the threshold is reached.
+    ///
+    /// Returns error of writing out buffered data, which is accepted regardless.
+    fn buffer(&mut self, cx: &mut Context<'_>, data: &[&[u8]]) -> io::Result<usize> {
+        let mut len = 0;
+
+        for data in data {
+            self.buf.extend_from_slice(data);
+            len += data.len();
+        }
+
+        if self.buffered().len() >= self.threshold {
+            if let Poll::Ready(Err(err)) = self.poll_write_buf(cx) {
+                return Err(err);
+            }
+        }
+
+        Ok(len)
+    }
+}
+
+impl<IO: fmt::Debug> fmt::Debug for Coalesced<IO> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Coalesced")
+            .field("io", &self.io)
+            .field("buffered", &self.buffered().len())
+            .field("capacity", &self.capacity)
+            .field("threshold", &self.threshold)
+            .field("flush_on_read", &self.flush_on_read)
+            .finish()
+    }
+}
+
+impl<IO: AsyncRead + AsyncWrite + Unpin> AsyncRead for Coalesced<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        // when the stream can not take buffered data right now, reading goes ahead and writing is
+        // resumed on the next read or write
+        if this.flush_on_read && !this.buffered().is_empty() {
+            if let Poll::Ready(Err(err)) = this.poll_write_buf(cx) {
+                return Poll::Ready(Err(err));
+            }
+        }
+
+        Pin::new(&mut this.io).poll_read(cx, buf)
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> AsyncWrite for Coalesced<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        if this.buffered().len() + buf.len() > this.capacity {
+            match this.poll_write_buf(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        if buf.len() >= this.capacity {
+            return Pin::new(&mut this.io).poll_write(cx, buf);
+        }
+
+        Poll::Ready(this.buffer(cx, &[buf]))
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let len = bufs.iter().map(|buf| buf.len()).sum::<usize>();
+
+        if this.buffered().len() + len > this.capacity {
+            match this.poll_write_buf(cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        if len >= this.capacity {
+            return Pin::new(&mut this.io).poll_write_vectored(cx, bufs);
+        }
+
+        let bufs = bufs.iter().map(|buf| &**buf).collect::<Vec<_>>();
+        Poll::Ready(this.buffer(cx, &bufs))
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        true
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        match this.poll_write_buf(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.io).poll_flush(cx),
+            res => res,
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        match this.poll_write_buf(cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.io).poll_shutdown(cx),
+            res => res,
+        }
+    }
+}
+
+impl<IO: ActixStream> ActixStream for Coalesced<IO> {
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        IO::poll_read_ready(&self.io, cx)
+    }
+
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        if self.buffered().len() < self.capacity {
+            return Poll::Ready(Ok(Ready::WRITABLE));
+        }
+
+        IO::poll_write_ready(&self.io, cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use static_assertions::assert_impl_all;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    use super::*;
+    use crate::duplex::{duplex, DuplexStream};
+
+    assert_impl_all!(Coalesced<DuplexStream>: ActixStream);
+
+    /// Writer recording the size of each write it receives.
+    #[derive(Default)]
+    struct Writes(Vec<usize>);
+
+    impl AsyncWrite for Writes {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.get_mut().0.push(buf.len());
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[actix_rt::test]
+    async fn coalesces_small_writes() {
+        let mut io = Coalesced::new(Writes::default())
+            .capacity(64)
+            .flush_threshold(16);
+
+        for _ in 0..10 {
+            io.write_all(b"abc").await.unwrap();
+        }
+        assert_eq!(io.get_ref().0, [18]);
+        assert_eq!(io.buffered(), b"abcabcabcabc");
+
+        io.flush().await.unwrap();
+        assert_eq!(io.get_ref().0, [18, 12]);
+        assert!(io.buffered().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn large_writes_bypass_buffer() {
+        let mut io = Coalesced::new(Writes::default()).capacity(8);
+
+        io.write_all(b"abcde").await.unwrap();
+        assert!(io.get_ref().0.is_empty());
+
+        // does not fit, so buffer is written out first
+        io.write_all(b"fghijk").await.unwrap();
+        assert_eq!(io.get_ref().0, [5]);
+        assert_eq!(io.buffered(), b"fghijk");
+
+        io.write_all(b"0123456789").await.unwrap();
+        assert_eq!(io.get_ref().0, [5, 6, 10]);
+        assert!(io.buffered().is_empty());
+    }
+
+    #[actix_rt::test]
+    async fn flushes_before_reading() {
+        let (client, mut server) = duplex(64);
+        let mut client = Coalesced::new(client);
+
+        client.write_all(b"ping").await.unwrap();
+        assert_eq!(client.buffered(), b"ping");
+
+        let server = actix_rt::spawn(async move {
+            let mut buf = [0; 4];
+            server.read_exact(&mut buf).await.unwrap();
+            assert_eq!(&buf, b"ping");
+            server.write_all(b"pong").await.unwrap();
+        });
+
+        let mut buf = [0; 4];
+        client.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+        server.await.unwrap();
+
+        // without flushing on read, data stays buffered
+        let (client, _server) = duplex(64);
+        let mut client = Coalesced::new(client).flush_on_read(false);
+        client.write_all(b"ping").await.unwrap();
+        let res =
+            actix_rt::time::timeout(core::time::Duration::from_millis(10), client.read(&mut buf))
+                .await;
+        assert!(res.is_err());
+        assert_eq!(client.buffered(), b"ping");
+    }
+}
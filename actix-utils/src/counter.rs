@@ -3,11 +3,12 @@
 use core::{cell::Cell, fmt, task};
 use std::rc::Rc;
 
-use local_waker::LocalWaker;
+use local_waker::LocalWakerSet;
 
-/// Simple counter with ability to notify task on reaching specific number
+/// Simple counter with ability to notify tasks on reaching specific number
 ///
-/// Counter could be cloned, total n-count is shared across all clones.
+/// Counter could be cloned, total n-count is shared across all clones. All tasks that observed the
+/// counter at capacity are woken once it drops below capacity again.
 #[derive(Debug, Clone)]
 pub struct Counter(Rc<CounterInner>);
 
@@ -17,7 +18,7 @@ impl Counter {
         Counter(Rc::new(CounterInner {
             capacity,
             count: Cell::new(0),
-            task: LocalWaker::new(),
+            tasks: LocalWakerSet::new(),
         }))
     }
 
@@ -28,6 +29,8 @@ impl Counter {
     }
 
     /// Returns true if counter is below capacity. Otherwise, register to wake task when it is.
+    ///
+    /// Any number of tasks may be waiting at the same time.
     #[inline]
     pub fn available(&self, cx: &mut task::Context<'_>) -> bool {
         self.0.available(cx)
@@ -43,7 +46,7 @@ impl Counter {
 struct CounterInner {
     count: Cell<usize>,
     capacity: usize,
-    task: LocalWaker,
+    tasks: LocalWakerSet,
 }
 
 impl CounterInner {
@@ -55,7 +58,7 @@ impl CounterInner {
         let num = self.count.get();
         self.count.set(num - 1);
         if num == self.capacity {
-            self.task.wake();
+            self.tasks.wake_all();
         }
     }
 
@@ -63,7 +66,7 @@ impl CounterInner {
         if self.count.get() < self.capacity {
             true
         } else {
-            self.task.register(cx.waker());
+            self.tasks.register(cx.waker());
             false
         }
     }
@@ -74,7 +77,7 @@ impl fmt::Debug for CounterInner {
         f.debug_struct("Counter")
             .field("count", &self.count.get())
             .field("capacity", &self.capacity)
-            .field("task", &self.task)
+            .field("tasks", &self.tasks)
             .finish()
     }
 }
@@ -97,3 +100,39 @@ impl Drop for CounterGuard {
         self.0.dec();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use core::task::Poll;
+
+    use super::*;
+    use crate::future::{join, poll_fn};
+
+    #[actix_rt::test]
+    async fn wakes_all_waiting_tasks() {
+        let counter = Counter::new(1);
+        let guard = counter.get();
+
+        let wait = || {
+            let counter = counter.clone();
+            poll_fn(move |cx| {
+                if counter.available(cx) {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            })
+        };
+
+        let a = actix_rt::spawn(wait());
+        let b = actix_rt::spawn(wait());
+
+        // let both tasks register before releasing capacity
+        actix_rt::task::yield_now().await;
+        drop(guard);
+
+        let (a, b) = join(a, b).await;
+        a.unwrap();
+        b.unwrap();
+    }
+}
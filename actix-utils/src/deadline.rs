@@ -0,0 +1,233 @@
+//! Resettable timer with an observable deadline.
+//!
+//! See [`Deadline`] for details.
+
+use core::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use actix_rt::time::Instant;
+
+use crate::clock::{Clock, RuntimeClock, Sleep};
+
+/// A timer that completes at a deadline which can be inspected and moved cheaply.
+///
+/// `Deadline` is designed for timers that are pushed back frequently, such as per-connection idle
+/// or keep-alive timers that are extended on every read. Moving the deadline later only records the
+/// new instant; the underlying timer is re-armed lazily, once, when it fires early. Moving the
+/// deadline earlier re-arms the timer immediately.
+///
+/// By default, all instants are taken from the runtime's clock, so `Deadline` behaves consistently
+/// when time is paused or advanced manually in tests. A different [`Clock`] can be used with
+/// [`Deadline::with_clock`].
+///
+/// `Deadline` is `Unpin`, so a timer that should be reused can be awaited by mutable reference.
+///
+/// # Panics
+/// Creating a `Deadline` panics if called outside of an Actix (Tokio) runtime with time enabled.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use actix_utils::deadline::Deadline;
+///
+/// # actix_rt::System::new().block_on(async {
+/// let mut idle = Deadline::after(Duration::from_millis(10));
+/// assert!(idle.remaining() > Duration::ZERO);
+///
+/// // activity pushes the deadline back
+/// idle.reset_after(Duration::from_millis(20));
+///
+/// (&mut idle).await;
+/// assert!(idle.is_elapsed());
+/// assert_eq!(idle.remaining(), Duration::ZERO);
+/// # });
+/// ```
+pub struct Deadline<C: Clock = RuntimeClock> {
+    clock: C,
+    deadline: Instant,
+    sleep: Pin<Box<Sleep>>,
+}
+
+/// Creates a [`Deadline`] that completes at `deadline`.
+pub fn sleep_until(deadline: Instant) -> Deadline {
+    Deadline::at(deadline)
+}
+
+impl Deadline {
+    /// Constructs new timer that completes at `deadline`.
+    pub fn at(deadline: Instant) -> Self {
+        Self::with_clock(RuntimeClock, deadline)
+    }
+
+    /// Constructs new timer that completes after `dur` has elapsed.
+    pub fn after(dur: Duration) -> Self {
+        Self::at(Instant::now() + dur)
+    }
+}
+
+impl<C: Clock> Deadline<C> {
+    /// Constructs new timer that completes once `clock` reaches `deadline`.
+    pub fn with_clock(clock: C, deadline: Instant) -> Self {
+        let sleep = Box::pin(clock.sleep_until(deadline));
+
+        Self {
+            clock,
+            deadline,
+            sleep,
+        }
+    }
+
+    /// Returns the clock used by the timer.
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Returns the instant at which the timer completes.
+    pub fn deadline(&self) -> Instant {
+        self.deadline
+    }
+
+    /// Returns the time left until the deadline, or zero if it has passed.
+    pub fn remaining(&self) -> Duration {
+        self.deadline.saturating_duration_since(self.clock.now())
+    }
+
+    /// Returns true if the deadline has passed.
+    pub fn is_elapsed(&self) -> bool {
+        self.deadline <= self.clock.now()
+    }
+
+    /// Moves the deadline to `deadline`.
+    ///
+    /// This can be called while the timer is being awaited, and also re-arms a timer that has
+    /// already completed.
+    pub fn reset(&mut self, deadline: Instant) {
+        self.deadline = deadline;
+
+        // a later deadline is picked up when the current timer fires
+        if deadline < self.sleep.deadline() {
+            self.sleep.set(self.clock.sleep_until(deadline));
+        }
+    }
+
+    /// Moves the deadline to `dur` from now.
+    pub fn reset_after(&mut self, dur: Duration) {
+        self.reset(self.clock.now() + dur);
+    }
+
+    /// Polls the timer, completing once the deadline has passed.
+    pub fn poll_elapsed(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        loop {
+            match self.sleep.as_mut().poll(cx) {
+                Poll::Pending => return Poll::Pending,
+
+                Poll::Ready(()) if self.sleep.deadline() >= self.deadline => return Poll::Ready(()),
+
+                // deadline was moved back since the timer was armed
+                Poll::Ready(()) => self.sleep.set(self.clock.sleep_until(self.deadline)),
+            }
+        }
+    }
+}
+
+// clock is never pinned
+impl<C: Clock> Unpin for Deadline<C> {}
+
+impl<C: Clock> Future for Deadline<C> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.get_mut().poll_elapsed(cx)
+    }
+}
+
+impl<C: Clock> fmt::Debug for Deadline<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Deadline")
+            .field("deadline", &self.deadline)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use static_assertions::assert_impl_all;
+
+    use super::*;
+    use crate::clock::MockClock;
+
+    assert_impl_all!(Deadline: Unpin, Send);
+
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn remaining_follows_runtime_clock() {
+        let start = Instant::now();
+        let mut deadline = Deadline::after(ms(100));
+        assert_eq!(deadline.remaining(), ms(100));
+
+        actix_rt::time::sleep(ms(40)).await;
+        assert_eq!(deadline.remaining(), ms(60));
+        assert!(!deadline.is_elapsed());
+
+        (&mut deadline).await;
+        assert_eq!(Instant::now() - start, ms(100));
+        assert!(deadline.is_elapsed());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reset_later_is_lazy() {
+        let start = Instant::now();
+        let mut deadline = Deadline::after(ms(100));
+        let armed = deadline.sleep.deadline();
+
+        deadline.reset_after(ms(250));
+        assert_eq!(deadline.sleep.deadline(), armed);
+
+        (&mut deadline).await;
+        assert_eq!(Instant::now() - start, ms(250));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reset_earlier_and_rearm() {
+        let start = Instant::now();
+        let mut deadline = sleep_until(start + ms(100));
+
+        deadline.reset(start + ms(10));
+        (&mut deadline).await;
+        assert_eq!(Instant::now() - start, ms(10));
+
+        // completed timer can be re-armed
+        deadline.reset_after(ms(30));
+        assert!(!deadline.is_elapsed());
+        (&mut deadline).await;
+        assert_eq!(Instant::now() - start, ms(40));
+    }
+
+    #[actix_rt::test]
+    async fn mock_clock() {
+        let clock = MockClock::new();
+        let start = clock.now();
+        let mut deadline = Deadline::with_clock(clock.clone(), start + ms(100));
+
+        clock.advance(ms(40));
+        assert_eq!(deadline.remaining(), ms(60));
+
+        deadline.reset_after(ms(100));
+        clock.advance(ms(60));
+        assert!(!deadline.is_elapsed());
+
+        clock.advance(ms(40));
+        (&mut deadline).await;
+        assert!(deadline.is_elapsed());
+        assert_eq!(clock.pending_timers(), 0);
+    }
+}
@@ -0,0 +1,551 @@
+//! In-memory duplex stream for testing.
+//!
+//! See [`duplex`] and [`DuplexBuilder`] for details.
+
+use core::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{Arc, Mutex},
+};
+
+use actix_rt::{
+    net::{ActixStream, Ready},
+    time::Instant,
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::clock::{Clock, RuntimeClock, Sleep};
+
+/// Creates a pair of connected in-memory streams, each buffering up to `max_buf_size` bytes
+/// written to it by the other.
+///
+/// Data written to one stream can be read from the other. Writes wait while the buffer is full.
+/// Shutting down or dropping one stream makes reads on the other reach EOF once buffered data is
+/// read, and writes to a dropped stream fail with [`io::ErrorKind::BrokenPipe`].
+///
+/// Use [`DuplexBuilder`] to simulate chunked transfers, latency, or errors.
+///
+/// # Panics
+/// Panics if `max_buf_size` is zero.
+///
+/// # Examples
+/// ```
+/// use actix_utils::duplex::duplex;
+/// use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+///
+/// # actix_rt::System::new().block_on(async {
+/// let (mut client, mut server) = duplex(64);
+///
+/// client.write_all(b"ping").await.unwrap();
+///
+/// let mut buf = [0; 4];
+/// server.read_exact(&mut buf).await.unwrap();
+/// assert_eq!(&buf, b"ping");
+/// # });
+/// ```
+pub fn duplex(max_buf_size: usize) -> (DuplexStream, DuplexStream) {
+    DuplexBuilder::new().max_buf_size(max_buf_size).build()
+}
+
+/// Builder for pairs of in-memory streams with simulated network conditions.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use actix_utils::{clock::MockClock, duplex::DuplexBuilder};
+/// use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+///
+/// # actix_rt::System::new().block_on(async {
+/// let clock = MockClock::new();
+///
+/// let (mut client, mut server) = DuplexBuilder::new()
+///     .chunk_size(2)
+///     .latency(Duration::from_millis(50))
+///     .clock(clock.clone())
+///     .build();
+///
+/// client.write_all(b"ping").await.unwrap();
+/// clock.advance(Duration::from_millis(50));
+///
+/// // data arrives in chunks of at most 2 bytes
+/// let mut buf = [0; 4];
+/// assert_eq!(server.read(&mut buf).await.unwrap(), 2);
+/// # });
+/// ```
+pub struct DuplexBuilder {
+    max_buf_size: usize,
+    chunk_size: usize,
+    latency: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl DuplexBuilder {
+    /// Constructs builder for streams buffering up to 64 KiB in each direction, without chunking or
+    /// latency.
+    pub fn new() -> Self {
+        Self {
+            max_buf_size: 64 * 1024,
+            chunk_size: usize::MAX,
+            latency: Duration::ZERO,
+            clock: Arc::new(RuntimeClock::new()),
+        }
+    }
+
+    /// Sets the number of bytes buffered in each direction before writes wait for the peer to read.
+    ///
+    /// # Panics
+    /// Panics if `max_buf_size` is zero.
+    pub fn max_buf_size(mut self, max_buf_size: usize) -> Self {
+        assert!(max_buf_size > 0, "buffer size must be non-zero");
+        self.max_buf_size = max_buf_size;
+        self
+    }
+
+    /// Limits the number of bytes transferred by each read and write call, simulating data that
+    /// arrives in small segments.
+    ///
+    /// # Panics
+    /// Panics if `chunk_size` is zero.
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be non-zero");
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// Delays written data by `latency` before it can be read by the peer.
+    pub fn latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Sets the clock used to measure latency.
+    ///
+    /// By default, the Actix (Tokio) runtime's timer is used.
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Creates a pair of connected streams.
+    pub fn build(self) -> (DuplexStream, DuplexStream) {
+        let a_to_b = Arc::new(Mutex::new(Pipe::new(self.max_buf_size)));
+        let b_to_a = Arc::new(Mutex::new(Pipe::new(self.max_buf_size)));
+
+        let a = DuplexStream {
+            read: b_to_a.clone(),
+            write: a_to_b.clone(),
+            chunk_size: self.chunk_size,
+            latency: self.latency,
+            clock: self.clock.clone(),
+        };
+
+        let b = DuplexStream {
+            read: a_to_b,
+            write: b_to_a,
+            chunk_size: self.chunk_size,
+            latency: self.latency,
+            clock: self.clock,
+        };
+
+        (a, b)
+    }
+}
+
+impl Default for DuplexBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for DuplexBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DuplexBuilder")
+            .field("max_buf_size", &self.max_buf_size)
+            .field("chunk_size", &self.chunk_size)
+            .field("latency", &self.latency)
+            .finish_non_exhaustive()
+    }
+}
+
+/// One end of an in-memory duplex stream.
+///
+/// Created with [`duplex`] or [`DuplexBuilder`].
+pub struct DuplexStream {
+    read: Arc<Mutex<Pipe>>,
+    write: Arc<Mutex<Pipe>>,
+    chunk_size: usize,
+    latency: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl DuplexStream {
+    /// Returns a handle for injecting errors into this stream.
+    ///
+    /// The handle can be kept by a test after the stream is moved into the code under test.
+    pub fn control(&self) -> DuplexControl {
+        DuplexControl {
+            read: self.read.clone(),
+            write: self.write.clone(),
+        }
+    }
+
+    fn poll_read_inner(
+        &self,
+        cx: &mut Context<'_>,
+        buf: Option<&mut ReadBuf<'_>>,
+    ) -> Poll<io::Result<()>> {
+        let mut pipe = self.read.lock().unwrap();
+
+        if let Some(err) = pipe.read_error.take() {
+            return Poll::Ready(Err(err));
+        }
+
+        loop {
+            let ready_at = match pipe.chunks.front() {
+                Some(chunk) => chunk.ready_at,
+
+                // wait for peer to write or shut down
+                None if pipe.closed => return Poll::Ready(Ok(())),
+                None => {
+                    pipe.read_waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+            };
+
+            if ready_at <= self.clock.now() {
+                break;
+            }
+
+            // wait for latency of front chunk to pass
+            let timer = match &mut pipe.timer {
+                Some(timer) if timer.deadline() == ready_at => timer,
+                timer => timer.insert(Box::pin(self.clock.sleep_until(ready_at))),
+            };
+
+            match timer.as_mut().poll(cx) {
+                Poll::Ready(()) => pipe.timer = None,
+                Poll::Pending => {
+                    pipe.read_waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+            }
+        }
+
+        let buf = match buf {
+            Some(buf) => buf,
+            None => return Poll::Ready(Ok(())),
+        };
+
+        let now = self.clock.now();
+        let mut limit = self.chunk_size.min(buf.remaining());
+
+        while limit > 0 {
+            let chunk = match pipe.chunks.front_mut() {
+                Some(chunk) if chunk.ready_at <= now => chunk,
+                _ => break,
+            };
+
+            let n = limit.min(chunk.data.len() - chunk.pos);
+            buf.put_slice(&chunk.data[chunk.pos..chunk.pos + n]);
+            chunk.pos += n;
+            limit -= n;
+
+            if chunk.pos == chunk.data.len() {
+                pipe.chunks.pop_front();
+            }
+
+            pipe.buffered -= n;
+        }
+
+        pipe.wake_writer();
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_write_inner(
+        &self,
+        cx: &mut Context<'_>,
+        buf: Option<&[u8]>,
+    ) -> Poll<io::Result<usize>> {
+        let mut pipe = self.write.lock().unwrap();
+
+        if let Some(err) = pipe.write_error.take() {
+            return Poll::Ready(Err(err));
+        }
+
+        if pipe.closed || pipe.reader_dropped {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+
+        let available = pipe.max_buf_size - pipe.buffered;
+
+        if available == 0 {
+            pipe.write_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        let buf = match buf {
+            Some(buf) => buf,
+            None => return Poll::Ready(Ok(0)),
+        };
+
+        let n = buf.len().min(available).min(self.chunk_size);
+
+        if n > 0 {
+            pipe.chunks.push_back(Chunk {
+                ready_at: self.clock.now() + self.latency,
+                data: buf[..n].to_vec(),
+                pos: 0,
+            });
+            pipe.buffered += n;
+            pipe.wake_reader();
+        }
+
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl AsyncRead for DuplexStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.poll_read_inner(cx, Some(buf))
+    }
+}
+
+impl AsyncWrite for DuplexStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.poll_write_inner(cx, Some(buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.write.lock().unwrap().close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl ActixStream for DuplexStream {
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        self.poll_read_inner(cx, None).map_ok(|()| Ready::READABLE)
+    }
+
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        self.poll_write_inner(cx, None).map_ok(|_| Ready::WRITABLE)
+    }
+}
+
+impl Drop for DuplexStream {
+    fn drop(&mut self) {
+        self.write.lock().unwrap().close();
+
+        let mut read = self.read.lock().unwrap();
+        read.reader_dropped = true;
+        read.wake_writer();
+    }
+}
+
+impl fmt::Debug for DuplexStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DuplexStream")
+            .field("readable", &self.read.lock().unwrap().buffered)
+            .field("chunk_size", &self.chunk_size)
+            .field("latency", &self.latency)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Handle for injecting errors into a [`DuplexStream`].
+#[derive(Clone)]
+pub struct DuplexControl {
+    read: Arc<Mutex<Pipe>>,
+    write: Arc<Mutex<Pipe>>,
+}
+
+impl DuplexControl {
+    /// Makes the next read or read readiness check on the stream fail with `err`.
+    pub fn fail_next_read(&self, err: io::Error) {
+        let mut pipe = self.read.lock().unwrap();
+        pipe.read_error = Some(err);
+        pipe.wake_reader();
+    }
+
+    /// Makes the next write or write readiness check on the stream fail with `err`.
+    pub fn fail_next_write(&self, err: io::Error) {
+        let mut pipe = self.write.lock().unwrap();
+        pipe.write_error = Some(err);
+        pipe.wake_writer();
+    }
+}
+
+impl fmt::Debug for DuplexControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DuplexControl").finish_non_exhaustive()
+    }
+}
+
+/// Data written in one direction of a duplex stream.
+struct Pipe {
+    chunks: VecDeque<Chunk>,
+    buffered: usize,
+    max_buf_size: usize,
+
+    /// Writer has shut down or was dropped.
+    closed: bool,
+    reader_dropped: bool,
+
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+
+    /// Timer waiting for the latency of the front chunk to pass.
+    timer: Option<Pin<Box<Sleep>>>,
+
+    read_error: Option<io::Error>,
+    write_error: Option<io::Error>,
+}
+
+struct Chunk {
+    ready_at: Instant,
+    data: Vec<u8>,
+    pos: usize,
+}
+
+impl Pipe {
+    fn new(max_buf_size: usize) -> Self {
+        Self {
+            chunks: VecDeque::new(),
+            buffered: 0,
+            max_buf_size,
+            closed: false,
+            reader_dropped: false,
+            read_waker: None,
+            write_waker: None,
+            timer: None,
+            read_error: None,
+            write_error: None,
+        }
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        self.wake_reader();
+    }
+
+    fn wake_reader(&mut self) {
+        if let Some(waker) = self.read_waker.take() {
+            waker.wake();
+        }
+    }
+
+    fn wake_writer(&mut self) {
+        if let Some(waker) = self.write_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use static_assertions::assert_impl_all;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    use super::*;
+    use crate::clock::MockClock;
+
+    assert_impl_all!(DuplexStream: Send, Sync, Unpin, ActixStream);
+
+    #[actix_rt::test]
+    async fn transfers_both_ways() {
+        let (mut a, mut b) = duplex(64);
+
+        a.write_all(b"ping").await.unwrap();
+        let mut buf = [0; 4];
+        b.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"ping");
+
+        b.write_all(b"pong").await.unwrap();
+        a.read_exact(&mut buf).await.unwrap();
+        assert_eq!(&buf, b"pong");
+
+        // shutdown reaches peer as EOF
+        a.shutdown().await.unwrap();
+        assert_eq!(b.read(&mut buf).await.unwrap(), 0);
+
+        drop(a);
+        let err = b.write_all(b"gone").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[actix_rt::test]
+    async fn full_buffer_waits_for_reader() {
+        let (mut a, mut b) = duplex(4);
+
+        let writer = actix_rt::spawn(async move {
+            a.write_all(b"0123456789").await.unwrap();
+        });
+
+        let mut buf = Vec::new();
+        b.read_to_end(&mut buf).await.unwrap();
+        assert_eq!(buf, b"0123456789");
+        writer.await.unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn chunking_and_latency() {
+        let clock = MockClock::new();
+        let (mut a, mut b) = DuplexBuilder::new()
+            .chunk_size(3)
+            .latency(Duration::from_millis(100))
+            .clock(clock.clone())
+            .build();
+
+        assert_eq!(a.write(b"abcdef").await.unwrap(), 3);
+        assert_eq!(a.write(b"def").await.unwrap(), 3);
+
+        let reader = actix_rt::spawn(async move {
+            let mut buf = [0; 8];
+            let n = b.read(&mut buf).await.unwrap();
+            buf[..n].to_vec()
+        });
+
+        actix_rt::task::yield_now().await;
+        assert!(!reader.is_finished());
+        assert_eq!(clock.pending_timers(), 1);
+
+        clock.advance(Duration::from_millis(100));
+        assert_eq!(reader.await.unwrap(), b"abc");
+    }
+
+    #[actix_rt::test]
+    async fn injected_errors() {
+        let (mut a, b) = duplex(64);
+        let control = a.control();
+
+        control.fail_next_write(io::ErrorKind::ConnectionReset.into());
+        let err = a.write(b"x").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::ConnectionReset);
+        assert_eq!(a.write(b"x").await.unwrap(), 1);
+
+        control.fail_next_read(io::ErrorKind::TimedOut.into());
+        let err = a.read(&mut [0; 1]).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+        drop(b);
+    }
+}
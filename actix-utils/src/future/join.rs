@@ -0,0 +1,356 @@
+//! Futures that wait for several futures to complete.
+
+use core::{
+    fmt,
+    future::Future,
+    mem,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+
+pin_project! {
+    /// A future that may have completed, storing its output until it is taken.
+    #[project = MaybeDoneProj]
+    #[project_replace = MaybeDoneProjReplace]
+    enum MaybeDone<F: Future> {
+        Future { #[pin] fut: F },
+        Done { output: F::Output },
+        Gone,
+    }
+}
+
+impl<F: Future> MaybeDone<F> {
+    /// Polls inner future if it has not completed yet, returning true once it has.
+    fn poll_done(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> bool {
+        match self.as_mut().project() {
+            MaybeDoneProj::Future { fut } => match fut.poll(cx) {
+                Poll::Ready(output) => {
+                    self.set(MaybeDone::Done { output });
+                    true
+                }
+                Poll::Pending => false,
+            },
+            MaybeDoneProj::Done { .. } => true,
+            MaybeDoneProj::Gone => panic!("future polled after completion"),
+        }
+    }
+
+    fn take_output(self: Pin<&mut Self>) -> F::Output {
+        match self.project_replace(MaybeDone::Gone) {
+            MaybeDoneProjReplace::Done { output } => output,
+            _ => unreachable!("output taken before future completed"),
+        }
+    }
+}
+
+pin_project! {
+    /// Future for the [`join`] function.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Join<A: Future, B: Future> {
+        #[pin]
+        a: MaybeDone<A>,
+        #[pin]
+        b: MaybeDone<B>,
+    }
+}
+
+/// Creates a future that waits for both futures to complete, resolving to a tuple of their outputs.
+///
+/// Both futures are polled concurrently on the current task; no allocation or spawning is
+/// involved, so they need not be `Send`.
+///
+/// # Examples
+/// ```
+/// use actix_utils::future::{join, ready};
+///
+/// # async fn run() {
+/// let (a, b) = join(ready(1), async { "two" }).await;
+/// assert_eq!((a, b), (1, "two"));
+/// # }
+/// # actix_rt::System::new().block_on(run());
+/// ```
+pub fn join<A, B>(a: A, b: B) -> Join<A, B>
+where
+    A: Future,
+    B: Future,
+{
+    Join {
+        a: MaybeDone::Future { fut: a },
+        b: MaybeDone::Future { fut: b },
+    }
+}
+
+impl<A: Future, B: Future> Future for Join<A, B> {
+    type Output = (A::Output, B::Output);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        let a_done = this.a.as_mut().poll_done(cx);
+        let b_done = this.b.as_mut().poll_done(cx);
+
+        if a_done && b_done {
+            Poll::Ready((this.a.take_output(), this.b.take_output()))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<A: Future, B: Future> fmt::Debug for Join<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Join").finish_non_exhaustive()
+    }
+}
+
+/// Future for the [`join_all`] function.
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct JoinAll<F: Future> {
+    elems: Pin<Box<[MaybeDone<F>]>>,
+}
+
+/// Creates a future that waits for all futures in `iter` to complete, resolving to a `Vec` of their
+/// outputs in the same order.
+///
+/// All futures are stored in a single allocation and polled on the current task.
+///
+/// # Examples
+/// ```
+/// use actix_utils::future::{join_all, ready};
+///
+/// # async fn run() {
+/// let res = join_all((1..=3).map(ready)).await;
+/// assert_eq!(res, [1, 2, 3]);
+/// # }
+/// # actix_rt::System::new().block_on(run());
+/// ```
+pub fn join_all<I>(iter: I) -> JoinAll<I::Item>
+where
+    I: IntoIterator,
+    I::Item: Future,
+{
+    let elems = iter
+        .into_iter()
+        .map(|fut| MaybeDone::Future { fut })
+        .collect::<Box<[_]>>();
+
+    JoinAll {
+        elems: Box::into_pin(elems),
+    }
+}
+
+/// Returns pinned references to each element of a pinned slice.
+fn iter_pin_mut<T>(slice: Pin<&mut [T]>) -> impl Iterator<Item = Pin<&mut T>> {
+    // SAFETY: elements of a pinned slice are never moved out of it; each is only exposed through
+    // a pinned reference.
+    unsafe { slice.get_unchecked_mut() }
+        .iter_mut()
+        .map(|elem| unsafe { Pin::new_unchecked(elem) })
+}
+
+impl<F: Future> Future for JoinAll<F> {
+    type Output = Vec<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut all_done = true;
+
+        for elem in iter_pin_mut(self.elems.as_mut()) {
+            if !elem.poll_done(cx) {
+                all_done = false;
+            }
+        }
+
+        if !all_done {
+            return Poll::Pending;
+        }
+
+        let mut elems = mem::replace(&mut self.elems, Box::into_pin(Box::new([])));
+        let output = iter_pin_mut(elems.as_mut())
+            .map(MaybeDone::take_output)
+            .collect();
+
+        Poll::Ready(output)
+    }
+}
+
+impl<F: Future> fmt::Debug for JoinAll<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JoinAll")
+            .field("len", &self.elems.len())
+            .finish()
+    }
+}
+
+pin_project! {
+    /// A fallible future that may have completed successfully, storing its output until taken.
+    #[project = TryMaybeDoneProj]
+    #[project_replace = TryMaybeDoneProjReplace]
+    enum TryMaybeDone<F, T> {
+        Future { #[pin] fut: F },
+        Done { output: T },
+        Gone,
+    }
+}
+
+impl<F, T, E> TryMaybeDone<F, T>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    /// Polls inner future if it has not completed yet, returning true once it has succeeded.
+    fn poll_done(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Result<bool, E> {
+        match self.as_mut().project() {
+            TryMaybeDoneProj::Future { fut } => match fut.poll(cx) {
+                Poll::Ready(Ok(output)) => {
+                    self.set(TryMaybeDone::Done { output });
+                    Ok(true)
+                }
+                Poll::Ready(Err(err)) => {
+                    self.set(TryMaybeDone::Gone);
+                    Err(err)
+                }
+                Poll::Pending => Ok(false),
+            },
+            TryMaybeDoneProj::Done { .. } => Ok(true),
+            TryMaybeDoneProj::Gone => panic!("future polled after completion"),
+        }
+    }
+
+    fn take_output(self: Pin<&mut Self>) -> T {
+        match self.project_replace(TryMaybeDone::Gone) {
+            TryMaybeDoneProjReplace::Done { output } => output,
+            _ => unreachable!("output taken before future completed"),
+        }
+    }
+}
+
+pin_project! {
+    /// Future for the [`try_join`] function.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct TryJoin<A, B, T1, T2> {
+        #[pin]
+        a: TryMaybeDone<A, T1>,
+        #[pin]
+        b: TryMaybeDone<B, T2>,
+    }
+}
+
+/// Creates a future that waits for both fallible futures to succeed, resolving to a tuple of their
+/// outputs, or resolving to the first error that occurs.
+///
+/// When either future fails, the other is not polled again.
+///
+/// # Examples
+/// ```
+/// use actix_utils::future::{err, ok, try_join};
+///
+/// # async fn run() {
+/// let res = try_join(ok::<_, ()>(1), ok(2)).await;
+/// assert_eq!(res, Ok((1, 2)));
+///
+/// let res = try_join(ok::<u8, _>(1), err::<u8, _>("failed")).await;
+/// assert_eq!(res, Err("failed"));
+/// # }
+/// # actix_rt::System::new().block_on(run());
+/// ```
+pub fn try_join<A, B, T1, T2, E>(a: A, b: B) -> TryJoin<A, B, T1, T2>
+where
+    A: Future<Output = Result<T1, E>>,
+    B: Future<Output = Result<T2, E>>,
+{
+    TryJoin {
+        a: TryMaybeDone::Future { fut: a },
+        b: TryMaybeDone::Future { fut: b },
+    }
+}
+
+impl<A, B, T1, T2, E> Future for TryJoin<A, B, T1, T2>
+where
+    A: Future<Output = Result<T1, E>>,
+    B: Future<Output = Result<T2, E>>,
+{
+    type Output = Result<(T1, T2), E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+
+        let a_done = this.a.as_mut().poll_done(cx)?;
+        let b_done = this.b.as_mut().poll_done(cx)?;
+
+        if a_done && b_done {
+            Poll::Ready(Ok((this.a.take_output(), this.b.take_output())))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<A, B, T1, T2> fmt::Debug for TryJoin<A, B, T1, T2> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TryJoin").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::rc::Rc;
+
+    use futures_util::task::noop_waker;
+    use static_assertions::assert_impl_all;
+
+    use super::*;
+    use crate::future::{err, ok, poll_fn, Ready};
+
+    assert_impl_all!(Join<Ready<()>, Ready<()>>: Unpin);
+    assert_impl_all!(JoinAll<Ready<Rc<()>>>: Unpin);
+
+    /// Future that is pending for the given number of polls.
+    fn pending_for<T>(mut polls: usize, value: T) -> impl Future<Output = T> {
+        let mut value = Some(value);
+
+        poll_fn(move |cx| {
+            if polls == 0 {
+                Poll::Ready(value.take().unwrap())
+            } else {
+                polls -= 1;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+    }
+
+    #[actix_rt::test]
+    async fn join_waits_for_both() {
+        let res = join(pending_for(3, 'a'), pending_for(1, Rc::new('b'))).await;
+        assert_eq!(res.0, 'a');
+        assert_eq!(*res.1, 'b');
+    }
+
+    #[actix_rt::test]
+    async fn join_all_keeps_order() {
+        let futs = (0..5).rev().map(|n| pending_for(n, n));
+        assert_eq!(join_all(futs).await, [4, 3, 2, 1, 0]);
+
+        let empty = join_all(Vec::<Ready<()>>::new()).await;
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn try_join_short_circuits() {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut fut = Box::pin(try_join(
+            pending_for(10, Ok::<_, &str>(1)),
+            err::<u8, _>("failed"),
+        ));
+        assert_eq!(fut.as_mut().poll(&mut cx), Poll::Ready(Err("failed")));
+    }
+
+    #[actix_rt::test]
+    async fn try_join_ok() {
+        let res = try_join(pending_for(2, Ok::<_, ()>(1)), ok("two")).await;
+        assert_eq!(res, Ok((1, "two")));
+    }
+}
@@ -1,11 +1,17 @@
 //! Helpers for constructing futures.
 
 mod either;
+mod join;
 mod poll_fn;
 mod ready;
+mod select;
+mod timeout;
 
 pub use self::{
     either::Either,
+    join::{join, join_all, try_join, Join, JoinAll, TryJoin},
     poll_fn::{poll_fn, PollFn},
     ready::{err, ok, ready, Ready},
+    select::{select, Select},
+    timeout::{timeout, timeout_at, timeout_with, Elapsed, Timeout},
 };
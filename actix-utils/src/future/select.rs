@@ -0,0 +1,100 @@
+//! A future that resolves to the output of whichever of two futures completes first.
+
+use core::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use pin_project_lite::pin_project;
+
+use super::Either;
+
+pin_project! {
+    /// Future for the [`select`] function.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Select<A, B> {
+        #[pin]
+        a: A,
+        #[pin]
+        b: B,
+    }
+}
+
+/// Creates a future that resolves to the output of whichever future completes first.
+///
+/// The output of `a` is returned as [`Either::Left`] and the output of `b` as [`Either::Right`].
+/// The other future is dropped along with the `Select`. Polling is biased: `a` is always polled
+/// before `b`, so if both are ready, `a` wins.
+///
+/// # Examples
+/// ```
+/// use std::future::pending;
+///
+/// use actix_utils::future::{ready, select, Either};
+///
+/// # async fn run() {
+/// let res = select(pending::<u8>(), ready("done")).await;
+/// assert!(matches!(res, Either::Right { value: "done" }));
+/// # }
+/// # actix_rt::System::new().block_on(run());
+/// ```
+pub fn select<A, B>(a: A, b: B) -> Select<A, B>
+where
+    A: Future,
+    B: Future,
+{
+    Select { a, b }
+}
+
+impl<A, B> Future for Select<A, B>
+where
+    A: Future,
+    B: Future,
+{
+    type Output = Either<A::Output, B::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(value) = this.a.poll(cx) {
+            return Poll::Ready(Either::left(value));
+        }
+
+        if let Poll::Ready(value) = this.b.poll(cx) {
+            return Poll::Ready(Either::right(value));
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<A, B> fmt::Debug for Select<A, B> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Select").finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::pending;
+
+    use super::*;
+    use crate::future::ready;
+
+    #[actix_rt::test]
+    async fn first_ready_wins() {
+        let res = select(ready(1), pending::<&str>()).await;
+        assert!(matches!(res, Either::Left { value: 1 }));
+
+        let res = select(pending::<u8>(), ready("b")).await;
+        assert!(matches!(res, Either::Right { value: "b" }));
+    }
+
+    #[actix_rt::test]
+    async fn biased_to_first() {
+        let res = select(ready(1), ready(2)).await;
+        assert_eq!(res.into_inner(), 1);
+    }
+}
@@ -0,0 +1,220 @@
+//! A future that limits how long another future may take to complete.
+
+use core::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use std::error::Error;
+
+use actix_rt::time::Instant;
+use pin_project_lite::pin_project;
+
+use crate::clock::{Clock, RuntimeClock, Sleep};
+
+pin_project! {
+    /// Future for the [`timeout`], [`timeout_at`], and [`timeout_with`] functions.
+    #[must_use = "futures do nothing unless you `.await` or poll them"]
+    pub struct Timeout<F> {
+        #[pin]
+        fut: F,
+        #[pin]
+        delay: Sleep,
+    }
+}
+
+/// Creates a future that resolves to the output of `fut`, or to an [`Elapsed`] error if `fut` does
+/// not complete within `dur`.
+///
+/// The inner future is always polled before the timer, so a future that is ready at the same time
+/// as the deadline passes still succeeds.
+///
+/// # Cancellation
+/// When the deadline passes, the inner future is dropped along with the `Timeout` without being
+/// polled to completion. The `Timeout` is therefore cancellation safe exactly when `fut` is: any
+/// partial progress made by `fut` is lost, just as if it was dropped directly.
+///
+/// # Panics
+/// Panics if called outside of an Actix (Tokio) runtime with time enabled.
+///
+/// # Examples
+/// ```
+/// use std::{future::pending, time::Duration};
+///
+/// use actix_utils::future::{ready, timeout};
+///
+/// # actix_rt::System::new().block_on(async {
+/// let res = timeout(Duration::from_secs(1), ready(42)).await;
+/// assert_eq!(res, Ok(42));
+///
+/// let res = timeout(Duration::from_millis(1), pending::<()>()).await;
+/// assert!(res.is_err());
+/// # });
+/// ```
+pub fn timeout<F: Future>(dur: Duration, fut: F) -> Timeout<F> {
+    timeout_with(&RuntimeClock, dur, fut)
+}
+
+/// Creates a future that resolves to the output of `fut`, or to an [`Elapsed`] error if `fut` does
+/// not complete before `deadline`.
+///
+/// See [`timeout`] for details.
+pub fn timeout_at<F: Future>(deadline: Instant, fut: F) -> Timeout<F> {
+    Timeout {
+        fut,
+        delay: RuntimeClock.sleep_until(deadline),
+    }
+}
+
+/// Creates a future that resolves to the output of `fut`, or to an [`Elapsed`] error if `fut` does
+/// not complete within `dur`, as measured by `clock`.
+///
+/// See [`timeout`] for details.
+///
+/// # Examples
+/// ```
+/// use std::{future::pending, time::Duration};
+///
+/// use actix_utils::{clock::MockClock, future::timeout_with};
+///
+/// # actix_rt::System::new().block_on(async {
+/// let clock = MockClock::new();
+///
+/// let timeout = timeout_with(&clock, Duration::from_secs(60), pending::<()>());
+/// clock.advance(Duration::from_secs(60));
+/// assert!(timeout.await.is_err());
+/// # });
+/// ```
+pub fn timeout_with<C, F>(clock: &C, dur: Duration, fut: F) -> Timeout<F>
+where
+    C: Clock + ?Sized,
+    F: Future,
+{
+    Timeout {
+        fut,
+        delay: clock.sleep(dur),
+    }
+}
+
+impl<F> Timeout<F> {
+    /// Returns a reference to the inner future.
+    pub fn get_ref(&self) -> &F {
+        &self.fut
+    }
+
+    /// Returns a mutable reference to the inner future.
+    pub fn get_mut(&mut self) -> &mut F {
+        &mut self.fut
+    }
+
+    /// Returns the instant at which the timeout elapses.
+    pub fn deadline(&self) -> Instant {
+        self.delay.deadline()
+    }
+
+    /// Consumes the timeout, returning the inner future.
+    pub fn into_inner(self) -> F {
+        self.fut
+    }
+}
+
+impl<F: Future> Future for Timeout<F> {
+    type Output = Result<F::Output, Elapsed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+
+        if let Poll::Ready(res) = this.fut.poll(cx) {
+            return Poll::Ready(Ok(res));
+        }
+
+        match this.delay.poll(cx) {
+            Poll::Ready(()) => Poll::Ready(Err(Elapsed(()))),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<F> fmt::Debug for Timeout<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Timeout")
+            .field("deadline", &self.delay.deadline())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Error returned from [`Timeout`] when the deadline passes before the inner future completes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Elapsed(());
+
+impl fmt::Display for Elapsed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("deadline has elapsed")
+    }
+}
+
+impl Error for Elapsed {}
+
+#[cfg(test)]
+mod tests {
+    use core::future::pending;
+
+    use super::*;
+    use crate::{
+        clock::MockClock,
+        future::{poll_fn, ready},
+    };
+
+    #[actix_rt::test]
+    async fn completes_before_deadline() {
+        let res = timeout(Duration::from_secs(10), ready("done")).await;
+        assert_eq!(res, Ok("done"));
+    }
+
+    #[actix_rt::test]
+    async fn elapses() {
+        let res = timeout(Duration::from_millis(5), pending::<()>()).await;
+        assert_eq!(res, Err(Elapsed(())));
+        assert_eq!(res.unwrap_err().to_string(), "deadline has elapsed");
+    }
+
+    #[actix_rt::test]
+    async fn inner_polled_first() {
+        // deadline has already passed but the inner future is ready on first poll
+        let deadline = Instant::now() - Duration::from_secs(1);
+        let res = timeout_at(deadline, ready(1)).await;
+        assert_eq!(res, Ok(1));
+    }
+
+    #[actix_rt::test]
+    async fn into_inner() {
+        let timeout = timeout(Duration::from_secs(10), ready(3));
+        assert!(timeout.deadline() > Instant::now());
+        assert_eq!(timeout.get_ref().clone().into_inner(), 3);
+        assert_eq!(timeout.into_inner().await, 3);
+    }
+
+    #[actix_rt::test]
+    async fn mock_clock() {
+        let clock = MockClock::new();
+
+        let res = timeout_with(&clock, Duration::from_secs(10), ready("done")).await;
+        assert_eq!(res, Ok("done"));
+
+        let mut timeout = Box::pin(timeout_with(
+            &clock,
+            Duration::from_secs(10),
+            pending::<()>(),
+        ));
+        assert_eq!(timeout.deadline(), clock.now() + Duration::from_secs(10));
+
+        // runtime time passing does not affect mock clock
+        actix_rt::time::sleep(Duration::from_millis(5)).await;
+        assert!(poll_fn(|cx| Poll::Ready(timeout.as_mut().poll(cx).is_pending())).await);
+
+        clock.advance(Duration::from_secs(10));
+        assert_eq!(timeout.await, Err(Elapsed(())));
+    }
+}
@@ -5,5 +5,18 @@
 #![doc(html_logo_url = "https://actix.rs/img/logo.png")]
 #![doc(html_favicon_url = "https://actix.rs/favicon.ico")]
 
+pub mod backoff;
+pub mod budget;
+pub mod clock;
+pub mod coalesce;
 pub mod counter;
+pub mod deadline;
+pub mod duplex;
 pub mod future;
+pub mod notify;
+pub mod proxy;
+pub mod record;
+pub mod semaphore;
+pub mod throttle;
+pub mod wait_queue;
+pub mod watch;
@@ -0,0 +1,289 @@
+//! Single-threaded task notification.
+//!
+//! See [`Notify`] for details.
+
+use core::{
+    cell::Cell,
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use std::rc::Rc;
+
+use crate::wait_queue::{WaitQueue, Waiter};
+
+/// Notifies one or all waiting tasks running on the same thread.
+///
+/// `Notify` is a condition-like primitive for signalling events such as "config updated" or
+/// "drain started" between tasks on the same arbiter, without the synchronization overhead of
+/// thread-safe alternatives. It can be cloned cheaply, with all clones notifying the same waiters.
+///
+/// A task waits for a notification by awaiting [`notified`](Self::notified).
+/// [`notify_one`](Self::notify_one) wakes the task that has been waiting longest or, if none are
+/// waiting, stores a permit so that the next call to `notified` completes immediately.
+/// [`notify_waiters`](Self::notify_waiters) wakes all tasks waiting at that moment but does not
+/// store a permit.
+///
+/// # Examples
+/// ```
+/// # actix_rt::System::new().block_on(async {
+/// use actix_utils::notify::Notify;
+///
+/// let notify = Notify::new();
+///
+/// let waiter = actix_rt::spawn({
+///     let notify = notify.clone();
+///     async move { notify.notified().await }
+/// });
+///
+/// notify.notify_one();
+/// waiter.await.unwrap();
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct Notify(Rc<Inner>);
+
+struct Inner {
+    permit: Cell<bool>,
+    generation: Cell<usize>,
+    waiters: WaitQueue<Notification>,
+}
+
+/// Kind of notification handed to a queued waiter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Notification {
+    One,
+    All,
+}
+
+impl Notify {
+    /// Constructs new `Notify` with no stored permit.
+    pub fn new() -> Self {
+        Self(Rc::new(Inner {
+            permit: Cell::new(false),
+            generation: Cell::new(0),
+            waiters: WaitQueue::new(),
+        }))
+    }
+
+    /// Returns a future that completes when this `Notify` is notified.
+    ///
+    /// The future captures calls to [`notify_waiters`](Self::notify_waiters) made after it is
+    /// created, even before it is first polled.
+    ///
+    /// # Cancellation
+    /// Dropping the future gives up its place in the queue. If a notification from
+    /// [`notify_one`](Self::notify_one) was already assigned to it but not yet observed, the
+    /// notification is passed on to the next waiter, or stored as a permit if there is none.
+    pub fn notified(&self) -> Notified {
+        Notified {
+            notify: self.0.clone(),
+            generation: self.0.generation.get(),
+            waiter: None,
+        }
+    }
+
+    /// Wakes the task that has been waiting longest.
+    ///
+    /// If no task is waiting, a permit is stored instead and the next call to
+    /// [`notified`](Self::notified) completes immediately. At most one permit is stored.
+    pub fn notify_one(&self) {
+        self.0.notify_one();
+    }
+
+    /// Wakes all currently waiting tasks.
+    ///
+    /// Tasks that start waiting after this call are not affected and no permit is stored.
+    pub fn notify_waiters(&self) {
+        self.0
+            .generation
+            .set(self.0.generation.get().wrapping_add(1));
+
+        self.0.waiters.wake_all(|| Notification::All);
+    }
+
+    /// Returns the number of tasks currently waiting for a notification.
+    pub fn waiters(&self) -> usize {
+        self.0.waiters.len()
+    }
+}
+
+impl Default for Notify {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Inner {
+    fn notify_one(&self) {
+        if self.waiters.wake_front(Notification::One).is_err() {
+            self.permit.set(true);
+        }
+    }
+}
+
+impl fmt::Debug for Notify {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Notify")
+            .field("permit", &self.0.permit.get())
+            .field("waiters", &self.0.waiters.len())
+            .finish()
+    }
+}
+
+/// Future returned by [`Notify::notified`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Notified {
+    notify: Rc<Inner>,
+    generation: usize,
+    waiter: Option<Waiter<Notification>>,
+}
+
+impl Future for Notified {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        match &this.waiter {
+            None => {
+                if this.notify.generation.get() != this.generation || this.notify.permit.take() {
+                    return Poll::Ready(());
+                }
+
+                this.waiter = Some(this.notify.waiters.register(cx.waker()));
+
+                Poll::Pending
+            }
+
+            Some(waiter) => match waiter.poll_take(cx) {
+                Poll::Pending => Poll::Pending,
+                Poll::Ready(_) => {
+                    this.waiter = None;
+                    Poll::Ready(())
+                }
+            },
+        }
+    }
+}
+
+impl Drop for Notified {
+    fn drop(&mut self) {
+        if let Some(waiter) = self.waiter.take() {
+            // hand on a notification that was assigned but never observed rather than losing it
+            if let Some(Notification::One) = self.notify.waiters.cancel(waiter) {
+                self.notify.notify_one();
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Notified {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Notified")
+            .field("queued", &self.waiter.is_some())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::task::noop_waker;
+    use static_assertions::assert_not_impl_any;
+
+    use super::*;
+
+    assert_not_impl_any!(Notify: Send, Sync);
+    assert_not_impl_any!(Notified: Send, Sync);
+
+    fn poll_once<F: Future + Unpin>(fut: &mut F) -> Poll<F::Output> {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        Pin::new(fut).poll(&mut cx)
+    }
+
+    #[test]
+    fn notify_one_stores_single_permit() {
+        let notify = Notify::new();
+        notify.notify_one();
+        notify.notify_one();
+
+        assert!(poll_once(&mut notify.notified()).is_ready());
+        assert!(poll_once(&mut notify.notified()).is_pending());
+    }
+
+    #[test]
+    fn notify_one_wakes_in_order() {
+        let notify = Notify::new();
+
+        let mut first = notify.notified();
+        let mut second = notify.notified();
+        assert!(poll_once(&mut first).is_pending());
+        assert!(poll_once(&mut second).is_pending());
+        assert_eq!(notify.waiters(), 2);
+
+        notify.notify_one();
+        assert!(poll_once(&mut second).is_pending());
+        assert!(poll_once(&mut first).is_ready());
+
+        notify.notify_one();
+        assert!(poll_once(&mut second).is_ready());
+        assert_eq!(notify.waiters(), 0);
+    }
+
+    #[test]
+    fn notify_waiters_does_not_store_permit() {
+        let notify = Notify::new();
+
+        // created before the call, so captures it without being polled
+        let mut early = notify.notified();
+        let mut polled = notify.notified();
+        assert!(poll_once(&mut polled).is_pending());
+
+        notify.notify_waiters();
+        assert!(poll_once(&mut early).is_ready());
+        assert!(poll_once(&mut polled).is_ready());
+
+        assert!(poll_once(&mut notify.notified()).is_pending());
+    }
+
+    #[test]
+    fn dropped_waiter_forwards_notification() {
+        let notify = Notify::new();
+
+        let mut first = notify.notified();
+        let mut second = notify.notified();
+        assert!(poll_once(&mut first).is_pending());
+        assert!(poll_once(&mut second).is_pending());
+
+        notify.notify_one();
+        drop(first);
+        assert!(poll_once(&mut second).is_ready());
+
+        let mut third = notify.notified();
+        assert!(poll_once(&mut third).is_pending());
+        drop(third);
+        assert_eq!(notify.waiters(), 0);
+    }
+
+    #[actix_rt::test]
+    async fn wakes_spawned_tasks() {
+        let notify = Notify::new();
+
+        let tasks = (0..3)
+            .map(|_| {
+                let notify = notify.clone();
+                actix_rt::spawn(async move { notify.notified().await })
+            })
+            .collect::<Vec<_>>();
+
+        actix_rt::task::yield_now().await;
+        assert_eq!(notify.waiters(), 3);
+        notify.notify_waiters();
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+    }
+}
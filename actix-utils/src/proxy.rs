@@ -0,0 +1,657 @@
+//! Primitives for proxying connections.
+//!
+//! See [`copy_bidirectional`] and [`Relay`] for details.
+
+use core::{
+    any::Any,
+    fmt,
+    future::Future as _,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use std::{io, sync::Arc};
+
+use actix_rt::{net::ActixStream, time::Instant};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::{
+    clock::{Clock, RuntimeClock, Sleep},
+    future::poll_fn,
+};
+
+/// Copies data in both directions between `a` and `b` until both reach EOF.
+///
+/// When a stream reaches EOF, the write side of the other stream is shut down, so half-closed
+/// connections are forwarded. Returns the number of bytes copied from `a` to `b` and from `b`
+/// to `a`, respectively.
+///
+/// On Linux, when both streams are plain [`TcpStream`]s or [`UnixStream`]s, data is moved between
+/// them using `splice(2)` without being copied through user space. Any other streams, e.g., TLS
+/// streams, are copied through buffers using [`tokio::io::copy_bidirectional`].
+///
+/// This is the core of TCP and SNI proxies: after reading the routing information from the client
+/// connection, connect to the upstream and hand both streams to this function.
+///
+/// # Errors
+/// Returns the first error encountered while reading from or writing to either stream. Data read
+/// but not yet written at that point is lost.
+///
+/// # Examples
+/// ```no_run
+/// use actix_rt::net::TcpStream;
+/// use actix_utils::proxy::copy_bidirectional;
+///
+/// # async fn proxy(mut client: TcpStream) -> std::io::Result<()> {
+/// let mut upstream = TcpStream::connect("127.0.0.1:8080").await?;
+/// let (sent, received) = copy_bidirectional(&mut client, &mut upstream).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`TcpStream`]: actix_rt::net::TcpStream
+/// [`UnixStream`]: actix_rt::net::UnixStream
+pub async fn copy_bidirectional<A, B>(a: &mut A, b: &mut B) -> io::Result<(u64, u64)>
+where
+    A: ActixStream + 'static,
+    B: ActixStream + 'static,
+{
+    #[cfg(target_os = "linux")]
+    if let (Some(a), Some(b)) = (splice::Plain::of(&*a), splice::Plain::of(&*b)) {
+        return splice::copy_bidirectional(a, b).await;
+    }
+
+    tokio::io::copy_bidirectional(a, b).await
+}
+
+/// Returns true if [`copy_bidirectional`] moves data between `a` and `b` using `splice(2)`.
+pub fn is_spliced<A, B>(a: &A, b: &B) -> bool
+where
+    A: ActixStream + 'static,
+    B: ActixStream + 'static,
+{
+    #[cfg(target_os = "linux")]
+    {
+        splice::Plain::of(a).is_some() && splice::Plain::of(b).is_some()
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = (a, b);
+        false
+    }
+}
+
+/// Default size of the buffer of each direction of a [`Relay`].
+const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Bidirectional relay between two streams with per-direction idle timeouts.
+///
+/// Both directions are copied independently. When one side finishes writing, signalled by EOF
+/// when reading from it, the relay shuts down the write side of the other stream and keeps copying
+/// the opposite direction until it finishes too. This keeps protocols that half-close connections,
+/// e.g., database clients that close their side after sending a final request, working through
+/// proxies.
+///
+/// Shutdown is propagated through [`AsyncWrite::poll_shutdown`]. For TCP streams this sends a FIN
+/// and for TLS streams a `close_notify` alert. Conversely, TLS streams report a received
+/// `close_notify` as EOF, while TLS streams closed without one fail with an error that ends the
+/// relay.
+///
+/// # Examples
+/// ```no_run
+/// use std::time::Duration;
+///
+/// use actix_rt::net::TcpStream;
+/// use actix_utils::proxy::Relay;
+///
+/// # async fn proxy(mut client: TcpStream) -> std::io::Result<()> {
+/// let mut upstream = TcpStream::connect("127.0.0.1:5432").await?;
+///
+/// let (sent, received) = Relay::new()
+///     .a_to_b_idle_timeout(Duration::from_secs(300))
+///     .b_to_a_idle_timeout(Duration::from_secs(30))
+///     .run(&mut client, &mut upstream)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Relay {
+    a_to_b_idle: Option<Duration>,
+    b_to_a_idle: Option<Duration>,
+    buffer_size: usize,
+    clock: Arc<dyn Clock>,
+}
+
+impl Relay {
+    /// Constructs relay without idle timeouts that buffers up to 8KiB in each direction.
+    #[allow(clippy::new_without_default)]
+    pub fn new() -> Self {
+        Self {
+            a_to_b_idle: None,
+            b_to_a_idle: None,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            clock: Arc::new(RuntimeClock::new()),
+        }
+    }
+
+    /// Sets idle timeout of both directions.
+    pub fn idle_timeout(self, dur: Duration) -> Self {
+        self.a_to_b_idle_timeout(dur).b_to_a_idle_timeout(dur)
+    }
+
+    /// Sets time after which the relay fails if no data was copied from `a` to `b`.
+    ///
+    /// The timeout stops applying once the direction has finished.
+    pub fn a_to_b_idle_timeout(mut self, dur: Duration) -> Self {
+        self.a_to_b_idle = Some(dur);
+        self
+    }
+
+    /// Sets time after which the relay fails if no data was copied from `b` to `a`.
+    ///
+    /// The timeout stops applying once the direction has finished.
+    pub fn b_to_a_idle_timeout(mut self, dur: Duration) -> Self {
+        self.b_to_a_idle = Some(dur);
+        self
+    }
+
+    /// Sets size of the buffer of each direction.
+    ///
+    /// # Panics
+    /// Panics if `size` is zero.
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        assert!(size > 0, "buffer size must be non-zero");
+        self.buffer_size = size;
+        self
+    }
+
+    /// Sets the clock used to measure idle timeouts.
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Relays data between `a` and `b` until both directions have finished.
+    ///
+    /// Returns the number of bytes copied from `a` to `b` and from `b` to `a`, respectively.
+    ///
+    /// # Errors
+    /// Returns the first error encountered while reading from, writing to, or shutting down either
+    /// stream, or an error of kind [`TimedOut`](io::ErrorKind::TimedOut) if a direction was idle
+    /// longer than its timeout.
+    pub async fn run<A, B>(&self, a: &mut A, b: &mut B) -> io::Result<(u64, u64)>
+    where
+        A: AsyncRead + AsyncWrite + Unpin + ?Sized,
+        B: AsyncRead + AsyncWrite + Unpin + ?Sized,
+    {
+        let mut a_to_b = Direction::new(self, self.a_to_b_idle);
+        let mut b_to_a = Direction::new(self, self.b_to_a_idle);
+
+        poll_fn(|cx| {
+            let a_to_b = a_to_b.poll(cx, &*self.clock, Pin::new(&mut *a), Pin::new(&mut *b))?;
+            let b_to_a = b_to_a.poll(cx, &*self.clock, Pin::new(&mut *b), Pin::new(&mut *a))?;
+
+            match (a_to_b, b_to_a) {
+                (Poll::Ready(a_to_b), Poll::Ready(b_to_a)) => Poll::Ready(Ok((a_to_b, b_to_a))),
+                _ => Poll::Pending,
+            }
+        })
+        .await
+    }
+}
+
+impl fmt::Debug for Relay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Relay")
+            .field("a_to_b_idle", &self.a_to_b_idle)
+            .field("b_to_a_idle", &self.b_to_a_idle)
+            .field("buffer_size", &self.buffer_size)
+            .finish_non_exhaustive()
+    }
+}
+
+/// One direction of a [`Relay`].
+struct Direction {
+    buf: Box<[u8]>,
+    pos: usize,
+    cap: usize,
+    read_done: bool,
+    need_flush: bool,
+    state: DirectionState,
+    transferred: u64,
+    idle: Option<Duration>,
+    deadline: Option<Instant>,
+    timer: Option<Pin<Box<Sleep>>>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum DirectionState {
+    Copying,
+    ShuttingDown,
+    Done,
+}
+
+impl Direction {
+    fn new(relay: &Relay, idle: Option<Duration>) -> Self {
+        Self {
+            buf: vec![0; relay.buffer_size].into_boxed_slice(),
+            pos: 0,
+            cap: 0,
+            read_done: false,
+            need_flush: false,
+            state: DirectionState::Copying,
+            transferred: 0,
+            idle,
+            deadline: idle.map(|idle| relay.clock.now() + idle),
+            timer: None,
+        }
+    }
+
+    /// Polls copying and shutdown, then checks the idle timeout if still pending.
+    fn poll<R, W>(
+        &mut self,
+        cx: &mut Context<'_>,
+        clock: &dyn Clock,
+        reader: Pin<&mut R>,
+        writer: Pin<&mut W>,
+    ) -> io::Result<Poll<u64>>
+    where
+        R: AsyncRead + ?Sized,
+        W: AsyncWrite + ?Sized,
+    {
+        let before = self.transferred;
+        let res = self.poll_transfer(cx, reader, writer)?;
+
+        if self.state == DirectionState::Done {
+            self.timer = None;
+            return Ok(Poll::Ready(self.transferred));
+        }
+
+        let idle = match self.idle {
+            Some(idle) => idle,
+            None => return Ok(res.map(|()| self.transferred)),
+        };
+
+        if self.transferred != before {
+            self.deadline = Some(clock.now() + idle);
+        }
+
+        let deadline = self
+            .deadline
+            .expect("deadline is set along with idle timeout");
+
+        let timer = match &mut self.timer {
+            Some(timer) if timer.deadline() == deadline => timer,
+            timer => timer.insert(Box::pin(clock.sleep_until(deadline))),
+        };
+
+        match timer.as_mut().poll(cx) {
+            Poll::Ready(()) => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "relay direction idle for too long",
+            )),
+            Poll::Pending => Ok(Poll::Pending),
+        }
+    }
+
+    fn poll_transfer<R, W>(
+        &mut self,
+        cx: &mut Context<'_>,
+        mut reader: Pin<&mut R>,
+        mut writer: Pin<&mut W>,
+    ) -> io::Result<Poll<()>>
+    where
+        R: AsyncRead + ?Sized,
+        W: AsyncWrite + ?Sized,
+    {
+        loop {
+            match self.state {
+                DirectionState::Copying => {}
+
+                DirectionState::ShuttingDown => match writer.as_mut().poll_shutdown(cx)? {
+                    Poll::Ready(()) => {
+                        self.state = DirectionState::Done;
+                        return Ok(Poll::Ready(()));
+                    }
+                    Poll::Pending => return Ok(Poll::Pending),
+                },
+
+                DirectionState::Done => return Ok(Poll::Ready(())),
+            }
+
+            if self.pos == self.cap && !self.read_done {
+                let mut buf = ReadBuf::new(&mut self.buf);
+
+                if reader.as_mut().poll_read(cx, &mut buf)?.is_pending() {
+                    // flush written data while waiting for more
+                    if self.need_flush && writer.as_mut().poll_flush(cx)?.is_ready() {
+                        self.need_flush = false;
+                    }
+
+                    return Ok(Poll::Pending);
+                }
+
+                match buf.filled().len() {
+                    0 => self.read_done = true,
+                    n => {
+                        self.pos = 0;
+                        self.cap = n;
+                    }
+                }
+            }
+
+            while self.pos < self.cap {
+                let n = match writer
+                    .as_mut()
+                    .poll_write(cx, &self.buf[self.pos..self.cap])?
+                {
+                    Poll::Ready(n) => n,
+                    Poll::Pending => return Ok(Poll::Pending),
+                };
+
+                if n == 0 {
+                    return Err(io::ErrorKind::WriteZero.into());
+                }
+
+                self.pos += n;
+                self.transferred += n as u64;
+                self.need_flush = true;
+            }
+
+            if self.read_done {
+                // shutdown flushes the writer
+                self.state = DirectionState::ShuttingDown;
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod splice {
+    use std::{
+        io,
+        os::unix::io::{AsRawFd as _, FromRawFd as _, OwnedFd, RawFd},
+    };
+
+    use actix_rt::net::{TcpStream, UnixStream};
+    use tokio::io::Interest;
+
+    use super::Any;
+    use crate::future::try_join;
+
+    /// Maximum number of bytes moved by one `splice` call, the default capacity of pipes.
+    const CHUNK_SIZE: usize = 64 * 1024;
+
+    /// Stream that can be spliced to and from, borrowed from a caller's stream.
+    #[derive(Clone, Copy)]
+    pub(super) enum Plain<'a> {
+        Tcp(&'a TcpStream),
+        Uds(&'a UnixStream),
+    }
+
+    impl<'a> Plain<'a> {
+        pub(super) fn of<S: Any>(stream: &'a S) -> Option<Self> {
+            let stream = stream as &dyn Any;
+
+            if let Some(stream) = stream.downcast_ref::<TcpStream>() {
+                return Some(Self::Tcp(stream));
+            }
+
+            stream.downcast_ref::<UnixStream>().map(Self::Uds)
+        }
+
+        fn fd(self) -> RawFd {
+            match self {
+                Self::Tcp(stream) => stream.as_raw_fd(),
+                Self::Uds(stream) => stream.as_raw_fd(),
+            }
+        }
+
+        /// Runs `f` until it does not fail with `WouldBlock`, waiting for `interest` in between.
+        async fn io<R>(
+            self,
+            interest: Interest,
+            mut f: impl FnMut() -> io::Result<R>,
+        ) -> io::Result<R> {
+            loop {
+                let res = match self {
+                    Self::Tcp(stream) => {
+                        stream.ready(interest).await?;
+                        stream.try_io(interest, &mut f)
+                    }
+                    Self::Uds(stream) => {
+                        stream.ready(interest).await?;
+                        stream.try_io(interest, &mut f)
+                    }
+                };
+
+                match res {
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => continue,
+                    res => return res,
+                }
+            }
+        }
+
+        fn shutdown_write(self) -> io::Result<()> {
+            // SAFETY: calling `shutdown` does not invalidate the file descriptor
+            if unsafe { libc::shutdown(self.fd(), libc::SHUT_WR) } != 0 {
+                let err = io::Error::last_os_error();
+
+                // peer may have closed the connection already
+                if err.kind() != io::ErrorKind::NotConnected {
+                    return Err(err);
+                }
+            }
+
+            Ok(())
+        }
+    }
+
+    /// Non-blocking pipe holding data in transit between two sockets.
+    struct Pipe {
+        read: OwnedFd,
+        write: OwnedFd,
+    }
+
+    impl Pipe {
+        fn new() -> io::Result<Self> {
+            let mut fds = [0; 2];
+
+            // SAFETY: `fds` is valid for writing two file descriptors
+            if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_NONBLOCK | libc::O_CLOEXEC) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            // SAFETY: file descriptors were just opened and are owned by nothing else
+            Ok(unsafe {
+                Self {
+                    read: OwnedFd::from_raw_fd(fds[0]),
+                    write: OwnedFd::from_raw_fd(fds[1]),
+                }
+            })
+        }
+    }
+
+    fn splice(from: RawFd, to: RawFd, len: usize) -> io::Result<usize> {
+        // SAFETY: null offsets are valid for sockets and pipes and file descriptors are open for
+        // the duration of the call
+        let ret = unsafe {
+            libc::splice(
+                from,
+                std::ptr::null_mut(),
+                to,
+                std::ptr::null_mut(),
+                len,
+                libc::SPLICE_F_MOVE | libc::SPLICE_F_NONBLOCK,
+            )
+        };
+
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(ret as usize)
+    }
+
+    pub(super) async fn copy_bidirectional(a: Plain<'_>, b: Plain<'_>) -> io::Result<(u64, u64)> {
+        try_join(copy(a, b), copy(b, a)).await
+    }
+
+    /// Moves data from `src` to `dst` through a pipe until `src` reaches EOF.
+    async fn copy(src: Plain<'_>, dst: Plain<'_>) -> io::Result<u64> {
+        let pipe = Pipe::new()?;
+        let mut total = 0;
+
+        loop {
+            // pipe is always drained before being filled, so `WouldBlock` is caused by `src`
+            let n = src
+                .io(Interest::READABLE, || {
+                    splice(src.fd(), pipe.write.as_raw_fd(), CHUNK_SIZE)
+                })
+                .await?;
+
+            if n == 0 {
+                dst.shutdown_write()?;
+                return Ok(total);
+            }
+
+            // pipe is not empty, so `WouldBlock` is caused by `dst`
+            let mut pending = n;
+            while pending > 0 {
+                pending -= dst
+                    .io(Interest::WRITABLE, || {
+                        splice(pipe.read.as_raw_fd(), dst.fd(), pending)
+                    })
+                    .await?;
+            }
+
+            total += n as u64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_rt::net::{TcpListener, TcpStream};
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    use super::*;
+    use crate::{clock::MockClock, duplex::duplex};
+
+    /// Returns connected client and server streams.
+    async fn tcp_pair() -> (TcpStream, TcpStream) {
+        let lst = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let client = TcpStream::connect(lst.local_addr().unwrap()).await.unwrap();
+        let (server, _) = lst.accept().await.unwrap();
+        (client, server)
+    }
+
+    /// Sends `req` to `stream`, half-closes it, and returns the response.
+    async fn exchange(mut stream: impl ActixStream, req: &[u8]) -> Vec<u8> {
+        stream.write_all(req).await.unwrap();
+        stream.shutdown().await.unwrap();
+
+        let mut res = Vec::new();
+        stream.read_to_end(&mut res).await.unwrap();
+        res
+    }
+
+    /// Reads the request until EOF and answers with it in reverse.
+    async fn reverse(mut stream: impl ActixStream) {
+        let mut req = Vec::new();
+        stream.read_to_end(&mut req).await.unwrap();
+        req.reverse();
+        stream.write_all(&req).await.unwrap();
+        stream.shutdown().await.unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn splices_tcp_streams() {
+        let (client, mut inbound) = tcp_pair().await;
+        let (mut outbound, upstream) = tcp_pair().await;
+
+        #[cfg(target_os = "linux")]
+        assert!(is_spliced(&inbound, &outbound));
+
+        let upstream = actix_rt::spawn(reverse(upstream));
+        let proxy =
+            actix_rt::spawn(async move { copy_bidirectional(&mut inbound, &mut outbound).await });
+
+        // more than fits a pipe to exercise draining it in several calls
+        let req = (0..200_000).map(|i| i as u8).collect::<Vec<_>>();
+        let mut res = exchange(client, &req).await;
+        res.reverse();
+        assert_eq!(res, req);
+
+        upstream.await.unwrap();
+        assert_eq!(proxy.await.unwrap().unwrap(), (200_000, 200_000));
+    }
+
+    #[actix_rt::test]
+    async fn copies_other_streams() {
+        let (client, mut inbound) = duplex(1024);
+        let (mut outbound, upstream) = tcp_pair().await;
+
+        assert!(!is_spliced(&inbound, &outbound));
+
+        let upstream = actix_rt::spawn(reverse(upstream));
+        let proxy =
+            actix_rt::spawn(async move { copy_bidirectional(&mut inbound, &mut outbound).await });
+
+        assert_eq!(exchange(client, b"hello").await, b"olleh");
+
+        upstream.await.unwrap();
+        assert_eq!(proxy.await.unwrap().unwrap(), (5, 5));
+    }
+
+    #[actix_rt::test]
+    async fn relay_propagates_half_close() {
+        let (client, mut inbound) = duplex(1024);
+        let (mut outbound, upstream) = duplex(1024);
+
+        let upstream = actix_rt::spawn(reverse(upstream));
+        let relay =
+            actix_rt::spawn(async move { Relay::new().run(&mut inbound, &mut outbound).await });
+
+        // upstream only answers after the client has half-closed its side
+        assert_eq!(exchange(client, b"hello").await, b"olleh");
+
+        upstream.await.unwrap();
+        assert_eq!(relay.await.unwrap().unwrap(), (5, 5));
+    }
+
+    #[actix_rt::test]
+    async fn relay_idle_timeouts() {
+        let clock = MockClock::new();
+        let (mut client, mut inbound) = duplex(1024);
+        let (mut outbound, mut upstream) = duplex(1024);
+
+        let relay = Relay::new()
+            .idle_timeout(Duration::from_secs(10))
+            .buffer_size(4)
+            .clock(clock.clone());
+        let relay = actix_rt::spawn(async move { relay.run(&mut inbound, &mut outbound).await });
+
+        // finished directions are exempt from their timeout
+        client.write_all(b"hello").await.unwrap();
+        client.shutdown().await.unwrap();
+        let mut req = Vec::new();
+        upstream.read_to_end(&mut req).await.unwrap();
+        assert_eq!(req, b"hello");
+
+        // copying data resets the timeout of its direction
+        clock.advance(Duration::from_secs(5));
+        upstream.write_all(b"ok").await.unwrap();
+        let mut res = [0; 2];
+        client.read_exact(&mut res).await.unwrap();
+
+        clock.advance(Duration::from_secs(6));
+        actix_rt::task::yield_now().await;
+        assert!(!relay.is_finished());
+
+        clock.advance(Duration::from_secs(4));
+        let err = relay.await.unwrap().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+}
@@ -0,0 +1,813 @@
+//! Recording and replaying of stream traffic.
+//!
+//! [`Recorder`] wraps a stream and records all bytes read from and written to it along with the
+//! time at which they were transferred. The resulting [`Recording`] can be saved in a simple framed
+//! log format using [`Recording::encode_to`] and loaded again using [`Recording::decode_from`],
+//! for example, to capture traffic of a misbehaving connection in production.
+//!
+//! [`Replay`] feeds a recording back to code under test, making it possible to reproduce
+//! protocol-level bugs from captured traffic.
+//!
+//! # Log Format
+//! A log starts with the magic bytes `ACTXREC\0`, followed by a version byte (currently `1`) and a
+//! flags byte, where the lowest bit is set if the recording was truncated. Each event follows as:
+//! - direction byte: `0` for data read from the stream, `1` for data written to it;
+//! - time since recording started in microseconds, as big-endian `u64`;
+//! - data length, as big-endian `u32`;
+//! - data.
+//!
+//! A read event with no data marks the end of the stream.
+
+use core::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+use std::{
+    collections::VecDeque,
+    io::{self, Read as _},
+    sync::{Arc, Mutex},
+};
+
+use actix_rt::{
+    net::{ActixStream, Ready},
+    time::Instant,
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::clock::{Clock, RuntimeClock, Sleep};
+
+const MAGIC: &[u8; 8] = b"ACTXREC\0";
+const VERSION: u8 = 1;
+const FLAG_TRUNCATED: u8 = 0b1;
+
+/// Direction of recorded data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Data read from the stream.
+    Read,
+
+    /// Data written to the stream.
+    Write,
+}
+
+/// Transfer of data in a [`Recording`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    direction: Direction,
+    elapsed: Duration,
+    data: Vec<u8>,
+}
+
+impl Event {
+    /// Constructs event transferring `data` in `direction`, `elapsed` after recording started.
+    pub fn new(direction: Direction, elapsed: Duration, data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            direction,
+            elapsed,
+            data: data.into(),
+        }
+    }
+
+    /// Returns direction of the transfer.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// Returns time between start of the recording and the transfer.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    /// Returns transferred data.
+    ///
+    /// Empty for read events marking the end of the stream.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+/// Sequence of recorded transfers on a stream.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Recording {
+    events: Vec<Event>,
+    truncated: bool,
+}
+
+impl Recording {
+    /// Constructs empty recording.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends event to recording.
+    pub fn push(&mut self, event: Event) {
+        self.events.push(event);
+    }
+
+    /// Returns recorded events in order.
+    pub fn events(&self) -> &[Event] {
+        &self.events
+    }
+
+    /// Returns true if recording stopped early because its size limit was reached.
+    ///
+    /// See [`Recorder::limit`].
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+
+    /// Returns all data transferred in `direction`, concatenated.
+    pub fn data(&self, direction: Direction) -> Vec<u8> {
+        self.events
+            .iter()
+            .filter(|ev| ev.direction == direction)
+            .flat_map(|ev| ev.data.iter().copied())
+            .collect()
+    }
+
+    /// Writes recording to `w` in the framed log format.
+    ///
+    /// See the [module documentation](self) for a description of the format.
+    pub fn encode_to(&self, mut w: impl io::Write) -> io::Result<()> {
+        let flags = if self.truncated { FLAG_TRUNCATED } else { 0 };
+
+        w.write_all(MAGIC)?;
+        w.write_all(&[VERSION, flags])?;
+
+        for ev in &self.events {
+            let len = u32::try_from(ev.data.len())
+                .map_err(|_| invalid_data("event data too large to encode"))?;
+            let elapsed = u64::try_from(ev.elapsed.as_micros()).unwrap_or(u64::MAX);
+
+            let direction = match ev.direction {
+                Direction::Read => 0,
+                Direction::Write => 1,
+            };
+
+            w.write_all(&[direction])?;
+            w.write_all(&elapsed.to_be_bytes())?;
+            w.write_all(&len.to_be_bytes())?;
+            w.write_all(&ev.data)?;
+        }
+
+        w.flush()
+    }
+
+    /// Reads recording in the framed log format from `r`.
+    ///
+    /// # Errors
+    /// Returns error of kind [`InvalidData`](io::ErrorKind::InvalidData) if the log is malformed
+    /// and propagates errors from `r`.
+    pub fn decode_from(mut r: impl io::Read) -> io::Result<Self> {
+        let mut header = [0; 10];
+        r.read_exact(&mut header)?;
+
+        if &header[..8] != MAGIC {
+            return Err(invalid_data("not a recording"));
+        }
+
+        if header[8] != VERSION {
+            return Err(invalid_data("unsupported recording version"));
+        }
+
+        let mut rec = Recording {
+            events: Vec::new(),
+            truncated: header[9] & FLAG_TRUNCATED != 0,
+        };
+
+        loop {
+            let mut direction = [0];
+            if r.read(&mut direction)? == 0 {
+                return Ok(rec);
+            }
+
+            let direction = match direction[0] {
+                0 => Direction::Read,
+                1 => Direction::Write,
+                _ => return Err(invalid_data("invalid event direction")),
+            };
+
+            let mut frame = [0; 12];
+            r.read_exact(&mut frame)?;
+
+            let elapsed = u64::from_be_bytes(frame[..8].try_into().unwrap());
+            let len = u32::from_be_bytes(frame[8..].try_into().unwrap());
+
+            let mut data = Vec::new();
+            let read = r.by_ref().take(len.into()).read_to_end(&mut data)?;
+            if read != len as usize {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            rec.push(Event::new(direction, Duration::from_micros(elapsed), data));
+        }
+    }
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Stream wrapper recording all transferred data.
+///
+/// The recording can be accessed through a [`RecordingHandle`], which remains usable after the
+/// recorder has been dropped, e.g., after a service has closed the connection.
+///
+/// # Examples
+/// ```
+/// use actix_utils::{
+///     duplex::duplex,
+///     record::{Direction, Recorder},
+/// };
+/// use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+///
+/// # actix_rt::System::new().block_on(async {
+/// let (mut client, server) = duplex(1024);
+///
+/// let mut server = Recorder::new(server);
+/// let handle = server.handle();
+///
+/// client.write_all(b"ping").await.unwrap();
+/// let mut buf = [0; 4];
+/// server.read_exact(&mut buf).await.unwrap();
+/// server.write_all(b"pong").await.unwrap();
+/// drop(server);
+///
+/// let rec = handle.snapshot();
+/// assert_eq!(rec.data(Direction::Read), b"ping");
+/// assert_eq!(rec.data(Direction::Write), b"pong");
+///
+/// let mut log = Vec::new();
+/// rec.encode_to(&mut log).unwrap();
+/// # });
+/// ```
+pub struct Recorder<IO> {
+    io: IO,
+    clock: Arc<dyn Clock>,
+    start: Instant,
+    limit: Option<usize>,
+    handle: RecordingHandle,
+}
+
+impl<IO> Recorder<IO> {
+    /// Wraps `io`, recording transfers from now on.
+    pub fn new(io: IO) -> Self {
+        Self::with_clock(io, RuntimeClock::new())
+    }
+
+    /// Wraps `io`, timestamping transfers using `clock`.
+    pub fn with_clock(io: IO, clock: impl Clock + 'static) -> Self {
+        let start = clock.now();
+
+        Self {
+            io,
+            clock: Arc::new(clock),
+            start,
+            limit: None,
+            handle: RecordingHandle::default(),
+        }
+    }
+
+    /// Sets maximum number of data bytes to record.
+    ///
+    /// Once a transfer would exceed the limit, recording stops and the recording is marked as
+    /// [truncated](Recording::is_truncated). By default, recording size is unlimited.
+    pub fn limit(mut self, max_bytes: usize) -> Self {
+        self.limit = Some(max_bytes);
+        self
+    }
+
+    /// Returns handle to the recording.
+    pub fn handle(&self) -> RecordingHandle {
+        self.handle.clone()
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &IO {
+        &self.io
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    ///
+    /// Transfers made directly on the underlying stream are not recorded.
+    pub fn get_mut(&mut self) -> &mut IO {
+        &mut self.io
+    }
+
+    /// Returns the underlying stream and the recording so far.
+    pub fn into_parts(self) -> (IO, Recording) {
+        let rec = self.handle.snapshot();
+        (self.io, rec)
+    }
+
+    fn record(&self, direction: Direction, data: &[u8]) {
+        let mut inner = self.handle.inner.lock().unwrap();
+
+        if inner.rec.truncated {
+            return;
+        }
+
+        if let Some(limit) = self.limit {
+            if inner.size + data.len() > limit {
+                inner.rec.truncated = true;
+                return;
+            }
+        }
+
+        inner.size += data.len();
+
+        let elapsed = self.clock.now().saturating_duration_since(self.start);
+        inner.rec.push(Event::new(direction, elapsed, data));
+    }
+}
+
+impl<IO: fmt::Debug> fmt::Debug for Recorder<IO> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Recorder")
+            .field("io", &self.io)
+            .field("limit", &self.limit)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<IO: AsyncRead + Unpin> AsyncRead for Recorder<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        let filled = buf.filled().len();
+        let res = Pin::new(&mut this.io).poll_read(cx, buf);
+
+        // an empty read into non-empty buffer marks the end of the stream
+        if let Poll::Ready(Ok(())) = res {
+            if buf.filled().len() > filled || buf.remaining() > 0 {
+                this.record(Direction::Read, &buf.filled()[filled..]);
+            }
+        }
+
+        res
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> AsyncWrite for Recorder<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        let res = Pin::new(&mut this.io).poll_write(cx, buf);
+
+        if let Poll::Ready(Ok(n)) = res {
+            if n > 0 {
+                this.record(Direction::Write, &buf[..n]);
+            }
+        }
+
+        res
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+impl<IO: ActixStream> ActixStream for Recorder<IO> {
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        IO::poll_read_ready(&self.io, cx)
+    }
+
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        IO::poll_write_ready(&self.io, cx)
+    }
+}
+
+/// Shared handle to the recording of a [`Recorder`].
+#[derive(Clone, Default)]
+pub struct RecordingHandle {
+    inner: Arc<Mutex<HandleInner>>,
+}
+
+#[derive(Default)]
+struct HandleInner {
+    rec: Recording,
+    size: usize,
+}
+
+impl RecordingHandle {
+    /// Returns copy of the recording so far.
+    pub fn snapshot(&self) -> Recording {
+        self.inner.lock().unwrap().rec.clone()
+    }
+
+    /// Returns number of data bytes recorded so far.
+    pub fn size(&self) -> usize {
+        self.inner.lock().unwrap().size
+    }
+}
+
+impl fmt::Debug for RecordingHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let inner = self.inner.lock().unwrap();
+
+        f.debug_struct("RecordingHandle")
+            .field("events", &inner.rec.events.len())
+            .field("size", &inner.size)
+            .field("truncated", &inner.rec.truncated)
+            .finish()
+    }
+}
+
+/// Stream feeding a [`Recording`] back to code under test.
+///
+/// Reads return recorded read data, chunked as it was recorded, followed by end of stream. To
+/// preserve the order of the recorded conversation, read data only becomes available once the
+/// data written before it in the recording has been written to the replay. Writes are accepted
+/// and collected; see [`written`](Self::written) and [`verify_writes`](Self::verify_writes).
+///
+/// By default, recorded timing is ignored. Use [`with_clock`](Self::with_clock) to delay reads
+/// until the time they were recorded at.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+///
+/// use actix_utils::record::{Direction, Event, Recording, Replay};
+/// use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+///
+/// # actix_rt::System::new().block_on(async {
+/// let mut rec = Recording::new();
+/// rec.push(Event::new(Direction::Read, Duration::ZERO, "ping"));
+/// rec.push(Event::new(Direction::Write, Duration::ZERO, "pong"));
+///
+/// let mut io = Replay::new(rec).verify_writes(true);
+///
+/// let mut buf = [0; 4];
+/// io.read_exact(&mut buf).await.unwrap();
+/// assert_eq!(&buf, b"ping");
+///
+/// io.write_all(b"pong").await.unwrap();
+/// assert!(io.is_finished());
+/// # });
+/// ```
+pub struct Replay {
+    state: Mutex<ReplayState>,
+}
+
+struct ReplayState {
+    events: VecDeque<Event>,
+
+    /// Number of bytes of the first event already transferred.
+    offset: usize,
+
+    written: Vec<u8>,
+    verify: bool,
+    timing: Option<(Arc<dyn Clock>, Instant)>,
+    sleep: Option<Pin<Box<Sleep>>>,
+    read_waker: Option<Waker>,
+}
+
+impl Replay {
+    /// Constructs replay of `rec`, ignoring recorded timing.
+    pub fn new(rec: Recording) -> Self {
+        Self {
+            state: Mutex::new(ReplayState {
+                events: rec.events.into(),
+                offset: 0,
+                written: Vec::new(),
+                verify: false,
+                timing: None,
+                sleep: None,
+                read_waker: None,
+            }),
+        }
+    }
+
+    /// Constructs replay of `rec` that makes read data available on `clock` at the time it was
+    /// recorded at, relative to now.
+    pub fn with_clock(rec: Recording, clock: impl Clock + 'static) -> Self {
+        let replay = Self::new(rec);
+
+        {
+            let mut state = replay.state.lock().unwrap();
+            let start = clock.now();
+            state.timing = Some((Arc::new(clock), start));
+        }
+
+        replay
+    }
+
+    /// Sets whether written data must match recorded written data.
+    ///
+    /// When enabled, writes that diverge from the recording fail with an error of kind
+    /// [`InvalidData`](io::ErrorKind::InvalidData). Disabled by default.
+    pub fn verify_writes(self, verify: bool) -> Self {
+        self.state.lock().unwrap().verify = verify;
+        self
+    }
+
+    /// Returns all data written to the replay so far.
+    pub fn written(&self) -> Vec<u8> {
+        self.state.lock().unwrap().written.clone()
+    }
+
+    /// Returns true if all recorded events have been replayed.
+    pub fn is_finished(&self) -> bool {
+        self.state.lock().unwrap().events.is_empty()
+    }
+
+    fn poll_read_inner(
+        &self,
+        cx: &mut Context<'_>,
+        buf: Option<&mut ReadBuf<'_>>,
+    ) -> Poll<io::Result<()>> {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+
+        let ev = match state.events.front() {
+            None => return Poll::Ready(Ok(())),
+            Some(ev) if ev.direction == Direction::Write => {
+                // wait for code under test to write recorded data first
+                state.read_waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+            Some(ev) => ev,
+        };
+
+        if let Some((clock, start)) = &state.timing {
+            let deadline = *start + ev.elapsed;
+
+            if clock.now() < deadline {
+                let sleep = match &mut state.sleep {
+                    Some(sleep) if sleep.deadline() == deadline => sleep,
+                    sleep => sleep.insert(Box::pin(clock.sleep_until(deadline))),
+                };
+
+                if sleep.as_mut().poll(cx).is_pending() {
+                    return Poll::Pending;
+                }
+            }
+
+            state.sleep = None;
+        }
+
+        let buf = match buf {
+            Some(buf) => buf,
+            None => return Poll::Ready(Ok(())),
+        };
+
+        let data = &ev.data[state.offset..];
+        let n = data.len().min(buf.remaining());
+        buf.put_slice(&data[..n]);
+        state.offset += n;
+
+        if state.offset == ev.data.len() {
+            state.events.pop_front();
+            state.offset = 0;
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn write_inner(&self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+
+        let mut rest = buf;
+
+        while !rest.is_empty() {
+            let ev = match state.events.front() {
+                Some(ev) if ev.direction == Direction::Write => ev,
+                _ if state.verify => return Err(invalid_data("write not in recording")),
+                _ => break,
+            };
+
+            let expected = &ev.data[state.offset..];
+            let n = expected.len().min(rest.len());
+
+            if state.verify && expected[..n] != rest[..n] {
+                return Err(invalid_data("written data diverges from recording"));
+            }
+
+            rest = &rest[n..];
+            state.offset += n;
+
+            if state.offset == ev.data.len() {
+                state.events.pop_front();
+                state.offset = 0;
+            }
+        }
+
+        state.written.extend_from_slice(buf);
+
+        if let Some(waker) = state.read_waker.take() {
+            waker.wake();
+        }
+
+        Ok(buf.len())
+    }
+}
+
+impl fmt::Debug for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.lock().unwrap();
+
+        f.debug_struct("Replay")
+            .field("remaining_events", &state.events.len())
+            .field("written", &state.written.len())
+            .field("verify_writes", &state.verify)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AsyncRead for Replay {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        self.poll_read_inner(cx, Some(buf))
+    }
+}
+
+impl AsyncWrite for Replay {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Poll::Ready(self.write_inner(buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl ActixStream for Replay {
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        self.poll_read_inner(cx, None).map_ok(|()| Ready::READABLE)
+    }
+
+    fn poll_write_ready(&self, _: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        Poll::Ready(Ok(Ready::WRITABLE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use static_assertions::assert_impl_all;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    use super::*;
+    use crate::{clock::MockClock, duplex::duplex};
+
+    assert_impl_all!(Recorder<crate::duplex::DuplexStream>: Send, ActixStream);
+    assert_impl_all!(RecordingHandle: Send, Sync, Clone);
+    assert_impl_all!(Replay: Send, Sync, ActixStream);
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[actix_rt::test]
+    async fn records_transfers() {
+        let clock = MockClock::new();
+        let (mut client, server) = duplex(1024);
+
+        let mut server = Recorder::with_clock(server, clock.clone());
+        let handle = server.handle();
+
+        client.write_all(b"hello").await.unwrap();
+        let mut buf = [0; 5];
+        server.read_exact(&mut buf).await.unwrap();
+
+        clock.advance(ms(20));
+        server.write_all(b"world").await.unwrap();
+
+        drop(client);
+        assert_eq!(server.read(&mut buf).await.unwrap(), 0);
+
+        let (_, rec) = server.into_parts();
+        assert_eq!(rec, handle.snapshot());
+        assert_eq!(
+            rec.events(),
+            [
+                Event::new(Direction::Read, Duration::ZERO, "hello"),
+                Event::new(Direction::Write, ms(20), "world"),
+                Event::new(Direction::Read, ms(20), ""),
+            ]
+        );
+    }
+
+    #[actix_rt::test]
+    async fn truncates_at_limit() {
+        let (client, mut server) = duplex(1024);
+
+        let mut client = Recorder::new(client).limit(8);
+        let handle = client.handle();
+
+        client.write_all(b"hello").await.unwrap();
+        client.write_all(b"world").await.unwrap();
+        client.write_all(b"!").await.unwrap();
+
+        let mut buf = [0; 11];
+        server.read_exact(&mut buf).await.unwrap();
+
+        let rec = handle.snapshot();
+        assert!(rec.is_truncated());
+        assert_eq!(rec.data(Direction::Write), b"hello");
+        assert_eq!(handle.size(), 5);
+    }
+
+    #[test]
+    fn encode_decode_roundtrip() {
+        let mut rec = Recording::new();
+        rec.push(Event::new(Direction::Read, ms(1), "GET / HTTP/1.1\r\n\r\n"));
+        rec.push(Event::new(Direction::Write, ms(3), "HTTP/1.1 200 OK\r\n"));
+        rec.push(Event::new(Direction::Read, ms(7), ""));
+        rec.truncated = true;
+
+        let mut log = Vec::new();
+        rec.encode_to(&mut log).unwrap();
+        assert_eq!(Recording::decode_from(&log[..]).unwrap(), rec);
+
+        let err = Recording::decode_from(&log[..log.len() - 1]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+
+        log[0] = b'X';
+        let err = Recording::decode_from(&log[..]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[actix_rt::test]
+    async fn replays_in_recorded_order() {
+        let mut rec = Recording::new();
+        rec.push(Event::new(Direction::Read, Duration::ZERO, "ping"));
+        rec.push(Event::new(Direction::Write, Duration::ZERO, "pong"));
+        rec.push(Event::new(Direction::Read, Duration::ZERO, "bye"));
+
+        let mut io = Replay::new(rec.clone());
+
+        let mut buf = [0; 8];
+        assert_eq!(io.read(&mut buf).await.unwrap(), 4);
+        assert_eq!(&buf[..4], b"ping");
+
+        // next read waits for recorded write
+        let mut cx = Context::from_waker(futures_util::task::noop_waker_ref());
+        let mut read_buf = ReadBuf::new(&mut buf);
+        assert!(Pin::new(&mut io)
+            .poll_read(&mut cx, &mut read_buf)
+            .is_pending());
+
+        io.write_all(b"po").await.unwrap();
+        io.write_all(b"ng").await.unwrap();
+        assert_eq!(io.read(&mut buf).await.unwrap(), 3);
+        assert_eq!(&buf[..3], b"bye");
+        assert_eq!(io.read(&mut buf).await.unwrap(), 0);
+
+        assert!(io.is_finished());
+        assert_eq!(io.written(), b"pong");
+
+        let mut io = Replay::new(rec).verify_writes(true);
+        io.read_exact(&mut buf[..4]).await.unwrap();
+        let err = io.write_all(b"pang").await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[actix_rt::test]
+    async fn replays_with_timing() {
+        let clock = MockClock::new();
+
+        let mut rec = Recording::new();
+        rec.push(Event::new(Direction::Read, ms(100), "late"));
+
+        let mut io = Replay::with_clock(rec, clock.clone());
+
+        let reader = actix_rt::spawn(async move {
+            let mut buf = String::new();
+            io.read_to_string(&mut buf).await.unwrap();
+            buf
+        });
+
+        actix_rt::task::yield_now().await;
+        assert_eq!(clock.pending_timers(), 1);
+
+        clock.advance(ms(100));
+        assert_eq!(reader.await.unwrap(), "late");
+    }
+}
@@ -0,0 +1,455 @@
+//! Fair, single-threaded async semaphore.
+//!
+//! Unlike [`Counter`](crate::counter::Counter), which can only wake the last task that observed it
+//! at capacity, [`Semaphore`] keeps a FIFO queue of waiting tasks and hands released permits to
+//! them in order.
+
+use core::{
+    cell::Cell,
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use std::{error::Error, rc::Rc};
+
+use crate::wait_queue::{WaitQueue, Waiter};
+
+/// Fair async semaphore for tasks running on the same thread.
+///
+/// Permits are handed out in the order they were requested; once a task is waiting, new
+/// acquisitions queue up behind it even if a permit is released in the meantime. The semaphore
+/// can be cloned cheaply, with all clones sharing the same permits.
+///
+/// # Examples
+/// ```
+/// # actix_rt::System::new().block_on(async {
+/// use actix_utils::semaphore::Semaphore;
+///
+/// let sem = Semaphore::new(1);
+///
+/// let permit = sem.acquire_owned().await.unwrap();
+/// assert_eq!(sem.available_permits(), 0);
+/// assert!(sem.try_acquire_owned().is_err());
+///
+/// drop(permit);
+/// assert!(sem.try_acquire_owned().is_ok());
+/// # });
+/// ```
+#[derive(Clone)]
+pub struct Semaphore(Rc<Inner>);
+
+struct Inner {
+    permits: Cell<usize>,
+    closed: Cell<bool>,
+    waiters: WaitQueue<Acquired>,
+}
+
+/// Outcome handed to a queued acquisition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Acquired {
+    Granted,
+    Closed,
+}
+
+impl Semaphore {
+    /// Constructs new semaphore with the given number of permits.
+    pub fn new(permits: usize) -> Self {
+        Self(Rc::new(Inner {
+            permits: Cell::new(permits),
+            closed: Cell::new(false),
+            waiters: WaitQueue::new(),
+        }))
+    }
+
+    /// Returns the number of permits available for immediate acquisition.
+    pub fn available_permits(&self) -> usize {
+        self.0.permits.get()
+    }
+
+    /// Returns the number of tasks waiting for a permit.
+    pub fn waiters(&self) -> usize {
+        self.0.waiters.len()
+    }
+
+    /// Adds `n` permits, waking waiting tasks as necessary.
+    pub fn add_permits(&self, n: usize) {
+        self.0.release(n);
+    }
+
+    /// Acquires a permit, waiting until one is available.
+    ///
+    /// Resolves to an error if the semaphore is closed before a permit is acquired.
+    ///
+    /// # Cancellation
+    /// Dropping the returned future gives up its place in the queue. If a permit was already
+    /// assigned to it but not yet returned, the permit is passed on to the next waiter.
+    pub fn acquire_owned(&self) -> AcquireOwned {
+        AcquireOwned {
+            sem: self.0.clone(),
+            waiter: None,
+        }
+    }
+
+    /// Acquires a permit if one is immediately available.
+    ///
+    /// Fails if the semaphore is closed, or if no permits are available or other tasks are already
+    /// queued for one.
+    pub fn try_acquire_owned(&self) -> Result<OwnedSemaphorePermit, TryAcquireError> {
+        if self.0.closed.get() {
+            return Err(TryAcquireError::Closed);
+        }
+
+        if self.0.try_take() {
+            Ok(OwnedSemaphorePermit::new(self.0.clone()))
+        } else {
+            Err(TryAcquireError::NoPermits)
+        }
+    }
+
+    /// Closes the semaphore.
+    ///
+    /// All waiting tasks are woken and resolve to an error, as do any subsequent acquisitions.
+    /// Permits that are already held remain valid.
+    pub fn close(&self) {
+        self.0.closed.set(true);
+
+        self.0.waiters.wake_all(|| Acquired::Closed);
+    }
+
+    /// Returns true if the semaphore has been closed.
+    pub fn is_closed(&self) -> bool {
+        self.0.closed.get()
+    }
+}
+
+impl Inner {
+    /// Takes a permit if one is available and nobody is queued ahead.
+    fn try_take(&self) -> bool {
+        let permits = self.permits.get();
+
+        if permits > 0 && self.waiters.is_empty() {
+            self.permits.set(permits - 1);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns permits, handing them to queued waiters first.
+    fn release(&self, n: usize) {
+        self.permits.set(self.permits.get() + n);
+
+        while self.permits.get() > 0 && !self.waiters.is_empty() {
+            self.permits.set(self.permits.get() - 1);
+            let _ = self.waiters.wake_front(Acquired::Granted);
+        }
+    }
+}
+
+impl fmt::Debug for Semaphore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Semaphore")
+            .field("permits", &self.0.permits.get())
+            .field("waiters", &self.0.waiters.len())
+            .field("closed", &self.0.closed.get())
+            .finish()
+    }
+}
+
+/// Future returned by [`Semaphore::acquire_owned`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct AcquireOwned {
+    sem: Rc<Inner>,
+    waiter: Option<Waiter<Acquired>>,
+}
+
+impl Future for AcquireOwned {
+    type Output = Result<OwnedSemaphorePermit, AcquireError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        match &this.waiter {
+            None => {
+                if this.sem.closed.get() {
+                    return Poll::Ready(Err(AcquireError(())));
+                }
+
+                if this.sem.try_take() {
+                    return Poll::Ready(Ok(OwnedSemaphorePermit::new(this.sem.clone())));
+                }
+
+                this.waiter = Some(this.sem.waiters.register(cx.waker()));
+
+                Poll::Pending
+            }
+
+            Some(waiter) => match waiter.poll_take(cx) {
+                Poll::Pending => Poll::Pending,
+
+                Poll::Ready(Acquired::Granted) => {
+                    this.waiter = None;
+                    Poll::Ready(Ok(OwnedSemaphorePermit::new(this.sem.clone())))
+                }
+
+                Poll::Ready(Acquired::Closed) => {
+                    this.waiter = None;
+                    Poll::Ready(Err(AcquireError(())))
+                }
+            },
+        }
+    }
+}
+
+impl Drop for AcquireOwned {
+    fn drop(&mut self) {
+        if let Some(waiter) = self.waiter.take() {
+            // return a permit that was granted but never picked up
+            if let Some(Acquired::Granted) = self.sem.waiters.cancel(waiter) {
+                self.sem.release(1);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for AcquireOwned {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AcquireOwned")
+            .field("queued", &self.waiter.is_some())
+            .finish()
+    }
+}
+
+/// An owned permit from a [`Semaphore`], returned to it when dropped.
+#[must_use = "permits are released immediately if not held"]
+pub struct OwnedSemaphorePermit {
+    sem: Rc<Inner>,
+    forgotten: bool,
+}
+
+impl OwnedSemaphorePermit {
+    fn new(sem: Rc<Inner>) -> Self {
+        Self {
+            sem,
+            forgotten: false,
+        }
+    }
+
+    /// Consumes the permit without returning it to the semaphore, permanently reducing the
+    /// number of available permits.
+    pub fn forget(mut self) {
+        self.forgotten = true;
+    }
+
+    /// Returns a handle to the semaphore this permit was acquired from.
+    pub fn semaphore(&self) -> Semaphore {
+        Semaphore(self.sem.clone())
+    }
+}
+
+impl Drop for OwnedSemaphorePermit {
+    fn drop(&mut self) {
+        if !self.forgotten {
+            self.sem.release(1);
+        }
+    }
+}
+
+impl fmt::Debug for OwnedSemaphorePermit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OwnedSemaphorePermit")
+            .finish_non_exhaustive()
+    }
+}
+
+/// Error returned from [`Semaphore::acquire_owned`] when the semaphore has been closed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AcquireError(());
+
+impl fmt::Display for AcquireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("semaphore closed")
+    }
+}
+
+impl Error for AcquireError {}
+
+/// Error returned from [`Semaphore::try_acquire_owned`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryAcquireError {
+    /// The semaphore has been closed.
+    Closed,
+
+    /// No permits are available without waiting.
+    NoPermits,
+}
+
+impl fmt::Display for TryAcquireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Closed => f.write_str("semaphore closed"),
+            Self::NoPermits => f.write_str("no permits available"),
+        }
+    }
+}
+
+impl Error for TryAcquireError {}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{future::poll_fn, task::noop_waker};
+    use static_assertions::assert_not_impl_any;
+
+    use super::*;
+
+    assert_not_impl_any!(Semaphore: Send, Sync);
+    assert_not_impl_any!(OwnedSemaphorePermit: Send, Sync);
+
+    fn poll_once<F: Future + Unpin>(fut: &mut F) -> Poll<F::Output> {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        Pin::new(fut).poll(&mut cx)
+    }
+
+    #[actix_rt::test]
+    async fn permits_released_on_drop() {
+        let sem = Semaphore::new(2);
+
+        let a = sem.acquire_owned().await.unwrap();
+        let b = sem.try_acquire_owned().unwrap();
+        assert_eq!(sem.available_permits(), 0);
+        assert_eq!(
+            sem.try_acquire_owned().unwrap_err(),
+            TryAcquireError::NoPermits
+        );
+
+        drop(a);
+        assert_eq!(sem.available_permits(), 1);
+        drop(b);
+        assert_eq!(sem.available_permits(), 2);
+    }
+
+    #[actix_rt::test]
+    async fn waiters_served_in_order() {
+        let sem = Semaphore::new(1);
+        let permit = sem.try_acquire_owned().unwrap();
+
+        let mut first = sem.acquire_owned();
+        let mut second = sem.acquire_owned();
+        assert!(poll_once(&mut first).is_pending());
+        assert!(poll_once(&mut second).is_pending());
+        assert_eq!(sem.waiters(), 2);
+
+        drop(permit);
+
+        // released permit goes to the first waiter; new acquisitions can not barge in
+        assert!(sem.try_acquire_owned().is_err());
+        assert!(poll_once(&mut second).is_pending());
+
+        let first_permit = match poll_once(&mut first) {
+            Poll::Ready(res) => res.unwrap(),
+            Poll::Pending => panic!("first waiter should have been granted"),
+        };
+
+        drop(first_permit);
+        assert!(matches!(poll_once(&mut second), Poll::Ready(Ok(_))));
+    }
+
+    #[actix_rt::test]
+    async fn all_waiters_woken() {
+        let sem = Semaphore::new(0);
+
+        let tasks = (0..3)
+            .map(|_| {
+                let sem = sem.clone();
+                actix_rt::spawn(async move { sem.acquire_owned().await.unwrap().forget() })
+            })
+            .collect::<Vec<_>>();
+
+        // let tasks queue up
+        actix_rt::task::yield_now().await;
+        assert_eq!(sem.waiters(), 3);
+
+        sem.add_permits(3);
+
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(sem.available_permits(), 0);
+        assert_eq!(sem.waiters(), 0);
+    }
+
+    #[actix_rt::test]
+    async fn cancelled_waiter_passes_permit_on() {
+        let sem = Semaphore::new(1);
+        let permit = sem.try_acquire_owned().unwrap();
+
+        let mut first = sem.acquire_owned();
+        let mut second = sem.acquire_owned();
+        assert!(poll_once(&mut first).is_pending());
+        assert!(poll_once(&mut second).is_pending());
+
+        // permit assigned to first waiter, which is then dropped without polling
+        drop(permit);
+        drop(first);
+
+        assert!(matches!(poll_once(&mut second), Poll::Ready(Ok(_))));
+    }
+
+    #[actix_rt::test]
+    async fn cancelled_waiter_leaves_queue() {
+        let sem = Semaphore::new(0);
+
+        let mut fut = sem.acquire_owned();
+        assert!(poll_once(&mut fut).is_pending());
+        assert_eq!(sem.waiters(), 1);
+
+        drop(fut);
+        assert_eq!(sem.waiters(), 0);
+
+        sem.add_permits(1);
+        assert_eq!(sem.available_permits(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn close_wakes_waiters() {
+        let sem = Semaphore::new(1);
+        let permit = sem.try_acquire_owned().unwrap();
+
+        let waiter = actix_rt::spawn({
+            let sem = sem.clone();
+            async move { sem.acquire_owned().await }
+        });
+        actix_rt::task::yield_now().await;
+
+        sem.close();
+        assert!(sem.is_closed());
+        assert!(waiter.await.unwrap().is_err());
+
+        assert_eq!(
+            sem.try_acquire_owned().unwrap_err(),
+            TryAcquireError::Closed
+        );
+        assert!(sem.acquire_owned().await.is_err());
+
+        // held permits are still returned
+        drop(permit);
+        assert_eq!(sem.available_permits(), 1);
+    }
+
+    #[actix_rt::test]
+    async fn forget_permit() {
+        let sem = Semaphore::new(1);
+        sem.try_acquire_owned().unwrap().forget();
+        assert_eq!(sem.available_permits(), 0);
+
+        poll_fn(|cx| {
+            let mut fut = sem.acquire_owned();
+            assert!(Pin::new(&mut fut).poll(cx).is_pending());
+            Poll::Ready(())
+        })
+        .await;
+    }
+}
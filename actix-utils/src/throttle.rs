@@ -0,0 +1,413 @@
+//! Bandwidth limiting for streams.
+//!
+//! See [`Throttled`] and [`BandwidthLimiter`] for details.
+
+use core::{
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+    time::Duration,
+};
+use std::{
+    io,
+    sync::{Arc, Mutex},
+};
+
+use actix_rt::{
+    net::{ActixStream, Ready},
+    time::Instant,
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::clock::{Clock, RuntimeClock, Sleep};
+
+/// Token bucket limiting throughput to a number of bytes per second.
+///
+/// The bucket holds up to `burst` bytes worth of tokens and is refilled at `bytes_per_sec`. A
+/// limiter is a cheap to clone handle and clones share the same bucket, so one limiter can cap the
+/// combined throughput of several streams, e.g., all connections of a tenant.
+///
+/// Transfers may overdraw the bucket slightly when it is shared between streams transferring data
+/// at the same time; the debt is paid back before further transfers are allowed.
+#[derive(Clone)]
+pub struct BandwidthLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+    clock: Arc<dyn Clock>,
+}
+
+struct Bucket {
+    bytes_per_sec: f64,
+    burst: f64,
+    tokens: f64,
+    updated: Instant,
+}
+
+impl BandwidthLimiter {
+    /// Constructs limiter allowing `bytes_per_sec` on average and bursts of up to `burst` bytes.
+    ///
+    /// The bucket starts out full.
+    ///
+    /// # Panics
+    /// Panics if `bytes_per_sec` or `burst` is zero.
+    pub fn new(bytes_per_sec: u64, burst: u64) -> Self {
+        Self::with_clock(RuntimeClock::new(), bytes_per_sec, burst)
+    }
+
+    /// Constructs limiter like [`new`](Self::new) that measures time using `clock`.
+    ///
+    /// # Panics
+    /// Panics if `bytes_per_sec` or `burst` is zero.
+    pub fn with_clock(clock: impl Clock + 'static, bytes_per_sec: u64, burst: u64) -> Self {
+        assert!(bytes_per_sec > 0, "rate must be non-zero");
+        assert!(burst > 0, "burst size must be non-zero");
+
+        let updated = clock.now();
+
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                bytes_per_sec: bytes_per_sec as f64,
+                burst: burst as f64,
+                tokens: burst as f64,
+                updated,
+            })),
+            clock: Arc::new(clock),
+        }
+    }
+
+    /// Returns the number of bytes that can currently be transferred without waiting.
+    pub fn available(&self) -> u64 {
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.refill(self.clock.now());
+        bucket.tokens.max(0.0) as u64
+    }
+
+    /// Returns the number of bytes, up to `want`, that can be transferred now, or the instant at
+    /// which enough tokens for a transfer will be available.
+    fn poll_available(&self, want: usize) -> Result<usize, Instant> {
+        let now = self.clock.now();
+        let mut bucket = self.bucket.lock().unwrap();
+        bucket.refill(now);
+
+        // avoid many tiny transfers by waiting for a reasonably sized chunk once empty
+        let min = (want as f64).min(bucket.burst);
+
+        if bucket.tokens >= 1.0 && (bucket.tokens >= min || bucket.tokens == bucket.burst) {
+            Ok(want.min(bucket.tokens as usize))
+        } else {
+            let wait = (min - bucket.tokens) / bucket.bytes_per_sec;
+            Err(now + Duration::from_secs_f64(wait.max(0.0)))
+        }
+    }
+
+    /// Takes tokens for `n` bytes that were transferred.
+    fn consume(&self, n: usize) {
+        self.bucket.lock().unwrap().tokens -= n as f64;
+    }
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.bytes_per_sec).min(self.burst);
+        self.updated = now;
+    }
+}
+
+impl fmt::Debug for BandwidthLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bucket = self.bucket.lock().unwrap();
+
+        f.debug_struct("BandwidthLimiter")
+            .field("bytes_per_sec", &bucket.bytes_per_sec)
+            .field("burst", &bucket.burst)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Stream wrapper limiting read and write throughput.
+///
+/// Reads and writes are capped to the tokens available in their [`BandwidthLimiter`] and wait
+/// for the bucket to refill once it is empty. Useful both for enforcing per-connection or
+/// per-tenant bandwidth caps and for simulating slow clients in tests.
+///
+/// Read and write readiness reported through [`ActixStream`] is that of the underlying stream.
+///
+/// # Examples
+/// ```
+/// use actix_utils::{
+///     duplex::duplex,
+///     throttle::{BandwidthLimiter, Throttled},
+/// };
+/// use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+///
+/// # actix_rt::System::new().block_on(async {
+/// let (client, mut server) = duplex(1024);
+///
+/// // 64 KiB/s with bursts of up to 16 KiB
+/// let limit = BandwidthLimiter::new(64 * 1024, 16 * 1024);
+/// let mut client = Throttled::new(client).write_limit(limit);
+///
+/// client.write_all(b"hello").await.unwrap();
+///
+/// let mut buf = [0; 5];
+/// server.read_exact(&mut buf).await.unwrap();
+/// # });
+/// ```
+pub struct Throttled<IO> {
+    io: IO,
+    read: Option<Direction>,
+    write: Option<Direction>,
+}
+
+struct Direction {
+    limiter: BandwidthLimiter,
+    sleep: Option<Pin<Box<Sleep>>>,
+}
+
+impl Direction {
+    /// Returns the number of bytes, up to `want`, that can be transferred now.
+    fn poll_available(&mut self, cx: &mut Context<'_>, want: usize) -> Poll<usize> {
+        loop {
+            let deadline = match self.limiter.poll_available(want) {
+                Ok(n) => {
+                    self.sleep = None;
+                    return Poll::Ready(n);
+                }
+                Err(deadline) => deadline,
+            };
+
+            let sleep = match &mut self.sleep {
+                Some(sleep) if sleep.deadline() == deadline => sleep,
+                sleep => sleep.insert(Box::pin(self.limiter.clock.sleep_until(deadline))),
+            };
+
+            if sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+impl<IO> Throttled<IO> {
+    /// Wraps `io` without limiting its throughput.
+    pub fn new(io: IO) -> Self {
+        Self {
+            io,
+            read: None,
+            write: None,
+        }
+    }
+
+    /// Limits read throughput using `limiter`.
+    pub fn read_limit(mut self, limiter: BandwidthLimiter) -> Self {
+        self.read = Some(Direction {
+            limiter,
+            sleep: None,
+        });
+        self
+    }
+
+    /// Limits write throughput using `limiter`.
+    pub fn write_limit(mut self, limiter: BandwidthLimiter) -> Self {
+        self.write = Some(Direction {
+            limiter,
+            sleep: None,
+        });
+        self
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &IO {
+        &self.io
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    ///
+    /// Transfers made directly on the underlying stream are not limited.
+    pub fn get_mut(&mut self) -> &mut IO {
+        &mut self.io
+    }
+
+    /// Returns the underlying stream.
+    pub fn into_inner(self) -> IO {
+        self.io
+    }
+}
+
+impl<IO: fmt::Debug> fmt::Debug for Throttled<IO> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Throttled")
+            .field("io", &self.io)
+            .field("read_limit", &self.read.as_ref().map(|dir| &dir.limiter))
+            .field("write_limit", &self.write.as_ref().map(|dir| &dir.limiter))
+            .finish()
+    }
+}
+
+impl<IO: AsyncRead + Unpin> AsyncRead for Throttled<IO> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        let dir = match &mut this.read {
+            Some(dir) if buf.remaining() > 0 => dir,
+            _ => return Pin::new(&mut this.io).poll_read(cx, buf),
+        };
+
+        let limit = match dir.poll_available(cx, buf.remaining()) {
+            Poll::Ready(limit) => limit,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        let mut limited = buf.take(limit);
+        let res = Pin::new(&mut this.io).poll_read(cx, &mut limited);
+        let n = limited.filled().len();
+
+        // SAFETY: `limited` reads into the unfilled part of `buf`, so the `n` bytes filled through
+        // it are initialized
+        unsafe { buf.assume_init(n) };
+        buf.advance(n);
+
+        dir.limiter.consume(n);
+        res
+    }
+}
+
+impl<IO: AsyncWrite + Unpin> AsyncWrite for Throttled<IO> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        let dir = match &mut this.write {
+            Some(dir) if !buf.is_empty() => dir,
+            _ => return Pin::new(&mut this.io).poll_write(cx, buf),
+        };
+
+        let limit = match dir.poll_available(cx, buf.len()) {
+            Poll::Ready(limit) => limit,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        let res = Pin::new(&mut this.io).poll_write(cx, &buf[..limit]);
+
+        if let Poll::Ready(Ok(n)) = res {
+            dir.limiter.consume(n);
+        }
+
+        res
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.get_mut().io).poll_shutdown(cx)
+    }
+}
+
+impl<IO: ActixStream> ActixStream for Throttled<IO> {
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        IO::poll_read_ready(&self.io, cx)
+    }
+
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<Ready>> {
+        IO::poll_write_ready(&self.io, cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use static_assertions::assert_impl_all;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+
+    use super::*;
+    use crate::{clock::MockClock, duplex::duplex};
+
+    assert_impl_all!(BandwidthLimiter: Send, Sync, Clone);
+    assert_impl_all!(Throttled<crate::duplex::DuplexStream>: Send, ActixStream);
+
+    fn ms(n: u64) -> Duration {
+        Duration::from_millis(n)
+    }
+
+    #[actix_rt::test]
+    async fn refills_over_time() {
+        let clock = MockClock::new();
+        let limiter = BandwidthLimiter::with_clock(clock.clone(), 1000, 100);
+        assert_eq!(limiter.available(), 100);
+
+        limiter.consume(100);
+        assert_eq!(limiter.available(), 0);
+
+        clock.advance(ms(50));
+        assert_eq!(limiter.available(), 50);
+
+        // bucket does not fill beyond burst size
+        clock.advance(ms(500));
+        assert_eq!(limiter.available(), 100);
+    }
+
+    #[actix_rt::test]
+    async fn limits_writes() {
+        let clock = MockClock::new();
+        let (client, mut server) = duplex(1024);
+
+        let limiter = BandwidthLimiter::with_clock(clock.clone(), 1000, 100);
+        let mut client = Throttled::new(client).write_limit(limiter);
+
+        let writer = actix_rt::spawn(async move {
+            client.write_all(&[1; 250]).await.unwrap();
+        });
+
+        let mut buf = vec![0; 100];
+        server.read_exact(&mut buf).await.unwrap();
+
+        // remaining bytes wait for bucket to refill
+        actix_rt::task::yield_now().await;
+        assert!(!writer.is_finished());
+
+        clock.advance(ms(100));
+        server.read_exact(&mut buf).await.unwrap();
+
+        clock.advance(ms(50));
+        let mut buf = vec![0; 50];
+        server.read_exact(&mut buf).await.unwrap();
+        writer.await.unwrap();
+    }
+
+    #[actix_rt::test]
+    async fn limits_reads() {
+        let clock = MockClock::new();
+        let (mut client, server) = duplex(1024);
+
+        let limiter = BandwidthLimiter::with_clock(clock.clone(), 1000, 10);
+        let mut server = Throttled::new(server).read_limit(limiter.clone());
+
+        client.write_all(&[1; 30]).await.unwrap();
+
+        let mut buf = [0; 30];
+        assert_eq!(server.read(&mut buf).await.unwrap(), 10);
+        assert_eq!(limiter.available(), 0);
+
+        let reader = actix_rt::spawn(async move {
+            let mut buf = [0; 20];
+            server.read_exact(&mut buf).await.unwrap();
+        });
+
+        actix_rt::task::yield_now().await;
+        assert!(!reader.is_finished());
+
+        clock.advance(ms(10));
+        actix_rt::task::yield_now().await;
+        clock.advance(ms(10));
+        reader.await.unwrap();
+    }
+}
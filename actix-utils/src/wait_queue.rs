@@ -0,0 +1,329 @@
+//! FIFO queue of waiting tasks, for building single-threaded synchronization primitives.
+//!
+//! See [`WaitQueue`] for details.
+
+use core::{
+    cell::{Cell, RefCell},
+    fmt,
+    task::{Context, Poll, Waker},
+};
+use std::{collections::VecDeque, rc::Rc};
+
+/// Cancelled entries are purged once they exceed the number of queued waiters by this much.
+const COMPACT_THRESHOLD: usize = 16;
+
+/// FIFO queue of tasks waiting to be handed a value of type `T`.
+///
+/// This is the building block behind [`Semaphore`](crate::semaphore::Semaphore) and
+/// [`Notify`](crate::notify::Notify), and is intended for other primitives that need to wake
+/// waiting tasks fairly, such as rate limiters or buffering services.
+///
+/// A task joins the queue with [`register`](Self::register), receiving a [`Waiter`] handle that
+/// it keeps in its future. [`wake_front`](Self::wake_front) hands a value to the task that has
+/// been waiting longest and wakes it; the task then picks up the value by polling its `Waiter`.
+/// Waiters are always woken in the order they registered, so a task can not be starved by ones
+/// that arrived after it.
+///
+/// A future that is dropped while queued must give its handle back with
+/// [`cancel`](Self::cancel), which also returns any value that was handed to it but not yet
+/// picked up, so it can be passed on rather than lost.
+///
+/// The queue is not intrusive: each registration allocates a reference-counted node shared by the
+/// queue and the `Waiter`. Cancelling only marks the node, leaving it to be skipped once it reaches
+/// the front; once cancelled nodes outnumber queued waiters by more than a few, they are purged in
+/// a single pass over the queue. Cancellation therefore takes amortized constant time.
+///
+/// # Examples
+/// ```
+/// use std::task::Poll;
+///
+/// use actix_utils::wait_queue::WaitQueue;
+/// use futures_util::task::noop_waker;
+///
+/// let queue = WaitQueue::new();
+/// let waker = noop_waker();
+///
+/// let first = queue.register(&waker);
+/// let second = queue.register(&waker);
+/// assert_eq!(queue.len(), 2);
+///
+/// queue.wake_front("hello").unwrap();
+/// assert_eq!(first.take(), Some("hello"));
+/// assert_eq!(second.take(), None);
+///
+/// assert_eq!(queue.cancel(second), None);
+/// assert!(queue.is_empty());
+/// ```
+pub struct WaitQueue<T> {
+    entries: RefCell<VecDeque<Rc<Node<T>>>>,
+    len: Cell<usize>,
+}
+
+struct Node<T> {
+    queued: Cell<bool>,
+    value: Cell<Option<T>>,
+    waker: Cell<Option<Waker>>,
+}
+
+impl<T> WaitQueue<T> {
+    /// Constructs new, empty queue.
+    pub fn new() -> Self {
+        Self {
+            entries: RefCell::new(VecDeque::new()),
+            len: Cell::new(0),
+        }
+    }
+
+    /// Returns the number of queued waiters.
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    /// Returns true if no waiters are queued.
+    pub fn is_empty(&self) -> bool {
+        self.len.get() == 0
+    }
+
+    /// Adds a waiter to the back of the queue, to be woken using `waker`.
+    pub fn register(&self, waker: &Waker) -> Waiter<T> {
+        let node = Rc::new(Node {
+            queued: Cell::new(true),
+            value: Cell::new(None),
+            waker: Cell::new(Some(waker.clone())),
+        });
+
+        self.entries.borrow_mut().push_back(node.clone());
+        self.len.set(self.len.get() + 1);
+
+        Waiter { node }
+    }
+
+    /// Removes the waiter at the front of the queue, hands it `value` and wakes its task.
+    ///
+    /// Returns the value back if the queue is empty.
+    pub fn wake_front(&self, value: T) -> Result<(), T> {
+        let node = loop {
+            let node = self.entries.borrow_mut().pop_front();
+
+            match node {
+                Some(node) if node.queued.get() => break node,
+                // skip cancelled entries
+                Some(_) => continue,
+                None => return Err(value),
+            }
+        };
+
+        self.len.set(self.len.get() - 1);
+        node.wake(value);
+
+        Ok(())
+    }
+
+    /// Removes all waiters from the queue, handing each a value produced by `f` and waking them.
+    pub fn wake_all(&self, mut f: impl FnMut() -> T) {
+        // take entries out first so that woken tasks may register again without a borrow conflict
+        let entries = self.entries.take();
+        self.len.set(0);
+
+        for node in entries {
+            if node.queued.get() {
+                node.wake(f());
+            }
+        }
+    }
+
+    /// Gives back a waiter handle, removing it from the queue if it is still queued.
+    ///
+    /// Returns the value handed to the waiter if it was woken but the value was not yet taken.
+    pub fn cancel(&self, waiter: Waiter<T>) -> Option<T> {
+        let node = waiter.node;
+
+        if !node.queued.get() {
+            return node.value.take();
+        }
+
+        // entry is left in place and skipped when reached; purge them once they pile up
+        node.queued.set(false);
+        self.len.set(self.len.get() - 1);
+
+        let mut entries = self.entries.borrow_mut();
+        if entries.len() > self.len.get() * 2 + COMPACT_THRESHOLD {
+            entries.retain(|node| node.queued.get());
+        }
+
+        None
+    }
+}
+
+impl<T> Node<T> {
+    fn wake(&self, value: T) {
+        self.queued.set(false);
+        self.value.set(Some(value));
+
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Default for WaitQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for WaitQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WaitQueue")
+            .field("len", &self.len.get())
+            .finish()
+    }
+}
+
+/// Handle to a task's place in a [`WaitQueue`].
+///
+/// Created by [`WaitQueue::register`]. Dropping a handle does not remove it from the queue; give it
+/// back with [`WaitQueue::cancel`] instead.
+#[must_use = "waiters should be given back with `WaitQueue::cancel` if no longer polled"]
+pub struct Waiter<T> {
+    node: Rc<Node<T>>,
+}
+
+impl<T> Waiter<T> {
+    /// Returns true if this waiter is still queued, waiting to be woken.
+    pub fn is_queued(&self) -> bool {
+        self.node.queued.get()
+    }
+
+    /// Takes the value handed to this waiter, if it has been woken.
+    pub fn take(&self) -> Option<T> {
+        self.node.value.take()
+    }
+
+    /// Takes the value handed to this waiter, or registers `cx`'s waker to be woken once it is.
+    ///
+    /// Returns `Poll::Pending` after the value has been taken.
+    pub fn poll_take(&self, cx: &mut Context<'_>) -> Poll<T> {
+        match self.node.value.take() {
+            Some(value) => Poll::Ready(value),
+            None => {
+                if self.node.queued.get() {
+                    self.node.waker.set(Some(cx.waker().clone()));
+                }
+
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> fmt::Debug for Waiter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Waiter")
+            .field("queued", &self.node.queued.get())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::task::noop_waker;
+    use static_assertions::assert_not_impl_any;
+
+    use super::*;
+
+    assert_not_impl_any!(WaitQueue<()>: Send, Sync);
+    assert_not_impl_any!(Waiter<()>: Send, Sync);
+
+    #[test]
+    fn wakes_in_registration_order() {
+        let queue = WaitQueue::new();
+        let waker = noop_waker();
+
+        let waiters = (0..3).map(|_| queue.register(&waker)).collect::<Vec<_>>();
+        for n in 0..3 {
+            queue.wake_front(n).unwrap();
+        }
+        assert_eq!(queue.wake_front(3), Err(3));
+
+        for (n, waiter) in waiters.iter().enumerate() {
+            assert!(!waiter.is_queued());
+            assert_eq!(waiter.take(), Some(n));
+        }
+    }
+
+    #[test]
+    fn cancelled_waiters_are_skipped() {
+        let queue = WaitQueue::new();
+        let waker = noop_waker();
+
+        let first = queue.register(&waker);
+        let second = queue.register(&waker);
+        assert_eq!(queue.cancel(first), None);
+        assert_eq!(queue.len(), 1);
+
+        queue.wake_front('a').unwrap();
+        assert_eq!(second.take(), Some('a'));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn cancel_returns_untaken_value() {
+        let queue = WaitQueue::new();
+        let waker = noop_waker();
+
+        let waiter = queue.register(&waker);
+        queue.wake_front(1).unwrap();
+        assert_eq!(queue.cancel(waiter), Some(1));
+    }
+
+    #[test]
+    fn wake_all() {
+        let queue = WaitQueue::new();
+        let waker = noop_waker();
+
+        let a = queue.register(&waker);
+        let b = queue.register(&waker);
+        let c = queue.register(&waker);
+        let _ = queue.cancel(b);
+
+        let mut n = 0;
+        queue.wake_all(|| {
+            n += 1;
+            n
+        });
+
+        assert_eq!(a.take(), Some(1));
+        assert_eq!(c.take(), Some(2));
+        assert!(queue.is_empty());
+    }
+
+    #[test]
+    fn cancelled_entries_compacted() {
+        let queue = WaitQueue::<()>::new();
+        let waker = noop_waker();
+
+        let _head = queue.register(&waker);
+        for _ in 0..1000 {
+            let waiter = queue.register(&waker);
+            let _ = queue.cancel(waiter);
+        }
+
+        assert_eq!(queue.len(), 1);
+        assert!(queue.entries.borrow().len() <= 2 + COMPACT_THRESHOLD);
+    }
+
+    #[test]
+    fn poll_take() {
+        let queue = WaitQueue::new();
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let waiter = queue.register(&waker);
+        assert!(waiter.poll_take(&mut cx).is_pending());
+
+        queue.wake_front("x").unwrap();
+        assert_eq!(waiter.poll_take(&mut cx), Poll::Ready("x"));
+        assert!(waiter.poll_take(&mut cx).is_pending());
+    }
+}
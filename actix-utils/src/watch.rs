@@ -0,0 +1,285 @@
+//! Single-threaded shared value with change notification.
+//!
+//! See [`LocalWatch`] for details.
+
+use core::{
+    cell::Ref,
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use std::error::Error;
+
+use local_channel::watch::{self, Receiver, Sender};
+
+/// A shared value that tasks on the same thread can watch for changes.
+///
+/// `LocalWatch` is a single-threaded watch channel: any handle can update the value, and each
+/// [`Watcher`] created with [`subscribe`](Self::subscribe) can wait until the value changes after
+/// it last looked. Only the latest value is retained; watchers that fall behind skip intermediate
+/// values. Handles are cloned cheaply and all refer to the same value.
+///
+/// It is built on the [`local_channel::watch`] channel, with a single handle type in place of
+/// separate senders.
+///
+/// Values are stored in a `RefCell`. Updating the value while a borrow from
+/// [`borrow`](Self::borrow) or [`Watcher::borrow`] is alive panics, so borrows should not be held
+/// across await points.
+///
+/// # Examples
+/// ```
+/// # actix_rt::System::new().block_on(async {
+/// use actix_utils::watch::LocalWatch;
+///
+/// let draining = LocalWatch::new(false);
+/// let mut watcher = draining.subscribe();
+///
+/// let task = actix_rt::spawn(async move {
+///     while !*watcher.borrow_and_update() {
+///         watcher.changed().await.unwrap();
+///     }
+/// });
+///
+/// draining.set(true);
+/// task.await.unwrap();
+/// # });
+/// ```
+pub struct LocalWatch<T> {
+    tx: Sender<T>,
+}
+
+impl<T> LocalWatch<T> {
+    /// Constructs new watched value.
+    pub fn new(value: T) -> Self {
+        let (tx, _) = watch::channel(value);
+        Self { tx }
+    }
+
+    /// Replaces the value and notifies all watchers.
+    ///
+    /// # Panics
+    /// Panics if the value is currently borrowed.
+    pub fn set(&self, value: T) {
+        self.replace(value);
+    }
+
+    /// Replaces the value, notifies all watchers and returns the previous value.
+    ///
+    /// # Panics
+    /// Panics if the value is currently borrowed.
+    pub fn replace(&self, value: T) -> T {
+        self.tx.send_replace(value)
+    }
+
+    /// Modifies the value in place and notifies all watchers.
+    ///
+    /// # Panics
+    /// Panics if the value is currently borrowed.
+    pub fn modify(&self, f: impl FnOnce(&mut T)) {
+        self.tx.send_modify(f);
+    }
+
+    /// Returns a reference to the current value.
+    pub fn borrow(&self) -> Ref<'_, T> {
+        self.tx.borrow()
+    }
+
+    /// Creates a new watcher that considers the current value as already seen.
+    pub fn subscribe(&self) -> Watcher<T> {
+        Watcher {
+            rx: self.tx.subscribe(),
+        }
+    }
+}
+
+// watchers are closed by the channel once the last sender, and so the last handle, is dropped
+impl<T> Clone for LocalWatch<T> {
+    fn clone(&self) -> Self {
+        Self {
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for LocalWatch<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalWatch")
+            .field("value", &*self.borrow())
+            .finish()
+    }
+}
+
+/// Watches a [`LocalWatch`] for changes.
+///
+/// Created using [`LocalWatch::subscribe`]. Watchers can be cloned; each clone tracks which
+/// version it has seen independently.
+pub struct Watcher<T> {
+    rx: Receiver<T>,
+}
+
+impl<T> Watcher<T> {
+    /// Returns a reference to the current value without marking it as seen.
+    pub fn borrow(&self) -> Ref<'_, T> {
+        self.rx.borrow()
+    }
+
+    /// Returns a reference to the current value and marks it as seen.
+    pub fn borrow_and_update(&mut self) -> Ref<'_, T> {
+        self.rx.borrow_and_update()
+    }
+
+    /// Returns true if the value has changed since it was last seen.
+    pub fn has_changed(&self) -> bool {
+        // channel only errors once closed without an unseen change
+        self.rx.has_changed().unwrap_or(false)
+    }
+
+    /// Waits for the value to change since it was last seen, then marks it as seen.
+    ///
+    /// Completes immediately if the value has already changed. Resolves to an error once all
+    /// [`LocalWatch`] handles have been dropped and no unseen change is left.
+    ///
+    /// # Cancellation
+    /// This method is cancellation safe; dropping the future does not mark any change as seen.
+    pub fn changed(&mut self) -> Changed<'_, T> {
+        Changed { watcher: self }
+    }
+
+    /// Polls for a change of the value since it was last seen.
+    ///
+    /// See [`changed`](Self::changed) for details.
+    pub fn poll_changed(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), WatchClosed>> {
+        self.rx
+            .poll_changed(cx)
+            .map_err(|watch::RecvError| WatchClosed(()))
+    }
+}
+
+impl<T> Clone for Watcher<T> {
+    fn clone(&self) -> Self {
+        Self {
+            rx: self.rx.clone(),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Watcher<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Watcher")
+            .field("value", &*self.borrow())
+            .field("has_changed", &self.has_changed())
+            .finish()
+    }
+}
+
+/// Future returned by [`Watcher::changed`].
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Changed<'a, T> {
+    watcher: &'a mut Watcher<T>,
+}
+
+impl<T> Future for Changed<'_, T> {
+    type Output = Result<(), WatchClosed>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.get_mut().watcher.poll_changed(cx)
+    }
+}
+
+impl<T> fmt::Debug for Changed<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Changed").finish_non_exhaustive()
+    }
+}
+
+/// Error returned from [`Watcher::changed`] when all [`LocalWatch`] handles have been dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchClosed(());
+
+impl fmt::Display for WatchClosed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("watched value has no remaining handles")
+    }
+}
+
+impl Error for WatchClosed {}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::task::noop_waker;
+    use static_assertions::assert_not_impl_any;
+
+    use super::*;
+
+    assert_not_impl_any!(LocalWatch<()>: Send, Sync);
+    assert_not_impl_any!(Watcher<()>: Send, Sync);
+
+    fn poll_changed<T>(watcher: &mut Watcher<T>) -> Poll<Result<(), WatchClosed>> {
+        let waker = noop_waker();
+        let mut cx = Context::from_waker(&waker);
+        watcher.poll_changed(&mut cx)
+    }
+
+    #[test]
+    fn tracks_seen_version() {
+        let watch = LocalWatch::new(1);
+        let mut watcher = watch.subscribe();
+        assert!(!watcher.has_changed());
+        assert!(poll_changed(&mut watcher).is_pending());
+
+        watch.set(2);
+        watch.modify(|n| *n += 1);
+        assert!(watcher.has_changed());
+        assert_eq!(*watcher.borrow(), 3);
+
+        // intermediate values are skipped
+        assert_eq!(poll_changed(&mut watcher), Poll::Ready(Ok(())));
+        assert!(poll_changed(&mut watcher).is_pending());
+
+        assert_eq!(watch.replace(4), 3);
+        assert_eq!(*watcher.borrow_and_update(), 4);
+        assert!(!watcher.has_changed());
+    }
+
+    #[test]
+    fn closed_after_last_handle_dropped() {
+        let watch = LocalWatch::new("a");
+        let watch2 = watch.clone();
+        let mut watcher = watch.subscribe();
+
+        watch.set("b");
+        drop(watch);
+        drop(watch2);
+
+        // unseen change is still delivered before reporting closure
+        assert_eq!(poll_changed(&mut watcher), Poll::Ready(Ok(())));
+        assert_eq!(*watcher.borrow(), "b");
+        assert_eq!(
+            poll_changed(&mut watcher),
+            Poll::Ready(Err(WatchClosed(())))
+        );
+    }
+
+    #[actix_rt::test]
+    async fn wakes_all_watchers() {
+        let watch = LocalWatch::new(0);
+
+        let tasks = (0..3)
+            .map(|_| {
+                let mut watcher = watch.subscribe();
+                actix_rt::spawn(async move {
+                    watcher.changed().await.unwrap();
+                    *watcher.borrow()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        actix_rt::task::yield_now().await;
+        watch.set(7);
+
+        for task in tasks {
+            assert_eq!(task.await.unwrap(), 7);
+        }
+    }
+}
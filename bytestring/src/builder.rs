@@ -0,0 +1,213 @@
+//! Growable UTF-8 buffer that freezes into a [`ByteString`].
+
+use alloc::string::String;
+use core::{fmt, ops, str};
+
+use bytes::BytesMut;
+
+use crate::ByteString;
+
+/// A growable UTF-8 encoded string with [`BytesMut`] as a storage.
+///
+/// Strings are composed by pushing `char`s and `&str`s, or with [`write!`], then converted into a
+/// [`ByteString`] with [`freeze`](Self::freeze) without going through a [`String`].
+///
+/// # Examples
+/// ```
+/// use std::fmt::Write as _;
+///
+/// use bytestring::ByteStringMut;
+///
+/// let mut buf = ByteStringMut::with_capacity(64);
+/// buf.push_str("HTTP/1.1 ");
+/// write!(buf, "{} {}", 200, "OK").unwrap();
+/// buf.push('\n');
+///
+/// let line = buf.freeze();
+/// assert_eq!(line, "HTTP/1.1 200 OK\n");
+/// ```
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteStringMut(BytesMut);
+
+impl ByteStringMut {
+    /// Creates a new empty `ByteStringMut`.
+    pub fn new() -> Self {
+        Self(BytesMut::new())
+    }
+
+    /// Creates a new empty `ByteStringMut` with at least the specified capacity, in bytes.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self(BytesMut::with_capacity(capacity))
+    }
+
+    /// Appends a string slice to the end of the buffer.
+    pub fn push_str(&mut self, string: &str) {
+        self.0.extend_from_slice(string.as_bytes());
+    }
+
+    /// Appends a `char` to the end of the buffer.
+    pub fn push(&mut self, ch: char) {
+        self.push_str(ch.encode_utf8(&mut [0; 4]));
+    }
+
+    /// Returns the buffer's contents as a string slice.
+    pub fn as_str(&self) -> &str {
+        // SAFETY: only valid UTF-8 is ever appended to the buffer.
+        unsafe { str::from_utf8_unchecked(&self.0) }
+    }
+
+    /// Returns the number of bytes the buffer can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.0.capacity()
+    }
+
+    /// Reserves capacity for at least `additional` more bytes.
+    pub fn reserve(&mut self, additional: usize) {
+        self.0.reserve(additional);
+    }
+
+    /// Removes all contents, keeping the allocated capacity.
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Converts the buffer into an immutable [`ByteString`].
+    ///
+    /// This does not copy.
+    pub fn freeze(self) -> ByteString {
+        ByteString(self.0.freeze())
+    }
+
+    /// Unwraps this `ByteStringMut` into the underlying `BytesMut` object.
+    pub fn into_inner(self) -> BytesMut {
+        self.0
+    }
+}
+
+impl ops::Deref for ByteStringMut {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for ByteStringMut {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl AsRef<[u8]> for ByteStringMut {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_ref()
+    }
+}
+
+impl From<&str> for ByteStringMut {
+    fn from(value: &str) -> Self {
+        Self(BytesMut::from(value))
+    }
+}
+
+impl From<String> for ByteStringMut {
+    fn from(value: String) -> Self {
+        Self::from(value.as_str())
+    }
+}
+
+impl From<ByteStringMut> for ByteString {
+    #[inline]
+    fn from(value: ByteStringMut) -> Self {
+        value.freeze()
+    }
+}
+
+impl<'a> Extend<&'a str> for ByteStringMut {
+    fn extend<I: IntoIterator<Item = &'a str>>(&mut self, iter: I) {
+        iter.into_iter().for_each(|string| self.push_str(string));
+    }
+}
+
+impl Extend<char> for ByteStringMut {
+    fn extend<I: IntoIterator<Item = char>>(&mut self, iter: I) {
+        let iter = iter.into_iter();
+        self.reserve(iter.size_hint().0);
+        iter.for_each(|ch| self.push(ch));
+    }
+}
+
+impl fmt::Write for ByteStringMut {
+    #[inline]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s);
+        Ok(())
+    }
+
+    #[inline]
+    fn write_char(&mut self, ch: char) -> fmt::Result {
+        self.push(ch);
+        Ok(())
+    }
+}
+
+impl fmt::Debug for ByteStringMut {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(fmt)
+    }
+}
+
+impl fmt::Display for ByteStringMut {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.as_str().fmt(fmt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, string::ToString as _};
+    use core::fmt::Write as _;
+
+    use static_assertions::assert_impl_all;
+
+    use super::*;
+
+    assert_impl_all!(ByteStringMut: Send, Sync, Unpin, Clone, Default, fmt::Write);
+
+    #[test]
+    fn push() {
+        let mut buf = ByteStringMut::new();
+        buf.push_str("caf");
+        buf.push('é');
+        buf.extend(["!", "?"]);
+        buf.extend(['a', 'b']);
+        assert_eq!(buf.as_str(), "café!?ab");
+        assert_eq!(buf.len(), 9);
+
+        buf.clear();
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn write() {
+        let mut buf = ByteStringMut::from("id=");
+        write!(buf, "{}", 42).unwrap();
+        assert_eq!(buf.to_string(), "id=42");
+        assert_eq!(format!("{buf:?}"), r#""id=42""#);
+    }
+
+    #[test]
+    fn freeze() {
+        let mut buf = ByteStringMut::with_capacity(16);
+        buf.push_str("frozen");
+        let ptr = buf.as_ptr();
+
+        let string = buf.freeze();
+        assert_eq!(string, "frozen");
+        assert_eq!(string.as_ptr(), ptr);
+
+        let string: ByteString = ByteStringMut::from("short").into();
+        assert_eq!(string, "short");
+    }
+}
@@ -0,0 +1,322 @@
+use alloc::{boxed::Box, string::String};
+use core::{borrow::Borrow, cmp::Ordering, convert::TryFrom, fmt, hash, ops, str};
+
+use bytes::Bytes;
+
+use crate::ByteString;
+
+/// Maximum length of strings stored inline.
+const INLINE_CAP: usize = 23;
+
+/// An immutable UTF-8 encoded string that stores short strings inline.
+///
+/// Strings of up to 23 bytes are stored inline, without allocating or reference counting, which
+/// suits workloads dominated by short values like header names and tokens. Longer strings, and
+/// strings created from `&'static str`, share their underlying [`Bytes`] like a [`ByteString`].
+///
+/// Since inline strings have no underlying `Bytes`, converting one into a `ByteString` copies it.
+///
+/// # Examples
+/// ```
+/// use bytestring::CompactByteString;
+///
+/// let name = CompactByteString::from("content-type");
+/// assert!(name.is_inline());
+/// assert_eq!(name, "content-type");
+/// ```
+#[derive(Clone)]
+pub struct CompactByteString(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    Inline { len: u8, buf: [u8; INLINE_CAP] },
+    Shared(ByteString),
+}
+
+impl Repr {
+    /// Stores `src` inline if it is short enough.
+    fn inline(src: &str) -> Option<Self> {
+        if src.len() > INLINE_CAP {
+            return None;
+        }
+
+        let mut buf = [0; INLINE_CAP];
+        buf[..src.len()].copy_from_slice(src.as_bytes());
+
+        Some(Repr::Inline {
+            len: src.len() as u8,
+            buf,
+        })
+    }
+
+    /// Stores `src` inline if it is short enough, or shares it otherwise.
+    fn from_byte_string(src: ByteString) -> Self {
+        Self::inline(&src).unwrap_or(Repr::Shared(src))
+    }
+}
+
+impl CompactByteString {
+    /// Creates a new empty `CompactByteString`.
+    pub const fn new() -> Self {
+        Self(Repr::Inline {
+            len: 0,
+            buf: [0; INLINE_CAP],
+        })
+    }
+
+    /// Creates a new `CompactByteString` from a `&'static str`, without copying.
+    pub const fn from_static(src: &'static str) -> Self {
+        Self(Repr::Shared(ByteString::from_static(src)))
+    }
+
+    /// Returns true if the string is stored inline.
+    pub fn is_inline(&self) -> bool {
+        matches!(self.0, Repr::Inline { .. })
+    }
+
+    /// Get a reference to the underlying bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        match &self.0 {
+            Repr::Inline { len, buf } => &buf[..*len as usize],
+            Repr::Shared(string) => string.as_bytes(),
+        }
+    }
+
+    /// Converts this string into a [`ByteString`].
+    ///
+    /// This copies strings stored inline.
+    pub fn into_byte_string(self) -> ByteString {
+        match self.0 {
+            Repr::Inline { .. } => ByteString::from(&*self),
+            Repr::Shared(string) => string,
+        }
+    }
+}
+
+impl Default for CompactByteString {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Eq for CompactByteString {}
+
+impl PartialEq for CompactByteString {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl PartialEq<str> for CompactByteString {
+    fn eq(&self, other: &str) -> bool {
+        &self[..] == other
+    }
+}
+
+impl PartialEq<&str> for CompactByteString {
+    fn eq(&self, other: &&str) -> bool {
+        &self[..] == *other
+    }
+}
+
+impl PartialEq<String> for CompactByteString {
+    fn eq(&self, other: &String) -> bool {
+        &self[..] == other
+    }
+}
+
+impl PartialEq<ByteString> for CompactByteString {
+    fn eq(&self, other: &ByteString) -> bool {
+        self[..] == other[..]
+    }
+}
+
+impl PartialOrd for CompactByteString {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for CompactByteString {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+impl hash::Hash for CompactByteString {
+    fn hash<H: hash::Hasher>(&self, state: &mut H) {
+        (**self).hash(state);
+    }
+}
+
+impl ops::Deref for CompactByteString {
+    type Target = str;
+
+    #[inline]
+    fn deref(&self) -> &str {
+        let bytes = self.as_bytes();
+        // SAFETY: UTF-8 validity is guaranteed during construction.
+        unsafe { str::from_utf8_unchecked(bytes) }
+    }
+}
+
+impl AsRef<[u8]> for CompactByteString {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl AsRef<str> for CompactByteString {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl Borrow<str> for CompactByteString {
+    fn borrow(&self) -> &str {
+        self
+    }
+}
+
+impl From<&str> for CompactByteString {
+    #[inline]
+    fn from(value: &str) -> Self {
+        Self(Repr::inline(value).unwrap_or_else(|| Repr::Shared(ByteString::from(value))))
+    }
+}
+
+impl From<String> for CompactByteString {
+    #[inline]
+    fn from(value: String) -> Self {
+        Self(Repr::inline(&value).unwrap_or_else(|| Repr::Shared(ByteString::from(value))))
+    }
+}
+
+impl From<Box<str>> for CompactByteString {
+    #[inline]
+    fn from(value: Box<str>) -> Self {
+        Self(Repr::inline(&value).unwrap_or_else(|| Repr::Shared(ByteString::from(value))))
+    }
+}
+
+/// Short strings are copied inline, releasing their reference to the buffer; longer strings are
+/// shared without copying.
+impl From<ByteString> for CompactByteString {
+    #[inline]
+    fn from(value: ByteString) -> Self {
+        Self(Repr::from_byte_string(value))
+    }
+}
+
+impl From<CompactByteString> for ByteString {
+    #[inline]
+    fn from(value: CompactByteString) -> Self {
+        value.into_byte_string()
+    }
+}
+
+/// Short strings are copied inline; longer strings are shared without copying.
+impl TryFrom<Bytes> for CompactByteString {
+    type Error = str::Utf8Error;
+
+    #[inline]
+    fn try_from(value: Bytes) -> Result<Self, Self::Error> {
+        ByteString::try_from(value).map(Self::from)
+    }
+}
+
+impl TryFrom<&[u8]> for CompactByteString {
+    type Error = str::Utf8Error;
+
+    #[inline]
+    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+        str::from_utf8(value).map(Self::from)
+    }
+}
+
+impl fmt::Debug for CompactByteString {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(fmt)
+    }
+}
+
+impl fmt::Display for CompactByteString {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (**self).fmt(fmt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{borrow::ToOwned, format};
+    use core::panic::{RefUnwindSafe, UnwindSafe};
+
+    use static_assertions::assert_impl_all;
+
+    use super::*;
+
+    assert_impl_all!(CompactByteString: Send, Sync, Unpin, Clone, Default, Eq, Ord, hash::Hash);
+    assert_impl_all!(CompactByteString: fmt::Debug, fmt::Display, UnwindSafe, RefUnwindSafe);
+
+    #[test]
+    fn inline_short_strings() {
+        let short = "a".repeat(INLINE_CAP);
+        let long = "a".repeat(INLINE_CAP + 1);
+
+        assert!(CompactByteString::new().is_inline());
+        assert!(CompactByteString::from(short.as_str()).is_inline());
+        assert!(CompactByteString::from(short.clone()).is_inline());
+        assert!(CompactByteString::try_from(Bytes::from(short.clone()))
+            .unwrap()
+            .is_inline());
+        assert!(!CompactByteString::from(long.as_str()).is_inline());
+        assert!(!CompactByteString::from(long.clone()).is_inline());
+
+        // static strings are never copied
+        assert!(!CompactByteString::from_static("static").is_inline());
+
+        assert_eq!(CompactByteString::from(short.as_str()), short);
+        assert_eq!(CompactByteString::from(long.as_str()), long);
+    }
+
+    #[test]
+    fn large_strings_are_shared() {
+        let bytes = Bytes::from("a".repeat(INLINE_CAP + 1));
+        let ptr = bytes.as_ptr();
+
+        let string = CompactByteString::try_from(bytes).unwrap();
+        assert_eq!(string.as_ptr(), ptr);
+        assert_eq!(string.into_byte_string().as_ptr(), ptr);
+    }
+
+    #[test]
+    fn into_byte_string() {
+        let string = CompactByteString::from("hello").into_byte_string();
+        assert_eq!(string, "hello");
+
+        let string: ByteString = CompactByteString::from_static("static").into();
+        assert_eq!(string, "static");
+    }
+
+    #[test]
+    fn ordering_is_consistent_between_representations() {
+        let a = CompactByteString::from("a");
+        let b = CompactByteString::from_static("b");
+        assert!(a < b);
+        assert_eq!(CompactByteString::from("b"), b);
+    }
+
+    #[test]
+    fn try_from_slice() {
+        assert_eq!(CompactByteString::try_from(&b"bytes"[..]).unwrap(), "bytes");
+        CompactByteString::try_from(&[0, 159, 146, 150][..]).unwrap_err();
+    }
+
+    #[test]
+    fn fmt() {
+        let string = CompactByteString::from("baz");
+        assert_eq!(format!("{string}"), "baz");
+        assert_eq!(format!("{string:?}"), r#""baz""#);
+        assert_eq!(string, "baz".to_owned());
+    }
+}
@@ -0,0 +1,172 @@
+//! Bounded pool of shared strings.
+
+use alloc::collections::BTreeMap;
+use core::fmt;
+
+use crate::ByteString;
+
+/// A bounded pool that deduplicates strings into shared [`ByteString`]s.
+///
+/// Interning a string returns a `ByteString` sharing the buffer of any equal string interned
+/// before, so that strings seen repeatedly, such as header values or hostnames, are stored once.
+/// When the pool is full, the least recently interned string is evicted; strings handed out
+/// before remain valid.
+///
+/// Lookups and insertions are `O(log n)` in the number of pooled strings. The pool is not
+/// synchronized; wrap it in a lock, or keep one per thread, to share it.
+///
+/// # Examples
+/// ```
+/// use bytestring::ByteStringInterner;
+///
+/// let mut pool = ByteStringInterner::new(1024);
+///
+/// let a = pool.intern("a rather long hostname.example.com");
+/// let b = pool.intern("a rather long hostname.example.com");
+/// assert_eq!(a.as_ptr(), b.as_ptr());
+/// assert_eq!(pool.len(), 1);
+/// ```
+pub struct ByteStringInterner {
+    capacity: usize,
+    tick: u64,
+    strings: BTreeMap<ByteString, u64>,
+    recency: BTreeMap<u64, ByteString>,
+}
+
+impl ByteStringInterner {
+    /// Creates a new empty pool holding at most `capacity` strings.
+    ///
+    /// # Panics
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "interner capacity must be non-zero");
+
+        Self {
+            capacity,
+            tick: 0,
+            strings: BTreeMap::new(),
+            recency: BTreeMap::new(),
+        }
+    }
+
+    /// Returns the maximum number of strings in the pool.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of strings in the pool.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    /// Returns true if the pool is empty.
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    /// Returns a pooled `ByteString` equal to `string`, adding it to the pool if not present.
+    pub fn intern(&mut self, string: &str) -> ByteString {
+        self.tick += 1;
+        let tick = self.tick;
+
+        if let Some((pooled, last_used)) = self.strings.get_key_value(string) {
+            let pooled = pooled.clone();
+            let last_used = *last_used;
+
+            self.recency.remove(&last_used);
+            self.recency.insert(tick, pooled.clone());
+            self.strings.insert(pooled.clone(), tick);
+
+            return pooled;
+        }
+
+        if self.strings.len() == self.capacity {
+            self.evict_oldest();
+        }
+
+        let pooled = ByteString::from(string);
+        self.strings.insert(pooled.clone(), tick);
+        self.recency.insert(tick, pooled.clone());
+
+        pooled
+    }
+
+    /// Returns true if a string equal to `string` is in the pool.
+    ///
+    /// This does not count as a use of the string.
+    pub fn contains(&self, string: &str) -> bool {
+        self.strings.contains_key(string)
+    }
+
+    /// Removes all strings from the pool.
+    pub fn clear(&mut self) {
+        self.strings.clear();
+        self.recency.clear();
+    }
+
+    fn evict_oldest(&mut self) {
+        let oldest = self.recency.keys().next().copied();
+
+        if let Some(string) = oldest.and_then(|tick| self.recency.remove(&tick)) {
+            self.strings.remove(&string);
+        }
+    }
+}
+
+impl fmt::Debug for ByteStringInterner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ByteStringInterner")
+            .field("capacity", &self.capacity)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+
+    use super::*;
+
+    fn long(n: usize) -> alloc::string::String {
+        format!("a string long enough to be worth sharing #{n}")
+    }
+
+    #[test]
+    fn deduplicates() {
+        let mut pool = ByteStringInterner::new(4);
+
+        let a = pool.intern(&long(1));
+        let b = pool.intern(&long(1));
+        assert_eq!(a, b);
+        assert_eq!(a.as_ptr(), b.as_ptr());
+        assert_eq!(pool.len(), 1);
+
+        pool.clear();
+        assert!(pool.is_empty());
+        assert_ne!(pool.intern(&long(1)).as_ptr(), a.as_ptr());
+    }
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut pool = ByteStringInterner::new(2);
+
+        pool.intern(&long(1));
+        pool.intern(&long(2));
+
+        // use 1 again, making 2 the least recently used
+        pool.intern(&long(1));
+        pool.intern(&long(3));
+
+        assert_eq!(pool.len(), 2);
+        assert!(pool.contains(&long(1)));
+        assert!(!pool.contains(&long(2)));
+        assert!(pool.contains(&long(3)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_capacity() {
+        ByteStringInterner::new(0);
+    }
+}
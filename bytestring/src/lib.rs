@@ -11,11 +11,25 @@ use alloc::{
     string::{String, ToString},
     vec::Vec,
 };
-use core::{borrow::Borrow, convert::TryFrom, fmt, hash, ops, str};
+use core::{
+    borrow::Borrow,
+    convert::TryFrom,
+    fmt, hash,
+    ops::{self, Bound, RangeBounds},
+    str,
+};
 
 use bytes::Bytes;
 
+mod builder;
+mod compact;
+mod interner;
+
+pub use self::{builder::ByteStringMut, compact::CompactByteString, interner::ByteStringInterner};
+
 /// An immutable UTF-8 encoded string with [`Bytes`] as a storage.
+///
+/// See [`CompactByteString`] for a variant that stores short strings inline.
 #[derive(Clone, Default, Eq, PartialOrd, Ord)]
 pub struct ByteString(Bytes);
 
@@ -51,6 +65,94 @@ impl ByteString {
         Self(src)
     }
 
+    /// Creates a new `ByteString` from `Bytes`, replacing invalid UTF-8 sequences with
+    /// [`U+FFFD REPLACEMENT CHARACTER`][char::REPLACEMENT_CHARACTER].
+    ///
+    /// Valid input is not copied. Input containing invalid sequences is copied into a new
+    /// buffer, like [`String::from_utf8_lossy`].
+    ///
+    /// # Examples
+    /// ```
+    /// # use bytes::Bytes;
+    /// # use bytestring::ByteString;
+    /// let string = ByteString::from_utf8_lossy(Bytes::from_static(b"Hello \xF0\x90\x80World"));
+    /// assert_eq!(string, "Hello \u{FFFD}World");
+    /// ```
+    pub fn from_utf8_lossy(src: Bytes) -> ByteString {
+        match str::from_utf8(&src) {
+            Ok(_) => Self(src),
+            Err(_) => Self::from(String::from_utf8_lossy(&src).into_owned()),
+        }
+    }
+
+    /// Creates a new `ByteString` from the longest valid UTF-8 prefix of `src`, returning it along
+    /// with the remaining bytes.
+    ///
+    /// The remainder starts at the first invalid or incomplete sequence, and is empty if all of
+    /// `src` is valid UTF-8. Neither part is copied. This is useful when decoding text that arrives in chunks, where a multi-byte
+    /// character may be split across chunks.
+    ///
+    /// # Examples
+    /// ```
+    /// # use bytes::Bytes;
+    /// # use bytestring::ByteString;
+    /// // "é" is encoded as two bytes, only one of which has arrived
+    /// let (string, rest) = ByteString::from_utf8_until_invalid(Bytes::from_static(b"caf\xC3"));
+    /// assert_eq!(string, "caf");
+    /// assert_eq!(rest, b"\xC3"[..]);
+    /// ```
+    pub fn from_utf8_until_invalid(mut src: Bytes) -> (ByteString, Bytes) {
+        let valid_up_to = match str::from_utf8(&src) {
+            Ok(_) => src.len(),
+            Err(err) => err.valid_up_to(),
+        };
+
+        let rest = src.split_off(valid_up_to);
+
+        // bytes before `valid_up_to` were validated above
+        (Self(src), rest)
+    }
+
+    /// Returns a slice of this byte string for the given byte range.
+    ///
+    /// The returned `ByteString` shares the underlying buffer.
+    ///
+    /// Corresponds to [`Bytes::slice`].
+    ///
+    /// This operation is `O(1)`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is out of bounds or if either end does not lie on a `char` boundary,
+    /// like indexing a `str`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use bytestring::ByteString;
+    /// let string = ByteString::from_static("GET /index.html");
+    /// assert_eq!(string.slice(..3), "GET");
+    /// assert_eq!(string.slice(4..), "/index.html");
+    /// ```
+    pub fn slice(&self, range: impl RangeBounds<usize>) -> Self {
+        let start = match range.start_bound() {
+            Bound::Included(&n) => n,
+            Bound::Excluded(&n) => n.checked_add(1).expect("out of range"),
+            Bound::Unbounded => 0,
+        };
+
+        let end = match range.end_bound() {
+            Bound::Included(&n) => n.checked_add(1).expect("out of range"),
+            Bound::Excluded(&n) => n,
+            Bound::Unbounded => self.len(),
+        };
+
+        // checks bounds and char boundaries
+        let subset = &self[start..end];
+
+        self.slice_ref(subset)
+    }
+
     /// Returns a new byte string that is equivalent to the given `subset`.
     ///
     /// When processing a `ByteString` buffer with other tools, one often gets a `&str` which is in
@@ -291,6 +393,74 @@ mod serde {
     }
 }
 
+#[cfg(feature = "rkyv")]
+mod rkyv {
+    use rkyv::{
+        string::{ArchivedString, StringResolver},
+        Archive, Deserialize, DeserializeUnsized, Fallible, Serialize, SerializeUnsized,
+    };
+
+    use super::ByteString;
+
+    impl Archive for ByteString {
+        type Archived = ArchivedString;
+        type Resolver = StringResolver;
+
+        #[inline]
+        unsafe fn resolve(&self, pos: usize, resolver: Self::Resolver, out: *mut Self::Archived) {
+            ArchivedString::resolve_from_str(self, pos, resolver, out);
+        }
+    }
+
+    impl<S: Fallible + ?Sized> Serialize<S> for ByteString
+    where
+        str: SerializeUnsized<S>,
+    {
+        #[inline]
+        fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+            ArchivedString::serialize_from_str(self, serializer)
+        }
+    }
+
+    impl<D: Fallible + ?Sized> Deserialize<ByteString, D> for ArchivedString
+    where
+        str: DeserializeUnsized<str, D>,
+    {
+        #[inline]
+        fn deserialize(&self, _: &mut D) -> Result<ByteString, D::Error> {
+            Ok(ByteString::from(self.as_str()))
+        }
+    }
+
+    impl PartialEq<ByteString> for ArchivedString {
+        #[inline]
+        fn eq(&self, other: &ByteString) -> bool {
+            self.as_str() == &**other
+        }
+    }
+
+    #[cfg(test)]
+    mod rkyv_impl_tests {
+        use rkyv::Infallible;
+
+        use super::*;
+
+        #[test]
+        fn round_trip() {
+            let string = ByteString::from_static("nice bytes");
+
+            let bytes = rkyv::to_bytes::<_, 256>(&string).unwrap();
+            // SAFETY: bytes were produced by serializing a `ByteString` above
+            let archived = unsafe { rkyv::archived_root::<ByteString>(&bytes) };
+            assert_eq!(archived.as_str(), "nice bytes");
+            assert_eq!(*archived, string);
+
+            let deserialized: ByteString = archived.deserialize(&mut Infallible).unwrap();
+            assert_eq!(deserialized, string);
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use alloc::{borrow::ToOwned, format, vec};
@@ -331,6 +501,67 @@ mod test {
         assert_eq!(buf.as_bytes(), "hello");
     }
 
+    #[test]
+    fn from_utf8_lossy() {
+        let bytes = Bytes::from_static(b"valid");
+        let ptr = bytes.as_ptr();
+        let string = ByteString::from_utf8_lossy(bytes);
+        assert_eq!(string, "valid");
+        assert_eq!(string.as_ptr(), ptr);
+
+        let string = ByteString::from_utf8_lossy(Bytes::from_static(b"foo\xFFbar"));
+        assert_eq!(string, "foo\u{FFFD}bar");
+    }
+
+    #[test]
+    fn from_utf8_until_invalid() {
+        let (string, rest) = ByteString::from_utf8_until_invalid(Bytes::from_static(b"hello"));
+        assert_eq!(string, "hello");
+        assert!(rest.is_empty());
+
+        let (string, rest) = ByteString::from_utf8_until_invalid(Bytes::from_static(b"a\xFFb"));
+        assert_eq!(string, "a");
+        assert_eq!(rest, b"\xFFb"[..]);
+
+        let (string, rest) = ByteString::from_utf8_until_invalid(Bytes::from_static(b"\xE2\x82"));
+        assert!(string.is_empty());
+        assert_eq!(rest, b"\xE2\x82"[..]);
+    }
+
+    #[test]
+    fn slice() {
+        let string = ByteString::from("a string");
+        let ptr = string.as_ptr();
+        assert_eq!(string.slice(2..8), "string");
+        assert_eq!(string.slice(2..).as_ptr(), ptr.wrapping_add(2));
+        assert_eq!(string.slice(..=0), "a");
+        assert_eq!(string.slice(..), string);
+
+        let string = ByteString::from("héllo");
+        assert_eq!(string.slice(1..3), "é");
+        assert_eq!(string.slice(3..3), "");
+    }
+
+    #[test]
+    fn slice_ref_shares_buffer() {
+        let string = ByteString::from(" foo ");
+        let substring = string.slice_ref(string.trim());
+        assert_eq!(substring, "foo");
+        assert_eq!(substring.as_ptr(), string.as_ptr().wrapping_add(1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn slice_catches_char_boundary() {
+        ByteString::from("héllo").slice(..2);
+    }
+
+    #[test]
+    #[should_panic]
+    fn slice_catches_out_of_bounds() {
+        ByteString::from_static("foo").slice(2..4);
+    }
+
     #[test]
     fn from_bytes_unchecked() {
         let buf = unsafe { ByteString::from_bytes_unchecked(Bytes::new()) };
@@ -0,0 +1,429 @@
+//! A non-thread-safe multi-producer, multi-consumer, futures-aware broadcast channel.
+//!
+//! Every value sent is seen by every [Receiver] that existed when it was sent. Values are cloned
+//! out of a buffer retaining the most recently sent values, so a receiver that falls behind by more
+//! than the buffer's capacity misses the oldest values; see [`LagPolicy`].
+
+use alloc::{collections::VecDeque, rc::Rc};
+use core::{
+    cell::RefCell,
+    fmt,
+    task::{Context, Poll},
+};
+use std::error::Error;
+
+use futures_util::future::poll_fn;
+use local_waker::LocalWakerSet;
+
+/// Creates a broadcast channel retaining up to `capacity` values, reporting lag to receivers.
+///
+/// Equivalent to `channel_with_policy(capacity, LagPolicy::Report)`.
+///
+/// # Panics
+/// Panics if `capacity` is zero.
+pub fn channel<T: Clone>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    channel_with_policy(capacity, LagPolicy::Report)
+}
+
+/// Creates a broadcast channel retaining up to `capacity` values, handling lagging receivers
+/// according to `policy`.
+///
+/// [Sender]s and [Receiver]s are `!Send`.
+///
+/// # Panics
+/// Panics if `capacity` is zero.
+pub fn channel_with_policy<T: Clone>(
+    capacity: usize,
+    policy: LagPolicy,
+) -> (Sender<T>, Receiver<T>) {
+    assert!(capacity > 0, "broadcast channel capacity must be non-zero");
+
+    let shared = Rc::new(RefCell::new(Shared {
+        buffer: VecDeque::with_capacity(capacity),
+        capacity,
+        head: 0,
+        senders: 1,
+        receivers: 1,
+        policy,
+        blocked_recv: LocalWakerSet::new(),
+    }));
+
+    let sender = Sender {
+        shared: shared.clone(),
+    };
+
+    let receiver = Receiver { shared, next: 0 };
+
+    (sender, receiver)
+}
+
+/// How receivers that fall behind by more than the channel's capacity are handled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LagPolicy {
+    /// Lagging receivers return [`RecvError::Lagged`] with the number of missed values, then
+    /// continue from the oldest retained value.
+    Report,
+
+    /// Lagging receivers silently continue from the oldest retained value.
+    Skip,
+}
+
+#[derive(Debug)]
+struct Shared<T> {
+    buffer: VecDeque<T>,
+    capacity: usize,
+    /// Position of the first value in the buffer.
+    head: u64,
+    senders: usize,
+    receivers: usize,
+    policy: LagPolicy,
+    blocked_recv: LocalWakerSet,
+}
+
+impl<T> Shared<T> {
+    /// Position of the next value to be sent.
+    fn tail(&self) -> u64 {
+        self.head + self.buffer.len() as u64
+    }
+}
+
+/// The transmission end of a broadcast channel.
+///
+/// This is created by the [`channel`] function.
+#[derive(Debug)]
+pub struct Sender<T> {
+    shared: Rc<RefCell<Shared<T>>>,
+}
+
+impl<T> Unpin for Sender<T> {}
+
+impl<T> Sender<T> {
+    /// Sends a value to all current receivers.
+    ///
+    /// Returns the number of receivers the value was sent to, or the value back if there are no
+    /// receivers. Sending never waits; when the buffer is full the oldest value is dropped.
+    pub fn send(&self, value: T) -> Result<usize, SendError<T>> {
+        let mut shared = self.shared.borrow_mut();
+
+        if shared.receivers == 0 {
+            return Err(SendError(value));
+        }
+
+        if shared.buffer.len() == shared.capacity {
+            shared.buffer.pop_front();
+            shared.head += 1;
+        }
+
+        shared.buffer.push_back(value);
+        shared.blocked_recv.wake_all();
+
+        Ok(shared.receivers)
+    }
+
+    /// Creates a new [Receiver] that sees all values sent from now on.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut shared = self.shared.borrow_mut();
+        shared.receivers += 1;
+
+        Receiver {
+            shared: self.shared.clone(),
+            next: shared.tail(),
+        }
+    }
+
+    /// Returns the number of receivers.
+    pub fn receiver_count(&self) -> usize {
+        self.shared.borrow().receivers
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.borrow_mut().senders += 1;
+
+        Sender {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.borrow_mut();
+        shared.senders -= 1;
+
+        if shared.senders == 0 {
+            // wake up receivers as the channel is closed
+            shared.blocked_recv.wake_all();
+        }
+    }
+}
+
+/// The receiving end of a broadcast channel.
+///
+/// This is created by the [`channel`] function or by [`Sender::subscribe`]. Cloning a receiver
+/// creates another that will see the same values from the current position onwards.
+#[derive(Debug)]
+pub struct Receiver<T> {
+    shared: Rc<RefCell<Shared<T>>>,
+    /// Position of the next value to receive.
+    next: u64,
+}
+
+impl<T> Unpin for Receiver<T> {}
+
+impl<T: Clone> Receiver<T> {
+    /// Receives the next value.
+    ///
+    /// Returns [`RecvError::Closed`] once all [Sender]s have been dropped and all retained values
+    /// have been received.
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Receives the next value without waiting.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        match self.next_value() {
+            Some(Ok(value)) => Ok(value),
+            Some(Err(RecvError::Lagged(n))) => Err(TryRecvError::Lagged(n)),
+            Some(Err(RecvError::Closed)) => Err(TryRecvError::Closed),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Polls for the next value.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, RecvError>> {
+        match self.next_value() {
+            Some(res) => Poll::Ready(res),
+            None => {
+                self.shared.borrow().blocked_recv.register(cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+
+    /// Returns the next value or error, or `None` if the receiver must wait.
+    fn next_value(&mut self) -> Option<Result<T, RecvError>> {
+        let shared = self.shared.borrow();
+
+        if self.next < shared.head {
+            let missed = shared.head - self.next;
+            self.next = shared.head;
+
+            if shared.policy == LagPolicy::Report {
+                return Some(Err(RecvError::Lagged(missed)));
+            }
+        }
+
+        if self.next < shared.tail() {
+            let value = shared.buffer[(self.next - shared.head) as usize].clone();
+            self.next += 1;
+            return Some(Ok(value));
+        }
+
+        if shared.senders == 0 {
+            return Some(Err(RecvError::Closed));
+        }
+
+        None
+    }
+}
+
+impl<T> Receiver<T> {
+    /// Returns the number of values sent that this receiver has not yet received, including any
+    /// it has missed by lagging.
+    pub fn len(&self) -> usize {
+        (self.shared.borrow().tail() - self.next) as usize
+    }
+
+    /// Returns true if there are no values waiting to be received.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.shared.borrow_mut().receivers += 1;
+
+        Receiver {
+            shared: self.shared.clone(),
+            next: self.next,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.borrow_mut();
+        shared.receivers -= 1;
+
+        if shared.receivers == 0 {
+            // nobody can receive retained values anymore
+            shared.head = shared.tail();
+            shared.buffer.clear();
+        }
+    }
+}
+
+/// Error returned when sending on a channel without receivers.
+///
+/// Allows access to the value that failed to send with [`into_inner`](Self::into_inner).
+pub struct SendError<T>(pub T);
+
+impl<T> SendError<T> {
+    /// Returns the value that was attempted to be sent but failed.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_tuple("SendError").field(&"...").finish()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "send failed because there are no receivers")
+    }
+}
+
+impl<T> Error for SendError<T> {}
+
+/// Error returned by [`Receiver::recv`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecvError {
+    /// All senders have been dropped and all retained values have been received.
+    Closed,
+
+    /// The receiver fell behind and missed the given number of values.
+    ///
+    /// The next receive returns the oldest retained value.
+    Lagged(u64),
+}
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecvError::Closed => write!(fmt, "channel closed"),
+            RecvError::Lagged(n) => write!(fmt, "receiver lagged by {n} values"),
+        }
+    }
+}
+
+impl Error for RecvError {}
+
+/// Error returned by [`Receiver::try_recv`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// No value is waiting to be received.
+    Empty,
+
+    /// All senders have been dropped and all retained values have been received.
+    Closed,
+
+    /// The receiver fell behind and missed the given number of values.
+    ///
+    /// The next receive returns the oldest retained value.
+    Lagged(u64),
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => write!(fmt, "channel empty"),
+            TryRecvError::Closed => write!(fmt, "channel closed"),
+            TryRecvError::Lagged(n) => write!(fmt, "receiver lagged by {n} values"),
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::lazy;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_broadcast() {
+        let (tx, mut rx1) = channel(4);
+        let mut rx2 = tx.subscribe();
+        assert_eq!(tx.receiver_count(), 2);
+
+        assert_eq!(tx.send("a").unwrap(), 2);
+        tx.send("b").unwrap();
+        assert_eq!(rx1.len(), 2);
+
+        assert_eq!(rx1.recv().await, Ok("a"));
+        assert_eq!(rx1.recv().await, Ok("b"));
+        assert_eq!(rx2.recv().await, Ok("a"));
+
+        // late subscribers do not see earlier values
+        let mut rx3 = tx.subscribe();
+        assert_eq!(rx3.try_recv(), Err(TryRecvError::Empty));
+
+        drop(tx);
+        assert_eq!(rx2.recv().await, Ok("b"));
+        assert_eq!(rx2.recv().await, Err(RecvError::Closed));
+        assert_eq!(rx3.recv().await, Err(RecvError::Closed));
+    }
+
+    #[tokio::test]
+    async fn test_lagged() {
+        let (tx, mut rx) = channel(2);
+        let (skip_tx, mut skip_rx) = channel_with_policy(2, LagPolicy::Skip);
+
+        for i in 0..5 {
+            tx.send(i).unwrap();
+            skip_tx.send(i).unwrap();
+        }
+
+        assert_eq!(rx.recv().await, Err(RecvError::Lagged(3)));
+        assert_eq!(rx.recv().await, Ok(3));
+        assert_eq!(rx.recv().await, Ok(4));
+
+        assert_eq!(skip_rx.recv().await, Ok(3));
+        assert_eq!(skip_rx.try_recv(), Ok(4));
+    }
+
+    #[tokio::test]
+    async fn test_wakes_receivers() {
+        let (tx, mut rx1) = channel(1);
+        let mut rx2 = rx1.clone();
+
+        assert!(lazy(|cx| rx1.poll_recv(cx)).await.is_pending());
+        assert!(lazy(|cx| rx2.poll_recv(cx)).await.is_pending());
+
+        let local = tokio::task::LocalSet::new();
+
+        local
+            .run_until(async move {
+                let rx1 = tokio::task::spawn_local(async move { rx1.recv().await });
+                let rx2 = tokio::task::spawn_local(async move { rx2.recv().await });
+
+                tokio::task::yield_now().await;
+                tx.send("shutdown").unwrap();
+
+                assert_eq!(rx1.await.unwrap(), Ok("shutdown"));
+                assert_eq!(rx2.await.unwrap(), Ok("shutdown"));
+            })
+            .await;
+    }
+
+    #[test]
+    fn test_no_receivers() {
+        let (tx, rx) = channel(2);
+        tx.send(1).unwrap();
+        drop(rx);
+        assert_eq!(tx.send(2).unwrap_err().into_inner(), 2);
+
+        let mut rx = tx.subscribe();
+        tx.send(3).unwrap();
+        assert_eq!(rx.try_recv(), Ok(3));
+    }
+}
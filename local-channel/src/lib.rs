@@ -5,4 +5,7 @@
 
 extern crate alloc;
 
+pub mod broadcast;
 pub mod mpsc;
+pub mod oneshot;
+pub mod watch;
@@ -1,6 +1,6 @@
 //! A non-thread-safe multi-producer, single-consumer, futures-aware, FIFO queue.
 
-use alloc::{collections::VecDeque, rc::Rc};
+use alloc::{collections::VecDeque, rc::Rc, vec::Vec};
 use core::{
     cell::RefCell,
     fmt,
@@ -12,17 +12,13 @@ use std::error::Error;
 use futures_core::stream::Stream;
 use futures_sink::Sink;
 use futures_util::future::poll_fn;
-use local_waker::LocalWaker;
+use local_waker::{LocalWaker, LocalWakerSet};
 
 /// Creates a unbounded in-memory channel with buffered storage.
 ///
 /// [Sender]s and [Receiver]s are `!Send`.
 pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
-    let shared = Rc::new(RefCell::new(Shared {
-        has_receiver: true,
-        buffer: VecDeque::new(),
-        blocked_recv: LocalWaker::new(),
-    }));
+    let shared = Shared::new(usize::MAX);
 
     let sender = Sender {
         shared: shared.clone(),
@@ -33,13 +29,72 @@ pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
     (sender, receiver)
 }
 
+/// Creates a bounded in-memory channel that buffers at most `capacity` messages.
+///
+/// Sending waits while the buffer is full, applying backpressure to producers that outpace the
+/// [Receiver]. [BoundedSender]s and [Receiver]s are `!Send`.
+///
+/// # Panics
+/// Panics if `capacity` is zero.
+pub fn bounded<T>(capacity: usize) -> (BoundedSender<T>, Receiver<T>) {
+    assert!(capacity > 0, "channel capacity must be non-zero");
+
+    let shared = Shared::new(capacity);
+
+    let sender = BoundedSender {
+        shared: shared.clone(),
+    };
+
+    let receiver = Receiver { shared };
+
+    (sender, receiver)
+}
+
 #[derive(Debug)]
 struct Shared<T> {
     buffer: VecDeque<T>,
+    capacity: usize,
     blocked_recv: LocalWaker,
+    blocked_send: LocalWakerSet,
     has_receiver: bool,
 }
 
+impl<T> Shared<T> {
+    fn new(capacity: usize) -> Rc<RefCell<Self>> {
+        Rc::new(RefCell::new(Shared {
+            has_receiver: true,
+            buffer: VecDeque::new(),
+            capacity,
+            blocked_recv: LocalWaker::new(),
+            blocked_send: LocalWakerSet::new(),
+        }))
+    }
+
+    fn is_full(&self) -> bool {
+        self.buffer.len() >= self.capacity
+    }
+
+    /// Prevents further messages from being sent, waking senders waiting for capacity and the
+    /// receiver if it is waiting for messages.
+    fn close(&mut self) {
+        self.has_receiver = false;
+        self.blocked_send.wake_all();
+        self.blocked_recv.wake();
+    }
+}
+
+/// Wakes the receiver if `sender` is the last sender and is about to drop.
+fn drop_sender<T>(sender: &Rc<RefCell<Shared<T>>>) {
+    let count = Rc::strong_count(sender);
+    let shared = sender.borrow_mut();
+
+    // check is last sender is about to drop
+    if shared.has_receiver && count == 2 {
+        // Wake up receiver as its stream has ended
+        shared.blocked_recv.wake();
+    }
+}
+
 /// The transmission end of a channel.
 ///
 /// This is created by the `channel` function.
@@ -71,7 +126,7 @@ impl<T> Sender<T> {
     /// This prevents any further messages from being sent on the channel, by any sender, while
     /// still enabling the receiver to drain messages that are already buffered.
     pub fn close(&mut self) {
-        self.shared.borrow_mut().has_receiver = false;
+        self.shared.borrow_mut().close();
     }
 }
 
@@ -105,17 +160,141 @@ impl<T> Sink<T> for Sender<T> {
 
 impl<T> Drop for Sender<T> {
     fn drop(&mut self) {
-        let count = Rc::strong_count(&self.shared);
-        let shared = self.shared.borrow_mut();
+        drop_sender(&self.shared);
+    }
+}
+
+/// The transmission end of a bounded channel.
+///
+/// This is created by the [`bounded`] function.
+#[derive(Debug)]
+pub struct BoundedSender<T> {
+    shared: Rc<RefCell<Shared<T>>>,
+}
+
+impl<T> Unpin for BoundedSender<T> {}
+
+impl<T> BoundedSender<T> {
+    /// Sends the provided message along this channel, waiting for capacity if the buffer is full.
+    ///
+    /// Returns the message back if the [Receiver] is dropped or the channel is closed, including
+    /// while waiting.
+    pub async fn send(&self, item: T) -> Result<(), SendError<T>> {
+        let mut item = Some(item);
+
+        poll_fn(|cx| match self.poll_reserve(cx) {
+            Poll::Ready(Ok(())) => {
+                self.push(item.take().unwrap());
+                Poll::Ready(Ok(()))
+            }
+            Poll::Ready(Err(())) => Poll::Ready(Err(SendError(item.take().unwrap()))),
+            Poll::Pending => Poll::Pending,
+        })
+        .await
+    }
+
+    /// Attempts to send the provided message along this channel without waiting.
+    pub fn try_send(&self, item: T) -> Result<(), TrySendError<T>> {
+        let shared = self.shared.borrow();
+
+        if !shared.has_receiver {
+            return Err(TrySendError::Closed(item));
+        }
 
-        // check is last sender is about to drop
-        if shared.has_receiver && count == 2 {
-            // Wake up receiver as its stream has ended
-            shared.blocked_recv.wake();
+        if shared.is_full() {
+            return Err(TrySendError::Full(item));
+        }
+
+        drop(shared);
+        self.push(item);
+
+        Ok(())
+    }
+
+    /// Returns the number of messages that can be sent before the buffer is full.
+    ///
+    /// Returns 0 while the buffer is overfilled by messages sent through [`Receiver::sender`].
+    pub fn capacity(&self) -> usize {
+        let shared = self.shared.borrow();
+        shared.capacity.saturating_sub(shared.buffer.len())
+    }
+
+    /// Returns the maximum number of buffered messages, as given when creating the channel.
+    pub fn max_capacity(&self) -> usize {
+        self.shared.borrow().capacity
+    }
+
+    /// Closes the sender half.
+    ///
+    /// This prevents any further messages from being sent on the channel, by any sender, while
+    /// still enabling the receiver to drain messages that are already buffered.
+    pub fn close(&mut self) {
+        self.shared.borrow_mut().close();
+    }
+
+    /// Polls for capacity to send a message.
+    ///
+    /// Returns an error if the [Receiver] is dropped or the channel is closed.
+    fn poll_reserve(&self, cx: &mut Context<'_>) -> Poll<Result<(), ()>> {
+        let shared = self.shared.borrow();
+
+        if !shared.has_receiver {
+            Poll::Ready(Err(()))
+        } else if shared.is_full() {
+            shared.blocked_send.register(cx.waker());
+            Poll::Pending
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn push(&self, item: T) {
+        let mut shared = self.shared.borrow_mut();
+        shared.buffer.push_back(item);
+        shared.blocked_recv.wake();
+    }
+}
+
+impl<T> Clone for BoundedSender<T> {
+    fn clone(&self) -> Self {
+        BoundedSender {
+            shared: self.shared.clone(),
         }
     }
 }
 
+impl<T> Sink<T> for BoundedSender<T> {
+    type Error = SendError<()>;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.poll_reserve(cx).map_err(|()| SendError(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), Self::Error> {
+        if !self.shared.borrow().has_receiver {
+            return Err(SendError(()));
+        }
+
+        // capacity is checked by `poll_ready`
+        self.push(item);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T> Drop for BoundedSender<T> {
+    fn drop(&mut self) {
+        drop_sender(&self.shared);
+    }
+}
+
 /// The receiving end of a channel which implements the `Stream` trait.
 ///
 /// This is created by the [`channel`] function.
@@ -135,7 +314,87 @@ impl<T> Receiver<T> {
         poll_fn(|cx| this.as_mut().poll_next(cx)).await
     }
 
+    /// Receives up to `limit` values into `buf`, waiting until at least one value is available.
+    ///
+    /// Returns the number of values received, which is only zero if `limit` is zero or the channel
+    /// is empty and closed, as for [`recv`](Self::recv) returning `None`.
+    pub async fn recv_many(&mut self, buf: &mut Vec<T>, limit: usize) -> usize {
+        if limit == 0 {
+            return 0;
+        }
+
+        poll_fn(|cx| self.poll_recv_many(cx, buf, limit)).await
+    }
+
+    /// Polls to receive up to `limit` values into `buf`.
+    ///
+    /// See [`recv_many`](Self::recv_many).
+    pub fn poll_recv_many(
+        &mut self,
+        cx: &mut Context<'_>,
+        buf: &mut Vec<T>,
+        limit: usize,
+    ) -> Poll<usize> {
+        let mut shared = self.shared.borrow_mut();
+
+        if !shared.buffer.is_empty() {
+            let count = limit.min(shared.buffer.len());
+            buf.extend(shared.buffer.drain(..count));
+            shared.blocked_send.wake_all();
+            return Poll::Ready(count);
+        }
+
+        if self.is_closed(&shared) || limit == 0 {
+            return Poll::Ready(0);
+        }
+
+        shared.blocked_recv.register(cx.waker());
+        Poll::Pending
+    }
+
+    /// Receives the next value without waiting.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut shared = self.shared.borrow_mut();
+
+        match shared.buffer.pop_front() {
+            Some(msg) => {
+                shared.blocked_send.wake_all();
+                Ok(msg)
+            }
+            None if self.is_closed(&shared) => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Returns the number of buffered values.
+    pub fn len(&self) -> usize {
+        self.shared.borrow().buffer.len()
+    }
+
+    /// Returns true if there are no buffered values.
+    pub fn is_empty(&self) -> bool {
+        self.shared.borrow().buffer.is_empty()
+    }
+
+    /// Closes the receiving half.
+    ///
+    /// This prevents any further messages from being sent on the channel, and wakes senders
+    /// waiting for capacity, while still enabling the receiver to drain messages that are already
+    /// buffered.
+    pub fn close(&mut self) {
+        self.shared.borrow_mut().close();
+    }
+
+    /// Returns true if no more messages can be sent, because all senders have been dropped or the
+    /// channel was closed.
+    fn is_closed(&self, shared: &Shared<T>) -> bool {
+        Rc::strong_count(&self.shared) == 1 || !shared.has_receiver
+    }
+
     /// Create an associated [Sender].
+    ///
+    /// Messages sent through a [Sender] are not limited by the capacity of a [`bounded`] channel,
+    /// though they count towards it.
     pub fn sender(&self) -> Sender<T> {
         Sender {
             shared: self.shared.clone(),
@@ -151,13 +410,13 @@ impl<T> Stream for Receiver<T> {
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
         let mut shared = self.shared.borrow_mut();
 
-        if Rc::strong_count(&self.shared) == 1 {
-            // All senders have been dropped, so drain the buffer and end the stream.
-            return Poll::Ready(shared.buffer.pop_front());
-        }
-
         if let Some(msg) = shared.buffer.pop_front() {
+            // woken senders race for the free slot; those that lose register again
+            shared.blocked_send.wake_all();
             Poll::Ready(Some(msg))
+        } else if self.is_closed(&shared) {
+            // no more values can be sent, so end the stream
+            Poll::Ready(None)
         } else {
             shared.blocked_recv.register(cx.waker());
             Poll::Pending
@@ -169,7 +428,7 @@ impl<T> Drop for Receiver<T> {
     fn drop(&mut self) {
         let mut shared = self.shared.borrow_mut();
         shared.buffer.clear();
-        shared.has_receiver = false;
+        shared.close();
     }
 }
 
@@ -199,8 +458,73 @@ impl<T> fmt::Display for SendError<T> {
 
 impl<T> Error for SendError<T> {}
 
+/// Error returned by [`Receiver::try_recv`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    /// The channel is empty.
+    Empty,
+
+    /// The channel is empty and all senders have been dropped or the channel was closed.
+    Disconnected,
+}
+
+impl fmt::Display for TryRecvError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TryRecvError::Empty => write!(fmt, "receiving failed because channel is empty"),
+            TryRecvError::Disconnected => {
+                write!(fmt, "receiving failed because channel is closed")
+            }
+        }
+    }
+}
+
+impl Error for TryRecvError {}
+
+/// Error returned by [`BoundedSender::try_send`].
+///
+/// Allows access to message that failed to send with [`into_inner`](Self::into_inner).
+pub enum TrySendError<T> {
+    /// The channel's buffer is full.
+    Full(T),
+
+    /// The channel's [Receiver] is dropped or the channel is closed.
+    Closed(T),
+}
+
+impl<T> TrySendError<T> {
+    /// Returns the message that was attempted to be sent but failed.
+    pub fn into_inner(self) -> T {
+        match self {
+            TrySendError::Full(item) | TrySendError::Closed(item) => item,
+        }
+    }
+}
+
+impl<T> fmt::Debug for TrySendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(_) => fmt.debug_tuple("Full").field(&"...").finish(),
+            TrySendError::Closed(_) => fmt.debug_tuple("Closed").field(&"...").finish(),
+        }
+    }
+}
+
+impl<T> fmt::Display for TrySendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TrySendError::Full(_) => write!(fmt, "send failed because channel is full"),
+            TrySendError::Closed(_) => write!(fmt, "send failed because receiver is gone"),
+        }
+    }
+}
+
+impl<T> Error for TrySendError<T> {}
+
 #[cfg(test)]
 mod tests {
+    use core::future::Future as _;
+
     use futures_util::{future::lazy, StreamExt as _};
 
     use super::*;
@@ -239,6 +563,145 @@ mod tests {
         assert!(tx2.send("test").is_err());
     }
 
+    #[tokio::test]
+    async fn test_bounded() {
+        let (tx, mut rx) = bounded(1);
+        assert_eq!(tx.max_capacity(), 1);
+
+        tx.try_send(1).unwrap();
+        assert_eq!(tx.capacity(), 0);
+        assert!(matches!(tx.try_send(2), Err(TrySendError::Full(2))));
+
+        // send waits for capacity
+        let mut send = Box::pin(tx.send(2));
+        assert!(lazy(|cx| send.as_mut().poll(cx)).await.is_pending());
+
+        assert_eq!(rx.recv().await, Some(1));
+        send.await.unwrap();
+
+        // sink readiness follows capacity
+        let mut sink = tx.clone();
+        assert!(lazy(|cx| Pin::new(&mut sink).poll_ready(cx))
+            .await
+            .is_pending());
+
+        assert_eq!(rx.recv().await, Some(2));
+        assert_eq!(tx.capacity(), 1);
+        assert!(lazy(|cx| Pin::new(&mut sink).poll_ready(cx))
+            .await
+            .is_ready());
+        drop(sink);
+
+        drop(tx);
+        assert_eq!(rx.recv().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_bounded_overfilled() {
+        let (tx, mut rx) = bounded(1);
+
+        // unbounded senders are not limited by capacity
+        let unbounded = rx.sender();
+        unbounded.send(1).unwrap();
+        unbounded.send(2).unwrap();
+        assert_eq!(tx.capacity(), 0);
+        assert!(matches!(tx.try_send(3), Err(TrySendError::Full(3))));
+
+        assert_eq!(rx.recv().await, Some(1));
+        assert_eq!(tx.capacity(), 0);
+        assert_eq!(rx.recv().await, Some(2));
+        assert_eq!(tx.capacity(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_bounded_closed() {
+        let (tx, rx) = bounded(1);
+        tx.try_send("test").unwrap();
+
+        let mut send = Box::pin(tx.send("test2"));
+        assert!(lazy(|cx| send.as_mut().poll(cx)).await.is_pending());
+
+        // waiting senders are notified when the receiver is dropped
+        drop(rx);
+        assert_eq!(send.await.unwrap_err().into_inner(), "test2");
+        assert!(matches!(
+            tx.try_send("test3"),
+            Err(TrySendError::Closed("test3"))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_bounded_across_tasks() {
+        let local = tokio::task::LocalSet::new();
+
+        local
+            .run_until(async {
+                let (tx, mut rx) = bounded(2);
+
+                let producer = tokio::task::spawn_local(async move {
+                    for i in 0..10 {
+                        tx.send(i).await.unwrap();
+                    }
+                });
+
+                for i in 0..10 {
+                    assert_eq!(rx.recv().await, Some(i));
+                }
+
+                producer.await.unwrap();
+                assert_eq!(rx.recv().await, None);
+            })
+            .await;
+    }
+
+    #[tokio::test]
+    async fn test_recv_many() {
+        let (tx, mut rx) = channel();
+        for i in 0..5 {
+            tx.send(i).unwrap();
+        }
+        assert_eq!(rx.len(), 5);
+
+        let mut buf = Vec::new();
+        assert_eq!(rx.recv_many(&mut buf, 3).await, 3);
+        assert_eq!(rx.recv_many(&mut buf, 3).await, 2);
+        assert_eq!(buf, [0, 1, 2, 3, 4]);
+        assert!(rx.is_empty());
+
+        let mut recv = Box::pin(async { rx.recv_many(&mut buf, 3).await });
+        assert!(lazy(|cx| recv.as_mut().poll(cx)).await.is_pending());
+        drop(tx);
+        assert_eq!(recv.await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_try_recv() {
+        let (tx, mut rx) = channel();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+        tx.send(1).unwrap();
+        assert_eq!(rx.try_recv(), Ok(1));
+        drop(tx);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
+    #[tokio::test]
+    async fn test_receiver_close() {
+        let (tx, mut rx) = bounded(1);
+        tx.try_send(1).unwrap();
+
+        let mut send = Box::pin(tx.send(2));
+        assert!(lazy(|cx| send.as_mut().poll(cx)).await.is_pending());
+
+        // closing wakes waiting senders and keeps buffered values
+        rx.close();
+        assert_eq!(send.await.unwrap_err().into_inner(), 2);
+        assert_eq!(rx.recv().await, Some(1));
+
+        // stream ends although a sender is alive
+        assert_eq!(rx.recv().await, None);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+    }
+
     #[tokio::test]
     async fn test_recv() {
         let (tx, mut rx) = channel();
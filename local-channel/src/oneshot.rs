@@ -0,0 +1,249 @@
+//! A non-thread-safe, futures-aware channel for sending a single value.
+
+use alloc::rc::Rc;
+use core::{
+    cell::RefCell,
+    fmt,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+use std::error::Error;
+
+use futures_util::future::poll_fn;
+use local_waker::LocalWaker;
+
+/// Creates a new one-shot channel for sending a single value.
+///
+/// The [Sender] completes the [Receiver] future, which resolves to an error if the sender is
+/// dropped without sending a value. [Sender]s and [Receiver]s are `!Send`.
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let shared = Rc::new(RefCell::new(Shared {
+        value: None,
+        has_sender: true,
+        has_receiver: true,
+        blocked_recv: LocalWaker::new(),
+        blocked_send: LocalWaker::new(),
+    }));
+
+    let sender = Sender {
+        shared: shared.clone(),
+    };
+
+    let receiver = Receiver { shared };
+
+    (sender, receiver)
+}
+
+#[derive(Debug)]
+struct Shared<T> {
+    value: Option<T>,
+    has_sender: bool,
+    has_receiver: bool,
+    blocked_recv: LocalWaker,
+    blocked_send: LocalWaker,
+}
+
+/// The transmission end of a one-shot channel.
+///
+/// This is created by the [`channel`] function.
+#[derive(Debug)]
+pub struct Sender<T> {
+    shared: Rc<RefCell<Shared<T>>>,
+}
+
+impl<T> Unpin for Sender<T> {}
+
+impl<T> Sender<T> {
+    /// Completes the channel with the provided value.
+    ///
+    /// Returns the value back if the [Receiver] was dropped or closed.
+    pub fn send(self, value: T) -> Result<(), T> {
+        let mut shared = self.shared.borrow_mut();
+
+        if !shared.has_receiver {
+            return Err(value);
+        }
+
+        shared.value = Some(value);
+        shared.blocked_recv.wake();
+
+        Ok(())
+    }
+
+    /// Returns true if the [Receiver] was dropped or closed, meaning a value can no longer be sent.
+    pub fn is_canceled(&self) -> bool {
+        !self.shared.borrow().has_receiver
+    }
+
+    /// Waits until the [Receiver] is dropped or closed.
+    ///
+    /// Useful for abandoning work whose result will not be received.
+    pub async fn canceled(&mut self) {
+        poll_fn(|cx| self.poll_canceled(cx)).await
+    }
+
+    /// Polls whether the [Receiver] was dropped or closed.
+    pub fn poll_canceled(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let shared = self.shared.borrow();
+
+        if shared.has_receiver {
+            shared.blocked_send.register(cx.waker());
+            Poll::Pending
+        } else {
+            Poll::Ready(())
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.borrow_mut();
+        shared.has_sender = false;
+
+        // wake receiver so it can observe a sent value or cancellation
+        shared.blocked_recv.wake();
+    }
+}
+
+/// The receiving end of a one-shot channel, which is a future resolving to the sent value.
+///
+/// This is created by the [`channel`] function.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+pub struct Receiver<T> {
+    shared: Rc<RefCell<Shared<T>>>,
+}
+
+impl<T> Unpin for Receiver<T> {}
+
+impl<T> Receiver<T> {
+    /// Takes the value if it has been sent, without waiting.
+    ///
+    /// Returns `Ok(None)` if no value has been sent yet and the [Sender] is still alive.
+    pub fn try_recv(&mut self) -> Result<Option<T>, Canceled> {
+        let mut shared = self.shared.borrow_mut();
+
+        match shared.value.take() {
+            Some(value) => Ok(Some(value)),
+            None if shared.has_sender && shared.has_receiver => Ok(None),
+            None => Err(Canceled),
+        }
+    }
+
+    /// Closes the receiving half, preventing a value from being sent.
+    ///
+    /// A value that was already sent can still be taken with [`try_recv`](Self::try_recv).
+    pub fn close(&mut self) {
+        let mut shared = self.shared.borrow_mut();
+        shared.has_receiver = false;
+        shared.blocked_send.wake();
+    }
+}
+
+impl<T> Future for Receiver<T> {
+    type Output = Result<T, Canceled>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut shared = self.shared.borrow_mut();
+
+        if let Some(value) = shared.value.take() {
+            return Poll::Ready(Ok(value));
+        }
+
+        if !shared.has_sender || !shared.has_receiver {
+            return Poll::Ready(Err(Canceled));
+        }
+
+        shared.blocked_recv.register(cx.waker());
+        Poll::Pending
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.borrow_mut();
+        shared.value = None;
+        shared.has_receiver = false;
+        shared.blocked_send.wake();
+    }
+}
+
+/// Error returned from a [Receiver] when the [Sender] is dropped without sending a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Canceled;
+
+impl fmt::Display for Canceled {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "oneshot canceled")
+    }
+}
+
+impl Error for Canceled {}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::lazy;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_oneshot() {
+        let (tx, rx) = channel();
+        tx.send("test").unwrap();
+        assert_eq!(rx.await, Ok("test"));
+
+        let (tx, mut rx) = channel::<()>();
+        assert_eq!(lazy(|cx| Pin::new(&mut rx).poll(cx)).await, Poll::Pending);
+        drop(tx);
+        assert_eq!(rx.await, Err(Canceled));
+
+        let (tx, rx) = channel();
+        drop(rx);
+        assert!(tx.is_canceled());
+        assert_eq!(tx.send("test"), Err("test"));
+    }
+
+    #[tokio::test]
+    async fn test_try_recv() {
+        let (tx, mut rx) = channel();
+        assert_eq!(rx.try_recv(), Ok(None));
+        tx.send(1).unwrap();
+        assert_eq!(rx.try_recv(), Ok(Some(1)));
+        assert_eq!(rx.try_recv(), Err(Canceled));
+
+        // sent value survives closing the receiver
+        let (tx, mut rx) = channel();
+        tx.send(2).unwrap();
+        rx.close();
+        assert_eq!(rx.try_recv(), Ok(Some(2)));
+    }
+
+    #[tokio::test]
+    async fn test_canceled() {
+        let (mut tx, mut rx) = channel::<()>();
+        assert_eq!(lazy(|cx| tx.poll_canceled(cx)).await, Poll::Pending);
+
+        rx.close();
+        tx.canceled().await;
+        assert!(tx.is_canceled());
+    }
+
+    #[tokio::test]
+    async fn test_across_tasks() {
+        let local = tokio::task::LocalSet::new();
+
+        local
+            .run_until(async {
+                let (tx, rx) = channel();
+
+                tokio::task::spawn_local(async move {
+                    tokio::task::yield_now().await;
+                    tx.send("done").unwrap();
+                });
+
+                assert_eq!(rx.await, Ok("done"));
+            })
+            .await;
+    }
+}
@@ -0,0 +1,364 @@
+//! A non-thread-safe, futures-aware, single-value channel that retains only the latest value.
+//!
+//! Useful for broadcasting state, such as configuration or a shutdown signal, to any number of
+//! tasks on the same thread. [Receiver]s can borrow the current value at any time and wait for it
+//! to change with [`Receiver::changed`]; intermediate values sent between two checks are not seen.
+
+use alloc::rc::Rc;
+use core::{
+    cell::{Ref, RefCell},
+    fmt, mem,
+    task::{Context, Poll},
+};
+use std::error::Error;
+
+use futures_util::future::poll_fn;
+use local_waker::LocalWakerSet;
+
+/// Creates a watch channel holding `init` as its initial value.
+///
+/// The initial value is treated as already seen by the returned [Receiver].
+///
+/// [Sender]s and [Receiver]s are `!Send`.
+pub fn channel<T>(init: T) -> (Sender<T>, Receiver<T>) {
+    let shared = Rc::new(RefCell::new(Shared {
+        value: init,
+        version: 0,
+        senders: 1,
+        receivers: 1,
+        blocked_recv: LocalWakerSet::new(),
+    }));
+
+    let sender = Sender {
+        shared: shared.clone(),
+    };
+
+    let receiver = Receiver { shared, seen: 0 };
+
+    (sender, receiver)
+}
+
+#[derive(Debug)]
+struct Shared<T> {
+    value: T,
+    /// Incremented each time the value is replaced or modified.
+    version: u64,
+    senders: usize,
+    receivers: usize,
+    blocked_recv: LocalWakerSet,
+}
+
+impl<T> Shared<T> {
+    fn notify(&mut self) {
+        self.version += 1;
+        self.blocked_recv.wake_all();
+    }
+}
+
+/// The transmission end of a watch channel.
+///
+/// This is created by the [`channel`] function.
+#[derive(Debug)]
+pub struct Sender<T> {
+    shared: Rc<RefCell<Shared<T>>>,
+}
+
+impl<T> Unpin for Sender<T> {}
+
+impl<T> Sender<T> {
+    /// Replaces the current value and notifies all receivers.
+    ///
+    /// Returns the value back if there are no receivers.
+    ///
+    /// # Panics
+    /// Panics if the current value is borrowed.
+    pub fn send(&self, value: T) -> Result<(), SendError<T>> {
+        if self.shared.borrow().receivers == 0 {
+            return Err(SendError(value));
+        }
+
+        self.send_replace(value);
+        Ok(())
+    }
+
+    /// Replaces the current value, notifying all receivers, and returns the previous value.
+    ///
+    /// Unlike [`send`](Self::send), the value is updated even if there are no receivers, so that
+    /// receivers later created with [`subscribe`](Self::subscribe) see it.
+    ///
+    /// # Panics
+    /// Panics if the current value is borrowed.
+    pub fn send_replace(&self, value: T) -> T {
+        let mut shared = self.shared.borrow_mut();
+        let prev = mem::replace(&mut shared.value, value);
+        shared.notify();
+        prev
+    }
+
+    /// Modifies the current value in place and notifies all receivers.
+    ///
+    /// # Panics
+    /// Panics if the current value is borrowed or if `modify` borrows the channel.
+    pub fn send_modify(&self, modify: impl FnOnce(&mut T)) {
+        let mut shared = self.shared.borrow_mut();
+        modify(&mut shared.value);
+        shared.notify();
+    }
+
+    /// Borrows the current value.
+    ///
+    /// Sending while the returned reference is alive panics.
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref::map(self.shared.borrow(), |shared| &shared.value)
+    }
+
+    /// Creates a new [Receiver] for which the current value is already seen.
+    pub fn subscribe(&self) -> Receiver<T> {
+        let mut shared = self.shared.borrow_mut();
+        shared.receivers += 1;
+
+        Receiver {
+            shared: self.shared.clone(),
+            seen: shared.version,
+        }
+    }
+
+    /// Returns the number of receivers.
+    pub fn receiver_count(&self) -> usize {
+        self.shared.borrow().receivers
+    }
+
+    /// Returns true if all receivers have been dropped.
+    pub fn is_closed(&self) -> bool {
+        self.receiver_count() == 0
+    }
+}
+
+impl<T> Clone for Sender<T> {
+    fn clone(&self) -> Self {
+        self.shared.borrow_mut().senders += 1;
+
+        Sender {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut shared = self.shared.borrow_mut();
+        shared.senders -= 1;
+
+        if shared.senders == 0 {
+            // wake up receivers as the channel is closed
+            shared.blocked_recv.wake_all();
+        }
+    }
+}
+
+/// The receiving end of a watch channel.
+///
+/// This is created by the [`channel`] function or by [`Sender::subscribe`]. Cloning a receiver
+/// creates another that has seen the same version of the value.
+#[derive(Debug)]
+pub struct Receiver<T> {
+    shared: Rc<RefCell<Shared<T>>>,
+    /// Version of the value last marked as seen.
+    seen: u64,
+}
+
+impl<T> Unpin for Receiver<T> {}
+
+impl<T> Receiver<T> {
+    /// Borrows the current value without marking it as seen.
+    ///
+    /// Sending while the returned reference is alive panics.
+    pub fn borrow(&self) -> Ref<'_, T> {
+        Ref::map(self.shared.borrow(), |shared| &shared.value)
+    }
+
+    /// Borrows the current value and marks it as seen.
+    ///
+    /// Sending while the returned reference is alive panics.
+    pub fn borrow_and_update(&mut self) -> Ref<'_, T> {
+        let shared = self.shared.borrow();
+        self.seen = shared.version;
+        Ref::map(shared, |shared| &shared.value)
+    }
+
+    /// Returns true if the value has changed since it was last marked as seen.
+    ///
+    /// Returns an error if all [Sender]s have been dropped and there is no unseen value.
+    pub fn has_changed(&self) -> Result<bool, RecvError> {
+        let shared = self.shared.borrow();
+
+        if shared.version != self.seen {
+            Ok(true)
+        } else if shared.senders == 0 {
+            Err(RecvError)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Waits for the value to change since it was last marked as seen, then marks it as seen.
+    ///
+    /// Returns immediately if there is already an unseen value. Returns an error once all
+    /// [Sender]s have been dropped and there is no unseen value.
+    pub async fn changed(&mut self) -> Result<(), RecvError> {
+        poll_fn(|cx| self.poll_changed(cx)).await
+    }
+
+    /// Polls for the value to change since it was last marked as seen.
+    ///
+    /// See [`changed`](Self::changed).
+    pub fn poll_changed(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), RecvError>> {
+        let shared = self.shared.borrow();
+
+        if shared.version != self.seen {
+            self.seen = shared.version;
+            Poll::Ready(Ok(()))
+        } else if shared.senders == 0 {
+            Poll::Ready(Err(RecvError))
+        } else {
+            shared.blocked_recv.register(cx.waker());
+            Poll::Pending
+        }
+    }
+
+    /// Marks the current value as seen.
+    pub fn mark_unchanged(&mut self) {
+        self.seen = self.shared.borrow().version;
+    }
+}
+
+impl<T> Clone for Receiver<T> {
+    fn clone(&self) -> Self {
+        self.shared.borrow_mut().receivers += 1;
+
+        Receiver {
+            shared: self.shared.clone(),
+            seen: self.seen,
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.shared.borrow_mut().receivers -= 1;
+    }
+}
+
+/// Error returned when sending on a channel without receivers.
+///
+/// Allows access to the value that failed to send with [`into_inner`](Self::into_inner).
+pub struct SendError<T>(pub T);
+
+impl<T> SendError<T> {
+    /// Returns the value that was attempted to be sent but failed.
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T> fmt::Debug for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt.debug_tuple("SendError").field(&"...").finish()
+    }
+}
+
+impl<T> fmt::Display for SendError<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "send failed because there are no receivers")
+    }
+}
+
+impl<T> Error for SendError<T> {}
+
+/// Error returned by [`Receiver::changed`] and [`Receiver::has_changed`] when all senders have
+/// been dropped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RecvError;
+
+impl fmt::Display for RecvError {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(fmt, "channel closed")
+    }
+}
+
+impl Error for RecvError {}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::future::lazy;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_watch() {
+        let (tx, mut rx) = channel(1);
+        assert_eq!(*rx.borrow(), 1);
+        assert_eq!(rx.has_changed(), Ok(false));
+
+        tx.send(2).unwrap();
+        tx.send(3).unwrap();
+        assert_eq!(rx.has_changed(), Ok(true));
+        assert_eq!(*rx.borrow(), 3);
+
+        // intermediate values are skipped
+        rx.changed().await.unwrap();
+        assert_eq!(*rx.borrow(), 3);
+        assert!(lazy(|cx| rx.poll_changed(cx)).await.is_pending());
+
+        tx.send_modify(|val| *val += 1);
+        assert_eq!(*rx.borrow_and_update(), 4);
+        assert_eq!(rx.has_changed(), Ok(false));
+
+        // unseen values are still reported after senders are dropped
+        tx.send(5).unwrap();
+        drop(tx);
+        assert_eq!(rx.changed().await, Ok(()));
+        assert_eq!(rx.changed().await, Err(RecvError));
+        assert_eq!(rx.has_changed(), Err(RecvError));
+    }
+
+    #[tokio::test]
+    async fn test_wakes_receivers() {
+        let (tx, mut rx1) = channel(false);
+        let mut rx2 = tx.subscribe();
+
+        let local = tokio::task::LocalSet::new();
+
+        local
+            .run_until(async move {
+                let rx1 = tokio::task::spawn_local(async move {
+                    rx1.changed().await.unwrap();
+                    *rx1.borrow()
+                });
+                let rx2 = tokio::task::spawn_local(async move {
+                    rx2.changed().await.unwrap();
+                    *rx2.borrow()
+                });
+
+                tokio::task::yield_now().await;
+                tx.send(true).unwrap();
+
+                assert!(rx1.await.unwrap());
+                assert!(rx2.await.unwrap());
+            })
+            .await;
+    }
+
+    #[test]
+    fn test_no_receivers() {
+        let (tx, rx) = channel(1);
+        drop(rx);
+        assert!(tx.is_closed());
+        assert_eq!(tx.send(2).unwrap_err().into_inner(), 2);
+
+        assert_eq!(tx.send_replace(3), 1);
+        let rx = tx.subscribe();
+        assert_eq!(rx.has_changed(), Ok(false));
+        assert_eq!(*rx.borrow(), 3);
+    }
+}
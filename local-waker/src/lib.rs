@@ -1,13 +1,19 @@
 //! A synchronization primitive for thread-local task wakeup.
 //!
-//! See docs for [`LocalWaker`].
+//! See docs for [`LocalWaker`] and [`LocalWakerSet`].
 
 #![no_std]
 #![deny(rust_2018_idioms, nonstandard_style)]
 #![warn(future_incompatible, missing_docs)]
 
+extern crate alloc;
+
 use core::{cell::Cell, fmt, marker::PhantomData, task::Waker};
 
+mod set;
+
+pub use self::set::LocalWakerSet;
+
 /// A synchronization primitive for task wakeup.
 ///
 /// Sometimes the task interested in a given event will change over time. A `LocalWaker` can
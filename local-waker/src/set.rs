@@ -0,0 +1,93 @@
+use alloc::vec::Vec;
+use core::{cell::RefCell, fmt, marker::PhantomData, task::Waker};
+
+/// A set of wakers for notifying multiple tasks waiting on the same event.
+///
+/// Where a [`LocalWaker`](crate::LocalWaker) only remembers the most recently registered waker,
+/// which silently drops any earlier waiter, a `LocalWakerSet` keeps one waker per registered task.
+/// Registering a waker that would wake the same task as an already registered one replaces it
+/// instead of adding a duplicate.
+///
+/// Wakers are woken in the order they were first registered.
+#[derive(Default)]
+pub struct LocalWakerSet {
+    wakers: RefCell<Vec<Waker>>,
+    // mark LocalWakerSet as a !Send type.
+    _phantom: PhantomData<*const ()>,
+}
+
+impl LocalWakerSet {
+    /// Creates a new, empty `LocalWakerSet`.
+    pub fn new() -> Self {
+        LocalWakerSet::default()
+    }
+
+    /// Registers the waker to be notified on calls to `wake_one` or `wake_all`.
+    ///
+    /// Returns `true` if a waker for the same task was registered before.
+    pub fn register(&self, waker: &Waker) -> bool {
+        let mut wakers = self.wakers.borrow_mut();
+
+        match wakers.iter_mut().find(|w| w.will_wake(waker)) {
+            Some(existing) => {
+                existing.clone_from(waker);
+                true
+            }
+            None => {
+                wakers.push(waker.clone());
+                false
+            }
+        }
+    }
+
+    /// Wakes the earliest registered task, removing it from the set.
+    ///
+    /// Returns `true` if a task was woken.
+    pub fn wake_one(&self) -> bool {
+        let waker = {
+            let mut wakers = self.wakers.borrow_mut();
+
+            if wakers.is_empty() {
+                return false;
+            }
+
+            wakers.remove(0)
+        };
+
+        waker.wake();
+        true
+    }
+
+    /// Wakes all registered tasks, clearing the set.
+    pub fn wake_all(&self) {
+        // take wakers out first so that woken tasks may register again without a borrow conflict
+        let wakers = self.wakers.take();
+
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+
+    /// Removes all registered wakers without waking them.
+    pub fn clear(&self) {
+        self.wakers.borrow_mut().clear();
+    }
+
+    /// Returns the number of registered wakers.
+    pub fn len(&self) -> usize {
+        self.wakers.borrow().len()
+    }
+
+    /// Returns `true` if no wakers are registered.
+    pub fn is_empty(&self) -> bool {
+        self.wakers.borrow().is_empty()
+    }
+}
+
+impl fmt::Debug for LocalWakerSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LocalWakerSet")
+            .field("len", &self.len())
+            .finish()
+    }
+}